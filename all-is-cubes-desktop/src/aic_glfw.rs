@@ -12,6 +12,7 @@ use std::time::Instant;
 
 use all_is_cubes::apps::AllIsCubesAppState;
 use all_is_cubes::camera::Viewport;
+use all_is_cubes::input::Input;
 use all_is_cubes::lum::GLRenderer;
 
 /// Run GLFW-based rendering and event loop.
@@ -55,7 +56,7 @@ pub fn glfw_main_loop(
     let mut renderer = GLRenderer::new(context, app.graphics_options(), viewport)?;
 
     renderer.set_character(app.character().map(Clone::clone));
-    renderer.set_ui_space(Some(app.ui_space().clone()));
+    renderer.set_ui_space(Some(app.ui_space()));
 
     let ready_time = Instant::now();
     log::debug!(
@@ -70,6 +71,8 @@ pub fn glfw_main_loop(
         if app.frame_clock.should_draw() {
             app.update_cursor(renderer.ui_camera(), renderer.world_camera());
             let render_info = renderer.render_frame(app.cursor_result()).unwrap();
+            app.frame_clock
+                .record_frame_timing(render_info.frame_time, render_info.frame_interval);
             renderer
                 .add_info_text(&format!("{}", app.info_text(render_info)))
                 .unwrap();
@@ -144,10 +147,11 @@ pub fn glfw_main_loop(
                         .mouse_pixel_position(renderer.viewport(), None, false);
                 }
                 WindowEvent::MouseButton(button, Action::Press, _) => {
-                    // TODO: this dumping should be replaced with in-game UI feedback
-                    log::info!("click: {:?}", app.click(map_glfw_button(button)));
+                    app.input_processor.input_down(map_glfw_button(button));
+                }
+                WindowEvent::MouseButton(button, Action::Release, _) => {
+                    app.input_processor.input_up(map_glfw_button(button));
                 }
-                WindowEvent::MouseButton(_, Action::Release, _) => {}
                 WindowEvent::MouseButton(_, Action::Repeat, _) => {}
                 WindowEvent::Scroll(..) => {
                     // TODO: Hook up to input processor once we have customizable bindings
@@ -185,9 +189,9 @@ pub fn map_glfw_viewport(window: &Window) -> Viewport {
     }
 }
 
-pub fn map_glfw_button(button: glfw::MouseButton) -> usize {
+pub fn map_glfw_button(button: glfw::MouseButton) -> Input {
     use glfw::MouseButton::*;
-    match button {
+    Input::MouseButton(match button {
         Button1 => 0,
         Button2 => 1,
         Button3 => 2,
@@ -196,7 +200,7 @@ pub fn map_glfw_button(button: glfw::MouseButton) -> usize {
         Button6 => 5,
         Button7 => 6,
         Button8 => 7,
-    }
+    })
 }
 
 pub fn map_glfw_key(key: glfw::Key) -> Option<all_is_cubes::apps::Key> {
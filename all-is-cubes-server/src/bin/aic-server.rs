@@ -4,9 +4,40 @@
 //! main() for a server that serves the All is Cubes client as well as being a game
 //! server.
 
-use all_is_cubes_server::webserver::server_main;
+#[cfg(not(feature = "observe"))]
+#[tokio::main]
+async fn main() {
+    all_is_cubes_server::webserver::server_main().await;
+}
 
+/// With the `observe` feature, also run a headless simulation of the demo world and
+/// serve snapshots of it.
+#[cfg(feature = "observe")]
 #[tokio::main]
 async fn main() {
-    server_main().await;
+    use all_is_cubes::apps::Session;
+    use all_is_cubes::content::UniverseTemplate;
+    use all_is_cubes_server::observe::Observer;
+    use std::time::{Duration, Instant};
+
+    let observer = Observer::new();
+    let listener = observer.listener();
+    std::thread::Builder::new()
+        .name("simulation".to_owned())
+        .spawn(move || {
+            // Session is not Send, so it must be created on this thread.
+            let mut session = Session::from_template(UniverseTemplate::default())
+                .expect("failed to generate universe")
+                .snapshot_interval(60);
+            session.listen(listener);
+            let mut last_time = Instant::now();
+            loop {
+                std::thread::sleep(Duration::from_millis(15));
+                let now = Instant::now();
+                session.advance(now - last_time);
+                last_time = now;
+            }
+        })
+        .expect("failed to start simulation thread");
+    all_is_cubes_server::webserver::server_main_with_observer(&observer).await;
 }
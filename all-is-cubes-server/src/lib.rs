@@ -6,4 +6,6 @@
 #![deny(rust_2018_idioms)]
 #![warn(clippy::cast_lossless)]
 
+#[cfg(feature = "observe")]
+pub mod observe;
 pub mod webserver;
@@ -0,0 +1,249 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Remote observation of a running simulation: raytraced snapshots served over HTTP.
+//!
+//! This requires no GPU and is intended for checking in on long-running headless
+//! worlds. Either the simulation registers [`Observer::listener`] with its
+//! [`Session`](all_is_cubes::apps::Session) to publish the session's periodic snapshots, or it
+//! keeps a [`Capturer`] and calls [`Capturer::capture`] whenever it wants to publish a new
+//! frame; [`Observer::filter`] serves the most recent one as PNG.
+//!
+//! Only available with the `observe` feature enabled.
+
+use std::sync::{Arc, Mutex, Weak};
+
+use warp::http::{Response, StatusCode};
+use warp::{Filter, Rejection, Reply};
+
+use all_is_cubes::apps::SessionEvent;
+use all_is_cubes::camera::Camera;
+use all_is_cubes::cgmath::Vector2;
+use all_is_cubes::listen::Listener;
+use all_is_cubes::math::Rgba;
+use all_is_cubes::raytracer::{ColorBuf, ProgressiveRaytracer};
+use all_is_cubes::space::Space;
+use all_is_cubes::universe::URef;
+
+/// Holds the most recently captured frame and serves it to HTTP clients.
+///
+/// Clones share the same frame, so one clone may be handed to the simulation
+/// (which need not be [`Send`]) and another to the web server.
+#[derive(Clone, Debug, Default)]
+pub struct Observer {
+    latest: Arc<Mutex<Option<Arc<[u8]>>>>,
+}
+
+impl Observer {
+    /// Constructs an [`Observer`] which has no frame yet; requests are answered with
+    /// an error until one is captured.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a [`Capturer`] which raytraces `space` and makes the result the frame
+    /// served to subsequent requests.
+    pub fn capturer(&self, space: URef<Space>) -> Capturer {
+        Capturer {
+            latest: self.latest.clone(),
+            tracer: ProgressiveRaytracer::new(space),
+        }
+    }
+
+    /// Returns a [`Listener`] which, when registered with
+    /// [`Session::listen`](all_is_cubes::apps::Session::listen), makes
+    /// each of the session's [snapshots](SessionEvent::Snapshot) the frame served to
+    /// subsequent requests.
+    pub fn listener(&self) -> impl Listener<SessionEvent> {
+        ObserverListener {
+            latest: Arc::downgrade(&self.latest),
+        }
+    }
+
+    /// Returns the PNG data of the most recently captured frame, if any.
+    pub fn latest_png(&self) -> Option<Arc<[u8]>> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    /// A [`warp`] filter which responds to `GET /observe.png` with the latest frame,
+    /// or 503 Service Unavailable if nothing has been captured yet.
+    pub fn filter(&self) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        let observer = self.clone();
+        warp::path("observe.png")
+            .and(warp::path::end())
+            .and(warp::get())
+            .map(move || match observer.latest_png() {
+                Some(data) => Response::builder()
+                    .header("Content-Type", "image/png")
+                    .header("Cache-Control", "no-store")
+                    .body(data.to_vec())
+                    .unwrap(),
+                None => Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .body(Vec::new())
+                    .unwrap(),
+            })
+    }
+}
+
+/// Raytraces a [`Space`] on request and publishes the result to the [`Observer`] it was
+/// obtained from; see [`Observer::capturer`].
+///
+/// The raytracer is kept between captures, so capturing again when neither the space nor
+/// the camera has changed does not trace any rays.
+#[derive(Debug)]
+pub struct Capturer {
+    latest: Arc<Mutex<Option<Arc<[u8]>>>>,
+    tracer: ProgressiveRaytracer<ColorBuf>,
+}
+
+impl Capturer {
+    /// Raytrace the space as seen from `camera` and make the result the frame served
+    /// to subsequent requests.
+    ///
+    /// The camera's [`GraphicsOptions`](all_is_cubes::camera::GraphicsOptions) are
+    /// used for the raytracer as well.
+    pub fn capture(&mut self, camera: &Camera) -> Result<(), png::EncodingError> {
+        self.tracer.refine(camera, usize::MAX);
+        let png_data = encode_png(self.tracer.image(), camera.viewport().framebuffer_size)?;
+        *self.latest.lock().unwrap() = Some(png_data.into());
+        Ok(())
+    }
+}
+
+struct ObserverListener {
+    latest: Weak<Mutex<Option<Arc<[u8]>>>>,
+}
+
+impl Listener<SessionEvent> for ObserverListener {
+    fn receive(&self, message: SessionEvent) {
+        if let SessionEvent::Snapshot(snapshot) = message {
+            if let Some(latest) = self.latest.upgrade() {
+                // Encoding into memory can only fail if the image is malformed, which
+                // Session does not produce; keep the previous frame in that case.
+                if let Ok(png_data) = encode_png(&snapshot.image, snapshot.size) {
+                    *latest.lock().unwrap() = Some(png_data.into());
+                }
+            }
+        }
+    }
+
+    fn alive(&self) -> bool {
+        self.latest.strong_count() > 0
+    }
+}
+
+/// Encode raytracer output as an 8-bit sRGB PNG.
+pub fn encode_png(image: &[Rgba], size: Vector2<u32>) -> Result<Vec<u8>, png::EncodingError> {
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buffer, size.x, size.y);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        // Declare that the image is sRGB, as to_srgb_32bit produces.
+        writer.write_chunk(*b"sRGB", &[0])?;
+        writer.write_image_data(
+            &image
+                .iter()
+                .flat_map(|c| c.to_srgb_32bit())
+                .collect::<Vec<u8>>(),
+        )?;
+    }
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use all_is_cubes::apps::Session;
+    use all_is_cubes::camera::{GraphicsOptions, Viewport};
+    use all_is_cubes::content::{make_some_blocks, UniverseTemplate};
+    use all_is_cubes::space::Grid;
+    use all_is_cubes::universe::Universe;
+
+    const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+    fn test_camera() -> Camera {
+        Camera::new(
+            GraphicsOptions::default(),
+            Viewport {
+                nominal_size: Vector2::new(8., 6.),
+                framebuffer_size: Vector2::new(8, 6),
+            },
+        )
+    }
+
+    #[test]
+    fn encode_png_signature() {
+        let data = encode_png(&[Rgba::BLACK; 4], Vector2::new(2, 2)).unwrap();
+        assert_eq!(data[..8], PNG_SIGNATURE);
+    }
+
+    #[tokio::test]
+    async fn serves_nothing_before_capture() {
+        let observer = Observer::new();
+        let response = warp::test::request()
+            .path("/observe.png")
+            .reply(&observer.filter())
+            .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn serves_captured_frame() {
+        let [block] = make_some_blocks();
+        let mut universe = Universe::new();
+        let mut space = Space::empty(Grid::new((-1, -1, -1), (2, 2, 2)));
+        space.fill_uniform(space.grid(), &block).unwrap();
+        let space = universe.insert_anonymous(space);
+        let observer = Observer::new();
+        observer.capturer(space).capture(&test_camera()).unwrap();
+
+        let response = warp::test::request()
+            .path("/observe.png")
+            .reply(&observer.filter())
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["Content-Type"], "image/png");
+        assert_eq!(response.body()[..8], PNG_SIGNATURE);
+    }
+
+    #[test]
+    fn capture_follows_space_changes() {
+        let [block] = make_some_blocks();
+        let mut universe = Universe::new();
+        let space = universe.insert_anonymous(Space::empty(Grid::new((-1, -1, -1), (2, 2, 2))));
+        let observer = Observer::new();
+        let mut capturer = observer.capturer(space.clone());
+        let camera = test_camera();
+
+        capturer.capture(&camera).unwrap();
+        let empty = observer.latest_png().unwrap();
+        capturer.capture(&camera).unwrap();
+        assert_eq!(observer.latest_png().unwrap(), empty);
+
+        let grid = space.borrow().grid();
+        space.borrow_mut().fill_uniform(grid, &block).unwrap();
+        capturer.capture(&camera).unwrap();
+        assert_ne!(observer.latest_png().unwrap(), empty);
+    }
+
+    #[tokio::test]
+    async fn serves_session_snapshot() {
+        let observer = Observer::new();
+        let mut session = Session::from_template(UniverseTemplate::CornellBox)
+            .unwrap()
+            .snapshot_interval(1)
+            .viewport(test_camera().viewport());
+        session.listen(observer.listener());
+        session.step();
+
+        let response = warp::test::request()
+            .path("/observe.png")
+            .reply(&observer.filter())
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body()[..8], PNG_SIGNATURE);
+    }
+}
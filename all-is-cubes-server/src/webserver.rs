@@ -5,6 +5,11 @@
 //! server in the multiplayer sense (eventually).
 
 use static_dir::static_dir;
+#[cfg(feature = "observe")]
+use warp::Filter as _;
+
+#[cfg(feature = "observe")]
+use crate::observe::Observer;
 
 /// Run the All is Cubes web server on port 8833.
 ///
@@ -14,3 +19,16 @@ pub async fn server_main() {
         .run(([127, 0, 0, 1], 8833))
         .await;
 }
+
+/// Run the All is Cubes web server on port 8833, additionally serving the frames
+/// published to `observer` at `/observe.png` (see [`Observer::filter`]).
+#[cfg(feature = "observe")]
+pub async fn server_main_with_observer(observer: &Observer) {
+    warp::serve(
+        observer
+            .filter()
+            .or(static_dir!("./static-all-is-cubes-wasm/")),
+    )
+    .run(([127, 0, 0, 1], 8833))
+    .await;
+}
@@ -9,12 +9,13 @@ use std::time::Duration;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast; // dyn_into()
 use web_sys::{
-    console, AddEventListenerOptions, Document, Element, Event, FocusEvent, HtmlElement,
-    KeyboardEvent, MouseEvent, Text, WebGlContextAttributes,
+    console, AddEventListenerOptions, Document, Element, Event, FocusEvent, Gamepad, GamepadButton,
+    HtmlElement, KeyboardEvent, MouseEvent, Text, WebGlContextAttributes,
 };
 
 use all_is_cubes::apps::{AllIsCubesAppState, Key};
 use all_is_cubes::cgmath::{Point2, Vector2};
+use all_is_cubes::input::{Axis, Input};
 use all_is_cubes::lum::GLRenderer;
 use all_is_cubes::universe::UniverseStepInfo;
 
@@ -80,7 +81,7 @@ pub fn start_game(gui_helpers: GuiHelpers) -> Result<(), JsValue> {
     )
     .map_err(|e| Error::new(&format!("did not initialize renderer: {}", e)))?;
     renderer.set_character(app.character().map(Clone::clone));
-    renderer.set_ui_space(Some(app.ui_space().clone()));
+    renderer.set_ui_space(Some(app.ui_space()));
 
     static_dom.scene_info_text_node.append_data("\nGL ready.")?;
 
@@ -225,23 +226,18 @@ impl WebGameRoot {
             true,
             move |this, event: MouseEvent| {
                 this.update_mouse_position(&event);
-                // MouseEvent button numbering is sequential for a three button mouse, instead of
-                // counting the middle/wheel button as the third button.
-                let mapped_button: usize = match event.button() {
-                    0 => 0,
-                    2 => 1,
-                    1 => 2,
-                    x => x as usize,
-                };
-                // TODO: outcome reporting should be directly in the UI, not our responsibility
-                let result = this.app.click(mapped_button);
-                console::log_1(&JsValue::from_str(&format!(
-                    "click {}: {:?}",
-                    mapped_button, result
-                )));
+                this.app
+                    .input_processor
+                    .input_down(map_mouse_button(event.button()));
             },
         );
 
+        self.add_canvas_to_self_event_listener("mouseup", true, move |this, event: MouseEvent| {
+            this.app
+                .input_processor
+                .input_up(map_mouse_button(event.button()));
+        });
+
         add_event_listener(
             &self.gui_helpers.canvas_helper().canvas(),
             "contextmenu",
@@ -302,6 +298,7 @@ impl WebGameRoot {
     fn raf_callback_impl(&mut self, dom_timestamp: f64) {
         let delta = Duration::from_secs_f64((dom_timestamp - self.last_raf_timestamp) / 1000.0);
         self.last_raf_timestamp = dom_timestamp;
+        self.poll_gamepads();
         let should_draw = self.app.frame_clock.request_frame(delta);
 
         if should_draw {
@@ -317,6 +314,9 @@ impl WebGameRoot {
                 .renderer
                 .render_frame(self.app.cursor_result())
                 .expect("error in render_frame");
+            self.app
+                .frame_clock
+                .record_frame_timing(render_info.frame_time, render_info.frame_interval);
 
             // Update info text
             self.static_dom
@@ -351,6 +351,33 @@ impl WebGameRoot {
         self.start_loop();
     }
 
+    /// Reads the state of the first connected gamepad, since the Gamepad API does not
+    /// provide events for buttons and axes.
+    fn poll_gamepads(&mut self) {
+        let gamepads = match web_sys::window().unwrap().navigator().get_gamepads() {
+            Ok(gamepads) => gamepads,
+            Err(_) => return,
+        };
+        // TODO: Support more than one gamepad, or at least choose the one last used.
+        let gamepad = gamepads
+            .iter()
+            .filter_map(|g| g.dyn_into::<Gamepad>().ok())
+            .find(|g| g.connected());
+        if let Some(gamepad) = gamepad {
+            let input_processor = &mut self.app.input_processor;
+            for (i, position) in gamepad.axes().iter().enumerate() {
+                if let Some(position) = position.as_f64() {
+                    input_processor.axis_position(Axis::GamepadAxis(i as u8), position);
+                }
+            }
+            for (i, button) in gamepad.buttons().iter().enumerate() {
+                if let Ok(button) = button.dyn_into::<GamepadButton>() {
+                    input_processor.input_held(Input::GamepadButton(i as u8), button.pressed());
+                }
+            }
+        }
+    }
+
     fn step_callback_impl(&mut self) {
         self.step_callback_scheduled = false;
         if let Some(universe_step_info) = self.app.maybe_step_universe() {
@@ -422,3 +449,14 @@ fn map_keyboard_event(event: &KeyboardEvent) -> Option<Key> {
         }
     })
 }
+
+/// MouseEvent button numbering is sequential for a three button mouse, instead of
+/// counting the middle/wheel button as the third button, so swap those.
+fn map_mouse_button(button: i16) -> Input {
+    Input::MouseButton(match button {
+        0 => 0,
+        2 => 1,
+        1 => 2,
+        x => x as u8,
+    })
+}
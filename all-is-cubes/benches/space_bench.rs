@@ -5,6 +5,7 @@ use criterion::{
     black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
 };
 
+use all_is_cubes::block::Block;
 use all_is_cubes::content::{axes, make_some_blocks};
 use all_is_cubes::content::{install_landscape_blocks, wavy_landscape};
 use all_is_cubes::linking::BlockProvider;
@@ -99,6 +100,54 @@ pub fn space_bulk_mutation(c: &mut Criterion) {
     group.finish();
 }
 
+/// Mutation of a space whose light has been computed, so that the recorded light
+/// dependencies of the changed cubes must be consulted.
+pub fn lit_space_mutation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("space-lit-mutation");
+    group.sample_size(20);
+    let [block] = make_some_blocks();
+    let row = Grid::new([0, 8, 8], [16, 1, 1]);
+    let layer = Grid::new([0, 8, 0], [16, 1, 16]);
+
+    group.throughput(Throughput::Elements(row.volume() as u64));
+    group.bench_function("set() row in lit space", |b| {
+        b.iter_batched(
+            || lit_space_for_mutation(&block),
+            |mut space| {
+                for cube in row.interior_iter() {
+                    space.set(cube, &block).unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.throughput(Throughput::Elements(layer.volume() as u64));
+    group.bench_function("fill() layer in lit space", |b| {
+        b.iter_batched(
+            || lit_space_for_mutation(&block),
+            |mut space| {
+                space.fill(layer, |_| Some(&block)).unwrap();
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn lit_space_for_mutation(block: &Block) -> Space {
+    let grid = Grid::new([0, 0, 0], [16, 16, 16]);
+    let mut space = Space::empty(grid);
+    space
+        .fill_uniform(Grid::new([0, 0, 0], [16, 1, 16]), block)
+        .unwrap();
+    space
+        .fill_uniform(Grid::new([0, 0, 0], [1, 16, 16]), block)
+        .unwrap();
+    space.evaluate_light(0, |_| {});
+    space
+}
+
 pub fn grid_bench(c: &mut Criterion) {
     let mut group = c.benchmark_group("Grid");
 
@@ -150,5 +199,11 @@ fn universe_for_lighting_test() -> Universe {
     universe
 }
 
-criterion_group!(benches, space_bulk_mutation, grid_bench, lighting_bench);
+criterion_group!(
+    benches,
+    space_bulk_mutation,
+    lit_space_mutation,
+    grid_bench,
+    lighting_bench
+);
 criterion_main!(benches);
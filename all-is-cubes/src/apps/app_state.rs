@@ -0,0 +1,365 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+use std::fmt::Display;
+use std::sync::Arc;
+
+use crate::apps::{FrameClock, InputProcessor, Tick};
+use crate::camera::{Camera, GraphicsOptions};
+use crate::character::{cursor_raycast, Character, CharacterChange, Cursor};
+use crate::command::{CommandError, CommandRegistry};
+use crate::content::UniverseTemplate;
+use crate::i18n::Localizer;
+use crate::linking::BlockProvider;
+use crate::listen::{DirtyFlag, ListenableCell, ListenableSource, ListenerHelper as _};
+use crate::mining::CrackStage;
+use crate::space::Space;
+use crate::tools::{Tool, ToolError};
+use crate::transactions::Transaction;
+use crate::universe::{ReadRef, URef, Universe, UniverseStepInfo};
+use crate::util::{CustomFormat, StatusText};
+use crate::vui::Vui;
+
+/// Everything that a game application needs regardless of platform.
+///
+/// Once we have multiplayer / client-server support, this will become the client-side
+/// structure.
+#[derive(Debug)]
+pub struct AllIsCubesAppState {
+    /// Determines the timing of simulation and drawing. The caller must arrange
+    /// to advance time in the clock.
+    pub frame_clock: FrameClock,
+
+    /// Handles (some) user input. The caller must provide input events/state;
+    /// `AllIsCubesAppState` will handle calling [`InputProcessor::apply_input`].
+    pub input_processor: InputProcessor,
+
+    graphics_options: ListenableCell<GraphicsOptions>,
+
+    game_universe: Universe,
+    game_character: Option<URef<Character>>,
+
+    paused: ListenableCell<bool>,
+
+    ui: Vui,
+    ui_dirty: DirtyFlag,
+
+    /// Last cursor raycast result.
+    /// TODO: This needs to handle clicking on the HUD and thus explicitly point into
+    /// one of two different spaces.
+    cursor_result: Option<Cursor>,
+
+    /// Mouse button which was pressed and not yet released, for tools which act
+    /// continuously while held.
+    held_button: Option<usize>,
+
+    /// Overlays used to display block-breaking progress.
+    crack_blocks: BlockProvider<CrackStage>,
+
+    last_step_info: UniverseStepInfo,
+
+    /// Commands which the user may type; see [`Self::execute_command`].
+    commands: CommandRegistry,
+}
+
+impl AllIsCubesAppState {
+    /// Construct a new `AllIsCubesAppState` with a new [`Universe`] from the given
+    /// template.
+    pub fn new(template: UniverseTemplate) -> Self {
+        Self::from_universe(
+            template
+                .build()
+                // TODO: better error handling
+                .expect("Failure while constructing template"),
+        )
+    }
+
+    /// Construct a new `AllIsCubesAppState` using the given [`Universe`], whose
+    /// default character (if any) will be the one the user controls.
+    pub fn from_universe(mut game_universe: Universe) -> Self {
+        let input_processor = InputProcessor::new();
+        let paused = ListenableCell::new(false);
+        let crack_blocks = CrackStage::new(&mut game_universe);
+
+        let mut new_self = Self {
+            ui: Vui::new(&input_processor, paused.as_source()),
+
+            frame_clock: FrameClock::new(),
+            input_processor,
+            graphics_options: ListenableCell::new(GraphicsOptions::default()),
+            game_character: game_universe.get_default_character(),
+            game_universe,
+            paused,
+            ui_dirty: DirtyFlag::new(true),
+            cursor_result: None,
+            held_button: None,
+            crack_blocks,
+            last_step_info: UniverseStepInfo::default(),
+            commands: CommandRegistry::standard(),
+        };
+
+        // TODO: once it's possible to switch characters we will need to clear and reinstall this
+        if let Some(character_ref) = &new_self.game_character {
+            character_ref
+                .borrow()
+                .listen(new_self.ui_dirty.listener().filter(|msg| match msg {
+                    CharacterChange::Inventory(_) | CharacterChange::Selections => Some(()),
+                }));
+        }
+        new_self.maybe_sync_ui();
+
+        new_self
+    }
+
+    /// Returns a reference to the [`Character`] that should be shown to the user.
+    pub fn character(&self) -> Option<&URef<Character>> {
+        self.game_character.as_ref()
+    }
+
+    /// Returns a mutable reference to the [`Universe`].
+    pub fn universe_mut(&mut self) -> &mut Universe {
+        &mut self.game_universe
+    }
+
+    /// Returns the commands which [`Self::execute_command`] accepts.
+    pub fn commands(&self) -> &CommandRegistry {
+        &self.commands
+    }
+
+    /// Returns the commands which [`Self::execute_command`] accepts, for adding more.
+    pub fn commands_mut(&mut self) -> &mut CommandRegistry {
+        &mut self.commands
+    }
+
+    /// Executes a command typed by the user, such as `/tp 0 10 0`, acting on the
+    /// [character](Self::character), and returns the message to show them.
+    pub fn execute_command(&mut self, line: &str) -> Result<String, CommandError> {
+        self.commands
+            .execute(line, &mut self.game_universe, self.game_character.as_ref())
+    }
+
+    /// Returns the possible completions of the last word of a partially typed command;
+    /// see [`CommandRegistry::complete`].
+    pub fn complete_command(&self, line: &str) -> Vec<String> {
+        self.commands.complete(line, &self.game_universe)
+    }
+
+    pub fn ui_space(&self) -> ReadRef<Space> {
+        self.ui.current_space()
+    }
+
+    pub fn graphics_options(&self) -> ListenableSource<GraphicsOptions> {
+        self.graphics_options.as_source()
+    }
+
+    pub fn graphics_options_mut(&self) -> &ListenableCell<GraphicsOptions> {
+        &self.graphics_options
+    }
+
+    /// Sets the [`Localizer`] which translates text in the user interface, such as the
+    /// names of tools. Text already displayed is not updated.
+    pub fn set_localizer(&mut self, localizer: Arc<dyn Localizer>) {
+        self.ui.set_localizer(localizer);
+    }
+
+    /// Steps the universe if the `FrameClock` says it's time to do so.
+    /// Always returns info for the last step even if multiple steps were taken.
+    ///
+    /// Also performs any tool uses requested through [`Self::input_processor`].
+    pub fn maybe_step_universe(&mut self) -> Option<UniverseStepInfo> {
+        for (button, pressed) in self.input_processor.take_tool_buttons() {
+            if pressed {
+                // TODO: this dumping should be replaced with in-game UI feedback
+                log::info!("click {}: {:?}", button, self.click(button));
+            } else {
+                self.release_button(button);
+            }
+        }
+
+        let mut result = None;
+        for _ in 0..self.frame_clock.catch_up_steps() {
+            if self.frame_clock.should_step() {
+                let unpaused_tick = self.frame_clock.tick();
+                // Pausing applies to the game universe only, so that the UI keeps running.
+                let paused = *self.paused.get();
+                self.game_universe.set_paused(paused);
+                let tick = if paused {
+                    unpaused_tick.pause()
+                } else {
+                    unpaused_tick
+                };
+                self.frame_clock.did_step();
+
+                if let Some(character_ref) = &self.game_character {
+                    self.input_processor.apply_input(
+                        &mut character_ref.borrow_mut(),
+                        &self.paused,
+                        tick,
+                    );
+                    for _ in 0..self.input_processor.take_tosses() {
+                        let slot = character_ref.borrow().selected_slots()[1];
+                        match Character::toss(character_ref, slot) {
+                            Ok(transaction) => {
+                                if let Err(e) = transaction.execute(&mut self.game_universe) {
+                                    log::debug!("failed to toss item: {}", e);
+                                }
+                            }
+                            Err(e) => log::debug!("failed to toss item: {}", e),
+                        }
+                    }
+                }
+                self.input_processor.step(tick);
+
+                self.continue_held_tool(tick);
+
+                let mut info = self.game_universe.step(unpaused_tick);
+
+                self.maybe_sync_ui();
+                info += self.ui.step(unpaused_tick);
+
+                self.last_step_info = info.clone();
+                result = Some(info)
+            }
+        }
+        result
+    }
+
+    /// If a button is being held down on a block that takes time to break, make
+    /// progress on breaking it.
+    fn continue_held_tool(&mut self, tick: Tick) {
+        let character_ref = match (&self.game_character, self.held_button) {
+            (Some(character_ref), Some(_)) => character_ref.clone(),
+            _ => return,
+        };
+        let transaction = match &self.cursor_result {
+            Some(cursor)
+                if cursor.space == character_ref.borrow().space
+                    && *character_ref
+                        .borrow()
+                        .selected_tool(self.held_button.unwrap())
+                        == Tool::DeleteBlock
+                    && cursor.evaluated.attributes.hardness.into_inner() > 0.0 =>
+            {
+                Character::continue_breaking(&character_ref, cursor, tick, &self.crack_blocks)
+            }
+            _ => Ok(Character::stop_breaking(&character_ref, &self.crack_blocks)),
+        };
+        match transaction {
+            Ok(transaction) => {
+                if let Err(e) = transaction.execute(&mut self.game_universe) {
+                    // The world changed out from under us; start over next time.
+                    log::debug!("failed to continue breaking block: {}", e);
+                    if let Err(e) = Character::stop_breaking(&character_ref, &self.crack_blocks)
+                        .execute(&mut self.game_universe)
+                    {
+                        log::debug!("failed to stop breaking block: {}", e);
+                    }
+                }
+            }
+            Err(e) => log::debug!("failed to continue breaking block: {}", e),
+        }
+    }
+
+    fn maybe_sync_ui(&mut self) {
+        if self.ui_dirty.get_and_clear() {
+            // TODO: Exact interaction between Character and Vui probably shouldn't be AllIsCubesAppState's responsibility.
+            if let Some(character_ref) = &self.game_character {
+                let character = character_ref.borrow();
+                self.ui
+                    .set_toolbar(&character.inventory().slots, &character.selected_slots())
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Call this once per frame to update the cursor raycast.
+    ///
+    /// TODO: bad API; revisit general cursor handling logic.
+    pub fn update_cursor(&mut self, ui_camera: &Camera, game_camera: &Camera) {
+        let ndc_pos = self.input_processor.cursor_ndc_position();
+
+        self.cursor_result = ndc_pos
+            .map(|p| ui_camera.project_ndc_into_world(p))
+            .and_then(|ray| cursor_raycast(ray, self.ui.current_space_mut()));
+
+        if self.cursor_result.is_none() {
+            if let Some(character_ref) = &self.game_character {
+                self.cursor_result = ndc_pos
+                    .map(|p| game_camera.project_ndc_into_world(p))
+                    .and_then(|ray| cursor_raycast(ray, &character_ref.borrow().space));
+            }
+        }
+    }
+
+    pub fn cursor_result(&self) -> &Option<Cursor> {
+        &self.cursor_result
+    }
+
+    /// TODO: Should have click feedback in VUI, not via return value.
+    pub fn click(&mut self, button: usize) -> Result<(), ToolError> {
+        if let (Some(cursor), Some(character_ref)) = (&self.cursor_result, &self.game_character) {
+            self.held_button = Some(button);
+            let transaction = match Character::click(character_ref.clone(), cursor, button) {
+                // The tool will take effect over time in `maybe_step_universe`.
+                Err(ToolError::RequiresHolding) => return Ok(()),
+                result => result?,
+            };
+            transaction
+                .execute(self.universe_mut())
+                .map_err(|e| ToolError::Internal(e.to_string()))?;
+            Ok(())
+        } else {
+            Err(ToolError::NothingSelected) // TODO: slightly wrong
+        }
+    }
+
+    /// Call this when a mouse button previously passed to [`Self::click`] is released.
+    pub fn release_button(&mut self, button: usize) {
+        if self.held_button != Some(button) {
+            return;
+        }
+        self.held_button = None;
+        if let Some(character_ref) = &self.game_character {
+            let transaction = Character::stop_breaking(character_ref, &self.crack_blocks);
+            if let Err(e) = transaction.execute(&mut self.game_universe) {
+                log::debug!("failed to stop breaking block: {}", e);
+            }
+        }
+    }
+
+    /// Returns textual information intended to be overlaid as a HUD on top of the rendered scene
+    /// containing diagnostic information about rendering and stepping.
+    pub fn info_text<T>(&self, render: T) -> InfoText<'_, T> {
+        InfoText { app: self, render }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct InfoText<'a, T> {
+    app: &'a AllIsCubesAppState,
+    render: T,
+}
+
+impl<T: CustomFormat<StatusText>> Display for InfoText<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{}\n",
+            self.app.game_universe.metadata().custom_format(StatusText)
+        )?;
+        if let Some(character_ref) = self.app.character() {
+            write!(f, "{}", character_ref.borrow().custom_format(StatusText)).unwrap();
+        }
+        write!(
+            f,
+            "\n\n{:#?}\n\n{:#?}\n\n",
+            self.app.last_step_info.custom_format(StatusText),
+            self.render.custom_format(StatusText),
+        )?;
+        match self.app.cursor_result() {
+            Some(cursor) => write!(f, "{}", cursor),
+            None => write!(f, "No block"),
+        }?;
+        Ok(())
+    }
+}
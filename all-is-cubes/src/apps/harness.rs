@@ -0,0 +1,311 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+use cgmath::{Point3, Vector2};
+use std::sync::Arc;
+
+use crate::apps::Session;
+use crate::camera::Viewport;
+#[cfg(feature = "content")]
+use crate::content::UniverseTemplate;
+#[cfg(feature = "content")]
+use crate::linking::GenError;
+use crate::math::{FreeCoordinate, GridCoordinate, Rgba};
+
+/// Runs a [`Session`] under a script, checking invariants as it goes, for use as an
+/// end-to-end smoke test.
+///
+/// Each frame, a caller-supplied script may provide input to the session, then the
+/// session takes exactly one [`Session::step`]; every
+/// [`SessionHarness::render_interval`] frames a [`Session::snapshot`] is taken. After
+/// the scripted frames, the harness keeps stepping (without input) until the
+/// character's space has no lighting updates left to do, unless that check is
+/// disabled with [`SessionHarness::settle_limit`]. Throughout, it checks that the
+/// character stays within its space.
+///
+/// ```
+/// use all_is_cubes::apps::{Key, Session, SessionHarness};
+/// use all_is_cubes::universe::Universe;
+///
+/// let mut harness = SessionHarness::from_session(Session::new(Universe::new()));
+/// let report = harness
+///     .run(10, |frame, session| {
+///         if frame == 0 {
+///             session.input_processor.key_down(Key::Character('w'));
+///         }
+///     })
+///     .unwrap();
+/// assert_eq!(report.scripted_frames, 10);
+/// assert_eq!(harness.session().step_count(), 10);
+/// ```
+#[derive(Debug)]
+pub struct SessionHarness {
+    session: Session,
+    render_interval: usize,
+    settle_limit: Option<usize>,
+    frame: usize,
+}
+
+impl SessionHarness {
+    /// Constructs a harness for a new session whose universe is built from `template`.
+    #[cfg(feature = "content")]
+    pub fn new(template: UniverseTemplate) -> Result<Self, GenError> {
+        Ok(Self::from_session(Session::from_template(template)?))
+    }
+
+    /// Constructs a harness for an existing session.
+    ///
+    /// The session's [`viewport`](Session::viewport) is set to 32×24, which is enough to
+    /// exercise the raytracer without taking much time; use
+    /// [`SessionHarness::viewport`] to change it.
+    pub fn from_session(session: Session) -> Self {
+        Self {
+            session: session.viewport(Viewport {
+                nominal_size: Vector2::new(32., 24.),
+                framebuffer_size: Vector2::new(32, 24),
+            }),
+            render_interval: 30,
+            settle_limit: Some(600),
+            frame: 0,
+        }
+    }
+
+    /// Sets how many frames pass between renders. Zero disables rendering.
+    /// The default is 30.
+    #[must_use]
+    pub fn render_interval(mut self, frames: usize) -> Self {
+        self.render_interval = frames;
+        self
+    }
+
+    /// Sets the size of the rendered images. The default is 32×24.
+    #[must_use]
+    pub fn viewport(mut self, viewport: Viewport) -> Self {
+        self.session = self.session.viewport(viewport);
+        self
+    }
+
+    /// Sets the maximum number of unscripted frames to run while waiting for lighting to
+    /// converge, or [`None`] to not wait or check convergence at all (appropriate for
+    /// spaces too large to fully light in a reasonable time).
+    /// The default is 600 (ten seconds of game time).
+    #[must_use]
+    pub fn settle_limit(mut self, frames: Option<usize>) -> Self {
+        self.settle_limit = frames;
+        self
+    }
+
+    /// Returns the session being run, such as for examining its universe after
+    /// [`SessionHarness::run`].
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns the session being run, for setup which the script passed to
+    /// [`SessionHarness::run`] does not do.
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Runs `frames` frames, calling `script` with the frame number (counting from 0
+    /// for this call) before each one, then waits for lighting to converge.
+    ///
+    /// Returns an error describing the first invariant that was found violated.
+    pub fn run<F>(
+        &mut self,
+        frames: usize,
+        mut script: F,
+    ) -> Result<SessionReport, InvariantViolation>
+    where
+        F: FnMut(usize, &mut Session),
+    {
+        let mut report = SessionReport {
+            scripted_frames: frames,
+            settle_frames: 0,
+            renders: 0,
+            last_image: None,
+        };
+
+        for frame in 0..frames {
+            script(frame, &mut self.session);
+            self.step_frame()?;
+            if self.render_interval > 0 && self.frame % self.render_interval == 0 {
+                if let Some(snapshot) = self.session.snapshot() {
+                    report.renders += 1;
+                    report.last_image = Some(snapshot.image);
+                }
+            }
+        }
+
+        let settle_limit = match self.settle_limit {
+            Some(limit) => limit,
+            None => return Ok(report),
+        };
+        while self.light_update_queue_len() > 0 {
+            if report.settle_frames >= settle_limit {
+                return Err(InvariantViolation::LightingDidNotConverge {
+                    frames: report.settle_frames,
+                    queue_len: self.light_update_queue_len(),
+                });
+            }
+            self.step_frame()?;
+            report.settle_frames += 1;
+        }
+
+        Ok(report)
+    }
+
+    /// Steps the session once and checks the invariants that should hold after a step.
+    fn step_frame(&mut self) -> Result<(), InvariantViolation> {
+        self.session.step();
+        self.frame += 1;
+        self.check_body()
+    }
+
+    fn check_body(&self) -> Result<(), InvariantViolation> {
+        let character_ref = match self.session.character() {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+        let character = character_ref.borrow();
+        let position = character.body.position;
+        let cube = position.map(|c| c.floor() as GridCoordinate);
+        if character.space.borrow().grid().contains_cube(cube) {
+            Ok(())
+        } else {
+            Err(InvariantViolation::BodyOutOfBounds {
+                frame: self.frame,
+                position,
+            })
+        }
+    }
+
+    fn light_update_queue_len(&self) -> usize {
+        match self.session.character() {
+            Some(character_ref) => character_ref
+                .borrow()
+                .space
+                .borrow()
+                .light_update_queue_len(),
+            None => 0,
+        }
+    }
+}
+
+/// Successful result of [`SessionHarness::run`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct SessionReport {
+    /// Number of frames the script was called for.
+    pub scripted_frames: usize,
+    /// Number of additional frames it took for lighting to converge.
+    pub settle_frames: usize,
+    /// Number of images rendered.
+    pub renders: usize,
+    /// The most recently rendered image, if any.
+    pub last_image: Option<Arc<[Rgba]>>,
+}
+
+/// An invariant checked by [`SessionHarness`] which did not hold.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum InvariantViolation {
+    #[error("character left its space at frame {frame}: position {position:?}")]
+    BodyOutOfBounds {
+        frame: usize,
+        position: Point3<FreeCoordinate>,
+    },
+    #[error("lighting did not converge after {frames} frames; {queue_len} cubes still queued")]
+    LightingDidNotConverge { frames: usize, queue_len: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::Key;
+    use crate::block::Block;
+    use crate::character::Character;
+    use crate::space::{Grid, Space};
+    use crate::universe::{Universe, UniverseIndex as _};
+    use ordered_float::NotNan;
+
+    /// A universe small enough that its lighting converges quickly.
+    fn small_universe() -> Universe {
+        let mut universe = Universe::new();
+        let mut space = Space::empty_positive(16, 8, 16);
+        space
+            .fill_uniform(Grid::new((0, 0, 0), (16, 1, 16)), &Block::from(Rgba::WHITE))
+            .unwrap();
+        space.spawn_mut().position = Point3::new(8.5, 2.5, 8.5).map(|s| NotNan::new(s).unwrap());
+        let space_ref = universe.insert("space".into(), space).unwrap();
+        universe
+            .insert("character".into(), Character::spawn_default(space_ref))
+            .unwrap();
+        universe
+    }
+
+    #[test]
+    fn lighting_converges() {
+        let mut harness = SessionHarness::from_session(Session::new(small_universe()));
+        harness
+            .run(10, |frame, session| {
+                if frame == 0 {
+                    session.input_processor.key_down(Key::Character('w'));
+                } else if frame == 5 {
+                    session.input_processor.key_up(Key::Character('w'));
+                }
+            })
+            .unwrap();
+        assert_eq!(harness.light_update_queue_len(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "content")]
+    fn demo_session() {
+        // The demo city is too big to wait for its lighting.
+        let mut harness = SessionHarness::new(UniverseTemplate::DemoCity)
+            .unwrap()
+            .render_interval(20)
+            .settle_limit(None);
+        let report = harness
+            .run(60, |frame, session| {
+                let input = &mut session.input_processor;
+                match frame {
+                    0 => {
+                        input.key_down(Key::Character('w'));
+                        input.key_down(Key::Left);
+                    }
+                    20 => {
+                        input.key_up(Key::Left);
+                        input.key_down(Key::Character(' '));
+                    }
+                    40 => {
+                        input.key_up(Key::Character('w'));
+                        input.key_up(Key::Character(' '));
+                        input.key_momentary(Key::Character('q'));
+                    }
+                    _ => {}
+                }
+            })
+            .unwrap();
+        assert_eq!(report.renders, 3);
+        assert_eq!(report.last_image.unwrap().len(), 32 * 24);
+    }
+
+    #[test]
+    fn body_out_of_bounds() {
+        let mut harness =
+            SessionHarness::from_session(Session::new(small_universe())).render_interval(0);
+        harness
+            .session()
+            .character()
+            .unwrap()
+            .borrow_mut()
+            .body
+            .position = Point3::new(1e6, 0.0, 0.0);
+        assert!(matches!(
+            harness.run(1, |_, _| {}),
+            Err(InvariantViolation::BodyOutOfBounds { frame: 1, .. })
+        ));
+    }
+}
@@ -5,12 +5,15 @@ use cgmath::{EuclideanSpace as _, Point2, Vector2, Vector3, Zero as _};
 use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
-use crate::apps::Tick;
+use crate::apps::{InputEvent, InputRecording, Tick};
 use crate::camera::Viewport;
-use crate::character::Character;
+use crate::character::{Character, Gait};
+use crate::input::{Action, Axis, Input, InputMap};
 use crate::listen::{ListenableCell, ListenableSource};
 use crate::math::FreeCoordinate;
 
+pub use crate::input::Key;
+
 /// Parse input events, particularly key-down/up pairs, into character control and such.
 ///
 /// This is designed to be a leaf of the dependency graph: it does not own or send
@@ -18,22 +21,25 @@ use crate::math::FreeCoordinate;
 /// must occur in the given order.
 ///
 /// 1. The platform-specific code should call [`InputProcessor::key_down`] and such to
-///    to provide input information.
+///    to provide input information. What each input does is determined by the
+///    processor's [`InputMap`].
 /// 2. The game loop should call [`InputProcessor::apply_input`] to apply the effects
 ///    of input on the relevant [`Character`].
 /// 3. The game loop should call [`InputProcessor::step`] to apply the effects of time
 ///    on the input processor.
 #[derive(Debug)]
 pub struct InputProcessor {
-    /// All [`Key`]s currently pressed.
-    keys_held: HashSet<Key>,
+    /// Which [`Action`] each [`Input`] performs.
+    bindings: InputMap,
+    /// All [`Input`]s currently pressed.
+    inputs_held: HashSet<Input>,
     /// As a special feature for supporting input without key-up events, stores all
     /// keypresses arriving through [`Self::key_momentary`] and virtually holds them
     /// for a short time. The value is the remaining time.
     momentary_timeout: HashMap<Key, Duration>,
-    /// [`Key`]s with one-shot effects when pressed which need to be applied
+    /// [`Action`]s with one-shot effects when pressed which need to be applied
     /// once per press rather than while held.
-    command_buffer: Vec<Key>,
+    command_buffer: Vec<Action>,
 
     /// Do we *want* pointer lock for mouselook?
     ///
@@ -45,79 +51,143 @@ pub struct InputProcessor {
     /// Net mouse movement since the last [`Self::apply_input`].
     mouselook_buffer: Vector2<FreeCoordinate>,
 
+    /// Most recent positions reported by [`Self::axis_position`].
+    axis_positions: HashMap<Axis, FreeCoordinate>,
+
     /// Mouse position in NDC. None if out of bounds/lost focus.
     mouse_ndc_position: Option<Point2<FreeCoordinate>>,
 
     /// Mouse position used for generating mouselook deltas.
     /// [`None`] if games.
     mouse_previous_pixel_position: Option<Point2<f64>>,
+
+    /// Number of item tosses requested and not yet retrieved by [`Self::take_tosses`].
+    pending_tosses: usize,
+
+    /// Tool button presses and releases not yet retrieved by [`Self::take_tool_buttons`].
+    pending_tool_buttons: Vec<(usize, bool)>,
+
+    /// If recording, the input so far.
+    recording: Option<InputRecording>,
 }
 
 impl InputProcessor {
     #[allow(clippy::new_without_default)] // I expect it'll grow some parameters
     pub fn new() -> Self {
         Self {
-            keys_held: HashSet::new(),
+            bindings: InputMap::default(),
+            inputs_held: HashSet::new(),
             momentary_timeout: HashMap::new(),
             command_buffer: Vec::new(),
             mouselook_mode: ListenableCell::new(false), // TODO: might want a parameter
             has_pointer_lock: false,
             mouselook_buffer: Vector2::zero(),
+            axis_positions: HashMap::new(),
             mouse_ndc_position: Some(Point2::origin()),
             mouse_previous_pixel_position: None,
+            pending_tosses: 0,
+            pending_tool_buttons: Vec::new(),
+            recording: None,
         }
     }
 
-    fn is_bound(key: Key) -> bool {
-        // Eventually we'll have actual configurable keybindings...
-        match key {
-            // Used in `InputProcessor::movement()`.
-            Key::Character('w') => true,
-            Key::Character('a') => true,
-            Key::Character('s') => true,
-            Key::Character('d') => true,
-            Key::Character('e') => true,
-            Key::Character('c') => true,
-            // Used in `InputProcessor::apply_input()`.
-            Key::Left => true,
-            Key::Right => true,
-            Key::Up => true,
-            Key::Down => true,
-            Key::Character(' ') => true,
-            Key::Character(d) if d.is_ascii_digit() => true,
-            Key::Character('l') => true,
-            Key::Character('p') => true,
-            _ => false,
-        }
+    /// Starts recording all input provided to this processor, discarding any previous
+    /// recording. Changes to [`Self::set_bindings`] are not recorded.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(InputRecording::default());
     }
 
-    /// Returns true if the key should go in `command_buffer`.
-    fn is_command(key: Key) -> bool {
-        #[allow(clippy::match_like_matches_macro)]
-        match key {
-            Key::Character(d) if d.is_ascii_digit() => true,
-            Key::Character('l') => true,
-            Key::Character('p') => true,
-            // TODO: move slot selection commands here
-            _ => false,
+    /// Stops recording and returns the input recorded since
+    /// [`Self::start_recording`], or [`None`] if there was no recording in progress.
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        self.recording.take()
+    }
+
+    fn record(&mut self, event: InputEvent) {
+        if let Some(recording) = &mut self.recording {
+            recording.record(event);
         }
     }
 
-    /// Handles incoming key-down events. Returns whether the key was unbound.
+    /// Returns the [`InputMap`] in use, which may be consulted to show the player which
+    /// inputs perform which actions.
+    pub fn bindings(&self) -> &InputMap {
+        &self.bindings
+    }
+
+    /// Replaces the [`InputMap`] in use. Inputs which are currently held are released.
+    pub fn set_bindings(&mut self, bindings: InputMap) {
+        self.release_all();
+        self.bindings = bindings;
+    }
+
+    /// Handles incoming key-down events. Returns whether the key was bound.
     pub fn key_down(&mut self, key: Key) -> bool {
-        let bound = Self::is_bound(key);
-        if bound {
-            self.keys_held.insert(key);
-            if Self::is_command(key) {
-                self.command_buffer.push(key);
-            }
-        }
-        bound
+        self.input_down(Input::Key(key))
     }
 
     /// Handles incoming key-up events.
     pub fn key_up(&mut self, key: Key) {
-        self.keys_held.remove(&key);
+        self.input_up(Input::Key(key))
+    }
+
+    /// Handles incoming press events from any kind of [`Input`]. Returns whether the
+    /// input was bound to an action.
+    pub fn input_down(&mut self, input: Input) -> bool {
+        self.record(InputEvent::Down(input));
+        let action = match self.bindings.action(input) {
+            Some(action) => action,
+            None => return false,
+        };
+        let newly_pressed = self.inputs_held.insert(input);
+        if action.is_command() {
+            self.command_buffer.push(action);
+        }
+        if let (true, Some(button)) = (newly_pressed, action.tool_button()) {
+            self.pending_tool_buttons.push((button, true));
+        }
+        true
+    }
+
+    /// Handles incoming release events from any kind of [`Input`].
+    pub fn input_up(&mut self, input: Input) {
+        self.record(InputEvent::Up(input));
+        if self.inputs_held.remove(&input) {
+            if let Some(button) = self.bindings.action(input).and_then(Action::tool_button) {
+                self.pending_tool_buttons.push((button, false));
+            }
+        }
+    }
+
+    /// Handles an input whose state is polled rather than reported by events, such as
+    /// a gamepad button: calls [`Self::input_down`] or [`Self::input_up`] only if the
+    /// state has changed since the last call.
+    pub fn input_held(&mut self, input: Input, held: bool) {
+        if held != self.inputs_held.contains(&input) {
+            if held {
+                self.input_down(input);
+            } else {
+                self.input_up(input);
+            }
+        }
+    }
+
+    /// Provide the position of an analog [`Axis`], from -1 to 1. The position is
+    /// assumed to remain the same until this is called again. What it does is
+    /// determined by [`InputMap::analog`].
+    pub fn axis_position(&mut self, axis: Axis, position: FreeCoordinate) {
+        self.record(InputEvent::AxisPosition(axis, position));
+        if self.bindings.axis_action(axis).is_some() {
+            self.axis_positions.insert(axis, position);
+        }
+    }
+
+    /// Releases all held inputs, as if [`Self::input_up`] were called for each.
+    fn release_all(&mut self) {
+        let held: Vec<Input> = self.inputs_held.iter().copied().collect();
+        for input in held {
+            self.input_up(input);
+        }
     }
 
     /// Handles incoming key events in the case where key-up events are not available,
@@ -135,11 +205,13 @@ impl InputProcessor {
     /// `InputProcessor` will assume that if focus is lost, key-up events may be lost and
     /// so currently held keys should stop taking effect.
     pub fn key_focus(&mut self, has_focus: bool) {
+        self.record(InputEvent::Focus(has_focus));
         if has_focus {
             // Nothing to do.
         } else {
-            self.keys_held.clear();
+            self.release_all();
             self.momentary_timeout.clear();
+            self.axis_positions.clear();
 
             self.mouselook_mode.set(false);
         }
@@ -157,6 +229,7 @@ impl InputProcessor {
     /// known to be successfully enabled, after [`InputProcessor::wants_pointer_lock`]
     /// requests it or it is disabled for any reason.
     pub fn has_pointer_lock(&mut self, value: bool) {
+        self.record(InputEvent::HasPointerLock(value));
         self.has_pointer_lock = value;
     }
 
@@ -167,6 +240,7 @@ impl InputProcessor {
     ///
     /// Note that absolute cursor positions must be provided separately.
     pub fn mouselook_delta(&mut self, delta: Vector2<FreeCoordinate>) {
+        self.record(InputEvent::MouselookDelta(delta.into()));
         // TODO: sensitivity option
         if self.has_pointer_lock {
             self.mouselook_buffer += delta * 0.2;
@@ -184,6 +258,7 @@ impl InputProcessor {
     /// If this is never called, the default value is (0, 0) which corresponds to the
     /// center of the screen.
     pub fn mouse_ndc_position(&mut self, position: Option<Point2<FreeCoordinate>>) {
+        self.record(InputEvent::MouseNdcPosition(position.map(Into::into)));
         self.mouse_ndc_position = position.filter(|p| p.x.abs() <= 1. && p.y.abs() <= 1.);
     }
 
@@ -221,11 +296,13 @@ impl InputProcessor {
 
     /// Returns the character movement velocity that input is currently requesting.
     pub fn movement(&self) -> Vector3<FreeCoordinate> {
+        let (walk, _) = self.analog();
         Vector3::new(
-            self.net_movement(Key::Character('a'), Key::Character('d')),
-            self.net_movement(Key::Character('c'), Key::Character('e')),
-            self.net_movement(Key::Character('w'), Key::Character('s')),
+            self.net_movement(Action::MoveLeft, Action::MoveRight) + walk.x,
+            self.net_movement(Action::MoveDown, Action::MoveUp),
+            self.net_movement(Action::MoveForward, Action::MoveBack) + walk.y,
         )
+        .map(|c| c.max(-1.0).min(1.0))
     }
 
     /// Advance time insofar as input interpretation is affected by time.
@@ -233,6 +310,12 @@ impl InputProcessor {
     /// This method should be called *after* [`apply_input`](Self::apply_input), when
     /// applicable.
     pub fn step(&mut self, tick: Tick) {
+        // Anything that happens from here on, including the releases below, is input
+        // to the next step.
+        if let Some(recording) = &mut self.recording {
+            recording.steps += 1;
+        }
+
         let mut to_drop = Vec::new();
         for (key, duration) in self.momentary_timeout.iter_mut() {
             if let Some(reduced) = duration.checked_sub(tick.delta_t) {
@@ -249,6 +332,23 @@ impl InputProcessor {
         self.mouselook_buffer = Vector2::zero();
     }
 
+    /// Returns the number of times the player has asked to toss the selected item since
+    /// the last call. The caller should use [`Character::toss`] for each.
+    ///
+    /// This should be called after [`apply_input`](Self::apply_input).
+    pub fn take_tosses(&mut self) -> usize {
+        std::mem::take(&mut self.pending_tosses)
+    }
+
+    /// Returns the tool buttons which have been pressed (`true`) or released (`false`)
+    /// since the last call, in order. The caller should use
+    /// [`AllIsCubesAppState::click`](super::AllIsCubesAppState::click) and
+    /// [`AllIsCubesAppState::release_button`](super::AllIsCubesAppState::release_button)
+    /// for each.
+    pub fn take_tool_buttons(&mut self) -> Vec<(usize, bool)> {
+        std::mem::take(&mut self.pending_tool_buttons)
+    }
+
     /// Applies the current input to the given [`Character`].
     ///
     /// TODO: We need a better information flow strategy that still keeps InputProcessor not too tied to AllIsCubesAppState.
@@ -263,21 +363,31 @@ impl InputProcessor {
 
         let movement = self.movement();
         character.set_velocity_input(movement);
+        character.set_gait(if self.is_held(Action::Sneak) {
+            Gait::Sneak
+        } else if self.is_held(Action::Sprint) {
+            Gait::Sprint
+        } else {
+            Gait::Normal
+        });
 
+        let (_, look) = self.analog();
         let turning = Vector2::new(
-            key_turning_step * self.net_movement(Key::Left, Key::Right) + self.mouselook_buffer.x,
-            key_turning_step * self.net_movement(Key::Up, Key::Down) + self.mouselook_buffer.y,
-        );
+            key_turning_step * self.net_movement(Action::TurnLeft, Action::TurnRight)
+                + self.mouselook_buffer.x,
+            key_turning_step * self.net_movement(Action::TurnUp, Action::TurnDown)
+                + self.mouselook_buffer.y,
+        ) + look * dt;
         character.body.yaw = (character.body.yaw + turning.x).rem_euclid(360.0);
         character.body.pitch = (character.body.pitch + turning.y).min(90.0).max(-90.0);
 
-        if self.keys_held.contains(&Key::Character(' ')) {
+        if self.is_held(Action::Jump) {
             character.jump_if_able();
         }
 
-        for key in self.command_buffer.drain(..) {
-            match key {
-                Key::Character('l') => {
+        for action in self.command_buffer.drain(..) {
+            match action {
+                Action::ToggleMouselook => {
                     let new_state = !*self.mouselook_mode.get();
                     self.mouselook_mode.set(new_state);
                     if new_state {
@@ -285,13 +395,18 @@ impl InputProcessor {
                         self.mouse_previous_pixel_position = None;
                     }
                 }
-                Key::Character('p') => {
+                Action::TogglePause => {
                     // TODO: bind escape key, focus loss, etc to pause
                     paused.set(!*paused.get());
                 }
-                Key::Character(numeral) if numeral.is_digit(10) => {
-                    let digit = numeral.to_digit(10).unwrap() as usize;
-                    let slot = (digit + 9).rem_euclid(10); // wrap 0 to 9
+                Action::ToggleNoclip => {
+                    character.body.noclip = !character.body.noclip;
+                }
+                Action::Toss => {
+                    // Tossing requires a transaction, which we can't make from here.
+                    self.pending_tosses += 1;
+                }
+                Action::SelectSlot(slot) => {
                     character.set_selected_slot(1, slot);
                 }
                 _ => {}
@@ -316,12 +431,25 @@ impl InputProcessor {
         }
     }
 
-    /// Computes the net effect of a pair of opposed inputs (e.g. "forward" and "back").
-    fn net_movement(&self, negative: Key, positive: Key) -> FreeCoordinate {
-        match (
-            self.keys_held.contains(&negative),
-            self.keys_held.contains(&positive),
-        ) {
+    /// Returns the walking and looking vectors from analog axes.
+    fn analog(&self) -> (Vector2<FreeCoordinate>, Vector2<FreeCoordinate>) {
+        self.bindings.analog(
+            self.axis_positions
+                .iter()
+                .map(|(&axis, &position)| (axis, position)),
+        )
+    }
+
+    /// Returns whether any input bound to `action` is held.
+    fn is_held(&self, action: Action) -> bool {
+        self.inputs_held
+            .iter()
+            .any(|&input| self.bindings.action(input) == Some(action))
+    }
+
+    /// Computes the net effect of a pair of opposed actions (e.g. "forward" and "back").
+    fn net_movement(&self, negative: Action, positive: Action) -> FreeCoordinate {
+        match (self.is_held(negative), self.is_held(positive)) {
             (true, false) => -1.0,
             (false, true) => 1.0,
             _ => 0.0,
@@ -329,22 +457,6 @@ impl InputProcessor {
     }
 }
 
-/// A platform-neutral representation of keyboard keys for [`InputProcessor`].
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-#[non_exhaustive]
-pub enum Key {
-    /// Letters should be lowercase.
-    Character(char),
-    /// Left arrow key.
-    Left,
-    /// Right arrow key.
-    Right,
-    /// Up arrow key.
-    Up,
-    /// Down arrow key.
-    Down,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -408,5 +520,77 @@ mod tests {
         assert_eq!(character.borrow_mut().selected_slots()[1], 9);
     }
 
+    #[test]
+    fn toss_requests() {
+        let mut u = Universe::new();
+        let space = u.insert_anonymous(Space::empty_positive(1, 1, 1));
+        let character = u.insert_anonymous(Character::spawn_default(space.clone()));
+        let mut input = InputProcessor::new();
+
+        input.key_down(Key::Character('q'));
+        input.key_up(Key::Character('q'));
+        input.key_down(Key::Character('q'));
+        input.key_up(Key::Character('q'));
+        assert_eq!(input.take_tosses(), 0);
+        input.apply_input(
+            &mut *character.borrow_mut(),
+            &ListenableCell::new(false),
+            Tick::arbitrary(),
+        );
+        assert_eq!(input.take_tosses(), 2);
+        assert_eq!(input.take_tosses(), 0);
+    }
+
+    #[test]
+    fn rebinding() {
+        let mut input = InputProcessor::new();
+        let mut bindings = InputMap::new();
+        bindings.bind(Key::Up, Action::MoveForward);
+        input.set_bindings(bindings);
+
+        assert!(!input.key_down(Key::Character('w')));
+        assert_eq!(input.movement(), Vector3::zero());
+        assert!(input.key_down(Key::Up));
+        assert_eq!(input.movement(), Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn tool_buttons() {
+        let mut input = InputProcessor::new();
+        input.input_down(Input::MouseButton(1));
+        input.input_down(Input::MouseButton(1)); // repeated press is ignored
+        input.key_focus(false); // releases the button
+        input.input_down(Input::MouseButton(0));
+        input.input_up(Input::MouseButton(0));
+        assert_eq!(
+            input.take_tool_buttons(),
+            vec![(1, true), (1, false), (0, true), (0, false)]
+        );
+        assert_eq!(input.take_tool_buttons(), vec![]);
+    }
+
+    #[test]
+    fn analog_walking() {
+        let mut input = InputProcessor::new();
+        input.axis_position(Axis::GamepadAxis(1), -1.0);
+        assert_eq!(input.movement(), Vector3::new(0.0, 0.0, -1.0));
+        // Combined with keys, but not faster than full speed.
+        input.key_down(Key::Character('w'));
+        assert_eq!(input.movement(), Vector3::new(0.0, 0.0, -1.0));
+        // Within the dead zone.
+        input.axis_position(Axis::GamepadAxis(1), 0.1);
+        input.key_up(Key::Character('w'));
+        assert_eq!(input.movement(), Vector3::zero());
+    }
+
+    #[test]
+    fn polled_buttons_act_once() {
+        let mut input = InputProcessor::new();
+        input.input_held(Input::GamepadButton(7), true);
+        input.input_held(Input::GamepadButton(7), true);
+        input.input_held(Input::GamepadButton(7), false);
+        assert_eq!(input.take_tool_buttons(), vec![(1, true), (1, false)]);
+    }
+
     // TODO: test jump and flying logic
 }
@@ -0,0 +1,309 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+use instant::Duration; // wasm-compatible replacement for std::time::Duration
+use ordered_float::NotNan;
+
+use crate::camera::{GraphicsOptions, LightingOption};
+use crate::math::FreeCoordinate;
+
+/// One step of reduced quality that [`QualityController`] may choose.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct QualityStep {
+    /// Multiplier for [`GraphicsOptions::view_distance`].
+    view_distance: FreeCoordinate,
+    /// Whether [`LightingOption::Smooth`] may be used.
+    smooth_lighting: bool,
+    /// Value of [`QualityController::resolution_scale`].
+    resolution_scale: f32,
+}
+
+/// The quality levels, from full quality to least; indexed by
+/// [`QualityController::level`].
+const QUALITY_STEPS: [QualityStep; 6] = [
+    QualityStep {
+        view_distance: 1.0,
+        smooth_lighting: true,
+        resolution_scale: 1.0,
+    },
+    QualityStep {
+        view_distance: 0.8,
+        smooth_lighting: true,
+        resolution_scale: 1.0,
+    },
+    QualityStep {
+        view_distance: 0.8,
+        smooth_lighting: false,
+        resolution_scale: 1.0,
+    },
+    QualityStep {
+        view_distance: 0.64,
+        smooth_lighting: false,
+        resolution_scale: 0.75,
+    },
+    QualityStep {
+        view_distance: 0.5,
+        smooth_lighting: false,
+        resolution_scale: 0.5,
+    },
+    QualityStep {
+        view_distance: 0.4,
+        smooth_lighting: false,
+        resolution_scale: 0.25,
+    },
+];
+
+/// Automatically reduces the quality of rendering when frames take longer than a
+/// target time to render, and restores it when there is time to spare.
+///
+/// Platform-independent; does not consult any clocks, only makes decisions given the
+/// provided information, like [`FrameClock`](super::FrameClock). For each frame, pass
+/// the time spent rendering to [`Self::record_frame_time`], then draw using
+/// [`Self::adjust_options`] applied to the user's chosen [`GraphicsOptions`], and, if
+/// the renderer can vary its resolution (as raytracers can), scale the image by
+/// [`Self::resolution_scale`].
+///
+/// The user's options are never exceeded, only reduced. To avoid visibly flickering
+/// between two levels, quality is reduced only after rendering has been slow for a
+/// while, and increased only after it has been well under the target for longer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualityController {
+    target_frame_time: Duration,
+    /// Smoothed recent values passed to [`Self::record_frame_time`].
+    frame_time: Option<Duration>,
+    /// Index into [`QUALITY_STEPS`].
+    level: usize,
+    /// If set, `level` is fixed at this value.
+    fixed_level: Option<usize>,
+    /// Number of consecutive frames, at the current level, for which the smoothed
+    /// frame time was over the target.
+    slow_frames: u32,
+    /// Number of consecutive frames, at the current level, for which the smoothed
+    /// frame time was under [`Self::INCREASE_THRESHOLD`] of the target.
+    fast_frames: u32,
+}
+
+impl QualityController {
+    /// The lowest quality level.
+    pub const MAX_LEVEL: usize = QUALITY_STEPS.len() - 1;
+
+    /// Number of consecutive slow frames after which quality is reduced.
+    const FRAMES_BEFORE_DECREASE: u32 = 30;
+    /// Number of consecutive fast frames after which quality is increased.
+    const FRAMES_BEFORE_INCREASE: u32 = 180;
+    /// Quality is increased only if frames take less than this fraction of the target,
+    /// so that the increase is unlikely to make them take longer than the target.
+    const INCREASE_THRESHOLD: f64 = 0.6;
+
+    /// Constructs a [`QualityController`] which tries to keep frames taking at most
+    /// `target_frame_time` to render, starting at full quality.
+    pub fn new(target_frame_time: Duration) -> Self {
+        Self {
+            target_frame_time,
+            frame_time: None,
+            level: 0,
+            fixed_level: None,
+            slow_frames: 0,
+            fast_frames: 0,
+        }
+    }
+
+    /// Returns the frame time this controller is aiming for.
+    pub fn target_frame_time(&self) -> Duration {
+        self.target_frame_time
+    }
+
+    /// Sets the frame time this controller is aiming for.
+    pub fn set_target_frame_time(&mut self, target_frame_time: Duration) {
+        self.target_frame_time = target_frame_time;
+    }
+
+    /// Informs the controller how long it took to render a frame, such as
+    /// [`RenderInfo::frame_time`](crate::lum::RenderInfo::frame_time) or the time a
+    /// [`SpaceRaytracer`](crate::raytracer::SpaceRaytracer) took to draw the image, and
+    /// adjusts the quality level if appropriate.
+    ///
+    /// As with [`FrameClock::record_frame_timing`](super::FrameClock::record_frame_timing),
+    /// this should not include time spent waiting for vsync.
+    pub fn record_frame_time(&mut self, frame_time: Duration) {
+        let smoothed = match self.frame_time {
+            // Exponential moving average, so that one slow frame doesn't count as
+            // falling behind.
+            Some(previous) => (previous * 3 + frame_time) / 4,
+            None => frame_time,
+        };
+        self.frame_time = Some(smoothed);
+
+        if self.fixed_level.is_some() {
+            return;
+        }
+        let target = self.target_frame_time.as_secs_f64();
+        if smoothed > self.target_frame_time {
+            self.slow_frames = self.slow_frames.saturating_add(1);
+        } else {
+            self.slow_frames = 0;
+        }
+        if smoothed.as_secs_f64() < target * Self::INCREASE_THRESHOLD {
+            self.fast_frames = self.fast_frames.saturating_add(1);
+        } else {
+            self.fast_frames = 0;
+        }
+
+        if self.slow_frames >= Self::FRAMES_BEFORE_DECREASE && self.level < Self::MAX_LEVEL {
+            self.set_level(self.level + 1);
+        } else if self.fast_frames >= Self::FRAMES_BEFORE_INCREASE && self.level > 0 {
+            self.set_level(self.level - 1);
+        }
+    }
+
+    /// Returns the current quality level: 0 is full quality, and greater numbers up to
+    /// [`Self::MAX_LEVEL`] are progressively reduced quality.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Overrides the automatic choice of quality level, such as when the user has
+    /// chosen one explicitly, or returns to automatic control if `level` is [`None`].
+    /// Levels greater than [`Self::MAX_LEVEL`] are treated as `MAX_LEVEL`.
+    pub fn set_fixed_level(&mut self, level: Option<usize>) {
+        let level = level.map(|level| level.min(Self::MAX_LEVEL));
+        self.fixed_level = level;
+        if let Some(level) = level {
+            self.set_level(level);
+        }
+    }
+
+    /// Returns the level set by [`Self::set_fixed_level`], if any.
+    pub fn fixed_level(&self) -> Option<usize> {
+        self.fixed_level
+    }
+
+    /// Returns `options` reduced to the current quality level.
+    pub fn adjust_options(&self, options: &GraphicsOptions) -> GraphicsOptions {
+        let step = QUALITY_STEPS[self.level];
+        let mut options = options.clone();
+        options.view_distance = options.view_distance * NotNan::new(step.view_distance).unwrap();
+        if !step.smooth_lighting && options.lighting_display == LightingOption::Smooth {
+            options.lighting_display = LightingOption::Flat;
+        }
+        options.repair()
+    }
+
+    /// Returns the factor, between 0 and 1, by which renderers that can vary their
+    /// resolution, such as raytracers, should scale the width and height of the image
+    /// they compute, at the current quality level.
+    pub fn resolution_scale(&self) -> f32 {
+        QUALITY_STEPS[self.level].resolution_scale
+    }
+
+    fn set_level(&mut self, level: usize) {
+        if level != self.level {
+            self.level = level;
+            self.slow_frames = 0;
+            self.fast_frames = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: Duration = Duration::from_millis(16);
+
+    fn record(controller: &mut QualityController, frames: u32, frame_time: Duration) {
+        for _ in 0..frames {
+            controller.record_frame_time(frame_time);
+        }
+    }
+
+    #[test]
+    fn fast_frames_keep_full_quality() {
+        let mut controller = QualityController::new(TARGET);
+        record(&mut controller, 1000, TARGET / 2);
+        assert_eq!(controller.level(), 0);
+        assert_eq!(controller.resolution_scale(), 1.0);
+        let options = GraphicsOptions::default();
+        assert_eq!(controller.adjust_options(&options), options.repair());
+    }
+
+    #[test]
+    fn slow_frames_reduce_quality_gradually() {
+        let mut controller = QualityController::new(TARGET);
+        // One slow frame is not enough.
+        record(&mut controller, 100, TARGET / 2);
+        record(&mut controller, 1, TARGET * 3);
+        assert_eq!(controller.level(), 0);
+
+        record(
+            &mut controller,
+            QualityController::FRAMES_BEFORE_DECREASE,
+            TARGET * 2,
+        );
+        assert_eq!(controller.level(), 1);
+        record(
+            &mut controller,
+            QualityController::FRAMES_BEFORE_DECREASE,
+            TARGET * 2,
+        );
+        assert_eq!(controller.level(), 2);
+        record(&mut controller, 10000, TARGET * 2);
+        assert_eq!(controller.level(), QualityController::MAX_LEVEL);
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut controller = QualityController::new(TARGET);
+        record(
+            &mut controller,
+            QualityController::FRAMES_BEFORE_DECREASE,
+            TARGET * 2,
+        );
+        assert_eq!(controller.level(), 1);
+        // Slightly under the target is not enough to increase quality again.
+        record(&mut controller, 1000, TARGET * 9 / 10);
+        assert_eq!(controller.level(), 1);
+        // Well under the target is, but only after a while.
+        record(&mut controller, 100, TARGET / 4);
+        assert_eq!(controller.level(), 1);
+        record(
+            &mut controller,
+            QualityController::FRAMES_BEFORE_INCREASE,
+            TARGET / 4,
+        );
+        assert_eq!(controller.level(), 0);
+    }
+
+    #[test]
+    fn fixed_level() {
+        let mut controller = QualityController::new(TARGET);
+        controller.set_fixed_level(Some(100));
+        assert_eq!(controller.fixed_level(), Some(QualityController::MAX_LEVEL));
+        assert_eq!(controller.level(), QualityController::MAX_LEVEL);
+        record(&mut controller, 1000, TARGET / 4);
+        assert_eq!(controller.level(), QualityController::MAX_LEVEL);
+
+        controller.set_fixed_level(None);
+        record(&mut controller, 1000, TARGET / 4);
+        assert_eq!(controller.level(), 0);
+    }
+
+    #[test]
+    fn adjust_options_reduces() {
+        let mut controller = QualityController::new(TARGET);
+        controller.set_fixed_level(Some(QualityController::MAX_LEVEL));
+        let mut options = GraphicsOptions {
+            lighting_display: LightingOption::Smooth,
+            ..GraphicsOptions::default()
+        };
+        let adjusted = controller.adjust_options(&options);
+        assert!(adjusted.view_distance < options.view_distance);
+        assert_eq!(adjusted.lighting_display, LightingOption::Flat);
+        assert!(controller.resolution_scale() < 1.0);
+
+        // Options already below what the level allows are left alone.
+        options.lighting_display = LightingOption::None;
+        let adjusted = controller.adjust_options(&options);
+        assert_eq!(adjusted.lighting_display, LightingOption::None);
+    }
+}
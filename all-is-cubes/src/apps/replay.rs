@@ -0,0 +1,239 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Recording and replaying the input to an [`AllIsCubesAppState`].
+
+use cgmath::{Point2, Vector2};
+use serde::{Deserialize, Serialize};
+
+use crate::apps::InputProcessor;
+#[cfg(feature = "content")]
+use crate::apps::{AllIsCubesAppState, FrameClock};
+#[cfg(feature = "content")]
+use crate::camera::{Camera, Viewport};
+use crate::input::{Axis, Input};
+use crate::math::FreeCoordinate;
+#[cfg(feature = "content")]
+use crate::vui::Vui;
+
+/// One call to an input method of [`InputProcessor`], as stored in an
+/// [`InputRecording`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum InputEvent {
+    /// [`InputProcessor::input_down`].
+    Down(Input),
+    /// [`InputProcessor::input_up`].
+    Up(Input),
+    /// [`InputProcessor::axis_position`].
+    AxisPosition(Axis, FreeCoordinate),
+    /// [`InputProcessor::mouselook_delta`].
+    MouselookDelta([FreeCoordinate; 2]),
+    /// [`InputProcessor::mouse_ndc_position`].
+    MouseNdcPosition(Option<[FreeCoordinate; 2]>),
+    /// [`InputProcessor::has_pointer_lock`].
+    HasPointerLock(bool),
+    /// [`InputProcessor::key_focus`].
+    Focus(bool),
+}
+
+impl InputEvent {
+    /// Performs the call this event records.
+    pub fn apply(self, input_processor: &mut InputProcessor) {
+        match self {
+            InputEvent::Down(input) => {
+                input_processor.input_down(input);
+            }
+            InputEvent::Up(input) => input_processor.input_up(input),
+            InputEvent::AxisPosition(axis, position) => {
+                input_processor.axis_position(axis, position)
+            }
+            InputEvent::MouselookDelta(delta) => {
+                input_processor.mouselook_delta(Vector2::from(delta))
+            }
+            InputEvent::MouseNdcPosition(position) => {
+                input_processor.mouse_ndc_position(position.map(Point2::from))
+            }
+            InputEvent::HasPointerLock(value) => input_processor.has_pointer_lock(value),
+            InputEvent::Focus(has_focus) => input_processor.key_focus(has_focus),
+        }
+    }
+}
+
+/// An [`InputEvent`] and when it happened.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RecordedEvent {
+    /// Number of steps which had been performed, since recording began, when the
+    /// event happened.
+    pub step: u64,
+    /// What happened.
+    pub event: InputEvent,
+}
+
+/// Input to an [`InputProcessor`], timestamped by the steps it occurred between, so
+/// that it can be replayed with [`ReplayPlayer`].
+///
+/// Obtain one from [`InputProcessor::start_recording`] and
+/// [`InputProcessor::stop_recording`]. It may be saved using [`serde`] for use in
+/// bug reports or tests.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    pub(crate) events: Vec<RecordedEvent>,
+    pub(crate) steps: u64,
+}
+
+impl InputRecording {
+    /// Returns the recorded events, in order.
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    /// Returns the number of steps performed during the recording.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub(crate) fn record(&mut self, event: InputEvent) {
+        self.events.push(RecordedEvent {
+            step: self.steps,
+            event,
+        });
+    }
+}
+
+/// Replays an [`InputRecording`] into an [`AllIsCubesAppState`].
+///
+/// Since the universe is deterministic given its input, replaying into an app whose
+/// universe was created the same way as the one recorded (whether that was in an app or
+/// a [`Session`](super::Session)) reproduces the recording exactly, as long as the app
+/// is stepped once per [`ReplayPlayer::feed`] (as [`ReplayPlayer::play_step`] does).
+///
+/// ```
+/// use all_is_cubes::apps::{AllIsCubesAppState, Key, ReplayPlayer, SessionHarness};
+/// use all_is_cubes::content::UniverseTemplate;
+///
+/// // Record walking forward for a while.
+/// let mut harness = SessionHarness::new(UniverseTemplate::Blank)
+///     .unwrap()
+///     .settle_limit(None);
+/// harness.session_mut().input_processor.start_recording();
+/// harness
+///     .run(10, |frame, session| {
+///         if frame == 2 {
+///             session.input_processor.key_down(Key::Character('w'));
+///         }
+///     })
+///     .unwrap();
+/// let recording = harness.session_mut().input_processor.stop_recording().unwrap();
+///
+/// // Replay it in a new app.
+/// let mut app = AllIsCubesAppState::new(UniverseTemplate::Blank);
+/// let mut player = ReplayPlayer::new(recording);
+/// while !player.is_finished() {
+///     player.play_step(&mut app);
+/// }
+/// assert_eq!(
+///     app.character().unwrap().borrow().body.position,
+///     harness.session().character().unwrap().borrow().body.position,
+/// );
+/// ```
+#[cfg(feature = "content")]
+#[derive(Clone, Debug)]
+pub struct ReplayPlayer {
+    recording: InputRecording,
+    viewport: Viewport,
+    next_event: usize,
+    step: u64,
+}
+
+#[cfg(feature = "content")]
+impl ReplayPlayer {
+    /// Constructs a player which starts at the beginning of `recording`.
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            viewport: Viewport {
+                nominal_size: Vector2::new(640., 480.),
+                framebuffer_size: Vector2::new(640, 480),
+            },
+            next_event: 0,
+            step: 0,
+        }
+    }
+
+    /// Sets the viewport used to aim the cursor at the positions recorded. This should
+    /// have the same aspect ratio as the one used while recording. The default is
+    /// 640×480.
+    #[must_use]
+    pub fn viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    /// Returns whether every recorded step has been fed.
+    pub fn is_finished(&self) -> bool {
+        self.step >= self.recording.steps && self.next_event >= self.recording.events.len()
+    }
+
+    /// Provides `app` with the input recorded before the next step, and updates its
+    /// cursor. The caller must then step the app exactly once.
+    pub fn feed(&mut self, app: &mut AllIsCubesAppState) {
+        while let Some(recorded) = self.recording.events.get(self.next_event) {
+            if recorded.step > self.step {
+                break;
+            }
+            recorded.event.apply(&mut app.input_processor);
+            self.next_event += 1;
+        }
+        self.step += 1;
+
+        let options = app.graphics_options().snapshot();
+        let mut ui_camera = Camera::new(Vui::graphics_options(options.clone()), self.viewport);
+        ui_camera.set_view_matrix(Vui::view_matrix(&*app.ui_space(), ui_camera.fov_y()));
+        let mut world_camera = Camera::new(options, self.viewport);
+        if let Some(character_ref) = app.character() {
+            world_camera.set_view_matrix(character_ref.borrow().view());
+        }
+        app.update_cursor(&ui_camera, &world_camera);
+    }
+
+    /// Calls [`ReplayPlayer::feed`], then steps `app` once.
+    pub fn play_step(&mut self, app: &mut AllIsCubesAppState) {
+        self.feed(app);
+        let _ = app.frame_clock.request_frame(FrameClock::STEP_LENGTH);
+        app.maybe_step_universe();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::{Key, Tick};
+
+    #[test]
+    fn events_are_timestamped_by_step() {
+        let mut input = InputProcessor::new();
+        input.key_down(Key::Character('w'));
+        input.start_recording();
+        input.key_up(Key::Character('w'));
+        input.step(Tick::arbitrary());
+        input.step(Tick::arbitrary());
+        input.key_focus(false);
+        input.step(Tick::arbitrary());
+        let recording = input.stop_recording().unwrap();
+
+        assert_eq!(recording.steps(), 3);
+        assert_eq!(
+            recording
+                .events()
+                .iter()
+                .map(|e| (e.step, e.event))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, InputEvent::Up(Input::Key(Key::Character('w')))),
+                (2, InputEvent::Focus(false)),
+            ]
+        );
+    }
+}
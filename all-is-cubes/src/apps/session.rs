@@ -0,0 +1,474 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+use cgmath::{Deg, Matrix3, Vector2, Vector3};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::apps::{FrameClock, InputEvent, InputProcessor, Tick};
+use crate::camera::{Camera, GraphicsOptions, Viewport};
+use crate::character::{cursor_raycast, Character, Cursor};
+#[cfg(feature = "content")]
+use crate::content::UniverseTemplate;
+#[cfg(feature = "content")]
+use crate::linking::GenError;
+use crate::listen::{ListenableCell, ListenableSource, Listener, Notifier};
+use crate::math::Rgba;
+use crate::raycast::Ray;
+use crate::raytracer::{ColorBuf, SpaceRaytracer};
+use crate::space::SpaceChange;
+use crate::tools::ToolError;
+use crate::transactions::Transaction as _;
+use crate::universe::{URef, Universe, UniverseStepInfo};
+
+/// Runs a [`Universe`] without any platform, window, or GPU: the canonical way to embed
+/// the game in a server, test, or other headless program.
+///
+/// The session steps the universe on a fixed tick as the caller reports the passage of
+/// time with [`Session::advance`], applies input and tool uses to the universe's
+/// default character, and reports what happened to listeners registered with
+/// [`Session::listen`] — including, every [`Session::snapshot_interval`] steps, a
+/// raytraced image of the character's view.
+///
+/// Since there is no mouse pointer, tools are aimed at whatever is in the center of the
+/// character's view.
+///
+/// ```
+/// use all_is_cubes::apps::{Session, SessionEvent};
+/// use all_is_cubes::listen::Sink;
+/// use all_is_cubes::universe::Universe;
+/// use std::time::Duration;
+///
+/// let mut session = Session::new(Universe::new()).step_length(Duration::from_millis(100));
+/// let mut sink = Sink::new();
+/// session.listen(sink.listener());
+///
+/// assert_eq!(session.advance(Duration::from_millis(250)), 2);
+/// assert_eq!(session.step_count(), 2);
+/// assert!(sink.all(|event| matches!(event, SessionEvent::Stepped { .. })));
+/// ```
+#[derive(Debug)]
+pub struct Session {
+    /// Handles input for the character. Input may also be delivered through
+    /// [`Session::input`]; either way, it takes effect at the next step.
+    pub input_processor: InputProcessor,
+
+    universe: Universe,
+    character: Option<URef<Character>>,
+    paused: ListenableCell<bool>,
+
+    step_length: Duration,
+    /// Time reported to [`Session::advance`] and not yet stepped.
+    accumulated_time: Duration,
+    step_count: u64,
+    game_time: Duration,
+
+    snapshot_interval: u64,
+    viewport: Viewport,
+    graphics_options: GraphicsOptions,
+
+    notifier: Notifier<SessionEvent>,
+}
+
+impl Session {
+    /// Constructs a session for the given [`Universe`], whose default character (if
+    /// any) will receive input and take snapshots.
+    pub fn new(universe: Universe) -> Self {
+        Self {
+            input_processor: InputProcessor::new(),
+            character: universe.get_default_character(),
+            universe,
+            paused: ListenableCell::new(false),
+            step_length: FrameClock::STEP_LENGTH,
+            accumulated_time: Duration::ZERO,
+            step_count: 0,
+            game_time: Duration::ZERO,
+            snapshot_interval: 0,
+            viewport: Viewport {
+                nominal_size: Vector2::new(64., 48.),
+                framebuffer_size: Vector2::new(64, 48),
+            },
+            graphics_options: GraphicsOptions::default(),
+            notifier: Notifier::new(),
+        }
+    }
+
+    /// Constructs a session with a new [`Universe`] built from `template`.
+    #[cfg(feature = "content")]
+    pub fn from_template(template: UniverseTemplate) -> Result<Self, GenError> {
+        Ok(Self::new(template.build()?))
+    }
+
+    /// Sets the amount of game time each step covers. The default is 1/60 second, the
+    /// same as interactive play.
+    ///
+    /// Panics if `length` is zero.
+    #[must_use]
+    pub fn step_length(mut self, length: Duration) -> Self {
+        assert!(length > Duration::ZERO, "step length must not be zero");
+        self.step_length = length;
+        self
+    }
+
+    /// Sets how many steps pass between [`SessionEvent::Snapshot`]s. Zero, the default,
+    /// disables snapshots.
+    #[must_use]
+    pub fn snapshot_interval(mut self, steps: u64) -> Self {
+        self.snapshot_interval = steps;
+        self
+    }
+
+    /// Sets the size of snapshot images. The default is 64×48.
+    #[must_use]
+    pub fn viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    /// Sets the options used to render snapshots.
+    #[must_use]
+    pub fn graphics_options(mut self, options: GraphicsOptions) -> Self {
+        self.graphics_options = options;
+        self
+    }
+
+    pub fn universe(&self) -> &Universe {
+        &self.universe
+    }
+
+    /// Returns a mutable reference to the [`Universe`], such as for applying
+    /// transactions from elsewhere.
+    pub fn universe_mut(&mut self) -> &mut Universe {
+        &mut self.universe
+    }
+
+    /// Returns the character which input is applied to, if the universe has one.
+    pub fn character(&self) -> Option<&URef<Character>> {
+        self.character.as_ref()
+    }
+
+    /// Returns the number of steps taken so far.
+    pub fn step_count(&self) -> u64 {
+        self.step_count
+    }
+
+    /// Returns the total game time stepped while not paused.
+    pub fn game_time(&self) -> Duration {
+        self.game_time
+    }
+
+    /// Returns whether steps are currently paused, either by [`Session::set_paused`] or
+    /// by the player's input.
+    pub fn paused(&self) -> ListenableSource<bool> {
+        self.paused.as_source()
+    }
+
+    /// Pauses or unpauses the game. Paused steps still happen, but with a
+    /// [paused](Tick::paused) [`Tick`].
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.set(paused);
+    }
+
+    /// Registers a listener for [`SessionEvent`]s.
+    pub fn listen(&self, listener: impl Listener<SessionEvent> + 'static) {
+        self.notifier.listen(listener);
+    }
+
+    /// Registers a listener for changes to the space the character is currently in.
+    /// Returns [`false`] if there is no character.
+    pub fn listen_space(&self, listener: impl Listener<SpaceChange> + 'static) -> bool {
+        match &self.character {
+            Some(character_ref) => {
+                character_ref.borrow().space.borrow().listen(listener);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Delivers an input event to [`Session::input_processor`]. Events which press tool
+    /// buttons cause [`Session::use_tool`] at the next step.
+    pub fn input(&mut self, event: InputEvent) {
+        event.apply(&mut self.input_processor);
+    }
+
+    /// Reports that `elapsed` real time has passed, and takes as many steps as fit in
+    /// the time accumulated so far. Returns the number of steps taken.
+    ///
+    /// Unlike [`FrameClock`], this does not drop time if the caller falls behind; every
+    /// step is eventually taken, so the game runs at a consistent rate on average.
+    pub fn advance(&mut self, elapsed: Duration) -> usize {
+        self.accumulated_time += elapsed;
+        let mut steps = 0;
+        while self.accumulated_time >= self.step_length {
+            self.accumulated_time -= self.step_length;
+            self.step();
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Takes one step immediately, regardless of [`Session::advance`].
+    pub fn step(&mut self) -> UniverseStepInfo {
+        let mut tick = Tick::from_duration(self.step_length);
+        if *self.paused.get() {
+            tick = tick.pause();
+        }
+
+        for (button, pressed) in self.input_processor.take_tool_buttons() {
+            if pressed {
+                // Errors are reported to listeners by use_tool.
+                let _ = self.use_tool(button);
+            }
+        }
+
+        if let Some(character_ref) = &self.character {
+            self.input_processor
+                .apply_input(&mut character_ref.borrow_mut(), &self.paused, tick);
+            for _ in 0..self.input_processor.take_tosses() {
+                let slot = character_ref.borrow().selected_slots()[1];
+                match Character::toss(character_ref, slot) {
+                    Ok(transaction) => {
+                        if let Err(e) = transaction.execute(&mut self.universe) {
+                            log::debug!("failed to toss item: {}", e);
+                        }
+                    }
+                    Err(e) => log::debug!("failed to toss item: {}", e),
+                }
+            }
+        }
+        self.input_processor.step(tick);
+
+        let info = self.universe.step(tick);
+        self.step_count += 1;
+        if !tick.paused() {
+            self.game_time += tick.delta_t;
+        }
+
+        self.notifier.notify(SessionEvent::Stepped {
+            step: self.step_count,
+            info: info.clone(),
+        });
+        if self.snapshot_interval > 0 && self.step_count % self.snapshot_interval == 0 {
+            if let Some(snapshot) = self.snapshot() {
+                self.notifier.notify(SessionEvent::Snapshot(snapshot));
+            }
+        }
+
+        info
+    }
+
+    /// Returns what the character is looking at, which is what tools will be used on.
+    pub fn aim(&self) -> Option<Cursor> {
+        let character = self.character.as_ref()?.borrow();
+        let look_direction = Matrix3::from_angle_y(-Deg(character.body.yaw))
+            * Matrix3::from_angle_x(-Deg(character.body.pitch))
+            * Vector3::new(0.0, 0.0, -1.0);
+        cursor_raycast(
+            Ray::new(character.body.position, look_direction),
+            &character.space,
+        )
+    }
+
+    /// Uses the character's tool for `button` on what it is looking at, and reports the
+    /// result as a [`SessionEvent::ToolUsed`].
+    ///
+    /// Tools which must be held down, such as for breaking hard blocks, are not
+    /// supported and fail with [`ToolError::RequiresHolding`].
+    pub fn use_tool(&mut self, button: usize) -> Result<(), ToolError> {
+        let result = self.use_tool_impl(button);
+        self.notifier.notify(SessionEvent::ToolUsed {
+            button,
+            result: result.clone().map_err(|e| e.to_string()),
+        });
+        result
+    }
+
+    fn use_tool_impl(&mut self, button: usize) -> Result<(), ToolError> {
+        let character_ref = self.character.clone().ok_or(ToolError::NothingSelected)?;
+        let cursor = self.aim().ok_or(ToolError::NothingSelected)?;
+        Character::click(character_ref, &cursor, button)?
+            .execute(&mut self.universe)
+            .map_err(|e| ToolError::Internal(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Raytraces the character's view now, if there is a character.
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        let character = self.character.as_ref()?.borrow();
+        let mut camera = Camera::new(self.graphics_options.clone(), self.viewport);
+        camera.set_view_matrix(character.view());
+        let (image, _info) = SpaceRaytracer::<ColorBuf>::new(
+            &*character.space.borrow(),
+            self.graphics_options.clone(),
+        )
+        .trace_scene_to_image(&camera);
+        Some(Snapshot {
+            step: self.step_count,
+            size: self.viewport.framebuffer_size,
+            image: image.into(),
+        })
+    }
+}
+
+/// Something that happened in a [`Session`], delivered to listeners registered with
+/// [`Session::listen`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum SessionEvent {
+    /// The universe was stepped.
+    Stepped {
+        /// The value of [`Session::step_count`] after the step.
+        step: u64,
+        info: UniverseStepInfo,
+    },
+    /// A tool was used, by [`Session::use_tool`] or by input.
+    ToolUsed {
+        button: usize,
+        /// The tool's success, or the message of the [`ToolError`] it failed with.
+        /// (The error itself is not included since it may not be [`Send`].)
+        result: Result<(), String>,
+    },
+    /// A periodic snapshot was taken, as configured by [`Session::snapshot_interval`].
+    Snapshot(Snapshot),
+}
+
+/// A raytraced image of a [`Session`]'s character's view.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Snapshot {
+    /// The value of [`Session::step_count`] when the image was taken.
+    pub step: u64,
+    /// Width and height of the image in pixels.
+    pub size: Vector2<u32>,
+    /// Pixels in row-major order, starting at the top left.
+    pub image: Arc<[Rgba]>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{Block, AIR};
+    use crate::character::Spawn;
+    use crate::content::make_some_blocks;
+    use crate::listen::{NullListener, Sink};
+    use crate::math::GridPoint;
+    use crate::space::Space;
+    use crate::tools::Tool;
+    use crate::universe::UniverseIndex as _;
+    use cgmath::Point3;
+    use ordered_float::NotNan;
+
+    /// A universe whose character is looking at the single block in its space, with
+    /// `tool` selected for button 1.
+    fn universe_with_block(block: &Block, tool: Tool) -> Universe {
+        let mut universe = Universe::new();
+        let mut space = Space::empty_positive(3, 3, 3);
+        space.set([1, 1, 1], block).unwrap();
+        let space_ref = universe.insert("space".into(), space).unwrap();
+        let spawn = Spawn {
+            position: Point3::new(1.5, 1.5, 5.5).map(|s| NotNan::new(s).unwrap()),
+            flying: true,
+            inventory: vec![tool.clone(), tool],
+        };
+        universe
+            .insert("character".into(), Character::spawn(&spawn, space_ref))
+            .unwrap();
+        universe
+    }
+
+    #[test]
+    fn advance_steps_on_fixed_tick() {
+        let [block] = make_some_blocks();
+        let mut session = Session::new(universe_with_block(&block, Tool::None))
+            .step_length(Duration::from_millis(10));
+        let sink = Sink::new();
+        session.listen(sink.listener());
+
+        assert_eq!(session.advance(Duration::from_millis(25)), 2);
+        assert_eq!(session.advance(Duration::from_millis(4)), 0);
+        assert_eq!(session.advance(Duration::from_millis(1)), 1);
+        assert_eq!(session.step_count(), 3);
+        assert_eq!(session.game_time(), Duration::from_millis(30));
+        let mut steps: Vec<u64> = sink
+            .filter_map(|event| match event {
+                SessionEvent::Stepped { step, .. } => Some(step),
+                _ => None,
+            })
+            .collect();
+        steps.sort_unstable();
+        assert_eq!(steps, vec![1, 2, 3]);
+
+        session.set_paused(true);
+        session.step();
+        assert_eq!(session.step_count(), 4);
+        assert_eq!(session.game_time(), Duration::from_millis(30));
+    }
+
+    #[test]
+    fn periodic_snapshots() {
+        let [block] = make_some_blocks();
+        let mut session = Session::new(universe_with_block(&block, Tool::None))
+            .snapshot_interval(2)
+            .viewport(Viewport {
+                nominal_size: Vector2::new(8., 6.),
+                framebuffer_size: Vector2::new(8, 6),
+            });
+        let sink = Sink::new();
+        session.listen(sink.listener());
+
+        for _ in 0..5 {
+            session.step();
+        }
+        let mut snapshots: Vec<Snapshot> = sink
+            .filter_map(|event| match event {
+                SessionEvent::Snapshot(snapshot) => Some(snapshot),
+                _ => None,
+            })
+            .collect();
+        snapshots.sort_by_key(|s| s.step);
+        assert_eq!(
+            snapshots.iter().map(|s| s.step).collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        assert_eq!(snapshots[0].size, Vector2::new(8, 6));
+        assert_eq!(snapshots[0].image.len(), 8 * 6);
+    }
+
+    #[test]
+    fn use_tool_aims_at_center_of_view() {
+        let [block, placed] = make_some_blocks();
+        let mut session = Session::new(universe_with_block(
+            &block,
+            Tool::PlaceBlock(placed.clone()),
+        ));
+        let tool_sink = Sink::new();
+        session.listen(tool_sink.listener());
+        let space_sink = Sink::new();
+        assert!(session.listen_space(space_sink.listener()));
+
+        assert_eq!(
+            session.aim().map(|cursor| cursor.place.cube),
+            Some(GridPoint::new(1, 1, 1))
+        );
+        session.use_tool(1).unwrap();
+
+        let space_ref = session.character().unwrap().borrow().space.clone();
+        let space = space_ref.borrow();
+        assert_eq!(space[[1, 1, 2]], placed);
+        assert_eq!(space[[1, 1, 0]], AIR);
+        assert!(space_sink.take_equal(SpaceChange::Block(GridPoint::new(1, 1, 2))));
+        assert!(tool_sink.take_equal(SessionEvent::ToolUsed {
+            button: 1,
+            result: Ok(())
+        }));
+    }
+
+    #[test]
+    fn use_tool_without_character() {
+        let mut session = Session::new(Universe::new());
+        assert_eq!(session.use_tool(0), Err(ToolError::NothingSelected));
+        assert_eq!(session.snapshot(), None);
+        assert!(!session.listen_space(NullListener));
+    }
+}
@@ -13,17 +13,20 @@ pub struct FrameClock {
     /// TODO: This might go away in favor of actual dirty-notifications.
     render_dirty: bool,
     accumulated_step_time: Duration,
+    /// Smoothed recent render times passed to [`FrameClock::record_frame_timing`].
+    render_time: Option<Duration>,
+    /// Shortest frame interval passed to [`FrameClock::record_frame_timing`], taken to be
+    /// the display's refresh interval since frames cannot be presented more often.
+    vsync_interval: Option<Duration>,
 }
 
 impl FrameClock {
     const STEP_LENGTH_MICROS: u64 = 1_000_000 / 60;
-    const STEP_LENGTH: Duration = Duration::from_micros(Self::STEP_LENGTH_MICROS);
+    pub(crate) const STEP_LENGTH: Duration = Duration::from_micros(Self::STEP_LENGTH_MICROS);
     /// Number of steps per frame to permit.
     /// This sets how low the frame rate can go below STEP_LENGTH before game time
     /// slows down.
     pub(crate) const CATCH_UP_STEPS: u8 = 2;
-    const ACCUMULATOR_CAP: Duration =
-        Duration::from_micros(Self::STEP_LENGTH_MICROS * Self::CATCH_UP_STEPS as u64);
 
     /// Constructs a new [`FrameClock`].
     ///
@@ -33,6 +36,8 @@ impl FrameClock {
             last_absolute_time: None,
             render_dirty: true,
             accumulated_step_time: Duration::ZERO,
+            render_time: None,
+            vsync_interval: None,
         }
     }
 
@@ -82,6 +87,52 @@ impl FrameClock {
         self.render_dirty = false;
     }
 
+    /// Informs the [`FrameClock`] of the timing of a frame just rendered, such as
+    /// [`RenderInfo::frame_time`](crate::lum::RenderInfo::frame_time) and
+    /// [`RenderInfo::frame_interval`](crate::lum::RenderInfo::frame_interval).
+    ///
+    /// `render_time` should be the time spent doing work, not including any time spent
+    /// waiting for vsync, so that a frame rate limited by the display is not mistaken for
+    /// one limited by rendering. `frame_interval` is the time since the previous frame
+    /// started, if known; the shortest one seen is taken as the display's refresh
+    /// interval. Both are used to decide [`FrameClock::catch_up_steps`].
+    pub fn record_frame_timing(&mut self, render_time: Duration, frame_interval: Option<Duration>) {
+        self.render_time = Some(match self.render_time {
+            // Exponential moving average, so that one slow frame doesn't count as
+            // falling behind.
+            Some(previous) => (previous * 3 + render_time) / 4,
+            None => render_time,
+        });
+        if let Some(interval) = frame_interval {
+            self.vsync_interval = Some(match self.vsync_interval {
+                Some(previous) => previous.min(interval),
+                None => interval,
+            });
+        }
+        self.cap_step_time();
+    }
+
+    /// Returns whether rendering is taking longer than the time available for a frame,
+    /// as reported by [`FrameClock::record_frame_timing`]: the display's refresh
+    /// interval if it is known, or else the length of a step. When it is, performing
+    /// extra steps to catch up would only cause the next vsync to be missed, so
+    /// [`FrameClock::catch_up_steps`] permits only one step per frame and the game runs
+    /// slower than real time instead.
+    pub fn is_render_behind(&self) -> bool {
+        let budget = self.vsync_interval.unwrap_or(Self::STEP_LENGTH);
+        matches!(self.render_time, Some(t) if t > budget)
+    }
+
+    /// Returns the maximum number of steps which should be performed before the next
+    /// frame is drawn.
+    pub fn catch_up_steps(&self) -> u8 {
+        if self.is_render_behind() {
+            1
+        } else {
+            Self::CATCH_UP_STEPS
+        }
+    }
+
     /// Indicates whether [`Universe::step`](crate::universe::Universe::step) should be performed,
     /// given the amount of time that this [`FrameClock`] has been informed has passed.
     ///
@@ -109,8 +160,10 @@ impl FrameClock {
     }
 
     fn cap_step_time(&mut self) {
-        if self.accumulated_step_time > Self::ACCUMULATOR_CAP {
-            self.accumulated_step_time = Self::ACCUMULATOR_CAP;
+        // Time beyond what we will catch up on is dropped rather than accumulated.
+        let cap = Self::STEP_LENGTH * u32::from(self.catch_up_steps());
+        if self.accumulated_step_time > cap {
+            self.accumulated_step_time = cap;
         }
     }
 }
@@ -148,6 +201,13 @@ impl Tick {
         }
     }
 
+    pub(crate) const fn from_duration(delta_t: Duration) -> Self {
+        Self {
+            delta_t,
+            paused: false,
+        }
+    }
+
     /// Set the paused flag. See [`Tick::paused`] for more information.
     #[must_use]
     pub fn pause(self) -> Self {
@@ -165,3 +225,55 @@ impl Tick {
         self.paused
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_rendering_skips_catch_up() {
+        let mut clock = FrameClock::new();
+        let _ = clock.request_frame(FrameClock::STEP_LENGTH * 10);
+        assert_eq!(clock.catch_up_steps(), FrameClock::CATCH_UP_STEPS);
+        for _ in 0..FrameClock::CATCH_UP_STEPS {
+            assert!(clock.should_step());
+            clock.did_step();
+        }
+        assert!(!clock.should_step());
+
+        clock.record_frame_timing(FrameClock::STEP_LENGTH * 3, None);
+        assert!(clock.is_render_behind());
+        assert_eq!(clock.catch_up_steps(), 1);
+        let _ = clock.request_frame(FrameClock::STEP_LENGTH * 10);
+        assert!(clock.should_step());
+        clock.did_step();
+        assert!(!clock.should_step());
+    }
+
+    #[test]
+    fn one_slow_frame_is_not_behind() {
+        let mut clock = FrameClock::new();
+        for _ in 0..10 {
+            clock.record_frame_timing(FrameClock::STEP_LENGTH / 4, None);
+        }
+        clock.record_frame_timing(FrameClock::STEP_LENGTH * 2, None);
+        assert!(!clock.is_render_behind());
+    }
+
+    #[test]
+    fn render_behind_is_relative_to_vsync() {
+        // A 30 Hz display leaves two steps' worth of time for each frame.
+        let vsync = FrameClock::STEP_LENGTH * 2;
+        let mut clock = FrameClock::new();
+        clock.record_frame_timing(FrameClock::STEP_LENGTH * 3 / 2, Some(vsync));
+        assert!(!clock.is_render_behind());
+        assert_eq!(clock.catch_up_steps(), FrameClock::CATCH_UP_STEPS);
+
+        // A slow frame's longer interval does not raise the budget.
+        for _ in 0..10 {
+            clock.record_frame_timing(FrameClock::STEP_LENGTH * 3, Some(vsync * 2));
+        }
+        assert!(clock.is_render_behind());
+        assert_eq!(clock.catch_up_steps(), 1);
+    }
+}
@@ -4,12 +4,14 @@
 //! Dynamic add-ons to game objects; we might also have called them “components”.
 
 use ordered_float::NotNan;
+use std::cell::{RefCell, RefMut};
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 
 use crate::apps::Tick;
 use crate::character::{Character, CharacterTransaction};
+use crate::math::SeededRng;
 use crate::physics::BodyTransaction;
 use crate::transactions::{
     PreconditionFailed, Transaction, TransactionConflict, Transactional, UniverseTransaction,
@@ -45,6 +47,7 @@ pub struct BehaviorContext<'a, H: Transactional> {
     pub host: &'a H,
     host_transaction_binder: &'a dyn Fn(H::Transaction) -> UniverseTransaction,
     self_transaction_binder: &'a dyn Fn(Arc<dyn Behavior<H>>) -> UniverseTransaction,
+    rng: RefCell<SeededRng>,
 }
 
 impl<'a, H: Transactional> BehaviorContext<'a, H> {
@@ -54,6 +57,19 @@ impl<'a, H: Transactional> BehaviorContext<'a, H> {
     pub fn replace_self<B: Behavior<H> + 'static>(&self, new_behavior: B) -> UniverseTransaction {
         (self.self_transaction_binder)(Arc::new(new_behavior))
     }
+
+    /// Returns a random number generator for this behavior's use during this step.
+    ///
+    /// It is derived from the host's generator, such as [`Space::rng_mut`], so that
+    /// the behavior's random choices are reproducible, and each behavior has its own
+    /// so that they do not depend on how many random numbers other behaviors used.
+    ///
+    /// Panics if the generator is already borrowed.
+    ///
+    /// [`Space::rng_mut`]: crate::space::Space::rng_mut
+    pub fn rng(&self) -> RefMut<'_, SeededRng> {
+        self.rng.borrow_mut()
+    }
 }
 
 /// Collects [`Behavior`]s and invokes them.
@@ -84,6 +100,7 @@ impl<H: Transactional> BehaviorSet<H> {
         host: &H,
         host_transaction_binder: &dyn Fn(H::Transaction) -> UniverseTransaction,
         set_transaction_binder: impl Fn(BehaviorSetTransaction<H>) -> H::Transaction,
+        rng: &mut SeededRng,
         tick: Tick,
     ) -> UniverseTransaction {
         let mut transactions = Vec::new();
@@ -96,6 +113,9 @@ impl<H: Transactional> BehaviorSet<H> {
                         BehaviorSetTransaction::replace(index, new_behavior),
                     ))
                 },
+                // Forked for every behavior, whether or not it is used, so that the
+                // numbers each one gets do not depend on the others.
+                rng: RefCell::new(rng.fork()),
             };
             if behavior.alive(context) {
                 transactions.push(behavior.step(context, tick));
@@ -119,6 +139,28 @@ impl<H> std::fmt::Debug for BehaviorSet<H> {
     }
 }
 
+impl<H> Clone for BehaviorSet<H> {
+    // Manual implementation to avoid bounds on `H`.
+    fn clone(&self) -> Self {
+        Self {
+            items: self.items.clone(),
+        }
+    }
+}
+
+impl<H> PartialEq for BehaviorSet<H> {
+    // Manual implementation to avoid bounds on `H` and to implement the partiality (comparing pointers instead of values).
+    #[allow(clippy::vtable_address_comparisons)] // The hazards should be okay for this use case
+    fn eq(&self, other: &Self) -> bool {
+        self.items.len() == other.items.len()
+            && self
+                .items
+                .iter()
+                .zip(other.items.iter())
+                .all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
 impl<H> Transactional for BehaviorSet<H> {
     type Transaction = BehaviorSetTransaction<H>;
 }
@@ -234,6 +276,7 @@ impl Behavior<Character> for AutoRotate {
     fn step(&self, c: &BehaviorContext<'_, Character>, tick: Tick) -> UniverseTransaction {
         c.bind_host(CharacterTransaction::body(BodyTransaction {
             delta_yaw: self.rate.into_inner() * tick.delta_t.as_secs_f64(),
+            ..Default::default()
         }))
     }
 
@@ -293,6 +336,7 @@ mod tests {
                 .merge(
                     context.bind_host(CharacterTransaction::body(BodyTransaction {
                         delta_yaw: FreeCoordinate::from(self.foo),
+                        ..Default::default()
                     })),
                 )
                 .unwrap()
@@ -323,4 +367,48 @@ mod tests {
         // read its effects.
         assert_eq!(character.borrow().body.yaw, 3.0);
     }
+
+    /// Turns by a random amount every step.
+    #[derive(Debug)]
+    struct RandomTurn;
+    impl Behavior<Character> for RandomTurn {
+        fn step(
+            &self,
+            context: &BehaviorContext<'_, Character>,
+            _tick: Tick,
+        ) -> UniverseTransaction {
+            use rand::Rng as _;
+            let delta_yaw = FreeCoordinate::from(context.rng().gen_range(1..1000));
+            context.bind_host(CharacterTransaction::body(BodyTransaction {
+                delta_yaw,
+                ..Default::default()
+            }))
+        }
+
+        fn alive(&self, _context: &BehaviorContext<'_, Character>) -> bool {
+            true
+        }
+
+        fn ephemeral(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn rng_is_reproducible() {
+        let run = |seed: u64| {
+            let mut u = Universe::new();
+            u.set_seed(seed);
+            let space = u.insert_anonymous(Space::empty_positive(1, 1, 1));
+            let mut character = Character::spawn_default(space);
+            character.add_behavior(RandomTurn);
+            let character = u.insert_anonymous(character);
+            for _ in 0..3 {
+                u.step(Tick::arbitrary());
+            }
+            character.borrow().body.yaw
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+    }
 }
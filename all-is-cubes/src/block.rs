@@ -11,17 +11,26 @@ use std::sync::Arc;
 
 use cgmath::{EuclideanSpace as _, Point3, Vector4, Zero as _};
 
+use crate::content::palette;
 use crate::listen::{Gate, Listener, ListenerHelper, Notifier};
-use crate::math::{FreeCoordinate, GridCoordinate, GridPoint, GridRotation, Rgb, Rgba};
+use crate::math::{
+    FaceMap, FreeCoordinate, GridCoordinate, GridPoint, GridRotation, NotNan, Rgb, Rgba,
+};
 use crate::raycast::{Ray, Raycaster};
 use crate::space::{Grid, GridArray, SetCubeError, Space, SpaceChange};
-use crate::universe::{RefError, URef};
+use crate::universe::{Name, RefError, URef};
 use crate::util::{ConciseDebug, CustomFormat};
 
 pub mod builder;
 #[doc(inline)]
 pub use builder::BlockBuilder;
 
+mod content_hash;
+pub use content_hash::*;
+
+mod modifier;
+pub use modifier::*;
+
 #[cfg(test)]
 mod tests;
 
@@ -73,6 +82,24 @@ pub enum Block {
     // TODO: Hmm, it'd be nice if this common case wasn't another allocation — should we
     // have an outer struct with a rotation field instead??
     Rotated(GridRotation, Box<Block>),
+
+    /// Identical to `base`, except that the visible voxels of `overlay` are painted over
+    /// its surface. The overlay does not add any voxels where `base` has none, and does
+    /// not affect the attributes or collision of `base`.
+    ///
+    /// This is used for temporary decorations such as the
+    /// [crack stages](crate::mining::CrackStage) of a block being broken.
+    Overlay {
+        base: Box<Block>,
+        overlay: Box<Block>,
+    },
+
+    /// Identical to `base`, except that each of the [`Modifier`]s is applied to it in
+    /// order. See [`Block::with_modifier`] for a convenient way to construct this.
+    Modified {
+        base: Box<Block>,
+        modifiers: Vec<Modifier>,
+    },
 }
 
 impl Block {
@@ -123,6 +150,39 @@ impl Block {
         }
     }
 
+    /// Adds a [`Modifier`] to be applied after any this block already has.
+    ///
+    /// ```
+    /// use all_is_cubes::block::{Block, Modifier};
+    /// use all_is_cubes::math::{Face, Rgb, Rgba};
+    ///
+    /// let block = Block::from(Rgba::WHITE)
+    ///     .with_modifier(Modifier::Mirror(Face::PX))
+    ///     .with_modifier(Modifier::Recolor(Rgb::ZERO));
+    /// assert_eq!(
+    ///     block,
+    ///     Block::Modified {
+    ///         base: Box::new(Block::from(Rgba::WHITE)),
+    ///         modifiers: vec![Modifier::Mirror(Face::PX), Modifier::Recolor(Rgb::ZERO)],
+    ///     }
+    /// );
+    /// ```
+    pub fn with_modifier(self, modifier: Modifier) -> Self {
+        match self {
+            Block::Modified {
+                base,
+                mut modifiers,
+            } => {
+                modifiers.push(modifier);
+                Block::Modified { base, modifiers }
+            }
+            _ => Block::Modified {
+                base: Box::new(self),
+                modifiers: vec![modifier],
+            },
+        }
+    }
+
     /// Standardizes any characteristics of this block which may be presumed to be
     /// specific to its usage in its current location, so that it can be used elsewhere
     /// or compared with others. Currently, this means removing rotation, but in the
@@ -145,6 +205,7 @@ impl Block {
     pub fn unspecialize(self) -> Self {
         match self {
             Block::Rotated(_rotation, boxed_block) => *boxed_block,
+            Block::Overlay { base, .. } => base.unspecialize(),
             other => other,
         }
     }
@@ -152,17 +213,62 @@ impl Block {
     /// Converts this `Block` into a “flattened” and snapshotted form which contains all
     /// information needed for rendering and physics, and does not require [`URef`] access
     /// to other objects.
+    ///
+    /// The work done is limited by [`EvalBudget::default()`]; use
+    /// [`Block::evaluate_with_budget`] to choose different limits.
     pub fn evaluate(&self) -> Result<EvaluatedBlock, EvalBlockError> {
-        self.evaluate_impl(0)
+        self.evaluate_with_budget(EvalBudget::default())
+    }
+
+    /// Like [`Block::evaluate`], but with the given limits on the work done.
+    ///
+    /// ```
+    /// use all_is_cubes::block::{EvalBlockError, EvalBudget};
+    /// use all_is_cubes::content::make_some_voxel_blocks;
+    /// use all_is_cubes::universe::Universe;
+    ///
+    /// let mut universe = Universe::new();
+    /// let [block] = make_some_voxel_blocks(&mut universe);
+    ///
+    /// let mut budget = EvalBudget::default();
+    /// budget.voxels = 10;
+    /// assert_eq!(
+    ///     block.evaluate_with_budget(budget),
+    ///     Err(EvalBlockError::VoxelBudgetExceeded { budget: 10 }),
+    /// );
+    /// ```
+    pub fn evaluate_with_budget(
+        &self,
+        budget: EvalBudget,
+    ) -> Result<EvaluatedBlock, EvalBlockError> {
+        self.evaluate_impl(
+            &mut EvalState {
+                budget,
+                voxels: 0,
+                definitions: Vec::new(),
+            },
+            0,
+        )
     }
 
     #[inline]
-    fn evaluate_impl(&self, depth: u8) -> Result<EvaluatedBlock, EvalBlockError> {
+    fn evaluate_impl(
+        &self,
+        state: &mut EvalState,
+        depth: u8,
+    ) -> Result<EvaluatedBlock, EvalBlockError> {
         match self {
-            Block::Indirect(def_ref) => def_ref
-                .try_borrow()?
-                .block
-                .evaluate_impl(next_depth(depth)?),
+            Block::Indirect(def_ref) => {
+                if state.definitions.contains(def_ref) {
+                    return Err(EvalBlockError::Cycle(Name::clone(def_ref.name())));
+                }
+                let depth = state.next_depth(depth)?;
+                let block_def = def_ref.try_borrow()?;
+                state.definitions.push(def_ref.clone());
+                let result = block_def.block.evaluate_impl(state, depth);
+                state.definitions.pop();
+                result
+            }
 
             &Block::Atom(ref attributes, color) => Ok(EvaluatedBlock {
                 attributes: attributes.clone(),
@@ -190,6 +296,7 @@ impl Block {
                 let occupied_grid = full_resolution_grid
                     .intersection(block_space.grid())
                     .unwrap_or_else(|| Grid::new(offset, [1, 1, 1]) /* arbitrary value */);
+                state.spend_voxels(occupied_grid.volume())?;
 
                 // TODO: The color sum actually needs to be weighted by alpha. (Too bad we're not using premultiplied alpha.)
                 // TODO: Should not be counting interior voxels for the color, only visible surfaces.
@@ -237,21 +344,30 @@ impl Block {
 
             // TODO: this has no unit tests
             Block::Rotated(rotation, block) => {
-                let base = block.evaluate()?;
-                let resolution = base.resolution;
-                Ok(EvaluatedBlock {
-                    voxels: base.voxels.map(|voxels| {
-                        let matrix = rotation.to_positive_octant_matrix(resolution.into());
-                        let inverse_matrix = rotation
-                            .inverse()
-                            .to_positive_octant_matrix(resolution.into());
-                        GridArray::from_fn(
-                            voxels.grid().transform(inverse_matrix).unwrap(),
-                            |cube| voxels[matrix.transform_cube(cube)],
-                        )
-                    }),
-                    ..base
-                })
+                let depth = state.next_depth(depth)?;
+                let base = block.evaluate_impl(state, depth)?;
+                rotate_evaluated(base, *rotation, state)
+            }
+
+            Block::Overlay { base, overlay } => {
+                let depth = state.next_depth(depth)?;
+                let base = base.evaluate_impl(state, depth)?;
+                let overlay = overlay.evaluate_impl(state, depth)?;
+                if overlay.visible {
+                    state.spend_voxels(
+                        Grid::for_block(base.resolution.max(overlay.resolution)).volume(),
+                    )?;
+                }
+                Ok(overlay_evaluated(base, &overlay))
+            }
+
+            Block::Modified { base, modifiers } => {
+                let depth = state.next_depth(depth)?;
+                let mut evaluated = base.evaluate_impl(state, depth)?;
+                for modifier in modifiers {
+                    evaluated = modifier.apply(evaluated, state, depth)?;
+                }
+                Ok(evaluated)
             }
         }
         // TODO: need to track which things we need change notifications on
@@ -299,18 +415,21 @@ impl Block {
                 let relevant_cubes = Grid::for_block(*resolution).translate(offset.to_vec());
                 space_ref
                     .try_borrow_mut()?
-                    .listen(listener.filter(move |msg| {
+                    .listen_batched(listener.filter(move |msg| {
                         match msg {
                             SpaceChange::Block(cube) if relevant_cubes.contains_cube(cube) => {
                                 Some(BlockChange::new())
                             }
                             SpaceChange::Block(_) => None,
+                            SpaceChange::BlockRegion(region) => relevant_cubes
+                                .intersection(region)
+                                .map(|_| BlockChange::new()),
                             SpaceChange::EveryBlock => Some(BlockChange::new()),
 
                             // TODO: It would be nice if the space gave more precise updates such that we could conclude
                             // e.g. "this is a new/removed block in an unaffected area" without needing to store any data.
                             SpaceChange::BlockValue(_) => Some(BlockChange::new()),
-                            SpaceChange::Lighting(_) => None,
+                            SpaceChange::Lighting(_) | SpaceChange::LightingRegion(_) => None,
                             SpaceChange::Number(_) => None,
                         }
                     }));
@@ -318,6 +437,21 @@ impl Block {
             Block::Rotated(_, base) => {
                 base.listen(listener)?;
             }
+            Block::Overlay { base, overlay } => {
+                // Type-erase so that nested overlays don't produce ever-larger types.
+                let listener: Arc<dyn Listener<BlockChange>> = Arc::new(listener);
+                base.listen(listener.clone())?;
+                overlay.listen(listener)?;
+            }
+            Block::Modified { base, modifiers } => {
+                let listener: Arc<dyn Listener<BlockChange>> = Arc::new(listener);
+                base.listen(listener.clone())?;
+                for modifier in modifiers {
+                    if let Modifier::Overlay(overlay) = modifier {
+                        overlay.listen(listener.clone())?;
+                    }
+                }
+            }
         }
         Ok(())
     }
@@ -333,12 +467,131 @@ impl Block {
     }
 }
 
-/// Recursion limiter helper for evaluate.
-fn next_depth(depth: u8) -> Result<u8, EvalBlockError> {
-    if depth > 32 {
-        Err(EvalBlockError::StackOverflow)
-    } else {
-        Ok(depth + 1)
+/// Implementation of evaluating [`Block::Rotated`].
+fn rotate_evaluated(
+    base: EvaluatedBlock,
+    rotation: GridRotation,
+    state: &mut EvalState,
+) -> Result<EvaluatedBlock, EvalBlockError> {
+    if let Some(voxels) = &base.voxels {
+        state.spend_voxels(voxels.grid().volume())?;
+    }
+    let resolution = base.resolution;
+    Ok(EvaluatedBlock {
+        voxels: base.voxels.map(|voxels| {
+            let matrix = rotation.to_positive_octant_matrix(resolution.into());
+            let inverse_matrix = rotation
+                .inverse()
+                .to_positive_octant_matrix(resolution.into());
+            GridArray::from_fn(voxels.grid().transform(inverse_matrix).unwrap(), |cube| {
+                voxels[matrix.transform_cube(cube)]
+            })
+        }),
+        ..base
+    })
+}
+
+/// Implementation of evaluating [`Block::Overlay`].
+fn overlay_evaluated(base: EvaluatedBlock, overlay: &EvaluatedBlock) -> EvaluatedBlock {
+    if !overlay.visible {
+        return base;
+    }
+    let resolution = base.resolution.max(overlay.resolution);
+    let voxel_at = |block: &EvaluatedBlock, cube: GridPoint| -> Evoxel {
+        match &block.voxels {
+            None => Evoxel {
+                color: block.color,
+                selectable: block.attributes.selectable,
+                collision: block.attributes.collision,
+            },
+            Some(voxels) => {
+                // Scale to the block's own resolution (nearest neighbor).
+                let scaled = cube.map(|c| {
+                    c * GridCoordinate::from(block.resolution) / GridCoordinate::from(resolution)
+                });
+                voxels.get(scaled).copied().unwrap_or(Evoxel::AIR)
+            }
+        }
+    };
+    let voxels = GridArray::from_fn(Grid::for_block(resolution), |cube| {
+        let under = voxel_at(&base, cube);
+        let over = voxel_at(overlay, cube);
+        if under.color.fully_transparent() {
+            under
+        } else {
+            let a = over.color.alpha().into_inner().max(0.0).min(1.0);
+            Evoxel {
+                color: (over.color.to_rgb() * a + under.color.to_rgb() * (1.0 - a))
+                    .with_alpha(under.color.alpha()),
+                ..under
+            }
+        }
+    });
+    EvaluatedBlock {
+        voxels: Some(voxels),
+        resolution,
+        ..base
+    }
+}
+
+/// Limits on the work [`Block::evaluate_with_budget`] may do, so that block definitions
+/// which are unreasonably large or deeply nested produce an error instead of using up
+/// memory or stack.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct EvalBudget {
+    /// Maximum number of nested [`Block`]s (through [`Block::Indirect`],
+    /// [`Block::Rotated`], [`Block::Overlay`], and [`Block::Modified`]) to evaluate.
+    pub depth: u8,
+    /// Maximum total number of voxels to compute, counting every [`Block::Recur`] and
+    /// every voxel-transforming step separately.
+    pub voxels: usize,
+}
+
+impl EvalBudget {
+    /// The limits used by [`Block::evaluate`], which are generous enough for any
+    /// reasonable block: a few blocks of the maximum resolution.
+    pub const DEFAULT: Self = Self {
+        depth: 32,
+        voxels: 4 << 24,
+    };
+}
+
+impl Default for EvalBudget {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Bookkeeping for a single [`Block::evaluate_with_budget`] operation.
+struct EvalState {
+    budget: EvalBudget,
+    /// Number of voxels computed so far.
+    voxels: usize,
+    /// The block definitions currently being evaluated, outermost first, to detect
+    /// cycles.
+    definitions: Vec<URef<BlockDef>>,
+}
+
+impl EvalState {
+    /// Recursion limiter helper for evaluate.
+    fn next_depth(&self, depth: u8) -> Result<u8, EvalBlockError> {
+        if depth >= self.budget.depth {
+            Err(EvalBlockError::StackOverflow)
+        } else {
+            Ok(depth + 1)
+        }
+    }
+
+    fn spend_voxels(&mut self, count: usize) -> Result<(), EvalBlockError> {
+        self.voxels = self.voxels.saturating_add(count);
+        if self.voxels > self.budget.voxels {
+            Err(EvalBlockError::VoxelBudgetExceeded {
+                budget: self.budget.voxels,
+            })
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -404,11 +657,53 @@ pub struct BlockAttributes {
     /// The default value is [`BlockCollision::Hard`].
     pub collision: BlockCollision,
 
-    /// Light emitted by the block.
+    /// Light emitted by the block, for each face through which it leaves the block.
     ///
-    /// The default value is [`Rgb::ZERO`].
-    pub light_emission: Rgb,
-    // TODO: add 'behavior' functionality, if we don't come up with something else
+    /// Light emitted from a face only illuminates what that face looks out on, so a
+    /// block which should glow on only one side, like a display panel, can use zero
+    /// for the other faces. The [`Face::Within`](crate::math::Face::Within) value is the
+    /// light that a non-opaque block contributes to its own cube; it is ignored for
+    /// opaque blocks, which have no interior to light.
+    ///
+    /// The default value is [`Rgb::ZERO`] for all faces.
+    pub light_emission: FaceMap<Rgb>,
+
+    /// Time, in seconds, it takes to break this block by holding the
+    /// [`DeleteBlock`](crate::character::Character::continue_breaking) tool on it.
+    /// Zero means it is removed immediately by a single use of the tool.
+    ///
+    /// The default value is zero.
+    pub hardness: NotNan<f32>,
+
+    /// Mass per unit volume of the block's material, relative to that of water (which
+    /// is also the density of every [`Body`](crate::physics::Body)). This currently
+    /// matters only if the [`collision`](Self::collision) is [`BlockCollision::Fluid`]:
+    /// a body immersed in a fluid denser than 1 floats, and one in a less dense fluid
+    /// sinks.
+    ///
+    /// The default value is 1.
+    pub density: NotNan<f32>,
+
+    /// Short keywords describing what kind of thing the block is, such as `"stone"` or
+    /// `"mechanism"`, which may be used to find it; see [`Self::has_tag`],
+    /// [`Universe::find_blocks_with_tag`](crate::universe::Universe::find_blocks_with_tag),
+    /// and [`Space::find_cubes_with_tag`](crate::space::Space::find_cubes_with_tag).
+    /// Tags have no effect on the block's behavior.
+    ///
+    /// The default value is no tags.
+    pub tags: Cow<'static, [Cow<'static, str>]>,
+
+    /// How the block takes part in the signals which blocks in a [`Space`] send to each
+    /// other, such as to operate a door from a switch.
+    ///
+    /// The default value is [`SignalRole::None`].
+    pub signal: SignalRole,
+
+    /// What happens to the block when its cube is given a random tick.
+    ///
+    /// The default value is [`RandomTickAction::None`].
+    pub random_tick: RandomTickAction,
+    // TODO: add more 'behavior' functionality, if we don't come up with something else
 
     // Reminder: When adding new fields, add them to the Debug implementation.
 }
@@ -432,7 +727,28 @@ impl std::fmt::Debug for BlockAttributes {
                 s.field("collision", &self.collision);
             }
             if self.light_emission != Self::default().light_emission {
-                s.field("light_emission", &self.light_emission);
+                let e = &self.light_emission;
+                if e.iter().all(|(_, &value)| value == e.within) {
+                    // Uniform emission is the common case, so abbreviate it.
+                    s.field("light_emission", &e.within);
+                } else {
+                    s.field("light_emission", e);
+                }
+            }
+            if self.hardness != Self::default().hardness {
+                s.field("hardness", &self.hardness.into_inner());
+            }
+            if self.density != Self::default().density {
+                s.field("density", &self.density.into_inner());
+            }
+            if self.tags != Self::default().tags {
+                s.field("tags", &&*self.tags);
+            }
+            if self.signal != Self::default().signal {
+                s.field("signal", &self.signal);
+            }
+            if self.random_tick != Self::default().random_tick {
+                s.field("random_tick", &self.random_tick);
             }
             s.finish()
         }
@@ -449,9 +765,29 @@ impl BlockAttributes {
             display_name: Cow::Borrowed(""),
             selectable: true,
             collision: BlockCollision::Hard,
-            light_emission: Rgb::ZERO,
+            light_emission: NO_EMISSION,
+            hardness: notnan!(0.0),
+            density: notnan!(1.0),
+            tags: Cow::Borrowed(&[]),
+            signal: SignalRole::None,
+            random_tick: RandomTickAction::None,
         }
     }
+
+    /// Returns whether [`Self::tags`] contains `tag`.
+    ///
+    /// ```
+    /// use all_is_cubes::block::Block;
+    /// use all_is_cubes::math::Rgba;
+    ///
+    /// let block = Block::builder().color(Rgba::WHITE).tag("stone").build();
+    /// let attributes = block.evaluate().unwrap().attributes;
+    /// assert!(attributes.has_tag("stone"));
+    /// assert!(!attributes.has_tag("mechanism"));
+    /// ```
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
 }
 
 impl Default for BlockAttributes {
@@ -470,6 +806,21 @@ impl<'a> arbitrary::Arbitrary<'a> for BlockAttributes {
             selectable: u.arbitrary()?,
             collision: u.arbitrary()?,
             light_emission: u.arbitrary()?,
+            hardness: NotNan::new(u.arbitrary::<f32>()?.abs()).unwrap_or(notnan!(0.0)),
+            density: NotNan::new(u.arbitrary::<f32>()?.abs()).unwrap_or(notnan!(1.0)),
+            tags: Cow::Owned(
+                u.arbitrary::<Vec<String>>()?
+                    .into_iter()
+                    .map(Cow::Owned)
+                    .collect(),
+            ),
+            // Sinks are not generated since `Block` does not implement `Arbitrary`.
+            signal: match u.int_in_range(0..=2)? {
+                0 => SignalRole::None,
+                1 => SignalRole::Source(u.arbitrary()?),
+                _ => SignalRole::Wire,
+            },
+            random_tick: RandomTickAction::None,
         })
     }
 }
@@ -485,7 +836,84 @@ pub enum BlockCollision {
     /// The block is a perfectly solid obstacle.
     /// TODO: Define the effect on recursive blocks once we have voxel collision.
     Hard,
-    // Future values might include bouncy solid, water-like resistance, force fields, etc.
+    /// The block is a fluid, like water, which bodies may move through but which buoys
+    /// them up and slows them down according to its
+    /// [`density`](BlockAttributes::density).
+    Fluid,
+    // Future values might include bouncy solid, force fields, etc.
+}
+
+/// Strength of a signal; see [`SignalRole`]. Zero means no signal.
+pub type SignalLevel = u8;
+
+/// How a block takes part in the signals which blocks in a [`Space`] send to each other,
+/// as with switches, wires, and the doors and lamps they operate.
+///
+/// Signals travel from sources, through chains of adjacent wires, to adjacent sinks.
+/// The [`Space`] recomputes the levels of the affected cubes whenever a block is placed
+/// or removed; see [`Space::signal_level`].
+///
+/// ```
+/// use all_is_cubes::block::{Block, SignalRole, AIR};
+/// use all_is_cubes::math::Rgba;
+///
+/// // A switch which is on:
+/// let switch = Block::builder().color(Rgba::WHITE).signal(SignalRole::Source(15)).build();
+/// // A closed door, which opens (becomes passable air) when powered:
+/// let door = Block::builder()
+///     .color(Rgba::BLACK)
+///     .signal(SignalRole::Sink(Box::new(AIR)))
+///     .build();
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum SignalRole {
+    /// The block neither produces, conducts, nor responds to signals.
+    None,
+    /// The block constantly produces a signal of the given level, like a switch
+    /// which is turned on.
+    Source(SignalLevel),
+    /// The block conducts signals: its level is one less than the greatest level of the
+    /// sources and wires adjacent to it, so a signal reaches only as many wires as its
+    /// source's level.
+    Wire,
+    /// The block is operated by signals: when any adjacent source or wire has a nonzero
+    /// level, the block is replaced by the given block, and when none does, the
+    /// original block is put back. The replacement happens during the next
+    /// [`Space::step`], rather than immediately.
+    ///
+    /// While replaced, the cube keeps acting as this sink, whatever the replacement
+    /// block's own role is, until something else is put in the cube.
+    Sink(Box<Block>),
+}
+
+/// What happens to a block when its cube is given a *random tick*: each step, a
+/// [`Space`] picks a few of its cubes at random, at the rate set by
+/// [`SpacePhysics::random_tick_rate`](crate::space::SpacePhysics::random_tick_rate),
+/// and performs the actions of their blocks. This is suited to gradual, irregular
+/// processes such as plants growing, which would be costly to check for in every cube
+/// on every step.
+///
+/// ```
+/// use all_is_cubes::block::{Block, RandomTickAction};
+/// use all_is_cubes::math::Rgba;
+///
+/// let ripe = Block::builder().color(Rgba::new(1.0, 0.8, 0.0, 1.0)).build();
+/// let sprout = Block::builder()
+///     .color(Rgba::new(0.0, 0.8, 0.0, 1.0))
+///     .random_tick(RandomTickAction::Become(Box::new(ripe)))
+///     .build();
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum RandomTickAction {
+    /// Nothing happens.
+    None,
+    /// The block is replaced by the given block, as a crop grows to its next stage.
+    Become(Box<Block>),
+    /// One of the six adjacent cubes is chosen at random, and if it contains the given
+    /// block, that block is replaced by a copy of this one, as grass spreads over dirt.
+    SpreadTo(Box<Block>),
 }
 
 /// Generic 'empty'/'null' block. It is used by [`Space`] to respond to out-of-bounds requests.
@@ -515,7 +943,24 @@ const AIR_ATTRIBUTES: BlockAttributes = BlockAttributes {
     display_name: Cow::Borrowed("<air>"),
     selectable: false,
     collision: BlockCollision::None,
-    light_emission: Rgb::ZERO,
+    light_emission: NO_EMISSION,
+    hardness: notnan!(0.0),
+    density: notnan!(1.0),
+    tags: Cow::Borrowed(&[]),
+    signal: SignalRole::None,
+    random_tick: RandomTickAction::None,
+};
+
+/// Value of [`BlockAttributes::light_emission`] for blocks that are not light sources.
+/// (Not `FaceMap::repeat` because that is not a `const fn`.)
+pub(crate) const NO_EMISSION: FaceMap<Rgb> = FaceMap {
+    within: Rgb::ZERO,
+    nx: Rgb::ZERO,
+    ny: Rgb::ZERO,
+    nz: Rgb::ZERO,
+    px: Rgb::ZERO,
+    py: Rgb::ZERO,
+    pz: Rgb::ZERO,
 };
 
 /// A “flattened” and snapshotted form of [`Block`] which contains all information needed
@@ -553,6 +998,54 @@ pub struct EvaluatedBlock {
     pub visible: bool,
 }
 
+/// Tag found in the [`BlockAttributes::tags`] of every [`EvaluatedBlock::placeholder`],
+/// marking it as standing in for a block which could not be evaluated.
+pub const PLACEHOLDER_TAG: &str = "error";
+
+impl EvaluatedBlock {
+    /// Returns the standard stand-in for a block which could not be evaluated:
+    /// a magenta and black checkerboard, solid so that whatever it replaces does not
+    /// unexpectedly become passable, and tagged with [`PLACEHOLDER_TAG`].
+    ///
+    /// ```
+    /// use all_is_cubes::block::EvaluatedBlock;
+    ///
+    /// let placeholder = EvaluatedBlock::placeholder("Missing block");
+    /// assert_eq!(placeholder.attributes.display_name, "Missing block");
+    /// assert!(placeholder.is_placeholder());
+    /// assert!(placeholder.opaque);
+    /// ```
+    pub fn placeholder(display_name: impl Into<Cow<'static, str>>) -> Self {
+        const TAGS: &[Cow<'static, str>] = &[Cow::Borrowed(PLACEHOLDER_TAG)];
+        let resolution: Resolution = 2;
+        let light = palette::BLOCK_EVAL_ERROR;
+        let dark = Rgba::BLACK;
+        EvaluatedBlock {
+            attributes: BlockAttributes {
+                display_name: display_name.into(),
+                tags: Cow::Borrowed(TAGS),
+                ..BlockAttributes::default()
+            },
+            color: (light.to_rgb() * 0.5 + dark.to_rgb() * 0.5).with_alpha_one(),
+            voxels: Some(GridArray::from_fn(Grid::for_block(resolution), |cube| {
+                Evoxel::new(if (cube.x + cube.y + cube.z).rem_euclid(2) == 0 {
+                    light
+                } else {
+                    dark
+                })
+            })),
+            resolution,
+            opaque: true,
+            visible: true,
+        }
+    }
+
+    /// Returns whether this is an [`EvaluatedBlock::placeholder`] (or imitates one).
+    pub fn is_placeholder(&self) -> bool {
+        self.attributes.has_tag(PLACEHOLDER_TAG)
+    }
+}
+
 impl CustomFormat<ConciseDebug> for EvaluatedBlock {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>, _: ConciseDebug) -> std::fmt::Result {
         fmt.debug_struct("EvaluatedBlock")
@@ -570,13 +1063,33 @@ impl CustomFormat<ConciseDebug> for EvaluatedBlock {
 #[derive(Clone, Debug, Eq, Hash, PartialEq, thiserror::Error)]
 #[non_exhaustive]
 pub enum EvalBlockError {
+    /// The block is nested more deeply than [`EvalBudget::depth`] allows.
     #[error("block definition contains too much recursion")]
     StackOverflow,
+    /// The block definition with this name refers to itself, directly or indirectly,
+    /// so evaluating it would never finish.
+    #[error("block definition {0} refers to itself")]
+    Cycle(Name),
+    /// The block has more voxels than [`EvalBudget::voxels`] allows.
+    #[error("block evaluation exceeded the budget of {budget} voxels")]
+    VoxelBudgetExceeded {
+        /// The budget which was exceeded.
+        budget: usize,
+    },
     /// This may be temporary or permanent.
     #[error("block data inaccessible: {0}")]
     DataRefIs(#[from] RefError),
 }
 
+impl EvalBlockError {
+    /// Returns an [`EvaluatedBlock::placeholder`] to use in place of the block which
+    /// could not be evaluated, so that the failure is visible without preventing the
+    /// use of everything else. Its display name describes the error.
+    pub fn to_placeholder(&self) -> EvaluatedBlock {
+        EvaluatedBlock::placeholder(format!("Error: {}", self))
+    }
+}
+
 /// Properties of an individual voxel within [`EvaluatedBlock`].
 ///
 /// This is essentially a subset of the information in a full [`EvaluatedBlock`] and
@@ -682,9 +1195,11 @@ impl BlockDef {
     pub fn new(block: Block) -> Self {
         let notifier = Arc::new(Notifier::new());
         let (gate, block_listener) = Notifier::forwarder(Arc::downgrade(&notifier)).gate();
-        // TODO: Log if listening fails. We can't meaningfully fail this because we want to do the
-        // parallel operation in `BlockDefMut::drop` but it does indicate trouble if it happens.
-        let _ = block.listen(block_listener);
+        // We can't meaningfully fail this because we want to do the parallel operation in
+        // `BlockDefMut::drop`, but it does indicate trouble if it happens.
+        if let Err(e) = block.listen(block_listener) {
+            log::warn!("failed to listen to block definition contents: {}", e);
+        }
         BlockDef {
             block,
             notifier,
@@ -749,7 +1264,9 @@ impl Drop for BlockDefMut<'_> {
         // Swap out what we're listening to
         let (gate, block_listener) =
             Notifier::forwarder(Arc::downgrade(&block_def.notifier)).gate();
-        let _ = block_def.block.listen(block_listener);
+        if let Err(e) = block_def.block.listen(block_listener) {
+            log::warn!("failed to listen to block definition contents: {}", e);
+        }
         block_def.block_listen_gate = gate; // old gate is now dropped
 
         block_def.notifier.notify(BlockChange::new());
@@ -6,8 +6,10 @@
 use cgmath::EuclideanSpace as _;
 use std::borrow::Cow;
 
-use crate::block::{Block, BlockAttributes, BlockCollision, BlockDef, Resolution};
-use crate::math::{GridPoint, Rgb, Rgba};
+use crate::block::{
+    Block, BlockAttributes, BlockCollision, BlockDef, RandomTickAction, Resolution, SignalRole,
+};
+use crate::math::{FaceMap, GridPoint, NotNan, Rgb, Rgba};
 use crate::space::{Grid, SetCubeError, Space, SpacePhysics};
 use crate::universe::{Name, URef, Universe, UniverseIndex};
 
@@ -84,9 +86,72 @@ impl<C> BlockBuilder<C> {
         self
     }
 
-    /// Sets the value for [`BlockAttributes::light_emission`].
+    /// Sets the value for [`BlockAttributes::light_emission`] to be the same for
+    /// all faces.
     pub fn light_emission(mut self, value: impl Into<Rgb>) -> Self {
-        self.attributes.light_emission = value.into();
+        self.attributes.light_emission = FaceMap::repeat(value.into());
+        self
+    }
+
+    /// Sets the value for [`BlockAttributes::light_emission`], with a potentially
+    /// different value for each face.
+    ///
+    /// ```
+    /// use all_is_cubes::block::Block;
+    /// use all_is_cubes::math::{Face, FaceMap, Rgb, Rgba};
+    ///
+    /// // A panel which glows only toward +Z.
+    /// let light = Rgb::new(1.0, 1.0, 2.0);
+    /// let block = Block::builder()
+    ///     .color(Rgba::new(0.1, 0.1, 0.1, 1.0))
+    ///     .light_emission_faces(FaceMap::from_fn(|face| {
+    ///         if face == Face::PZ { light } else { Rgb::ZERO }
+    ///     }))
+    ///     .build();
+    /// assert_eq!(
+    ///     block.evaluate().unwrap().attributes.light_emission[Face::PZ],
+    ///     light,
+    /// );
+    /// ```
+    pub fn light_emission_faces(mut self, value: FaceMap<Rgb>) -> Self {
+        self.attributes.light_emission = value;
+        self
+    }
+
+    /// Sets the value for [`BlockAttributes::hardness`].
+    ///
+    /// Panics if `value` is NaN.
+    pub fn hardness(mut self, value: f32) -> Self {
+        self.attributes.hardness = NotNan::new(value).expect("hardness must not be NaN");
+        self
+    }
+
+    /// Sets the value for [`BlockAttributes::density`].
+    ///
+    /// Panics if `value` is NaN.
+    pub fn density(mut self, value: f32) -> Self {
+        self.attributes.density = NotNan::new(value).expect("density must not be NaN");
+        self
+    }
+
+    /// Sets the value for [`BlockAttributes::signal`].
+    pub fn signal(mut self, value: SignalRole) -> Self {
+        self.attributes.signal = value;
+        self
+    }
+
+    /// Sets the value for [`BlockAttributes::random_tick`].
+    pub fn random_tick(mut self, value: RandomTickAction) -> Self {
+        self.attributes.random_tick = value;
+        self
+    }
+
+    /// Adds a tag to [`BlockAttributes::tags`], if it is not already present.
+    pub fn tag(mut self, tag: impl Into<Cow<'static, str>>) -> Self {
+        let tag = tag.into();
+        if !self.attributes.has_tag(&tag) {
+            self.attributes.tags.to_mut().push(tag);
+        }
         self
     }
 
@@ -0,0 +1,258 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`BlockContentHash`], for recognizing equal blocks without comparing references.
+
+use std::fmt;
+
+use crate::block::{
+    BlockAttributes, BlockCollision, BlockDef, EvalBlockError, EvaluatedBlock, RandomTickAction,
+    SignalRole,
+};
+use crate::math::{Face, Rgba};
+
+/// A hash of everything about an [`EvaluatedBlock`] except what is derived from the rest
+/// (such as [`EvaluatedBlock::opaque`]).
+///
+/// Blocks which evaluate to equal [`EvaluatedBlock`]s have equal hashes, no matter how
+/// they were defined, which [`Universe`](crate::universe::Universe) they belong to,
+/// or which process or platform computed the hash. This makes it suitable for
+/// recognizing “the same block” in saved data, network messages, and caches, where
+/// [`URef`](crate::universe::URef) identity does not carry over.
+///
+/// As with any hash, distinct blocks may have equal hashes, but this is very unlikely.
+/// The algorithm will not change except in a version of this crate with other
+/// incompatible changes.
+#[allow(clippy::exhaustive_structs)]
+#[derive(
+    Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize,
+)]
+pub struct BlockContentHash(pub u64);
+
+impl fmt::Display for BlockContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl EvaluatedBlock {
+    /// Computes the [`BlockContentHash`] of this block.
+    ///
+    /// ```
+    /// use all_is_cubes::block::Block;
+    /// use all_is_cubes::math::Rgba;
+    ///
+    /// let red = Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0)).evaluate().unwrap();
+    /// let blue = Block::from(Rgba::new(0.0, 0.0, 1.0, 1.0)).evaluate().unwrap();
+    /// assert_eq!(red.content_hash(), red.clone().content_hash());
+    /// assert_ne!(red.content_hash(), blue.content_hash());
+    /// ```
+    pub fn content_hash(&self) -> BlockContentHash {
+        let mut h = StableHasher::new();
+
+        let BlockAttributes {
+            display_name,
+            selectable,
+            collision,
+            light_emission,
+            hardness,
+            density,
+            tags,
+            signal,
+            random_tick,
+        } = &self.attributes;
+        h.str(display_name);
+        h.bool(*selectable);
+        h.collision(*collision);
+        for &face in Face::ALL_SEVEN {
+            let [r, g, b]: [f32; 3] = light_emission[face].into();
+            h.f32(r);
+            h.f32(g);
+            h.f32(b);
+        }
+        h.f32(hardness.into_inner());
+        h.u64(tags.len() as u64);
+        for tag in tags.iter() {
+            h.str(tag);
+        }
+        // Hashed only if not the default, so that the hashes of other blocks are
+        // unchanged from before signals existed.
+        match signal {
+            SignalRole::None => {}
+            SignalRole::Source(level) => {
+                h.u8(1);
+                h.u8(*level);
+            }
+            SignalRole::Wire => h.u8(2),
+            // The replacement block is not hashed, since that would require evaluating
+            // it, which might never finish if it is a sink replaced by the first block.
+            SignalRole::Sink(_) => h.u8(3),
+        }
+        // Likewise, and the blocks are not hashed for the same reason.
+        match random_tick {
+            RandomTickAction::None => {}
+            RandomTickAction::Become(_) => h.u8(4),
+            RandomTickAction::SpreadTo(_) => h.u8(5),
+        }
+        if *density != notnan!(1.0) {
+            h.u8(6);
+            h.f32(density.into_inner());
+        }
+
+        h.rgba(self.color);
+        h.u8(self.resolution);
+        match &self.voxels {
+            None => h.u8(0),
+            Some(voxels) => {
+                h.u8(1);
+                let grid = voxels.grid();
+                let lower_bounds: [i32; 3] = grid.lower_bounds().into();
+                let size: [i32; 3] = grid.size().into();
+                for &coordinate in lower_bounds.iter().chain(size.iter()) {
+                    h.bytes(&coordinate.to_le_bytes());
+                }
+                for cube in grid.interior_iter() {
+                    let voxel = &voxels[cube];
+                    h.rgba(voxel.color);
+                    h.bool(voxel.selectable);
+                    h.collision(voxel.collision);
+                }
+            }
+        }
+
+        BlockContentHash(h.0)
+    }
+}
+
+impl BlockDef {
+    /// Evaluates the defined block and computes its [`BlockContentHash`].
+    pub fn content_hash(&self) -> Result<BlockContentHash, EvalBlockError> {
+        Ok(self.evaluate()?.content_hash())
+    }
+}
+
+/// 64-bit FNV-1a, fed with explicitly little-endian and length-prefixed data so that it
+/// does not depend on the platform or on [`std::hash::Hash`] implementation details.
+struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value.into());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u64(value.len() as u64);
+        self.bytes(value.as_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        // Negative zero is equal to zero and must hash the same.
+        let bits = if value == 0.0 { 0 } else { value.to_bits() };
+        self.bytes(&bits.to_le_bytes());
+    }
+
+    fn rgba(&mut self, value: Rgba) {
+        for &component in <[f32; 4]>::from(value).iter() {
+            self.f32(component);
+        }
+    }
+
+    fn collision(&mut self, value: BlockCollision) {
+        self.u8(match value {
+            BlockCollision::None => 0,
+            BlockCollision::Hard => 1,
+            BlockCollision::Fluid => 2,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{Block, AIR};
+    use crate::content::make_some_voxel_blocks;
+    use crate::universe::{Universe, UniverseIndex as _};
+
+    /// The hash must not change unintentionally, since it may be stored.
+    #[test]
+    fn known_value() {
+        assert_eq!(
+            Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0))
+                .evaluate()
+                .unwrap()
+                .content_hash(),
+            BlockContentHash(KNOWN_RED_HASH)
+        );
+    }
+    const KNOWN_RED_HASH: u64 = 0xc12a_3692_7b33_8556;
+
+    #[test]
+    fn same_across_universes() {
+        let block = Block::builder()
+            .display_name("x")
+            .color(Rgba::WHITE)
+            .tag("t")
+            .build();
+        let mut u1 = Universe::new();
+        let mut u2 = Universe::new();
+        let def1 = u1.insert("a".into(), BlockDef::new(block.clone())).unwrap();
+        let def2 = u2.insert("b".into(), BlockDef::new(block.clone())).unwrap();
+        assert_ne!(Block::Indirect(def1.clone()), Block::Indirect(def2.clone()));
+        assert_eq!(
+            def1.borrow().content_hash().unwrap(),
+            def2.borrow().content_hash().unwrap()
+        );
+        assert_eq!(
+            def1.borrow().content_hash().unwrap(),
+            block.evaluate().unwrap().content_hash()
+        );
+    }
+
+    #[test]
+    fn voxels_and_attributes_distinguished() {
+        let mut universe = Universe::new();
+        let [b1, b2] = make_some_voxel_blocks(&mut universe);
+        let e1 = b1.evaluate().unwrap();
+        assert_ne!(e1.content_hash(), b2.evaluate().unwrap().content_hash());
+        assert_ne!(e1.content_hash(), AIR.evaluate().unwrap().content_hash());
+
+        let mut e1_renamed = e1.clone();
+        e1_renamed.attributes.display_name = "renamed".into();
+        assert_ne!(e1.content_hash(), e1_renamed.content_hash());
+    }
+
+    #[test]
+    fn negative_zero() {
+        assert_eq!(
+            Block::from(Rgba::new(0.0, 0.0, 0.0, 1.0))
+                .evaluate()
+                .unwrap()
+                .content_hash(),
+            Block::from(Rgba::new(-0.0, 0.0, 0.0, 1.0))
+                .evaluate()
+                .unwrap()
+                .content_hash(),
+        );
+    }
+}
@@ -0,0 +1,256 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Modifier`]s, which transform the appearance of a block as part of
+//! [`Block::Modified`].
+
+use cgmath::{Vector4, Zero as _};
+use std::convert::TryFrom as _;
+
+use crate::block::{
+    overlay_evaluated, rotate_evaluated, Block, BlockAttributes, EvalBlockError, EvalState,
+    EvaluatedBlock, Evoxel, Resolution,
+};
+use crate::math::{Face, FaceMap, GridRotation, Rgb, Rgba};
+use crate::space::{Grid, GridArray};
+
+/// A transformation applied to a block's evaluated appearance, as part of the list in
+/// [`Block::Modified`].
+///
+/// Modifiers let a small set of block definitions be combined into many variants
+/// without defining a new [`Space`](crate::space::Space) of voxels for each one.
+///
+/// ```
+/// use all_is_cubes::block::{Block, Modifier};
+/// use all_is_cubes::math::{Rgb, Rgba};
+///
+/// let block = Block::from(Rgba::new(1.0, 0.5, 0.5, 1.0))
+///     .with_modifier(Modifier::Tint(Rgb::new(0.5, 1.0, 1.0)));
+/// assert_eq!(block.evaluate().unwrap().color, Rgba::new(0.5, 0.5, 0.5, 1.0));
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Modifier {
+    /// Reflects the block across the plane through its center which is perpendicular
+    /// to the given face; thus [`Face::PX`] and [`Face::NX`] have the same effect.
+    /// [`Face::Within`] has no effect.
+    Mirror(Face),
+
+    /// Multiplies the color of every voxel by the given color, leaving alpha unchanged.
+    Tint(Rgb),
+
+    /// Replaces the color of every voxel with the given color, leaving alpha unchanged.
+    Recolor(Rgb),
+
+    /// Removes all voxels outside of the given region, which is in the coordinates of
+    /// the block's voxels (that is, within [`Grid::for_block`] of its resolution).
+    Crop(Grid),
+
+    /// Paints the visible voxels of the given block over the surface, in the same way
+    /// as [`Block::Overlay`].
+    Overlay(Block),
+
+    /// Shapes the block according to which of its neighbors it is connected to, for
+    /// making fences, pipes, rails, and similar things.
+    ///
+    /// Keeps the voxels within `core` (in the coordinates of the block's voxels), and
+    /// within each “arm” formed by extending `core` to the edge of the block toward
+    /// a face whose entry in `connected` is true; removes all other voxels. So, the
+    /// block should be defined with arms toward every side it might connect to.
+    ///
+    /// A [`Space`](crate::space::Space) sets `connected` whenever it is changed: a face
+    /// is connected if the adjacent cube contains the same block, disregarding the
+    /// `connected` values of both. It does not matter what `connected` value is used
+    /// when placing the block.
+    Connect {
+        core: Grid,
+        connected: FaceMap<bool>,
+    },
+}
+
+impl Modifier {
+    /// Applies this modifier to `block`.
+    pub(super) fn apply(
+        &self,
+        block: EvaluatedBlock,
+        state: &mut EvalState,
+        depth: u8,
+    ) -> Result<EvaluatedBlock, EvalBlockError> {
+        Ok(match *self {
+            Modifier::Mirror(Face::Within) => block,
+            Modifier::Mirror(face) => {
+                let mut basis = [Face::PX, Face::PY, Face::PZ];
+                basis[face.axis_number()] = basis[face.axis_number()].opposite();
+                rotate_evaluated(block, GridRotation::from_basis(basis), state)?
+            }
+
+            Modifier::Tint(tint) => map_colors(block, state, |color| {
+                (color.to_rgb() * tint).with_alpha(color.alpha())
+            })?,
+
+            Modifier::Recolor(new_color) => {
+                map_colors(block, state, |color| new_color.with_alpha(color.alpha()))?
+            }
+
+            Modifier::Crop(region) => {
+                let resolution = block.resolution;
+                let voxels = match block.voxels {
+                    Some(ref voxels) => voxels,
+                    None if region.contains_cube([0, 0, 0]) => return Ok(block),
+                    None => {
+                        // An atom block has only one voxel, and it has been cropped away.
+                        return Ok(from_voxels(
+                            block.attributes,
+                            resolution,
+                            GridArray::from_fn(Grid::new([0, 0, 0], [0, 0, 0]), |_| Evoxel::AIR),
+                        ));
+                    }
+                };
+                let kept = voxels
+                    .grid()
+                    .intersection(region)
+                    .unwrap_or_else(|| Grid::new([0, 0, 0], [0, 0, 0]));
+                state.spend_voxels(kept.volume())?;
+                let voxels = GridArray::from_fn(kept, |cube| voxels[cube]);
+                from_voxels(block.attributes, resolution, voxels)
+            }
+
+            Modifier::Overlay(ref overlay) => {
+                let overlay = overlay.evaluate_impl(state, depth)?;
+                if overlay.visible {
+                    state.spend_voxels(
+                        Grid::for_block(block.resolution.max(overlay.resolution)).volume(),
+                    )?;
+                }
+                overlay_evaluated(block, &overlay)
+            }
+
+            Modifier::Connect { core, connected } => {
+                let resolution = block.resolution;
+                let voxels = match block.voxels {
+                    Some(ref voxels) => voxels,
+                    // An atom block has no shape to change.
+                    None => return Ok(block),
+                };
+                let arms: Vec<Grid> = Face::ALL_SIX
+                    .iter()
+                    .filter(|&&face| connected[face])
+                    .map(|&face| connection_arm(core, resolution, face))
+                    .collect();
+                state.spend_voxels(voxels.grid().volume())?;
+                let voxels = GridArray::from_fn(voxels.grid(), |cube| {
+                    if core.contains_cube(cube) || arms.iter().any(|arm| arm.contains_cube(cube)) {
+                        voxels[cube]
+                    } else {
+                        Evoxel::AIR
+                    }
+                });
+                from_voxels(block.attributes, resolution, voxels)
+            }
+        })
+    }
+}
+
+/// If `block` has any [`Modifier::Connect`], returns `block` with all of their
+/// `connected` values replaced with `connected`; otherwise returns [`None`].
+///
+/// Two blocks connect to each other if this function, given the same `connected`,
+/// returns the same block for both.
+pub(crate) fn with_connections(block: &Block, connected: FaceMap<bool>) -> Option<Block> {
+    match block {
+        Block::Modified { base, modifiers }
+            if modifiers
+                .iter()
+                .any(|m| matches!(m, Modifier::Connect { .. })) =>
+        {
+            let modifiers = modifiers
+                .iter()
+                .map(|modifier| match *modifier {
+                    Modifier::Connect { core, .. } => Modifier::Connect { core, connected },
+                    ref other => other.clone(),
+                })
+                .collect();
+            Some(Block::Modified {
+                base: base.clone(),
+                modifiers,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Returns `core` extended to the edge of the block toward `face`.
+fn connection_arm(core: Grid, resolution: Resolution, face: Face) -> Grid {
+    let full = Grid::for_block(resolution);
+    let axis = face.axis_number();
+    let mut lower = core.lower_bounds();
+    let mut upper = core.upper_bounds();
+    if face.is_positive() {
+        upper[axis] = upper[axis].max(full.upper_bounds()[axis]);
+    } else {
+        lower[axis] = lower[axis].min(full.lower_bounds()[axis]);
+    }
+    Grid::from_lower_upper(lower, upper)
+}
+
+/// Applies `function` to the color of `block` and of each of its voxels.
+fn map_colors(
+    block: EvaluatedBlock,
+    state: &mut EvalState,
+    mut function: impl FnMut(Rgba) -> Rgba,
+) -> Result<EvaluatedBlock, EvalBlockError> {
+    if let Some(voxels) = &block.voxels {
+        state.spend_voxels(voxels.grid().volume())?;
+    }
+    Ok(EvaluatedBlock {
+        color: function(block.color),
+        voxels: block.voxels.map(|voxels| {
+            GridArray::from_fn(voxels.grid(), |cube| {
+                let voxel = voxels[cube];
+                Evoxel {
+                    color: function(voxel.color),
+                    ..voxel
+                }
+            })
+        }),
+        ..block
+    })
+}
+
+/// Computes the properties of a voxel block which are derived from its voxels, the
+/// same way evaluating a [`Block::Recur`] does.
+fn from_voxels(
+    attributes: BlockAttributes,
+    resolution: Resolution,
+    voxels: GridArray<Evoxel>,
+) -> EvaluatedBlock {
+    let full_resolution_grid = Grid::for_block(resolution.max(1));
+    let mut color_sum: Vector4<f32> = Vector4::zero();
+    for cube in voxels.grid().interior_iter() {
+        color_sum += voxels[cube].color.into();
+    }
+    let color = if voxels.grid().volume() == 0 {
+        Rgba::TRANSPARENT
+    } else {
+        Rgba::try_from(
+            (color_sum.truncate() / (voxels.grid().volume() as f32))
+                .extend(color_sum.w / (full_resolution_grid.volume() as f32)),
+        )
+        .expect("modified block color computation produced NaN")
+    };
+    EvaluatedBlock {
+        attributes,
+        color,
+        resolution,
+        opaque: voxels.grid() == full_resolution_grid
+            && voxels
+                .grid()
+                .interior_iter()
+                .all(|p| voxels[p].color.fully_opaque()),
+        visible: voxels
+            .grid()
+            .interior_iter()
+            .any(|p| !voxels[p].color.fully_transparent()),
+        voxels: Some(voxels),
+    }
+}
@@ -8,13 +8,13 @@ use std::borrow::Cow;
 
 use crate::block::{
     builder, Block, BlockAttributes, BlockBuilder, BlockCollision, BlockDef, EvalBlockError,
-    Evoxel, Resolution, AIR,
+    EvalBudget, Evoxel, Modifier, RandomTickAction, Resolution, SignalRole, AIR,
 };
-use crate::content::make_some_blocks;
+use crate::content::{make_some_blocks, make_some_voxel_blocks};
 use crate::listen::{NullListener, Sink};
-use crate::math::{GridPoint, GridVector, Rgb, Rgba};
+use crate::math::{Face, FaceMap, GridCoordinate, GridPoint, GridRotation, GridVector, Rgb, Rgba};
 use crate::space::{Grid, GridArray, Space};
-use crate::universe::Universe;
+use crate::universe::{Name, Universe};
 
 #[test]
 fn evaluate_opaque_atom_and_attributes() {
@@ -23,7 +23,7 @@ fn evaluate_opaque_atom_and_attributes() {
         display_name: Cow::Borrowed(&"hello world"),
         selectable: false,
         collision: BlockCollision::None,
-        light_emission: Rgb::ONE,
+        light_emission: FaceMap::repeat(Rgb::ONE),
         ..BlockAttributes::default()
     };
     let block = Block::Atom(attributes.clone(), color);
@@ -175,6 +175,54 @@ fn evaluate_voxels_partial_not_filling() {
     assert_eq!(e.visible, true);
 }
 
+/// Evaluation and rotation shouldn't depend on the resolution being a power of two or
+/// any other particular size. The voxel space is kept small so this is cheap to check
+/// for every resolution.
+#[test]
+fn evaluate_voxels_at_every_resolution() {
+    let mut universe = Universe::new();
+    for resolution in 1..=Resolution::MAX {
+        let corner = GridCoordinate::from(resolution) - 1;
+        let mut space = Space::empty(Grid::new([corner, corner, 0], [1, 1, 1]));
+        space
+            .set([corner, corner, 0], Block::from(Rgba::WHITE))
+            .unwrap();
+        let block = Block::builder()
+            .voxels_ref(resolution, universe.insert_anonymous(space))
+            .build();
+
+        let e = block.evaluate().unwrap();
+        assert_eq!(e.resolution, resolution);
+        assert_eq!(e.opaque, resolution == 1, "resolution {}", resolution);
+        assert_eq!(e.visible, true);
+        assert_eq!(
+            e.color.alpha().into_inner(),
+            1.0 / (resolution as f32).powi(3),
+            "resolution {}",
+            resolution
+        );
+
+        // Rotating keeps the voxel within the block, and four rotations undo themselves.
+        let rotated = block
+            .clone()
+            .rotate(GridRotation::CLOCKWISE)
+            .evaluate()
+            .unwrap();
+        let rotated_voxels = rotated.voxels.unwrap();
+        assert!(
+            Grid::for_block(resolution).contains_grid(rotated_voxels.grid()),
+            "resolution {}: {:?}",
+            resolution,
+            rotated_voxels.grid()
+        );
+        let mut full_turn = block.clone();
+        for _ in 0..4 {
+            full_turn = full_turn.rotate(GridRotation::CLOCKWISE);
+        }
+        assert_eq!(full_turn.evaluate().unwrap().voxels, e.voxels);
+    }
+}
+
 /// Tests that the `offset` field of `Block::Recur` is respected.
 #[test]
 fn recur_with_offset() {
@@ -239,6 +287,208 @@ fn indirect_equivalence() {
     assert_eq!(eval_bare, eval_def);
 }
 
+/// A 4×4×4 opaque block whose voxel colors vary with position, for testing modifiers.
+fn gradient_block(universe: &mut Universe) -> Block {
+    Block::builder()
+        .voxels_fn(universe, 4, |point| {
+            let point = point.cast::<f32>().unwrap() / 4.0;
+            Block::from(Rgba::new(point.x, point.y, point.z, 1.0))
+        })
+        .unwrap()
+        .build()
+}
+
+#[test]
+fn modifier_mirror() {
+    let mut universe = Universe::new();
+    let block = gradient_block(&mut universe);
+    let original = block.evaluate().unwrap().voxels.unwrap();
+    let mirrored = block
+        .clone()
+        .with_modifier(Modifier::Mirror(Face::NX))
+        .evaluate()
+        .unwrap();
+    let voxels = mirrored.voxels.unwrap();
+    assert_eq!(voxels.grid(), Grid::for_block(4));
+    for cube in voxels.grid().interior_iter() {
+        assert_eq!(voxels[cube], original[[3 - cube.x, cube.y, cube.z]]);
+    }
+    assert_eq!(mirrored.opaque, true);
+
+    assert_eq!(
+        block
+            .clone()
+            .with_modifier(Modifier::Mirror(Face::Within))
+            .evaluate(),
+        block.evaluate()
+    );
+}
+
+#[test]
+fn modifier_tint_and_recolor() {
+    let mut universe = Universe::new();
+    let block = Block::builder()
+        .voxels_fn(&mut universe, 2, |point| {
+            Block::from(Rgba::new(
+                0.5,
+                1.0,
+                1.0,
+                if point.x == 0 { 1.0 } else { 0.5 },
+            ))
+        })
+        .unwrap()
+        .build();
+
+    let tinted = block
+        .clone()
+        .with_modifier(Modifier::Tint(Rgb::new(1.0, 0.5, 0.0)))
+        .evaluate()
+        .unwrap();
+    let recolored = block
+        .clone()
+        .with_modifier(Modifier::Recolor(Rgb::new(0.0, 0.0, 1.0)))
+        .evaluate()
+        .unwrap();
+    for (e, expected_rgb) in vec![
+        (&tinted, Rgb::new(0.5, 0.5, 0.0)),
+        (&recolored, Rgb::new(0.0, 0.0, 1.0)),
+    ] {
+        let voxels = e.voxels.as_ref().unwrap();
+        assert_eq!(voxels[[0, 0, 0]].color, expected_rgb.with_alpha_one());
+        assert_eq!(
+            voxels[[1, 0, 0]].color,
+            expected_rgb.with_alpha(notnan!(0.5))
+        );
+        assert_eq!(e.color.to_rgb(), expected_rgb);
+        assert_eq!(e.opaque, false);
+    }
+
+    // Atoms are modified too.
+    assert_eq!(
+        Block::from(Rgba::WHITE)
+            .with_modifier(Modifier::Recolor(Rgb::ZERO))
+            .evaluate()
+            .unwrap()
+            .color,
+        Rgba::BLACK
+    );
+}
+
+#[test]
+fn modifier_crop() {
+    let mut universe = Universe::new();
+    let block = gradient_block(&mut universe);
+    let region = Grid::new([0, 0, 0], [2, 4, 4]);
+    let cropped = block
+        .clone()
+        .with_modifier(Modifier::Crop(region))
+        .evaluate()
+        .unwrap();
+    let original = block.evaluate().unwrap().voxels.unwrap();
+    let voxels = cropped.voxels.unwrap();
+    assert_eq!(voxels.grid(), region);
+    for cube in region.interior_iter() {
+        assert_eq!(voxels[cube], original[cube]);
+    }
+    assert_eq!(cropped.resolution, 4);
+    assert_eq!(cropped.color.alpha().into_inner(), 0.5);
+    assert_eq!(cropped.opaque, false);
+    assert_eq!(cropped.visible, true);
+
+    // Cropping away everything leaves nothing visible.
+    let outside = Grid::new([10, 10, 10], [1, 1, 1]);
+    for base in vec![block, Block::from(Rgba::WHITE)] {
+        let e = base
+            .with_modifier(Modifier::Crop(outside))
+            .evaluate()
+            .unwrap();
+        assert_eq!(e.visible, false);
+        assert_eq!(e.color, Rgba::TRANSPARENT);
+    }
+}
+
+#[test]
+fn modifier_connect() {
+    let mut universe = Universe::new();
+    let block = gradient_block(&mut universe);
+    let original = block.evaluate().unwrap().voxels.unwrap();
+    let core = Grid::new([1, 1, 1], [2, 2, 2]);
+    let connected = FaceMap {
+        px: true,
+        ..FaceMap::repeat(false)
+    };
+    let evaluated = block
+        .with_modifier(Modifier::Connect { core, connected })
+        .evaluate()
+        .unwrap();
+    assert_eq!(evaluated.opaque, false);
+    let voxels = evaluated.voxels.unwrap();
+    assert_eq!(voxels.grid(), original.grid());
+    for cube in voxels.grid().interior_iter() {
+        let in_arm = (1..3).contains(&cube.y) && (1..3).contains(&cube.z) && cube.x >= 1;
+        let expected = if in_arm { original[cube] } else { Evoxel::AIR };
+        assert_eq!(voxels[cube], expected, "{:?}", cube);
+    }
+}
+
+#[test]
+fn modifiers_applied_in_order() {
+    let mut universe = Universe::new();
+    let block = gradient_block(&mut universe);
+    let crop = Modifier::Crop(Grid::new([0, 0, 0], [1, 4, 4]));
+    let mirror = Modifier::Mirror(Face::PX);
+    let crop_then_mirror = block
+        .clone()
+        .with_modifier(crop.clone())
+        .with_modifier(mirror.clone())
+        .evaluate()
+        .unwrap();
+    let mirror_then_crop = block
+        .with_modifier(mirror)
+        .with_modifier(crop)
+        .evaluate()
+        .unwrap();
+    assert_eq!(
+        crop_then_mirror.voxels.unwrap().grid(),
+        Grid::new([3, 0, 0], [1, 4, 4])
+    );
+    let voxels = mirror_then_crop.voxels.unwrap();
+    assert_eq!(voxels.grid(), Grid::new([0, 0, 0], [1, 4, 4]));
+    assert_eq!(voxels[[0, 0, 0]].color, Rgba::new(0.75, 0.0, 0.0, 1.0));
+}
+
+#[test]
+fn modifier_overlay_equivalent_to_block_overlay() {
+    let mut universe = Universe::new();
+    let base = gradient_block(&mut universe);
+    let [overlay] = make_some_voxel_blocks(&mut universe);
+    assert_eq!(
+        base.clone()
+            .with_modifier(Modifier::Overlay(overlay.clone()))
+            .evaluate(),
+        Block::Overlay {
+            base: Box::new(base),
+            overlay: Box::new(overlay),
+        }
+        .evaluate()
+    );
+}
+
+#[test]
+fn listen_modifier_overlay() {
+    let mut universe = Universe::new();
+    let [block_0, block_1] = make_some_blocks();
+    let overlay_def = universe.insert_anonymous(BlockDef::new(block_0));
+    let block = Block::from(Rgba::WHITE)
+        .with_modifier(Modifier::Overlay(Block::Indirect(overlay_def.clone())));
+    let mut sink = Sink::new();
+    block.listen(sink.listener()).unwrap();
+    assert_eq!(None, sink.next());
+
+    *(overlay_def.borrow_mut().modify()) = block_1;
+    assert!(sink.next().is_some());
+}
+
 #[test]
 fn listen_atom() {
     let block = Block::from(Rgba::WHITE);
@@ -328,17 +578,27 @@ fn builder_every_field() {
     assert_eq!(
         Block::builder()
             .display_name("hello world")
-            .collision(BlockCollision::None) // TODO: when we have more interesting values, use one
+            .collision(BlockCollision::Fluid)
             .color(color)
             .selectable(false)
             .light_emission(light_emission)
+            .hardness(2.5)
+            .density(1.5)
+            .tag("t")
+            .signal(SignalRole::Wire)
+            .random_tick(RandomTickAction::Become(Box::new(AIR)))
             .build(),
         Block::Atom(
             BlockAttributes {
                 display_name: "hello world".into(),
-                collision: BlockCollision::None,
+                collision: BlockCollision::Fluid,
                 selectable: false,
-                light_emission
+                light_emission: FaceMap::repeat(light_emission),
+                hardness: notnan!(2.5),
+                density: notnan!(1.5),
+                tags: Cow::Owned(vec![Cow::Borrowed("t")]),
+                signal: SignalRole::Wire,
+                random_tick: RandomTickAction::Become(Box::new(AIR)),
             },
             color
         ),
@@ -413,11 +673,37 @@ fn attributes_debug() {
     );
     assert_eq!(
         &*debug(BlockAttributes {
-            light_emission: Rgb::new(1.0, 2.0, 3.0),
+            light_emission: FaceMap::repeat(Rgb::new(1.0, 2.0, 3.0)),
             ..default()
         }),
         "BlockAttributes { light_emission: Rgb(1.0, 2.0, 3.0) }",
     );
+    assert_eq!(
+        &*debug(BlockAttributes {
+            light_emission: FaceMap::from_fn(|face| if face == Face::PX {
+                Rgb::ONE
+            } else {
+                Rgb::ZERO
+            }),
+            ..default()
+        }),
+        "BlockAttributes { light_emission: FaceMap { \
+            within: Rgb(0.0, 0.0, 0.0), \
+            nx: Rgb(0.0, 0.0, 0.0), \
+            ny: Rgb(0.0, 0.0, 0.0), \
+            nz: Rgb(0.0, 0.0, 0.0), \
+            px: Rgb(1.0, 1.0, 1.0), \
+            py: Rgb(0.0, 0.0, 0.0), \
+            pz: Rgb(0.0, 0.0, 0.0) } }",
+    );
+
+    assert_eq!(
+        &*debug(BlockAttributes {
+            tags: Cow::Owned(vec!["stone".into(), "natural".into()]),
+            ..default()
+        }),
+        "BlockAttributes { tags: [\"stone\", \"natural\"] }",
+    );
 
     // Test a case of multiple attributes
     assert_eq!(
@@ -434,9 +720,82 @@ fn attributes_debug() {
 fn overflow_evaluate() {
     let mut universe = Universe::new();
     let block = self_referential_block(&mut universe);
+    assert_eq!(
+        block.evaluate(),
+        Err(EvalBlockError::Cycle(Name::Anonym(0)))
+    );
+}
+
+#[test]
+fn overflow_evaluate_through_rotation() {
+    let mut universe = Universe::new();
+    let block_def = universe.insert_anonymous(BlockDef::new(AIR));
+    *(block_def.borrow_mut().modify()) = Block::Rotated(
+        GridRotation::CLOCKWISE,
+        Box::new(Block::Indirect(block_def.clone())),
+    );
+    assert_eq!(
+        Block::Indirect(block_def).evaluate(),
+        Err(EvalBlockError::Cycle(Name::Anonym(0)))
+    );
+}
+
+#[test]
+fn evaluate_depth_limit() {
+    let mut universe = Universe::new();
+    let mut block = AIR;
+    for _ in 0..EvalBudget::DEFAULT.depth {
+        block = Block::Indirect(universe.insert_anonymous(BlockDef::new(block)));
+    }
+    // Exactly at the limit is allowed.
+    assert_eq!(block.evaluate(), AIR.evaluate());
+
+    let block = Block::Indirect(universe.insert_anonymous(BlockDef::new(block)));
     assert_eq!(block.evaluate(), Err(EvalBlockError::StackOverflow));
 }
 
+#[test]
+fn evaluate_voxel_budget() {
+    let mut universe = Universe::new();
+    let [block] = make_some_voxel_blocks(&mut universe);
+    let volume = block.evaluate().unwrap().voxels.unwrap().grid().volume();
+
+    let mut budget = EvalBudget::default();
+    budget.voxels = volume;
+    assert_eq!(block.evaluate_with_budget(budget), block.evaluate());
+
+    budget.voxels = volume - 1;
+    assert_eq!(
+        block.evaluate_with_budget(budget),
+        Err(EvalBlockError::VoxelBudgetExceeded { budget: volume - 1 })
+    );
+    // The budget covers the whole evaluation, not each part separately.
+    assert_eq!(
+        Block::Rotated(GridRotation::CLOCKWISE, Box::new(block.clone())).evaluate_with_budget(
+            EvalBudget {
+                voxels: volume,
+                ..budget
+            }
+        ),
+        Err(EvalBlockError::VoxelBudgetExceeded { budget: volume })
+    );
+}
+
+#[test]
+fn eval_error_placeholder() {
+    let placeholder = EvalBlockError::StackOverflow.to_placeholder();
+    assert_eq!(
+        placeholder.attributes.display_name,
+        "Error: block definition contains too much recursion"
+    );
+    assert!(placeholder.is_placeholder());
+    assert!(placeholder.visible);
+
+    let voxels = placeholder.voxels.unwrap();
+    assert_ne!(voxels[(0, 0, 0)].color, voxels[(1, 0, 0)].color);
+    assert_eq!(voxels[(0, 0, 0)].color, voxels[(1, 1, 0)].color);
+}
+
 #[test]
 fn overflow_listen() {
     let mut universe = Universe::new();
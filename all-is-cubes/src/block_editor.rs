@@ -0,0 +1,282 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Editing the voxels of a block by walking around inside an enlarged copy of it.
+//!
+//! [`BlockEditor`] provides the world-model parts of a block editing mode: a separate
+//! [`Universe`] to present to the player, and writing the results back to the block.
+//! User interface around it is left to the frontend.
+
+use cgmath::{EuclideanSpace as _, Vector3};
+use std::collections::HashSet;
+
+use crate::block::{Block, BlockDef, Resolution, AIR};
+use crate::character::{Character, Spawn};
+use crate::math::{FaceMap, GridCoordinate, GridMatrix};
+use crate::space::{Grid, SetCubeError, Space, SpacePhysics};
+use crate::tools::Tool;
+use crate::universe::{InsertError, RefError, URef, Universe, UniverseIndex as _};
+
+/// Number of empty cubes around the block in the editing space, so that the player
+/// may see and reach all of its sides.
+const MARGIN: GridCoordinate = 2;
+
+/// An editing session for a voxel block: a [`Universe`] containing a copy of the
+/// block's voxels at the scale of whole blocks, and a character to edit them with,
+/// whose changes are applied to the original block by [`BlockEditor::commit`].
+///
+/// The editing space uses the same coordinates as the block's voxels, so the cubes
+/// of [`BlockEditor::region`] are the voxels; anything outside of it is not part of
+/// the block and is not written back.
+///
+/// ```
+/// use all_is_cubes::block::{Block, BlockDef, AIR};
+/// use all_is_cubes::block_editor::BlockEditor;
+/// use all_is_cubes::math::Rgba;
+/// use all_is_cubes::universe::Universe;
+///
+/// let mut universe = Universe::new();
+/// let block = Block::builder()
+///     .voxels_fn(&mut universe, 4, |_| Block::from(Rgba::WHITE))
+///     .unwrap()
+///     .build();
+/// let def_ref = universe.insert_anonymous(BlockDef::new(block));
+///
+/// let editor = BlockEditor::new(&def_ref).unwrap();
+/// editor.space().borrow_mut().set([1, 1, 1], AIR).unwrap();
+/// editor.commit().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct BlockEditor {
+    universe: Universe,
+    space: URef<Space>,
+    character: URef<Character>,
+    /// The space containing the voxels of the block being edited.
+    target_space: URef<Space>,
+    region: Grid,
+    resolution: Resolution,
+}
+
+impl BlockEditor {
+    /// Starts editing the block defined by `block_def`, which must be a
+    /// [`Block::Recur`].
+    ///
+    /// The character's inventory is given the distinct atom blocks (colors) found in
+    /// the voxels, so that it can place them, and [`Tool::PickColor`] to pick up
+    /// more, in addition to the usual tools for removing and copying blocks.
+    pub fn new(block_def: &URef<BlockDef>) -> Result<Self, BlockEditorError> {
+        let (target_space, region, resolution) = match &**block_def.try_borrow()? {
+            Block::Recur {
+                space,
+                offset,
+                resolution,
+                ..
+            } => (
+                space.clone(),
+                Grid::for_block(*resolution).translate(offset.to_vec()),
+                *resolution,
+            ),
+            _ => return Err(BlockEditorError::NotVoxels),
+        };
+
+        let mut space = Space::empty(region.expand(FaceMap::repeat(MARGIN)));
+        space.set_physics(SpacePhysics {
+            gravity: Vector3::new(notnan!(0.), notnan!(0.), notnan!(0.)),
+            ..SpacePhysics::default()
+        });
+        let mut palette: Vec<Block> = Vec::new();
+        {
+            let voxels = target_space.try_borrow()?;
+            let mut seen = HashSet::new();
+            space.fill(region, |cube| {
+                let block = &voxels[cube];
+                if matches!(block, Block::Atom(..)) && *block != AIR && seen.insert(block.clone()) {
+                    palette.push(block.clone());
+                }
+                Some(block.clone())
+            })?;
+        }
+        space.seed_sky_light();
+
+        let mut universe = Universe::new();
+        let space = universe.insert("space".into(), space)?;
+        let spawn = Spawn {
+            inventory: std::iter::once(Tool::PickColor)
+                .chain(palette.into_iter().map(Tool::PlaceBlock))
+                .collect(),
+            ..Spawn::looking_at_space(space.clone(), [0., 0., 1.])
+        };
+        let character =
+            universe.insert("character".into(), Character::spawn(&spawn, space.clone()))?;
+
+        Ok(Self {
+            universe,
+            space,
+            character,
+            target_space,
+            region,
+            resolution,
+        })
+    }
+
+    /// Returns the editing universe, which contains [`BlockEditor::space`] and
+    /// [`BlockEditor::character`].
+    pub fn universe(&self) -> &Universe {
+        &self.universe
+    }
+
+    /// Returns the editing universe mutably, such as for stepping it.
+    pub fn universe_mut(&mut self) -> &mut Universe {
+        &mut self.universe
+    }
+
+    /// Returns the space in which the enlarged voxels are edited.
+    pub fn space(&self) -> &URef<Space> {
+        &self.space
+    }
+
+    /// Returns the character with which the player edits the voxels.
+    pub fn character(&self) -> &URef<Character> {
+        &self.character
+    }
+
+    /// Returns the region of [`BlockEditor::space`] which holds the voxels.
+    pub fn region(&self) -> Grid {
+        self.region
+    }
+
+    /// Returns the resolution of the block being edited.
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Writes the contents of the editing region back into the block's voxels.
+    ///
+    /// This changes the block's [`Space`], so it sends the usual change notifications
+    /// to everything using the block, which will update after their next step.
+    /// The editor may continue to be used afterward.
+    pub fn commit(&self) -> Result<(), BlockEditorError> {
+        let source = self.space.try_borrow()?;
+        self.target_space.try_borrow_mut()?.copy_from(
+            &source,
+            self.region,
+            GridMatrix::from_translation([0, 0, 0]),
+        )?;
+        Ok(())
+    }
+}
+
+/// Errors from [`BlockEditor`] operations.
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum BlockEditorError {
+    /// The block is not defined by voxels, so it cannot be edited as voxels.
+    #[error("block is not made of voxels")]
+    NotVoxels,
+    /// The block definition or one of the spaces could not be accessed.
+    #[error("could not access block data: {0}")]
+    Ref(#[from] RefError),
+    /// Copying the voxels failed.
+    #[error("could not copy voxels: {0}")]
+    SetCube(#[from] SetCubeError),
+    /// Setting up the editing universe failed.
+    #[error("could not create editing universe: {0}")]
+    Insert(#[from] InsertError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::Tick;
+    use crate::content::make_some_blocks;
+    use crate::listen::Sink;
+    use crate::space::SpaceChange;
+
+    fn voxel_block_def(universe: &mut Universe, voxels: &[Block; 2]) -> URef<BlockDef> {
+        let block = Block::builder()
+            .voxels_fn(universe, 4, |cube| {
+                if cube.y == 0 {
+                    &voxels[0]
+                } else if cube.y == 1 {
+                    &voxels[1]
+                } else {
+                    &AIR
+                }
+            })
+            .unwrap()
+            .build();
+        universe.insert_anonymous(BlockDef::new(block))
+    }
+
+    #[test]
+    fn editor_contents() {
+        let mut universe = Universe::new();
+        let blocks: [Block; 2] = make_some_blocks();
+        let def_ref = voxel_block_def(&mut universe, &blocks);
+        let editor = BlockEditor::new(&def_ref).unwrap();
+
+        assert_eq!(editor.region(), Grid::for_block(4));
+        assert_eq!(editor.resolution(), 4);
+        let space = editor.space().borrow();
+        assert_eq!(
+            space.grid(),
+            Grid::for_block(4).expand(FaceMap::repeat(MARGIN))
+        );
+        assert_eq!(space[[1, 0, 2]], blocks[0]);
+        assert_eq!(space[[1, 1, 2]], blocks[1]);
+        assert_eq!(space[[1, 2, 2]], AIR);
+
+        let character = editor.character().borrow();
+        for block in &blocks {
+            assert!(character
+                .inventory()
+                .slots
+                .contains(&Tool::PlaceBlock(block.clone())));
+        }
+    }
+
+    #[test]
+    fn commit_updates_block() {
+        let mut universe = Universe::new();
+        let blocks: [Block; 2] = make_some_blocks();
+        let def_ref = voxel_block_def(&mut universe, &blocks);
+        let mut using_space = Space::empty_positive(1, 1, 1);
+        using_space
+            .set([0, 0, 0], Block::Indirect(def_ref.clone()))
+            .unwrap();
+        let mut sink = Sink::new();
+        using_space.listen(sink.listener());
+
+        let editor = BlockEditor::new(&def_ref).unwrap();
+        editor
+            .space()
+            .borrow_mut()
+            .set([3, 3, 3], &blocks[1])
+            .unwrap();
+        // Outside the region, so not copied.
+        editor
+            .space()
+            .borrow_mut()
+            .set([-1, 0, 0], &blocks[1])
+            .unwrap();
+        assert_eq!(sink.next(), None);
+        editor.commit().unwrap();
+
+        let (_, _) = using_space.step(None, Tick::arbitrary());
+        assert!(sink.take_equal(SpaceChange::BlockValue(0)));
+        let evaluated = using_space.get_evaluated([0, 0, 0]);
+        let voxels = evaluated.voxels.as_ref().unwrap();
+        assert_eq!(voxels.grid(), Grid::for_block(4));
+        assert_eq!(voxels[(3, 3, 3)].color, blocks[1].evaluate().unwrap().color);
+    }
+
+    #[test]
+    fn not_voxels() {
+        let mut universe = Universe::new();
+        let def_ref = universe.insert_anonymous(BlockDef::new(AIR));
+        assert_eq!(
+            BlockEditor::new(&def_ref).unwrap_err(),
+            BlockEditorError::NotVoxels
+        );
+    }
+}
@@ -5,17 +5,20 @@
 //! raycasting into the scene, etc.
 
 use cgmath::{
-    Deg, EuclideanSpace as _, InnerSpace as _, Matrix as _, Matrix4, Point2, Point3, SquareMatrix,
-    Transform, Vector2, Vector3,
+    Angle as _, Deg, EuclideanSpace as _, InnerSpace as _, Matrix as _, Matrix3, Matrix4, Point2,
+    Point3, SquareMatrix, Transform, Vector2, Vector3,
 };
 use itertools::Itertools as _;
 use ordered_float::NotNan;
 use std::convert::TryInto as _;
 
-use crate::math::{Aab, FreeCoordinate, Rgba};
+use crate::math::{Aab, FreeCoordinate, Rgb, Rgba};
 use crate::raycast::Ray;
 use crate::space::Grid;
 
+mod path;
+pub use path::*;
+
 type M = Matrix4<FreeCoordinate>;
 
 /// Defines a viewpoint in/of the world: a viewport (aspect ratio), projection matrix,
@@ -31,10 +34,23 @@ pub struct Camera {
     /// Caller-provided view matrix.
     view_matrix: M,
 
+    /// Horizontal displacement, in view space, of the eye from the position given by
+    /// the view matrix; nonzero only for the eyes of a [`StereoCamera`].
+    eye_offset: FreeCoordinate,
+
+    /// Distance from the eye at which the two eyes of a [`StereoCamera`] see the same
+    /// image; only meaningful if `eye_offset` is nonzero.
+    convergence_distance: FreeCoordinate,
+
     /// Projection matrix derived from viewport and options.
     /// Calculated by [`Self::compute_matrices`].
     projection: M,
 
+    /// View matrix derived from `view_matrix` and options; differs from it if the
+    /// [`ProjectionMode`] chooses the direction of view.
+    /// Calculated by [`Self::compute_matrices`].
+    effective_view_matrix: M,
+
     /// View point derived from view matrix.
     /// Calculated by [`Self::compute_matrices`].
     view_position: Point3<FreeCoordinate>,
@@ -52,9 +68,12 @@ impl Camera {
             options: options.repair(),
             viewport,
             view_matrix: M::identity(),
+            eye_offset: 0.0,
+            convergence_distance: 1.0,
 
             // Overwritten immediately by compute_matrices
             projection: M::identity(),
+            effective_view_matrix: M::identity(),
             view_position: Point3::origin(),
             inverse_projection_view: M::identity(),
             view_frustum_corners: [Point3::origin(); 8],
@@ -75,6 +94,14 @@ impl Camera {
 
     pub fn set_options(&mut self, options: GraphicsOptions) {
         self.options = options.repair();
+        self.compute_matrices();
+    }
+
+    /// Sets the eye displacement used by [`StereoCamera`], and recalculates matrices.
+    fn set_eye_offset(&mut self, eye_offset: FreeCoordinate, convergence_distance: FreeCoordinate) {
+        self.eye_offset = eye_offset;
+        self.convergence_distance = convergence_distance;
+        self.compute_matrices();
     }
 
     /// Sets the contained viewport value, and recalculates matrices to be suitable for
@@ -105,6 +132,10 @@ impl Camera {
     /// [`view_position`](Self::view_position) and,
     /// [`project_ndc_into_world`](Self::project_ndc_into_world).
     /// to determine what world coordinates are.
+    ///
+    /// If the [`ProjectionMode`] is not [`ProjectionMode::Perspective`], only the
+    /// position of the eye is taken from this matrix, and the direction of view is
+    /// determined by the mode.
     pub fn set_view_matrix(&mut self, view_matrix: M) {
         if view_matrix != self.view_matrix {
             self.view_matrix = view_matrix;
@@ -113,13 +144,20 @@ impl Camera {
     }
 
     /// Returns a projection matrix suitable for OpenGL use.
+    ///
+    /// If this camera is one eye of a [`StereoCamera`], the projection includes the
+    /// displacement of the eye from the shared [view matrix](Self::view_matrix) and is
+    /// off-center.
     pub fn projection(&self) -> M {
         self.projection
     }
 
     /// Returns a view matrix suitable for OpenGL use.
+    ///
+    /// This is the matrix given to [`Self::set_view_matrix`], unless the
+    /// [`ProjectionMode`] replaces its direction of view.
     pub fn view_matrix(&self) -> M {
-        self.view_matrix
+        self.effective_view_matrix
     }
 
     /// Returns the eye position in world coordinates, as set by [`Self::set_view_matrix()`].
@@ -130,6 +168,9 @@ impl Camera {
     /// Converts a screen position in normalized device coordinates (as produced by
     /// [`Viewport::normalize_nominal_point`]) into a ray in world space.
     /// Uses the view transformation given by [`set_view_matrix`](Self::set_view_matrix).
+    ///
+    /// With an orthographic [`ProjectionMode`], all rays are parallel, and start behind
+    /// the view position by half of the view distance.
     pub fn project_ndc_into_world(&self, ndc: Point2<FreeCoordinate>) -> Ray {
         let ndc_near = ndc.to_vec().extend(-1.0).extend(1.0);
         let ndc_far = ndc.to_vec().extend(1.0).extend(1.0);
@@ -215,13 +256,56 @@ impl Camera {
             .unwrap()
     }
 
+    /// Computes the projection matrix for [`ProjectionMode::Perspective`].
+    fn perspective_projection(&self) -> M {
+        let near = 1. / 32.; // half a voxel at resolution=16
+        let far = self.view_distance();
+        let aspect_ratio = self.viewport.nominal_aspect_ratio();
+        if self.eye_offset == 0.0 {
+            cgmath::perspective(self.fov_y(), aspect_ratio, near, far)
+        } else {
+            // Both eyes' frustums have the same cross-section at the convergence
+            // distance, so each is shifted towards the other.
+            let top = near * (self.fov_y() / 2.0).tan();
+            let half_width = top * aspect_ratio;
+            let shift = self.eye_offset * near / self.convergence_distance;
+            cgmath::frustum(
+                -half_width - shift,
+                half_width - shift,
+                -top,
+                top,
+                near,
+                far,
+            ) * M::from_translation(Vector3::new(-self.eye_offset, 0.0, 0.0))
+        }
+    }
+
+    /// Computes the projection and view matrices for an orthographic projection
+    /// looking in `direction` and showing `height` cubes vertically. Everything within
+    /// half the view distance in front of or behind the view position is visible.
+    fn orthographic_matrices(
+        &self,
+        direction: Vector3<FreeCoordinate>,
+        up: Vector3<FreeCoordinate>,
+        height: FreeCoordinate,
+    ) -> (M, M) {
+        let half_height = height / 2.0;
+        let half_width = half_height * self.viewport.nominal_aspect_ratio();
+        let half_depth = self.view_distance() / 2.0;
+        (
+            cgmath::ortho(
+                -half_width,
+                half_width,
+                -half_height,
+                half_height,
+                -half_depth,
+                half_depth,
+            ),
+            M::look_to_rh(self.view_position, direction, up),
+        )
+    }
+
     fn compute_matrices(&mut self) {
-        self.projection = cgmath::perspective(
-            self.fov_y(),
-            self.viewport.nominal_aspect_ratio(),
-            /* near: */ 1. / 32., // half a voxel at resolution=16
-            /* far: */ self.view_distance(),
-        );
         self.view_position = Point3::from_vec(
             self.view_matrix
                 .inverse_transform()
@@ -230,7 +314,24 @@ impl Camera {
                 .row(3)
                 .truncate(),
         );
-        self.inverse_projection_view = (self.projection * self.view_matrix)
+
+        let (projection, effective_view_matrix) = match self.options.projection_mode {
+            ProjectionMode::Perspective => (self.perspective_projection(), self.view_matrix),
+            ProjectionMode::TopDown { height } => self.orthographic_matrices(
+                Vector3::new(0., -1., 0.),
+                // North (-Z) is at the top of the view.
+                Vector3::new(0., 0., -1.),
+                height.into_inner(),
+            ),
+            ProjectionMode::Isometric { height } => self.orthographic_matrices(
+                Vector3::new(-1., -1., -1.),
+                Vector3::new(0., 1., 0.),
+                height.into_inner(),
+            ),
+        };
+        self.projection = projection;
+        self.effective_view_matrix = effective_view_matrix;
+        self.inverse_projection_view = (self.projection * self.effective_view_matrix)
             .inverse_transform()
             .expect("projection and view matrix was not invertible");
 
@@ -247,6 +348,138 @@ impl Camera {
     }
 }
 
+/// One of the two eyes of a [`StereoCamera`].
+#[allow(clippy::exhaustive_enums)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+impl Eye {
+    /// Both eyes, in the order in which they are usually drawn.
+    pub const ALL: [Eye; 2] = [Eye::Left, Eye::Right];
+
+    /// Direction along the view-space X axis of this eye from the center.
+    fn sign(self) -> FreeCoordinate {
+        match self {
+            Eye::Left => -1.0,
+            Eye::Right => 1.0,
+        }
+    }
+}
+
+/// A pair of [`Camera`]s for stereoscopic (e.g. VR headset) rendering: one for each
+/// [`Eye`], separated horizontally by the interpupillary distance, and sharing a single
+/// view matrix which gives the point between the eyes.
+///
+/// Each eye's projection is off-center, so that the two views coincide at the
+/// convergence distance instead of being parallel; renderers can treat each eye as an
+/// ordinary [`Camera`]. The eyes are separated only with [`ProjectionMode::Perspective`].
+///
+/// ```
+/// use all_is_cubes::camera::{Eye, GraphicsOptions, StereoCamera, Viewport};
+/// use all_is_cubes::cgmath::{Point2, Vector2};
+///
+/// let stereo = StereoCamera::new(
+///     GraphicsOptions::default(),
+///     Viewport {
+///         nominal_size: Vector2::new(10.0, 10.0),
+///         framebuffer_size: Vector2::new(10, 10),
+///     },
+/// );
+/// let left_ray = stereo.eye(Eye::Left).project_ndc_into_world(Point2::new(0.0, 0.0));
+/// let right_ray = stereo.eye(Eye::Right).project_ndc_into_world(Point2::new(0.0, 0.0));
+/// assert!(left_ray.origin.x < 0.0);
+/// assert!(right_ray.origin.x > 0.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct StereoCamera {
+    left: Camera,
+    right: Camera,
+    interpupillary_distance: FreeCoordinate,
+    convergence_distance: FreeCoordinate,
+}
+
+impl StereoCamera {
+    /// Typical human interpupillary distance, taking a cube to be one meter.
+    pub const DEFAULT_INTERPUPILLARY_DISTANCE: FreeCoordinate = 0.064;
+
+    /// Default distance at which the two eyes' views coincide.
+    pub const DEFAULT_CONVERGENCE_DISTANCE: FreeCoordinate = 2.0;
+
+    /// Constructs a [`StereoCamera`] with the default interpupillary and convergence
+    /// distances. `viewport` is the viewport of each eye.
+    pub fn new(options: GraphicsOptions, viewport: Viewport) -> Self {
+        let mut new_self = Self {
+            left: Camera::new(options.clone(), viewport),
+            right: Camera::new(options, viewport),
+            interpupillary_distance: Self::DEFAULT_INTERPUPILLARY_DISTANCE,
+            convergence_distance: Self::DEFAULT_CONVERGENCE_DISTANCE,
+        };
+        new_self.update_eyes();
+        new_self
+    }
+
+    /// Returns the camera for the given eye.
+    pub fn eye(&self, eye: Eye) -> &Camera {
+        match eye {
+            Eye::Left => &self.left,
+            Eye::Right => &self.right,
+        }
+    }
+
+    pub fn interpupillary_distance(&self) -> FreeCoordinate {
+        self.interpupillary_distance
+    }
+
+    /// Sets the distance between the eyes. Negative values are treated as zero.
+    pub fn set_interpupillary_distance(&mut self, distance: FreeCoordinate) {
+        self.interpupillary_distance = distance.max(0.0);
+        self.update_eyes();
+    }
+
+    pub fn convergence_distance(&self) -> FreeCoordinate {
+        self.convergence_distance
+    }
+
+    /// Sets the distance from the eyes at which their views coincide; objects nearer
+    /// than this appear in front of the display, and farther ones behind it.
+    /// Values smaller than the near plane are increased to it.
+    pub fn set_convergence_distance(&mut self, distance: FreeCoordinate) {
+        self.convergence_distance = distance.max(1. / 32.);
+        self.update_eyes();
+    }
+
+    /// Sets the options of both eyes, as by [`Camera::set_options`].
+    pub fn set_options(&mut self, options: GraphicsOptions) {
+        self.left.set_options(options.clone());
+        self.right.set_options(options);
+    }
+
+    /// Sets the viewport of each eye, as by [`Camera::set_viewport`].
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.left.set_viewport(viewport);
+        self.right.set_viewport(viewport);
+    }
+
+    /// Sets the view matrix shared by both eyes, whose origin is the point midway
+    /// between them, as by [`Camera::set_view_matrix`].
+    pub fn set_view_matrix(&mut self, view_matrix: M) {
+        self.left.set_view_matrix(view_matrix);
+        self.right.set_view_matrix(view_matrix);
+    }
+
+    fn update_eyes(&mut self) {
+        let half = self.interpupillary_distance / 2.0;
+        let convergence = self.convergence_distance;
+        self.left
+            .set_eye_offset(Eye::Left.sign() * half, convergence);
+        self.right
+            .set_eye_offset(Eye::Right.sign() * half, convergence);
+    }
+}
+
 /// Viewport dimensions for rendering and UI layout with the correct resolution and
 /// aspect ratio.
 #[allow(clippy::exhaustive_structs)]
@@ -308,6 +541,114 @@ impl Viewport {
     // invertible transform.
 }
 
+/// A rectangular part of a [`Viewport`], expressed in fractions of its size with the
+/// origin at the top left; used to draw several views into one frame, such as the two
+/// eyes of a [`StereoCamera`], or a map inset over the main view.
+///
+/// ```
+/// use all_is_cubes::camera::{Viewport, ViewportRect};
+/// use all_is_cubes::cgmath::{Point2, Vector2};
+///
+/// let viewport = Viewport {
+///     nominal_size: Vector2::new(100.0, 50.0),
+///     framebuffer_size: Vector2::new(200, 100),
+/// };
+/// // A quarter-size inset in the top right corner.
+/// let inset = ViewportRect::new(0.75, 0.0, 0.25, 0.25);
+/// assert_eq!(
+///     inset.framebuffer_bounds(viewport),
+///     (Point2::new(150, 0), Vector2::new(50, 25)),
+/// );
+/// assert_eq!(inset.viewport_within(viewport).nominal_size, Vector2::new(25.0, 12.5));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ViewportRect {
+    /// Distance of the left edge from the left edge of the viewport.
+    pub left: FreeCoordinate,
+    /// Distance of the top edge from the top edge of the viewport.
+    pub top: FreeCoordinate,
+    pub width: FreeCoordinate,
+    pub height: FreeCoordinate,
+}
+
+impl ViewportRect {
+    /// The entire viewport.
+    pub const FULL: Self = Self {
+        left: 0.0,
+        top: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    pub const fn new(
+        left: FreeCoordinate,
+        top: FreeCoordinate,
+        width: FreeCoordinate,
+        height: FreeCoordinate,
+    ) -> Self {
+        Self {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    /// Divides this rectangle into `count` equal columns, ordered from left to right.
+    pub fn columns(self, count: usize) -> Vec<Self> {
+        let width = self.width / count as FreeCoordinate;
+        (0..count)
+            .map(|i| Self {
+                left: self.left + width * i as FreeCoordinate,
+                width,
+                ..self
+            })
+            .collect()
+    }
+
+    /// Converts `inner`, which is expressed as a part of this rectangle, to be a part of
+    /// the whole viewport instead.
+    pub fn sub_rect(self, inner: Self) -> Self {
+        Self {
+            left: self.left + inner.left * self.width,
+            top: self.top + inner.top * self.height,
+            width: inner.width * self.width,
+            height: inner.height * self.height,
+        }
+    }
+
+    /// Returns the position of the top left corner and the size, in pixels, of this
+    /// rectangle in the framebuffer of `viewport`.
+    ///
+    /// Each edge is rounded to the nearest pixel boundary, so rectangles which share an
+    /// edge, such as those from [`Self::columns`], cover the framebuffer without gaps or
+    /// overlaps. Parts outside the viewport are excluded.
+    pub fn framebuffer_bounds(self, viewport: Viewport) -> (Point2<u32>, Vector2<u32>) {
+        fn edges(start: FreeCoordinate, length: FreeCoordinate, size: u32) -> (u32, u32) {
+            let size = FreeCoordinate::from(size);
+            let to_pixel = |f: FreeCoordinate| (f * size).round().max(0.0).min(size) as u32;
+            let low = to_pixel(start);
+            (low, to_pixel(start + length).max(low) - low)
+        }
+        let (x, width) = edges(self.left, self.width, viewport.framebuffer_size.x);
+        let (y, height) = edges(self.top, self.height, viewport.framebuffer_size.y);
+        (Point2::new(x, y), Vector2::new(width, height))
+    }
+
+    /// Returns the [`Viewport`] of a camera which is to draw into this rectangle of
+    /// `viewport`.
+    pub fn viewport_within(self, viewport: Viewport) -> Viewport {
+        Viewport {
+            nominal_size: Vector2::new(
+                viewport.nominal_size.x * self.width,
+                viewport.nominal_size.y * self.height,
+            ),
+            framebuffer_size: self.framebuffer_bounds(viewport).1,
+        }
+    }
+}
+
 /// User/debug options for rendering (i.e. not affecting gameplay except informationally).
 /// Not all of these options are applicable to all renderers.
 ///
@@ -317,16 +658,16 @@ impl Viewport {
 #[non_exhaustive]
 pub struct GraphicsOptions {
     /// Whether and how to draw fog obscuring the view distance limit.
-    ///
-    /// TODO: Implement fog in raytracer.
     pub fog: FogOption,
 
+    /// How the world is projected onto the screen.
+    pub projection_mode: ProjectionMode,
+
     /// Field of view, in degrees from top to bottom edge of the viewport.
+    /// Applies only to [`ProjectionMode::Perspective`].
     pub fov_y: NotNan<FreeCoordinate>,
 
     /// Distance, in unit cubes, from the camera to the farthest visible point.
-    ///
-    /// TODO: Implement view distance limit (and fog) in raytracer.
     pub view_distance: NotNan<FreeCoordinate>,
 
     /// Style in which to draw the lighting of [`Space`](crate::space::Space)s.
@@ -336,7 +677,25 @@ pub struct GraphicsOptions {
     /// Method/fidelity to use for transparency.
     pub transparency: TransparencyOption,
 
-    /// Number of space chunks (16³ groups of blocks) to redraw if needed, per frame.
+    /// Adjustment of all colors drawn, to make them easier to distinguish.
+    pub color_filter: ColorFilter,
+
+    /// How the GPU renderer should draw the world.
+    ///
+    /// Does not apply to the CPU raytracer, or to the user interface.
+    pub render_method: RenderMethod,
+
+    /// Whether the GPU renderer should draw blocks which are plain opaque cubes (a single
+    /// color and no voxels) by instancing a single cube face, instead of including
+    /// them in the chunk meshes. This uses less memory for scenes with much plain
+    /// terrain, but may be slower to draw.
+    ///
+    /// Does not apply to raytracing.
+    pub instance_whole_cubes: bool,
+
+    /// Number of space chunks (16³ groups of blocks) to be triangulated at once, and
+    /// hence the most whose meshes are replaced per frame. Triangulation is done on a
+    /// background thread where possible.
     ///
     /// Does not apply to raytracing.
     pub chunks_per_frame: u16,
@@ -347,6 +706,14 @@ pub struct GraphicsOptions {
     /// visible effects.
     pub use_frustum_culling: bool,
 
+    /// Approximate limit, in bytes, on the GPU memory used for chunk meshes and block
+    /// textures for each [`Space`](crate::space::Space) drawn. When it is exceeded, the
+    /// meshes of chunks which have not been in view recently, and the textures of blocks
+    /// no longer present in the space, are discarded, to be recomputed if needed again.
+    ///
+    /// [`None`] means no limit. Does not apply to raytracing.
+    pub gpu_memory_budget: Option<usize>,
+
     /// Draw boxes around chunk borders and some debug info.
     pub debug_chunk_boxes: bool,
 
@@ -368,6 +735,12 @@ impl GraphicsOptions {
             .view_distance
             .max(NotNan::new(1.0).unwrap())
             .min(NotNan::new(10000.0).unwrap());
+        match &mut self.projection_mode {
+            ProjectionMode::Perspective => {}
+            ProjectionMode::TopDown { height } | ProjectionMode::Isometric { height } => {
+                *height = (*height).max(NotNan::new(1.0).unwrap());
+            }
+        }
         self
     }
 }
@@ -376,12 +749,17 @@ impl Default for GraphicsOptions {
     fn default() -> Self {
         Self {
             fog: FogOption::Compromise,
+            projection_mode: ProjectionMode::Perspective,
             fov_y: NotNan::new(90.).unwrap(),
             view_distance: NotNan::new(200.).unwrap(),
             lighting_display: LightingOption::Flat,
             transparency: TransparencyOption::Volumetric,
+            color_filter: ColorFilter::None,
+            render_method: RenderMethod::Mesh,
+            instance_whole_cubes: false,
             chunks_per_frame: 4,
             use_frustum_culling: true,
+            gpu_memory_budget: Some(256 * 1024 * 1024),
             debug_chunk_boxes: false,
             debug_collision_boxes: false,
             debug_light_rays_at_cursor: false,
@@ -389,6 +767,30 @@ impl Default for GraphicsOptions {
     }
 }
 
+/// How a [`Camera`] projects the world onto the screen; part of a [`GraphicsOptions`].
+///
+/// The orthographic modes are suited to maps and schematic pictures. They take only the
+/// position of the eye from the [view matrix](Camera::set_view_matrix), and choose the
+/// direction themselves.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[non_exhaustive]
+pub enum ProjectionMode {
+    /// Perspective projection, in which farther things look smaller, with the field of
+    /// view given by [`GraphicsOptions::fov_y`].
+    Perspective,
+    /// Orthographic projection looking straight down, with north (-Z) at the top.
+    TopDown {
+        /// Number of cubes visible from the top to the bottom of the viewport.
+        height: NotNan<FreeCoordinate>,
+    },
+    /// Orthographic projection looking diagonally down, towards -X, -Y, and -Z, so that
+    /// the three axes look equally long.
+    Isometric {
+        /// Number of cubes' lengths visible from the top to the bottom of the viewport.
+        height: NotNan<FreeCoordinate>,
+    },
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 #[non_exhaustive]
 pub enum FogOption {
@@ -402,6 +804,84 @@ pub enum FogOption {
     Physical,
 }
 
+/// How to draw a [`Space`](crate::space::Space) on the GPU; part of a
+/// [`GraphicsOptions`].
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[non_exhaustive]
+pub enum RenderMethod {
+    /// Convert blocks to triangle meshes and rasterize them.
+    Mesh,
+    /// Trace a ray for every pixel in a shader, following the same rules as
+    /// [`SpaceRaytracer`](crate::raytracer::SpaceRaytracer).
+    ///
+    /// TODO: Recursive blocks are drawn as solid cubes of their average color, and
+    /// [`LightingOption::Smooth`] is drawn as [`LightingOption::Flat`].
+    Raytrace,
+}
+
+/// The fog equation parameters implied by a [`GraphicsOptions`], shared by all
+/// renderers so that they draw the same fog.
+///
+/// The mesh renderer receives these as shader uniforms; the GLSL version of
+/// [`FogParameters::fog_mix`] is in `lum/shaders/common.glsl`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct FogParameters {
+    /// Blending between fog equations: 0 is exponential (constant density) fog and 1
+    /// is fog which is thin nearby and then abruptly thickens near `distance`.
+    pub mode_blend: f32,
+    /// Distance from the eye at which the fog becomes fully opaque.
+    /// Infinite if there is no fog.
+    pub distance: f32,
+}
+
+impl FogParameters {
+    /// Density of the exponential fog, in units of [`Self::distance`].
+    const DENSITY: f32 = 1.6;
+
+    /// Computes the fog parameters for the given options.
+    pub fn new(options: &GraphicsOptions) -> Self {
+        let view_distance = options.view_distance.into_inner() as f32;
+        let (mode_blend, distance) = match options.fog {
+            FogOption::None => (0.0, f32::INFINITY),
+            FogOption::Abrupt => (1.0, view_distance),
+            FogOption::Compromise => (0.5, view_distance),
+            FogOption::Physical => (0.0, view_distance),
+        };
+        Self {
+            mode_blend,
+            distance,
+        }
+    }
+
+    /// Returns the fraction, from 0 to 1, of the color of a surface at `distance` from
+    /// the eye which should be replaced with the fog color.
+    ///
+    /// ```
+    /// use all_is_cubes::camera::{FogOption, FogParameters, GraphicsOptions};
+    ///
+    /// let mut options = GraphicsOptions::default();
+    /// options.fog = FogOption::Physical;
+    /// let fog = FogParameters::new(&options);
+    /// assert_eq!(fog.fog_mix(0.0), 0.0);
+    /// assert!(fog.fog_mix(options.view_distance.into_inner() as f32) >= 0.999);
+    ///
+    /// options.fog = FogOption::None;
+    /// assert_eq!(FogParameters::new(&options).fog_mix(1e6), 0.0);
+    /// ```
+    pub fn fog_mix(&self, distance: f32) -> f32 {
+        fn exponential(d: f32) -> f32 {
+            1.0 - (-FogParameters::DENSITY * d).exp()
+        }
+        // Distance in range 0 (eye) to 1 (fully fogged).
+        let d = distance / self.distance;
+        // Rescaled so that it reaches 1 at d = 1.
+        let exp_fudged = exponential(d) / exponential(1.0);
+        let combo = exp_fudged * (1.0 - self.mode_blend) + d.powi(4) * self.mode_blend;
+        combo.max(0.0).min(1.0)
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 #[non_exhaustive]
 pub enum LightingOption {
@@ -458,6 +938,103 @@ impl TransparencyOption {
     }
 }
 
+/// Adjustment of all colors drawn, for users with impaired color vision; part of a
+/// [`GraphicsOptions`].
+///
+/// Every renderer applies the same transformation, given by [`ColorFilter::matrix`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, serde::Deserialize, serde::Serialize)]
+#[non_exhaustive]
+pub enum ColorFilter {
+    /// Colors are drawn as they are.
+    None,
+    /// Red–green differences, which are hard to see with protanopia (missing long-wavelength
+    /// cones), are shifted into lightness and blue–yellow differences.
+    Protanopia,
+    /// Red–green differences, which are hard to see with deuteranopia (missing
+    /// medium-wavelength cones), are shifted into lightness and blue–yellow differences.
+    Deuteranopia,
+    /// Blue–yellow differences, which are hard to see with tritanopia (missing
+    /// short-wavelength cones), are shifted into red–green differences.
+    Tritanopia,
+    /// Differences in lightness are exaggerated.
+    HighContrast,
+}
+
+impl ColorFilter {
+    /// Returns the affine transformation, applied to linear RGB colors extended with
+    /// a 1 component, that this filter performs.
+    pub fn matrix(self) -> Matrix4<f32> {
+        // Daltonization: compute what a viewer with the deficiency would see (in the LMS
+        // cone color space, with one cone type's response guessed from the other two),
+        // and add the difference they cannot see to the channels they can.
+        // Matrices are from Fidaner, Lin, and Ozguven, “Analysis of Color Blindness”.
+        // They are written transposed because `Matrix3::new` takes columns.
+        fn daltonize(lms_simulation: Matrix3<f32>) -> Matrix4<f32> {
+            let rgb_to_lms = Matrix3::new(
+                17.8824, 3.45565, 0.0299566, //
+                43.5161, 27.1554, 0.184309, //
+                4.11935, 3.86714, 1.46709,
+            );
+            let lms_to_rgb = rgb_to_lms.invert().unwrap();
+            let error_shift = Matrix3::new(
+                0.0, 0.7, 0.7, //
+                0.0, 1.0, 0.0, //
+                0.0, 0.0, 1.0,
+            );
+            let identity = Matrix3::identity();
+            let simulation = lms_to_rgb * lms_simulation * rgb_to_lms;
+            Matrix4::from(identity + error_shift * (identity - simulation))
+        }
+
+        match self {
+            ColorFilter::None => Matrix4::identity(),
+            ColorFilter::Protanopia => daltonize(Matrix3::new(
+                0.0, 0.0, 0.0, //
+                2.02344, 1.0, 0.0, //
+                -2.52581, 0.0, 1.0,
+            )),
+            ColorFilter::Deuteranopia => daltonize(Matrix3::new(
+                1.0, 0.494207, 0.0, //
+                0.0, 0.0, 0.0, //
+                0.0, 1.24827, 1.0,
+            )),
+            ColorFilter::Tritanopia => daltonize(Matrix3::new(
+                1.0, 0.0, -0.395913, //
+                0.0, 1.0, 0.801109, //
+                0.0, 0.0, 0.0,
+            )),
+            ColorFilter::HighContrast => {
+                // Scale around a middle gray (in linear terms), then offset to keep it fixed.
+                const CONTRAST: f32 = 1.6;
+                const PIVOT: f32 = 0.2;
+                let mut m = Matrix4::from_scale(CONTRAST);
+                let offset = PIVOT * (1.0 - CONTRAST);
+                m.w = Vector3::new(offset, offset, offset).extend(1.0);
+                m
+            }
+        }
+    }
+
+    /// Applies this filter to `color`. Negative results are clamped to zero.
+    ///
+    /// ```
+    /// use all_is_cubes::camera::ColorFilter;
+    /// use all_is_cubes::math::Rgb;
+    ///
+    /// let grey = Rgb::new(0.5, 0.5, 0.5);
+    /// assert_eq!(ColorFilter::None.apply(grey), grey);
+    /// let filtered = ColorFilter::Deuteranopia.apply(grey);
+    /// assert!((filtered.red().into_inner() - 0.5).abs() < 0.01);
+    /// ```
+    pub fn apply(self, color: Rgb) -> Rgb {
+        if self == ColorFilter::None {
+            return color;
+        }
+        let v = self.matrix() * Vector3::<f32>::from(color).extend(1.0);
+        Rgb::new(v.x.max(0.0), v.y.max(0.0), v.z.max(0.0))
+    }
+}
+
 /// Calculate an “eye position” (camera position) to view the entire given `grid`.
 ///
 /// `direction` points in the direction the camera should be relative to the space.
@@ -481,6 +1058,40 @@ mod tests {
         framebuffer_size: Vector2::new(2, 2),
     };
 
+    #[test]
+    fn fog_mix_is_monotonic_and_complete() {
+        for fog in [
+            FogOption::Abrupt,
+            FogOption::Compromise,
+            FogOption::Physical,
+        ]
+        .iter()
+        {
+            let mut options = GraphicsOptions::default();
+            options.fog = fog.clone();
+            let params = FogParameters::new(&options);
+            let mut previous = 0.0;
+            for i in 0..=20 {
+                let mix = params.fog_mix(params.distance * i as f32 / 20.0);
+                assert!(
+                    mix >= previous,
+                    "{:?} at {}: {} < {}",
+                    fog,
+                    i,
+                    mix,
+                    previous
+                );
+                previous = mix;
+            }
+            assert!(
+                (previous - 1.0).abs() < 1e-6,
+                "{:?} ends at {}",
+                fog,
+                previous
+            );
+        }
+    }
+
     #[test]
     fn camera_bad_viewport_doesnt_panic() {
         Camera::new(
@@ -492,6 +1103,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stereo_eyes_converge() {
+        let mut stereo = StereoCamera::new(GraphicsOptions::default(), DUMMY_VIEWPORT);
+        stereo.set_interpupillary_distance(0.5);
+        stereo.set_convergence_distance(4.0);
+        stereo.set_view_matrix(Matrix4::from_translation(Vector3::new(0.0, 0.0, -10.0)));
+        for &eye in Eye::ALL.iter() {
+            let camera = stereo.eye(eye);
+            assert_eq!(camera.view_position(), Point3::new(0.0, 0.0, 10.0));
+            // The ray through the center of each eye's view starts from that eye and
+            // crosses the center line at the convergence distance.
+            let ray = camera.project_ndc_into_world(Point2::new(0.0, 0.0));
+            assert_eq!(ray.origin.x.signum(), eye.sign(), "{:?}", ray);
+            let t = (6.0 - ray.origin.z) / ray.direction.z;
+            let crossing = ray.origin + ray.direction * t;
+            assert!(crossing.x.abs() < 1e-9, "{:?} {:?}", eye, crossing);
+        }
+    }
+
+    #[test]
+    fn stereo_zero_distance_is_mono() {
+        let mono = Camera::new(GraphicsOptions::default(), DUMMY_VIEWPORT);
+        let mut stereo = StereoCamera::new(GraphicsOptions::default(), DUMMY_VIEWPORT);
+        stereo.set_interpupillary_distance(0.0);
+        assert_eq!(stereo.eye(Eye::Left).projection(), mono.projection());
+        assert_eq!(stereo.eye(Eye::Right).projection(), mono.projection());
+    }
+
+    #[test]
+    fn viewport_rect_columns_tile_framebuffer() {
+        let viewport = Viewport {
+            nominal_size: Vector2::new(10.0, 4.0),
+            framebuffer_size: Vector2::new(10, 4),
+        };
+        let bounds: Vec<(Point2<u32>, Vector2<u32>)> = ViewportRect::FULL
+            .columns(3)
+            .into_iter()
+            .map(|rect| rect.framebuffer_bounds(viewport))
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![
+                (Point2::new(0, 0), Vector2::new(3, 4)),
+                (Point2::new(3, 0), Vector2::new(4, 4)),
+                (Point2::new(7, 0), Vector2::new(3, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn viewport_rect_sub_rect() {
+        let right_half = ViewportRect::FULL.columns(2)[1];
+        assert_eq!(
+            right_half.sub_rect(ViewportRect::new(0.5, 0.5, 0.5, 0.5)),
+            ViewportRect::new(0.75, 0.5, 0.25, 0.5)
+        );
+    }
+
+    #[test]
+    fn top_down_projection() {
+        let mut options = GraphicsOptions::default();
+        options.projection_mode = ProjectionMode::TopDown {
+            height: NotNan::new(10.0).unwrap(),
+        };
+        let mut camera = Camera::new(options, DUMMY_VIEWPORT);
+        let pos = Point3::new(1.0, 2.0, 3.0);
+        // The direction given by the view matrix is ignored.
+        camera.set_view_matrix(
+            Matrix4::from_angle_y(Deg(30.0)) * Matrix4::from_translation(-pos.to_vec()),
+        );
+        assert!((camera.view_position() - pos).magnitude() < 1e-9);
+
+        let center = camera.project_ndc_into_world(Point2::new(0.0, 0.0));
+        assert!((center.origin - Point3::new(1.0, 102.0, 3.0)).magnitude() < 1e-9);
+        assert!((center.direction.normalize() - Vector3::new(0.0, -1.0, 0.0)).magnitude() < 1e-9);
+        // Rays are parallel, and the top of the view is north.
+        let top = camera.project_ndc_into_world(Point2::new(0.0, 1.0));
+        assert!((top.origin - Point3::new(1.0, 102.0, -2.0)).magnitude() < 1e-9);
+        assert!((top.direction - center.direction).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn isometric_projection() {
+        let mut options = GraphicsOptions::default();
+        options.projection_mode = ProjectionMode::Isometric {
+            height: NotNan::new(10.0).unwrap(),
+        };
+        let camera = Camera::new(options, DUMMY_VIEWPORT);
+        let direction = camera
+            .project_ndc_into_world(Point2::new(0.5, -0.5))
+            .direction
+            .normalize();
+        let expected = Vector3::new(-1.0, -1.0, -1.0).normalize();
+        assert!((direction - expected).magnitude() < 1e-9, "{:?}", direction);
+    }
+
     #[test]
     fn camera_view_position() {
         let mut camera = Camera::new(GraphicsOptions::default(), DUMMY_VIEWPORT);
@@ -0,0 +1,256 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`CameraPath`], keyframed movement of a viewpoint over time.
+
+use cgmath::{Deg, EuclideanSpace as _, Matrix4, Point3};
+use std::time::Duration;
+
+use crate::math::{smoothstep, FreeCoordinate};
+
+/// How a [`CameraPath`] moves between one [`CameraKeyframe`] and the next.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Easing {
+    /// Constant speed from start to end.
+    Linear,
+    /// Starting from rest, speeding up, and slowing to rest again at the end.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the fraction of time elapsed, from 0 to 1, to the fraction of the distance
+    /// covered.
+    fn apply(self, t: FreeCoordinate) -> FreeCoordinate {
+        match self {
+            Easing::Linear => t.max(0.0).min(1.0),
+            Easing::EaseInOut => smoothstep(t),
+        }
+    }
+}
+
+/// A position and look direction of a camera.
+///
+/// The direction is expressed in the same way as that of a
+/// [`Body`](crate::physics::Body), so a pose may be copied from a
+/// [`Character`](crate::character::Character).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct CameraPose {
+    /// Position of the eye.
+    pub position: Point3<FreeCoordinate>,
+    /// Degrees clockwise from looking towards -Z.
+    pub yaw: FreeCoordinate,
+    /// Degrees downward from looking horizontally.
+    pub pitch: FreeCoordinate,
+}
+
+impl CameraPose {
+    pub fn new(
+        position: impl Into<Point3<FreeCoordinate>>,
+        yaw: FreeCoordinate,
+        pitch: FreeCoordinate,
+    ) -> Self {
+        Self {
+            position: position.into(),
+            yaw,
+            pitch,
+        }
+    }
+
+    /// Constructs a pose at `position` looking directly towards `target`.
+    pub fn looking_at(
+        position: impl Into<Point3<FreeCoordinate>>,
+        target: impl Into<Point3<FreeCoordinate>>,
+    ) -> Self {
+        let position = position.into();
+        let direction = target.into() - position;
+        let horizontal_distance = direction.x.hypot(direction.z);
+        Self {
+            position,
+            yaw: (180.0 - (direction.x).atan2(direction.z).to_degrees()).rem_euclid(360.0),
+            pitch: -(direction.y).atan2(horizontal_distance).to_degrees(),
+        }
+    }
+
+    /// Computes the view matrix for this pose, suitable for
+    /// [`Camera::set_view_matrix`](super::Camera::set_view_matrix).
+    pub fn view_matrix(&self) -> Matrix4<FreeCoordinate> {
+        Matrix4::from_angle_x(Deg(self.pitch))
+            * Matrix4::from_angle_y(Deg(self.yaw))
+            * Matrix4::from_translation(-(self.position.to_vec()))
+    }
+
+    /// Interpolates between `self` and `other`, turning the shorter way around.
+    fn lerp(&self, other: &Self, t: FreeCoordinate) -> Self {
+        let yaw_change = (other.yaw - self.yaw + 180.0).rem_euclid(360.0) - 180.0;
+        Self {
+            position: self.position + (other.position - self.position) * t,
+            yaw: (self.yaw + yaw_change * t).rem_euclid(360.0),
+            pitch: self.pitch + (other.pitch - self.pitch) * t,
+        }
+    }
+}
+
+/// The [`CameraPose`] at a particular time in a [`CameraPath`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct CameraKeyframe {
+    /// Time since the start of the path.
+    pub time: Duration,
+    pub pose: CameraPose,
+    /// How the camera moves from this keyframe to the next one.
+    pub easing: Easing,
+}
+
+impl CameraKeyframe {
+    /// Constructs a keyframe with [`Easing::Linear`].
+    pub fn new(time: Duration, pose: CameraPose) -> Self {
+        Self {
+            time,
+            pose,
+            easing: Easing::Linear,
+        }
+    }
+
+    #[must_use]
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+}
+
+/// A movement of a camera over time, specified by [`CameraKeyframe`]s between which
+/// the pose is interpolated; for example, for a flyover of a world, which may be
+/// rendered with [`render_camera_path`](crate::raytracer::render_camera_path).
+///
+/// ```
+/// use all_is_cubes::camera::{CameraKeyframe, CameraPath, CameraPose, Easing};
+/// use std::time::Duration;
+///
+/// let path = CameraPath::new()
+///     .with_keyframe(
+///         CameraKeyframe::new(Duration::ZERO, CameraPose::new([0.0, 10.0, 0.0], 0.0, 0.0))
+///             .with_easing(Easing::EaseInOut),
+///     )
+///     .with_keyframe(CameraKeyframe::new(
+///         Duration::from_secs(4),
+///         CameraPose::new([20.0, 10.0, 0.0], 90.0, 0.0),
+///     ));
+/// assert_eq!(path.duration(), Duration::from_secs(4));
+///
+/// let halfway = path.pose(Duration::from_secs(2)).unwrap();
+/// assert_eq!(halfway.position.x, 10.0);
+/// assert_eq!(halfway.yaw, 45.0);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CameraPath {
+    /// Sorted by time.
+    keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    /// Constructs a path with no keyframes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a keyframe. Keyframes may be added in any order.
+    #[must_use]
+    pub fn with_keyframe(mut self, keyframe: CameraKeyframe) -> Self {
+        let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+        self
+    }
+
+    /// Returns the keyframes, sorted by time.
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    /// Returns the time of the last keyframe, after which the camera does not move.
+    pub fn duration(&self) -> Duration {
+        self.keyframes
+            .last()
+            .map_or(Duration::ZERO, |keyframe| keyframe.time)
+    }
+
+    /// Returns the pose of the camera at `time` since the start of the path, or
+    /// [`None`] if there are no keyframes. Before the first keyframe and after the last,
+    /// the camera is at that keyframe's pose.
+    pub fn pose(&self, time: Duration) -> Option<CameraPose> {
+        let next_index = self.keyframes.partition_point(|k| k.time <= time);
+        if next_index == 0 {
+            return self.keyframes.first().map(|k| k.pose);
+        }
+        let previous = &self.keyframes[next_index - 1];
+        let next = match self.keyframes.get(next_index) {
+            Some(next) => next,
+            None => return Some(previous.pose),
+        };
+        let span = (next.time - previous.time).as_secs_f64();
+        let t = (time - previous.time).as_secs_f64() / span;
+        Some(previous.pose.lerp(&next.pose, previous.easing.apply(t)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{InnerSpace as _, Transform as _};
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn pose_outside_and_between_keyframes() {
+        let a = CameraPose::new([0.0, 0.0, 0.0], 350.0, 0.0);
+        let b = CameraPose::new([4.0, 0.0, 0.0], 10.0, 40.0);
+        let path = CameraPath::new()
+            .with_keyframe(CameraKeyframe::new(secs(3), b))
+            .with_keyframe(CameraKeyframe::new(secs(1), a));
+        assert_eq!(CameraPath::new().pose(secs(0)), None);
+        assert_eq!(path.pose(secs(0)), Some(a));
+        assert_eq!(path.pose(secs(1)), Some(a));
+        assert_eq!(path.pose(secs(5)), Some(b));
+
+        let middle = path.pose(secs(2)).unwrap();
+        assert_eq!(middle.position, Point3::new(2.0, 0.0, 0.0));
+        // Turns through 0 rather than the long way around.
+        assert_eq!(middle.yaw, 0.0);
+        assert_eq!(middle.pitch, 20.0);
+    }
+
+    #[test]
+    fn easing() {
+        let path = CameraPath::new()
+            .with_keyframe(
+                CameraKeyframe::new(secs(0), CameraPose::new([0.0, 0.0, 0.0], 0.0, 0.0))
+                    .with_easing(Easing::EaseInOut),
+            )
+            .with_keyframe(CameraKeyframe::new(
+                secs(4),
+                CameraPose::new([8.0, 0.0, 0.0], 0.0, 0.0),
+            ));
+        let x = |s: u64| path.pose(secs(s)).unwrap().position.x;
+        assert_eq!(x(2), 4.0);
+        // Slower than linear at the start.
+        assert!(x(1) < 2.0, "{}", x(1));
+    }
+
+    #[test]
+    fn looking_at_view_matrix() {
+        let pose = CameraPose::looking_at([1.0, 2.0, 3.0], [1.0, 2.0, 10.0]);
+        let view = pose.view_matrix();
+        // The eye is at the origin of view space, and the target is straight ahead.
+        let eye = view.transform_point(Point3::new(1.0, 2.0, 3.0));
+        assert!(eye.to_vec().magnitude() < 1e-9, "{:?}", eye);
+        let target = view.transform_point(Point3::new(1.0, 2.0, 10.0));
+        assert!(
+            (target - Point3::new(0.0, 0.0, -7.0)).magnitude() < 1e-9,
+            "{:?}",
+            target
+        );
+    }
+}
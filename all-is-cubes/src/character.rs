@@ -3,9 +3,7 @@
 
 //! Player-character stuff.
 
-use cgmath::{
-    Deg, ElementWise as _, EuclideanSpace as _, InnerSpace as _, Matrix3, Matrix4, Point3, Vector3,
-};
+use cgmath::{Deg, EuclideanSpace as _, InnerSpace as _, Matrix3, Matrix4, Point3, Vector3};
 use num_traits::identities::Zero;
 use ordered_float::NotNan;
 use std::collections::HashSet;
@@ -14,24 +12,28 @@ use std::fmt;
 
 use crate::apps::Tick;
 use crate::behavior::{Behavior, BehaviorSet, BehaviorSetTransaction};
-use crate::block::{recursive_raycast, Block, EvaluatedBlock};
+use crate::block::{recursive_raycast, Block, BlockDef, EvaluatedBlock, AIR};
 use crate::camera::eye_for_look_at;
+use crate::item_drop::ItemDrop;
+use crate::linking::BlockProvider;
 use crate::listen::{Listener, Notifier};
-use crate::math::{Aab, Face, FreeCoordinate};
+use crate::math::{Aab, Face, FreeCoordinate, GridPoint, SeededRng};
+use crate::mining::{BreakingProgress, CrackStage};
 use crate::physics::{Body, BodyTransaction, Contact};
 use crate::raycast::{CubeFace, Ray};
-use crate::space::{Grid, PackedLight, Space};
+use crate::space::{Grid, PackedLight, Space, SpaceTransaction};
 use crate::tools::{Inventory, InventoryChange, InventoryTransaction, Tool, ToolError};
 use crate::transactions::{
     PreconditionFailed, Transaction, TransactionConflict, Transactional, UniverseTransaction,
 };
-use crate::universe::URef;
+use crate::universe::{Bookmark, Name, RefError, URef};
 use crate::util::{ConciseDebug, CustomFormat, StatusText};
 
+mod controller;
+pub use controller::*;
+
 // Control characteristics.
-const WALKING_SPEED: FreeCoordinate = 4.0;
-const FLYING_SPEED: FreeCoordinate = 10.0;
-const JUMP_SPEED: FreeCoordinate = 8.0;
+const TOSS_SPEED: FreeCoordinate = 6.0;
 
 /// A `Character`:
 ///
@@ -48,10 +50,16 @@ pub struct Character {
     /// Refers to the [`Space`] to be viewed and collided with.
     pub space: URef<Space>,
 
+    /// How the character responds to [`Self::set_velocity_input`] and jumping.
+    pub controller: CharacterController,
+
     /// Velocity specified by user input, which the actual velocity is smoothly adjusted
     /// towards.
     velocity_input: Vector3<FreeCoordinate>,
 
+    /// Modifies the speed requested by [`Self::velocity_input`].
+    gait: Gait,
+
     // TODO: Does this belong here? Or in the Space?
     pub(crate) colliding_cubes: HashSet<Contact>,
 
@@ -61,6 +69,9 @@ pub struct Character {
     /// Indices into [`Self::inventory`] slots.
     selected_slots: [usize; 3],
 
+    /// The block currently being broken by [`Self::continue_breaking`], if any.
+    breaking: Option<BreakingProgress>,
+
     /// Notifier for modifications.
     notifier: Notifier<CharacterChange>,
 
@@ -76,8 +87,10 @@ impl std::fmt::Debug for Character {
                 "velocity_input",
                 &self.velocity_input.custom_format(ConciseDebug),
             )
+            .field("gait", &self.gait)
             .field("colliding_cubes", &self.colliding_cubes)
             .field("inventory", &self.inventory)
+            .field("breaking", &self.breaking)
             .field("behaviors", &self.behaviors)
             .finish()
     }
@@ -114,16 +127,22 @@ impl Character {
         Self {
             body: Body {
                 flying: spawn.flying,
+                // Collision is currently at the resolution of whole cubes, so any
+                // smaller step height would never take effect.
+                step_height: 1.0,
                 ..Body::new_minimal(
                     spawn.position.map(|s| s.into_inner()),
                     Aab::new(-0.35, 0.35, -1.75, 0.15, -0.35, 0.35),
                 )
             },
             space,
+            controller: CharacterController::default(),
             velocity_input: Vector3::zero(),
+            gait: Gait::Normal,
             colliding_cubes: HashSet::new(),
             inventory: Inventory::from_items(inventory),
             selected_slots: [10, 1, 11],
+            breaking: None,
             notifier: Notifier::new(),
             behaviors: BehaviorSet::new(),
         }
@@ -147,6 +166,24 @@ impl Character {
             * Matrix4::from_translation(-(self.body.position.to_vec()))
     }
 
+    /// Moves this character to the space and pose of `bookmark`, stopping it.
+    ///
+    /// If the character would then be stuck inside solid blocks, it is instead put at
+    /// the nearest clear position, as by [`Body::teleport`].
+    ///
+    /// Returns an error, and does not move the character, if the bookmark's space cannot
+    /// be borrowed.
+    pub fn teleport(&mut self, bookmark: &Bookmark) -> Result<(), RefError> {
+        let space = bookmark.space.try_borrow()?;
+        self.body.teleport(&*space, bookmark.pose.position);
+        self.body.yaw = bookmark.pose.yaw;
+        self.body.pitch = bookmark.pose.pitch;
+        self.space = bookmark.space.clone();
+        self.colliding_cubes.clear();
+        self.breaking = None;
+        Ok(())
+    }
+
     pub fn inventory(&self) -> &Inventory {
         &self.inventory
     }
@@ -173,44 +210,51 @@ impl Character {
 
     /// Advances time.
     ///
-    /// Normally, this is called from [`Universe::step`](crate::universe::Universe::step).
+    /// Normally, this is called from [`Universe::step`](crate::universe::Universe::step),
+    /// which gives the character's behaviors random numbers derived from
+    /// [`Universe::rng_mut`](crate::universe::Universe::rng_mut); when this is called
+    /// directly, they are derived from a fixed seed instead.
     pub fn step(&mut self, self_ref: Option<&URef<Character>>, tick: Tick) -> UniverseTransaction {
+        self.step_with_rng(self_ref, tick, &mut SeededRng::new(0))
+    }
+
+    /// As [`Character::step`], with the random number generator to derive the
+    /// behaviors' generators from.
+    pub(crate) fn step_with_rng(
+        &mut self,
+        self_ref: Option<&URef<Character>>,
+        tick: Tick,
+        rng: &mut SeededRng,
+    ) -> UniverseTransaction {
         if tick.paused() {
             return UniverseTransaction::default();
         }
 
-        let dt = tick.delta_t.as_secs_f64();
-        let control_orientation: Matrix3<FreeCoordinate> =
-            Matrix3::from_angle_y(-Deg(self.body.yaw));
         // TODO: apply pitch too, but only if wanted for flying (once we have not-flying)
+        let on_ground = self.is_on_ground();
+        self.controller.accelerate(
+            &mut self.body,
+            self.velocity_input,
+            self.gait,
+            on_ground,
+            tick,
+        );
 
-        let speed = if self.body.flying {
-            FLYING_SPEED
-        } else {
-            WALKING_SPEED
-        };
-        let velocity_target = control_orientation * self.velocity_input * speed;
-        // TODO should have an on-ground condition...
-        let stiffness = if self.body.flying {
-            Vector3::new(10.8, 10.8, 10.8)
-        } else {
-            Vector3::new(10.8, 0., 10.8)
-        }; // TODO constants/tables...
-
-        self.body.velocity +=
-            (velocity_target - self.body.velocity).mul_element_wise(stiffness) * dt;
-
+        let mut pickup = UniverseTransaction::default();
         if let Ok(space) = self.space.try_borrow() {
             let colliding_cubes = &mut self.colliding_cubes;
             colliding_cubes.clear();
             self.body.step(tick, Some(&*space), |cube| {
                 colliding_cubes.insert(cube);
             });
+            if let Some(self_ref) = self_ref {
+                pickup = self.pick_up_item_drops(self_ref, &space);
+            }
         } else {
             // TODO: set a warning flag
         }
 
-        if velocity_target.y > 0. {
+        if self.velocity_input.y > 0. {
             self.body.flying = true;
         } else if self.is_on_ground() {
             self.body.flying = false;
@@ -220,22 +264,73 @@ impl Character {
         // combining behavior calls with step() means behaviors on different characters
         // see other characters as not having been stepped yet.
         if let Some(self_ref) = self_ref {
-            self.behaviors.step(
+            let behaviors_transaction = self.behaviors.step(
                 &self,
                 &(|t: CharacterTransaction| t.bind(self_ref.clone())),
                 CharacterTransaction::behaviors,
+                rng,
                 tick,
-            )
+            );
+            // If a behavior is doing something to the inventory, it takes priority;
+            // the drops will still be there next step.
+            behaviors_transaction
+                .clone()
+                .merge(pickup)
+                .unwrap_or(behaviors_transaction)
         } else {
             UniverseTransaction::default()
         }
     }
 
+    /// Returns a transaction which moves every [`ItemDrop`] within reach into this
+    /// character's inventory, as far as there are free slots for them.
+    fn pick_up_item_drops(&self, self_ref: &URef<Character>, space: &Space) -> UniverseTransaction {
+        let mut free_slots = self
+            .inventory
+            .slots
+            .iter()
+            .filter(|item| **item == Tool::None)
+            .count();
+        let collision_box = self.body.collision_box_abs();
+        let mut space_transaction = SpaceTransaction::default();
+        let mut inventory_transaction = InventoryTransaction::default();
+        for (id, drop) in space.item_drops().iter() {
+            if free_slots == 0 {
+                break;
+            }
+            if !drop.can_be_picked_up() || !drop.is_within_reach_of(collision_box) {
+                continue;
+            }
+            let count = drop.count().min(free_slots as u32);
+            free_slots -= count as usize;
+            space_transaction = space_transaction
+                .merge(SpaceTransaction::take_item_drop(id, count))
+                .expect("drop IDs are distinct");
+            for _ in 0..count {
+                inventory_transaction = inventory_transaction
+                    .merge(InventoryTransaction::insert(drop.item().clone()))
+                    .expect("inserts do not conflict");
+            }
+        }
+        if space_transaction == SpaceTransaction::default() {
+            return UniverseTransaction::default();
+        }
+        space_transaction
+            .bind(self.space.clone())
+            .merge(CharacterTransaction::inventory(inventory_transaction).bind(self_ref.clone()))
+            .expect("transactions on different targets do not conflict")
+    }
+
     /// Maximum range for normal keyboard input should be -1 to 1
     pub fn set_velocity_input(&mut self, velocity: impl Into<Vector3<FreeCoordinate>>) {
         self.velocity_input = velocity.into();
     }
 
+    /// Sets whether the character is moving normally, sprinting, or sneaking.
+    pub fn set_gait(&mut self, gait: Gait) {
+        self.gait = gait;
+    }
+
     /// Use this character's selected tool on the given cursor.
     ///
     /// TODO: Dubious API: shouldn't this only work with the character's space?
@@ -246,11 +341,7 @@ impl Character {
         button: usize,
     ) -> Result<UniverseTransaction, ToolError> {
         let tb = this.borrow();
-        let slot_index = tb
-            .selected_slots
-            .get(button)
-            .copied()
-            .unwrap_or(tb.selected_slots[0]);
+        let slot_index = tb.slot_for_button(button);
         tb.inventory.use_tool(
             cursor,
             this,
@@ -265,6 +356,146 @@ impl Character {
         )
     }
 
+    /// Returns the tool which [`Self::click`] would use for the given mouse button.
+    pub fn selected_tool(&self, button: usize) -> &Tool {
+        self.inventory
+            .slots
+            .get(self.slot_for_button(button))
+            .unwrap_or(&Tool::None)
+    }
+
+    fn slot_for_button(&self, button: usize) -> usize {
+        self.selected_slots
+            .get(button)
+            .copied()
+            .unwrap_or(self.selected_slots[0])
+    }
+
+    /// Throw the item in the given inventory slot out into the character's space, in the
+    /// direction the character is looking. It will land as an [`ItemDrop`], which
+    /// cannot immediately be picked up again.
+    pub fn toss(this: &URef<Character>, slot: usize) -> Result<UniverseTransaction, ToolError> {
+        let c = this.borrow();
+        let item = match c.inventory.slots.get(slot) {
+            None | Some(Tool::None) => return Err(ToolError::NotUsable),
+            Some(item) => item.clone(),
+        };
+        let look_direction = Matrix3::from_angle_y(-Deg(c.body.yaw))
+            * Matrix3::from_angle_x(-Deg(c.body.pitch))
+            * Vector3::new(0.0, 0.0, -1.0);
+        let drop = ItemDrop::tossed(
+            item.clone(),
+            c.body.position + look_direction * 0.5,
+            c.body.velocity + look_direction * TOSS_SPEED,
+        );
+        Ok(
+            CharacterTransaction::inventory(InventoryTransaction::replace(slot, item, Tool::None))
+                .bind(this.clone())
+                .merge(SpaceTransaction::add_item_drop(drop).bind(c.space.clone()))
+                .expect("transactions on different targets do not conflict"),
+        )
+    }
+
+    /// Returns the progress this character has made in breaking a block, if it is
+    /// currently doing so.
+    pub fn breaking(&self) -> Option<&BreakingProgress> {
+        self.breaking.as_ref()
+    }
+
+    /// Advance this character's progress in breaking the block under `cursor`, as when
+    /// [`Tool::DeleteBlock`] is held down for the duration of `tick`.
+    ///
+    /// If the cursor has moved to a different block since the last call, the previous
+    /// progress is abandoned. The returned transaction updates the crack overlay (one of
+    /// `cracks`) on the block, and once enough time has passed according to its
+    /// [`hardness`](crate::block::BlockAttributes::hardness), replaces the block with an
+    /// [`ItemDrop`] of it, which the character may then pick up.
+    pub fn continue_breaking(
+        this: &URef<Character>,
+        cursor: &Cursor,
+        tick: Tick,
+        cracks: &BlockProvider<CrackStage>,
+    ) -> Result<UniverseTransaction, ToolError> {
+        let c = this.borrow();
+        let mut transaction = UniverseTransaction::default();
+
+        // The new state is only stored in the character by executing the transaction,
+        // so that if it fails, the progress matches what is in the space.
+        let mut progress = match &c.breaking {
+            Some(progress)
+                if progress.space == cursor.space
+                    && progress.cube == cursor.place.cube
+                    && progress.block_for_stage(progress.displayed, cracks) == cursor.block =>
+            {
+                progress.clone()
+            }
+            old => {
+                if let Some(old) = old {
+                    transaction = old.set_stage(None, cracks);
+                }
+                let base = match &cursor.block {
+                    Block::Overlay { base, .. } => Block::clone(base),
+                    block => block.clone(),
+                };
+                BreakingProgress::new(cursor.space.clone(), cursor.place.cube, base)
+            }
+        };
+        drop(c);
+
+        let hardness = f64::from(cursor.evaluated.attributes.hardness.into_inner());
+        if !tick.paused() {
+            progress.elapsed += tick.delta_t.as_secs_f64();
+        }
+        let fraction = if hardness > 0.0 {
+            progress.elapsed / hardness
+        } else {
+            1.0
+        };
+
+        if fraction < 1.0 {
+            let new_stage = CrackStage::for_fraction(fraction);
+            let stage_change = progress.set_stage(new_stage, cracks);
+            progress.displayed = new_stage;
+            return Ok(transaction
+                .merge(stage_change)
+                .expect("failed to merge crack stage change")
+                .merge(CharacterTransaction::breaking(Some(progress)).bind(this.clone()))
+                .expect("failed to merge breaking progress"));
+        }
+
+        let removal = SpaceTransaction::set_cube(
+            progress.cube,
+            Some(progress.block_for_stage(progress.displayed, cracks)),
+            Some(AIR),
+        )
+        .merge(SpaceTransaction::add_item_drop(ItemDrop::new(
+            Tool::PlaceBlock(progress.block.unspecialize()),
+            1,
+            progress.cube.map(FreeCoordinate::from) + Vector3::new(0.5, 0.5, 0.5),
+        )))
+        .expect("failed to merge item drop");
+        Ok(transaction
+            .merge(removal.bind(progress.space.clone()))
+            .expect("failed to merge block removal")
+            .merge(CharacterTransaction::breaking(None).bind(this.clone()))
+            .expect("failed to merge breaking progress"))
+    }
+
+    /// Abandon any progress made by [`Self::continue_breaking`], returning a transaction
+    /// which removes the crack overlay from the block.
+    pub fn stop_breaking(
+        this: &URef<Character>,
+        cracks: &BlockProvider<CrackStage>,
+    ) -> UniverseTransaction {
+        match &this.borrow().breaking {
+            Some(progress) => progress
+                .set_stage(None, cracks)
+                .merge(CharacterTransaction::breaking(None).bind(this.clone()))
+                .expect("failed to merge breaking progress"),
+            None => UniverseTransaction::default(),
+        }
+    }
+
     // TODO: this code's location is driven by colliding_cubes being here, which is probably wrong
     // If nothing else, the jump height probably belongs elsewhere.
     // Figure out what the correct overall thing is and make it public
@@ -272,7 +503,7 @@ impl Character {
         if self.is_on_ground() {
             self.body.velocity += Vector3 {
                 x: 0.,
-                y: JUMP_SPEED,
+                y: self.controller.jump_speed,
                 z: 0.,
             };
         }
@@ -294,6 +525,8 @@ pub struct CharacterTransaction {
     body: BodyTransaction,
     inventory: InventoryTransaction,
     behaviors: BehaviorSetTransaction<Character>,
+    /// If [`Some`], the new value of [`Character::breaking`].
+    breaking: Option<Option<BreakingProgress>>,
 }
 
 impl CharacterTransaction {
@@ -317,6 +550,13 @@ impl CharacterTransaction {
             ..Default::default()
         }
     }
+
+    fn breaking(progress: Option<BreakingProgress>) -> Self {
+        Self {
+            breaking: Some(progress),
+            ..Default::default()
+        }
+    }
 }
 
 #[allow(clippy::type_complexity)]
@@ -330,6 +570,7 @@ impl Transaction<Character> for CharacterTransaction {
         <BodyTransaction as Transaction<Body>>::MergeCheck,
         <InventoryTransaction as Transaction<Inventory>>::MergeCheck,
         <BehaviorSetTransaction<Character> as Transaction<BehaviorSet<Character>>>::MergeCheck,
+        (),
     );
     type Output = ();
 
@@ -359,6 +600,10 @@ impl Transaction<Character> for CharacterTransaction {
         self.behaviors
             .commit(&mut target.behaviors, behaviors_check)?;
 
+        if let Some(breaking) = &self.breaking {
+            target.breaking = breaking.clone();
+        }
+
         Ok(())
     }
 
@@ -367,13 +612,16 @@ impl Transaction<Character> for CharacterTransaction {
             self.body.check_merge(&other.body)?,
             self.inventory.check_merge(&other.inventory)?,
             self.behaviors.check_merge(&other.behaviors)?,
+            if self.breaking.is_some() && other.breaking.is_some() {
+                return Err(TransactionConflict {});
+            },
         ))
     }
 
     fn commit_merge(
         self,
         other: Self,
-        (body_check, inventory_check, behaviors_check): Self::MergeCheck,
+        (body_check, inventory_check, behaviors_check, ()): Self::MergeCheck,
     ) -> Self {
         Self {
             body: self.body.commit_merge(other.body, body_check),
@@ -383,6 +631,7 @@ impl Transaction<Character> for CharacterTransaction {
             behaviors: self
                 .behaviors
                 .commit_merge(other.behaviors, behaviors_check),
+            breaking: self.breaking.or(other.breaking),
         }
     }
 }
@@ -405,7 +654,14 @@ pub fn cursor_raycast(mut ray: Ray, space_ref: &URef<Space>) -> Option<Cursor> {
     // TODO: implement 'reach' radius limit
     ray.direction = ray.direction.normalize();
     let space = space_ref.try_borrow().ok()?;
-    for step in ray.cast().within_grid(space.grid()) {
+    // If the space wraps, allow reaching once around it.
+    let grid = space.grid();
+    let reach = grid.size().x.max(grid.size().y).max(grid.size().z);
+    let raycast_grid = space
+        .physics()
+        .topology
+        .raycast_grid(grid, FreeCoordinate::from(reach));
+    for step in ray.cast().within_grid(raycast_grid) {
         let cube = step.cube_ahead();
         let evaluated = space.get_evaluated(cube);
         let lighting_ahead = space.get_lighting(cube);
@@ -446,6 +702,7 @@ pub struct Cursor {
     pub space: URef<Space>,
     /// The cube the cursor is at and which face was hit.
     pub place: CubeFace,
+    /// The point on the surface of the block where the ray struck it.
     pub point: Point3<FreeCoordinate>,
     /// Distance from viewpoint to intersection point.
     pub distance: FreeCoordinate,
@@ -453,10 +710,67 @@ pub struct Cursor {
     pub block: Block,
     /// The EvaluatedBlock data for the block.
     pub evaluated: EvaluatedBlock,
+    /// Light in the cube that was struck.
     pub lighting_ahead: PackedLight,
+    /// Light in the cube the ray passed through just before it struck; that is, at
+    /// [`Cursor::placement_cube`].
     pub lighting_behind: PackedLight,
 }
 
+impl Cursor {
+    /// Returns the [`BlockDef`] that the struck block is defined by, if it is defined
+    /// by one, looking through rotations and other modifications of it.
+    pub fn block_definition(&self) -> Option<&URef<BlockDef>> {
+        let mut block = &self.block;
+        loop {
+            match block {
+                Block::Indirect(def_ref) => return Some(def_ref),
+                Block::Rotated(_, base)
+                | Block::Overlay { base, .. }
+                | Block::Modified { base, .. } => block = &**base,
+                Block::Atom(..) | Block::Recur { .. } => return None,
+            }
+        }
+    }
+
+    /// Returns the name of the [`BlockDef`] the struck block is defined by; see
+    /// [`Cursor::block_definition`].
+    pub fn block_name(&self) -> Option<&Name> {
+        self.block_definition().map(|def_ref| &**def_ref.name())
+    }
+
+    /// Returns the [`Name::folder`] of [`Cursor::block_name`], which for blocks
+    /// installed from a [`BlockModule`](crate::linking::BlockModule) is the module's
+    /// namespace.
+    pub fn block_namespace(&self) -> Option<&str> {
+        self.block_name()?.folder()
+    }
+
+    /// Returns the cube in which a block would be placed by clicking on this cursor:
+    /// the one adjacent to the face that was struck.
+    pub fn placement_cube(&self) -> GridPoint {
+        self.place.adjacent()
+    }
+
+    /// Returns whether a block could currently be placed in
+    /// [`Cursor::placement_cube`]: it must be within the space, must contain only
+    /// [`AIR`], and must not overlap `body` (normally that of the character doing the
+    /// placing).
+    pub fn can_place(&self, body: &Body) -> bool {
+        let cube = self.placement_cube();
+        let space = match self.space.try_borrow() {
+            Ok(space) => space,
+            Err(_) => return false,
+        };
+        space.grid().contains_cube(cube)
+            && space[cube] == AIR
+            && !body
+                .collision_box_abs()
+                .round_up_to_grid()
+                .contains_cube(cube)
+    }
+}
+
 // TODO: this probably shouldn't be Display any more, but Debug or ConciseDebug
 // — or just a regular method.
 impl std::fmt::Display for Cursor {
@@ -468,7 +782,11 @@ impl std::fmt::Display for Cursor {
             self.evaluated.custom_format(ConciseDebug),
             self.lighting_ahead,
             self.lighting_behind,
-        )
+        )?;
+        if let Some(name) = self.block_name() {
+            write!(f, "\nDefined as {}", name)?;
+        }
+        Ok(())
     }
 }
 
@@ -502,7 +820,6 @@ impl Spawn {
     ///
     /// `direction` gives the direction in which the character will lie relative to the
     /// center of the space.
-    #[allow(dead_code)] // TODO: Saving this in case it turns out useful e.g. for looking at blocks.
     pub(crate) fn looking_at_space(
         space: URef<Space>,
         direction: impl Into<Vector3<FreeCoordinate>>,
@@ -521,9 +838,11 @@ mod tests {
 
     use super::*;
     use crate::block::AIR;
+    use crate::content::make_some_blocks;
     use crate::listen::Sink;
+    use crate::math::GridRotation;
     use crate::transactions::TransactionTester;
-    use crate::universe::Universe;
+    use crate::universe::{Universe, UniverseIndex};
 
     #[test]
     fn spawn_inventory() {
@@ -570,6 +889,144 @@ mod tests {
         // TODO: Actually assert inventory contents -- no public interface for that
     }
 
+    #[test]
+    fn teleport_avoids_solid_blocks() {
+        let [block] = make_some_blocks();
+        let mut universe = Universe::new();
+        let start = universe.insert_anonymous(Space::empty_positive(1, 1, 1));
+        let mut destination = Space::empty_positive(5, 5, 5);
+        destination
+            .fill_uniform(Grid::new([0, 0, 0], [5, 2, 5]), &block)
+            .unwrap();
+        let destination = universe.insert_anonymous(destination);
+        let mut character = Character::spawn_default(start);
+        character.body.velocity = Vector3::new(1.0, 2.0, 3.0);
+
+        let bookmark = Bookmark::new(
+            destination.clone(),
+            crate::camera::CameraPose::new([2.5, 1.0, 2.5], 90.0, 10.0),
+        );
+        character.teleport(&bookmark).unwrap();
+        assert_eq!(character.space, destination);
+        assert_eq!((character.body.yaw, character.body.pitch), (90.0, 10.0));
+        assert_eq!(character.body.velocity, Vector3::zero());
+        assert_ne!(character.body.position, bookmark.pose.position);
+        assert_eq!(
+            crate::physics::find_colliding_cubes(
+                &*destination.borrow(),
+                character.body.collision_box_abs()
+            )
+            .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn continue_breaking_to_completion() {
+        let mut universe = Universe::new();
+        let cracks = CrackStage::new(&mut universe);
+        let block = Block::builder()
+            .display_name("hard")
+            .color(rgba_const!(1.0, 1.0, 1.0, 1.0))
+            .hardness(1.0)
+            .build();
+        let mut space = Space::empty_positive(3, 1, 1);
+        space.set([2, 0, 0], &block).unwrap();
+        let space_ref = universe.insert_anonymous(space);
+        let character_ref = universe.insert_anonymous(Character::spawn_default(space_ref.clone()));
+        let ray = Ray::new([0., 0.5, 0.5], [1., 0., 0.]);
+
+        let mut saw_cracks = false;
+        for _ in 0..10 {
+            let cursor = cursor_raycast(ray, &space_ref).unwrap();
+            Character::continue_breaking(
+                &character_ref,
+                &cursor,
+                Tick::from_seconds(0.25),
+                &cracks,
+            )
+            .unwrap()
+            .execute(&mut universe)
+            .unwrap();
+            if character_ref.borrow().breaking().is_none() {
+                break;
+            }
+            saw_cracks |= matches!(space_ref.borrow()[[2, 0, 0]], Block::Overlay { .. });
+        }
+
+        assert!(saw_cracks);
+        assert_eq!(space_ref.borrow()[[2, 0, 0]], AIR);
+        let item = Tool::PlaceBlock(block);
+        assert_eq!(
+            space_ref
+                .borrow()
+                .item_drops()
+                .iter()
+                .map(|(_, drop)| drop.item().clone())
+                .collect::<Vec<_>>(),
+            vec![item.clone()]
+        );
+
+        // Moving next to the drop picks it up.
+        character_ref.borrow_mut().body.position = Point3::new(1.5, 1.0, 0.5);
+        universe.step(Tick::from_seconds(1.0 / 60.0));
+        assert!(space_ref.borrow().item_drops().is_empty());
+        assert!(character_ref.borrow().inventory().slots.contains(&item));
+    }
+
+    #[test]
+    fn tossed_item_is_not_immediately_picked_up() {
+        let item = Tool::PlaceBlock(Block::from(rgb_const!(0.1, 0.2, 0.3)));
+        let mut universe = Universe::new();
+        let space_ref = universe.insert_anonymous(Space::empty_positive(8, 8, 8));
+        let spawn = Spawn {
+            position: Point3::new(notnan!(4.0), notnan!(4.0), notnan!(4.0)),
+            inventory: vec![item.clone()],
+            ..Spawn::default_for_new_space(space_ref.borrow().grid())
+        };
+        let character_ref = universe.insert_anonymous(Character::spawn(&spawn, space_ref.clone()));
+
+        Character::toss(&character_ref, 0)
+            .unwrap()
+            .execute(&mut universe)
+            .unwrap();
+        assert_eq!(character_ref.borrow().inventory().slots[0], Tool::None);
+        assert_eq!(space_ref.borrow().item_drops().len(), 1);
+
+        universe.step(Tick::from_seconds(1.0 / 60.0));
+        assert_eq!(character_ref.borrow().inventory().slots[0], Tool::None);
+        assert_eq!(space_ref.borrow().item_drops().len(), 1);
+
+        Character::toss(&character_ref, 0).unwrap_err();
+    }
+
+    #[test]
+    fn stop_breaking_restores_block() {
+        let mut universe = Universe::new();
+        let cracks = CrackStage::new(&mut universe);
+        let block = Block::builder()
+            .color(rgba_const!(1.0, 1.0, 1.0, 1.0))
+            .hardness(1.0)
+            .build();
+        let mut space = Space::empty_positive(3, 1, 1);
+        space.set([2, 0, 0], &block).unwrap();
+        let space_ref = universe.insert_anonymous(space);
+        let character_ref = universe.insert_anonymous(Character::spawn_default(space_ref.clone()));
+
+        let cursor = cursor_raycast(Ray::new([0., 0.5, 0.5], [1., 0., 0.]), &space_ref).unwrap();
+        Character::continue_breaking(&character_ref, &cursor, Tick::from_seconds(0.7), &cracks)
+            .unwrap()
+            .execute(&mut universe)
+            .unwrap();
+        assert_ne!(space_ref.borrow()[[2, 0, 0]], block);
+
+        Character::stop_breaking(&character_ref, &cracks)
+            .execute(&mut universe)
+            .unwrap();
+        assert_eq!(space_ref.borrow()[[2, 0, 0]], block);
+        assert_eq!(character_ref.borrow().breaking(), None);
+    }
+
     #[test]
     fn transaction_systematic() {
         let mut universe = Universe::new();
@@ -587,7 +1044,10 @@ mod tests {
                 |_, _| Ok(()),
             )
             .transaction(
-                CharacterTransaction::body(BodyTransaction { delta_yaw: 1.0 }),
+                CharacterTransaction::body(BodyTransaction {
+                    delta_yaw: 1.0,
+                    ..Default::default()
+                }),
                 |_, _| Ok(()),
             )
             // Inventory transactions
@@ -639,4 +1099,47 @@ mod tests {
     }
 
     // TODO: more tests
+
+    #[test]
+    fn cursor_inspection() {
+        let mut universe = Universe::new();
+        let [block] = make_some_blocks();
+        let def_ref = universe
+            .insert("example/block".into(), BlockDef::new(block))
+            .unwrap();
+        let mut space = Space::empty_positive(3, 1, 1);
+        space
+            .set(
+                [1, 0, 0],
+                Block::Indirect(def_ref).rotate(GridRotation::CLOCKWISE),
+            )
+            .unwrap();
+        let space_ref = universe.insert_anonymous(space);
+
+        let cursor = cursor_raycast(Ray::new([-1., 0.5, 0.5], [1., 0., 0.]), &space_ref).unwrap();
+        assert_eq!(cursor.place.cube, GridPoint::new(1, 0, 0));
+        assert!((cursor.distance - 2.0).abs() < 1e-9, "{}", cursor.distance);
+        assert_eq!(cursor.block_name(), Some(&Name::from("example/block")));
+        assert_eq!(cursor.block_namespace(), Some("example"));
+        assert_eq!(cursor.placement_cube(), GridPoint::new(0, 0, 0));
+
+        let small_box = Aab::new(-0.25, 0.25, -0.25, 0.25, -0.25, 0.25);
+        assert!(cursor.can_place(&Body::new_minimal([2.5, 0.5, 0.5], small_box)));
+        assert!(!cursor.can_place(&Body::new_minimal([0.5, 0.5, 0.5], small_box)));
+    }
+
+    #[test]
+    fn cursor_cannot_place_outside_space() {
+        let mut universe = Universe::new();
+        let [block] = make_some_blocks();
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &block).unwrap();
+        let space_ref = universe.insert_anonymous(space);
+
+        let cursor = cursor_raycast(Ray::new([-1., 0.5, 0.5], [1., 0., 0.]), &space_ref).unwrap();
+        assert_eq!(cursor.block_name(), None);
+        assert_eq!(cursor.placement_cube(), GridPoint::new(-1, 0, 0));
+        let small_box = Aab::new(-0.25, 0.25, -0.25, 0.25, -0.25, 0.25);
+        assert!(!cursor.can_place(&Body::new_minimal([5., 5., 5.], small_box)));
+    }
 }
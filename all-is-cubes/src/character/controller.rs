@@ -0,0 +1,271 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`CharacterController`], which turns movement input into changes of velocity.
+
+use cgmath::{Deg, Matrix3, Vector3};
+
+use crate::apps::Tick;
+use crate::math::FreeCoordinate;
+use crate::physics::Body;
+
+/// How a [`Character`](super::Character) is moving, apart from the direction; chosen by
+/// the player along with the movement input.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Gait {
+    /// Moving at the normal speed.
+    Normal,
+    /// Moving faster, by [`CharacterController::sprint_multiplier`].
+    Sprint,
+    /// Moving slower, by [`CharacterController::sneak_multiplier`].
+    Sneak,
+}
+
+impl Default for Gait {
+    fn default() -> Self {
+        Gait::Normal
+    }
+}
+
+/// Parameters of how a [`Character`](super::Character)'s [`Body`] responds to movement
+/// input.
+///
+/// The velocity approaches the velocity the input asks for at a rate which depends on
+/// whether the body is on the ground, in the air, or flying. Each rate is the fraction
+/// of the difference between the two which is removed per second; the result depends
+/// only on the inputs, so it is the same on every platform.
+///
+/// ```
+/// use all_is_cubes::apps::Tick;
+/// use all_is_cubes::character::{CharacterController, Gait};
+/// use all_is_cubes::math::Aab;
+/// use all_is_cubes::physics::Body;
+///
+/// let controller = CharacterController::default();
+/// let mut body = Body::new_minimal([0.0, 0.0, 0.0], Aab::ZERO);
+/// for _ in 0..60 {
+///     controller.accelerate(
+///         &mut body,
+///         [0.0, 0.0, -1.0].into(),
+///         Gait::Normal,
+///         true,
+///         Tick::from_seconds(1.0 / 60.0),
+///     );
+/// }
+/// assert!((body.velocity.z + controller.walking_speed).abs() < 0.01);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct CharacterController {
+    /// Speed, in cubes per second, of walking with full input.
+    pub walking_speed: FreeCoordinate,
+    /// Speed, in cubes per second, of flying with full input.
+    pub flying_speed: FreeCoordinate,
+    /// Speed, in cubes per second, of moving with full input while the body is
+    /// [`noclip`](Body::noclip).
+    pub noclip_speed: FreeCoordinate,
+    /// Upward speed, in cubes per second, given by jumping.
+    pub jump_speed: FreeCoordinate,
+    /// Rate at which the horizontal velocity approaches that requested while on the
+    /// ground.
+    pub ground_acceleration: FreeCoordinate,
+    /// Rate at which the horizontal velocity approaches zero while on the ground with no
+    /// horizontal movement input.
+    pub friction: FreeCoordinate,
+    /// Rate at which the horizontal velocity approaches that requested while in the air
+    /// and not flying.
+    pub air_control: FreeCoordinate,
+    /// Rate at which the velocity, in all three axes, approaches that requested while
+    /// flying or noclip.
+    pub flying_acceleration: FreeCoordinate,
+    /// Multiplier for the speed while the [`Gait`] is [`Gait::Sprint`].
+    pub sprint_multiplier: FreeCoordinate,
+    /// Multiplier for the speed while the [`Gait`] is [`Gait::Sneak`].
+    pub sneak_multiplier: FreeCoordinate,
+}
+
+impl CharacterController {
+    /// Changes the velocity of `body` in response to `input` for the duration of `tick`.
+    ///
+    /// `input` is the requested movement relative to the direction the body is facing
+    /// ([`Body::yaw`]), with each component ranging from -1 to 1: positive X is
+    /// rightward, positive Y upward, and negative Z forward. The vertical component
+    /// has an effect only if the body is [flying](Body::flying) or
+    /// [noclip](Body::noclip); a noclip body also moves in the direction it is looking
+    /// up or down ([`Body::pitch`]). `on_ground` is whether the body is standing on
+    /// something.
+    pub fn accelerate(
+        &self,
+        body: &mut Body,
+        input: Vector3<FreeCoordinate>,
+        gait: Gait,
+        on_ground: bool,
+        tick: Tick,
+    ) {
+        if tick.paused() {
+            return;
+        }
+        let dt = tick.delta_t.as_secs_f64();
+
+        let speed = if body.noclip {
+            self.noclip_speed
+        } else if body.flying {
+            self.flying_speed
+        } else {
+            self.walking_speed
+        } * match gait {
+            Gait::Normal => 1.0,
+            Gait::Sprint => self.sprint_multiplier,
+            Gait::Sneak => self.sneak_multiplier,
+        };
+        let mut orientation: Matrix3<FreeCoordinate> = Matrix3::from_angle_y(-Deg(body.yaw));
+        if body.noclip {
+            orientation = orientation * Matrix3::from_angle_x(-Deg(body.pitch));
+        }
+        let target = orientation * input * speed;
+
+        let free_flight = body.flying || body.noclip;
+        let rate = if free_flight {
+            self.flying_acceleration
+        } else if !on_ground {
+            self.air_control
+        } else if input.x == 0.0 && input.z == 0.0 {
+            self.friction
+        } else {
+            self.ground_acceleration
+        };
+        // Never overshoot the target, however long the tick is.
+        let mut change = (target - body.velocity) * (rate * dt).min(1.0);
+        if !free_flight {
+            // Vertical movement is up to gravity and jumping.
+            change.y = 0.0;
+        }
+        body.velocity += change;
+    }
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            walking_speed: 4.0,
+            flying_speed: 10.0,
+            noclip_speed: 20.0,
+            jump_speed: 8.0,
+            ground_acceleration: 10.8,
+            friction: 10.8,
+            air_control: 2.0,
+            flying_acceleration: 10.8,
+            sprint_multiplier: 1.75,
+            sneak_multiplier: 0.3,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Aab;
+
+    /// Runs the controller for one second, starting at rest, and returns the velocity.
+    fn velocity_after_one_second(
+        flying: bool,
+        input: [FreeCoordinate; 3],
+        gait: Gait,
+        on_ground: bool,
+    ) -> Vector3<FreeCoordinate> {
+        let controller = CharacterController::default();
+        let mut body = Body {
+            flying,
+            ..Body::new_minimal([0.0, 0.0, 0.0], Aab::ZERO)
+        };
+        for _ in 0..60 {
+            controller.accelerate(
+                &mut body,
+                input.into(),
+                gait,
+                on_ground,
+                Tick::from_seconds(1.0 / 60.0),
+            );
+        }
+        body.velocity
+    }
+
+    #[test]
+    fn gait_changes_speed() {
+        let walk = velocity_after_one_second(false, [1.0, 0.0, 0.0], Gait::Normal, true).x;
+        let sprint = velocity_after_one_second(false, [1.0, 0.0, 0.0], Gait::Sprint, true).x;
+        let sneak = velocity_after_one_second(false, [1.0, 0.0, 0.0], Gait::Sneak, true).x;
+        assert!((walk - 4.0).abs() < 0.01, "{}", walk);
+        assert!((sprint - 7.0).abs() < 0.01, "{}", sprint);
+        assert!((sneak - 1.2).abs() < 0.01, "{}", sneak);
+    }
+
+    #[test]
+    fn air_control_is_weaker() {
+        let ground = velocity_after_one_second(false, [1.0, 0.0, 0.0], Gait::Normal, true).x;
+        let air = velocity_after_one_second(false, [1.0, 0.0, 0.0], Gait::Normal, false).x;
+        assert!(air > 0.0 && air < ground, "air {} ground {}", air, ground);
+    }
+
+    #[test]
+    fn vertical_input_only_when_flying() {
+        let walking = velocity_after_one_second(false, [0.0, 1.0, 0.0], Gait::Normal, true);
+        assert_eq!(walking, Vector3::new(0.0, 0.0, 0.0));
+        let flying = velocity_after_one_second(true, [0.0, 1.0, 0.0], Gait::Normal, false);
+        assert!((flying.y - 10.0).abs() < 0.01, "{:?}", flying);
+    }
+
+    #[test]
+    fn noclip_follows_pitch() {
+        let controller = CharacterController::default();
+        let mut body = Body {
+            noclip: true,
+            // Looking straight up.
+            pitch: -90.0,
+            ..Body::new_minimal([0.0, 0.0, 0.0], Aab::ZERO)
+        };
+        controller.accelerate(
+            &mut body,
+            Vector3::new(0.0, 0.0, -1.0),
+            Gait::Normal,
+            false,
+            Tick::from_seconds(1.0),
+        );
+        assert!(body.velocity.x.abs() < 1e-9, "{:?}", body.velocity);
+        assert!((body.velocity.y - 20.0).abs() < 1e-9, "{:?}", body.velocity);
+        assert!(body.velocity.z.abs() < 1e-9, "{:?}", body.velocity);
+    }
+
+    #[test]
+    fn friction_stops() {
+        let controller = CharacterController::default();
+        let mut body = Body {
+            velocity: Vector3::new(3.0, -1.0, 0.0),
+            ..Body::new_minimal([0.0, 0.0, 0.0], Aab::ZERO)
+        };
+        controller.accelerate(
+            &mut body,
+            Vector3::new(0.0, 0.0, 0.0),
+            Gait::Normal,
+            true,
+            Tick::from_seconds(1.0),
+        );
+        // A long tick does not overshoot, and gravity's business is left alone.
+        assert_eq!(body.velocity, Vector3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn paused_does_nothing() {
+        let controller = CharacterController::default();
+        let mut body = Body::new_minimal([0.0, 0.0, 0.0], Aab::ZERO);
+        controller.accelerate(
+            &mut body,
+            Vector3::new(1.0, 0.0, 0.0),
+            Gait::Normal,
+            true,
+            Tick::from_seconds(1.0).pause(),
+        );
+        assert_eq!(body.velocity, Vector3::new(0.0, 0.0, 0.0));
+    }
+}
@@ -72,14 +72,7 @@ pub struct ChunkChart<const CHUNK_SIZE: GridCoordinate> {
 impl<const CHUNK_SIZE: GridCoordinate> ChunkChart<CHUNK_SIZE> {
     pub fn new(view_distance: FreeCoordinate) -> Self {
         let view_distance = Self::sanitize_distance(view_distance);
-
-        // We're going to compute in the zero-or-positive octant, which means that the chunk origin
-        // coordinates we work with are (conveniently) the coordinates for the _nearest corner_ of
-        // each chunk.
-        let view_distance_in_chunks = view_distance.into_inner() / FreeCoordinate::from(CHUNK_SIZE);
-        // We can do the squared distance calculation in GridCoordinate integers but only after
-        // the squaring.
-        let distance_squared = view_distance_in_chunks.powf(2.).ceil() as GridCoordinate;
+        let distance_squared = Self::distance_squared_in_chunks(view_distance);
 
         let candidates = Grid::new((0, 0, 0), Vector3::new(1, 1, 1) * (distance_squared + 1));
         let mut octant_chunks: Vec<GridVector> = Vec::with_capacity(candidates.volume());
@@ -93,11 +86,7 @@ impl<const CHUNK_SIZE: GridCoordinate> ChunkChart<CHUNK_SIZE> {
             //
             // The shape formed (after mirroring) is the Minkowski sum of the view sphere
             // and the chunk cube.
-            if int_magnitude_squared(chunk.map(
-                #[inline(always)]
-                |s| (s - 1).max(0),
-            )) <= distance_squared
-            {
+            if Self::offset_is_within(chunk, distance_squared) {
                 octant_chunks.push(chunk);
             }
         }
@@ -114,6 +103,42 @@ impl<const CHUNK_SIZE: GridCoordinate> ChunkChart<CHUNK_SIZE> {
         NotNan::try_from(view_distance.max(0.)).unwrap_or_else(|_| NotNan::zero())
     }
 
+    fn distance_squared_in_chunks(view_distance: NotNan<FreeCoordinate>) -> GridCoordinate {
+        // We're going to compute in the zero-or-positive octant, which means that the chunk origin
+        // coordinates we work with are (conveniently) the coordinates for the _nearest corner_ of
+        // each chunk.
+        let view_distance_in_chunks = view_distance.into_inner() / FreeCoordinate::from(CHUNK_SIZE);
+        // We can do the squared distance calculation in GridCoordinate integers but only after
+        // the squaring.
+        view_distance_in_chunks.powf(2.).ceil() as GridCoordinate
+    }
+
+    /// Whether the chunk at `offset` (in chunks) from the origin chunk belongs in a chart
+    /// with the given squared distance. `offset` may be in any octant.
+    #[inline(always)]
+    fn offset_is_within(offset: GridVector, distance_squared: GridCoordinate) -> bool {
+        int_magnitude_squared(offset.map(
+            #[inline(always)]
+            |s| (s.abs() - 1).max(0),
+        )) <= distance_squared
+    }
+
+    /// Returns whether `chunk` is one of the chunks which [`ChunkChart::chunks`] would
+    /// return for `origin` if the chart had been created with the given `view_distance`.
+    ///
+    /// This allows checking membership in a larger or smaller chart without
+    /// constructing it.
+    pub fn is_within_distance(
+        origin: ChunkPos<CHUNK_SIZE>,
+        chunk: ChunkPos<CHUNK_SIZE>,
+        view_distance: FreeCoordinate,
+    ) -> bool {
+        Self::offset_is_within(
+            chunk.0 - origin.0,
+            Self::distance_squared_in_chunks(Self::sanitize_distance(view_distance)),
+        )
+    }
+
     fn sort_key(
         &chunk: &GridVector,
     ) -> (
@@ -388,6 +413,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn chunk_chart_is_within_distance_consistent() {
+        let distance = 3.5 * 16.;
+        let chart = ChunkChart::<16>::new(distance);
+        let origin = ChunkPos::new(1, -2, 3);
+        let charted: HashSet<ChunkPos<16>> = chart.chunks(origin).collect();
+        for p in Grid::new((-6, -7, -2), (14, 14, 14)).interior_iter() {
+            let p = ChunkPos(p);
+            assert_eq!(
+                ChunkChart::is_within_distance(origin, p, distance),
+                charted.contains(&p),
+                "{:?}",
+                p
+            );
+        }
+    }
+
     #[test]
     fn chunk_chart_resize() {
         let chart1 = ChunkChart::<16>::new(200.0);
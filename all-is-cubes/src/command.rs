@@ -0,0 +1,760 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Textual commands such as `/tp 0 10 0`, for a chat or console interface.
+//!
+//! A [`CommandRegistry`] holds the available [`Command`]s and parses and executes lines
+//! of text, so that every frontend which offers a console accepts the same commands.
+//! Each command declares its arguments with an [`ArgKind`], which determines how they
+//! are parsed and what completions are offered for them.
+//!
+//! ```
+//! use all_is_cubes::command::CommandRegistry;
+//! use all_is_cubes::universe::Universe;
+//!
+//! let commands = CommandRegistry::standard();
+//! let mut universe = Universe::new();
+//! assert_eq!(
+//!     commands.execute("/time set 0.5", &mut universe, None).unwrap(),
+//!     "Time scale is now 0.5",
+//! );
+//! assert_eq!(universe.time_scale(), 0.5);
+//! assert_eq!(commands.complete("/ti", &universe), vec!["time"]);
+//! ```
+
+use cgmath::Point3;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::block::{Block, BlockDef};
+use crate::character::{Character, CharacterTransaction};
+use crate::math::FreeCoordinate;
+use crate::tools::{InventoryTransaction, Tool};
+use crate::transactions::{Transaction as _, UniverseTransaction};
+use crate::universe::{Bookmark, Name, RefError, URef, Universe, UniverseIndex};
+use crate::util::{ConciseDebug, CustomFormat as _};
+
+type Handler =
+    Box<dyn Fn(&mut CommandContext<'_>, &Args<'_>) -> Result<CommandOutput, CommandError>>;
+
+/// A command which may be executed by a [`CommandRegistry`].
+pub struct Command {
+    name: String,
+    description: String,
+    args: Vec<ArgSpec>,
+    handler: Handler,
+}
+
+impl Command {
+    /// Constructs a command with no arguments; add them with [`Command::arg`].
+    ///
+    /// `name` is what the user types to invoke it, without the leading `/`, and
+    /// `handler` carries it out, given the arguments parsed as specified.
+    pub fn new<F>(name: impl Into<String>, description: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&mut CommandContext<'_>, &Args<'_>) -> Result<CommandOutput, CommandError> + 'static,
+    {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            args: Vec::new(),
+            handler: Box::new(handler),
+        }
+    }
+
+    /// Adds a required argument after those already added.
+    ///
+    /// Panics if an optional argument has already been added.
+    #[must_use]
+    pub fn arg(mut self, name: &'static str, kind: ArgKind) -> Self {
+        assert!(
+            !self.args.iter().any(|arg| arg.optional),
+            "required argument {:?} may not follow an optional one",
+            name
+        );
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional: false,
+        });
+        self
+    }
+
+    /// Adds an argument which may be omitted, after those already added.
+    #[must_use]
+    pub fn optional_arg(mut self, name: &'static str, kind: ArgKind) -> Self {
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional: true,
+        });
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Explanation of what the command does, for help text.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn args(&self) -> &[ArgSpec] {
+        &self.args
+    }
+
+    /// Returns a summary of how to type this command, such as `/give <block>`.
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for arg in &self.args {
+            if arg.optional {
+                usage += &format!(" [{}]", arg.name);
+            } else {
+                usage += &format!(" <{}>", arg.name);
+            }
+        }
+        usage
+    }
+
+    fn parse_args<'c>(
+        &'c self,
+        words: &[&str],
+        universe: &Universe,
+    ) -> Result<Args<'c>, CommandError> {
+        let required = self.args.iter().filter(|arg| !arg.optional).count();
+        if words.len() < required || words.len() > self.args.len() {
+            return Err(CommandError::Usage(self.usage()));
+        }
+        let values = self
+            .args
+            .iter()
+            .zip(words)
+            .map(|(spec, &word)| {
+                spec.kind
+                    .parse(word, universe)
+                    .ok_or_else(|| CommandError::InvalidArgument {
+                        name: spec.name.to_owned(),
+                        expected: spec.kind.to_string(),
+                        value: word.to_owned(),
+                    })
+            })
+            .collect::<Result<Vec<ArgValue>, CommandError>>()?;
+        Ok(Args {
+            command: self,
+            words: words.iter().map(|&word| word.to_owned()).collect(),
+            values,
+        })
+    }
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Command")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("args", &self.args)
+            .finish()
+    }
+}
+
+/// Declaration of one argument of a [`Command`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct ArgSpec {
+    /// Name shown in [usage](Command::usage) text.
+    pub name: &'static str,
+    pub kind: ArgKind,
+    /// Whether the argument may be omitted. Only the last arguments may be optional.
+    pub optional: bool,
+}
+
+/// The type of an argument of a [`Command`], which determines how it is parsed and
+/// what completions are offered for it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ArgKind {
+    /// A number, parsed as [`ArgValue::Number`].
+    Number,
+    /// Any single word, parsed as [`ArgValue::Word`].
+    Word,
+    /// The name of a [`BlockDef`] in the universe, parsed as [`ArgValue::Block`].
+    Block,
+    /// The name of a [`Bookmark`] in the universe, parsed as [`ArgValue::Bookmark`].
+    Bookmark,
+    /// One of the given words, parsed as [`ArgValue::Keyword`].
+    Keyword(&'static [&'static str]),
+}
+
+impl ArgKind {
+    fn parse(self, word: &str, universe: &Universe) -> Option<ArgValue> {
+        match self {
+            ArgKind::Number => word
+                .parse::<FreeCoordinate>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .map(ArgValue::Number),
+            ArgKind::Word => Some(ArgValue::Word(word.to_owned())),
+            ArgKind::Block => universe
+                .get::<BlockDef>(&Name::from(word))
+                .map(|def| ArgValue::Block(Block::Indirect(def))),
+            ArgKind::Bookmark => universe
+                .bookmark(word)
+                .map(|bookmark| ArgValue::Bookmark(bookmark.clone())),
+            ArgKind::Keyword(keywords) => keywords
+                .iter()
+                .find(|&&keyword| keyword == word)
+                .map(|&keyword| ArgValue::Keyword(keyword)),
+        }
+    }
+
+    /// Returns all the values this argument could take, or an empty list if there are
+    /// too many to enumerate.
+    fn candidates(self, universe: &Universe) -> Vec<String> {
+        match self {
+            ArgKind::Number | ArgKind::Word => Vec::new(),
+            ArgKind::Block => UniverseIndex::<BlockDef>::iter_by_type(universe)
+                .filter_map(|(name, _)| match name {
+                    Name::Specific(name) => Some(name),
+                    Name::Anonym(_) => None,
+                })
+                .collect(),
+            ArgKind::Bookmark => universe
+                .bookmarks()
+                .map(|(name, _)| name.to_owned())
+                .collect(),
+            ArgKind::Keyword(keywords) => keywords.iter().map(|&k| k.to_owned()).collect(),
+        }
+    }
+}
+
+impl fmt::Display for ArgKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgKind::Number => write!(f, "a number"),
+            ArgKind::Word => write!(f, "a word"),
+            ArgKind::Block => write!(f, "the name of a block"),
+            ArgKind::Bookmark => write!(f, "the name of a bookmark"),
+            ArgKind::Keyword(keywords) => write!(f, "one of {}", keywords.join(", ")),
+        }
+    }
+}
+
+/// A parsed argument of a [`Command`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ArgValue {
+    Number(FreeCoordinate),
+    Word(String),
+    Block(Block),
+    Bookmark(Bookmark),
+    Keyword(&'static str),
+}
+
+/// The arguments passed to a [`Command`]'s handler, parsed according to its
+/// [`ArgSpec`]s.
+///
+/// The accessors for particular kinds of argument return a [`CommandError`] if the
+/// argument was omitted or is of a different kind, so that handlers may simply use `?`.
+#[derive(Debug)]
+pub struct Args<'c> {
+    command: &'c Command,
+    words: Vec<String>,
+    values: Vec<ArgValue>,
+}
+
+impl<'c> Args<'c> {
+    /// Returns the argument at `index`, or [`None`] if it was omitted.
+    pub fn get(&self, index: usize) -> Option<&ArgValue> {
+        self.values.get(index)
+    }
+
+    /// Returns the argument at `index` as it was typed.
+    pub fn word(&self, index: usize) -> Result<&str, CommandError> {
+        self.words
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| self.usage_error())
+    }
+
+    pub fn number(&self, index: usize) -> Result<FreeCoordinate, CommandError> {
+        match self.get(index) {
+            Some(&ArgValue::Number(value)) => Ok(value),
+            _ => Err(self.wrong_kind(index)),
+        }
+    }
+
+    pub fn block(&self, index: usize) -> Result<&Block, CommandError> {
+        match self.get(index) {
+            Some(ArgValue::Block(block)) => Ok(block),
+            _ => Err(self.wrong_kind(index)),
+        }
+    }
+
+    pub fn bookmark(&self, index: usize) -> Result<&Bookmark, CommandError> {
+        match self.get(index) {
+            Some(ArgValue::Bookmark(bookmark)) => Ok(bookmark),
+            _ => Err(self.wrong_kind(index)),
+        }
+    }
+
+    pub fn keyword(&self, index: usize) -> Result<&'static str, CommandError> {
+        match self.get(index) {
+            Some(&ArgValue::Keyword(keyword)) => Ok(keyword),
+            _ => Err(self.wrong_kind(index)),
+        }
+    }
+
+    /// Error for when the argument at `index` is not of the kind the handler expected,
+    /// which can only be because it was omitted or the handler does not match the
+    /// command's [`ArgSpec`]s.
+    fn wrong_kind(&self, index: usize) -> CommandError {
+        match (self.command.args.get(index), self.words.get(index)) {
+            (Some(spec), Some(word)) => CommandError::InvalidArgument {
+                name: spec.name.to_owned(),
+                expected: spec.kind.to_string(),
+                value: word.clone(),
+            },
+            _ => self.usage_error(),
+        }
+    }
+
+    /// Returns an error showing the usage of the command, for when the arguments are
+    /// each valid but do not make sense together.
+    pub fn usage_error(&self) -> CommandError {
+        CommandError::Usage(self.command.usage())
+    }
+}
+
+/// What a [`Command`]'s handler may act on.
+#[non_exhaustive]
+pub struct CommandContext<'a> {
+    pub universe: &'a mut Universe,
+    /// The character controlled by whoever typed the command, if any.
+    pub character: Option<URef<Character>>,
+    registry: &'a CommandRegistry,
+}
+
+impl<'a> CommandContext<'a> {
+    /// Returns [`Self::character`], or an error if there is none.
+    pub fn character(&self) -> Result<&URef<Character>, CommandError> {
+        self.character.as_ref().ok_or(CommandError::NoCharacter)
+    }
+
+    /// Returns the registry the command is being executed by.
+    pub fn registry(&self) -> &CommandRegistry {
+        self.registry
+    }
+}
+
+impl fmt::Debug for CommandContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandContext")
+            .field("character", &self.character)
+            .finish()
+    }
+}
+
+/// The result of a successfully executed [`Command`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct CommandOutput {
+    /// Text to show to the user who typed the command.
+    pub message: String,
+    /// Changes to make to the universe, which are made after the handler returns.
+    pub transaction: UniverseTransaction,
+}
+
+impl CommandOutput {
+    /// Constructs an output which only shows a message.
+    pub fn message(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            transaction: UniverseTransaction::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_transaction(mut self, transaction: UniverseTransaction) -> Self {
+        self.transaction = transaction;
+        self
+    }
+}
+
+/// Errors resulting from [`CommandRegistry::execute`].
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum CommandError {
+    /// The line contained no command.
+    #[error("no command given")]
+    Empty,
+    #[error("unknown command /{0}")]
+    UnknownCommand(String),
+    /// The command was given the wrong number or combination of arguments.
+    #[error("usage: {0}")]
+    Usage(String),
+    #[error("{name} must be {expected}, not {value:?}")]
+    InvalidArgument {
+        name: String,
+        expected: String,
+        value: String,
+    },
+    /// The command acts on a character, and there is none.
+    #[error("there is no character to act on")]
+    NoCharacter,
+    #[error("{0}")]
+    DataRefIs(#[from] RefError),
+    /// The command could not be carried out for some other reason.
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// The set of [`Command`]s available to a user, which parses and executes lines of
+/// text.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<String, Command>,
+}
+
+impl CommandRegistry {
+    /// Constructs a registry with no commands.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs a registry with the commands built in to the game:
+    /// `/help`, `/tp`, `/goto`, `/bookmark`, `/give`, and `/time`.
+    pub fn standard() -> Self {
+        let mut registry = Self::new();
+        registry.register(help_command());
+        registry.register(tp_command());
+        registry.register(goto_command());
+        registry.register(bookmark_command());
+        registry.register(give_command());
+        registry.register(time_command());
+        registry
+    }
+
+    /// Adds a command, replacing any existing command with the same name.
+    pub fn register(&mut self, command: Command) {
+        self.commands.insert(command.name.clone(), command);
+    }
+
+    /// Returns the command with the given name, if there is one.
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    /// Returns all commands, sorted by name.
+    pub fn commands(&self) -> impl Iterator<Item = &Command> + '_ {
+        self.commands.values()
+    }
+
+    /// Parses and executes `line`, which consists of a command name, optionally
+    /// preceded by `/`, and its arguments, separated by whitespace.
+    ///
+    /// `character` is the character controlled by whoever typed the line, if any.
+    /// Returns the message to show them.
+    pub fn execute(
+        &self,
+        line: &str,
+        universe: &mut Universe,
+        character: Option<&URef<Character>>,
+    ) -> Result<String, CommandError> {
+        let line = line.trim_start();
+        let mut words = line.strip_prefix('/').unwrap_or(line).split_whitespace();
+        let name = words.next().ok_or(CommandError::Empty)?;
+        let command = self
+            .get(name)
+            .ok_or_else(|| CommandError::UnknownCommand(name.to_owned()))?;
+        let words: Vec<&str> = words.collect();
+        let args = command.parse_args(&words, universe)?;
+
+        let mut context = CommandContext {
+            universe,
+            character: character.cloned(),
+            registry: self,
+        };
+        let output = (command.handler)(&mut context, &args)?;
+        output
+            .transaction
+            .execute(context.universe)
+            .map_err(|e| CommandError::Failed(e.to_string()))?;
+        Ok(output.message)
+    }
+
+    /// Returns the possible completions of the last word of `line`, which is
+    /// incomplete, sorted alphabetically. If `line` ends with whitespace, the word is
+    /// empty and every possible next word is returned.
+    ///
+    /// The leading `/` is not part of the command name, so completions of the command
+    /// name do not include it.
+    pub fn complete(&self, line: &str, universe: &Universe) -> Vec<String> {
+        let line = line.trim_start();
+        let line = line.strip_prefix('/').unwrap_or(line);
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if line.is_empty() || line.ends_with(char::is_whitespace) {
+            words.push("");
+        }
+        let (partial, previous) = words.split_last().expect("words is not empty");
+
+        let mut candidates: Vec<String> = match previous.split_first() {
+            None => self.commands.keys().cloned().collect(),
+            Some((name, args)) => match self.get(name).and_then(|c| c.args.get(args.len())) {
+                Some(spec) => spec.kind.candidates(universe),
+                None => Vec::new(),
+            },
+        };
+        candidates.retain(|candidate| candidate.starts_with(*partial));
+        candidates.sort();
+        candidates
+    }
+}
+
+impl fmt::Debug for CommandRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.commands.keys()).finish()
+    }
+}
+
+fn help_command() -> Command {
+    Command::new("help", "Lists the available commands.", |context, _| {
+        let lines: Vec<String> = context
+            .registry()
+            .commands()
+            .map(|command| format!("{} — {}", command.usage(), command.description()))
+            .collect();
+        Ok(CommandOutput::message(lines.join("\n")))
+    })
+}
+
+fn tp_command() -> Command {
+    Command::new(
+        "tp",
+        "Moves your character to the given coordinates, or as near as there is room.",
+        |context, args| {
+            let position = Point3::new(args.number(0)?, args.number(1)?, args.number(2)?);
+            let mut character = context.character()?.try_borrow_mut()?;
+            let space_ref = character.space.clone();
+            let actual = character.body.teleport(&*space_ref.try_borrow()?, position);
+            Ok(CommandOutput::message(format!(
+                "Teleported to {:?}",
+                actual.custom_format(ConciseDebug)
+            )))
+        },
+    )
+    .arg("x", ArgKind::Number)
+    .arg("y", ArgKind::Number)
+    .arg("z", ArgKind::Number)
+}
+
+fn goto_command() -> Command {
+    Command::new(
+        "goto",
+        "Moves your character to a bookmarked place.",
+        |context, args| {
+            context
+                .character()?
+                .try_borrow_mut()?
+                .teleport(args.bookmark(0)?)?;
+            Ok(CommandOutput::message(format!(
+                "Teleported to {}",
+                args.word(0)?
+            )))
+        },
+    )
+    .arg("bookmark", ArgKind::Bookmark)
+}
+
+fn bookmark_command() -> Command {
+    Command::new(
+        "bookmark",
+        "Bookmarks where your character is, to return to with /goto.",
+        |context, args| {
+            let bookmark = Bookmark::from_character(&*context.character()?.try_borrow()?);
+            let name = args.word(0)?;
+            let message = if context.universe.set_bookmark(name, bookmark).is_some() {
+                format!("Replaced bookmark {}", name)
+            } else {
+                format!("Added bookmark {}", name)
+            };
+            Ok(CommandOutput::message(message))
+        },
+    )
+    .arg("name", ArgKind::Word)
+}
+
+fn give_command() -> Command {
+    Command::new(
+        "give",
+        "Puts a block in your character's inventory.",
+        |context, args| {
+            let transaction = CharacterTransaction::inventory(InventoryTransaction::insert(
+                Tool::PlaceBlock(args.block(0)?.clone()),
+            ))
+            .bind(context.character()?.clone());
+            Ok(CommandOutput::message(format!("Gave {}", args.word(0)?))
+                .with_transaction(transaction))
+        },
+    )
+    .arg("block", ArgKind::Block)
+}
+
+fn time_command() -> Command {
+    Command::new(
+        "time",
+        "Sets how fast time passes in the world, relative to normal.",
+        |context, args| match args.keyword(0)? {
+            "set" => {
+                context.universe.set_time_scale(args.number(1)?);
+                Ok(CommandOutput::message(format!(
+                    "Time scale is now {}",
+                    context.universe.time_scale()
+                )))
+            }
+            _ => Err(args.usage_error()),
+        },
+    )
+    .arg("action", ArgKind::Keyword(&["set"]))
+    .arg("scale", ArgKind::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraPose;
+    use crate::space::Space;
+
+    fn universe_with_character() -> (Universe, URef<Character>) {
+        let mut universe = Universe::new();
+        let space = universe.insert_anonymous(Space::empty_positive(10, 10, 10));
+        let character = universe.insert_anonymous(Character::spawn_default(space));
+        (universe, character)
+    }
+
+    #[test]
+    fn tp_and_bookmarks() {
+        let commands = CommandRegistry::standard();
+        let (mut universe, character) = universe_with_character();
+
+        commands
+            .execute("/tp 1 2 3", &mut universe, Some(&character))
+            .unwrap();
+        assert_eq!(character.borrow().body.position, Point3::new(1.0, 2.0, 3.0));
+
+        assert_eq!(
+            commands.execute("bookmark home", &mut universe, Some(&character)),
+            Ok("Added bookmark home".to_owned())
+        );
+        commands
+            .execute("/tp 4 5 6", &mut universe, Some(&character))
+            .unwrap();
+        assert_eq!(
+            commands.execute("/goto home", &mut universe, Some(&character)),
+            Ok("Teleported to home".to_owned())
+        );
+        assert_eq!(character.borrow().body.position, Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(
+            commands.execute("/tp 4 5 6", &mut universe, None),
+            Err(CommandError::NoCharacter)
+        );
+    }
+
+    #[test]
+    fn give_executes_transaction() {
+        let commands = CommandRegistry::standard();
+        let (mut universe, character) = universe_with_character();
+        let def = universe
+            .insert(
+                "stone".into(),
+                BlockDef::new(Block::from(crate::math::Rgba::WHITE)),
+            )
+            .unwrap();
+
+        assert_eq!(
+            commands.execute("/give stone", &mut universe, Some(&character)),
+            Ok("Gave stone".to_owned())
+        );
+        assert!(character
+            .borrow()
+            .inventory()
+            .slots
+            .contains(&Tool::PlaceBlock(Block::Indirect(def))));
+    }
+
+    #[test]
+    fn errors() {
+        let commands = CommandRegistry::standard();
+        let mut universe = Universe::new();
+        let mut execute = |line: &str| commands.execute(line, &mut universe, None);
+        assert_eq!(execute("  "), Err(CommandError::Empty));
+        assert_eq!(
+            execute("/fly"),
+            Err(CommandError::UnknownCommand("fly".into()))
+        );
+        assert_eq!(
+            execute("/tp 1 2"),
+            Err(CommandError::Usage("/tp <x> <y> <z>".into()))
+        );
+        assert_eq!(
+            execute("/time set fast"),
+            Err(CommandError::InvalidArgument {
+                name: "scale".into(),
+                expected: "a number".into(),
+                value: "fast".into(),
+            })
+        );
+        assert_eq!(
+            execute("/give stone"),
+            Err(CommandError::InvalidArgument {
+                name: "block".into(),
+                expected: "the name of a block".into(),
+                value: "stone".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn omitted_optional_arg_is_usage_error() {
+        let mut commands = CommandRegistry::new();
+        commands.register(
+            Command::new("count", "Counts.", |_, args| {
+                Ok(CommandOutput::message(args.number(0)?.to_string()))
+            })
+            .optional_arg("n", ArgKind::Number),
+        );
+        let mut universe = Universe::new();
+        assert_eq!(
+            commands.execute("/count 3", &mut universe, None),
+            Ok("3".to_owned())
+        );
+        assert_eq!(
+            commands.execute("/count", &mut universe, None),
+            Err(CommandError::Usage("/count [n]".into()))
+        );
+    }
+
+    #[test]
+    fn completion() {
+        let commands = CommandRegistry::standard();
+        let (mut universe, character) = universe_with_character();
+        let space = character.borrow().space.clone();
+        for name in &["beach", "base", "castle"] {
+            universe.set_bookmark(
+                *name,
+                Bookmark::new(space.clone(), CameraPose::new([0.0, 0.0, 0.0], 0.0, 0.0)),
+            );
+        }
+
+        assert_eq!(commands.complete("/g", &universe), vec!["give", "goto"]);
+        assert_eq!(
+            commands.complete("/goto b", &universe),
+            vec!["base", "beach"]
+        );
+        assert_eq!(commands.complete("/goto ", &universe).len(), 3);
+        assert_eq!(commands.complete("/time ", &universe), vec!["set"]);
+        assert!(commands.complete("/time set ", &universe).is_empty());
+        assert!(commands.complete("/nonsense ", &universe).is_empty());
+        assert_eq!(commands.complete("", &universe).len(), 6);
+    }
+}
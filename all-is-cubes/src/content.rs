@@ -2,88 +2,49 @@
 // in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
 
 //! Built-in content: either demos or basic shapes and colors used in the UI.
+//!
+//! Only [`palette`] and the simple block and space generators in this module itself are
+//! available without the `content` package feature.
 
 use cgmath::{Vector3, Vector4};
-use embedded_graphics::mono_font::iso_8859_1::FONT_9X15_BOLD;
-use embedded_graphics::mono_font::MonoTextStyle;
-use embedded_graphics::prelude::{Dimensions as _, Drawable, Point};
-use embedded_graphics::text::Alignment;
-use embedded_graphics::text::Baseline;
-use embedded_graphics::text::Text;
-use embedded_graphics::text::TextStyleBuilder;
 use std::borrow::Cow;
 use std::convert::{TryFrom, TryInto};
 
-use crate::block::Block;
-use crate::drawing::VoxelBrush;
-use crate::math::{FaceMap, FreeCoordinate, GridCoordinate, GridMatrix, Rgb, Rgba};
+use crate::block::{Block, Resolution};
+use crate::math::{FreeCoordinate, GridCoordinate, Rgb, Rgba};
 use crate::raycast::{Face, Raycaster};
 use crate::space::{Grid, SetCubeError, Space};
 use crate::universe::Universe;
 
+#[cfg(feature = "content")]
 mod blocks;
+#[cfg(feature = "content")]
 pub use blocks::*;
+#[cfg(feature = "content")]
 mod city;
-pub(crate) use city::*;
+#[cfg(feature = "content")]
+pub use city::*;
+#[cfg(feature = "content")]
 mod demo;
+#[cfg(feature = "content")]
 pub use demo::*;
+#[cfg(feature = "content")]
 mod exhibits;
+#[cfg(feature = "content")]
 pub(crate) use exhibits::*;
+#[cfg(feature = "content")]
 mod landscape;
+#[cfg(feature = "content")]
 pub use landscape::*;
+#[cfg(feature = "content")]
+mod logo;
+#[cfg(feature = "content")]
+pub use logo::*;
 pub mod palette;
-
-/// Draw the All Is Cubes logo text.
-pub fn logo_text(midpoint_transform: GridMatrix, space: &mut Space) -> Result<(), SetCubeError> {
-    logo_text_drawable(|d| {
-        d.draw(&mut space.draw_target(midpoint_transform * GridMatrix::FLIP_Y))
-    })?;
-    Ok(())
-}
-
-pub fn logo_text_extent() -> Grid {
-    logo_text_drawable(|d| {
-        let bounding_box = d.bounding_box();
-        let top_left_2d = bounding_box.top_left;
-        let bottom_right_2d = bounding_box.bottom_right().unwrap();
-        Grid::from_lower_upper(
-            [top_left_2d.x, -(bottom_right_2d.y - 1), 0],
-            [bottom_right_2d.x - 1, -top_left_2d.y, 2],
-        )
-        .expand(FaceMap::from_fn(|f| {
-            // Expand horizontally due to the VoxelBrush's size. TODO: We should be able to ask the brush to do this.
-            ([Face::PX, Face::PY, Face::NX, Face::NY].contains(&f)) as GridCoordinate
-        }))
-    })
-}
-
-/// Calls the given function with `Drawable` logo text.
-/// Unfortunately there is no way to return an owned Drawable.
-fn logo_text_drawable<F, R>(f: F) -> R
-where
-    F: for<'a> FnOnce(Text<'static, MonoTextStyle<'a, &VoxelBrush<'a>>>) -> R,
-{
-    let foreground_text_block: Block = palette::LOGO_FILL.into();
-    let background_text_block: Block = palette::LOGO_STROKE.into();
-    let brush = VoxelBrush::new(vec![
-        ((0, 0, 1), &foreground_text_block),
-        ((1, 0, 0), &background_text_block),
-        ((-1, 0, 0), &background_text_block),
-        ((0, 1, 0), &background_text_block),
-        ((0, -1, 0), &background_text_block),
-    ]);
-
-    let text = Text::with_text_style(
-        "All is Cubes",
-        Point::new(0, 0),
-        MonoTextStyle::new(&FONT_9X15_BOLD, &brush),
-        TextStyleBuilder::new()
-            .alignment(Alignment::Center)
-            .baseline(Baseline::Middle)
-            .build(),
-    );
-    f(text)
-}
+#[cfg(feature = "content")]
+pub mod texgen;
+#[cfg(feature = "content")]
+pub mod worldgen;
 
 /// Generate a set of distinct [`Block::Atom`] blocks for use in tests.
 /// They will have distinct colors and names, and all other attributes default.
@@ -137,21 +98,7 @@ pub fn make_some_voxel_blocks<const COUNT: usize>(universe: &mut Universe) -> [B
                 .fill_uniform(block_space.grid(), Block::from(color))
                 .unwrap();
             axes(&mut block_space).unwrap();
-            for &face in Face::ALL_SIX {
-                Text::with_text_style(
-                    &i.to_string(),
-                    Point::new(i32::from(resolution / 2), i32::from(resolution / 2)),
-                    MonoTextStyle::new(&FONT_9X15_BOLD, palette::ALMOST_BLACK),
-                    TextStyleBuilder::new()
-                        .baseline(Baseline::Middle)
-                        .alignment(Alignment::Center)
-                        .build(),
-                )
-                .draw(
-                    &mut block_space.draw_target(face.matrix(GridCoordinate::from(resolution) - 1)),
-                )
-                .unwrap();
-            }
+            draw_face_labels(&mut block_space, resolution, &i.to_string());
 
             Block::builder()
                 .display_name(i.to_string())
@@ -163,6 +110,34 @@ pub fn make_some_voxel_blocks<const COUNT: usize>(universe: &mut Universe) -> [B
         .unwrap()
 }
 
+/// Draws `label` centered on each face of `block_space`, for [`make_some_voxel_blocks`].
+#[cfg(feature = "content")]
+fn draw_face_labels(block_space: &mut Space, resolution: Resolution, label: &str) {
+    use embedded_graphics::mono_font::iso_8859_1::FONT_9X15_BOLD;
+    use embedded_graphics::mono_font::MonoTextStyle;
+    use embedded_graphics::prelude::{Drawable, Point};
+    use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+    for &face in Face::ALL_SIX {
+        Text::with_text_style(
+            label,
+            Point::new(i32::from(resolution / 2), i32::from(resolution / 2)),
+            MonoTextStyle::new(&FONT_9X15_BOLD, palette::ALMOST_BLACK),
+            TextStyleBuilder::new()
+                .baseline(Baseline::Middle)
+                .alignment(Alignment::Center)
+                .build(),
+        )
+        .draw(&mut block_space.draw_target(face.matrix(GridCoordinate::from(resolution) - 1)))
+        .unwrap();
+    }
+}
+
+/// Without the `content` feature there is no text drawing, so the blocks from
+/// [`make_some_voxel_blocks`] are distinguished by color alone.
+#[cfg(not(feature = "content"))]
+fn draw_face_labels(_block_space: &mut Space, _resolution: Resolution, _label: &str) {}
+
 fn color_sequence_for_make_blocks(n: usize) -> impl Iterator<Item = (usize, Rgba)> {
     (0..n).map(move |i| {
         let luminance = if n > 1 {
@@ -7,15 +7,15 @@
 use cgmath::{ElementWise as _, EuclideanSpace as _};
 use embedded_graphics::prelude::Point;
 use embedded_graphics::primitives::{Line, PrimitiveStyle, Rectangle, StyledDrawable};
-use noise::Seedable as _;
 
 use crate::block::{Block, BlockCollision, AIR};
-use crate::content::landscape::install_landscape_blocks;
+use crate::content::landscape::install_landscape_blocks_with_layers;
 use crate::content::palette;
-use crate::linking::{BlockModule, BlockProvider, GenError, InGenError};
+use crate::content::texgen::{speckle, uniform};
+use crate::linking::{BlockLayer, BlockModule, BlockProvider, GenError, InGenError};
 use crate::math::{
-    int_magnitude_squared, GridCoordinate, GridMatrix, GridPoint, GridRotation, GridVector,
-    NoiseFnExt as _, NotNan, Rgb, Rgba,
+    int_magnitude_squared, Face, FaceMap, GridCoordinate, GridMatrix, GridPoint, GridRotation,
+    GridVector, NotNan, Rgb, Rgba,
 };
 use crate::space::{Grid, Space};
 use crate::universe::Universe;
@@ -41,6 +41,15 @@ impl BlockModule for DemoBlocks {
 
 /// Add to `universe` demo-content blocks, that might be used by demo worldgen or offered to the player.
 pub fn install_demo_blocks(universe: &mut Universe) -> Result<(), GenError> {
+    install_demo_blocks_with_layers(universe, &[])
+}
+
+/// As [`install_demo_blocks`], but with some of the [`DemoBlocks`] and
+/// [`LandscapeBlocks`](crate::content::LandscapeBlocks) replaced by those in `layers`.
+pub fn install_demo_blocks_with_layers(
+    universe: &mut Universe,
+    layers: &[BlockLayer],
+) -> Result<(), GenError> {
     let resolution = 16;
     let resolution_g = GridCoordinate::from(resolution);
 
@@ -52,15 +61,13 @@ pub fn install_demo_blocks(universe: &mut Universe) -> Result<(), GenError> {
     let one_diagonal = GridVector::new(1, 1, 1);
     let center_point_doubled = GridPoint::from_vec(one_diagonal * resolution_g);
 
-    install_landscape_blocks(universe, resolution)?;
+    install_landscape_blocks_with_layers(universe, resolution, layers)?;
 
     use DemoBlocks::*;
     let road_color: Block = Rgba::new(0.157, 0.130, 0.154, 1.0).into();
     let curb_color: Block = Rgba::new(0.788, 0.765, 0.741, 1.0).into();
-    let road_noise_v = noise::Value::new().set_seed(0x52b19f6a);
-    let road_noise = noise::ScaleBias::new(&road_noise_v)
-        .set_bias(1.0)
-        .set_scale(0.12);
+    let road_pattern = speckle(uniform(road_color), 0x52b19f6a, 0.12);
+    let curb_pattern = speckle(uniform(curb_color), 0x52b19f6a, 0.12);
 
     let curb_fn = |cube: GridPoint| {
         let width = resolution_g / 3;
@@ -68,23 +75,27 @@ pub fn install_demo_blocks(universe: &mut Universe) -> Result<(), GenError> {
             (cube - GridPoint::new(width / 2 + 2, 0, 0)).mul_element_wise(GridVector::new(1, 2, 0)),
         ) < width.pow(2)
         {
-            scale_color(curb_color.clone(), road_noise.at_grid(cube), 0.02)
+            curb_pattern(cube)
         } else {
             AIR
         }
     };
 
+    let corner_rotations: Vec<GridMatrix> = GridRotation::CLOCKWISE
+        .iterate()
+        .map(|rotation| rotation.to_positive_octant_matrix(resolution.into()))
+        .collect();
+
     BlockProvider::<DemoBlocks>::new(|key| {
         Ok(match key {
             Road => Block::builder()
                 .display_name("Road")
-                .voxels_fn(universe, resolution, |cube| {
-                    scale_color(road_color.clone(), road_noise.at_grid(cube), 0.02)
-                })?
+                .voxels_fn(universe, resolution, &road_pattern)?
                 .build(),
 
             Lamp => Block::builder()
                 .display_name("Lamp")
+                .tag("light")
                 .light_emission(Rgb::new(20.0, 20.0, 20.0))
                 .voxels_fn(universe, resolution, |p| {
                     if int_magnitude_squared(p * 2 + one_diagonal - center_point_doubled)
@@ -99,6 +110,7 @@ pub fn install_demo_blocks(universe: &mut Universe) -> Result<(), GenError> {
 
             Lamppost => Block::builder()
                 .display_name("Lamppost")
+                .tag("light")
                 .light_emission(Rgb::new(3.0, 3.0, 3.0))
                 .voxels_fn(universe, resolution, |p| {
                     if int_magnitude_squared(
@@ -115,7 +127,15 @@ pub fn install_demo_blocks(universe: &mut Universe) -> Result<(), GenError> {
 
             Sconce => Block::builder()
                 .display_name("Sconce")
-                .light_emission(Rgb::new(8.0, 7.0, 6.0))
+                .tag("light")
+                // Mounted on a wall on the -Z side, which shouldn't be lit from behind.
+                .light_emission_faces(FaceMap::from_fn(|face| {
+                    if face == Face::NZ {
+                        Rgb::ZERO
+                    } else {
+                        Rgb::new(8.0, 7.0, 6.0)
+                    }
+                }))
                 .voxels_fn(universe, resolution, |p| {
                     // TODO: fancier appearance with a bracket
                     if int_magnitude_squared(
@@ -141,13 +161,10 @@ pub fn install_demo_blocks(universe: &mut Universe) -> Result<(), GenError> {
                 .display_name("Curb Corner")
                 .collision(BlockCollision::None) // TODO: make solid when we have voxel-level collision
                 .voxels_fn(universe, resolution, |cube| {
-                    // TODO: rework so this isn't redoing the rotation calculations for every single voxel
-                    // We should have tools for composing blocks instead...
-                    for rot in GridRotation::CLOCKWISE.iterate() {
-                        let block = curb_fn(
-                            rot.to_positive_octant_matrix(resolution.into())
-                                .transform_cube(cube),
-                        );
+                    // This is the union of the curb rotated to each side, which
+                    // Space::rotate_copies() can't do since its copies overwrite.
+                    for rotation in &corner_rotations {
+                        let block = curb_fn(rotation.transform_cube(cube));
                         if block != AIR {
                             return block;
                         }
@@ -224,7 +241,7 @@ pub fn install_demo_blocks(universe: &mut Universe) -> Result<(), GenError> {
             }
         })
     })?
-    .install(universe)?;
+    .install_layered(layers, universe)?;
 
     Ok(())
 }
@@ -256,4 +273,36 @@ mod tests {
         install_demo_blocks(&mut universe).unwrap();
         // TODO: assert what entries were created, once Universe has iteration
     }
+
+    #[test]
+    fn install_demo_blocks_with_override() {
+        use crate::content::LandscapeBlocks;
+        let [grass, road] = crate::content::make_some_blocks();
+        let mut universe = Universe::new();
+        install_demo_blocks_with_layers(
+            &mut universe,
+            &[BlockLayer::new("test")
+                .with(LandscapeBlocks::Grass, grass.clone())
+                .with(DemoBlocks::Road, road.clone())],
+        )
+        .unwrap();
+        let landscape = BlockProvider::<LandscapeBlocks>::using(&universe).unwrap();
+        let demo = BlockProvider::<DemoBlocks>::using(&universe).unwrap();
+        assert_eq!(
+            landscape[LandscapeBlocks::Grass].evaluate().unwrap(),
+            grass.evaluate().unwrap()
+        );
+        assert_eq!(
+            demo[DemoBlocks::Road].evaluate().unwrap(),
+            road.evaluate().unwrap()
+        );
+        assert_eq!(
+            landscape[LandscapeBlocks::Stone]
+                .evaluate()
+                .unwrap()
+                .attributes
+                .display_name,
+            "Stone"
+        );
+    }
 }
@@ -2,9 +2,10 @@
 // in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
 
 //! A space with miscellaneous demonstrations/tests of functionality.
-//! The individual buildings/exhibits are defined in [`DEMO_CITY_EXHIBITS`].
+//! The individual buildings/exhibits are listed in a [`Gallery`]; the built-in ones
+//! are defined in [`DEMO_CITY_EXHIBITS`].
 
-use cgmath::{EuclideanSpace as _, One as _, Transform as _, Vector3};
+use cgmath::{Transform as _, Vector3};
 use embedded_graphics::geometry::Point;
 use embedded_graphics::mono_font::iso_8859_1::FONT_9X18_BOLD;
 use embedded_graphics::mono_font::MonoTextStyle;
@@ -19,19 +20,29 @@ use ordered_float::NotNan;
 use crate::block::Resolution;
 use crate::block::{BlockAttributes, BlockCollision, AIR};
 use crate::content::palette;
-use crate::content::{logo_text, wavy_landscape, DemoBlocks, LandscapeBlocks, DEMO_CITY_EXHIBITS};
-use crate::drawing::{draw_to_blocks, VoxelBrush};
-use crate::linking::{BlockProvider, InGenError};
+use crate::content::worldgen::{CityLayout, RoadNetwork, Worldgen};
+use crate::content::{logo_text, DemoBlocks, LandscapeBlocks, DEMO_CITY_EXHIBITS};
+use crate::drawing::draw_to_blocks;
+use crate::linking::{BlockProvider, GenError, InGenError};
 use crate::math::{
-    Face, FaceMap, FreeCoordinate, GridCoordinate, GridMatrix, GridPoint, GridRotation, GridVector,
-    NoiseFnExt as _, Rgb,
+    Face, FaceMap, GridCoordinate, GridMatrix, GridRotation, GridVector, NoiseFnExt as _, Rgb,
 };
-use crate::raycast::Raycaster;
-use crate::space::{Grid, SetCubeError, Space, SpacePhysics};
+use crate::space::{Grid, SetCubeError, SkyGradient, Skybox, Space, SpacePhysics};
 use crate::tools::Tool;
-use crate::universe::Universe;
-
-pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
+use crate::universe::{Name, Universe};
+use crate::util::WarningsResult;
+
+/// Generates the demo city: roads, lamps, a landscape, and one plot for each of the
+/// exhibits in `gallery`, in order, with its name on a sign in front of it.
+///
+/// A landscape pass or exhibit which fails does not stop the rest of the city from
+/// being generated; instead, the failure is returned as a warning naming the pass or
+/// exhibit, and the city is left without (some or all of) it. Errors are returned only
+/// when the basic structure of the city cannot be built.
+pub fn demo_city(
+    universe: &mut Universe,
+    gallery: &Gallery,
+) -> Result<WarningsResult<Space, GenError>, InGenError> {
     let start_city_time = Instant::now();
 
     let landscape_blocks = BlockProvider::<LandscapeBlocks>::using(universe)?;
@@ -40,10 +51,9 @@ pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
     use LandscapeBlocks::*;
 
     // Layout parameters
-    // TODO: move to CityPlanner
-    let road_radius = CityPlanner::ROAD_RADIUS;
-    let lamp_position_radius = CityPlanner::LAMP_POSITION_RADIUS;
-    let exhibit_front_radius = CityPlanner::PLOT_FRONT_RADIUS;
+    let road_radius = CityLayout::ROAD_RADIUS;
+    let lamp_position_radius = CityLayout::LAMP_POSITION_RADIUS;
+    let exhibit_front_radius = CityLayout::PLOT_FRONT_RADIUS;
     let lamp_spacing = 20;
     let sky_height = 30;
     let ground_depth = 30; // TODO: wavy_landscape is forcing us to have extra symmetry here
@@ -54,20 +64,23 @@ pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
         (radius_xz, sky_height, radius_xz),
     );
 
-    let mut planner = CityPlanner::new(grid);
-
-    // Prepare brushes.
-    let lamp_brush = VoxelBrush::new(vec![
-        ((0, 0, 0), &demo_blocks[Lamppost]),
-        ((0, 1, 0), &demo_blocks[Lamppost]),
-        ((0, 2, 0), &demo_blocks[Lamppost]),
-        ((0, 3, 0), &demo_blocks[Lamp]),
-    ]);
+    let mut layout =
+        CityLayout::new(grid, RoadNetwork::cross(grid, [0, 0, 0])).lamp_spacing(lamp_spacing);
 
     // Construct space.
     let mut space = Space::empty(grid);
+    let horizon = Rgb::new(1.0, 1.0, 1.2);
+    let zenith = Rgb::new(0.7, 0.7, 1.6);
     space.set_physics(SpacePhysics {
         sky_color: Rgb::new(0.9, 0.9, 1.4),
+        skybox: Some(Skybox::Gradients(FaceMap {
+            py: SkyGradient::uniform(zenith),
+            ny: SkyGradient::uniform(horizon),
+            ..FaceMap::repeat(SkyGradient {
+                below: horizon,
+                above: zenith,
+            })
+        })),
         ..SpacePhysics::default()
     });
 
@@ -90,8 +103,8 @@ pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
     }
 
     // Fill basic layers, underground and top
-    space.fill_uniform(planner.y_range(-ground_depth, 0), &landscape_blocks[Stone])?;
-    space.fill_uniform(planner.y_range(0, 1), &landscape_blocks[Grass])?;
+    space.fill_uniform(y_range(grid, -ground_depth, 0), &landscape_blocks[Stone])?;
+    space.fill_uniform(y_range(grid, 0, 1), &landscape_blocks[Grass])?;
 
     // Stray grass
     let grass_noise_v = noise::OpenSimplex::new().set_seed(0x21b5cc6b);
@@ -116,53 +129,21 @@ pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
     )?;
 
     // Roads and lamps
-    for &face in &[Face::PX, Face::NX, Face::PZ, Face::NZ] {
-        let forward: GridVector = face.normal_vector();
-        let perpendicular: GridVector = forward.cross(Face::PY.normal_vector());
-        // TODO: should be able to express this in look-at terms.
+    layout.build_roads(&mut space, &demo_blocks)?;
+    for road in layout.network().roads() {
+        let face = road.direction();
+        let perpendicular = road.perpendicular();
         let road_aligned_rotation =
             GridRotation::from_basis([face.cross(Face::PY), Face::PY, face]);
         let other_side_of_road =
             GridRotation::from_basis([Face::NX, Face::PY, Face::NZ]) * road_aligned_rotation;
         let rotations = [other_side_of_road, road_aligned_rotation];
-        let raycaster = Raycaster::new((0.5, 0.5, 0.5), face.normal_vector::<FreeCoordinate>())
-            .within_grid(space.grid());
-        let curb_y = GridVector::unit_y();
-        for (i, step) in raycaster.enumerate() {
-            let i = i as GridCoordinate;
-            // Road surface
-            for p in -road_radius..=road_radius {
-                space.set(step.cube_ahead() + perpendicular * p, &demo_blocks[Road])?;
-            }
-
-            // Curbs
-            if i > road_radius {
-                for (side, &p) in [-(road_radius + 1), road_radius + 1].iter().enumerate() {
-                    space.set(
-                        step.cube_ahead() + perpendicular * p + curb_y,
-                        demo_blocks[Curb].clone().rotate(rotations[side]),
-                    )?;
-                }
-            }
-
-            // Lampposts
-            if (i - lamp_position_radius) % lamp_spacing == 0 {
-                for p in &[-lamp_position_radius, lamp_position_radius] {
-                    lamp_brush.paint(
-                        &mut space,
-                        step.cube_ahead() + GridVector::new(0, 1, 0) + perpendicular * *p,
-                    )?;
-                }
-            }
-
+        for (i, cube) in road.center_line() {
             // Dig underground passages
             // TODO: They need a connection to the surface
             for p in -road_radius..=road_radius {
                 for z in underground_floor_y..0 {
-                    space.set(
-                        step.cube_ahead() + perpendicular * p + Vector3::unit_y() * z,
-                        &AIR,
-                    )?;
+                    space.set(cube + perpendicular * p + Vector3::unit_y() * z, &AIR)?;
                 }
             }
 
@@ -171,7 +152,7 @@ pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
                 // Underground lamps
                 for (side, &p) in [-road_radius, road_radius].iter().enumerate() {
                     space.set(
-                        step.cube_ahead() + GridVector::new(0, -2, 0) + perpendicular * p,
+                        cube + GridVector::new(0, -2, 0) + perpendicular * p,
                         demo_blocks[Sconce]
                             .clone()
                             .rotate(GridRotation::RzYX * rotations[side]),
@@ -179,14 +160,6 @@ pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
                 }
             }
         }
-
-        // Patch up curb corners
-        for &p in &[-(road_radius + 1), road_radius + 1] {
-            space.set(
-                GridPoint::origin() + curb_y + forward * (road_radius + 1) + perpendicular * p,
-                &demo_blocks[CurbCorner],
-            )?;
-        }
     }
 
     let blank_city_time = Instant::now();
@@ -197,14 +170,18 @@ pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
             .as_secs_f32()
     );
 
+    let mut warnings = Vec::new();
+
     // Landscape filling one quadrant
     let landscape_region = Grid::from_lower_upper(
         [-radius_xz, -ground_depth * 8 / 10, -radius_xz],
         [-exhibit_front_radius, sky_height, -exhibit_front_radius],
     );
-    space.fill_uniform(landscape_region, AIR)?;
-    wavy_landscape(landscape_region, &mut space, &landscape_blocks, 1.0)?;
-    planner.occupied_plots.push(landscape_region);
+    gallery
+        .landscape()
+        .generate(&mut space, &landscape_blocks, landscape_region)
+        .collect_warnings(&mut warnings);
+    layout.occupy(landscape_region);
 
     let landscape_time = Instant::now();
     log::trace!(
@@ -213,98 +190,12 @@ pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
     );
 
     // Exhibits
-    for exhibit in DEMO_CITY_EXHIBITS.iter() {
+    for exhibit in gallery.exhibits() {
         let start_exhibit_time = Instant::now();
-        let exhibit_space = (exhibit.factory)(exhibit, universe)
-            .expect("exhibit generation failure. TODO: place an error marker and continue instead");
-        let exhibit_footprint = exhibit_space.grid();
-
-        let enclosure_footprint = exhibit_footprint.expand(FaceMap::repeat(1));
-
-        let plot_transform = planner
-            .find_plot(enclosure_footprint)
-            .expect("Out of city space!");
-        let (plot_rotation, _) = plot_transform.decompose().unwrap();
-        let plot = exhibit_footprint.transform(plot_transform).unwrap();
-
-        // Mark the exhibit bounds
-        let enclosure = Grid::from_lower_upper(
-            plot.lower_bounds().map(|x| x - 1),
-            [
-                plot.upper_bounds().x + 1,
-                1.max(plot.lower_bounds()[1]), // handles case where plot is floating
-                plot.upper_bounds().z + 1,
-            ],
-        );
-        space.fill_uniform(enclosure, &demo_blocks[ExhibitBackground])?;
-
-        // TODO: Add "entrances" so it's clear what the "front" of the exhibit is supposed to be.
-
-        // Draw exhibit name
-        let name_transform = GridMatrix::from_translation([
-            exhibit_footprint.lower_bounds().x - 1,
-            0,
-            exhibit_footprint.upper_bounds().z + 1,
-        ]);
-        let name_block_resolution = 32;
-        let font = &FONT_9X18_BOLD;
-        let name_bottom_y =
-            (name_block_resolution - font.character_size.height as GridCoordinate) / 2;
-        let name_text = Text::with_baseline(
-            exhibit.name,
-            Point::new(0, -name_bottom_y),
-            MonoTextStyle::new(font, palette::ALMOST_BLACK),
-            Baseline::Bottom,
-        );
-        // TODO: This is an awful lot of code to benerate "text is centered on a number of whole blocks"
-        let name_width = name_text.bounding_box().size.width as GridCoordinate;
-        let name_width_in_blocks: GridCoordinate =
-            (name_width + name_block_resolution - 1) / name_block_resolution; // rounding up
-        let name_blocks = draw_to_blocks(
-            universe,
-            name_block_resolution as Resolution,
-            0,
-            0..1,
-            BlockAttributes {
-                display_name: format!("Exhibit name {:?}", exhibit.name).into(),
-                collision: BlockCollision::None,
-                ..BlockAttributes::default()
-            },
-            &name_text.translate(Point::new(
-                ((name_width_in_blocks * name_block_resolution) - name_width) / 2,
-                0,
-            )),
-        )
-        .expect("name drawing failure");
-        // Truncate name to not overrun the exhibit itself
-        let truncated_name_grid = name_blocks
-            .grid()
-            .intersection(Grid::new([0, 0, 0], [exhibit_footprint.size().x + 3, 1, 1]))
-            .unwrap();
-        space_to_space_copy(
-            &name_blocks,
-            truncated_name_grid,
-            &mut space,
-            plot_transform * name_transform,
-        )?; // TODO: on failure, place an error marker and continue
-        space.fill_uniform(
-            truncated_name_grid
-                .transform(
-                    plot_transform * name_transform * GridMatrix::from_translation([0, 0, -1]),
-                )
-                .unwrap(),
-            demo_blocks[Signboard]
-                .clone()
-                .rotate(plot_rotation.inverse()),
-        )?;
-
-        // Place exhibit content
-        space_to_space_copy(
-            &exhibit_space,
-            exhibit_footprint,
-            &mut space,
-            plot_transform,
-        )?; // TODO: on failure, place an error marker and continue
+        if let Err(e) = place_exhibit(exhibit, universe, &mut space, &mut layout, &demo_blocks) {
+            warnings.push(GenError::failure(e, Name::from(exhibit.name)));
+            continue;
+        }
 
         // Log build time
         let exhibit_time = Instant::now().duration_since(start_exhibit_time);
@@ -330,128 +221,225 @@ pub(crate) fn demo_city(universe: &mut Universe) -> Result<Space, InGenError> {
         &mut space,
     )?;
 
-    Ok(space)
+    Ok(WarningsResult::with_warnings(space, warnings))
+}
+
+/// Generates `exhibit` and places it, with its name sign, on a plot found in `layout`.
+fn place_exhibit(
+    exhibit: &Exhibit,
+    universe: &mut Universe,
+    space: &mut Space,
+    layout: &mut CityLayout,
+    demo_blocks: &BlockProvider<DemoBlocks>,
+) -> Result<(), InGenError> {
+    use DemoBlocks::*;
+
+    let exhibit_space = (exhibit.factory)(exhibit, universe)?;
+    let exhibit_footprint = exhibit.footprint.unwrap_or_else(|| exhibit_space.grid());
+    if !exhibit_footprint.contains_grid(exhibit_space.grid()) {
+        return Err(InGenError::other(ExhibitError::DoesNotFit {
+            generated: exhibit_space.grid(),
+            footprint: exhibit_footprint,
+        }));
+    }
+
+    let enclosure_footprint = exhibit_footprint.expand(FaceMap::repeat(1));
+
+    let plot_transform = layout
+        .find_plot(enclosure_footprint)
+        .ok_or_else(|| InGenError::other(ExhibitError::OutOfSpace))?;
+    let (plot_rotation, _) = plot_transform.decompose().unwrap();
+    let plot = exhibit_footprint.transform(plot_transform).unwrap();
+
+    // Mark the exhibit bounds
+    let enclosure = Grid::from_lower_upper(
+        plot.lower_bounds().map(|x| x - 1),
+        [
+            plot.upper_bounds().x + 1,
+            1.max(plot.lower_bounds()[1]), // handles case where plot is floating
+            plot.upper_bounds().z + 1,
+        ],
+    );
+    space.fill_uniform(enclosure, &demo_blocks[ExhibitBackground])?;
+
+    // TODO: Add "entrances" so it's clear what the "front" of the exhibit is supposed to be.
+
+    // Draw exhibit name
+    let name_transform = GridMatrix::from_translation([
+        exhibit_footprint.lower_bounds().x - 1,
+        0,
+        exhibit_footprint.upper_bounds().z + 1,
+    ]);
+    let name_block_resolution = 32;
+    let font = &FONT_9X18_BOLD;
+    let name_bottom_y = (name_block_resolution - font.character_size.height as GridCoordinate) / 2;
+    let name_text = Text::with_baseline(
+        exhibit.name,
+        Point::new(0, -name_bottom_y),
+        MonoTextStyle::new(font, palette::ALMOST_BLACK),
+        Baseline::Bottom,
+    );
+    // TODO: This is an awful lot of code to benerate "text is centered on a number of whole blocks"
+    let name_width = name_text.bounding_box().size.width as GridCoordinate;
+    let name_width_in_blocks: GridCoordinate =
+        (name_width + name_block_resolution - 1) / name_block_resolution; // rounding up
+    let name_blocks = draw_to_blocks(
+        universe,
+        name_block_resolution as Resolution,
+        0,
+        0..1,
+        BlockAttributes {
+            display_name: format!("Exhibit name {:?}", exhibit.name).into(),
+            collision: BlockCollision::None,
+            ..BlockAttributes::default()
+        },
+        &name_text.translate(Point::new(
+            ((name_width_in_blocks * name_block_resolution) - name_width) / 2,
+            0,
+        )),
+    )?;
+    // Truncate name to not overrun the exhibit itself
+    let truncated_name_grid = name_blocks
+        .grid()
+        .intersection(Grid::new([0, 0, 0], [exhibit_footprint.size().x + 3, 1, 1]))
+        .unwrap();
+    space.copy_from(
+        &name_blocks,
+        truncated_name_grid,
+        plot_transform * name_transform,
+    )?;
+    space.fill_uniform(
+        truncated_name_grid
+            .transform(plot_transform * name_transform * GridMatrix::from_translation([0, 0, -1]))
+            .unwrap(),
+        demo_blocks[Signboard]
+            .clone()
+            .rotate(plot_rotation.inverse()),
+    )?;
+
+    // Place exhibit content
+    space_to_space_copy(&exhibit_space, exhibit_space.grid(), space, plot_transform)?;
+
+    Ok(())
 }
 
-// TODO: move this since it is a generally useful utility
-fn space_to_space_copy(
-    src: &Space,
-    src_grid: Grid,
-    dst: &mut Space,
-    src_to_dst_transform: GridMatrix,
-) -> Result<(), SetCubeError> {
-    // TODO: don't panic
-    let dst_to_src_transform = src_to_dst_transform.inverse_transform().unwrap();
-    let (block_rotation, _) = dst_to_src_transform
-        .decompose()
-        .expect("could not decompose transform");
-    dst.fill(src_grid.transform(src_to_dst_transform).unwrap(), |p| {
-        Some(
-            src[dst_to_src_transform.transform_cube(p)]
-                .clone()
-                .rotate(block_rotation),
-        )
-    })
+/// Reasons, other than failure of the exhibit itself, why [`demo_city`] could not
+/// place an [`Exhibit`].
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ExhibitError {
+    /// The exhibit's factory returned a space which extends outside its
+    /// [`Exhibit::footprint`].
+    #[error("exhibit generated {generated:?}, which does not fit in its footprint {footprint:?}")]
+    DoesNotFit { generated: Grid, footprint: Grid },
+    /// There were no plots left in the city big enough for the exhibit.
+    #[error("no room left in the city for the exhibit")]
+    OutOfSpace,
 }
 
-#[allow(clippy::type_complexity)]
-pub(crate) struct Exhibit {
+/// A structure to be placed on its own plot in [`demo_city`], to show off some content
+/// or feature.
+///
+/// Exhibits are collected in a [`Gallery`]; crates other than this one may define their
+/// own and add them to a gallery to have them appear in the city.
+#[allow(clippy::exhaustive_structs, clippy::type_complexity)]
+#[derive(Clone, Copy)]
+pub struct Exhibit {
+    /// The name, which is displayed on a sign in front of the exhibit.
     pub name: &'static str,
+    /// The region the exhibit occupies, in the coordinates of the [`Space`] returned by
+    /// [`Self::factory`]. If [`None`], the bounds of that space are used.
+    ///
+    /// Y = 0 is ground level, so negative Y extends underground. The +Z side of the
+    /// exhibit faces the road.
+    pub footprint: Option<Grid>,
+    /// Constructs the exhibit's contents, given the exhibit itself and a universe in
+    /// which to store any blocks it needs.
     pub factory: fn(&Exhibit, &mut Universe) -> Result<Space, InGenError>,
 }
 
-/// Tracks available land while the city is being generated.
-#[derive(Clone, Debug, PartialEq)]
-struct CityPlanner {
-    space_grid: Grid,
-    /// Count of blocks beyond the origin that are included in the city space.
-    city_radius: GridCoordinate, // TODO redundant with grid
-    /// Each plot/exhibit that has already been placed. (This could be a spatial data structure but we're not that big yet.)
-    occupied_plots: Vec<Grid>,
+impl std::fmt::Debug for Exhibit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Exhibit")
+            .field("name", &self.name)
+            .field("footprint", &self.footprint)
+            .finish()
+    }
 }
 
-impl CityPlanner {
-    const ROAD_RADIUS: GridCoordinate = 2;
-    const LAMP_POSITION_RADIUS: GridCoordinate = Self::ROAD_RADIUS + 2;
-    const PLOT_FRONT_RADIUS: GridCoordinate = Self::LAMP_POSITION_RADIUS + 2;
-    const GAP_BETWEEN_PLOTS: GridCoordinate = 1;
-
-    pub fn new(space_grid: Grid) -> Self {
-        let city_radius = space_grid.upper_bounds().x; // TODO: compare everything and take the max
+/// An ordered collection of [`Exhibit`]s, for [`demo_city`] to lay out, and the
+/// [`Worldgen`] it uses for the landscape between them.
+///
+/// ```
+/// use all_is_cubes::content::{Exhibit, Gallery};
+/// use all_is_cubes::space::{Grid, Space};
+///
+/// let mut gallery = Gallery::builtin();
+/// gallery.register(Exhibit {
+///     name: "Empty Lot",
+///     footprint: Some(Grid::new([0, 0, 0], [4, 1, 4])),
+///     factory: |this, _universe| Ok(Space::empty(this.footprint.unwrap())),
+/// });
+/// assert_eq!(gallery.exhibits().last().unwrap().name, "Empty Lot");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Gallery {
+    exhibits: Vec<Exhibit>,
+    landscape: Worldgen,
+}
 
-        let mut occupied_plots = Vec::new();
-        let road = Grid::from_lower_upper(
-            [-Self::LAMP_POSITION_RADIUS, 0, -city_radius],
-            [Self::LAMP_POSITION_RADIUS + 1, 2, city_radius + 1],
-        );
-        occupied_plots.push(road);
-        occupied_plots.push(
-            road.transform(GridRotation::CLOCKWISE.to_rotation_matrix())
-                .unwrap(),
-        );
+impl Gallery {
+    /// Constructs a [`Gallery`] with no exhibits and the standard
+    /// [`Worldgen::landscape`].
+    pub fn new() -> Self {
         Self {
-            space_grid,
-            city_radius,
-            occupied_plots,
+            exhibits: Vec::new(),
+            landscape: Worldgen::landscape(),
         }
     }
 
-    pub fn find_plot(&mut self, plot_shape: Grid) -> Option<GridMatrix> {
-        // TODO: We'd like to resume the search from _when we left off_, but that's tricky since a
-        // smaller plot might fit where a large one didn't. So, quadratic search it is for now.
-        for d in 0..=self.city_radius {
-            for street_axis in GridRotation::COUNTERCLOCKWISE.iterate() {
-                // TODO exercising opposite sides logic
-                'search: for &left_side in &[false, true] {
-                    // The translation is expressed along the +X axis street, so
-                    // "left" is -Z and "right" is +Z.
-                    let mut transform = GridMatrix::from_translation(GridVector::new(
-                        d,
-                        1,
-                        if left_side {
-                            -Self::PLOT_FRONT_RADIUS - plot_shape.upper_bounds().z
-                        } else {
-                            Self::PLOT_FRONT_RADIUS + plot_shape.upper_bounds().z
-                        },
-                    )) * if left_side {
-                        GridMatrix::one()
-                    } else {
-                        (GridRotation::COUNTERCLOCKWISE * GridRotation::COUNTERCLOCKWISE)
-                            .to_rotation_matrix()
-                    };
-                    // Rotate to match street
-                    transform = street_axis.to_rotation_matrix() * transform;
-
-                    let transformed = plot_shape
-                        .transform(transform)
-                        .expect("can't happen: city plot transformation failure");
-
-                    if !self.space_grid.contains_grid(transformed) {
-                        continue 'search;
-                    }
-
-                    let for_occupancy_check =
-                        transformed.expand(FaceMap::repeat(Self::GAP_BETWEEN_PLOTS));
-
-                    for occupied in self.occupied_plots.iter() {
-                        if occupied.intersection(for_occupancy_check).is_some() {
-                            continue 'search;
-                        }
-                    }
-
-                    self.occupied_plots.push(transformed);
-                    return Some(transform);
-                }
-            }
+    /// Constructs a [`Gallery`] containing the exhibits built into this crate.
+    pub fn builtin() -> Self {
+        Self {
+            exhibits: DEMO_CITY_EXHIBITS.to_vec(),
+            ..Self::new()
         }
-        None
     }
 
-    fn y_range(&self, lower_y: GridCoordinate, upper_y: GridCoordinate) -> Grid {
-        let mut lower = self.space_grid.lower_bounds();
-        let mut upper = self.space_grid.upper_bounds();
-        lower.y = lower_y;
-        upper.y = upper_y;
-        Grid::from_lower_upper(lower, upper)
+    /// Adds an exhibit, to be placed after those already present.
+    pub fn register(&mut self, exhibit: Exhibit) {
+        self.exhibits.push(exhibit);
+    }
+
+    /// Returns the exhibits, in the order they will be placed.
+    pub fn exhibits(&self) -> &[Exhibit] {
+        &self.exhibits
     }
+
+    /// Returns the passes which generate the landscape, so that they may be
+    /// inspected.
+    pub fn landscape(&self) -> &Worldgen {
+        &self.landscape
+    }
+
+    /// Returns the passes which generate the landscape, so that passes may be added
+    /// or replaced.
+    pub fn landscape_mut(&mut self) -> &mut Worldgen {
+        &mut self.landscape
+    }
+}
+
+impl Default for Gallery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the part of `grid` between the given Y coordinates.
+fn y_range(grid: Grid, lower_y: GridCoordinate, upper_y: GridCoordinate) -> Grid {
+    let mut lower = grid.lower_bounds();
+    let mut upper = grid.upper_bounds();
+    lower.y = lower_y;
+    upper.y = upper_y;
+    Grid::from_lower_upper(lower, upper)
 }
@@ -9,13 +9,14 @@ use std::convert::TryInto as _;
 
 use crate::block::Block;
 use crate::character::Character;
-use crate::content::{demo_city, install_demo_blocks};
+use crate::content::{demo_city, install_demo_blocks, Gallery};
 use crate::linking::{GenError, InGenError};
 use crate::math::{FreeCoordinate, GridCoordinate, GridPoint, GridVector, Rgb, Rgba};
-use crate::space::LightPhysics;
 use crate::space::SpacePhysics;
 use crate::space::{Grid, Space};
+use crate::space::{LightPhysics, LightRayParameters};
 use crate::universe::{Name, Universe, UniverseIndex};
+use crate::util::WarningsResult;
 
 /// Selection of initial content for constructing a new [`Universe`].
 //
@@ -42,14 +43,38 @@ pub enum UniverseTemplate {
 }
 
 impl UniverseTemplate {
+    /// Constructs the universe, logging any parts of it which could not be generated.
+    /// Use [`UniverseTemplate::build_with_warnings`] to handle those problems otherwise.
     pub fn build(self) -> Result<Universe, GenError> {
-        use UniverseTemplate::*;
-        match self {
-            Blank => Ok(Universe::new()),
-            DemoCity => new_universe_with_space_setup(demo_city),
-            CornellBox => new_universe_with_space_setup(cornell_box),
-            PhysicsLab => new_universe_with_space_setup(|_| physics_lab(50, 16)),
+        let result = self.build_with_warnings()?;
+        for warning in &result.warnings {
+            log::warn!("{}", warning);
         }
+        Ok(result.value)
+    }
+
+    /// Constructs the universe, returning a warning for each part of it (such as an
+    /// exhibit in the demo city) which failed to generate and was left out.
+    ///
+    /// An error is returned only if the failure prevents constructing the universe at all.
+    pub fn build_with_warnings(self) -> Result<WarningsResult<Universe, GenError>, GenError> {
+        use UniverseTemplate::*;
+        let (result, name) = match self {
+            Blank => (WarningsResult::new(Universe::new()), ""),
+            DemoCity => (demo_city_universe(&Gallery::builtin())?, "Demo City"),
+            CornellBox => (
+                new_universe_with_space_setup(|u| cornell_box(u).map(WarningsResult::new))?,
+                "Cornell Box",
+            ),
+            PhysicsLab => (
+                new_universe_with_space_setup(|_| physics_lab(50, 16).map(WarningsResult::new))?,
+                "Physics Lab",
+            ),
+        };
+        Ok(result.map(|mut universe| {
+            universe.metadata_mut().name = name.to_owned();
+            universe
+        }))
     }
 }
 
@@ -59,6 +84,17 @@ impl Default for UniverseTemplate {
     }
 }
 
+/// Constructs a universe like [`UniverseTemplate::DemoCity`] but containing the
+/// exhibits in `gallery` instead of only the built-in ones.
+///
+/// Exhibits and landscape passes which fail are left out and reported as warnings;
+/// see [`demo_city`].
+pub fn demo_city_universe(
+    gallery: &Gallery,
+) -> Result<WarningsResult<Universe, GenError>, GenError> {
+    new_universe_with_space_setup(|universe| demo_city(universe, gallery))
+}
+
 #[rustfmt::skip]
 fn cornell_box(_universe: &mut Universe) -> Result<Space, InGenError> {
     // Coordinates are set up based on this dimension because, being blocks, we're not
@@ -74,9 +110,10 @@ fn cornell_box(_universe: &mut Universe) -> Result<Space, InGenError> {
     // There shall be no light but that which we make for ourselves!
     space.set_physics(SpacePhysics {
         sky_color: Rgb::ZERO,
-        light: LightPhysics::Rays {
+        light: LightPhysics::Rays(LightRayParameters {
             maximum_distance: (box_size * 2).try_into().unwrap_or(u16::MAX),
-        },
+            ..LightRayParameters::default()
+        }),
         ..SpacePhysics::default()
     });
     space.spawn_mut().position = (Point3::<FreeCoordinate>::new(0.5, 0.5, 1.6) * box_size.into()).map(|s| NotNan::new(s).unwrap());
@@ -113,23 +150,29 @@ fn cornell_box(_universe: &mut Universe) -> Result<Space, InGenError> {
     Ok(space)
 }
 
-fn new_universe_with_space_setup<F>(space_fn: F) -> Result<Universe, GenError>
+fn new_universe_with_space_setup<F>(
+    space_fn: F,
+) -> Result<WarningsResult<Universe, GenError>, GenError>
 where
-    F: FnOnce(&mut Universe) -> Result<Space, InGenError>,
+    F: FnOnce(&mut Universe) -> Result<WarningsResult<Space, GenError>, InGenError>,
 {
     let mut universe = Universe::new();
     install_demo_blocks(&mut universe)?;
 
     let space_name1: Name = "space".into();
     let space_name2 = space_name1.clone();
-    let space: Space = space_fn(&mut universe).map_err(|e| GenError::failure(e, space_name1))?;
+    let WarningsResult {
+        value: mut space,
+        warnings,
+    } = space_fn(&mut universe).map_err(|e| GenError::failure(e, space_name1))?;
+    space.seed_sky_light();
     let space_ref = universe.insert(space_name2, space)?;
 
     // TODO: "character" is a special default name used for finding the character the
     // player actually uses, and we should replace that or handle it more formally.
     universe.insert("character".into(), Character::spawn_default(space_ref))?;
 
-    Ok(universe)
+    Ok(WarningsResult::with_warnings(universe, warnings))
 }
 
 /// Generate a space which is both completely enclosed and has a convenient flat surface
@@ -209,16 +252,106 @@ fn physics_lab(shell_radius: u16, planet_radius: u16) -> Result<Space, InGenErro
 mod tests {
     use super::*;
     use crate::apps::Tick;
+    use crate::content::worldgen::FnPass;
+    use crate::universe::URef;
     use strum::IntoEnumIterator as _;
 
     #[test]
     pub fn template_smoke_test() {
         for template in UniverseTemplate::iter() {
-            let mut u = template.clone().build().unwrap();
+            let mut u = template
+                .clone()
+                .build_with_warnings()
+                .unwrap()
+                .into_result()
+                .unwrap();
             if template != UniverseTemplate::Blank {
                 let _ = u.get_default_character().unwrap().borrow();
             }
             u.step(Tick::arbitrary());
         }
     }
+
+    #[test]
+    pub fn demo_city_with_custom_exhibit() {
+        let mut gallery = Gallery::new();
+        gallery.register(crate::content::Exhibit {
+            name: "Custom",
+            footprint: Some(Grid::new([0, 0, 0], [3, 2, 3])),
+            factory: |_this, _universe| {
+                let mut space = Space::empty_positive(1, 1, 1);
+                space.set(
+                    [0, 0, 0],
+                    Block::builder()
+                        .color(Rgba::WHITE)
+                        .tag("custom-exhibit")
+                        .build(),
+                )?;
+                Ok(space)
+            },
+        });
+        let universe = demo_city_universe(&gallery).unwrap().into_result().unwrap();
+        let space: URef<Space> = universe.get(&"space".into()).unwrap();
+        assert_eq!(
+            space.borrow().find_cubes_with_tag("custom-exhibit").count(),
+            1
+        );
+    }
+
+    #[test]
+    pub fn demo_city_with_failing_exhibit() {
+        let mut gallery = Gallery::new();
+        gallery.register(crate::content::Exhibit {
+            name: "Broken",
+            footprint: None,
+            factory: |_this, _universe| {
+                let mut space = Space::empty_positive(1, 1, 1);
+                // Out of bounds, so this fails.
+                space.set([5, 5, 5], Block::from(Rgba::WHITE))?;
+                Ok(space)
+            },
+        });
+        gallery.register(crate::content::Exhibit {
+            name: "Working",
+            footprint: None,
+            factory: |_this, _universe| {
+                let mut space = Space::empty_positive(1, 1, 1);
+                space.set([0, 0, 0], Block::builder().tag("working").build())?;
+                Ok(space)
+            },
+        });
+        let result = demo_city_universe(&gallery).unwrap();
+        assert_eq!(result.warnings.len(), 1);
+        assert!(
+            result.warnings[0].to_string().contains("'Broken'"),
+            "unexpected warning: {}",
+            result.warnings[0]
+        );
+        let space: URef<Space> = result.value.get(&"space".into()).unwrap();
+        assert_eq!(space.borrow().find_cubes_with_tag("working").count(), 1);
+    }
+
+    #[test]
+    pub fn demo_city_with_custom_landscape_pass() {
+        let mut gallery = Gallery::builtin();
+        gallery
+            .landscape_mut()
+            .insert_after(
+                "terrain",
+                FnPass::new("custom", |context, region| {
+                    context.space.set(
+                        region.lower_bounds(),
+                        Block::builder()
+                            .color(Rgba::WHITE)
+                            .tag("custom-pass")
+                            .build(),
+                    )?;
+                    Ok(())
+                }),
+            )
+            .unwrap();
+        let universe = demo_city_universe(&gallery).unwrap().into_result().unwrap();
+        let space: URef<Space> = universe.get(&"space".into()).unwrap();
+        assert_eq!(space.borrow().find_cubes_with_tag("custom-pass").count(), 1);
+    }
 }
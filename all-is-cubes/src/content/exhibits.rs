@@ -35,9 +35,10 @@ pub(crate) static DEMO_CITY_EXHIBITS: &[Exhibit] = &[
 
 const TRANSPARENCY: Exhibit = Exhibit {
     name: "Transparency",
-    factory: |_this, _universe| {
+    footprint: Some(Grid::new([-3, 0, -3], [7, 5, 7])),
+    factory: |this, _universe| {
         // TODO: Add some partial-block transparency once we're any good at implementing it
-        let mut space = Space::empty(Grid::new([-3, 0, -3], [7, 5, 7]));
+        let mut space = Space::empty(this.footprint.unwrap());
 
         let colors = [
             Rgb::new(1.0, 0.5, 0.5),
@@ -67,8 +68,9 @@ const TRANSPARENCY: Exhibit = Exhibit {
 
 const KNOT: Exhibit = Exhibit {
     name: "Knot",
+    footprint: Some(Grid::new([-2, -2, -1], [5, 5, 3])),
     factory: |this, universe| {
-        let footprint = Grid::new([-2, -2, -1], [5, 5, 3]);
+        let footprint = this.footprint.unwrap();
         let resolution = 16;
         let toroidal_radius = 24.;
         let knot_split_radius = 9.;
@@ -114,6 +116,7 @@ const KNOT: Exhibit = Exhibit {
 
 const TEXT: Exhibit = Exhibit {
     name: "Text",
+    footprint: None,
     factory: |_, universe| {
         let space = draw_to_blocks(
             universe,
@@ -134,9 +137,9 @@ const TEXT: Exhibit = Exhibit {
 
 const RESOLUTIONS: Exhibit = Exhibit {
     name: "Resolutions",
-    factory: |_this, universe| {
-        let footprint = Grid::new([0, 0, 0], [5, 2, 3]);
-        let mut space = Space::empty(footprint);
+    footprint: Some(Grid::new([0, 0, 0], [5, 2, 3])),
+    factory: |this, universe| {
+        let mut space = Space::empty(this.footprint.unwrap());
 
         for (i, &resolution) in [1, 2, 3, 8, 16, 32].iter().enumerate() {
             let i = i as GridCoordinate;
@@ -189,6 +192,7 @@ const RESOLUTIONS: Exhibit = Exhibit {
 
 const COLORS: Exhibit = Exhibit {
     name: "Colors",
+    footprint: None,
     factory: |_this, universe| {
         let gradient_resolution = 5;
         let mut space = Space::empty(Grid::new(
@@ -258,6 +262,7 @@ const COLORS: Exhibit = Exhibit {
 
 const CHUNK_CHART: Exhibit = Exhibit {
     name: "Visible chunk chart",
+    footprint: None,
     factory: |_this, _universe| {
         use crate::chunking::ChunkChart;
 
@@ -269,6 +274,7 @@ const CHUNK_CHART: Exhibit = Exhibit {
 
 const MAKE_SOME_BLOCKS: Exhibit = Exhibit {
     name: "make_some_blocks",
+    footprint: None,
     factory: |_this, mut universe| {
         use crate::content::{make_some_blocks, make_some_voxel_blocks};
         const ROWS: GridCoordinate = 5;
@@ -298,6 +304,7 @@ const MAKE_SOME_BLOCKS: Exhibit = Exhibit {
 
 const SWIMMING_POOL: Exhibit = Exhibit {
     name: "Swimming Pool",
+    footprint: None,
     factory: |_this, _universe| {
         let width = 6;
         let depth = 6;
@@ -308,7 +315,7 @@ const SWIMMING_POOL: Exhibit = Exhibit {
             Block::builder()
                 .display_name("Not entirely unlike water")
                 .color(Rgba::new(0.96, 0.96, 1.0, 0.1))
-                .collision(BlockCollision::None)
+                .collision(BlockCollision::Fluid)
                 .build(),
         )?;
         Ok(space)
@@ -5,9 +5,11 @@ use noise::Seedable as _;
 use ordered_float::NotNan;
 
 use crate::block::{Block, BlockCollision, Resolution, AIR};
-use crate::content::blocks::scale_color;
 use crate::content::palette;
-use crate::linking::{BlockModule, BlockProvider, DefaultProvision, GenError, InGenError};
+use crate::content::texgen::{speckle, uniform};
+use crate::linking::{
+    BlockLayer, BlockModule, BlockProvider, DefaultProvision, GenError, InGenError,
+};
 use crate::math::{FreeCoordinate, GridCoordinate, GridPoint, GridVector, NoiseFnExt as _, Rgb};
 use crate::space::{Grid, SetCubeError, Space};
 use crate::universe::Universe;
@@ -72,18 +74,22 @@ impl DefaultProvision for LandscapeBlocks {
 pub fn install_landscape_blocks(
     universe: &mut Universe,
     resolution: Resolution,
+) -> Result<(), GenError> {
+    install_landscape_blocks_with_layers(universe, resolution, &[])
+}
+
+/// As [`install_landscape_blocks`], but with some blocks replaced by those in `layers`.
+pub(crate) fn install_landscape_blocks_with_layers(
+    universe: &mut Universe,
+    resolution: Resolution,
+    layers: &[BlockLayer],
 ) -> Result<(), GenError> {
     use LandscapeBlocks::*;
     let colors = BlockProvider::<LandscapeBlocks>::default();
 
-    let stone_noise_v = noise::Value::new().set_seed(0x21b5cc6b);
-    let stone_noise = noise::ScaleBias::new(&stone_noise_v)
-        .set_bias(1.0)
-        .set_scale(0.04);
-    let dirt_noise_v = noise::Value::new().set_seed(0x2e240365);
-    let dirt_noise = noise::ScaleBias::new(&dirt_noise_v)
-        .set_bias(1.0)
-        .set_scale(0.12);
+    let stone_pattern = speckle(uniform(colors[Stone].clone()), 0x21b5cc6b, 0.04);
+    let grass_pattern = speckle(uniform(colors[Grass].clone()), 0x2e240365, 0.12);
+    let dirt_pattern = speckle(uniform(colors[Dirt].clone()), 0x2e240365, 0.12);
     let overhang_noise_v = noise::Value::new();
     let overhang_noise = noise::ScaleBias::new(&overhang_noise_v)
         .set_bias(f64::from(resolution) * 0.75)
@@ -113,7 +119,7 @@ pub fn install_landscape_blocks(
                             ) * index,
                         )
                     {
-                        scale_color(colors[Grass].clone(), dirt_noise.at_grid(cube), 0.02)
+                        grass_pattern(cube)
                     } else {
                         AIR
                     }
@@ -129,9 +135,7 @@ pub fn install_landscape_blocks(
                         .map_err(InGenError::other)?
                         .attributes,
                 )
-                .voxels_fn(universe, resolution, |cube| {
-                    scale_color(colors[Stone].clone(), stone_noise.at_grid(cube), 0.02)
-                })?
+                .voxels_fn(universe, resolution, &stone_pattern)?
                 .build(),
 
             Grass => Block::builder()
@@ -143,9 +147,9 @@ pub fn install_landscape_blocks(
                 )
                 .voxels_fn(universe, resolution, |cube| {
                     if f64::from(cube.y) >= overhang_noise.at_grid(cube) {
-                        scale_color(colors[Grass].clone(), dirt_noise.at_grid(cube), 0.02)
+                        grass_pattern(cube)
                     } else {
-                        scale_color(colors[Dirt].clone(), dirt_noise.at_grid(cube), 0.02)
+                        dirt_pattern(cube)
                     }
                 })?
                 .build(),
@@ -160,9 +164,7 @@ pub fn install_landscape_blocks(
                         .map_err(InGenError::other)?
                         .attributes,
                 )
-                .voxels_fn(universe, resolution, |cube| {
-                    scale_color(colors[Dirt].clone(), dirt_noise.at_grid(cube), 0.02)
-                })?
+                .voxels_fn(universe, resolution, &dirt_pattern)?
                 .build(),
 
             Trunk => colors[Trunk].clone(),
@@ -170,7 +172,7 @@ pub fn install_landscape_blocks(
             Leaves => colors[Leaves].clone(),
         })
     })?
-    .install(universe)?;
+    .install_layered(layers, universe)?;
     Ok(())
 }
 
@@ -243,3 +245,105 @@ pub fn wavy_landscape(
     }
     Ok(())
 }
+
+/// Constructs a [`Space`] containing terrain whose surface heights are taken from a
+/// heightmap image: each pixel becomes a column of blocks, with black at Y = 0 and
+/// white at Y = `vertical_scale`. The image's X axis is the space's X axis, and the
+/// image's rows (downward) run along +Z.
+///
+/// Color images are converted to grayscale, and 16-bit images keep their precision.
+/// The columns are made of `blocks` the same way as [`wavy_landscape`]: grass on top,
+/// then dirt, then stone. The space's spawn point is above the center of the terrain.
+///
+/// Panics if `vertical_scale` is negative or not finite.
+///
+/// ```
+/// use all_is_cubes::block::AIR;
+/// use all_is_cubes::content::{heightmap_terrain, LandscapeBlocks};
+/// use all_is_cubes::linking::BlockProvider;
+///
+/// let image = image::DynamicImage::ImageLuma8(image::GrayImage::from_fn(4, 3, |x, _| {
+///     image::Luma([x as u8 * 85])
+/// }));
+/// let blocks = BlockProvider::<LandscapeBlocks>::default();
+/// let space = heightmap_terrain(&image, 9.0, &blocks).unwrap();
+///
+/// assert_eq!(space.grid().size(), [4, 10, 3].into());
+/// assert_eq!(space[[3, 9, 0]], blocks[LandscapeBlocks::Grass]);
+/// assert_eq!(space[[2, 6, 2]], blocks[LandscapeBlocks::Grass]);
+/// assert_eq!(space[[2, 7, 2]], AIR);
+/// ```
+#[cfg(feature = "image")]
+pub fn heightmap_terrain(
+    image: &image::DynamicImage,
+    vertical_scale: FreeCoordinate,
+    blocks: &BlockProvider<LandscapeBlocks>,
+) -> Result<Space, SetCubeError> {
+    use cgmath::Point3;
+    use std::convert::TryFrom as _;
+    use LandscapeBlocks::*;
+
+    assert!(
+        vertical_scale.is_finite() && vertical_scale >= 0.0,
+        "vertical scale must be finite and nonnegative, not {}",
+        vertical_scale
+    );
+    let heights = image.to_luma16();
+    let surface_y = |x: u32, z: u32| -> GridCoordinate {
+        let fraction = FreeCoordinate::from(heights.get_pixel(x, z).0[0]) / 65535.0;
+        (fraction * vertical_scale).round() as GridCoordinate
+    };
+
+    let size_x = GridCoordinate::try_from(heights.width()).expect("image too large");
+    let size_z = GridCoordinate::try_from(heights.height()).expect("image too large");
+    let grid = Grid::new(
+        [0, 0, 0],
+        [size_x, vertical_scale.round() as GridCoordinate + 1, size_z],
+    );
+    let mut space = Space::empty(grid);
+    space.fill(grid, |cube| {
+        let altitude = cube.y - surface_y(cube.x as u32, cube.z as u32);
+        if altitude > 0 {
+            None
+        } else if altitude == 0 {
+            Some(&blocks[Grass])
+        } else if altitude == -1 {
+            Some(&blocks[Dirt])
+        } else {
+            Some(&blocks[Stone])
+        }
+    })?;
+
+    if size_x > 0 && size_z > 0 {
+        let center = grid.center();
+        let center_surface = surface_y((size_x / 2) as u32, (size_z / 2) as u32);
+        space.spawn_mut().position = Point3::new(
+            center.x,
+            FreeCoordinate::from(center_surface) + 2.0,
+            center.z,
+        )
+        .map(|s| NotNan::new(s).unwrap());
+    }
+
+    Ok(space)
+}
+
+#[cfg(all(test, feature = "image"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heightmap_16_bit() {
+        let image = image::DynamicImage::ImageLuma16(image::ImageBuffer::from_fn(2, 1, |x, _| {
+            image::Luma([[0, 65535][x as usize]])
+        }));
+        let blocks = BlockProvider::<LandscapeBlocks>::default();
+        let space = heightmap_terrain(&image, 1000.0, &blocks).unwrap();
+        assert_eq!(space.grid(), Grid::new([0, 0, 0], [2, 1001, 1]));
+        assert_eq!(space[[0, 0, 0]], blocks[LandscapeBlocks::Grass]);
+        assert_eq!(space[[0, 1, 0]], AIR);
+        assert_eq!(space[[1, 998, 0]], blocks[LandscapeBlocks::Stone]);
+        assert_eq!(space[[1, 999, 0]], blocks[LandscapeBlocks::Dirt]);
+        assert_eq!(space[[1, 1000, 0]], blocks[LandscapeBlocks::Grass]);
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Drawing the All is Cubes logo.
+
+use embedded_graphics::mono_font::iso_8859_1::FONT_9X15_BOLD;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::prelude::{Dimensions as _, Drawable, Point};
+use embedded_graphics::text::Alignment;
+use embedded_graphics::text::Baseline;
+use embedded_graphics::text::Text;
+use embedded_graphics::text::TextStyleBuilder;
+
+use crate::block::Block;
+use crate::content::palette;
+use crate::drawing::VoxelBrush;
+use crate::math::{Face, FaceMap, GridCoordinate, GridMatrix};
+use crate::space::{Grid, SetCubeError, Space};
+
+/// Draw the All Is Cubes logo text.
+pub fn logo_text(midpoint_transform: GridMatrix, space: &mut Space) -> Result<(), SetCubeError> {
+    logo_text_drawable(|d| {
+        d.draw(&mut space.draw_target(midpoint_transform * GridMatrix::FLIP_Y))
+    })?;
+    Ok(())
+}
+
+pub fn logo_text_extent() -> Grid {
+    logo_text_drawable(|d| {
+        let bounding_box = d.bounding_box();
+        let top_left_2d = bounding_box.top_left;
+        let bottom_right_2d = bounding_box.bottom_right().unwrap();
+        Grid::from_lower_upper(
+            [top_left_2d.x, -(bottom_right_2d.y - 1), 0],
+            [bottom_right_2d.x - 1, -top_left_2d.y, 2],
+        )
+        .expand(FaceMap::from_fn(|f| {
+            // Expand horizontally due to the VoxelBrush's size. TODO: We should be able to ask the brush to do this.
+            ([Face::PX, Face::PY, Face::NX, Face::NY].contains(&f)) as GridCoordinate
+        }))
+    })
+}
+
+/// Calls the given function with `Drawable` logo text.
+/// Unfortunately there is no way to return an owned Drawable.
+fn logo_text_drawable<F, R>(f: F) -> R
+where
+    F: for<'a> FnOnce(Text<'static, MonoTextStyle<'a, &VoxelBrush<'a>>>) -> R,
+{
+    let foreground_text_block: Block = palette::LOGO_FILL.into();
+    let background_text_block: Block = palette::LOGO_STROKE.into();
+    let brush = VoxelBrush::new(vec![
+        ((0, 0, 1), &foreground_text_block),
+        ((1, 0, 0), &background_text_block),
+        ((-1, 0, 0), &background_text_block),
+        ((0, 1, 0), &background_text_block),
+        ((0, -1, 0), &background_text_block),
+    ]);
+
+    let text = Text::with_text_style(
+        "All is Cubes",
+        Point::new(0, 0),
+        MonoTextStyle::new(&FONT_9X15_BOLD, &brush),
+        TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build(),
+    );
+    f(text)
+}
@@ -0,0 +1,203 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Procedural patterns for the voxels of blocks.
+//!
+//! Each function here returns a pattern: a function from a cube within a block to the
+//! [`Block`] to put there, as accepted by
+//! [`BlockBuilder::voxels_fn`](crate::block::BlockBuilder::voxels_fn). Patterns which
+//! take other patterns as parameters may be nested to combine their effects.
+//!
+//! ```
+//! use all_is_cubes::prelude::*;
+//! use all_is_cubes::content::texgen::{border, speckle, uniform};
+//!
+//! let mut universe = Universe::new();
+//! let frame = Block::from(Rgba::new(0.2, 0.2, 0.2, 1.0));
+//! let panel = Block::from(Rgba::new(0.6, 0.5, 0.4, 1.0));
+//! let block = Block::builder()
+//!     .display_name("Panel")
+//!     .voxels_fn(
+//!         &mut universe,
+//!         16,
+//!         border(16, 2, uniform(frame), speckle(uniform(panel), 0x1234, 0.1)),
+//!     )
+//!     .unwrap()
+//!     .build();
+//! ```
+
+use noise::{NoiseFn, Seedable as _};
+
+use crate::block::{Block, Resolution};
+use crate::content::blocks::scale_color;
+use crate::math::{GridCoordinate, GridPoint, NoiseFnExt as _};
+
+/// Step size to which brightness changes are rounded, so that patterns do not produce
+/// an excessive number of distinct blocks.
+const QUANTIZATION: f64 = 0.02;
+
+/// A pattern which is `block` everywhere.
+pub fn uniform(block: Block) -> impl Fn(GridPoint) -> Block {
+    move |_| block.clone()
+}
+
+/// Varies the brightness of `pattern` randomly from cube to cube, by up to
+/// `amplitude` (a fraction of the original brightness) in either direction.
+///
+/// Only [`Block::Atom`]s produced by `pattern` are affected. `seed` selects which
+/// random variation is used.
+pub fn speckle<P>(pattern: P, seed: u32, amplitude: f64) -> impl Fn(GridPoint) -> Block
+where
+    P: Fn(GridPoint) -> Block,
+{
+    noise_brightness(pattern, noise::Value::new().set_seed(seed), amplitude)
+}
+
+/// Varies the brightness of `pattern` according to `noise`, sampled at the integer
+/// coordinates of each cube, by `amplitude` times the noise value.
+///
+/// This is the general form of [`speckle`], for when the noise function should be
+/// something other than independent values per cube.
+pub fn noise_brightness<P, N>(pattern: P, noise: N, amplitude: f64) -> impl Fn(GridPoint) -> Block
+where
+    P: Fn(GridPoint) -> Block,
+    N: NoiseFn<[f64; 3]>,
+{
+    move |cube| {
+        scale_color(
+            pattern(cube),
+            1.0 + amplitude * noise.at_grid(cube),
+            QUANTIZATION,
+        )
+    }
+}
+
+/// Alternates between `a` and `b` in layers `width` cubes thick, perpendicular to
+/// `axis` (0 for X, 1 for Y, 2 for Z). The layer starting at zero is `a`.
+///
+/// Panics if `width` is not positive.
+pub fn stripes<A, B>(axis: usize, width: GridCoordinate, a: A, b: B) -> impl Fn(GridPoint) -> Block
+where
+    A: Fn(GridPoint) -> Block,
+    B: Fn(GridPoint) -> Block,
+{
+    assert!(width > 0, "stripe width must be positive, not {}", width);
+    move |cube| {
+        if cube[axis].div_euclid(width).rem_euclid(2) == 0 {
+            a(cube)
+        } else {
+            b(cube)
+        }
+    }
+}
+
+/// Scales the brightness of `pattern` smoothly along `axis` (0 for X, 1 for Y, 2 for
+/// Z) of a block of the given `resolution`, from `low` at the lower face to `high`
+/// at the upper face.
+pub fn gradient<P>(
+    pattern: P,
+    axis: usize,
+    resolution: Resolution,
+    low: f64,
+    high: f64,
+) -> impl Fn(GridPoint) -> Block
+where
+    P: Fn(GridPoint) -> Block,
+{
+    move |cube| {
+        // Sample at the cube's center so that the gradient is symmetric.
+        let t = (f64::from(cube[axis]) + 0.5) / f64::from(resolution);
+        scale_color(pattern(cube), low + (high - low) * t, QUANTIZATION)
+    }
+}
+
+/// Uses `edge` for cubes within `width` cubes of any face of a block of the given
+/// `resolution`, and `interior` for all other cubes.
+pub fn border<E, I>(
+    resolution: Resolution,
+    width: GridCoordinate,
+    edge: E,
+    interior: I,
+) -> impl Fn(GridPoint) -> Block
+where
+    E: Fn(GridPoint) -> Block,
+    I: Fn(GridPoint) -> Block,
+{
+    let inner_range = width..(GridCoordinate::from(resolution) - width);
+    move |cube| {
+        if inner_range.contains(&cube.x)
+            && inner_range.contains(&cube.y)
+            && inner_range.contains(&cube.z)
+        {
+            interior(cube)
+        } else {
+            edge(cube)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::AIR;
+    use crate::content::make_some_blocks;
+    use crate::math::Rgba;
+
+    fn brightness(block: &Block) -> f32 {
+        match block {
+            Block::Atom(_, color) => color.red().into_inner(),
+            _ => panic!("not an atom: {:?}", block),
+        }
+    }
+
+    #[test]
+    fn speckle_is_deterministic_and_bounded() {
+        let base = Block::from(Rgba::new(0.5, 0.5, 0.5, 1.0));
+        let pattern = speckle(uniform(base.clone()), 1, 0.2);
+        let mut distinct = false;
+        for x in 0..16 {
+            let cube = GridPoint::new(x, 3, 5);
+            let value = brightness(&pattern(cube));
+            assert!((0.39..=0.61).contains(&value), "{}", value);
+            assert_eq!(pattern(cube), speckle(uniform(base.clone()), 1, 0.2)(cube));
+            distinct |= pattern(cube) != base;
+        }
+        assert!(distinct);
+    }
+
+    #[test]
+    fn speckle_leaves_non_atoms() {
+        let pattern = speckle(uniform(AIR), 1, 0.5);
+        assert_eq!(pattern(GridPoint::new(1, 2, 3)), AIR);
+    }
+
+    #[test]
+    fn stripes_alternate_including_negative() {
+        let [a, b] = make_some_blocks();
+        let pattern = stripes(1, 2, uniform(a.clone()), uniform(b.clone()));
+        let expected = [&b, &b, &a, &a, &b, &b, &a, &a];
+        for (y, &block) in (-4..4).zip(expected.iter()) {
+            assert_eq!(&pattern(GridPoint::new(0, y, 0)), block, "{}", y);
+        }
+    }
+
+    #[test]
+    fn gradient_endpoints() {
+        let pattern = gradient(uniform(Block::from(Rgba::WHITE)), 0, 10, 0.0, 1.0);
+        // Quantization may round these by up to 0.01.
+        let low = brightness(&pattern(GridPoint::new(0, 0, 0)));
+        let high = brightness(&pattern(GridPoint::new(9, 0, 0)));
+        assert!((low - 0.05).abs() <= 0.0101, "{}", low);
+        assert!((high - 0.95).abs() <= 0.0101, "{}", high);
+    }
+
+    #[test]
+    fn border_selects_edge_cubes() {
+        let [a, b] = make_some_blocks();
+        let pattern = border(8, 2, uniform(a.clone()), uniform(b.clone()));
+        assert_eq!(pattern(GridPoint::new(1, 4, 4)), a);
+        assert_eq!(pattern(GridPoint::new(4, 4, 6)), a);
+        assert_eq!(pattern(GridPoint::new(2, 5, 4)), b);
+        assert_eq!(pattern(GridPoint::new(4, 4, 4)), b);
+    }
+}
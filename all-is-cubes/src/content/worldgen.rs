@@ -0,0 +1,581 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Generation of outdoor landscapes as a sequence of composable passes.
+//!
+//! A [`Worldgen`] runs its [`WorldgenPass`]es in order, each on the same
+//! [`WorldgenContext`], so each pass can build on what the previous ones left:
+//! [`Worldgen::landscape`] shapes the [`TerrainPass`], hollows out a [`CavesPass`],
+//! and plants a [`VegetationPass`], and structures or anything else can be added with
+//! a custom pass such as a [`FnPass`].
+//!
+//! Towns may be laid out with a [`CityLayout`], which finds plots for structures along
+//! the roads of a [`RoadNetwork`] and builds the roads themselves; indoor areas may be
+//! generated with a [`DungeonPass`].
+//!
+//! ```
+//! use all_is_cubes::content::worldgen::{FnPass, Worldgen};
+//! use all_is_cubes::content::LandscapeBlocks;
+//! use all_is_cubes::linking::BlockProvider;
+//! use all_is_cubes::math::Rgba;
+//! use all_is_cubes::space::Space;
+//!
+//! let mut worldgen = Worldgen::landscape().seed(1234);
+//! worldgen
+//!     .insert_after(
+//!         "vegetation",
+//!         FnPass::new("monument", |context, region| {
+//!             context.space.set(region.center().map(|c| c as i32), Rgba::WHITE)?;
+//!             Ok(())
+//!         }),
+//!     )
+//!     .unwrap();
+//!
+//! let mut space = Space::empty_positive(20, 20, 20);
+//! let region = space.grid();
+//! worldgen
+//!     .generate(&mut space, &BlockProvider::<LandscapeBlocks>::default(), region)
+//!     .into_result()
+//!     .unwrap();
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+use cgmath::Vector3;
+use noise::{NoiseFn, Seedable as _};
+
+use crate::block::AIR;
+use crate::content::{wavy_landscape, LandscapeBlocks};
+use crate::linking::{BlockProvider, GenError, InGenError};
+use crate::math::{FreeCoordinate, GridCoordinate, GridPoint, GridVector, SeededRng};
+use crate::space::{Grid, Space};
+use crate::universe::Name;
+use crate::util::WarningsResult;
+
+mod city_layout;
+pub use city_layout::*;
+mod dungeon;
+pub use dungeon::*;
+
+/// The state shared by the passes of a [`Worldgen`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct WorldgenContext<'a> {
+    /// Seed from which passes should derive any randomness, so that the same seed
+    /// generates the same world.
+    pub seed: u64,
+    /// The space being generated in.
+    pub space: &'a mut Space,
+    /// Blocks to build the landscape from.
+    pub landscape_blocks: &'a BlockProvider<LandscapeBlocks>,
+    /// The region the [`Worldgen`] was asked to generate.
+    pub region: Grid,
+}
+
+impl WorldgenContext<'_> {
+    /// Returns a random number generator derived from [`Self::seed`] and `salt`, a
+    /// constant distinguishing one pass's use of it from others', so that the same
+    /// seed generates the same world however many random numbers each pass uses.
+    pub fn rng(&self, salt: u32) -> SeededRng {
+        SeededRng::with_stream(self.seed, u64::from(salt))
+    }
+}
+
+/// One step of a [`Worldgen`], such as shaping the terrain or placing trees.
+pub trait WorldgenPass {
+    /// Name by which this pass may be found by [`Worldgen::insert_before`] and
+    /// similar, and which appears in errors.
+    fn name(&self) -> &str;
+
+    /// Returns the region of the space which this pass reads and modifies, when the
+    /// [`Worldgen`] is generating `generation_region`; [`WorldgenPass::run`] will be
+    /// given this region, and generation fails if it is not within the space.
+    ///
+    /// The default is `generation_region` itself.
+    fn region(&self, generation_region: Grid) -> Grid {
+        generation_region
+    }
+
+    /// Modifies `context.space` within `region`.
+    fn run(&self, context: &mut WorldgenContext<'_>, region: Grid) -> Result<(), InGenError>;
+}
+
+/// An ordered list of [`WorldgenPass`]es to generate a region of a [`Space`] with.
+/// See the [module documentation](self) for an example.
+#[derive(Clone, Default)]
+pub struct Worldgen {
+    seed: u64,
+    passes: Vec<Arc<dyn WorldgenPass>>,
+}
+
+impl Worldgen {
+    /// Constructs a [`Worldgen`] with no passes, which generates nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs a [`Worldgen`] with the standard landscape passes, named
+    /// `"terrain"`, `"caves"`, and `"vegetation"`.
+    pub fn landscape() -> Self {
+        Self::new()
+            .with_pass(TerrainPass::default())
+            .with_pass(CavesPass::default())
+            .with_pass(VegetationPass::default())
+    }
+
+    /// Sets the seed passed to every pass. The default is 0.
+    #[must_use]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Adds a pass to run after all the existing passes.
+    #[must_use]
+    pub fn with_pass(mut self, pass: impl WorldgenPass + 'static) -> Self {
+        self.push(pass);
+        self
+    }
+
+    /// Adds a pass to run after all the existing passes.
+    pub fn push(&mut self, pass: impl WorldgenPass + 'static) {
+        self.passes.push(Arc::new(pass));
+    }
+
+    /// Adds a pass to run immediately before the pass named `name`.
+    pub fn insert_before(
+        &mut self,
+        name: &str,
+        pass: impl WorldgenPass + 'static,
+    ) -> Result<(), WorldgenError> {
+        let index = self.position(name)?;
+        self.passes.insert(index, Arc::new(pass));
+        Ok(())
+    }
+
+    /// Adds a pass to run immediately after the pass named `name`.
+    pub fn insert_after(
+        &mut self,
+        name: &str,
+        pass: impl WorldgenPass + 'static,
+    ) -> Result<(), WorldgenError> {
+        let index = self.position(name)?;
+        self.passes.insert(index + 1, Arc::new(pass));
+        Ok(())
+    }
+
+    /// Removes the pass named `name`.
+    pub fn remove(&mut self, name: &str) -> Result<(), WorldgenError> {
+        let index = self.position(name)?;
+        self.passes.remove(index);
+        Ok(())
+    }
+
+    /// Returns the names of the passes, in the order they run.
+    pub fn pass_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.passes.iter().map(|pass| pass.name())
+    }
+
+    fn position(&self, name: &str) -> Result<usize, WorldgenError> {
+        self.passes
+            .iter()
+            .position(|pass| pass.name() == name)
+            .ok_or_else(|| WorldgenError::NoSuchPass(name.to_owned()))
+    }
+
+    /// Runs all the passes, in order, to generate `region` of `space`.
+    ///
+    /// If a pass fails, the failure is reported as a warning naming the pass, and the
+    /// remaining passes are still run; whatever the failed pass did before it failed is
+    /// left in place.
+    pub fn generate(
+        &self,
+        space: &mut Space,
+        landscape_blocks: &BlockProvider<LandscapeBlocks>,
+        region: Grid,
+    ) -> WarningsResult<(), GenError> {
+        let space_grid = space.grid();
+        let mut context = WorldgenContext {
+            seed: self.seed,
+            space,
+            landscape_blocks,
+            region,
+        };
+        let mut warnings = Vec::new();
+        for pass in &self.passes {
+            let pass_region = pass.region(region);
+            let result = if space_grid.contains_grid(pass_region) {
+                pass.run(&mut context, pass_region)
+            } else {
+                Err(InGenError::other(WorldgenError::RegionOutsideSpace {
+                    pass: pass.name().to_owned(),
+                    region: pass_region,
+                    space: space_grid,
+                }))
+            };
+            if let Err(e) = result {
+                warnings.push(GenError::failure(e, Name::from(pass.name())));
+            }
+        }
+        WarningsResult::with_warnings((), warnings)
+    }
+}
+
+impl fmt::Debug for Worldgen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worldgen")
+            .field("seed", &self.seed)
+            .field("passes", &self.pass_names().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Errors from configuring or running a [`Worldgen`].
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum WorldgenError {
+    /// There is no pass with the given name.
+    #[error("no worldgen pass named {0:?}")]
+    NoSuchPass(String),
+
+    /// A pass needed a region which is not within the space being generated.
+    #[error("worldgen pass {pass:?} needs {region:?}, which is outside of the space's {space:?}")]
+    RegionOutsideSpace {
+        pass: String,
+        region: Grid,
+        space: Grid,
+    },
+}
+
+/// A [`WorldgenPass`] which calls a function.
+pub struct FnPass<F> {
+    name: String,
+    function: F,
+}
+
+impl<F> FnPass<F> {
+    /// Constructs a pass named `name` which runs `function` with the same arguments
+    /// as [`WorldgenPass::run`].
+    pub fn new(name: impl Into<String>, function: F) -> Self
+    where
+        F: Fn(&mut WorldgenContext<'_>, Grid) -> Result<(), InGenError>,
+    {
+        Self {
+            name: name.into(),
+            function,
+        }
+    }
+}
+
+impl<F> fmt::Debug for FnPass<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnPass").field("name", &self.name).finish()
+    }
+}
+
+impl<F> WorldgenPass for FnPass<F>
+where
+    F: Fn(&mut WorldgenContext<'_>, Grid) -> Result<(), InGenError>,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, context: &mut WorldgenContext<'_>, region: Grid) -> Result<(), InGenError> {
+        (self.function)(context, region)
+    }
+}
+
+/// Clears the region and fills it with gently rolling grass-covered ground, using
+/// [`wavy_landscape`]. Named `"terrain"`.
+///
+/// The shape of the terrain does not currently depend on the seed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainPass {
+    max_slope: FreeCoordinate,
+}
+
+impl TerrainPass {
+    /// Constructs a [`TerrainPass`] whose ground has at most the given slope.
+    pub fn new(max_slope: FreeCoordinate) -> Self {
+        Self { max_slope }
+    }
+}
+
+impl Default for TerrainPass {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl WorldgenPass for TerrainPass {
+    fn name(&self) -> &str {
+        "terrain"
+    }
+
+    fn run(&self, context: &mut WorldgenContext<'_>, region: Grid) -> Result<(), InGenError> {
+        context.space.fill_uniform(region, AIR)?;
+        wavy_landscape(
+            region,
+            context.space,
+            context.landscape_blocks,
+            self.max_slope,
+        )?;
+        Ok(())
+    }
+}
+
+/// Hollows out winding caves in the [`LandscapeBlocks::Stone`] of the region, leaving
+/// the soil above intact. Named `"caves"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CavesPass {
+    threshold: f64,
+}
+
+impl CavesPass {
+    /// Constructs a [`CavesPass`] which removes stone wherever a noise function in the
+    /// range -1 to 1 exceeds `threshold`; thus, higher thresholds make smaller caves.
+    pub fn new(threshold: f64) -> Self {
+        Self { threshold }
+    }
+}
+
+impl Default for CavesPass {
+    fn default() -> Self {
+        Self::new(0.45)
+    }
+}
+
+impl WorldgenPass for CavesPass {
+    fn name(&self) -> &str {
+        "caves"
+    }
+
+    fn run(&self, context: &mut WorldgenContext<'_>, region: Grid) -> Result<(), InGenError> {
+        const FEATURE_SIZE: f64 = 8.0;
+        let cave_noise = noise::OpenSimplex::new().set_seed(seed_for_pass(context.seed, 0xca7e));
+        let stone = context.landscape_blocks[LandscapeBlocks::Stone].clone();
+        let space = &mut *context.space;
+        for cube in region.interior_iter() {
+            if space[cube] != stone {
+                continue;
+            }
+            let point = (cube.map(f64::from) + Vector3::new(0.5, 0.5, 0.5)) / FEATURE_SIZE;
+            if cave_noise.get(point.into()) > self.threshold {
+                space.set(cube, &AIR)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Plants trees on the [`LandscapeBlocks::Grass`] of the region, using
+/// [`LandscapeBlocks::Trunk`] and [`LandscapeBlocks::Leaves`]. Named `"vegetation"`.
+///
+/// Trees which would not fit entirely within the region are not planted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VegetationPass {
+    tree_density: f64,
+}
+
+impl VegetationPass {
+    /// Constructs a [`VegetationPass`] which plants a tree on about `tree_density`
+    /// (from 0 to 1) of the grass-covered columns.
+    pub fn new(tree_density: f64) -> Self {
+        Self { tree_density }
+    }
+}
+
+impl Default for VegetationPass {
+    fn default() -> Self {
+        Self::new(0.005)
+    }
+}
+
+impl WorldgenPass for VegetationPass {
+    fn name(&self) -> &str {
+        "vegetation"
+    }
+
+    fn run(&self, context: &mut WorldgenContext<'_>, region: Grid) -> Result<(), InGenError> {
+        const LEAF_RADIUS: GridCoordinate = 2;
+        let blocks = context.landscape_blocks;
+        let seed = u64::from(seed_for_pass(context.seed, 0x7ee5));
+        for x in region.x_range() {
+            for z in region.z_range() {
+                let hash = column_hash(seed, x, z);
+                if (hash >> 11) as f64 / (1u64 << 53) as f64 >= self.tree_density {
+                    continue;
+                }
+                let ground = match region
+                    .y_range()
+                    .rev()
+                    .find(|&y| context.space[[x, y, z]] == blocks[LandscapeBlocks::Grass])
+                {
+                    Some(y) => GridPoint::new(x, y, z),
+                    None => continue,
+                };
+
+                let trunk_height = 3 + (hash % 3) as GridCoordinate;
+                let crown = ground + GridVector::unit_y() * trunk_height;
+                let tree_bounds = Grid::from_lower_upper(
+                    [x - LEAF_RADIUS, ground.y + 1, z - LEAF_RADIUS],
+                    [
+                        x + LEAF_RADIUS + 1,
+                        crown.y + LEAF_RADIUS + 1,
+                        z + LEAF_RADIUS + 1,
+                    ],
+                );
+                if !region.contains_grid(tree_bounds) {
+                    continue;
+                }
+
+                for cube in tree_bounds.interior_iter() {
+                    let offset = cube - crown;
+                    if offset.x.pow(2) + offset.y.pow(2) + offset.z.pow(2) <= LEAF_RADIUS.pow(2) + 1
+                        && context.space[cube] == AIR
+                    {
+                        context.space.set(cube, &blocks[LandscapeBlocks::Leaves])?;
+                    }
+                }
+                for y in (ground.y + 1)..=crown.y {
+                    context
+                        .space
+                        .set([x, y, z], &blocks[LandscapeBlocks::Trunk])?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Combines the worldgen seed with a constant distinguishing one pass's use of it,
+/// so that different passes do not produce correlated patterns.
+fn seed_for_pass(seed: u64, salt: u32) -> u32 {
+    (column_hash(seed, 0, 0) as u32) ^ salt
+}
+
+/// Pseudorandom function of a seed and a column position.
+fn column_hash(seed: u64, x: GridCoordinate, z: GridCoordinate) -> u64 {
+    // SplitMix64's finalizer, applied to all the inputs combined.
+    let mut h = seed ^ u64::from(x as u32) ^ (u64::from(z as u32) << 32);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::math::Rgba;
+
+    fn generate(worldgen: &Worldgen, space: &mut Space) -> WarningsResult<(), GenError> {
+        let region = space.grid();
+        worldgen.generate(space, &BlockProvider::<LandscapeBlocks>::default(), region)
+    }
+
+    #[test]
+    fn pass_ordering() {
+        let mut worldgen = Worldgen::landscape();
+        assert_eq!(
+            worldgen.pass_names().collect::<Vec<_>>(),
+            vec!["terrain", "caves", "vegetation"]
+        );
+        worldgen
+            .insert_before("caves", FnPass::new("a", |_, _| Ok(())))
+            .unwrap();
+        worldgen
+            .insert_after("vegetation", FnPass::new("b", |_, _| Ok(())))
+            .unwrap();
+        worldgen.remove("terrain").unwrap();
+        assert_eq!(
+            worldgen.pass_names().collect::<Vec<_>>(),
+            vec!["a", "caves", "vegetation", "b"]
+        );
+        assert_eq!(
+            worldgen.remove("nonexistent"),
+            Err(WorldgenError::NoSuchPass("nonexistent".into()))
+        );
+    }
+
+    #[test]
+    fn passes_run_in_order_on_shared_space() {
+        let marker = Block::from(Rgba::WHITE);
+        let worldgen = Worldgen::new()
+            .with_pass({
+                let marker = marker.clone();
+                FnPass::new("place", move |context, _| {
+                    context.space.set([0, 0, 0], &marker)?;
+                    Ok(())
+                })
+            })
+            .with_pass({
+                let marker = marker.clone();
+                FnPass::new("copy", move |context, _| {
+                    assert_eq!(context.space[[0, 0, 0]], marker);
+                    context.space.set([1, 0, 0], &marker)?;
+                    Ok(())
+                })
+            });
+        let mut space = Space::empty_positive(2, 1, 1);
+        generate(&worldgen, &mut space).into_result().unwrap();
+        assert_eq!(space[[1, 0, 0]], marker);
+    }
+
+    #[test]
+    fn pass_region_outside_space() {
+        struct Greedy;
+        impl WorldgenPass for Greedy {
+            fn name(&self) -> &str {
+                "greedy"
+            }
+            fn region(&self, generation_region: Grid) -> Grid {
+                generation_region.expand(crate::math::FaceMap::repeat(1))
+            }
+            fn run(&self, _: &mut WorldgenContext<'_>, _: Grid) -> Result<(), InGenError> {
+                unreachable!()
+            }
+        }
+        let mut space = Space::empty_positive(2, 2, 2);
+        let mut errors = generate(&Worldgen::new().with_pass(Greedy), &mut space)
+            .into_result()
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let error = errors.remove(0);
+        assert!(
+            error.to_string().contains("\"greedy\""),
+            "unexpected error: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn landscape_is_deterministic_and_seeded() {
+        // Denser trees than the default, so that this size is sure to get some.
+        let blocks = BlockProvider::<LandscapeBlocks>::default();
+        let gen = |seed| {
+            let mut space = Space::empty_positive(40, 30, 40);
+            let region = space.grid();
+            Worldgen::new()
+                .with_pass(TerrainPass::default())
+                .with_pass(CavesPass::default())
+                .with_pass(VegetationPass::new(0.05))
+                .seed(seed)
+                .generate(&mut space, &blocks, region)
+                .into_result()
+                .unwrap();
+            space
+                .grid()
+                .interior_iter()
+                .map(|cube| space[cube].clone())
+                .collect::<Vec<Block>>()
+        };
+        let first = gen(1);
+        assert_eq!(first, gen(1));
+        assert_ne!(first, gen(2));
+        assert!(first.contains(&blocks[LandscapeBlocks::Trunk]));
+        assert!(first.contains(&blocks[LandscapeBlocks::Grass]));
+    }
+}
@@ -0,0 +1,493 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`CityLayout`], for laying out roads and the plots of land along them.
+
+use cgmath::{EuclideanSpace as _, One as _};
+
+use crate::block::Block;
+use crate::content::DemoBlocks;
+use crate::linking::BlockProvider;
+use crate::math::{Face, FaceMap, GridCoordinate, GridMatrix, GridPoint, GridRotation, GridVector};
+use crate::space::{Grid, Prefab, SetCubeError, Space};
+
+/// A straight road, one of the edges of a [`RoadNetwork`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Road {
+    start: GridPoint,
+    direction: Face,
+    length: GridCoordinate,
+}
+
+impl Road {
+    /// Constructs a road whose center line runs along the ground from `start`, for
+    /// `length` cubes in the horizontal `direction`.
+    ///
+    /// Panics if `direction` is not horizontal or `length` is not positive.
+    pub fn new(start: impl Into<GridPoint>, direction: Face, length: GridCoordinate) -> Self {
+        assert!(
+            matches!(direction, Face::PX | Face::NX | Face::PZ | Face::NZ),
+            "road direction must be horizontal, not {:?}",
+            direction
+        );
+        assert!(length > 0, "road length must be positive, not {}", length);
+        Self {
+            start: start.into(),
+            direction,
+            length,
+        }
+    }
+
+    /// The first cube of the road's center line.
+    pub fn start(&self) -> GridPoint {
+        self.start
+    }
+
+    /// The last cube of the road's center line.
+    pub fn end(&self) -> GridPoint {
+        self.start + self.forward() * (self.length - 1)
+    }
+
+    /// The direction the road runs in from [`Road::start`].
+    pub fn direction(&self) -> Face {
+        self.direction
+    }
+
+    /// The number of cubes along the road's center line.
+    pub fn length(&self) -> GridCoordinate {
+        self.length
+    }
+
+    /// Returns the cubes of the road's center line, in order, with their distance from
+    /// [`Road::start`].
+    pub fn center_line(&self) -> impl Iterator<Item = (GridCoordinate, GridPoint)> {
+        let start = self.start;
+        let forward = self.forward();
+        (0..self.length).map(move |i| (i, start + forward * i))
+    }
+
+    fn forward(&self) -> GridVector {
+        self.direction.normal_vector()
+    }
+
+    /// Unit vector across the road, such that it points to the right when facing along
+    /// the road.
+    pub fn perpendicular(&self) -> GridVector {
+        self.forward().cross(Face::PY.normal_vector())
+    }
+
+    /// Returns the rotation which turns +X into this road's direction.
+    fn rotation(&self) -> GridRotation {
+        GridRotation::COUNTERCLOCKWISE
+            .iterate()
+            .find(|rotation| rotation.transform(Face::PX) == self.direction)
+            .expect("can't happen: horizontal road has no rotation")
+    }
+}
+
+/// The roads of a city, and the intersections where they meet.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RoadNetwork {
+    roads: Vec<Road>,
+    intersections: Vec<GridPoint>,
+}
+
+impl RoadNetwork {
+    /// Constructs a network with no roads.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs a network of two straight roads crossing at `center`, each extending
+    /// to the edges of `region` at the height of `center`.
+    ///
+    /// ```
+    /// use all_is_cubes::content::worldgen::RoadNetwork;
+    /// use all_is_cubes::space::Grid;
+    ///
+    /// let network = RoadNetwork::cross(Grid::new([-10, -1, -10], [20, 4, 20]), [0, 0, 0]);
+    /// assert_eq!(network.roads().len(), 4);
+    /// assert_eq!(network.intersections(), &[[0, 0, 0].into()]);
+    /// ```
+    pub fn cross(region: Grid, center: impl Into<GridPoint>) -> Self {
+        let center = center.into();
+        let mut network = Self::new();
+        network.add_intersection(center);
+        for &direction in &[Face::PX, Face::NZ, Face::NX, Face::PZ] {
+            let axis = direction.axis_number();
+            let length = if direction.is_positive() {
+                region.upper_bounds()[axis] - center[axis]
+            } else {
+                center[axis] - region.lower_bounds()[axis] + 1
+            };
+            if length > 0 {
+                network.add_road(Road::new(center, direction, length));
+            }
+        }
+        network
+    }
+
+    /// Constructs a network of roads in a square grid pattern with the given `spacing`
+    /// between parallel roads, filling `region` at height `ground_y`.
+    ///
+    /// ```
+    /// use all_is_cubes::content::worldgen::RoadNetwork;
+    /// use all_is_cubes::space::Grid;
+    ///
+    /// let network = RoadNetwork::grid(Grid::new([0, 0, 0], [41, 4, 41]), 0, 20);
+    /// // 3 × 3 intersections, joined by 2 roads in each of 3 rows and 3 columns.
+    /// assert_eq!(network.intersections().len(), 9);
+    /// assert_eq!(network.roads().len(), 12);
+    /// ```
+    pub fn grid(region: Grid, ground_y: GridCoordinate, spacing: GridCoordinate) -> Self {
+        assert!(spacing > 0, "road spacing must be positive");
+        let xs: Vec<GridCoordinate> = region.x_range().step_by(spacing as usize).collect();
+        let zs: Vec<GridCoordinate> = region.z_range().step_by(spacing as usize).collect();
+        let mut network = Self::new();
+        for &x in &xs {
+            for &z in &zs {
+                let intersection = GridPoint::new(x, ground_y, z);
+                network.add_intersection(intersection);
+                if x + spacing < region.upper_bounds().x {
+                    network.add_road(Road::new(intersection, Face::PX, spacing + 1));
+                }
+                if z + spacing < region.upper_bounds().z {
+                    network.add_road(Road::new(intersection, Face::PZ, spacing + 1));
+                }
+            }
+        }
+        network
+    }
+
+    /// Adds a road.
+    pub fn add_road(&mut self, road: Road) {
+        self.roads.push(road);
+    }
+
+    /// Marks a cube as an intersection, where the roads starting or ending there
+    /// should not have curbs or lampposts.
+    pub fn add_intersection(&mut self, cube: GridPoint) {
+        if !self.intersections.contains(&cube) {
+            self.intersections.push(cube);
+        }
+    }
+
+    /// Returns the roads, in the order [`CityLayout::find_plot`] searches them.
+    pub fn roads(&self) -> &[Road] {
+        &self.roads
+    }
+
+    /// Returns the intersections.
+    pub fn intersections(&self) -> &[GridPoint] {
+        &self.intersections
+    }
+
+    fn is_intersection(&self, cube: GridPoint) -> bool {
+        self.intersections.contains(&cube)
+    }
+}
+
+/// Tracks the roads and occupied land of a city while it is being generated: finds
+/// plots along the roads for structures of given footprints, and builds the roads.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CityLayout {
+    region: Grid,
+    network: RoadNetwork,
+    lamp_spacing: GridCoordinate,
+    /// Each plot that has already been placed, including the roads themselves. (This
+    /// could be a spatial data structure but we're not that big yet.)
+    occupied_plots: Vec<Grid>,
+}
+
+impl CityLayout {
+    /// Distance from the center line of a road to its edge.
+    pub const ROAD_RADIUS: GridCoordinate = 2;
+    /// Distance from the center line of a road to its lampposts.
+    pub const LAMP_POSITION_RADIUS: GridCoordinate = Self::ROAD_RADIUS + 2;
+    /// Distance from the center line of a road to the fronts of plots.
+    pub const PLOT_FRONT_RADIUS: GridCoordinate = Self::LAMP_POSITION_RADIUS + 2;
+    const GAP_BETWEEN_PLOTS: GridCoordinate = 1;
+
+    /// Constructs a layout of the roads in `network`, within `region`, with no plots
+    /// yet allocated.
+    pub fn new(region: Grid, network: RoadNetwork) -> Self {
+        let occupied_plots = network
+            .roads()
+            .iter()
+            .map(|road| {
+                let across = road.perpendicular() * Self::LAMP_POSITION_RADIUS;
+                let a = road.start() - across;
+                let b = road.end() + across;
+                Grid::from_lower_upper(
+                    [a.x.min(b.x), a.y, a.z.min(b.z)],
+                    [a.x.max(b.x) + 1, a.y + 2, a.z.max(b.z) + 1],
+                )
+            })
+            .collect();
+        Self {
+            region,
+            network,
+            lamp_spacing: 20,
+            occupied_plots,
+        }
+    }
+
+    /// Sets the distance between lampposts along each road. The default is 20.
+    #[must_use]
+    pub fn lamp_spacing(mut self, spacing: GridCoordinate) -> Self {
+        assert!(spacing > 0, "lamp spacing must be positive");
+        self.lamp_spacing = spacing;
+        self
+    }
+
+    /// Returns the road network.
+    pub fn network(&self) -> &RoadNetwork {
+        &self.network
+    }
+
+    /// Marks `region` as occupied, so no plots will be placed overlapping it.
+    pub fn occupy(&mut self, region: Grid) {
+        self.occupied_plots.push(region);
+    }
+
+    /// Finds a place for a structure with the given footprint alongside one of the
+    /// roads, as close to the start of the road as possible, and marks it occupied.
+    ///
+    /// The footprint is in coordinates where Y = 0 is the ground level and the +Z side
+    /// faces the road. Returns the transform from those coordinates to the region's, or
+    /// [`None`] if there is no room left.
+    pub fn find_plot(&mut self, plot_shape: Grid) -> Option<GridMatrix> {
+        // TODO: We'd like to resume the search from _when we left off_, but that's tricky since a
+        // smaller plot might fit where a large one didn't. So, quadratic search it is for now.
+        let max_length = self
+            .network
+            .roads()
+            .iter()
+            .map(Road::length)
+            .max()
+            .unwrap_or(0);
+        for d in 0..max_length {
+            for road in self.network.roads() {
+                if d >= road.length() {
+                    continue;
+                }
+                // TODO exercising opposite sides logic
+                'search: for &left_side in &[false, true] {
+                    // The translation is expressed along a +X axis street, so
+                    // "left" is -Z and "right" is +Z.
+                    let mut transform = GridMatrix::from_translation(GridVector::new(
+                        d,
+                        1,
+                        if left_side {
+                            -Self::PLOT_FRONT_RADIUS - plot_shape.upper_bounds().z
+                        } else {
+                            Self::PLOT_FRONT_RADIUS + plot_shape.upper_bounds().z
+                        },
+                    )) * if left_side {
+                        GridMatrix::one()
+                    } else {
+                        (GridRotation::COUNTERCLOCKWISE * GridRotation::COUNTERCLOCKWISE)
+                            .to_rotation_matrix()
+                    };
+                    // Rotate to match street, and move to its start
+                    transform = GridMatrix::from_translation(road.start().to_vec())
+                        * road.rotation().to_rotation_matrix()
+                        * transform;
+
+                    let transformed = plot_shape
+                        .transform(transform)
+                        .expect("can't happen: city plot transformation failure");
+
+                    if !self.region.contains_grid(transformed) {
+                        continue 'search;
+                    }
+
+                    let for_occupancy_check =
+                        transformed.expand(FaceMap::repeat(Self::GAP_BETWEEN_PLOTS));
+
+                    for occupied in self.occupied_plots.iter() {
+                        if occupied.intersection(for_occupancy_check).is_some() {
+                            continue 'search;
+                        }
+                    }
+
+                    self.occupied_plots.push(transformed);
+                    return Some(transform);
+                }
+            }
+        }
+        None
+    }
+
+    /// Builds the road surfaces, with curbs along their sides and lampposts beside
+    /// them, out of [`DemoBlocks`]. Nothing is built outside of the layout's region.
+    pub fn build_roads(
+        &self,
+        space: &mut Space,
+        demo_blocks: &BlockProvider<DemoBlocks>,
+    ) -> Result<(), SetCubeError> {
+        let road_radius = Self::ROAD_RADIUS;
+        let lamp_position_radius = Self::LAMP_POSITION_RADIUS;
+        let curb_y = GridVector::unit_y();
+        let region = self.region;
+        let set = |space: &mut Space, cube: GridPoint, block: &Block| {
+            if region.contains_cube(cube) {
+                space.set(cube, block)?;
+            }
+            Ok::<(), SetCubeError>(())
+        };
+
+        let lamp_prefab = {
+            let mut lamp_space = Space::empty_positive(1, 4, 1);
+            lamp_space.fill_uniform(lamp_space.grid(), &demo_blocks[DemoBlocks::Lamppost])?;
+            lamp_space.set([0, 3, 0], &demo_blocks[DemoBlocks::Lamp])?;
+            Prefab::capture(&lamp_space, lamp_space.grid())
+        };
+
+        for road in self.network.roads() {
+            let face = road.direction();
+            let forward = face.normal_vector();
+            let perpendicular = road.perpendicular();
+            // TODO: should be able to express this in look-at terms.
+            let road_aligned_rotation =
+                GridRotation::from_basis([face.cross(Face::PY), Face::PY, face]);
+            let other_side_of_road =
+                GridRotation::from_basis([Face::NX, Face::PY, Face::NZ]) * road_aligned_rotation;
+            let curbs = [
+                demo_blocks[DemoBlocks::Curb]
+                    .clone()
+                    .rotate(other_side_of_road),
+                demo_blocks[DemoBlocks::Curb]
+                    .clone()
+                    .rotate(road_aligned_rotation),
+            ];
+            let starts_at_intersection = self.network.is_intersection(road.start());
+            let ends_at_intersection = self.network.is_intersection(road.end());
+            let last = road.length() - 1;
+
+            for (i, cube) in road.center_line() {
+                // Road surface
+                for p in -road_radius..=road_radius {
+                    set(
+                        space,
+                        cube + perpendicular * p,
+                        &demo_blocks[DemoBlocks::Road],
+                    )?;
+                }
+
+                // Curbs, except where they would cross another road
+                if (!starts_at_intersection || i > road_radius)
+                    && (!ends_at_intersection || i < last - road_radius)
+                {
+                    for (side, &p) in [-(road_radius + 1), road_radius + 1].iter().enumerate() {
+                        set(space, cube + perpendicular * p + curb_y, &curbs[side])?;
+                    }
+                }
+
+                // Lampposts
+                if (i - lamp_position_radius) % self.lamp_spacing == 0
+                    && (!ends_at_intersection || i < last - lamp_position_radius)
+                {
+                    for p in &[-lamp_position_radius, lamp_position_radius] {
+                        let origin = cube + curb_y + perpendicular * *p;
+                        if region
+                            .contains_grid(lamp_prefab.stamped_grid(origin, GridRotation::IDENTITY))
+                        {
+                            lamp_prefab.stamp(space, origin, GridRotation::IDENTITY)?;
+                        }
+                    }
+                }
+            }
+
+            // Patch up curb corners
+            let mut corners = Vec::new();
+            if starts_at_intersection {
+                corners.push(road.start() + forward * (road_radius + 1));
+            }
+            if ends_at_intersection {
+                corners.push(road.end() - forward * (road_radius + 1));
+            }
+            for corner in corners {
+                for &p in &[-(road_radius + 1), road_radius + 1] {
+                    set(
+                        space,
+                        corner + perpendicular * p + curb_y,
+                        &demo_blocks[DemoBlocks::CurbCorner],
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::install_demo_blocks;
+    use crate::universe::Universe;
+
+    #[test]
+    fn cross_matches_region() {
+        let region = Grid::from_lower_upper([-60, -30, -60], [60, 30, 60]);
+        let network = RoadNetwork::cross(region, [0, 0, 0]);
+        let ends: Vec<GridPoint> = network.roads().iter().map(Road::end).collect();
+        assert_eq!(
+            ends,
+            vec![
+                GridPoint::new(59, 0, 0),
+                GridPoint::new(0, 0, -60),
+                GridPoint::new(-60, 0, 0),
+                GridPoint::new(0, 0, 59),
+            ]
+        );
+    }
+
+    #[test]
+    fn plots_do_not_overlap_roads_or_each_other() {
+        let region = Grid::from_lower_upper([0, -4, 0], [61, 10, 61]);
+        let mut layout = CityLayout::new(region, RoadNetwork::grid(region, 0, 30));
+        let footprint = Grid::new([0, 0, 0], [5, 3, 5]);
+        let mut plots = Vec::new();
+        while let Some(transform) = layout.find_plot(footprint) {
+            plots.push(footprint.transform(transform).unwrap());
+        }
+        assert!(plots.len() > 4, "only {} plots", plots.len());
+        for (i, plot) in plots.iter().enumerate() {
+            assert!(region.contains_grid(*plot));
+            for road in layout.network().roads() {
+                for (_, cube) in road.center_line() {
+                    assert!(!plot.contains_cube(cube + GridVector::unit_y()));
+                }
+            }
+            for other in &plots[..i] {
+                assert_eq!(plot.intersection(*other), None);
+            }
+        }
+    }
+
+    #[test]
+    fn build_roads_grid() {
+        let region = Grid::from_lower_upper([0, -1, 0], [41, 6, 41]);
+        let mut space = Space::empty(region);
+        let mut universe = Universe::new();
+        install_demo_blocks(&mut universe).unwrap();
+        let demo_blocks = BlockProvider::<DemoBlocks>::using(&universe).unwrap();
+        let layout = CityLayout::new(region, RoadNetwork::grid(region, 0, 20));
+        layout.build_roads(&mut space, &demo_blocks).unwrap();
+
+        // Center lines are road; the middle of a block is not.
+        assert_eq!(space[[10, 0, 0]], demo_blocks[DemoBlocks::Road]);
+        assert_eq!(space[[20, 0, 13]], demo_blocks[DemoBlocks::Road]);
+        assert_eq!(space[[10, 0, 10]], crate::block::AIR);
+        // Curbs line the road between intersections, but not across them.
+        assert_eq!(
+            space[[10, 1, 23]].clone().unspecialize(),
+            demo_blocks[DemoBlocks::Curb]
+        );
+        assert_ne!(
+            space[[20, 1, 23]].clone().unspecialize(),
+            demo_blocks[DemoBlocks::Curb]
+        );
+    }
+}
@@ -0,0 +1,535 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`DungeonPass`], which generates a maze of rooms joined by corridors.
+
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Range;
+
+use rand::seq::SliceRandom as _;
+use rand::Rng as _;
+
+use crate::block::{Block, AIR};
+use crate::content::palette;
+use crate::content::worldgen::{WorldgenContext, WorldgenPass};
+use crate::linking::{BlockModule, BlockProvider, DefaultProvision, InGenError};
+use crate::math::{FaceMap, GridCoordinate, GridPoint, GridRotation, GridVector, Rgb};
+use crate::space::{Grid, Prefab};
+
+/// Names for blocks assigned specific roles in generating a [`DungeonPass`].
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, strum::Display, strum::EnumIter)]
+#[strum(serialize_all = "kebab-case")]
+#[non_exhaustive]
+pub enum DungeonBlocks {
+    /// The walls of rooms and corridors.
+    Wall,
+    /// The floors of rooms and corridors.
+    Floor,
+    /// The ceilings of rooms and corridors.
+    Ceiling,
+    /// Surrounds each doorway, on the wall of the room it leads into.
+    DoorFrame,
+    /// Placed in the middle of the ceiling of each room.
+    Light,
+}
+
+impl BlockModule for DungeonBlocks {
+    fn namespace() -> &'static str {
+        "all-is-cubes/dungeon"
+    }
+}
+
+/// Provides a bland instance of [`DungeonBlocks`] with single color blocks.
+impl DefaultProvision for DungeonBlocks {
+    fn default(self) -> Block {
+        fn color_and_name(color: Rgb, name: &'static str) -> Block {
+            Block::builder()
+                .display_name(name)
+                .color(color.with_alpha_one())
+                .build()
+        }
+
+        use DungeonBlocks::*;
+        match self {
+            Wall => color_and_name(palette::STONE, "Dungeon Wall"),
+            Floor => color_and_name(palette::STONE * 0.6, "Dungeon Floor"),
+            Ceiling => color_and_name(palette::STONE * 0.8, "Dungeon Ceiling"),
+            DoorFrame => color_and_name(palette::PLANK, "Door Frame"),
+            Light => Block::builder()
+                .display_name("Dungeon Light")
+                .color(Rgb::new(1.0, 0.97, 0.9).with_alpha_one())
+                .light_emission(Rgb::new(8.0, 7.5, 6.5))
+                .build(),
+        }
+    }
+}
+
+/// Fills the bottom of the region with a maze of rooms joined by corridors. Named
+/// `"dungeon"`.
+///
+/// The rooms are laid out in a rectangular grid, as many as fit in the region, and
+/// every room can be reached from every other. Each room may be furnished with one of
+/// the room templates, stamped at a random rotation; templates which do not fit within
+/// a room's interior are not used for it, and templates should leave the middle of each
+/// wall clear so as not to block the doorways.
+///
+/// ```
+/// use all_is_cubes::content::worldgen::{DungeonPass, Worldgen};
+/// use all_is_cubes::content::LandscapeBlocks;
+/// use all_is_cubes::linking::BlockProvider;
+/// use all_is_cubes::space::Space;
+///
+/// let mut space = Space::empty_positive(40, 8, 40);
+/// let region = space.grid();
+/// Worldgen::new()
+///     .with_pass(DungeonPass::new().room_size([7, 4, 7]).door_size(2, 3))
+///     .generate(&mut space, &BlockProvider::<LandscapeBlocks>::default(), region)
+///     .into_result()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct DungeonPass {
+    blocks: BlockProvider<DungeonBlocks>,
+    room_size: GridVector,
+    corridor_length: GridCoordinate,
+    door_width: GridCoordinate,
+    door_height: GridCoordinate,
+    extra_door_probability: f64,
+    templates: Vec<Prefab>,
+}
+
+impl DungeonPass {
+    /// Constructs a [`DungeonPass`] with the default parameters: 5×3×5 rooms joined by
+    /// corridors 3 long, through doorways 1 wide and 2 high, built from the
+    /// [default](DefaultProvision) [`DungeonBlocks`], with no room templates.
+    pub fn new() -> Self {
+        Self {
+            blocks: BlockProvider::default(),
+            room_size: GridVector::new(5, 3, 5),
+            corridor_length: 3,
+            door_width: 1,
+            door_height: 2,
+            extra_door_probability: 0.1,
+            templates: Vec::new(),
+        }
+    }
+
+    /// Sets the blocks to build from.
+    #[must_use]
+    pub fn blocks(mut self, blocks: BlockProvider<DungeonBlocks>) -> Self {
+        self.blocks = blocks;
+        self
+    }
+
+    /// Sets the size of the interior of each room, not including its walls, floor, and
+    /// ceiling.
+    ///
+    /// Panics if any component is not positive.
+    #[must_use]
+    pub fn room_size(mut self, size: impl Into<GridVector>) -> Self {
+        let size = size.into();
+        assert!(
+            size.x > 0 && size.y > 0 && size.z > 0,
+            "room size must be positive, not {:?}",
+            size
+        );
+        self.room_size = size;
+        self
+    }
+
+    /// Sets the length of the corridors between rooms, not including the rooms' walls.
+    /// Zero places rooms directly against each other.
+    ///
+    /// Panics if `length` is negative.
+    #[must_use]
+    pub fn corridor_length(mut self, length: GridCoordinate) -> Self {
+        assert!(length >= 0, "corridor length must not be negative");
+        self.corridor_length = length;
+        self
+    }
+
+    /// Sets the size of the doorways between rooms, which is also the cross-section of
+    /// the corridors. The doorways are clamped to fit the walls they are in.
+    ///
+    /// Panics if either size is not positive.
+    #[must_use]
+    pub fn door_size(mut self, width: GridCoordinate, height: GridCoordinate) -> Self {
+        assert!(width > 0 && height > 0, "door size must be positive");
+        self.door_width = width;
+        self.door_height = height;
+        self
+    }
+
+    /// Sets the probability (from 0 to 1) that two adjacent rooms are joined even
+    /// though there is already another route between them. The default is 0.1; with 0,
+    /// there is exactly one route between any two rooms.
+    #[must_use]
+    pub fn extra_door_probability(mut self, probability: f64) -> Self {
+        self.extra_door_probability = probability;
+        self
+    }
+
+    /// Adds a template to furnish rooms with. The template's lower bounds are placed
+    /// at the lower corner of the room's interior, so the cubes just above the floor
+    /// are those with Y = 0.
+    #[must_use]
+    pub fn with_room_template(mut self, template: Prefab) -> Self {
+        self.templates.push(template);
+        self
+    }
+
+    /// Returns the number of rooms along X and Z which fit in `region`.
+    fn room_counts(&self, region: Grid) -> [GridCoordinate; 2] {
+        if region.size().y < self.room_size.y + 2 {
+            return [0, 0];
+        }
+        let count = |axis: usize| {
+            (region.size()[axis] + self.corridor_length)
+                / (self.room_size[axis] + 2 + self.corridor_length)
+        };
+        [count(0), count(2)]
+    }
+
+    /// Returns the bounds of the room at `(i, j)`, including its walls.
+    fn room_bounds(&self, region: Grid, i: GridCoordinate, j: GridCoordinate) -> Grid {
+        let outer_size = self.room_size + GridVector::new(2, 2, 2);
+        let pitch = outer_size + GridVector::new(1, 0, 1) * self.corridor_length;
+        Grid::new(
+            region.lower_bounds() + GridVector::new(i * pitch.x, 0, j * pitch.z),
+            outer_size,
+        )
+    }
+}
+
+impl Default for DungeonPass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorldgenPass for DungeonPass {
+    fn name(&self) -> &str {
+        "dungeon"
+    }
+
+    fn run(&self, context: &mut WorldgenContext<'_>, region: Grid) -> Result<(), InGenError> {
+        use DungeonBlocks::*;
+        let [count_x, count_z] = self.room_counts(region);
+        if count_x == 0 || count_z == 0 {
+            return Ok(());
+        }
+        let mut rng = context.rng(0xd00);
+        let blocks = &self.blocks;
+        let space = &mut *context.space;
+
+        // Choose which rooms are joined, as a spanning tree built by random depth-first
+        // search plus some extra connections. Each connection is identified by the
+        // room with lower coordinates and the axis along which it leads to the other.
+        let mut connections: BTreeSet<(GridCoordinate, GridCoordinate, usize)> = BTreeSet::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(0, 0)];
+        visited.insert((0, 0));
+        while let Some(&(i, j)) = stack.last() {
+            let mut neighbors: Vec<(GridCoordinate, GridCoordinate)> =
+                [(i - 1, j), (i + 1, j), (i, j - 1), (i, j + 1)]
+                    .iter()
+                    .copied()
+                    .filter(|&(ni, nj)| {
+                        (0..count_x).contains(&ni)
+                            && (0..count_z).contains(&nj)
+                            && !visited.contains(&(ni, nj))
+                    })
+                    .collect();
+            neighbors.shuffle(&mut rng);
+            match neighbors.first() {
+                Some(&(ni, nj)) => {
+                    connections.insert(connection_between((i, j), (ni, nj)));
+                    visited.insert((ni, nj));
+                    stack.push((ni, nj));
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        for i in 0..count_x {
+            for j in 0..count_z {
+                for &(axis, ni, nj) in &[(0, i + 1, j), (2, i, j + 1)] {
+                    if ni < count_x
+                        && nj < count_z
+                        && rng.gen_bool(self.extra_door_probability.clamp(0.0, 1.0))
+                    {
+                        connections.insert((i, j, axis));
+                    }
+                }
+            }
+        }
+
+        // Rooms
+        for i in 0..count_x {
+            for j in 0..count_z {
+                let bounds = self.room_bounds(region, i, j);
+                let interior = bounds.expand(FaceMap::repeat(-1));
+                space.fill_uniform(bounds, &blocks[Wall])?;
+                space.fill_uniform(interior, &AIR)?;
+                space.fill_uniform(
+                    Grid::new(
+                        [
+                            interior.lower_bounds().x,
+                            bounds.lower_bounds().y,
+                            interior.lower_bounds().z,
+                        ],
+                        [interior.size().x, 1, interior.size().z],
+                    ),
+                    &blocks[Floor],
+                )?;
+                let ceiling_y = bounds.upper_bounds().y - 1;
+                space.fill_uniform(
+                    Grid::new(
+                        [
+                            interior.lower_bounds().x,
+                            ceiling_y,
+                            interior.lower_bounds().z,
+                        ],
+                        [interior.size().x, 1, interior.size().z],
+                    ),
+                    &blocks[Ceiling],
+                )?;
+                space.set(
+                    [
+                        interior.lower_bounds().x + interior.size().x / 2,
+                        ceiling_y,
+                        interior.lower_bounds().z + interior.size().z / 2,
+                    ],
+                    &blocks[Light],
+                )?;
+
+                // Furnishings
+                if !self.templates.is_empty() {
+                    let template = &self.templates[rng.gen_range(0..self.templates.len())];
+                    let rotation = GridRotation::COUNTERCLOCKWISE
+                        .iterate()
+                        .nth(rng.gen_range(0..4))
+                        .unwrap();
+                    let origin = interior.lower_bounds();
+                    if interior.contains_grid(template.stamped_grid(origin, rotation)) {
+                        template.stamp(space, origin, rotation)?;
+                    }
+                }
+            }
+        }
+
+        // Corridors and doorways
+        let door_width = self.door_width.min(self.room_size.x).min(self.room_size.z);
+        let door_height = self.door_height.min(self.room_size.y);
+        for &(i, j, axis) in &connections {
+            let across_axis = 2 - axis;
+            let from = self.room_bounds(region, i, j);
+            let to = if axis == 0 {
+                self.room_bounds(region, i + 1, j)
+            } else {
+                self.room_bounds(region, i, j + 1)
+            };
+            let floor_y = from.lower_bounds().y;
+            // From the wall of one room to the wall of the other, inclusive.
+            let along = (from.upper_bounds()[axis] - 1)..(to.lower_bounds()[axis] + 1);
+            let passage_start = from.lower_bounds()[across_axis]
+                + 1
+                + (self.room_size[across_axis] - door_width) / 2;
+            let passage_across = passage_start..(passage_start + door_width);
+            let walls_across = (passage_start - 1)..(passage_start + door_width + 1);
+            let passage_y = (floor_y + 1)..(floor_y + 1 + door_height);
+
+            let corridor = axis_grid(
+                axis,
+                along.clone(),
+                walls_across.clone(),
+                floor_y..(floor_y + door_height + 2),
+            );
+            // The corridor's walls and ceiling, which become the door frames where they
+            // pass through the rooms' walls. The floor and passage are filled in next.
+            space.fill(corridor, |cube| {
+                if cube.y == floor_y {
+                    Some(&blocks[Wall])
+                } else if cube[axis] == along.start || cube[axis] == along.end - 1 {
+                    Some(&blocks[DoorFrame])
+                } else if cube.y == corridor.upper_bounds().y - 1 {
+                    Some(&blocks[Ceiling])
+                } else {
+                    Some(&blocks[Wall])
+                }
+            })?;
+            space.fill_uniform(
+                axis_grid(
+                    axis,
+                    along.clone(),
+                    passage_across.clone(),
+                    floor_y..(floor_y + 1),
+                ),
+                &blocks[Floor],
+            )?;
+            space.fill_uniform(axis_grid(axis, along, passage_across, passage_y), &AIR)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the connection, as stored by [`DungeonPass::run`], between two adjacent
+/// rooms.
+fn connection_between(
+    a: (GridCoordinate, GridCoordinate),
+    b: (GridCoordinate, GridCoordinate),
+) -> (GridCoordinate, GridCoordinate, usize) {
+    let (low, high) = if a < b { (a, b) } else { (b, a) };
+    (low.0, low.1, if high.0 > low.0 { 0 } else { 2 })
+}
+
+/// Constructs a [`Grid`] from ranges along a horizontal axis (0 or 2), the other
+/// horizontal axis, and Y.
+fn axis_grid(
+    along_axis: usize,
+    along: Range<GridCoordinate>,
+    across: Range<GridCoordinate>,
+    y: Range<GridCoordinate>,
+) -> Grid {
+    let (x, z) = if along_axis == 0 {
+        (along, across)
+    } else {
+        (across, along)
+    };
+    Grid::from_lower_upper(
+        GridPoint::new(x.start, y.start, z.start),
+        GridPoint::new(x.end, y.end, z.end),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::worldgen::Worldgen;
+    use crate::content::LandscapeBlocks;
+    use crate::math::Rgba;
+    use crate::space::Space;
+
+    fn generate(pass: DungeonPass, space: &mut Space) {
+        let region = space.grid();
+        Worldgen::new()
+            .with_pass(pass)
+            .generate(space, &BlockProvider::<LandscapeBlocks>::default(), region)
+            .into_result()
+            .unwrap();
+    }
+
+    /// Centers of the floors of the rooms of a default dungeon in a 3×3 layout.
+    fn room_centers() -> Vec<GridPoint> {
+        let mut centers = Vec::new();
+        for i in 0..3 {
+            for j in 0..3 {
+                centers.push(GridPoint::new(i * 10 + 3, 1, j * 10 + 3));
+            }
+        }
+        centers
+    }
+
+    #[test]
+    fn every_room_is_reachable() {
+        for &seed in &[0, 1, 2, 3] {
+            let mut space = Space::empty_positive(29, 6, 29);
+            let region = space.grid();
+            Worldgen::new()
+                .with_pass(DungeonPass::new().extra_door_probability(0.0))
+                .seed(seed)
+                .generate(
+                    &mut space,
+                    &BlockProvider::<LandscapeBlocks>::default(),
+                    region,
+                )
+                .into_result()
+                .unwrap();
+
+            // Flood fill through the air just above the floor.
+            let start = room_centers()[0];
+            let mut reached = HashSet::new();
+            let mut stack = vec![start];
+            reached.insert(start);
+            while let Some(cube) = stack.pop() {
+                for &offset in &[
+                    GridVector::unit_x(),
+                    -GridVector::unit_x(),
+                    GridVector::unit_z(),
+                    -GridVector::unit_z(),
+                ] {
+                    let next = cube + offset;
+                    if space.grid().contains_cube(next)
+                        && space[next] == AIR
+                        && reached.insert(next)
+                    {
+                        stack.push(next);
+                    }
+                }
+            }
+            for center in room_centers() {
+                assert!(reached.contains(&center), "seed {}: {:?}", seed, center);
+            }
+        }
+    }
+
+    #[test]
+    fn rooms_have_floor_ceiling_and_light() {
+        let mut space = Space::empty_positive(29, 6, 29);
+        generate(DungeonPass::new(), &mut space);
+        let blocks = BlockProvider::<DungeonBlocks>::default();
+        for center in room_centers() {
+            assert_eq!(
+                space[center - GridVector::unit_y()],
+                blocks[DungeonBlocks::Floor]
+            );
+            assert_eq!(space[center], AIR);
+            assert_eq!(
+                space[center + GridVector::unit_y() * 3],
+                blocks[DungeonBlocks::Light]
+            );
+        }
+        // The corner of each room is a wall.
+        assert_eq!(space[[0, 1, 0]], blocks[DungeonBlocks::Wall]);
+    }
+
+    #[test]
+    fn room_templates_are_stamped() {
+        let marker = Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0));
+        let template = {
+            let mut template_space = Space::empty_positive(1, 1, 1);
+            template_space.set([0, 0, 0], &marker).unwrap();
+            Prefab::capture(&template_space, template_space.grid())
+        };
+        let count_markers = |space: &Space| {
+            space
+                .grid()
+                .interior_iter()
+                .filter(|&cube| space[cube] == marker)
+                .count()
+        };
+
+        let mut space = Space::empty_positive(29, 6, 29);
+        generate(DungeonPass::new().with_room_template(template), &mut space);
+        assert_eq!(count_markers(&space), 9);
+
+        // Too big for any room, so never used.
+        let mut oversized_space = Space::empty_positive(6, 1, 6);
+        oversized_space
+            .fill_uniform(oversized_space.grid(), &marker)
+            .unwrap();
+        let oversized = Prefab::capture(&oversized_space, oversized_space.grid());
+        let mut space = Space::empty_positive(29, 6, 29);
+        generate(DungeonPass::new().with_room_template(oversized), &mut space);
+        assert_eq!(count_markers(&space), 0);
+    }
+
+    #[test]
+    fn region_too_small() {
+        let mut space = Space::empty_positive(29, 4, 29);
+        generate(DungeonPass::new(), &mut space);
+        assert!(space.grid().interior_iter().all(|cube| space[cube] == AIR));
+    }
+}
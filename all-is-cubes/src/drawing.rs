@@ -6,6 +6,9 @@
 //!
 //! The [`VoxelBrush`] type can also be useful in direct 3D drawing.
 //!
+//! [`PixelArt`] converts images to blocks chosen from a [`BlockPalette`], so that they
+//! can be drawn the same way.
+//!
 //! ## Coordinate system differences
 //!
 //! [`embedded_graphics`] uses coordinates which are different from ours in
@@ -35,6 +38,9 @@ use crate::math::{Face, GridCoordinate, GridMatrix, GridPoint, GridVector, Rgb,
 use crate::space::{Grid, SetCubeError, Space, SpacePhysics};
 use crate::universe::Universe;
 
+mod quantize;
+pub use quantize::*;
+
 /// Adapter to use a [`Space`] as a [`DrawTarget`].
 /// Use [`Space::draw_target`] to construct this.
 ///
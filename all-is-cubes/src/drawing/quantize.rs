@@ -0,0 +1,306 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Converting images into arrangements of blocks, by choosing the block of the
+//! closest color for each pixel.
+
+use cgmath::{InnerSpace as _, Vector3, Zero as _};
+use embedded_graphics::geometry::{OriginDimensions, Point, Size};
+use embedded_graphics::prelude::{DrawTarget, Drawable, Pixel};
+use std::convert::TryFrom as _;
+
+use crate::block::{Block, EvalBlockError};
+use crate::content::palette;
+use crate::math::{Rgb, Rgba};
+
+/// A set of blocks to choose from when converting colors to blocks, such as in
+/// [`PixelArt`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockPalette {
+    blocks: Vec<Block>,
+    colors: Vec<Vector3<f32>>,
+}
+
+impl BlockPalette {
+    /// Constructs a palette of the given blocks, which will be chosen according to
+    /// their [evaluated](Block::evaluate) colors.
+    pub fn new(blocks: impl IntoIterator<Item = Block>) -> Result<Self, EvalBlockError> {
+        let blocks: Vec<Block> = blocks.into_iter().collect();
+        let colors = blocks
+            .iter()
+            .map(|block| Ok(block.evaluate()?.color.to_rgb().into()))
+            .collect::<Result<Vec<_>, EvalBlockError>>()?;
+        Ok(Self { blocks, colors })
+    }
+
+    /// Constructs a palette of blocks of the given colors.
+    pub fn from_colors(colors: impl IntoIterator<Item = Rgba>) -> Self {
+        let colors: Vec<Rgba> = colors.into_iter().collect();
+        Self {
+            blocks: colors.iter().copied().map(Block::from).collect(),
+            colors: colors.iter().map(|color| color.to_rgb().into()).collect(),
+        }
+    }
+
+    /// Constructs a palette of blocks of the colors in [`palette`] which are used for
+    /// the demo content, plus white.
+    pub fn demo() -> Self {
+        Self::from_colors(
+            [
+                palette::ALMOST_BLACK,
+                palette::GRASS,
+                palette::DIRT,
+                palette::STONE,
+                palette::TREE_BARK,
+                palette::TREE_LEAVES,
+                palette::STEEL,
+                palette::PLANK,
+                palette::LOGO_FILL,
+                Rgb::ONE,
+            ]
+            .iter()
+            .map(|color| color.with_alpha_one()),
+        )
+    }
+
+    /// Returns the blocks in this palette.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Returns the block whose color is closest to `color`, or [`None`] if the palette
+    /// is empty. Alpha is ignored.
+    ///
+    /// ```
+    /// use all_is_cubes::block::Block;
+    /// use all_is_cubes::drawing::BlockPalette;
+    /// use all_is_cubes::math::Rgba;
+    ///
+    /// let palette = BlockPalette::from_colors(vec![Rgba::BLACK, Rgba::WHITE]);
+    /// assert_eq!(
+    ///     palette.nearest(Rgba::new(0.9, 0.8, 0.7, 1.0)),
+    ///     Some(&Block::from(Rgba::WHITE)),
+    /// );
+    /// ```
+    pub fn nearest(&self, color: Rgba) -> Option<&Block> {
+        self.nearest_index(color.to_rgb().into())
+            .map(|index| &self.blocks[index])
+    }
+
+    fn nearest_index(&self, color: Vector3<f32>) -> Option<usize> {
+        self.colors
+            .iter()
+            .map(|&candidate| (candidate - color).magnitude2())
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+}
+
+/// Whether and how [`PixelArt`] approximates colors between those in its palette
+/// by mixing nearby pixels.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Dithering {
+    /// Each pixel becomes the closest color in the palette.
+    None,
+    /// Floyd–Steinberg error diffusion: the difference between each pixel and the
+    /// palette color chosen for it is carried over to the pixels after it.
+    FloydSteinberg,
+}
+
+/// An image converted to [`Block`]s from a [`BlockPalette`], which may be drawn into a
+/// [`Space`](crate::space::Space) via [`Space::draw_target`](crate::space::Space::draw_target).
+///
+/// Pixels which are less than half opaque are left undrawn.
+///
+/// Like all [`embedded_graphics`] drawing, +Y is downward in the image's coordinates,
+/// so the transform used to draw it should usually flip Y.
+///
+/// ```
+/// use all_is_cubes::block::{Block, AIR};
+/// use all_is_cubes::drawing::embedded_graphics::Drawable as _;
+/// use all_is_cubes::drawing::{BlockPalette, Dithering, PixelArt};
+/// use all_is_cubes::math::{Face, GridMatrix, Rgba};
+/// use all_is_cubes::space::Space;
+///
+/// let palette = BlockPalette::from_colors(vec![Rgba::BLACK, Rgba::WHITE]);
+/// let art = PixelArt::from_fn(2, 2, &palette, Dithering::None, |x, y| {
+///     if y == 0 { Rgba::WHITE } else if x == 0 { Rgba::BLACK } else { Rgba::TRANSPARENT }
+/// });
+///
+/// let mut space = Space::empty_positive(2, 2, 1);
+/// (&art)
+///     .draw(&mut space.draw_target(GridMatrix::from_origin([0, 1, 0], Face::PX, Face::NY, Face::PZ)))
+///     .unwrap();
+/// assert_eq!(space[[0, 1, 0]], Block::from(Rgba::WHITE));
+/// assert_eq!(space[[1, 1, 0]], Block::from(Rgba::WHITE));
+/// assert_eq!(space[[0, 0, 0]], Block::from(Rgba::BLACK));
+/// assert_eq!(space[[1, 0, 0]], AIR);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PixelArt {
+    width: u32,
+    height: u32,
+    blocks: Vec<Block>,
+    /// Indices into `blocks` in row-major order, or [`None`] for transparent pixels.
+    pixels: Vec<Option<usize>>,
+}
+
+impl PixelArt {
+    /// Converts an image of `width` × `height` pixels, whose colors are given by
+    /// calling `pixel(x, y)`, to blocks from `palette`.
+    ///
+    /// If the palette is empty, every pixel is left undrawn.
+    pub fn from_fn(
+        width: u32,
+        height: u32,
+        palette: &BlockPalette,
+        dithering: Dithering,
+        mut pixel: impl FnMut(u32, u32) -> Rgba,
+    ) -> Self {
+        let w = width as usize;
+        let h = height as usize;
+        let mut errors: Vec<Vector3<f32>> = vec![Vector3::zero(); w * h];
+        let mut pixels = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                let color = pixel(x as u32, y as u32);
+                if color.alpha().into_inner() < 0.5 {
+                    pixels.push(None);
+                    continue;
+                }
+                let wanted = (Vector3::from(color.to_rgb()) + errors[y * w + x])
+                    .map(|c| c.max(0.0).min(1.0));
+                let chosen = palette.nearest_index(wanted);
+                pixels.push(chosen);
+
+                if let (Dithering::FloydSteinberg, Some(index)) = (dithering, chosen) {
+                    let error = wanted - palette.colors[index];
+                    let mut spread = |dx: isize, dy: usize, weight: f32| {
+                        let nx = x as isize + dx;
+                        let ny = y + dy;
+                        if nx >= 0 && (nx as usize) < w && ny < h {
+                            errors[ny * w + nx as usize] += error * weight;
+                        }
+                    };
+                    spread(1, 0, 7. / 16.);
+                    spread(-1, 1, 3. / 16.);
+                    spread(0, 1, 5. / 16.);
+                    spread(1, 1, 1. / 16.);
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            blocks: palette.blocks.clone(),
+            pixels,
+        }
+    }
+
+    /// Converts `image` to blocks from `palette`.
+    #[cfg(feature = "image")]
+    pub fn from_image(
+        image: &image::DynamicImage,
+        palette: &BlockPalette,
+        dithering: Dithering,
+    ) -> Self {
+        let image = image.to_rgba8();
+        Self::from_fn(image.width(), image.height(), palette, dithering, |x, y| {
+            Rgba::from_srgb_32bit(image.get_pixel(x, y).0)
+        })
+    }
+
+    /// Returns the block chosen for the pixel at `(x, y)`, or [`None`] if that pixel
+    /// is transparent or out of bounds.
+    pub fn get(&self, x: u32, y: u32) -> Option<&Block> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.pixels[y as usize * self.width as usize + x as usize].map(|index| &self.blocks[index])
+    }
+}
+
+impl OriginDimensions for PixelArt {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+impl<'a> Drawable for &'a PixelArt {
+    type Color = &'a Block;
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let art: &'a PixelArt = *self;
+        target.draw_iter((0..art.height).flat_map(move |y| {
+            (0..art.width).filter_map(move |x| {
+                let block = art.get(x, y)?;
+                Some(Pixel(
+                    Point::new(i32::try_from(x).ok()?, i32::try_from(y).ok()?),
+                    block,
+                ))
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::make_some_blocks;
+
+    fn gray(level: f32) -> Rgba {
+        Rgba::new(level, level, level, 1.0)
+    }
+
+    #[test]
+    fn palette_of_blocks_uses_evaluated_color() {
+        let blocks: [Block; 3] = make_some_blocks();
+        let palette = BlockPalette::new(blocks.to_vec()).unwrap();
+        for block in &blocks {
+            let color = block.evaluate().unwrap().color;
+            assert_eq!(palette.nearest(color), Some(block));
+        }
+    }
+
+    #[test]
+    fn empty_palette() {
+        let palette = BlockPalette::from_colors(vec![]);
+        assert_eq!(palette.nearest(Rgba::WHITE), None);
+        let art = PixelArt::from_fn(2, 2, &palette, Dithering::FloydSteinberg, |_, _| {
+            Rgba::WHITE
+        });
+        assert_eq!(art.get(0, 0), None);
+    }
+
+    #[test]
+    fn without_dithering_is_uniform() {
+        let palette = BlockPalette::from_colors(vec![Rgba::BLACK, Rgba::WHITE]);
+        let art = PixelArt::from_fn(8, 8, &palette, Dithering::None, |_, _| gray(0.4));
+        for y in 0..8 {
+            for x in 0..8 {
+                assert_eq!(art.get(x, y), Some(&Block::from(Rgba::BLACK)));
+            }
+        }
+    }
+
+    #[test]
+    fn dithering_preserves_average() {
+        let palette = BlockPalette::from_colors(vec![Rgba::BLACK, Rgba::WHITE]);
+        let art = PixelArt::from_fn(16, 16, &palette, Dithering::FloydSteinberg, |_, _| {
+            gray(0.25)
+        });
+        let white = Block::from(Rgba::WHITE);
+        let white_count = (0..16)
+            .flat_map(|y| (0..16).map(move |x| (x, y)))
+            .filter(|&(x, y)| art.get(x, y) == Some(&white))
+            .count();
+        // Exactly a quarter would be 64.
+        assert!((56..=72).contains(&white_count), "{}", white_count);
+    }
+}
@@ -0,0 +1,671 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Objects in a [`Space`] which are not made of its blocks: creatures, vehicles,
+//! decorations, and so on.
+//!
+//! An [`Entity`] has a position which is not aligned to the grid, an optional physical
+//! [`Body`] which falls and collides with the blocks of its space, an appearance given
+//! by a [`Block`] drawn as a (scaled and rotated) cube centered on its position or as a
+//! camera-facing sprite (see [`EntityShape`]) or an articulated [`Model`] of several
+//! blocks, and [`Behavior`]s which are stepped along with the space.
+//!
+//! Entities are added to, changed within, and removed from their space by
+//! [`SpaceTransaction`]s; see [`SpaceTransaction::add_entity`].
+
+use cgmath::{Deg, EuclideanSpace as _, Matrix4, Point3, Vector3};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::apps::Tick;
+use crate::behavior::{Behavior, BehaviorSet, BehaviorSetTransaction};
+use crate::block::Block;
+use crate::math::{Aab, FreeCoordinate, SeededRng};
+use crate::physics::{Body, BodyTransaction};
+use crate::space::{Space, SpaceTransaction};
+use crate::transactions::{
+    PreconditionFailed, Transaction, TransactionConflict, Transactional, UniverseTransaction,
+};
+use crate::universe::URef;
+
+mod model;
+pub use model::*;
+
+/// Identifies an [`Entity`] within its [`Space`]. IDs are not reused, so a stale
+/// ID will not find a different entity.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct EntityId(u64);
+
+/// How an [`Entity`]'s [appearance](Entity::appearance) is drawn.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum EntityShape {
+    /// A cube, rotated to match the entity's facing. This is the default.
+    Cube,
+    /// A flat square (“billboard” or “sprite”) which always faces the viewer, showing
+    /// the block as seen looking at its [`Face::PZ`](crate::math::Face::PZ) side.
+    /// Suitable for particles and small items.
+    Billboard,
+}
+
+impl Default for EntityShape {
+    fn default() -> Self {
+        Self::Cube
+    }
+}
+
+/// An object in a [`Space`] which is not one of its blocks; see the
+/// [module documentation](self).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entity {
+    body: Body,
+    /// Whether [`Self::body`] is subject to physics (gravity and collision), rather
+    /// than only recording position and orientation.
+    physics: bool,
+    appearance: Option<Block>,
+    shape: EntityShape,
+    /// Edge length of the cube in which [`Self::appearance`] is drawn.
+    scale: FreeCoordinate,
+    /// If present, drawn instead of [`Self::appearance`].
+    model: Option<Arc<Model>>,
+    /// Name of the animation of [`Self::model`] being played, if any.
+    animation: Option<String>,
+    /// How long [`Self::animation`] has been playing.
+    animation_time: Duration,
+    behaviors: BehaviorSet<Entity>,
+}
+
+impl Entity {
+    /// Constructs an invisible entity at `position`, which does not move unless
+    /// modified, and has no behaviors. Use the `with_*` methods to add those.
+    pub fn new(position: impl Into<Point3<FreeCoordinate>>) -> Self {
+        Self {
+            body: Body::new_minimal(position, Aab::ZERO),
+            physics: false,
+            appearance: None,
+            shape: EntityShape::default(),
+            scale: 1.0,
+            model: None,
+            animation: None,
+            animation_time: Duration::ZERO,
+            behaviors: BehaviorSet::new(),
+        }
+    }
+
+    /// Sets the block drawn at this entity's position.
+    #[must_use]
+    pub fn with_appearance(mut self, block: Block) -> Self {
+        self.appearance = Some(block);
+        self
+    }
+
+    /// Sets how [`Self::appearance`] is drawn.
+    #[must_use]
+    pub fn with_shape(mut self, shape: EntityShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Sets the edge length of the cube in which [`Self::appearance`] is drawn.
+    /// The default is 1, the size of a block in the space.
+    #[must_use]
+    pub fn with_scale(mut self, scale: FreeCoordinate) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the [`Model`] drawn at this entity's position, in place of
+    /// [`Self::appearance`]. Its coordinates are scaled by [`Self::scale`].
+    #[must_use]
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = Some(Arc::new(model));
+        self
+    }
+
+    /// Starts playing the named animation of [`Self::model`].
+    #[must_use]
+    pub fn with_animation(mut self, animation: impl Into<String>) -> Self {
+        self.animation = Some(animation.into());
+        self.animation_time = Duration::ZERO;
+        self
+    }
+
+    /// Makes this entity a physical object, which falls and collides with the blocks of
+    /// its space, with the given collision box relative to its position.
+    #[must_use]
+    pub fn with_physics(mut self, collision_box: Aab) -> Self {
+        self.body.collision_box = collision_box;
+        self.physics = true;
+        self
+    }
+
+    /// Sets the initial velocity of this entity, in cubes per second.
+    #[must_use]
+    pub fn with_velocity(mut self, velocity: Vector3<FreeCoordinate>) -> Self {
+        self.body.velocity = velocity;
+        self
+    }
+
+    /// Adds a [`Behavior`], which will be stepped whenever the entity's space is.
+    #[must_use]
+    pub fn with_behavior<B>(mut self, behavior: B) -> Self
+    where
+        B: Behavior<Entity> + 'static,
+    {
+        self.behaviors.insert(behavior);
+        self
+    }
+
+    /// The position of this entity, at the center of its appearance.
+    pub fn position(&self) -> Point3<FreeCoordinate> {
+        self.body.position
+    }
+
+    /// The physical state of this entity.
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// Whether this entity is subject to physics; see [`Self::with_physics`].
+    pub fn has_physics(&self) -> bool {
+        self.physics
+    }
+
+    /// The block drawn at this entity's position, if it is visible.
+    pub fn appearance(&self) -> Option<&Block> {
+        self.appearance.as_ref()
+    }
+
+    /// How [`Self::appearance`] is drawn.
+    pub fn shape(&self) -> EntityShape {
+        self.shape
+    }
+
+    /// Edge length of the cube in which [`Self::appearance`] is drawn.
+    pub fn scale(&self) -> FreeCoordinate {
+        self.scale
+    }
+
+    /// The model drawn at this entity's position, if any.
+    pub fn model(&self) -> Option<&Model> {
+        self.model.as_deref()
+    }
+
+    /// The name of the animation of [`Self::model`] being played, if any.
+    pub fn animation(&self) -> Option<&str> {
+        self.animation.as_deref()
+    }
+
+    /// How long [`Self::animation`] has been playing, counting only unpaused time.
+    pub fn animation_time(&self) -> Duration {
+        self.animation_time
+    }
+
+    /// Returns, for each part of [`Self::model`], the transformation from a unit cube
+    /// centered on the origin to that part as currently posed and positioned in the
+    /// space; or nothing if there is no model.
+    pub fn model_part_transforms(&self) -> Vec<Matrix4<FreeCoordinate>> {
+        match &self.model {
+            Some(model) => {
+                let transform = self.transform();
+                model
+                    .pose(self.animation.as_deref(), self.animation_time)
+                    .into_iter()
+                    .map(|part_transform| transform * part_transform)
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Transformation from a unit cube centered on the origin to the position, size, and
+    /// facing of this entity, for rendering it.
+    pub fn transform(&self) -> Matrix4<FreeCoordinate> {
+        Matrix4::from_translation(self.body.position.to_vec())
+            * Matrix4::from_angle_y(-Deg(self.body.yaw))
+            * Matrix4::from_scale(self.scale)
+    }
+
+    /// Transformation from the [`Face::PZ`](crate::math::Face::PZ) face of a unit cube
+    /// with its corner at the origin (as produced by
+    /// [`triangulate_billboard`](crate::triangulator::triangulate_billboard)) to this
+    /// entity's [billboard](EntityShape::Billboard), where `facing` is a rotation
+    /// taking +Z to the direction toward the viewer and +Y to the viewer's up.
+    pub fn billboard_transform(&self, facing: Matrix4<FreeCoordinate>) -> Matrix4<FreeCoordinate> {
+        Matrix4::from_translation(self.body.position.to_vec())
+            * facing
+            * Matrix4::from_scale(self.scale)
+            * Matrix4::from_translation(Vector3::new(-0.5, -0.5, -1.0))
+    }
+
+    /// Advances time for this entity's physics and animation, and returns a transaction
+    /// for the effects of its behaviors, which act on it via `self_binder`.
+    fn step(
+        &mut self,
+        self_binder: &dyn Fn(EntityTransaction) -> UniverseTransaction,
+        rng: &mut SeededRng,
+        tick: Tick,
+        space: &Space,
+    ) -> UniverseTransaction {
+        if self.physics {
+            self.body.step(tick, Some(space), |_| {});
+        }
+        if self.animation.is_some() {
+            self.animation_time += tick.delta_t;
+        }
+        self.behaviors
+            .step(&*self, self_binder, EntityTransaction::behaviors, rng, tick)
+    }
+}
+
+impl Transactional for Entity {
+    type Transaction = EntityTransaction;
+}
+
+/// A modification to an [`Entity`], to be applied via
+/// [`SpaceTransaction::modify_entity`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntityTransaction {
+    body: BodyTransaction,
+    /// If not [`None`], replaces [`Entity::appearance`].
+    set_appearance: Option<Option<Block>>,
+    /// If not [`None`], replaces [`Entity::animation`].
+    set_animation: Option<Option<String>>,
+    behaviors: BehaviorSetTransaction<Entity>,
+}
+
+impl EntityTransaction {
+    pub fn body(t: BodyTransaction) -> Self {
+        Self {
+            body: t,
+            ..Default::default()
+        }
+    }
+
+    /// Replaces the entity's appearance; [`None`] makes it invisible.
+    pub fn set_appearance(appearance: Option<Block>) -> Self {
+        Self {
+            set_appearance: Some(appearance),
+            ..Default::default()
+        }
+    }
+
+    /// Starts playing the named animation of the entity's [`Model`] from its beginning,
+    /// or stops animating if [`None`]. Fails if the model has no such animation.
+    pub fn set_animation(animation: Option<String>) -> Self {
+        Self {
+            set_animation: Some(animation),
+            ..Default::default()
+        }
+    }
+
+    fn behaviors(t: BehaviorSetTransaction<Entity>) -> Self {
+        Self {
+            behaviors: t,
+            ..Default::default()
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+impl Transaction<Entity> for EntityTransaction {
+    type CommitCheck = (
+        <BodyTransaction as Transaction<Body>>::CommitCheck,
+        <BehaviorSetTransaction<Entity> as Transaction<BehaviorSet<Entity>>>::CommitCheck,
+    );
+    type MergeCheck = (
+        <BodyTransaction as Transaction<Body>>::MergeCheck,
+        <BehaviorSetTransaction<Entity> as Transaction<BehaviorSet<Entity>>>::MergeCheck,
+    );
+    type Output = ();
+
+    fn check(&self, target: &Entity) -> Result<Self::CommitCheck, PreconditionFailed> {
+        if let Some(Some(animation)) = &self.set_animation {
+            if target
+                .model()
+                .and_then(|m| m.animation(animation))
+                .is_none()
+            {
+                return Err(PreconditionFailed {});
+            }
+        }
+        Ok((
+            self.body.check(&target.body)?,
+            self.behaviors.check(&target.behaviors)?,
+        ))
+    }
+
+    fn commit(
+        &self,
+        target: &mut Entity,
+        (body_check, behaviors_check): Self::CommitCheck,
+    ) -> Result<(), Box<dyn Error>> {
+        self.body.commit(&mut target.body, body_check)?;
+        if let Some(appearance) = &self.set_appearance {
+            target.appearance = appearance.clone();
+        }
+        if let Some(animation) = &self.set_animation {
+            target.animation = animation.clone();
+            target.animation_time = Duration::ZERO;
+        }
+        self.behaviors
+            .commit(&mut target.behaviors, behaviors_check)?;
+        Ok(())
+    }
+
+    fn check_merge(&self, other: &Self) -> Result<Self::MergeCheck, TransactionConflict> {
+        if (self.set_appearance.is_some() && other.set_appearance.is_some())
+            || (self.set_animation.is_some() && other.set_animation.is_some())
+        {
+            return Err(TransactionConflict {});
+        }
+        Ok((
+            self.body.check_merge(&other.body)?,
+            self.behaviors.check_merge(&other.behaviors)?,
+        ))
+    }
+
+    fn commit_merge(self, other: Self, (body_check, behaviors_check): Self::MergeCheck) -> Self {
+        Self {
+            body: self.body.commit_merge(other.body, body_check),
+            set_appearance: self.set_appearance.or(other.set_appearance),
+            set_animation: self.set_animation.or(other.set_animation),
+            behaviors: self
+                .behaviors
+                .commit_merge(other.behaviors, behaviors_check),
+        }
+    }
+}
+
+/// The collection of [`Entity`]s in a [`Space`]; obtained from [`Space::entities`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Entities {
+    entities: BTreeMap<EntityId, Entity>,
+    next_id: u64,
+}
+
+impl Entities {
+    /// Returns the entity with the given ID, if it still exists.
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    /// Iterates over all entities in the space.
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Entity)> + '_ {
+        self.entities.iter().map(|(&id, entity)| (id, entity))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub(crate) fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(&id)
+    }
+
+    pub(crate) fn insert(&mut self, entity: Entity) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.entities.insert(id, entity);
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        self.entities.remove(&id)
+    }
+
+    /// Advances time for all entities, applying physics, and returns the combined
+    /// effects of their behaviors. Behaviors are only run if `space_ref` is given, since
+    /// they can only act on the entity through it. Each entity's behaviors get their own
+    /// generator forked from `rng`.
+    pub(crate) fn step(
+        &mut self,
+        space_ref: Option<&URef<Space>>,
+        rng: &mut SeededRng,
+        tick: Tick,
+        space: &Space,
+    ) -> UniverseTransaction {
+        let mut transaction = UniverseTransaction::default();
+        if tick.paused() {
+            return transaction;
+        }
+        for (&id, entity) in self.entities.iter_mut() {
+            let mut entity_rng = rng.fork();
+            let entity_transaction = match space_ref {
+                Some(space_ref) => entity.step(
+                    &|t: EntityTransaction| {
+                        SpaceTransaction::modify_entity(id, t).bind(space_ref.clone())
+                    },
+                    &mut entity_rng,
+                    tick,
+                    space,
+                ),
+                None => entity.step(
+                    &|_: EntityTransaction| UniverseTransaction::default(),
+                    &mut entity_rng,
+                    tick,
+                    space,
+                ),
+            };
+            transaction = match transaction.clone().merge(entity_transaction) {
+                Ok(merged) => merged,
+                Err(_) => {
+                    // The entities will have another chance next step.
+                    log::debug!("entity behaviors conflicted; dropping {:?}", id);
+                    transaction
+                }
+            };
+        }
+        transaction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behavior::BehaviorContext;
+    use crate::content::make_some_blocks;
+    use crate::math::Rgba;
+    use crate::space::Grid;
+    use crate::universe::Universe;
+    use cgmath::{InnerSpace as _, Transform as _};
+
+    /// A space with a floor at y = 0 for entities to rest on.
+    fn floor_space() -> Space {
+        let mut space = Space::empty_positive(4, 3, 4);
+        space
+            .fill_uniform(Grid::new([0, 0, 0], [4, 1, 4]), &Block::from(Rgba::WHITE))
+            .unwrap();
+        space
+    }
+
+    #[derive(Debug)]
+    struct Turn;
+
+    impl Behavior<Entity> for Turn {
+        fn step(&self, c: &BehaviorContext<'_, Entity>, _tick: Tick) -> UniverseTransaction {
+            c.bind_host(EntityTransaction::body(BodyTransaction {
+                delta_yaw: 90.0,
+                ..Default::default()
+            }))
+        }
+
+        fn alive(&self, _context: &BehaviorContext<'_, Entity>) -> bool {
+            true
+        }
+
+        fn ephemeral(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn physics_falls_and_rests_on_floor() {
+        let space = floor_space();
+        let mut entities = Entities::default();
+        let physical = entities.insert(
+            Entity::new([1.5, 2.5, 1.5])
+                .with_physics(Aab::new(-0.25, 0.25, -0.25, 0.25, -0.25, 0.25)),
+        );
+        let floating = entities.insert(Entity::new([2.5, 2.5, 2.5]));
+        let mut rng = SeededRng::new(0);
+        for _ in 0..120 {
+            let _ = entities.step(None, &mut rng, Tick::from_seconds(1.0 / 60.0), &space);
+        }
+        let position = entities.get(physical).unwrap().position();
+        assert!((position.y - 1.25).abs() < 1e-3, "{:?}", position);
+        assert_eq!(
+            entities.get(floating).unwrap().position(),
+            Point3::new(2.5, 2.5, 2.5)
+        );
+    }
+
+    #[test]
+    fn transform() {
+        let entity = Entity::new([10.0, 0.0, 0.0]).with_scale(2.0);
+        assert_eq!(
+            entity
+                .transform()
+                .transform_point(Point3::new(0.5, 0.5, 0.5)),
+            Point3::new(11.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            entity
+                .transform()
+                .transform_vector(Vector3::new(1.0, 0.0, 0.0)),
+            Vector3::new(2.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn billboard_transform() {
+        let entity = Entity::new([10.0, 0.0, 0.0])
+            .with_shape(EntityShape::Billboard)
+            .with_scale(2.0);
+        // Facing +X; the viewer's right is -Z.
+        let facing = Matrix4::from_angle_y(Deg(90.0));
+        let transform = entity.billboard_transform(facing);
+        let center = transform.transform_point(Point3::new(0.5, 0.5, 1.0));
+        assert!((center - Point3::new(10.0, 0.0, 0.0)).magnitude() < 1e-12);
+        let corner = transform.transform_point(Point3::new(1.0, 1.0, 1.0));
+        assert!(
+            (corner - Point3::new(10.0, 1.0, -1.0)).magnitude() < 1e-12,
+            "{:?}",
+            corner
+        );
+    }
+
+    #[test]
+    fn transactions_add_modify_remove() {
+        let [b1, b2] = make_some_blocks();
+        let mut space = Space::empty_positive(1, 1, 1);
+        SpaceTransaction::add_entity(Entity::new([0.5, 0.5, 0.5]).with_appearance(b1.clone()))
+            .execute(&mut space)
+            .unwrap();
+        let (id, entity) = space.entities().iter().next().unwrap();
+        assert_eq!(entity.appearance(), Some(&b1));
+
+        // Two changes of appearance conflict.
+        SpaceTransaction::modify_entity(id, EntityTransaction::set_appearance(None))
+            .merge(SpaceTransaction::modify_entity(
+                id,
+                EntityTransaction::set_appearance(Some(b2.clone())),
+            ))
+            .unwrap_err();
+        // Body changes do not.
+        SpaceTransaction::modify_entity(id, EntityTransaction::set_appearance(Some(b2.clone())))
+            .merge(SpaceTransaction::modify_entity(
+                id,
+                EntityTransaction::body(BodyTransaction {
+                    delta_yaw: 10.0,
+                    ..Default::default()
+                }),
+            ))
+            .unwrap()
+            .execute(&mut space)
+            .unwrap();
+        let entity = space.entities().get(id).unwrap();
+        assert_eq!(entity.appearance(), Some(&b2));
+        assert_eq!(entity.body().yaw, 10.0);
+
+        // Removing and modifying the same entity conflicts.
+        SpaceTransaction::remove_entity(id)
+            .merge(SpaceTransaction::modify_entity(
+                id,
+                EntityTransaction::set_appearance(None),
+            ))
+            .unwrap_err();
+        SpaceTransaction::remove_entity(id)
+            .execute(&mut space)
+            .unwrap();
+        assert!(space.entities().is_empty());
+        // The entity is gone, so it cannot be modified or removed again.
+        SpaceTransaction::remove_entity(id)
+            .execute(&mut space)
+            .unwrap_err();
+        SpaceTransaction::modify_entity(id, EntityTransaction::set_appearance(None))
+            .execute(&mut space)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn model_animation_advances_when_unpaused() {
+        let [block] = make_some_blocks();
+        let mut model = Model::new();
+        model
+            .add_part(ModelPart::new(
+                "part",
+                block,
+                Aab::new(-0.5, 0.5, -0.5, 0.5, -0.5, 0.5),
+            ))
+            .unwrap();
+        model
+            .add_animation("spin", Animation::new(Duration::from_secs(4)))
+            .unwrap();
+        let mut space = Space::empty_positive(1, 1, 1);
+        SpaceTransaction::add_entity(
+            Entity::new([0.5, 0.5, 0.5])
+                .with_model(model)
+                .with_scale(0.5),
+        )
+        .execute(&mut space)
+        .unwrap();
+        let (id, entity) = space.entities().iter().next().unwrap();
+        assert_eq!(entity.model_part_transforms(), vec![entity.transform()]);
+
+        // Unknown animations are rejected.
+        SpaceTransaction::modify_entity(id, EntityTransaction::set_animation(Some("x".into())))
+            .execute(&mut space)
+            .unwrap_err();
+        SpaceTransaction::modify_entity(id, EntityTransaction::set_animation(Some("spin".into())))
+            .execute(&mut space)
+            .unwrap();
+
+        let (_, _) = space.step(None, Tick::from_seconds(1.0));
+        let (_, _) = space.step(None, Tick::from_seconds(1.0).pause());
+        let entity = space.entities().get(id).unwrap();
+        assert_eq!(entity.animation(), Some("spin"));
+        assert_eq!(entity.animation_time(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn behaviors_are_stepped_with_space() {
+        let mut universe = Universe::new();
+        let mut space = Space::empty_positive(1, 1, 1);
+        SpaceTransaction::add_entity(Entity::new([0.5, 0.5, 0.5]).with_behavior(Turn))
+            .execute(&mut space)
+            .unwrap();
+        let space = universe.insert_anonymous(space);
+        universe.step(Tick::arbitrary());
+        universe.step(Tick::arbitrary());
+        let space = space.borrow();
+        let (_, entity) = space.entities().iter().next().unwrap();
+        assert_eq!(entity.body().yaw, 180.0);
+    }
+}
@@ -0,0 +1,473 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Articulated models for [`Entity`](super::Entity): boxes connected by joints, and
+//! keyframe animations of those joints.
+//!
+//! This module is internal and reexported by its parent.
+
+use cgmath::{EuclideanSpace as _, Matrix4, One as _, Point3, Quaternion, SquareMatrix as _};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::block::Block;
+use crate::math::{Aab, FreeCoordinate};
+
+/// The appearance of an [`Entity`](super::Entity) as a set of [`ModelPart`]s, each drawn
+/// as a block stretched over a box, which may be rotated about joints by
+/// [`Animation`]s.
+///
+/// Each part is attached either to the entity itself or to a previously added parent
+/// part, and follows its parent's movement.
+///
+/// ```
+/// use all_is_cubes::block::Block;
+/// use all_is_cubes::cgmath::{Deg, Quaternion, Rotation3 as _};
+/// use all_is_cubes::entity::{Animation, Keyframe, Model, ModelPart};
+/// use all_is_cubes::math::{Aab, Rgba};
+/// use std::time::Duration;
+///
+/// let block = Block::from(Rgba::WHITE);
+/// let mut model = Model::new();
+/// let body_box = Aab::new(-0.25, 0.25, 0.0, 0.5, -0.125, 0.125);
+/// model.add_part(ModelPart::new("body", block.clone(), body_box)).unwrap();
+/// let leg_box = Aab::new(-0.1, 0.1, -0.5, 0.0, -0.1, 0.1);
+/// for &(name, x) in &[("left leg", -0.125), ("right leg", 0.125)] {
+///     model
+///         .add_part(
+///             ModelPart::new(name, block.clone(), leg_box)
+///                 .with_parent("body")
+///                 .with_pivot([x, 0.0, 0.0]),
+///         )
+///         .unwrap();
+/// }
+///
+/// // A walk cycle: the legs swing forward and back, in opposite phases.
+/// let forward = Quaternion::from_angle_x(Deg(30.0));
+/// let back = Quaternion::from_angle_x(Deg(-30.0));
+/// let second = Duration::from_secs(1);
+/// let walk = Animation::new(second)
+///     .with_keyframe("left leg", Keyframe::new(Duration::ZERO, forward))
+///     .with_keyframe("left leg", Keyframe::new(second / 2, back))
+///     .with_keyframe("right leg", Keyframe::new(Duration::ZERO, back))
+///     .with_keyframe("right leg", Keyframe::new(second / 2, forward));
+/// model.add_animation("walk", walk).unwrap();
+///
+/// assert_eq!(model.pose(Some("walk"), Duration::ZERO).len(), 3);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Model {
+    parts: Vec<ModelPart>,
+    /// For each element of `parts`, the index of its parent part.
+    parents: Vec<Option<usize>>,
+    animations: BTreeMap<String, Animation>,
+}
+
+impl Model {
+    /// Constructs a model with no parts (which will therefore be invisible).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a part to the model. Its parent, if it has one, must already have been
+    /// added, and its name must differ from those of all other parts.
+    pub fn add_part(&mut self, part: ModelPart) -> Result<(), ModelError> {
+        if self.part_index(&part.name).is_some() {
+            return Err(ModelError::DuplicatePart(part.name));
+        }
+        let parent = match &part.parent {
+            Some(parent) => Some(
+                self.part_index(parent)
+                    .ok_or_else(|| ModelError::NoSuchPart(parent.clone()))?,
+            ),
+            None => None,
+        };
+        self.parts.push(part);
+        self.parents.push(parent);
+        Ok(())
+    }
+
+    /// Adds an animation to the model, replacing any existing one with the same name.
+    /// Every part the animation has keyframes for must already have been added.
+    pub fn add_animation(
+        &mut self,
+        name: impl Into<String>,
+        animation: Animation,
+    ) -> Result<(), ModelError> {
+        if let Some(missing) = animation
+            .tracks
+            .keys()
+            .find(|part| self.part_index(part).is_none())
+        {
+            return Err(ModelError::NoSuchPart(missing.clone()));
+        }
+        self.animations.insert(name.into(), animation);
+        Ok(())
+    }
+
+    /// Returns the parts of this model, in the order they were added.
+    pub fn parts(&self) -> &[ModelPart] {
+        &self.parts
+    }
+
+    /// Returns the animation with the given name, if there is one.
+    pub fn animation(&self, name: &str) -> Option<&Animation> {
+        self.animations.get(name)
+    }
+
+    /// Returns, for each of [`Self::parts`], the transformation from the unit cube
+    /// centered on the origin to that part's box, as posed by the named animation
+    /// `time` after it started (or at rest if [`None`] or there is no such animation).
+    ///
+    /// The results are in the model's coordinates, which are the same as those of
+    /// [`Entity::transform`](super::Entity::transform)'s input.
+    pub fn pose(&self, animation: Option<&str>, time: Duration) -> Vec<Matrix4<FreeCoordinate>> {
+        let animation = animation.and_then(|name| self.animations.get(name));
+        let mut joints: Vec<Matrix4<FreeCoordinate>> = Vec::with_capacity(self.parts.len());
+        let mut boxes = Vec::with_capacity(self.parts.len());
+        for (part, &parent) in self.parts.iter().zip(self.parents.iter()) {
+            let rotation = animation.map_or_else(Quaternion::one, |animation| {
+                animation.rotation(&part.name, time)
+            });
+            let joint = parent.map_or_else(Matrix4::identity, |parent| joints[parent])
+                * Matrix4::from_translation(part.pivot.to_vec())
+                * Matrix4::from(rotation);
+            joints.push(joint);
+            let size = part.bounds.size();
+            boxes.push(
+                joint
+                    * Matrix4::from_translation(part.bounds.lower_bounds_v() + size / 2.0)
+                    * Matrix4::from_nonuniform_scale(size.x, size.y, size.z),
+            );
+        }
+        boxes
+    }
+
+    fn part_index(&self, name: &str) -> Option<usize> {
+        self.parts.iter().position(|part| part.name == name)
+    }
+}
+
+/// One piece of a [`Model`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelPart {
+    name: String,
+    appearance: Block,
+    parent: Option<String>,
+    pivot: Point3<FreeCoordinate>,
+    bounds: Aab,
+}
+
+impl ModelPart {
+    /// Constructs a part named `name`, drawn as `appearance` stretched to fill `bounds`,
+    /// which are relative to the part's pivot (the point it rotates around when
+    /// animated). It is attached to the entity, with its pivot at the entity's origin,
+    /// unless changed using [`Self::with_parent`] and [`Self::with_pivot`].
+    pub fn new(name: impl Into<String>, appearance: Block, bounds: Aab) -> Self {
+        Self {
+            name: name.into(),
+            appearance,
+            parent: None,
+            pivot: Point3::origin(),
+            bounds,
+        }
+    }
+
+    /// Attaches this part to the part named `parent`, so that it moves along with it.
+    #[must_use]
+    pub fn with_parent(mut self, parent: impl Into<String>) -> Self {
+        self.parent = Some(parent.into());
+        self
+    }
+
+    /// Sets the position of this part's pivot, relative to its parent's pivot if it has
+    /// a parent and to the entity's origin otherwise.
+    #[must_use]
+    pub fn with_pivot(mut self, pivot: impl Into<Point3<FreeCoordinate>>) -> Self {
+        self.pivot = pivot.into();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The block drawn to fill [`Self::bounds`].
+    pub fn appearance(&self) -> &Block {
+        &self.appearance
+    }
+
+    /// The name of the part this part is attached to, if any.
+    pub fn parent(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    pub fn pivot(&self) -> Point3<FreeCoordinate> {
+        self.pivot
+    }
+
+    /// The box this part occupies, relative to [`Self::pivot`], when not rotated.
+    pub fn bounds(&self) -> Aab {
+        self.bounds
+    }
+}
+
+/// A movement of some of the parts of a [`Model`], specified by [`Keyframe`]s for each
+/// part; the parts' rotations are interpolated between keyframes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    length: Duration,
+    looping: bool,
+    /// Keyframes for each part name, sorted by time.
+    tracks: BTreeMap<String, Vec<Keyframe>>,
+}
+
+impl Animation {
+    /// Constructs an animation with no keyframes, which repeats every `length`.
+    pub fn new(length: Duration) -> Self {
+        Self {
+            length,
+            looping: true,
+            tracks: BTreeMap::new(),
+        }
+    }
+
+    /// Sets whether the animation repeats. If it does not, then once it has been playing
+    /// for its length, the parts remain in their final positions.
+    #[must_use]
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Adds a keyframe for the part named `part`.
+    ///
+    /// If the animation loops, the part's rotation after the last keyframe is
+    /// interpolated toward its first keyframe, so that the animation repeats smoothly.
+    #[must_use]
+    pub fn with_keyframe(mut self, part: impl Into<String>, keyframe: Keyframe) -> Self {
+        let track = self.tracks.entry(part.into()).or_insert_with(Vec::new);
+        let index = track.partition_point(|k| k.time <= keyframe.time);
+        track.insert(index, keyframe);
+        self
+    }
+
+    pub fn length(&self) -> Duration {
+        self.length
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Returns the rotation of the part named `part` when the animation has been playing
+    /// for `time`. Parts without keyframes are not rotated.
+    pub fn rotation(&self, part: &str, time: Duration) -> Quaternion<FreeCoordinate> {
+        let track = match self.tracks.get(part) {
+            Some(track) if !track.is_empty() => track,
+            _ => return Quaternion::one(),
+        };
+        let length = self.length.as_secs_f64();
+        let time = time.as_secs_f64();
+        let time = if self.looping && length > 0.0 {
+            time % length
+        } else {
+            time.min(length)
+        };
+
+        let first = track[0];
+        let last = track[track.len() - 1];
+        let next_index = track.partition_point(|k| k.time.as_secs_f64() <= time);
+        let ((t0, r0), (t1, r1)) = if next_index == 0 {
+            if !self.looping {
+                return first.rotation;
+            }
+            (
+                (last.time.as_secs_f64() - length, last.rotation),
+                (first.time.as_secs_f64(), first.rotation),
+            )
+        } else if next_index == track.len() {
+            if !self.looping {
+                return last.rotation;
+            }
+            (
+                (last.time.as_secs_f64(), last.rotation),
+                (first.time.as_secs_f64() + length, first.rotation),
+            )
+        } else {
+            let (previous, next) = (track[next_index - 1], track[next_index]);
+            (
+                (previous.time.as_secs_f64(), previous.rotation),
+                (next.time.as_secs_f64(), next.rotation),
+            )
+        };
+        if t1 > t0 {
+            r0.slerp(r1, (time - t0) / (t1 - t0))
+        } else {
+            r0
+        }
+    }
+}
+
+/// The rotation of a [`ModelPart`] at a particular time in an [`Animation`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Keyframe {
+    /// Time since the start of the animation.
+    pub time: Duration,
+    /// Rotation of the part about its pivot.
+    pub rotation: Quaternion<FreeCoordinate>,
+}
+
+impl Keyframe {
+    pub fn new(time: Duration, rotation: impl Into<Quaternion<FreeCoordinate>>) -> Self {
+        Self {
+            time,
+            rotation: rotation.into(),
+        }
+    }
+}
+
+/// Errors resulting from building a [`Model`] inconsistently.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ModelError {
+    /// A part or animation referred to a part which has not been added to the model.
+    #[error("model has no part named {0:?}")]
+    NoSuchPart(String),
+    /// A part was added with the same name as an existing part.
+    #[error("model already has a part named {0:?}")]
+    DuplicatePart(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::make_some_blocks;
+    use cgmath::{Deg, InnerSpace as _, Rotation3 as _, Transform as _};
+
+    fn approx_eq(a: Point3<FreeCoordinate>, b: Point3<FreeCoordinate>) -> bool {
+        (a - b).magnitude() < 1e-9
+    }
+
+    /// A body with an arm attached at its top, which swings down to point forward
+    /// (-Z) halfway through the animation.
+    fn arm_model() -> Model {
+        let [body, arm] = make_some_blocks();
+        let mut model = Model::new();
+        model
+            .add_part(ModelPart::new(
+                "body",
+                body,
+                Aab::new(-0.5, 0.5, 0.0, 1.0, -0.5, 0.5),
+            ))
+            .unwrap();
+        model
+            .add_part(
+                ModelPart::new("arm", arm, Aab::new(0.0, 0.2, -1.0, 0.0, -0.1, 0.1))
+                    .with_parent("body")
+                    .with_pivot([0.5, 1.0, 0.0]),
+            )
+            .unwrap();
+        let second = Duration::from_secs(1);
+        model
+            .add_animation(
+                "swing",
+                Animation::new(second)
+                    .with_keyframe(
+                        "arm",
+                        Keyframe::new(second / 2, Quaternion::from_angle_x(Deg(90.0))),
+                    )
+                    .with_keyframe("arm", Keyframe::new(Duration::ZERO, Quaternion::one())),
+            )
+            .unwrap();
+        model
+    }
+
+    #[test]
+    fn rest_pose() {
+        let model = arm_model();
+        let pose = model.pose(None, Duration::from_millis(500));
+        assert_eq!(pose.len(), 2);
+        assert!(approx_eq(
+            pose[0].transform_point(Point3::new(0.5, 0.5, 0.5)),
+            Point3::new(0.5, 1.0, 0.5)
+        ));
+        // Tip of the arm, at the center of its lower face.
+        assert!(approx_eq(
+            pose[1].transform_point(Point3::new(0.0, -0.5, 0.0)),
+            Point3::new(0.6, 0.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn animated_pose_follows_keyframes_and_loops() {
+        let model = arm_model();
+        let tip = |millis: u64| {
+            model.pose(Some("swing"), Duration::from_millis(millis))[1]
+                .transform_point(Point3::new(0.0, -0.5, 0.0))
+        };
+        assert!(approx_eq(tip(0), Point3::new(0.6, 0.0, 0.0)));
+        // Rotating -Y by 90° about X gives -Z.
+        assert!(
+            approx_eq(tip(500), Point3::new(0.6, 1.0, -1.0)),
+            "{:?}",
+            tip(500)
+        );
+        // Halfway between, at 45°.
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        assert!(approx_eq(tip(250), Point3::new(0.6, 1.0 - half, -half)));
+        // Returning to the first keyframe, and then repeating.
+        assert!(approx_eq(tip(750), tip(250)));
+        assert!(approx_eq(tip(1500), tip(500)));
+        // The body is not animated.
+        assert_eq!(
+            model.pose(Some("swing"), Duration::from_millis(500))[0],
+            model.pose(None, Duration::ZERO)[0]
+        );
+    }
+
+    #[test]
+    fn non_looping_holds_last_keyframe() {
+        let animation = Animation::new(Duration::from_secs(1))
+            .with_looping(false)
+            .with_keyframe(
+                "a",
+                Keyframe::new(Duration::from_secs(1), Quaternion::from_angle_y(Deg(90.0))),
+            );
+        assert_eq!(
+            animation.rotation("a", Duration::from_secs(5)),
+            Quaternion::from_angle_y(Deg(90.0))
+        );
+        assert_eq!(
+            animation.rotation("b", Duration::from_secs(5)),
+            Quaternion::one()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let [block] = make_some_blocks();
+        let mut model = Model::new();
+        assert_eq!(
+            model.add_part(ModelPart::new("a", block.clone(), Aab::ZERO).with_parent("b")),
+            Err(ModelError::NoSuchPart("b".into()))
+        );
+        model
+            .add_part(ModelPart::new("a", block.clone(), Aab::ZERO))
+            .unwrap();
+        assert_eq!(
+            model.add_part(ModelPart::new("a", block, Aab::ZERO)),
+            Err(ModelError::DuplicatePart("a".into()))
+        );
+        assert_eq!(
+            model.add_animation(
+                "x",
+                Animation::new(Duration::from_secs(1))
+                    .with_keyframe("c", Keyframe::new(Duration::ZERO, Quaternion::one()))
+            ),
+            Err(ModelError::NoSuchPart("c".into()))
+        );
+        assert!(model.animation("x").is_none());
+    }
+}
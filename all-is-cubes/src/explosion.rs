@@ -0,0 +1,324 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Explosion`]s, which destroy the blocks around a point and push things away from it.
+
+use cgmath::{InnerSpace as _, Point3, Vector3, Zero as _};
+use rand::Rng;
+
+use crate::block::{Block, AIR};
+use crate::entity::{Entity, EntityShape, EntityTransaction};
+use crate::math::{Aab, FreeCoordinate, GridPoint};
+use crate::physics::{Body, BodyTransaction};
+use crate::space::{Space, SpaceTransaction};
+use crate::transactions::Transaction as _;
+
+/// Edge length of the debris entities added by an [`Explosion`].
+const DEBRIS_SCALE: FreeCoordinate = 0.25;
+
+/// A sudden burst at a point, which destroys blocks and pushes bodies within a radius
+/// of it.
+///
+/// The effect of an explosion falls off linearly from full at its center to nothing at
+/// its radius. Applying it to a [`Space`] with [`Explosion::space_transaction`]:
+///
+/// * removes each block whose [hardness](crate::block::BlockAttributes::hardness) is
+///   less than the explosion's [strength](Explosion::strength) at that cube, so that
+///   hard blocks are destroyed only near the center, if at all;
+/// * adds up to [`Explosion::debris`] small [`Entity`]s that look like some of the
+///   removed blocks, flying outward;
+/// * pushes the physical entities in the space away from the center.
+///
+/// [Characters](crate::character::Character) are not part of a space; push them using
+/// [`Explosion::body_transaction`].
+///
+/// ```
+/// use all_is_cubes::block::AIR;
+/// use all_is_cubes::explosion::Explosion;
+/// use all_is_cubes::math::{Rgba, SeededRng};
+/// use all_is_cubes::space::Space;
+/// use all_is_cubes::transactions::Transaction as _;
+///
+/// let mut space = Space::empty_positive(5, 5, 5);
+/// space.fill_uniform(space.grid(), Rgba::WHITE).unwrap();
+///
+/// let explosion = Explosion::new([2.5, 2.5, 2.5], 2.0);
+/// explosion
+///     .space_transaction(&space, &mut SeededRng::new(0))
+///     .execute(&mut space)
+///     .unwrap();
+/// assert_eq!(space[[2, 2, 2]], AIR);
+/// assert_ne!(space[[0, 0, 0]], AIR);
+/// assert!(!space.entities().is_empty());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explosion {
+    center: Point3<FreeCoordinate>,
+    radius: FreeCoordinate,
+    strength: FreeCoordinate,
+    impulse: FreeCoordinate,
+    debris: usize,
+}
+
+impl Explosion {
+    /// Constructs an explosion centered on `center` and affecting everything within
+    /// `radius` of it, with a [strength](Self::strength) of 1, an
+    /// [impulse](Self::impulse) of 10, and 8 pieces of [debris](Self::debris).
+    pub fn new(center: impl Into<Point3<FreeCoordinate>>, radius: FreeCoordinate) -> Self {
+        Self {
+            center: center.into(),
+            radius: radius.max(0.0),
+            strength: 1.0,
+            impulse: 10.0,
+            debris: 8,
+        }
+    }
+
+    /// Sets the strength of the explosion at its center, which is compared with the
+    /// hardness of blocks (the number of seconds it takes to break them) to decide
+    /// which are destroyed.
+    #[must_use]
+    pub fn strength(mut self, strength: FreeCoordinate) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    /// Sets the change in velocity, in cubes per second, of a body at the center of the
+    /// explosion.
+    #[must_use]
+    pub fn impulse(mut self, impulse: FreeCoordinate) -> Self {
+        self.impulse = impulse;
+        self
+    }
+
+    /// Sets the greatest number of debris entities added for the destroyed blocks.
+    #[must_use]
+    pub fn debris(mut self, count: usize) -> Self {
+        self.debris = count;
+        self
+    }
+
+    /// Returns the point at the center of the explosion.
+    pub fn center(&self) -> Point3<FreeCoordinate> {
+        self.center
+    }
+
+    /// Returns the distance from the center beyond which the explosion has no effect.
+    pub fn radius(&self) -> FreeCoordinate {
+        self.radius
+    }
+
+    /// Returns the fraction of the explosion's full effect which reaches `point`.
+    fn falloff(&self, point: Point3<FreeCoordinate>) -> FreeCoordinate {
+        if self.radius <= 0.0 {
+            return 0.0;
+        }
+        (1.0 - (point - self.center).magnitude() / self.radius).max(0.0)
+    }
+
+    /// Returns the change in velocity of a body at `position`.
+    fn push(&self, position: Point3<FreeCoordinate>) -> Vector3<FreeCoordinate> {
+        let falloff = self.falloff(position);
+        if falloff == 0.0 {
+            return Vector3::zero();
+        }
+        let offset = position - self.center;
+        let direction = if offset.magnitude2() > 1e-12 {
+            offset.normalize()
+        } else {
+            // Straight up is as good a direction as any.
+            Vector3::unit_y()
+        };
+        direction * (self.impulse * falloff)
+    }
+
+    /// Returns a transaction which pushes `body` away from the center of the explosion,
+    /// or does nothing if it is out of range.
+    pub fn body_transaction(&self, body: &Body) -> BodyTransaction {
+        BodyTransaction {
+            delta_velocity: self.push(body.position),
+            ..Default::default()
+        }
+    }
+
+    /// Returns a transaction which applies the explosion to `space`, as described in
+    /// the [type documentation](Self). `rng` chooses which destroyed blocks become
+    /// debris and how the debris flies.
+    ///
+    /// The transaction fails if any of the blocks it removes has been changed.
+    pub fn space_transaction(&self, space: &Space, rng: &mut impl Rng) -> SpaceTransaction {
+        let mut transaction = SpaceTransaction::default();
+
+        let r = Vector3::new(self.radius, self.radius, self.radius);
+        let bounds = Aab::from_lower_upper(self.center - r, self.center + r).round_up_to_grid();
+        let mut destroyed: Vec<(GridPoint, &Block)> = Vec::new();
+        for cube in bounds
+            .intersection(space.grid())
+            .into_iter()
+            .flat_map(|grid| grid.interior_iter())
+        {
+            let block = &space[cube];
+            let attributes = &space.get_evaluated(cube).attributes;
+            let strength = self.strength * self.falloff(cube_midpoint(cube));
+            if *block == AIR
+                || !attributes.selectable
+                || FreeCoordinate::from(attributes.hardness.into_inner()) >= strength
+            {
+                continue;
+            }
+            transaction = transaction
+                .merge(SpaceTransaction::set_cube(
+                    cube,
+                    Some(block.clone()),
+                    Some(AIR),
+                ))
+                .expect("can't happen: cubes are distinct");
+            destroyed.push((cube, block));
+        }
+
+        // Choose which of the destroyed blocks become debris, by partially shuffling.
+        let debris_count = self.debris.min(destroyed.len());
+        for i in 0..debris_count {
+            let j = rng.gen_range(i..destroyed.len());
+            destroyed.swap(i, j);
+        }
+        for &(cube, block) in &destroyed[..debris_count] {
+            let position = cube_midpoint(cube);
+            let scatter = Vector3::new(
+                rng.gen_range(-1.0..=1.0),
+                rng.gen_range(0.0..=1.0),
+                rng.gen_range(-1.0..=1.0),
+            );
+            let half = DEBRIS_SCALE / 2.0;
+            let debris = Entity::new(position)
+                .with_appearance(block.clone())
+                .with_shape(EntityShape::Billboard)
+                .with_scale(DEBRIS_SCALE)
+                .with_physics(Aab::new(-half, half, -half, half, -half, half))
+                .with_velocity(self.push(position) + scatter);
+            transaction = transaction
+                .merge(SpaceTransaction::add_entity(debris))
+                .expect("can't happen: adding entities does not conflict");
+        }
+
+        for (id, entity) in space.entities().iter() {
+            if !entity.has_physics() || self.falloff(entity.position()) == 0.0 {
+                continue;
+            }
+            transaction = transaction
+                .merge(SpaceTransaction::modify_entity(
+                    id,
+                    EntityTransaction::body(self.body_transaction(entity.body())),
+                ))
+                .expect("can't happen: entities are distinct");
+        }
+
+        transaction
+    }
+}
+
+fn cube_midpoint(cube: GridPoint) -> Point3<FreeCoordinate> {
+    cube.cast::<FreeCoordinate>().unwrap() + Vector3::new(0.5, 0.5, 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{Rgba, SeededRng};
+
+    fn filled_space(block: &Block) -> Space {
+        let mut space = Space::empty_positive(9, 9, 9);
+        space.fill_uniform(space.grid(), block).unwrap();
+        space
+    }
+
+    fn explode(explosion: &Explosion, space: &mut Space) {
+        explosion
+            .space_transaction(space, &mut SeededRng::new(0))
+            .execute(space)
+            .unwrap();
+    }
+
+    #[test]
+    fn destroys_within_radius() {
+        let block = Block::from(Rgba::WHITE);
+        let mut space = filled_space(&block);
+        explode(&Explosion::new([4.5, 4.5, 4.5], 3.0), &mut space);
+        assert_eq!(space[[4, 4, 4]], AIR);
+        assert_eq!(space[[6, 4, 4]], AIR);
+        // Exactly at the radius, the effect is zero.
+        assert_eq!(space[[7, 4, 4]], block);
+        assert_eq!(space[[0, 0, 0]], block);
+    }
+
+    #[test]
+    fn hardness_resists() {
+        let block = Block::builder().color(Rgba::WHITE).hardness(0.5).build();
+        let mut space = filled_space(&block);
+        explode(&Explosion::new([4.5, 4.5, 4.5], 4.0), &mut space);
+        assert_eq!(space[[4, 4, 4]], AIR);
+        assert_eq!(space[[5, 4, 4]], AIR);
+        assert_eq!(space[[6, 4, 4]], block);
+
+        let mut space = filled_space(&block);
+        explode(
+            &Explosion::new([4.5, 4.5, 4.5], 4.0).strength(0.25),
+            &mut space,
+        );
+        assert_eq!(space[[4, 4, 4]], block);
+    }
+
+    #[test]
+    fn debris_count() {
+        let mut space = filled_space(&Block::from(Rgba::WHITE));
+        explode(&Explosion::new([4.5, 4.5, 4.5], 3.0).debris(5), &mut space);
+        assert_eq!(space.entities().len(), 5);
+        for (_, entity) in space.entities().iter() {
+            assert_eq!(entity.appearance(), Some(&Block::from(Rgba::WHITE)));
+            assert!(entity.has_physics());
+        }
+
+        // Fewer blocks destroyed than the limit.
+        let mut space = filled_space(&Block::from(Rgba::WHITE));
+        explode(&Explosion::new([4.5, 4.5, 4.5], 0.5), &mut space);
+        assert_eq!(space.entities().len(), 1);
+    }
+
+    #[test]
+    fn pushes_entities() {
+        let mut space = Space::empty_positive(9, 9, 9);
+        SpaceTransaction::add_entity(
+            Entity::new([6.5, 4.5, 4.5]).with_physics(Aab::new(-0.1, 0.1, -0.1, 0.1, -0.1, 0.1)),
+        )
+        .merge(SpaceTransaction::add_entity(Entity::new([6.5, 4.5, 4.5])))
+        .unwrap()
+        .execute(&mut space)
+        .unwrap();
+        explode(&Explosion::new([4.5, 4.5, 4.5], 4.0), &mut space);
+
+        let velocities: Vec<Vector3<FreeCoordinate>> = space
+            .entities()
+            .iter()
+            .map(|(_, entity)| entity.body().velocity)
+            .collect();
+        // Half of the impulse, since the entity is halfway to the radius.
+        assert_eq!(velocities[0], Vector3::new(5.0, 0.0, 0.0));
+        // Not a physical entity.
+        assert_eq!(velocities[1], Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn body_transaction() {
+        let explosion = Explosion::new([0.0, 0.0, 0.0], 2.0).impulse(4.0);
+        let at_center = Body::new_minimal([0.0, 0.0, 0.0], Aab::ZERO);
+        assert_eq!(
+            explosion.body_transaction(&at_center).delta_velocity,
+            Vector3::new(0.0, 4.0, 0.0)
+        );
+        let out_of_range = Body::new_minimal([0.0, 0.0, 3.0], Aab::ZERO);
+        assert_eq!(
+            explosion.body_transaction(&out_of_range),
+            BodyTransaction::default()
+        );
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Localization of text shown to the user, such as block display names.
+//!
+//! Any text may be either literal, or a *key* marked by starting with [`KEY_PREFIX`],
+//! such as `"@block.glass"`. Keys are looked up in a [`Localizer`] when the text is
+//! displayed, by [`localize`]; keys with no translation are displayed as the key
+//! itself. Literal text which begins with the prefix character is written with it
+//! doubled, as in `"@@home"` for “@home”.
+//!
+//! ```
+//! use all_is_cubes::i18n::{localize, NoLocalizer, TableLocalizer};
+//!
+//! let mut localizer = TableLocalizer::new();
+//! localizer.insert("block.glass", "Verre");
+//! assert_eq!(localize(&localizer, "@block.glass"), "Verre");
+//! assert_eq!(localize(&localizer, "@block.stone"), "block.stone");
+//! assert_eq!(localize(&localizer, "Glass"), "Glass");
+//! assert_eq!(localize(&localizer, "@@home"), "@home");
+//! assert_eq!(localize(&NoLocalizer, "@block.glass"), "block.glass");
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::iter::FromIterator;
+
+/// Character which marks text as a key to be looked up in a [`Localizer`].
+pub const KEY_PREFIX: char = '@';
+
+/// Source of translated text for localization keys; see the [module
+/// documentation](self).
+pub trait Localizer: Debug + Send + Sync {
+    /// Returns the translation of `key` (which does not include [`KEY_PREFIX`]), or
+    /// [`None`] if there is none.
+    fn translate(&self, key: &str) -> Option<Cow<'_, str>>;
+}
+
+/// Returns `text` as it should be displayed: translated by `localizer` if it is a key,
+/// or as written if it is literal.
+pub fn localize<'a>(localizer: &'a dyn Localizer, text: &'a str) -> Cow<'a, str> {
+    match text.strip_prefix(KEY_PREFIX) {
+        None => Cow::Borrowed(text),
+        Some(escaped) if escaped.starts_with(KEY_PREFIX) => Cow::Borrowed(escaped),
+        Some(key) => localizer.translate(key).unwrap_or(Cow::Borrowed(key)),
+    }
+}
+
+/// [`Localizer`] with no translations, which therefore displays keys as themselves.
+#[allow(clippy::exhaustive_structs)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct NoLocalizer;
+
+impl Localizer for NoLocalizer {
+    fn translate(&self, _key: &str) -> Option<Cow<'_, str>> {
+        None
+    }
+}
+
+/// [`Localizer`] which looks up keys in a table, such as one loaded from a file of
+/// translations for one language.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TableLocalizer {
+    table: HashMap<String, String>,
+}
+
+impl TableLocalizer {
+    /// Constructs an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the translation of `key`, replacing any previous one.
+    pub fn insert(&mut self, key: impl Into<String>, text: impl Into<String>) {
+        self.table.insert(key.into(), text.into());
+    }
+}
+
+impl Localizer for TableLocalizer {
+    fn translate(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.table.get(key).map(|text| Cow::Borrowed(text.as_str()))
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for TableLocalizer {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            table: iter
+                .into_iter()
+                .map(|(key, text)| (key.into(), text.into()))
+                .collect(),
+        }
+    }
+}
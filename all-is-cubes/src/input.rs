@@ -0,0 +1,487 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Platform-independent description of user input and what it does: [`Input`]s
+//! (keys, mouse buttons, gamepad buttons) are mapped to [`Action`]s, and analog
+//! [`Axis`] inputs to [`AxisAction`]s, by an [`InputMap`].
+//!
+//! Frontends translate their native events into [`Input`]s and pass them to
+//! [`InputProcessor`](crate::apps::InputProcessor), which consults its [`InputMap`];
+//! user interface can consult the same map to show which input performs an action.
+
+use cgmath::{InnerSpace as _, Vector2, Zero as _};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::math::FreeCoordinate;
+
+/// A platform-neutral representation of keyboard keys.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Key {
+    /// Letters should be lowercase.
+    Character(char),
+    /// Left arrow key.
+    Left,
+    /// Right arrow key.
+    Right,
+    /// Up arrow key.
+    Up,
+    /// Down arrow key.
+    Down,
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Key::Character(' ') => write!(f, "Space"),
+            Key::Character(c) => write!(f, "{}", c.to_uppercase()),
+            Key::Left => write!(f, "←"),
+            Key::Right => write!(f, "→"),
+            Key::Up => write!(f, "↑"),
+            Key::Down => write!(f, "↓"),
+        }
+    }
+}
+
+/// A single button-like input which may be bound to an [`Action`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Input {
+    /// A keyboard key.
+    Key(Key),
+    /// A mouse button. 0 is the primary (usually left) button, 1 the secondary (right),
+    /// and 2 the middle button.
+    MouseButton(u8),
+    /// A gamepad button, numbered as in the
+    /// [standard gamepad layout](https://www.w3.org/TR/gamepad/#remapping).
+    GamepadButton(u8),
+}
+
+impl From<Key> for Input {
+    fn from(key: Key) -> Self {
+        Input::Key(key)
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Input::Key(key) => key.fmt(f),
+            Input::MouseButton(0) => write!(f, "Left Click"),
+            Input::MouseButton(1) => write!(f, "Right Click"),
+            Input::MouseButton(2) => write!(f, "Middle Click"),
+            Input::MouseButton(n) => write!(f, "Mouse {}", n + 1),
+            Input::GamepadButton(n) => write!(f, "Gamepad {}", n),
+        }
+    }
+}
+
+/// Something the player can do by pressing an [`Input`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Action {
+    /// Move left while held.
+    MoveLeft,
+    /// Move right while held.
+    MoveRight,
+    /// Move forward while held.
+    MoveForward,
+    /// Move backward while held.
+    MoveBack,
+    /// Move (fly) up while held.
+    MoveUp,
+    /// Move (fly) down while held.
+    MoveDown,
+    /// Turn left while held.
+    TurnLeft,
+    /// Turn right while held.
+    TurnRight,
+    /// Look up while held.
+    TurnUp,
+    /// Look down while held.
+    TurnDown,
+    /// Jump while held.
+    Jump,
+    /// Move faster while held; see [`Gait::Sprint`](crate::character::Gait::Sprint).
+    Sprint,
+    /// Move slower while held; see [`Gait::Sneak`](crate::character::Gait::Sneak).
+    Sneak,
+    /// Select the given inventory slot (counting from 0) for [`Action::Place`].
+    SelectSlot(usize),
+    /// Use the tool which breaks blocks (tool button 0).
+    Break,
+    /// Use the selected inventory item, which usually places a block (tool button 1).
+    Place,
+    /// Use the tool which copies a block into the inventory (tool button 2).
+    Pick,
+    /// Toss the selected inventory item.
+    Toss,
+    /// Turn mouselook (pointer lock) on or off.
+    ToggleMouselook,
+    /// Pause or unpause the game.
+    TogglePause,
+    /// Turn spectator movement, which ignores collision and gravity, on or off; see
+    /// [`Body::noclip`](crate::physics::Body::noclip).
+    ToggleNoclip,
+}
+
+impl Action {
+    /// Returns whether this action happens once per press, rather than continuing
+    /// while the input is held.
+    pub fn is_command(self) -> bool {
+        matches!(
+            self,
+            Action::SelectSlot(_)
+                | Action::Toss
+                | Action::ToggleMouselook
+                | Action::TogglePause
+                | Action::ToggleNoclip
+        )
+    }
+
+    /// If this action uses a tool, returns the index of the tool button, as used by
+    /// [`Character::click`](crate::character::Character::click).
+    pub fn tool_button(self) -> Option<usize> {
+        match self {
+            Action::Break => Some(0),
+            Action::Place => Some(1),
+            Action::Pick => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/// An analog input, whose position ranges from -1 to 1.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Axis {
+    /// A gamepad axis, numbered as in the
+    /// [standard gamepad layout](https://www.w3.org/TR/gamepad/#remapping):
+    /// 0 and 1 are the left stick's X and Y, and 2 and 3 the right stick's.
+    /// Positive Y is downward.
+    GamepadAxis(u8),
+}
+
+/// Something the player can control with an [`Axis`].
+///
+/// Pairs of these are treated as the components of a stick, whose position is shaped
+/// by an [`AnalogCurve`] as a whole.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum AxisAction {
+    /// Walk rightward (positive) or leftward (negative).
+    WalkX,
+    /// Walk backward (positive) or forward (negative).
+    WalkZ,
+    /// Turn right (positive) or left (negative).
+    LookX,
+    /// Look down (positive) or up (negative).
+    LookY,
+}
+
+/// Response curve applied to the position of an analog stick.
+///
+/// The curve is applied to the distance of the stick from center, so that the dead
+/// zone is round and diagonal movement is not distorted.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct AnalogCurve {
+    /// Distance from center, out of 1, within which the stick is treated as centered.
+    /// This hides the imprecision of physical sticks at rest. The remaining range is
+    /// rescaled so that the output still starts at zero.
+    pub dead_zone: FreeCoordinate,
+    /// Exponent applied to the distance after removing the dead zone. Values greater
+    /// than 1 give finer control near the center.
+    pub exponent: FreeCoordinate,
+    /// Multiplier for the output, which otherwise has a magnitude of at most 1.
+    pub scale: FreeCoordinate,
+}
+
+impl AnalogCurve {
+    /// Applies the curve to a raw stick position, whose components are clamped to
+    /// the range -1 to 1.
+    ///
+    /// ```
+    /// use all_is_cubes::cgmath::Vector2;
+    /// use all_is_cubes::input::AnalogCurve;
+    ///
+    /// let curve = AnalogCurve::default();
+    /// assert_eq!(curve.apply(Vector2::new(0.05, 0.0)), Vector2::new(0.0, 0.0));
+    /// assert_eq!(curve.apply(Vector2::new(0.0, -1.0)), Vector2::new(0.0, -1.0));
+    /// ```
+    pub fn apply(&self, raw: Vector2<FreeCoordinate>) -> Vector2<FreeCoordinate> {
+        let raw = raw.map(|c| c.max(-1.0).min(1.0));
+        let magnitude = raw.magnitude();
+        if magnitude.is_nan() || magnitude <= self.dead_zone {
+            return Vector2::zero();
+        }
+        let live =
+            ((magnitude.min(1.0) - self.dead_zone) / (1.0 - self.dead_zone)).powf(self.exponent);
+        raw / magnitude * (live * self.scale)
+    }
+}
+
+impl Default for AnalogCurve {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.15,
+            exponent: 1.0,
+            scale: 1.0,
+        }
+    }
+}
+
+/// Assignment of [`Input`]s to [`Action`]s and [`Axis`]es to [`AxisAction`]s.
+///
+/// Each input performs at most one action, but an action may have any number of inputs.
+/// [`InputMap::default()`] provides the standard bindings.
+///
+/// ```
+/// use all_is_cubes::input::{Action, Input, InputMap, Key};
+///
+/// let mut map = InputMap::default();
+/// assert_eq!(map.action(Input::Key(Key::Character('w'))), Some(Action::MoveForward));
+///
+/// map.bind(Key::Up, Action::MoveForward);
+/// assert_eq!(map.action(Input::Key(Key::Up)), Some(Action::MoveForward));
+/// assert_eq!(map.primary_input(Action::TurnUp), None);
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: Vec<(Input, Action)>,
+    #[serde(default)]
+    axes: Vec<(Axis, AxisAction)>,
+    /// Response curve for [`AxisAction::WalkX`] and [`AxisAction::WalkZ`]. The output
+    /// is a fraction of full walking speed.
+    #[serde(default)]
+    pub walk_curve: AnalogCurve,
+    /// Response curve for [`AxisAction::LookX`] and [`AxisAction::LookY`]. The output
+    /// is a turning rate in degrees per second.
+    #[serde(default = "default_look_curve")]
+    pub look_curve: AnalogCurve,
+}
+
+fn default_look_curve() -> AnalogCurve {
+    AnalogCurve {
+        dead_zone: 0.1,
+        exponent: 2.0,
+        scale: 180.0,
+    }
+}
+
+impl InputMap {
+    /// Constructs an [`InputMap`] with nothing bound and default response curves.
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            axes: Vec::new(),
+            walk_curve: AnalogCurve::default(),
+            look_curve: default_look_curve(),
+        }
+    }
+
+    /// Makes `input` perform `action`, replacing any previous binding of `input`.
+    pub fn bind(&mut self, input: impl Into<Input>, action: Action) {
+        let input = input.into();
+        self.unbind(input);
+        self.bindings.push((input, action));
+    }
+
+    /// Makes `input` do nothing.
+    pub fn unbind(&mut self, input: impl Into<Input>) {
+        let input = input.into();
+        self.bindings.retain(|&(i, _)| i != input);
+    }
+
+    /// Returns the action `input` performs, if any.
+    pub fn action(&self, input: Input) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|&&(i, _)| i == input)
+            .map(|&(_, action)| action)
+    }
+
+    /// Returns all inputs which perform `action`, in the order they were bound.
+    pub fn inputs(&self, action: Action) -> impl Iterator<Item = Input> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |&&(_, a)| a == action)
+            .map(|&(input, _)| input)
+    }
+
+    /// Returns the input which should be shown to the player as the way to perform
+    /// `action`, if there is any.
+    pub fn primary_input(&self, action: Action) -> Option<Input> {
+        self.inputs(action).next()
+    }
+
+    /// Returns all bindings, in the order they were bound.
+    pub fn bindings(&self) -> impl Iterator<Item = (Input, Action)> + '_ {
+        self.bindings.iter().copied()
+    }
+
+    /// Makes `axis` control `action`, replacing any previous binding of `axis`.
+    pub fn bind_axis(&mut self, axis: Axis, action: AxisAction) {
+        self.unbind_axis(axis);
+        self.axes.push((axis, action));
+    }
+
+    /// Makes `axis` do nothing.
+    pub fn unbind_axis(&mut self, axis: Axis) {
+        self.axes.retain(|&(a, _)| a != axis);
+    }
+
+    /// Returns the action `axis` controls, if any.
+    pub fn axis_action(&self, axis: Axis) -> Option<AxisAction> {
+        self.axes
+            .iter()
+            .find(|&&(a, _)| a == axis)
+            .map(|&(_, action)| action)
+    }
+
+    /// Computes the walking and looking vectors (in that order) from the given axis
+    /// positions, applying [`InputMap::walk_curve`] and [`InputMap::look_curve`].
+    ///
+    /// Walking is in the X and Z directions as in
+    /// [`Character::set_velocity_input`](crate::character::Character::set_velocity_input);
+    /// looking is in degrees per second of yaw and pitch.
+    pub fn analog(
+        &self,
+        positions: impl IntoIterator<Item = (Axis, FreeCoordinate)>,
+    ) -> (Vector2<FreeCoordinate>, Vector2<FreeCoordinate>) {
+        let mut walk = Vector2::zero();
+        let mut look = Vector2::zero();
+        for (axis, position) in positions {
+            match self.axis_action(axis) {
+                Some(AxisAction::WalkX) => walk.x += position,
+                Some(AxisAction::WalkZ) => walk.y += position,
+                Some(AxisAction::LookX) => look.x += position,
+                Some(AxisAction::LookY) => look.y += position,
+                None => {}
+            }
+        }
+        (self.walk_curve.apply(walk), self.look_curve.apply(look))
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let mut map = Self::new();
+        for &(c, action) in &[
+            ('w', Action::MoveForward),
+            ('a', Action::MoveLeft),
+            ('s', Action::MoveBack),
+            ('d', Action::MoveRight),
+            ('e', Action::MoveUp),
+            ('c', Action::MoveDown),
+            (' ', Action::Jump),
+            ('r', Action::Sprint),
+            ('z', Action::Sneak),
+            ('q', Action::Toss),
+            ('l', Action::ToggleMouselook),
+            ('p', Action::TogglePause),
+            ('n', Action::ToggleNoclip),
+        ] {
+            map.bind(Key::Character(c), action);
+        }
+        map.bind(Key::Left, Action::TurnLeft);
+        map.bind(Key::Right, Action::TurnRight);
+        map.bind(Key::Up, Action::TurnUp);
+        map.bind(Key::Down, Action::TurnDown);
+        for digit in 0..10 {
+            // The "1" key selects the first slot, and "0" the tenth.
+            let slot = (digit + 9) % 10;
+            map.bind(
+                Key::Character(std::char::from_digit(digit, 10).unwrap()),
+                Action::SelectSlot(slot as usize),
+            );
+        }
+        map.bind(Input::MouseButton(0), Action::Break);
+        map.bind(Input::MouseButton(1), Action::Place);
+        map.bind(Input::MouseButton(2), Action::Pick);
+
+        // Standard gamepad layout: sticks, then face buttons, bumpers, and triggers.
+        map.bind_axis(Axis::GamepadAxis(0), AxisAction::WalkX);
+        map.bind_axis(Axis::GamepadAxis(1), AxisAction::WalkZ);
+        map.bind_axis(Axis::GamepadAxis(2), AxisAction::LookX);
+        map.bind_axis(Axis::GamepadAxis(3), AxisAction::LookY);
+        map.bind(Input::GamepadButton(0), Action::Jump);
+        map.bind(Input::GamepadButton(4), Action::MoveDown);
+        map.bind(Input::GamepadButton(5), Action::MoveUp);
+        map.bind(Input::GamepadButton(6), Action::Break);
+        map.bind(Input::GamepadButton(7), Action::Place);
+        map.bind(Input::GamepadButton(9), Action::TogglePause);
+        map.bind(Input::GamepadButton(10), Action::Sprint);
+        map.bind(Input::GamepadButton(11), Action::Sneak);
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_slot_keys() {
+        let map = InputMap::default();
+        assert_eq!(
+            map.action(Input::Key(Key::Character('1'))),
+            Some(Action::SelectSlot(0))
+        );
+        assert_eq!(
+            map.action(Input::Key(Key::Character('0'))),
+            Some(Action::SelectSlot(9))
+        );
+    }
+
+    #[test]
+    fn bind_replaces_previous_binding() {
+        let mut map = InputMap::new();
+        let input = Input::Key(Key::Character('x'));
+        map.bind(input, Action::Jump);
+        map.bind(input, Action::Toss);
+        assert_eq!(map.action(input), Some(Action::Toss));
+        assert_eq!(map.primary_input(Action::Jump), None);
+        assert_eq!(map.bindings().count(), 1);
+    }
+
+    #[test]
+    fn curve_dead_zone_is_radial() {
+        let curve = AnalogCurve {
+            dead_zone: 0.5,
+            exponent: 1.0,
+            scale: 2.0,
+        };
+        // Each component is inside the dead zone, but the stick as a whole isn't.
+        let out = curve.apply(Vector2::new(0.4, 0.4));
+        assert!(out.x > 0.0 && out.x == out.y, "{:?}", out);
+        // Rescaled so that the edge of the dead zone maps to zero and full deflection
+        // to the scale.
+        assert_eq!(curve.apply(Vector2::new(0.75, 0.0)), Vector2::new(1.0, 0.0));
+        assert_eq!(
+            curve.apply(Vector2::new(0.0, -2.0)),
+            Vector2::new(0.0, -2.0)
+        );
+    }
+
+    #[test]
+    fn analog_combines_axes() {
+        let map = InputMap::default();
+        let (walk, look) = map.analog(vec![
+            (Axis::GamepadAxis(1), -1.0),
+            (Axis::GamepadAxis(2), 0.05),
+            (Axis::GamepadAxis(17), 1.0),
+        ]);
+        assert_eq!(walk, Vector2::new(0.0, -1.0));
+        assert_eq!(look, Vector2::zero());
+    }
+
+    #[test]
+    fn input_display() {
+        assert_eq!(Input::Key(Key::Character('w')).to_string(), "W");
+        assert_eq!(Input::Key(Key::Character(' ')).to_string(), "Space");
+        assert_eq!(Input::MouseButton(1).to_string(), "Right Click");
+    }
+}
@@ -0,0 +1,329 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Items lying loose in a [`Space`], left behind by breaking blocks or tossed by a
+//! [`Character`](crate::character::Character).
+//!
+//! Each [`ItemDrop`] is a small physical object which falls and collides with the
+//! blocks of its space, spins in place so that it catches the eye, combines with
+//! identical drops that come to rest next to it, and eventually despawns if nobody
+//! collects it. A character that comes close enough to a drop picks it up
+//! automatically during [`Character::step`](crate::character::Character::step),
+//! if its inventory has room.
+
+use cgmath::{Deg, EuclideanSpace as _, InnerSpace as _, Matrix4, Point3, Vector3};
+use std::collections::BTreeMap;
+
+use crate::apps::Tick;
+use crate::math::{Aab, FreeCoordinate};
+use crate::physics::Body;
+use crate::space::Space;
+use crate::tools::Tool;
+
+/// Seconds after which an [`ItemDrop`] that has not been picked up is removed.
+pub const DESPAWN_SECONDS: f64 = 300.0;
+
+/// Seconds after being tossed before an item can be picked up, so that it can get
+/// away from the character that tossed it.
+const TOSSED_PICKUP_DELAY_SECONDS: f64 = 1.5;
+
+/// Distance from a character's collision box within which drops are picked up.
+pub(crate) const PICKUP_RADIUS: FreeCoordinate = 1.0;
+
+/// Distance between drops at which identical drops are combined.
+const MERGE_RADIUS: FreeCoordinate = 0.5;
+
+/// Rate at which drops turn about the vertical axis.
+const SPIN_DEGREES_PER_SECOND: FreeCoordinate = 90.0;
+
+/// Half the edge length of a drop's collision box.
+const HALF_SIZE: FreeCoordinate = 0.125;
+
+/// Identifies an [`ItemDrop`] within its [`Space`]. IDs are not reused, so a stale
+/// ID will not find a different drop.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DropId(u64);
+
+/// One or more identical items lying loose in a [`Space`]; see the
+/// [module documentation](self).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ItemDrop {
+    item: Tool,
+    count: u32,
+    body: Body,
+    /// Seconds since this drop was created.
+    age: f64,
+    /// Seconds remaining before this drop may be picked up.
+    pickup_delay: f64,
+    /// Rotation about the vertical axis, in degrees.
+    spin: FreeCoordinate,
+}
+
+impl ItemDrop {
+    /// Constructs a drop of `count` copies of `item` at rest at `position`, which is
+    /// the center of its (small) collision box.
+    pub fn new(item: Tool, count: u32, position: impl Into<Point3<FreeCoordinate>>) -> Self {
+        Self {
+            item,
+            count,
+            body: Body::new_minimal(
+                position,
+                Aab::new(
+                    -HALF_SIZE, HALF_SIZE, -HALF_SIZE, HALF_SIZE, -HALF_SIZE, HALF_SIZE,
+                ),
+            ),
+            age: 0.0,
+            pickup_delay: 0.0,
+            spin: 0.0,
+        }
+    }
+
+    /// Constructs a drop of a single item thrown from `position` with `velocity`, which
+    /// cannot be picked up until it has had time to move away.
+    pub fn tossed(
+        item: Tool,
+        position: impl Into<Point3<FreeCoordinate>>,
+        velocity: impl Into<Vector3<FreeCoordinate>>,
+    ) -> Self {
+        let mut drop = Self::new(item, 1, position);
+        drop.body.velocity = velocity.into();
+        drop.pickup_delay = TOSSED_PICKUP_DELAY_SECONDS;
+        drop
+    }
+
+    /// The item of which this drop consists.
+    pub fn item(&self) -> &Tool {
+        &self.item
+    }
+
+    /// How many copies of [`Self::item`] this drop represents.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// The physical state of this drop.
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// Seconds since this drop was created (or since the most recent drop it was
+    /// merged with was created). It despawns when this reaches [`DESPAWN_SECONDS`].
+    pub fn age_seconds(&self) -> f64 {
+        self.age
+    }
+
+    /// Whether a character touching this drop may pick it up now.
+    pub fn can_be_picked_up(&self) -> bool {
+        self.pickup_delay <= 0.0
+    }
+
+    /// Transformation from a unit cube centered on the origin to the position, size, and
+    /// current spin of this drop, for rendering it.
+    pub fn transform(&self) -> Matrix4<FreeCoordinate> {
+        Matrix4::from_translation(self.body.position.to_vec())
+            * Matrix4::from_angle_y(Deg(self.spin))
+            * Matrix4::from_scale(HALF_SIZE * 2.0)
+    }
+
+    /// Whether a character with the given collision box is close enough to pick up
+    /// this drop (ignoring [`Self::can_be_picked_up`]).
+    pub(crate) fn is_within_reach_of(&self, collision_box: Aab) -> bool {
+        let reach = collision_box.enlarge(PICKUP_RADIUS);
+        let p = self.body.position;
+        let (lower, upper) = (reach.lower_bounds_p(), reach.upper_bounds_p());
+        (0..3).all(|axis| lower[axis] <= p[axis] && p[axis] <= upper[axis])
+    }
+
+    /// Advances time for this drop. Returns false if it should be removed.
+    fn step(&mut self, tick: Tick, space: &Space) -> bool {
+        let dt = tick.delta_t.as_secs_f64();
+        self.body.step(tick, Some(space), |_| {});
+        // Come to rest instead of sliding forever; there is no general friction yet.
+        self.body.velocity.x *= (-4.0 * dt).exp();
+        self.body.velocity.z *= (-4.0 * dt).exp();
+        self.spin = (self.spin + SPIN_DEGREES_PER_SECOND * dt).rem_euclid(360.0);
+        self.age += dt;
+        self.pickup_delay = (self.pickup_delay - dt).max(0.0);
+        self.age < DESPAWN_SECONDS && self.count > 0
+    }
+}
+
+/// The collection of [`ItemDrop`]s in a [`Space`]; obtained from [`Space::item_drops`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ItemDrops {
+    drops: BTreeMap<DropId, ItemDrop>,
+    next_id: u64,
+}
+
+impl ItemDrops {
+    /// Returns the drop with the given ID, if it still exists.
+    pub fn get(&self, id: DropId) -> Option<&ItemDrop> {
+        self.drops.get(&id)
+    }
+
+    /// Iterates over all drops in the space.
+    pub fn iter(&self) -> impl Iterator<Item = (DropId, &ItemDrop)> + '_ {
+        self.drops.iter().map(|(&id, drop)| (id, drop))
+    }
+
+    /// Number of drops (not counting multiple items in one drop).
+    pub fn len(&self) -> usize {
+        self.drops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.drops.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, drop: ItemDrop) -> DropId {
+        let id = DropId(self.next_id);
+        self.next_id += 1;
+        self.drops.insert(id, drop);
+        id
+    }
+
+    /// Removes `count` items from the drop `id`, removing the drop if it is emptied.
+    /// Returns false, and does nothing, if there are not that many items.
+    pub(crate) fn take(&mut self, id: DropId, count: u32) -> bool {
+        match self.drops.get_mut(&id) {
+            Some(drop) if drop.count > count => {
+                drop.count -= count;
+                true
+            }
+            Some(drop) if drop.count == count => {
+                self.drops.remove(&id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Advances time for all drops, applying physics, despawning, and merging.
+    pub(crate) fn step(&mut self, tick: Tick, space: &Space) {
+        if tick.paused() {
+            return;
+        }
+        let mut despawned = Vec::new();
+        for (&id, drop) in self.drops.iter_mut() {
+            if !drop.step(tick, space) {
+                despawned.push(id);
+            }
+        }
+        for id in despawned {
+            self.drops.remove(&id);
+        }
+        self.merge_nearby();
+    }
+
+    /// Combine identical drops which are close to each other into the older one.
+    fn merge_nearby(&mut self) {
+        let ids: Vec<DropId> = self.drops.keys().copied().collect();
+        for (i, &absorber_id) in ids.iter().enumerate() {
+            for &absorbed_id in &ids[i + 1..] {
+                let (absorber, absorbed) =
+                    match (self.drops.get(&absorber_id), self.drops.get(&absorbed_id)) {
+                        (Some(a), Some(b)) => (a, b),
+                        _ => continue,
+                    };
+                if absorber.item != absorbed.item
+                    || (absorber.body.position - absorbed.body.position).magnitude() > MERGE_RADIUS
+                {
+                    continue;
+                }
+                let absorbed = self.drops.remove(&absorbed_id).unwrap();
+                let absorber = self.drops.get_mut(&absorber_id).unwrap();
+                absorber.count = absorber.count.saturating_add(absorbed.count);
+                // The despawn timer restarts for the newer items.
+                absorber.age = absorber.age.min(absorbed.age);
+                absorber.pickup_delay = absorber.pickup_delay.max(absorbed.pickup_delay);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::math::Rgba;
+    use crate::space::Grid;
+
+    fn item(n: u8) -> Tool {
+        Tool::PlaceBlock(Block::from(Rgba::new(f32::from(n) / 10.0, 0.0, 0.0, 1.0)))
+    }
+
+    /// A space with a floor at y = 0 for drops to rest on.
+    fn floor_space() -> Space {
+        let mut space = Space::empty_positive(4, 3, 4);
+        space
+            .fill_uniform(Grid::new([0, 0, 0], [4, 1, 4]), &Block::from(Rgba::WHITE))
+            .unwrap();
+        space
+    }
+
+    #[test]
+    fn falls_and_rests_on_floor() {
+        let space = floor_space();
+        let mut drops = ItemDrops::default();
+        let id = drops.insert(ItemDrop::new(item(1), 1, [1.5, 2.5, 1.5]));
+        for _ in 0..120 {
+            drops.step(Tick::from_seconds(1.0 / 60.0), &space);
+        }
+        let drop = drops.get(id).unwrap();
+        assert!(
+            (drop.body().position.y - (1.0 + HALF_SIZE)).abs() < 1e-3,
+            "{:?}",
+            drop.body().position
+        );
+        assert_ne!(drop.spin, 0.0);
+    }
+
+    #[test]
+    fn despawns_after_timeout() {
+        let space = floor_space();
+        let mut drops = ItemDrops::default();
+        drops.insert(ItemDrop::new(item(1), 1, [1.5, 1.2, 1.5]));
+        drops.step(Tick::from_seconds(DESPAWN_SECONDS - 1.0), &space);
+        assert_eq!(drops.len(), 1);
+        drops.step(Tick::from_seconds(2.0), &space);
+        assert!(drops.is_empty());
+    }
+
+    #[test]
+    fn identical_nearby_drops_merge() {
+        let space = floor_space();
+        let mut drops = ItemDrops::default();
+        let first = drops.insert(ItemDrop::new(item(1), 2, [1.5, 1.2, 1.5]));
+        drops.insert(ItemDrop::new(item(1), 3, [1.7, 1.2, 1.5]));
+        let different = drops.insert(ItemDrop::new(item(2), 1, [1.6, 1.2, 1.5]));
+        let far = drops.insert(ItemDrop::new(item(1), 1, [3.5, 1.2, 3.5]));
+        drops.step(Tick::from_seconds(0.01), &space);
+
+        assert_eq!(drops.len(), 3);
+        assert_eq!(drops.get(first).unwrap().count(), 5);
+        assert_eq!(drops.get(different).unwrap().count(), 1);
+        assert_eq!(drops.get(far).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn take_partial_and_all() {
+        let mut drops = ItemDrops::default();
+        let id = drops.insert(ItemDrop::new(item(1), 3, [0.0, 0.0, 0.0]));
+        assert!(!drops.take(id, 4));
+        assert!(drops.take(id, 2));
+        assert_eq!(drops.get(id).unwrap().count(), 1);
+        assert!(drops.take(id, 1));
+        assert_eq!(drops.get(id), None);
+        assert!(!drops.take(id, 1));
+    }
+
+    #[test]
+    fn tossed_pickup_delay() {
+        let space = floor_space();
+        let mut drops = ItemDrops::default();
+        let id = drops.insert(ItemDrop::tossed(item(1), [1.5, 1.5, 1.5], [0.0, 0.0, 0.0]));
+        assert!(!drops.get(id).unwrap().can_be_picked_up());
+        drops.step(Tick::from_seconds(TOSSED_PICKUP_DELAY_SECONDS), &space);
+        assert!(drops.get(id).unwrap().can_be_picked_up());
+    }
+}
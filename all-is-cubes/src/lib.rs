@@ -6,6 +6,23 @@
 //!
 //! This crate defines the world model, simulation rules, rendering, and in-game user
 //! interface. Glue for displaying on specific platforms is kept in other crates.
+//!
+//! The most commonly needed types are available together from the [`prelude`].
+//!
+//! ## Package features
+//!
+//! * `content` (default): built-in content and world generation, the `drawing` module
+//!   (using `embedded-graphics`), and the in-game user interface (`vui` and
+//!   `apps::AllIsCubesAppState`). Disabling it leaves the world model, physics,
+//!   [`raycast`]ing, and the renderers, for headless and constrained uses.
+//! * `lum` (default): the GPU renderer in the `lum` module, using `luminance`. Implies
+//!   `content`.
+//! * `rayon`: parallelism in the raytracer.
+//! * `image`: PNG import and export, such as for heightmaps and minimaps.
+//! * `tracing`: performance instrumentation; see [`trace`].
+//!
+//! The crate requires `std` even without default features, and some dependencies are
+//! always needed, such as `serde_json` for the [`save`] format.
 
 #![deny(rust_2018_idioms)]
 #![allow(clippy::collapsible_if)]
@@ -29,24 +46,46 @@ pub mod math;
 pub mod apps;
 pub mod behavior;
 pub mod block;
+pub mod block_editor;
 pub mod camera;
 pub mod character;
 mod chunking;
+pub mod command;
 pub mod content;
+#[cfg(feature = "content")]
 pub mod drawing;
+pub mod entity;
+pub mod explosion;
+pub mod i18n;
+pub mod input;
 mod intalloc;
+pub mod item_drop;
 pub mod linking;
 pub mod listen;
 pub mod lum;
+pub mod minimap;
+pub mod mining;
 pub mod physics;
+pub mod prelude;
+pub mod rasterizer;
 pub mod raycast;
 pub mod raytracer;
+#[cfg(any(test, feature = "rendertest"))]
+#[doc(hidden)] // Test support only, not part of the stable API
+pub mod rendertest;
+pub mod save;
 pub mod space;
+pub mod swatches;
+pub mod timeline;
 mod tools;
+#[doc(hidden)] // Instrumentation for benchmarks and tests, not part of the stable API
+pub mod trace;
 pub mod transactions;
 pub mod triangulator;
 pub mod universe;
+#[doc(hidden)] // Formatting helpers for this crate's types, not a stable API of its own
 pub mod util;
+#[cfg(feature = "content")]
 pub mod vui;
 
 /// Re-export the version of the `cgmath` crate we're using.
@@ -7,6 +7,10 @@
 //! [`BlockProvider`] assists in ensuring that all of those names are defined
 //! and storing or retrieving their block values in a specific [`Universe`].
 //!
+//! A [`BlockLayer`] replaces some of those definitions, such as to let a mod change the
+//! appearance of grass while keeping the rest of the standard blocks; layers are applied
+//! by [`BlockProvider::install_layered`].
+//!
 //! In the future this mechanism may grow to become a dynamic linker/dependency injector
 //! by becoming aware of dependencies between “modules”. For now, it's just enough to
 //! solve bootstrapping needs.
@@ -23,7 +27,7 @@ use crate::space::SetCubeError;
 use crate::universe::{InsertError, Name, URef, Universe, UniverseIndex};
 
 fn name_in_module<E: BlockModule>(key: &E) -> Name {
-    Name::from(format!("{}/{}", E::namespace(), key).as_str())
+    Name::in_folder(E::namespace(), &key.to_string())
 }
 
 // TODO: document
@@ -79,6 +83,51 @@ where
         Ok(Self { map })
     }
 
+    /// Returns a copy of this provider with the definitions replaced by those in
+    /// `layers`, where they have any for this module.
+    ///
+    /// Returns an error if more than one layer defines the same block, since there
+    /// is no way to tell which of them was intended to take precedence.
+    ///
+    /// ```
+    /// # #[cfg(feature = "content")] {
+    /// use all_is_cubes::content::LandscapeBlocks;
+    /// use all_is_cubes::linking::{BlockLayer, BlockProvider};
+    /// use all_is_cubes::math::Rgba;
+    ///
+    /// let base = BlockProvider::<LandscapeBlocks>::default();
+    /// let layer = BlockLayer::new("blue grass")
+    ///     .with(LandscapeBlocks::Grass, Rgba::new(0.2, 0.3, 0.9, 1.0));
+    /// let layered = base.clone().layered(&[layer]).unwrap();
+    /// assert_eq!(layered[LandscapeBlocks::Grass], Rgba::new(0.2, 0.3, 0.9, 1.0).into());
+    /// assert_eq!(layered[LandscapeBlocks::Dirt], base[LandscapeBlocks::Dirt]);
+    /// # }
+    /// ```
+    pub fn layered(mut self, layers: &[BlockLayer]) -> Result<Self, LayerConflictError> {
+        for key in E::iter() {
+            let name = name_in_module(&key);
+            let mut defining = layers
+                .iter()
+                .filter_map(|layer| Some((layer, layer.blocks.get(&name)?)));
+            if let Some((first_layer, block)) = defining.next() {
+                let others: Vec<&BlockLayer> = defining.map(|(layer, _)| layer).collect();
+                if !others.is_empty() {
+                    return Err(LayerConflictError {
+                        name,
+                        layers: std::iter::once(first_layer)
+                            .chain(others)
+                            .map(|layer| layer.label.clone())
+                            .collect(),
+                    });
+                }
+                self.map.insert(key, block.clone());
+            }
+        }
+        Ok(self)
+    }
+
+    /// Stores the block definitions in `universe`, under names derived from
+    /// [`BlockModule::namespace`], and returns a provider of references to them.
     pub fn install(&self, universe: &mut Universe) -> Result<BlockProvider<E>, InsertError> {
         for key in E::iter() {
             // TODO: the &* mess should not be required
@@ -87,6 +136,20 @@ where
         Ok(Self::using(universe).expect("failed to retrieve names we just inserted??"))
     }
 
+    /// As [`BlockProvider::install`], but first replaces definitions with those from
+    /// `layers` as by [`BlockProvider::layered`].
+    pub fn install_layered(
+        &self,
+        layers: &[BlockLayer],
+        universe: &mut Universe,
+    ) -> Result<BlockProvider<E>, GenError> {
+        let layered = self
+            .clone()
+            .layered(layers)
+            .map_err(|e| GenError::failure(e.clone(), e.name.clone()))?;
+        Ok(layered.install(universe)?)
+    }
+
     pub fn using(universe: &Universe) -> Result<BlockProvider<E>, ProviderError>
     where
         E: Eq + Hash + Display,
@@ -125,6 +188,64 @@ impl<E: Eq + Hash> Index<E> for BlockProvider<E> {
     }
 }
 
+/// A set of block definitions which replace some of those from one or more
+/// [`BlockModule`]s, leaving the rest unchanged, when passed to
+/// [`BlockProvider::layered`] or [`BlockProvider::install_layered`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockLayer {
+    label: String,
+    blocks: HashMap<Name, Block>,
+}
+
+impl BlockLayer {
+    /// Constructs an empty layer. The `label` identifies it in error messages.
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            blocks: HashMap::new(),
+        }
+    }
+
+    /// Replaces the definition of `key` with `block`, or changes the replacement
+    /// if there already is one in this layer.
+    #[must_use]
+    pub fn with<E: BlockModule>(mut self, key: E, block: impl Into<Block>) -> Self {
+        self.blocks.insert(name_in_module(&key), block.into());
+        self
+    }
+
+    /// Returns the label given to this layer.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the replacement for `key` in this layer, if there is one.
+    pub fn get<E: BlockModule>(&self, key: &E) -> Option<&Block> {
+        self.blocks.get(&name_in_module(key))
+    }
+}
+
+/// Error from [`BlockProvider::layered`] when more than one [`BlockLayer`] defines the
+/// same block.
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+#[error("{name} is defined by more than one layer: {layers:?}")]
+pub struct LayerConflictError {
+    name: Name,
+    layers: Vec<String>,
+}
+
+impl LayerConflictError {
+    /// Returns the name of the block which was defined more than once.
+    pub fn name(&self) -> &Name {
+        &self.name
+    }
+
+    /// Returns the labels of the layers which defined it.
+    pub fn layers(&self) -> &[String] {
+        &self.layers
+    }
+}
+
 #[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
 #[error("missing block definitions: {missing:?}")] // TODO: use Name's Display within the list
 pub struct ProviderError {
@@ -166,7 +287,9 @@ impl From<InsertError> for GenError {
     fn from(error: InsertError) -> Self {
         GenError {
             for_object: match &error {
-                InsertError::AlreadyExists(name) => Some(name.clone()),
+                InsertError::AlreadyExists(name) | InsertError::InvalidName(name) => {
+                    Some(name.clone())
+                }
             },
             detail: error.into(),
         }
@@ -205,6 +328,10 @@ pub enum InGenError {
     #[error(transparent)]
     Provider(#[from] ProviderError),
 
+    /// More than one [`BlockLayer`] tried to replace the same block.
+    #[error(transparent)]
+    LayerConflict(#[from] LayerConflictError),
+
     /// Failed during [`Space`](crate::space::Space) manipulation.
     // TODO: Break apart `SetCubeError::EvalBlock` to its contents?
     #[error(transparent)]
@@ -228,6 +355,8 @@ impl From<GenError> for InGenError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::content::make_some_blocks;
+    use crate::math::Rgba;
     use crate::space::Grid;
 
     #[test]
@@ -239,6 +368,67 @@ mod tests {
         );
     }
 
+    #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, strum::Display, strum::EnumIter)]
+    #[strum(serialize_all = "kebab-case")]
+    enum Key {
+        Foo,
+        Bar,
+    }
+    impl BlockModule for Key {
+        fn namespace() -> &'static str {
+            "test-layers"
+        }
+    }
+
+    fn base_provider() -> BlockProvider<Key> {
+        BlockProvider::new(|_| Ok(Rgba::BLACK)).unwrap()
+    }
+
+    #[test]
+    fn install_layered_replaces_and_inherits() {
+        let [a, b] = make_some_blocks();
+        let mut universe = Universe::new();
+        let layers = [
+            BlockLayer::new("first").with(Key::Foo, a.clone()),
+            // Layers for other modules are ignored.
+            BlockLayer::new("second").with(crate::mining::CrackStage::Stage1, b),
+        ];
+        let installed = base_provider()
+            .install_layered(&layers, &mut universe)
+            .unwrap();
+        assert_eq!(
+            installed[Key::Foo].evaluate().unwrap(),
+            a.evaluate().unwrap()
+        );
+        assert_eq!(installed[Key::Bar].evaluate().unwrap().color, Rgba::BLACK);
+    }
+
+    #[test]
+    fn layer_conflict() {
+        let [a, b] = make_some_blocks();
+        let mut universe = Universe::new();
+        let layers = [
+            BlockLayer::new("first").with(Key::Bar, a.clone()),
+            BlockLayer::new("second").with(Key::Foo, a),
+            BlockLayer::new("third").with(Key::Bar, b),
+        ];
+        let expected = LayerConflictError {
+            name: "test-layers/bar".into(),
+            layers: vec!["first".to_owned(), "third".to_owned()],
+        };
+        assert_eq!(base_provider().layered(&layers).unwrap_err(), expected);
+        let error = base_provider()
+            .install_layered(&layers, &mut universe)
+            .unwrap_err();
+        assert!(
+            matches!(&error.detail, InGenError::LayerConflict(e) if *e == expected),
+            "{:?}",
+            error
+        );
+        // Nothing was installed.
+        assert!(UniverseIndex::<BlockDef>::get(&universe, &"test-layers/foo".into()).is_none());
+    }
+
     #[test]
     #[allow(clippy::try_err)]
     fn gen_error_composition() {
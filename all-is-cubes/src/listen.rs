@@ -135,6 +135,16 @@ pub trait Listener<M> {
     fn alive(&self) -> bool;
 }
 
+/// Allows one listener to be registered with several sources.
+impl<M, L: Listener<M> + ?Sized> Listener<M> for Arc<L> {
+    fn receive(&self, message: M) {
+        (**self).receive(message)
+    }
+    fn alive(&self) -> bool {
+        (**self).alive()
+    }
+}
+
 /// Methods for adapting listeners that would make `Listener` not [object-safe]
 /// (https://doc.rust-lang.org/book/ch17-02-trait-objects.html).
 pub trait ListenerHelper<M>
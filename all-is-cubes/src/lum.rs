@@ -10,7 +10,7 @@
 
 use std::error::Error;
 
-use cgmath::{Transform as _, Vector3, Zero as _};
+use cgmath::{InnerSpace as _, Matrix4, Point3, Transform as _, Vector3, Zero as _};
 use luminance_front::context::GraphicsContext;
 use luminance_front::framebuffer::FramebufferError;
 use luminance_front::pipeline::PipelineError;
@@ -18,11 +18,14 @@ use luminance_front::tess::{Mode, Tess, TessError};
 use luminance_front::texture::TextureError;
 use luminance_front::Backend;
 
+use crate::block::Block;
 use crate::character::Cursor;
 use crate::content::palette;
+use crate::entity::EntityShape;
 use crate::lum::types::{empty_tess, LumBlockVertex};
-use crate::math::{Aab, Geometry, Rgba};
+use crate::math::{Aab, FreeCoordinate, Geometry, Rgba};
 use crate::raycast::Face;
+use crate::space::Space;
 use crate::util::MapExtend;
 
 // TODO: Right now, only the top level renderer struct is public, because it is
@@ -30,11 +33,15 @@ use crate::util::MapExtend;
 // (that we can stably support). Revisit.
 
 mod block_texture;
+mod chunk_worker;
 mod frame_texture;
 mod glrender;
 pub use glrender::*;
+mod raytrace;
 mod shading;
+mod skybox;
 mod space;
+pub use space::SpaceRenderInfo;
 mod types;
 
 /// Creates a [`Tess`] to draw a [`Cursor`] as a wireframe cube.
@@ -92,6 +99,76 @@ where
     }
 }
 
+/// Creates a [`Tess`] to draw the [`Entity`](crate::entity::Entity)s in `space` which
+/// have [`EntityShape::Cube`], each as a cube of the color of its appearance block, or
+/// which have a [`Model`](crate::entity::Model), each part of which is drawn likewise.
+/// Caller must set up the camera for the space.
+pub(crate) fn make_entities_tess<C>(
+    context: &mut C,
+    space: &Space,
+) -> Result<Tess<LumBlockVertex>, GraphicsResourceError>
+where
+    C: GraphicsContext<Backend = Backend>,
+{
+    // TODO: Draw voxels instead of only the overall color.
+    let color_of = |block: &Block| match block.evaluate() {
+        Ok(evaluated) => evaluated.color,
+        Err(error) => error.to_placeholder().color,
+    };
+
+    let mut vertices = Vec::new();
+    for (_, entity) in space.entities().iter() {
+        if let Some(model) = entity.model() {
+            for (part, transform) in model.parts().iter().zip(entity.model_part_transforms()) {
+                cube_vertices(&mut vertices, transform, color_of(part.appearance()));
+            }
+        } else if entity.shape() != EntityShape::Cube {
+            // Billboards are drawn by the SpaceRenderer, which has a texture allocator.
+            continue;
+        } else if let Some(block) = entity.appearance() {
+            cube_vertices(&mut vertices, entity.transform(), color_of(block));
+        }
+    }
+
+    if vertices.is_empty() {
+        empty_tess(context)
+    } else {
+        Ok(context
+            .new_tess()
+            .set_vertices(vertices)
+            .set_mode(Mode::Triangle)
+            .build()?)
+    }
+}
+
+/// Add triangles for a solid-colored cube to `vertices`, where `transform` maps the
+/// unit cube centered on the origin to the cube to be drawn.
+fn cube_vertices(
+    vertices: &mut Vec<LumBlockVertex>,
+    transform: Matrix4<FreeCoordinate>,
+    color: Rgba,
+) {
+    let from_unit_cube = transform * Matrix4::from_translation(Vector3::new(-0.5, -0.5, -0.5));
+    for &face in Face::ALL_SIX {
+        let face_transform = from_unit_cube * face.matrix(1).to_free();
+        let normal = transform.transform_vector(face.normal_vector()).normalize();
+        for &(x, y) in &[
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (1.0, 1.0),
+            (0.0, 1.0),
+            (0.0, 0.0),
+        ] {
+            vertices.push(LumBlockVertex::new_colored(
+                face_transform.transform_point(Point3::new(x, y, 0.0)),
+                normal,
+                color,
+            ));
+        }
+    }
+}
+
 /// Add the wireframe of `geometry` to `vertices` (to be drawn in [`Line`](Mode::Line)
 /// mode) with the given `color`.
 pub(crate) fn wireframe_vertices<E, G>(vertices: &mut E, color: Rgba, geometry: G)
@@ -33,11 +33,18 @@ pub type BoundBlockTexture<'a> = BoundTexture<'a, Dim3, NormRGBA8UI>;
 ///
 /// After any allocations, you must call [`LumAtlasAllocator::flush`] to write the
 /// updates to the actual GPU texture for drawing.
+///
+/// The atlas grows as needed by adding layers of tiles. Existing tiles keep their
+/// positions, and texture coordinates are in units of texels, so growth does not
+/// invalidate any texture coordinates. When most of the tiles have been released, the
+/// rest are moved together so that the atlas can shrink; this *does* change their
+/// texture coordinates, as reported by [`AtlasFlushInfo::tiles_moved`].
 pub struct LumAtlasAllocator {
     pub texture: BlockTexture,
-    layout: AtlasLayout,
-    backing: Rc<RefCell<AllocatorBacking>>,
-    in_use: Vec<Weak<RefCell<TileBacking>>>,
+    /// Layout `texture` was created with, which may lag behind `tiles.layout` until
+    /// the next [`LumAtlasAllocator::flush`].
+    texture_layout: AtlasLayout,
+    tiles: AtlasTiles,
 }
 /// Texture tile handle used by [`LumAtlasAllocator`].
 ///
@@ -52,11 +59,11 @@ pub struct LumAtlasTile {
 struct TileBacking {
     /// Index in the linear ordering of the texture atlas.
     index: u32,
-    /// Origin of the tile in the texture.
+    /// Origin of the tile in the texture, in texels.
     ///
     /// Technically redundant with `index`, but precomputed.
     origin: Vector3<TextureCoordinate>,
-    /// Scale factor for tile coordinates (0..1) to texture coordinates (some fraction of that).
+    /// Scale factor for tile coordinates (0..1) to texture coordinates (texels).
     scale: f32,
     data: Option<Box<[Texel]>>,
     /// Whether the data has changed so that we need to send it to the GPU on next
@@ -74,16 +81,36 @@ struct AllocatorBacking {
     index_allocator: IntAllocator<u32>,
 }
 
+/// The part of [`LumAtlasAllocator`] which does not involve the GPU: deciding where
+/// tiles go in the atlas.
+#[derive(Debug)]
+struct AtlasTiles {
+    /// Layout which the tiles' coordinates are computed for.
+    layout: AtlasLayout,
+    backing: Rc<RefCell<AllocatorBacking>>,
+    in_use: Vec<Weak<RefCell<TileBacking>>>,
+    /// Whether [`AtlasTiles::compact_if_sparse`] has moved tiles since this was last
+    /// cleared.
+    tiles_moved: bool,
+}
+
 impl LumAtlasAllocator {
     pub fn new<C>(context: &mut C) -> Result<Self, TextureError>
     where
         C: GraphicsContext<Backend = Backend>,
     {
-        let layout = AtlasLayout {
-            resolution: 16,
-            row_length: 16,
-        };
+        let layout = AtlasLayout::INITIAL;
+        Ok(Self {
+            texture: Self::new_texture(context, layout)?,
+            texture_layout: layout,
+            tiles: AtlasTiles::new(layout),
+        })
+    }
 
+    fn new_texture<C>(context: &mut C, layout: AtlasLayout) -> Result<BlockTexture, TextureError>
+    where
+        C: GraphicsContext<Backend = Backend>,
+    {
         let mut texture = context.new_texture_no_texels(
             layout.dimensions(),
             0, // mipmaps
@@ -96,7 +123,7 @@ impl LumAtlasAllocator {
                 ..Sampler::default()
             },
         )?;
-        // TODO: distinguish between "logic error" errors and "out of texture memory" errors...though it doesn't matter much until we have atlas resizing reallocations.
+        // TODO: distinguish between "logic error" errors and "out of texture memory" errors.
 
         // Mark unused area for easier debugging (error color instead of transparency)
         texture.clear(
@@ -104,32 +131,38 @@ impl LumAtlasAllocator {
             palette::UNPAINTED_TEXTURE_FALLBACK.to_linear_32bit(),
         )?;
 
-        Ok(Self {
-            texture,
-            layout,
-            backing: Rc::new(RefCell::new(AllocatorBacking {
-                dirty: false,
-                index_allocator: IntAllocator::new(),
-            })),
-            in_use: Vec::new(),
-        })
+        Ok(texture)
     }
 
-    /// Copy the texels of all modified and still-referenced tiles to the GPU's texture.
+    /// Copy the texels of all modified and still-referenced tiles to the GPU's texture,
+    /// first replacing the texture if the atlas has grown or shrunk.
     ///
     /// If any errors prevent complete flushing, it will be attempted again on the next
     /// call.
-    pub fn flush(&mut self) -> Result<AtlasFlushInfo, TextureError> {
-        let dirty = &mut self.backing.borrow_mut().dirty;
+    pub fn flush<C>(&mut self, context: &mut C) -> Result<AtlasFlushInfo, TextureError>
+    where
+        C: GraphicsContext<Backend = Backend>,
+    {
+        self.tiles.compact_if_sparse();
+
+        if self.tiles.layout != self.texture_layout {
+            self.texture = Self::new_texture(context, self.tiles.layout)?;
+            self.texture_layout = self.tiles.layout;
+            // Everything must be copied into the new texture.
+            self.tiles.mark_all_dirty();
+        }
+
+        let dirty = &mut self.tiles.backing.borrow_mut().dirty;
         if !*dirty {
             return Ok(AtlasFlushInfo {
                 flushed: 0,
-                in_use: self.in_use.len(),
-                capacity: self.layout.tile_count() as usize,
+                in_use: self.tiles.in_use.len(),
+                capacity: self.tiles.layout.tile_count() as usize,
+                tiles_moved: std::mem::replace(&mut self.tiles.tiles_moved, false),
             });
         }
 
-        let layout = self.layout;
+        let layout = self.texture_layout;
         let rg = u32::from(layout.resolution);
         let mut count_written = 0;
 
@@ -138,7 +171,7 @@ impl LumAtlasAllocator {
         let mut error: Option<TextureError> = None;
 
         let texture = &mut self.texture;
-        self.in_use.retain(|weak_backing| {
+        self.tiles.in_use.retain(|weak_backing| {
             // Process the non-dropped weak references
             weak_backing.upgrade().map_or(false, |strong_backing| {
                 let backing: &mut TileBacking = &mut strong_backing.borrow_mut();
@@ -173,16 +206,24 @@ impl LumAtlasAllocator {
         *dirty = false;
         Ok(AtlasFlushInfo {
             flushed: count_written,
-            in_use: self.in_use.len(),
-            capacity: self.layout.tile_count() as usize,
+            in_use: self.tiles.in_use.len(),
+            capacity: self.tiles.layout.tile_count() as usize,
+            tiles_moved: std::mem::replace(&mut self.tiles.tiles_moved, false),
         })
     }
 
+    /// Returns the number of bytes of texture memory occupied by tiles which are
+    /// currently allocated.
+    pub(crate) fn bytes_in_use(&self) -> usize {
+        self.tiles.live_count() * self.tiles.layout.tile_bytes()
+    }
+
     #[allow(dead_code)]
     pub(crate) fn debug_atlas_tess<C>(&self, context: &mut C) -> Tess<LumBlockVertex>
     where
         C: GraphicsContext<Backend = Backend>,
     {
+        let [width, height, _] = self.texture_layout.dimensions();
         let mut vertices = Vec::new();
         //for layer in 0..self.layer_count {
         let layer = 0;
@@ -192,7 +233,11 @@ impl LumAtlasAllocator {
             Vector3::new(1.0, 1.0, 0.0),
             // texture
             Vector3::new(0.0, 0.0, layer as TextureCoordinate),
-            Vector3::new(1.0, 1.0, layer as TextureCoordinate),
+            Vector3::new(
+                width as TextureCoordinate,
+                height as TextureCoordinate,
+                layer as TextureCoordinate,
+            ),
         ));
         //}
         context
@@ -207,6 +252,84 @@ impl LumAtlasAllocator {
 impl TextureAllocator for LumAtlasAllocator {
     type Tile = LumAtlasTile;
 
+    fn resolution(&self) -> GridCoordinate {
+        self.tiles.resolution()
+    }
+
+    fn allocate(&mut self) -> Option<LumAtlasTile> {
+        self.tiles.allocate()
+    }
+}
+
+impl AtlasTiles {
+    fn new(layout: AtlasLayout) -> Self {
+        Self {
+            layout,
+            backing: Rc::new(RefCell::new(AllocatorBacking {
+                dirty: false,
+                index_allocator: IntAllocator::new(),
+            })),
+            in_use: Vec::new(),
+            tiles_moved: false,
+        }
+    }
+
+    /// Number of tiles which have not been dropped.
+    fn live_count(&self) -> usize {
+        self.in_use
+            .iter()
+            .filter(|weak_backing| weak_backing.strong_count() > 0)
+            .count()
+    }
+
+    fn mark_all_dirty(&mut self) {
+        for strong_backing in self.in_use.iter().filter_map(Weak::upgrade) {
+            strong_backing.borrow_mut().dirty = true;
+        }
+        self.backing.borrow_mut().dirty = true;
+    }
+
+    /// If at most a quarter of the atlas is in use, moves all the tiles to the
+    /// beginning and shrinks the layout to fit them with room to spare.
+    fn compact_if_sparse(&mut self) {
+        let live_count = self.live_count();
+        if self.layout.layer_count <= AtlasLayout::INITIAL.layer_count
+            || live_count.saturating_mul(4) > self.layout.tile_count() as usize
+        {
+            return;
+        }
+
+        let tiles_per_layer = self.layout.tiles_per_layer() as usize;
+        let wanted_layers = (live_count * 2 + tiles_per_layer - 1) / tiles_per_layer;
+        let new_layout = AtlasLayout {
+            layer_count: (wanted_layers as AtlasCoord).max(AtlasLayout::INITIAL.layer_count),
+            ..self.layout
+        };
+
+        let mut tiles: Vec<Rc<RefCell<TileBacking>>> =
+            self.in_use.iter().filter_map(Weak::upgrade).collect();
+        // Preserve the existing order, so that the oldest tiles move the least.
+        tiles.sort_by_key(|strong_backing| strong_backing.borrow().index);
+        {
+            let mut allocator_backing = self.backing.borrow_mut();
+            allocator_backing.index_allocator = IntAllocator::new();
+            for strong_backing in &tiles {
+                let mut backing = strong_backing.borrow_mut();
+                backing.index = allocator_backing.index_allocator.allocate().unwrap();
+                backing.origin = new_layout.index_to_origin(backing.index);
+                backing.dirty = true;
+            }
+            allocator_backing.dirty = true;
+        }
+        self.in_use = tiles.iter().map(Rc::downgrade).collect();
+        self.layout = new_layout;
+        self.tiles_moved = true;
+    }
+}
+
+impl TextureAllocator for AtlasTiles {
+    type Tile = LumAtlasTile;
+
     fn resolution(&self) -> GridCoordinate {
         self.layout.resolution.into()
     }
@@ -215,9 +338,14 @@ impl TextureAllocator for LumAtlasAllocator {
         let index_allocator = &mut self.backing.borrow_mut().index_allocator;
         let index = index_allocator.allocate().unwrap();
         if index >= self.layout.tile_count() {
-            // TODO: Attempt expansion of the atlas.
-            index_allocator.free(index);
-            return None;
+            // Grow the atlas, unless it is already as big as allowed.
+            match self.layout.grown() {
+                Some(layout) if index < layout.tile_count() => self.layout = layout,
+                _ => {
+                    index_allocator.free(index);
+                    return None;
+                }
+            }
         }
         let result = LumAtlasTile {
             backing: Rc::new(RefCell::new(TileBacking {
@@ -271,6 +399,9 @@ pub struct AtlasFlushInfo {
     flushed: usize,
     in_use: usize,
     capacity: usize,
+    /// Whether tiles were moved since the previous flush, so that texture coordinates
+    /// obtained from them before then are no longer valid.
+    pub(crate) tiles_moved: bool,
 }
 
 impl CustomFormat<StatusText> for AtlasFlushInfo {
@@ -287,12 +418,17 @@ impl CustomFormat<StatusText> for AtlasFlushInfo {
 }
 
 /// Does the coordinate math for a texture atlas of uniform 3D tiles.
+///
+/// Tiles are arranged in square layers stacked along the Z axis, so that adding layers
+/// does not move any existing tiles.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 struct AtlasLayout {
     /// Edge length of a tile.
     resolution: AtlasCoord,
-    /// Number of tiles in texture atlas along one edge (cube root of total tiles).
+    /// Number of tiles in texture atlas along the X and Y axes.
     row_length: AtlasCoord,
+    /// Number of tiles in texture atlas along the Z axis.
+    layer_count: AtlasCoord,
 }
 
 /// Type of texel indices (coordinates) and single-row (-column/-layer) tile positions.
@@ -307,15 +443,50 @@ type AtlasIndex = u32; // TODO: Review whether this will be more convenient as u
 impl AtlasLayout {
     // TODO: Add a constructor which sanity checks the size parameters.
 
+    /// Layout of a newly created atlas.
+    const INITIAL: Self = Self {
+        resolution: 16,
+        row_length: 16,
+        layer_count: 1,
+    };
+
+    /// Largest texture depth, in texels, the atlas will grow to.
+    ///
+    /// TODO: Ask the graphics context what its actual limit is.
+    const MAX_TEXEL_DEPTH: u32 = 2048;
+
     /// Texture size in the format used by [`luminance`].
     fn dimensions(&self) -> <Dim3 as Dimensionable>::Size {
         let texel_edge_length = self.texel_edge_length();
-        [texel_edge_length, texel_edge_length, texel_edge_length]
+        [
+            texel_edge_length,
+            texel_edge_length,
+            u32::from(self.layer_count) * u32::from(self.resolution),
+        ]
+    }
+
+    /// Returns this layout with twice as many layers, or [`None`] if that would exceed
+    /// [`Self::MAX_TEXEL_DEPTH`].
+    fn grown(&self) -> Option<Self> {
+        let layer_count = self.layer_count.checked_mul(2)?;
+        if u32::from(layer_count) * u32::from(self.resolution) > Self::MAX_TEXEL_DEPTH {
+            return None;
+        }
+        Some(Self {
+            layer_count,
+            ..*self
+        })
+    }
+
+    #[inline]
+    fn tiles_per_layer(&self) -> AtlasIndex {
+        AtlasIndex::from(self.row_length).saturating_pow(2)
     }
 
     #[inline]
     fn tile_count(&self) -> AtlasIndex {
-        AtlasIndex::from(self.row_length).saturating_pow(3)
+        self.tiles_per_layer()
+            .saturating_mul(AtlasIndex::from(self.layer_count))
     }
 
     #[inline]
@@ -334,7 +505,7 @@ impl AtlasLayout {
         let row = row_and_layer % row_length;
         let layer = row_and_layer / row_length;
         assert!(
-            layer <= AtlasIndex::from(self.row_length),
+            layer <= AtlasIndex::from(self.layer_count),
             "Atlas tile index {} out of range",
             index
         );
@@ -343,28 +514,37 @@ impl AtlasLayout {
         Vector3::new(column as AtlasCoord, row as AtlasCoord, layer as AtlasCoord)
     }
 
-    /// Compute location in the atlas of a tile, as \[0, 1\] texture coordinates.
+    /// Compute location in the atlas of a tile, as texture coordinates (in texels).
     ///
     /// Panics if `index >= self.tile_count()`.
     /// TODO: Return Option instead, which the caller can handle as choosing a missing-texture
     /// tile, so data mismatches are only graphical glitches.
     #[inline]
     fn index_to_origin(&self, index: AtlasIndex) -> Vector3<TextureCoordinate> {
-        let step = TextureCoordinate::from(self.resolution)
-            / (self.texel_edge_length() as TextureCoordinate);
+        let step = TextureCoordinate::from(self.resolution);
         self.index_to_location(index)
             .map(|s| TextureCoordinate::from(s) * step)
     }
 
+    /// Size of the texel data of a single tile.
+    #[inline]
+    fn tile_bytes(&self) -> usize {
+        usize::from(self.resolution).pow(3) * std::mem::size_of::<Texel>()
+    }
+
     #[inline]
     fn texcoord_scale(&self) -> TextureCoordinate {
-        TextureCoordinate::from(self.resolution) / (self.texel_edge_length() as TextureCoordinate)
+        TextureCoordinate::from(self.resolution)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::camera::TransparencyOption;
+    use crate::content::make_some_voxel_blocks;
+    use crate::triangulator::{triangulate_block, BlockTriangulation};
+    use crate::universe::Universe;
     use std::convert::TryFrom;
 
     /// This shouldn't happen, but if it does, this is how we handle it.
@@ -373,6 +553,7 @@ mod tests {
         let layout = AtlasLayout {
             resolution: 0xFFFF,
             row_length: 0xFFFF,
+            layer_count: 0xFFFF,
         };
         assert_eq!(0xFFFFFFFF, layout.tile_count());
 
@@ -390,4 +571,78 @@ mod tests {
             layout.index_to_location(large_index)
         );
     }
+
+    fn tile_indices(tiles: &AtlasTiles) -> Vec<AtlasIndex> {
+        tiles
+            .in_use
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|strong_backing| strong_backing.borrow().index)
+            .collect()
+    }
+
+    #[test]
+    fn grow_and_compact_with_many_blocks() {
+        const COUNT: usize = 300;
+        let mut universe = Universe::new();
+        let blocks: [_; COUNT] = make_some_voxel_blocks(&mut universe);
+        let mut tiles = AtlasTiles::new(AtlasLayout::INITIAL);
+        assert!(AtlasLayout::INITIAL.tile_count() < COUNT as AtlasIndex);
+
+        let mut triangulations: Vec<BlockTriangulation<LumBlockVertex, LumAtlasTile>> = blocks
+            .iter()
+            .map(|block| {
+                triangulate_block(
+                    &block.evaluate().unwrap(),
+                    &mut tiles,
+                    &TransparencyOption::Volumetric,
+                )
+            })
+            .collect();
+
+        // Every block got its own tile, and the atlas grew without moving any.
+        assert!(triangulations.iter().all(|t| t.textures().len() == 1));
+        assert!(tiles.layout.tile_count() >= COUNT as AtlasIndex);
+        assert_eq!(
+            tile_indices(&tiles),
+            (0..COUNT as AtlasIndex).collect::<Vec<_>>()
+        );
+        tiles.compact_if_sparse();
+        assert!(!tiles.tiles_moved);
+
+        // Release all but a scattering of tiles; they should then be compacted.
+        let kept: Vec<_> = triangulations.drain(..).step_by(25).collect();
+        let kept_tile = kept[5].textures()[0].clone();
+        let old_texcoord = kept_tile.texcoord(Vector3::new(0.5, 0.5, 0.5));
+        tiles.compact_if_sparse();
+        assert!(tiles.tiles_moved);
+        assert_eq!(tiles.layout, AtlasLayout::INITIAL);
+        assert_eq!(
+            tile_indices(&tiles),
+            (0..kept.len() as AtlasIndex).collect::<Vec<_>>()
+        );
+        assert_eq!(kept_tile.backing.borrow().index, 5);
+        assert_ne!(
+            kept_tile.texcoord(Vector3::new(0.5, 0.5, 0.5)),
+            old_texcoord
+        );
+
+        // New allocations don't collide with the moved tiles.
+        let new_tile = tiles.allocate().unwrap();
+        assert_eq!(new_tile.backing.borrow().index, kept.len() as AtlasIndex);
+    }
+
+    #[test]
+    fn growth_is_limited() {
+        let mut tiles = AtlasTiles::new(AtlasLayout {
+            resolution: 1024,
+            row_length: 1,
+            layer_count: 1,
+        });
+        let _t1 = tiles.allocate().unwrap();
+        let _t2 = tiles.allocate().unwrap();
+        assert_eq!(tiles.layout.layer_count, 2);
+        assert!(tiles.allocate().is_none());
+        assert_eq!(tiles.live_count(), 2);
+    }
 }
@@ -0,0 +1,333 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Triangulating chunks of a [`Space`] on a background thread, so that a large backlog
+//! of chunks does not slow down drawing.
+
+use bitvec::prelude::BitVec;
+use std::collections::HashSet;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use crate::camera::GraphicsOptions;
+use crate::chunking::ChunkPos;
+use crate::lum::space::CHUNK_SIZE;
+use crate::lum::types::LumBlockVertex;
+use crate::math::{FaceMap, GridPoint};
+use crate::space::{BlockIndex, Grid, GridArray, PackedLight, Space};
+use crate::trace::trace_span;
+use crate::triangulator::{
+    BlockTriangulation, BlockTriangulationProvider, GfxVertex as _, SpaceTriangulation,
+    SpaceTriangulationSource,
+};
+
+/// Copies of a [`SpaceRenderer`](super::space::SpaceRenderer)'s block triangulations,
+/// without their texture tiles so that they can be sent to the worker thread.
+#[derive(Debug)]
+pub(super) struct SharedBlocks {
+    /// Value of `SpaceRenderer::block_version_counter` when this was copied.
+    pub(super) version_counter: u32,
+    pub(super) triangulations: Vec<BlockTriangulation<LumBlockVertex, ()>>,
+    /// Version of each block triangulation, as in `SpaceRenderer::block_versioning`.
+    pub(super) versions: Vec<u32>,
+}
+
+impl SharedBlocks {
+    /// Returns the copy in `cache` if it is of the current version, and otherwise
+    /// replaces it with a new copy of `triangulations`.
+    pub(super) fn current<T>(
+        cache: &mut Option<Arc<SharedBlocks>>,
+        version_counter: u32,
+        triangulations: &[BlockTriangulation<LumBlockVertex, T>],
+        versions: &[u32],
+    ) -> Arc<SharedBlocks> {
+        match cache {
+            Some(shared) if shared.version_counter == version_counter => shared.clone(),
+            _ => {
+                let shared = Arc::new(SharedBlocks {
+                    version_counter,
+                    triangulations: triangulations
+                        .iter()
+                        .map(BlockTriangulation::without_textures)
+                        .collect(),
+                    versions: versions.to_vec(),
+                });
+                *cache = Some(shared.clone());
+                shared
+            }
+        }
+    }
+}
+
+/// Copy of the parts of a [`Space`] that triangulating one chunk reads: the block
+/// indices, and light if it is wanted, of the chunk and the cubes adjacent to it.
+#[derive(Clone, Debug)]
+pub(super) struct ChunkInput {
+    block_indices: GridArray<Option<BlockIndex>>,
+    light: Option<GridArray<PackedLight>>,
+}
+
+impl ChunkInput {
+    pub(super) fn copy_from(space: &Space, bounds: Grid) -> Self {
+        let region = bounds.expand(FaceMap::repeat(1));
+        Self {
+            block_indices: GridArray::from_fn(region, |cube| space.get_block_index(cube)),
+            light: if LumBlockVertex::WANTS_LIGHT {
+                Some(GridArray::from_fn(region, |cube| space.get_lighting(cube)))
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl SpaceTriangulationSource for ChunkInput {
+    fn get_block_index(&self, cube: GridPoint) -> Option<BlockIndex> {
+        self.block_indices.get(cube).copied().flatten()
+    }
+
+    fn get_lighting(&self, cube: GridPoint) -> PackedLight {
+        self.light
+            .as_ref()
+            .and_then(|light| light.get(cube).copied())
+            .unwrap_or(PackedLight::ONE)
+    }
+}
+
+/// A chunk to be triangulated by a [`ChunkMeshWorker`].
+#[derive(Debug)]
+pub(super) struct ChunkMeshJob {
+    pub(super) position: ChunkPos<CHUNK_SIZE>,
+    /// The chunk's `chunk_content_hash` when the job was made, for caching the result.
+    pub(super) content_hash: u64,
+    pub(super) input: ChunkInput,
+    pub(super) blocks: Arc<SharedBlocks>,
+    pub(super) options: GraphicsOptions,
+}
+
+impl ChunkMeshJob {
+    fn run(self) -> ChunkMeshResult {
+        let _span = trace_span!("chunk_mesh_job");
+        let mut block_provider = TrackingBlockProvider::new(&self.blocks.triangulations);
+        let mut triangulation = SpaceTriangulation::new();
+        triangulation.compute(
+            &self.input,
+            self.position.grid(),
+            &self.options,
+            &mut block_provider,
+        );
+        let block_dependencies = block_provider
+            .seen()
+            .filter_map(|index| {
+                let version = *self.blocks.versions.get(index)?;
+                Some((index as BlockIndex, version))
+            })
+            .collect();
+        ChunkMeshResult {
+            position: self.position,
+            content_hash: self.content_hash,
+            triangulation,
+            block_dependencies,
+        }
+    }
+}
+
+/// A chunk triangulated by a [`ChunkMeshWorker`].
+#[derive(Debug)]
+pub(super) struct ChunkMeshResult {
+    pub(super) position: ChunkPos<CHUNK_SIZE>,
+    pub(super) content_hash: u64,
+    pub(super) triangulation: SpaceTriangulation<LumBlockVertex>,
+    /// The block triangulations used and their versions. The result is only valid if
+    /// these are still current, since otherwise the texture tiles its vertices refer to
+    /// may have been deallocated.
+    pub(super) block_dependencies: Vec<(BlockIndex, u32)>,
+}
+
+/// Runs [`ChunkMeshJob`]s on a background thread, or, where threads are not available
+/// (on the web), immediately when they are submitted; in either case the results are
+/// picked up by [`ChunkMeshWorker::take_results`].
+#[derive(Debug)]
+pub(super) struct ChunkMeshWorker {
+    /// [`None`] if there is no thread and jobs should be run immediately.
+    jobs: Option<mpsc::Sender<ChunkMeshJob>>,
+    results: mpsc::Receiver<ChunkMeshResult>,
+    /// Used to deliver results of jobs run without the thread.
+    result_sender: mpsc::Sender<ChunkMeshResult>,
+    /// Chunks which have been submitted and whose results have not yet been taken.
+    in_flight: HashSet<ChunkPos<CHUNK_SIZE>>,
+}
+
+impl ChunkMeshWorker {
+    pub(super) fn new() -> Self {
+        let (result_sender, results) = mpsc::channel();
+        let jobs = if cfg!(target_arch = "wasm32") {
+            None
+        } else {
+            let (job_sender, job_receiver) = mpsc::channel::<ChunkMeshJob>();
+            let thread_result_sender = result_sender.clone();
+            let spawned = thread::Builder::new()
+                .name("chunk meshing".to_owned())
+                .spawn(move || {
+                    // Stops when the worker is dropped and the job channel closes.
+                    for job in job_receiver {
+                        if thread_result_sender.send(job.run()).is_err() {
+                            break;
+                        }
+                    }
+                });
+            match spawned {
+                Ok(_) => Some(job_sender),
+                Err(error) => {
+                    log::warn!(
+                        "failed to start chunk meshing thread; meshing on render thread: {}",
+                        error
+                    );
+                    None
+                }
+            }
+        };
+        Self {
+            jobs,
+            results,
+            result_sender,
+            in_flight: HashSet::new(),
+        }
+    }
+
+    /// Starts triangulating a chunk. Its result will be returned from a later call to
+    /// [`Self::take_results`].
+    pub(super) fn submit(&mut self, job: ChunkMeshJob) {
+        self.in_flight.insert(job.position);
+        let job = match &self.jobs {
+            Some(sender) => match sender.send(job) {
+                Ok(()) => return,
+                Err(mpsc::SendError(job)) => {
+                    // The thread has stopped, which can only be by panicking.
+                    // Jobs it did not finish will never be returned, so do them here.
+                    log::error!("chunk meshing thread stopped; meshing on render thread");
+                    self.jobs = None;
+                    self.in_flight.clear();
+                    self.in_flight.insert(job.position);
+                    job
+                }
+            },
+            None => job,
+        };
+        // Can't fail since we hold the receiver.
+        let _ = self.result_sender.send(job.run());
+    }
+
+    /// Whether a job for this chunk has been submitted and its result not yet taken.
+    pub(super) fn is_in_flight(&self, position: ChunkPos<CHUNK_SIZE>) -> bool {
+        self.in_flight.contains(&position)
+    }
+
+    /// Number of jobs submitted whose results have not yet been taken.
+    pub(super) fn in_flight_count(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Returns the results of all jobs which have finished since the last call.
+    pub(super) fn take_results(&mut self) -> Vec<ChunkMeshResult> {
+        let results: Vec<ChunkMeshResult> = self.results.try_iter().collect();
+        for result in &results {
+            self.in_flight.remove(&result.position);
+        }
+        results
+    }
+}
+
+/// Helper for [`ChunkMeshJob`]'s dependency tracking.
+struct TrackingBlockProvider<'a> {
+    block_triangulations: &'a [BlockTriangulation<LumBlockVertex, ()>],
+    seen: BitVec,
+}
+impl<'a> TrackingBlockProvider<'a> {
+    fn new(block_triangulations: &'a [BlockTriangulation<LumBlockVertex, ()>]) -> Self {
+        Self {
+            block_triangulations,
+            seen: BitVec::with_capacity(256), // TODO: cleverer choice
+        }
+    }
+
+    /// Return the indices of all the block triangulations that were used.
+    ///
+    /// Note: In principle, the value type should be [`BlockIndex`], but in practice it
+    /// is used as an array index so this avoids writing a double conversion.
+    fn seen<'s: 'a>(&'s self) -> impl Iterator<Item = usize> + 's {
+        self.seen.iter_ones()
+    }
+}
+impl<'a> BlockTriangulationProvider<'a, LumBlockVertex, ()> for &mut TrackingBlockProvider<'a> {
+    fn get(&mut self, index: BlockIndex) -> Option<&'a BlockTriangulation<LumBlockVertex, ()>> {
+        let index = usize::from(index);
+        if index >= self.seen.len() {
+            self.seen.resize(index + 1, false);
+        }
+        self.seen.set(index, true);
+        self.block_triangulations.get(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::TransparencyOption;
+    use crate::content::make_some_blocks;
+    use crate::triangulator::{triangulate_blocks, triangulate_space, TestTextureAllocator};
+    use std::time::{Duration, Instant};
+
+    /// Waits for the worker to return a result.
+    fn wait_for_result(worker: &mut ChunkMeshWorker) -> ChunkMeshResult {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some(result) = worker.take_results().pop() {
+                return result;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for result");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn job_result_matches_triangulating_space() {
+        let [b1, b2] = make_some_blocks();
+        let mut space = Space::empty_positive(CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE + 1);
+        space.set([1, 1, 1], &b1).unwrap();
+        space.set([2, 1, 1], &b2).unwrap();
+        // Outside the chunk, but hides a face inside it.
+        space.set([1, 1, CHUNK_SIZE], &b1).unwrap();
+        space.set([1, 1, CHUNK_SIZE - 1], &b2).unwrap();
+
+        let options = GraphicsOptions::default();
+        let block_triangulations: Vec<BlockTriangulation<LumBlockVertex, _>> =
+            Vec::from(triangulate_blocks(
+                &space,
+                &mut TestTextureAllocator::new(16),
+                &TransparencyOption::Volumetric,
+            ));
+        let position = ChunkPos(GridPoint::new(0, 0, 0));
+        let expected: SpaceTriangulation<LumBlockVertex> =
+            triangulate_space(&space, position.grid(), &options, &*block_triangulations);
+
+        let versions = vec![7; block_triangulations.len()];
+        let mut worker = ChunkMeshWorker::new();
+        worker.submit(ChunkMeshJob {
+            position,
+            content_hash: 123,
+            input: ChunkInput::copy_from(&space, position.grid()),
+            blocks: SharedBlocks::current(&mut None, 0, &block_triangulations, &versions),
+            options,
+        });
+        assert!(worker.is_in_flight(position));
+        let result = wait_for_result(&mut worker);
+
+        assert!(!worker.is_in_flight(position));
+        assert_eq!(result.position, position);
+        assert_eq!(result.content_hash, 123);
+        assert_eq!(result.triangulation, expected);
+        assert_eq!(result.block_dependencies, vec![(0, 7), (1, 7), (2, 7)]);
+    }
+}
@@ -14,9 +14,10 @@ use luminance::blending::Blending;
 use luminance::blending::Equation;
 use luminance::blending::Factor;
 use luminance::depth_test::DepthWrite;
+use luminance::scissor::ScissorRegion;
 use luminance_front::context::GraphicsContext;
 use luminance_front::framebuffer::Framebuffer;
-use luminance_front::pipeline::PipelineState;
+use luminance_front::pipeline::{PipelineState, Viewport as LumViewport};
 use luminance_front::render_state::RenderState;
 use luminance_front::tess::Mode;
 use luminance_front::texture::Dim2;
@@ -24,19 +25,24 @@ use luminance_front::Backend;
 use std::fmt;
 use std::time::Duration;
 
-use crate::camera::{Camera, GraphicsOptions, Viewport};
+use crate::camera::{
+    Camera, Eye, GraphicsOptions, RenderMethod, StereoCamera, Viewport, ViewportRect,
+};
 use crate::character::{Character, Cursor};
 use crate::content::palette;
 use crate::listen::{DirtyFlag, ListenableSource};
 use crate::lum::frame_texture::{FullFramePainter, FullFrameTexture};
+use crate::lum::raytrace::RaytraceRenderer;
 use crate::lum::shading::BlockPrograms;
+use crate::lum::skybox::SkyboxRenderer;
 use crate::lum::space::{SpaceRenderInfo, SpaceRenderer};
 use crate::lum::types::LumBlockVertex;
 use crate::lum::GraphicsResourceError;
-use crate::lum::{make_cursor_tess, wireframe_vertices};
-use crate::math::{Aab, Rgba};
+use crate::lum::{make_cursor_tess, make_entities_tess, wireframe_vertices};
+use crate::math::{Aab, FreeCoordinate, Rgba};
 use crate::space::Space;
-use crate::universe::URef;
+use crate::trace::trace_span;
+use crate::universe::{ReadRef, URef};
 use crate::util::{CustomFormat, StatusText};
 use crate::vui::Vui;
 
@@ -53,14 +59,24 @@ where
     pub surface: C,
     back_buffer: Framebuffer<Dim2, (), ()>,
     block_programs: BlockPrograms,
+    skybox_renderer: SkyboxRenderer,
     info_text_texture: FullFrameTexture,
 
     // Rendering state
     character: Option<URef<Character>>,
     world_renderer: Option<SpaceRenderer>,
+    /// Used instead of drawing `world_renderer`'s chunks if the options say so.
+    world_raytracer: Option<RaytraceRenderer>,
     ui_renderer: Option<SpaceRenderer>,
     world_camera: Camera,
+    /// If present, the world is drawn once for each eye of this camera instead of with
+    /// `world_camera`, which is still used for everything else.
+    stereo_camera: Option<StereoCamera>,
+    /// Additional views of the world, drawn over the main one in order.
+    extra_views: Vec<(ViewportRect, Camera)>,
     ui_camera: Camera,
+    /// Start time of the previous call to [`GLRenderer::render_frame`].
+    last_frame_start: Option<Instant>,
 }
 
 impl<C> GLRenderer<C>
@@ -80,6 +96,7 @@ where
         let initial_options = &*graphics_options.get();
 
         let block_programs = BlockPrograms::compile(&mut surface, initial_options)?;
+        let skybox_renderer = SkyboxRenderer::new(&mut surface)?;
         let back_buffer = luminance::framebuffer::Framebuffer::back_buffer(
             &mut surface,
             viewport.framebuffer_size.into(),
@@ -96,12 +113,17 @@ where
             surface,
             back_buffer,
             block_programs,
+            skybox_renderer,
             info_text_texture,
             character: None,
             world_renderer: None,
+            world_raytracer: None,
             ui_renderer: None,
             ui_camera: Camera::new(Vui::graphics_options(initial_options.clone()), viewport),
             world_camera: Camera::new(initial_options.clone(), viewport),
+            stereo_camera: None,
+            extra_views: Vec::new(),
+            last_frame_start: None,
         })
     }
 
@@ -113,6 +135,9 @@ where
     /// Sets the expected viewport dimensions. Use in case of window resizing.
     pub fn set_viewport(&mut self, viewport: Viewport) -> Result<(), GraphicsResourceError> {
         self.world_camera.set_viewport(viewport);
+        if let Some(stereo_camera) = &mut self.stereo_camera {
+            stereo_camera.set_viewport(stereo_viewport(viewport));
+        }
 
         self.ui_camera.set_viewport(viewport);
         if let Some(ui_renderer) = &self.ui_renderer {
@@ -140,7 +165,7 @@ where
         self.character = character;
     }
 
-    pub fn set_ui_space(&mut self, space: Option<URef<Space>>) {
+    pub fn set_ui_space(&mut self, space: Option<ReadRef<Space>>) {
         self.ui_renderer = space.map(|space| {
             self.ui_camera
                 .set_view_matrix(Vui::view_matrix(&*space.borrow(), self.ui_camera.fov_y()));
@@ -148,6 +173,38 @@ where
         });
     }
 
+    /// Enables or disables stereoscopic rendering: if `interpupillary_distance` is not
+    /// [`None`], the world is drawn twice, side by side, for the left and right eyes of a
+    /// [`StereoCamera`]. The user interface is drawn once over both.
+    ///
+    /// [`Self::world_camera`] continues to describe the point between the eyes.
+    pub fn set_stereo(&mut self, interpupillary_distance: Option<FreeCoordinate>) {
+        self.stereo_camera = interpupillary_distance.map(|distance| {
+            let mut stereo_camera = StereoCamera::new(
+                self.world_camera.options().clone(),
+                stereo_viewport(self.world_camera.viewport()),
+            );
+            stereo_camera.set_interpupillary_distance(distance);
+            stereo_camera.set_view_matrix(self.world_camera.view_matrix());
+            stereo_camera
+        });
+    }
+
+    /// Sets additional views of the character's space to draw in each frame, on top of
+    /// the main view, such as a rear view or map inset. Each is drawn in the given part
+    /// of the viewport, with the given camera, whose viewport is set to match.
+    ///
+    /// Unlike [`Self::world_camera`], the view matrices of these cameras are not updated
+    /// by the renderer; use [`Self::extra_views_mut`] to move them.
+    pub fn set_extra_views(&mut self, views: Vec<(ViewportRect, Camera)>) {
+        self.extra_views = views;
+    }
+
+    /// Returns the views set by [`Self::set_extra_views`], for modification.
+    pub fn extra_views_mut(&mut self) -> &mut [(ViewportRect, Camera)] {
+        &mut self.extra_views
+    }
+
     /// Return the camera used to render the space.
     /// TODO: This interface exists to support cursor usage and should perhaps be made more
     /// high-level by doing the raycast in here.
@@ -167,21 +224,33 @@ where
         &mut self,
         cursor_result: &Option<Cursor>,
     ) -> Result<RenderInfo, GraphicsResourceError> {
+        let _span = trace_span!("render_frame");
         let mut info = RenderInfo::default();
         let start_frame_time = Instant::now();
+        info.frame_interval = self
+            .last_frame_start
+            .replace(start_frame_time)
+            .map(|last| start_frame_time.duration_since(last));
 
         if self.graphics_options_dirty.get_and_clear() {
             // TODO: (asynchronously?) recompile shaders with new options
             self.world_camera
                 .set_options(self.graphics_options.snapshot());
+            if let Some(stereo_camera) = &mut self.stereo_camera {
+                stereo_camera.set_options(self.graphics_options.snapshot());
+            }
             self.ui_camera
                 .set_options(Vui::graphics_options(self.graphics_options.snapshot()));
 
             // TODO: going to need invalidation of chunks etc. here
+
+            // The raytracer's shader depends on the options, so rebuild it.
+            self.world_raytracer = None;
         }
 
         let surface = &mut self.surface;
         let block_programs = &mut self.block_programs;
+        let skybox_renderer = &mut self.skybox_renderer;
 
         let character: &Character = &*(if let Some(character_ref) = &self.character {
             character_ref.borrow()
@@ -196,15 +265,52 @@ where
         });
 
         self.world_camera.set_view_matrix(character.view());
+        if let Some(stereo_camera) = &mut self.stereo_camera {
+            stereo_camera.set_view_matrix(character.view());
+        }
         let graphics_options = self.world_camera.options(); // arbitrary choice of borrowable source
 
+        let viewport = self.world_camera.viewport();
+        for (rect, camera) in &mut self.extra_views {
+            camera.set_viewport(rect.viewport_within(viewport));
+        }
+
+        // Each pass draws the world as seen by one camera, into part of the framebuffer.
+        let mut world_passes: Vec<(&Camera, ViewportRect)> = match &self.stereo_camera {
+            None => vec![(&self.world_camera, ViewportRect::FULL)],
+            Some(stereo_camera) => Eye::ALL
+                .iter()
+                .map(|&eye| stereo_camera.eye(eye))
+                .zip(ViewportRect::FULL.columns(2))
+                .collect(),
+        };
+        world_passes.extend(
+            self.extra_views
+                .iter()
+                .map(|(rect, camera)| (camera, *rect)),
+        );
+
         // Prepare Tess and Texture for space.
         let start_prepare_time = Instant::now();
-        if self.world_renderer.as_ref().map(|sr| sr.space()) != Some(&character.space) {
-            self.world_renderer = Some(SpaceRenderer::new(character.space.clone()));
+        if !matches!(&self.world_renderer, Some(sr) if sr.space().is(&character.space)) {
+            self.world_renderer = Some(SpaceRenderer::new(character.space.read_only()));
         }
         let world_renderer = self.world_renderer.as_mut().unwrap();
-        let world_output = world_renderer.prepare_frame(surface, &self.world_camera)?;
+        let mut world_raytracer = if graphics_options.render_method == RenderMethod::Raytrace {
+            if !matches!(&self.world_raytracer, Some(r) if r.space().is(&character.space)) {
+                self.world_raytracer = Some(RaytraceRenderer::new(
+                    surface,
+                    character.space.read_only(),
+                    graphics_options,
+                )?);
+            }
+            let world_raytracer = self.world_raytracer.as_mut().unwrap();
+            world_raytracer.prepare_frame(surface)?;
+            Some(world_raytracer)
+        } else {
+            self.world_raytracer = None;
+            None
+        };
 
         let ui_output = if let Some(ui_renderer) = &mut self.ui_renderer {
             Some(ui_renderer.prepare_frame(surface, &self.ui_camera)?)
@@ -274,50 +380,111 @@ where
 
         // TODO: cache
         let cursor_tess = make_cursor_tess(surface, &cursor_result)?;
+        // The raytracer draws entities itself.
+        let entities_tess = if world_raytracer.is_none() {
+            Some(make_entities_tess(surface, &*character.space.borrow())?)
+        } else {
+            None
+        };
 
-        let start_draw_time = Instant::now();
-        surface
-            .new_pipeline_gate()
-            .pipeline(
-                &self.back_buffer,
-                // TODO: port skybox cube map code
-                &PipelineState::default()
-                    .set_clear_color(world_output.data.sky_color.with_alpha_one().into()),
-                |pipeline, mut shading_gate| {
-                    let world_output_bound = world_output.bind(&pipeline)?;
-                    // Space
-                    info.space = world_output_bound.render(&mut shading_gate, block_programs)?;
-
-                    // Cursor and debug info
-                    // Note: This will fall on top of transparent world content due to draw order.
-                    shading_gate.shade(
-                        &mut block_programs.opaque,
-                        |ref mut program_iface, u, mut render_gate| {
-                            u.initialize(program_iface, &world_output_bound);
-                            render_gate.render(&RenderState::default(), |mut tess_gate| {
-                                // Draw cursor only if it's in the same space.
-                                if matches!(cursor_result, Some(c) if c.space == character.space) {
-                                    tess_gate.render(&cursor_tess)?;
-                                }
-
-                                if let Some(tess) = &debug_lines_tess {
-                                    tess_gate.render(tess)?;
-                                }
+        let start_submit_time = Instant::now();
+        // Draw cursor only if it's in the same space.
+        let draw_cursor = matches!(cursor_result, Some(c) if c.space == character.space);
+        for (camera, rect) in world_passes {
+            let (origin, size) = rect.framebuffer_bounds(viewport);
+            if size.x == 0 || size.y == 0 {
+                continue;
+            }
+            // luminance's origin is at the bottom left.
+            let y = viewport.framebuffer_size.y - origin.y - size.y;
+            let world_output = world_renderer.prepare_frame(surface, camera)?;
+            skybox_renderer.set_skybox(surface, world_output.data.skybox.as_ref())?;
+            let world_raytracer = world_raytracer.as_deref_mut();
+            let debug_lines_tess = &debug_lines_tess;
+            let cursor_tess = &cursor_tess;
+            let entities_tess = &entities_tess;
+            surface
+                .new_pipeline_gate()
+                .pipeline(
+                    &self.back_buffer,
+                    // TODO: The skybox texture is not color filtered.
+                    &PipelineState::default()
+                        .set_clear_color(
+                            camera
+                                .options()
+                                .color_filter
+                                .apply(world_output.data.sky_color)
+                                .with_alpha_one()
+                                .into(),
+                        )
+                        .set_viewport(LumViewport::Specific {
+                            x: origin.x,
+                            y,
+                            width: size.x,
+                            height: size.y,
+                        })
+                        // The viewport does not limit clearing, so this is needed as well.
+                        .set_scissor(ScissorRegion {
+                            x: origin.x,
+                            y,
+                            width: size.x,
+                            height: size.y,
+                        }),
+                    |pipeline, mut shading_gate| {
+                        skybox_renderer.render(
+                            &pipeline,
+                            &mut shading_gate,
+                            &world_output.data.camera,
+                        )?;
+
+                        let world_output_bound = world_output.bind(&pipeline)?;
+                        // Space
+                        if let Some(world_raytracer) = world_raytracer {
+                            world_raytracer.render(
+                                &pipeline,
+                                &mut shading_gate,
+                                &world_output_bound,
+                            )?;
+                        } else {
+                            // If there is more than one pass, this is the last one's info.
+                            info.space =
+                                world_output_bound.render(&mut shading_gate, block_programs)?;
+                        }
+
+                        // Entities, cursor, and debug info
+                        // Note: This will fall on top of transparent world content due to draw
+                        // order.
+                        shading_gate.shade(
+                            &mut block_programs.opaque,
+                            |ref mut program_iface, u, mut render_gate| {
+                                u.initialize(program_iface, &world_output_bound);
+                                render_gate.render(&RenderState::default(), |mut tess_gate| {
+                                    if let Some(tess) = entities_tess {
+                                        tess_gate.render(tess)?;
+                                    }
+
+                                    if draw_cursor {
+                                        tess_gate.render(cursor_tess)?;
+                                    }
+
+                                    if let Some(tess) = debug_lines_tess {
+                                        tess_gate.render(tess)?;
+                                    }
+                                    Ok(())
+                                })?;
                                 Ok(())
-                            })?;
-                            Ok(())
-                        },
-                    )
-                },
-            )
-            .assume()
-            .into_result()?;
+                            },
+                        )
+                    },
+                )
+                .assume()
+                .into_result()?;
+        }
 
         surface
             .new_pipeline_gate()
             .pipeline(
                 &self.back_buffer,
-                // TODO: port skybox cube map code
                 &PipelineState::default().enable_clear_color(false),
                 |ref pipeline, ref mut shading_gate| {
                     if let Some(ui_output) = ui_output {
@@ -332,7 +499,7 @@ where
             .assume()
             .into_result()?;
 
-        info.draw_time = Instant::now().duration_since(start_draw_time);
+        info.submit_time = Instant::now().duration_since(start_submit_time);
         info.frame_time = Instant::now().duration_since(start_frame_time);
         Ok(info)
     }
@@ -377,25 +544,53 @@ where
     }
 }
 
-/// Information about render performance.
+/// Information about render performance, returned by [`GLRenderer::render_frame`].
+///
+/// All times are measured on the CPU. Since the GPU works asynchronously, the time it
+/// takes to actually draw is not included, except to the extent that submitting
+/// commands waits for it.
 #[derive(Clone, Debug, Default)]
+#[non_exhaustive]
 pub struct RenderInfo {
-    frame_time: Duration,
-    prepare_time: Duration,
-    draw_time: Duration,
-    space: SpaceRenderInfo,
+    /// Total time spent in [`GLRenderer::render_frame`]. This does not include waiting
+    /// for vsync (which happens when the embedder swaps buffers), so it is suitable for
+    /// [`FrameClock::record_frame_timing`](crate::apps::FrameClock::record_frame_timing).
+    pub frame_time: Duration,
+    /// Time spent updating meshes, textures, and other data before drawing.
+    pub prepare_time: Duration,
+    /// Time spent issuing draw commands.
+    pub submit_time: Duration,
+    /// Time since the start of the previous frame, or [`None`] if this is the first.
+    /// When rendering is keeping up, this reflects the display's refresh rate.
+    pub frame_interval: Option<Duration>,
+    /// Information about the world space.
+    pub space: SpaceRenderInfo,
 }
 
 impl CustomFormat<StatusText> for RenderInfo {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>, _: StatusText) -> fmt::Result {
         writeln!(
             fmt,
-            "Frame time: {} (prep {}, draw {})",
+            "Frame time: {} (prep {}, submit {})",
             self.frame_time.custom_format(StatusText),
             self.prepare_time.custom_format(StatusText),
-            self.draw_time.custom_format(StatusText),
+            self.submit_time.custom_format(StatusText),
         )?;
+        if let Some(interval) = self.frame_interval {
+            writeln!(
+                fmt,
+                "Frame interval: {} ({:5.1} fps)",
+                interval.custom_format(StatusText),
+                1.0 / interval.as_secs_f64().max(1e-6),
+            )?;
+        }
         write!(fmt, "{}", self.space.custom_format(StatusText))?;
         Ok(())
     }
 }
+
+/// Returns the viewport of each eye of a [`StereoCamera`] drawn side by side in
+/// `viewport`.
+fn stereo_viewport(viewport: Viewport) -> Viewport {
+    ViewportRect::FULL.columns(2)[0].viewport_within(viewport)
+}
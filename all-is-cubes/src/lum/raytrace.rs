@@ -0,0 +1,256 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Drawing a [`Space`] by raytracing in a shader; see
+//! [`RenderMethod::Raytrace`](crate::camera::RenderMethod::Raytrace).
+
+use cgmath::{EuclideanSpace as _, Matrix4, SquareMatrix as _};
+use luminance::blending::{Blending, Equation, Factor};
+use luminance::depth_test::DepthWrite;
+use luminance::UniformInterface;
+use luminance_front::context::GraphicsContext;
+use luminance_front::pipeline::{Pipeline, TextureBinding};
+use luminance_front::pixel::{NormRGBA8UI, NormUnsigned};
+use luminance_front::render_state::RenderState;
+use luminance_front::shader::{Program, Uniform};
+use luminance_front::shading_gate::ShadingGate;
+use luminance_front::tess::{Mode, Tess};
+use luminance_front::texture::{Dim3, GenMipmaps, Sampler, Texture, TextureError};
+use luminance_front::Backend;
+
+use crate::camera::{FogParameters, GraphicsOptions, LightingOption, TransparencyOption};
+use crate::lum::shading::{map_shader_result, SHADER_COMMON};
+use crate::lum::space::SpaceRendererBound;
+use crate::lum::GraphicsResourceError;
+use crate::math::GridCoordinate;
+use crate::space::{DirtyRegions, Grid, Space};
+use crate::universe::ReadRef;
+
+/// Size of the regions in which [`RaytraceRenderer`] updates its texture.
+const DIRTY_CHUNK_SIZE: GridCoordinate = 16;
+
+/// Draws a [`Space`] by tracing a ray for each pixel in a fragment shader, instead of
+/// drawing the chunk meshes of a [`SpaceRenderer`](crate::lum::space::SpaceRenderer).
+///
+/// The space's blocks are uploaded to a 3D texture with one texel per cube, holding
+/// the block's color; light is taken from the [`SpaceRenderer`]'s light texture.
+/// Since the raytracer writes no depth, it should be drawn after the skybox and before
+/// anything else, which will then be drawn on top of it.
+///
+/// [`SpaceRenderer`]: crate::lum::space::SpaceRenderer
+pub(crate) struct RaytraceRenderer {
+    space: ReadRef<Space>,
+    dirty: DirtyRegions,
+    program: Program<(), (), RaytraceUniformInterface>,
+    tess: Tess<()>,
+    /// None if the space has zero volume, since there is nothing to draw.
+    block_texture: Option<SpaceBlockColorTexture>,
+}
+
+impl RaytraceRenderer {
+    /// Constructs a [`RaytraceRenderer`] for the given [`Space`] and options.
+    /// If the options change, a new renderer should be constructed.
+    pub fn new<C>(
+        context: &mut C,
+        space: ReadRef<Space>,
+        options: &GraphicsOptions,
+    ) -> Result<Self, GraphicsResourceError>
+    where
+        C: GraphicsContext<Backend = Backend>,
+    {
+        let mut defines = String::new();
+        if options.lighting_display != LightingOption::None {
+            defines += "#define LIGHTING 1\n";
+        }
+        let fragment_shader = defines
+            + "\n#line 1 0\n"
+            + SHADER_COMMON
+            + "\n#line 1 1\n"
+            + include_str!("shaders/raytrace-fragment.glsl");
+        let program = map_shader_result(context.new_shader_program().from_strings(
+            // Covers the screen and provides NDC, which is exactly what we need too.
+            include_str!("shaders/skybox-vertex.glsl"),
+            None,
+            None,
+            &fragment_shader,
+        ))?;
+
+        let dirty = DirtyRegions::new(DIRTY_CHUNK_SIZE);
+        space.borrow().listen_batched(dirty.listener());
+
+        Ok(Self {
+            space,
+            dirty,
+            program,
+            tess: context
+                .new_tess()
+                .set_render_vertex_nb(3)
+                .set_mode(Mode::Triangle)
+                .build()?,
+            block_texture: None,
+        })
+    }
+
+    /// Get the reference to the [`Space`] this draws.
+    pub fn space(&self) -> &ReadRef<Space> {
+        &self.space
+    }
+
+    /// Uploads whatever has changed in the space since the last call.
+    pub fn prepare_frame<C>(&mut self, context: &mut C) -> Result<(), GraphicsResourceError>
+    where
+        C: GraphicsContext<Backend = Backend>,
+    {
+        let space = &*self.space.borrow();
+
+        let grid = space.grid();
+        if grid.volume() == 0 {
+            self.block_texture = None;
+            return Ok(());
+        }
+        if self.block_texture.as_ref().map(|t| t.grid) != Some(grid) {
+            self.block_texture = Some(SpaceBlockColorTexture::new(context, grid)?);
+            self.dirty.mark_everything();
+        }
+        let block_texture = self.block_texture.as_mut().unwrap();
+
+        for region in self.dirty.take(grid) {
+            block_texture.update(space, region)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the space, as seen by the camera of `space_bound` (which must be for the
+    /// same space).
+    pub fn render(
+        &mut self,
+        pipeline: &Pipeline<'_>,
+        shading_gate: &mut ShadingGate<'_>,
+        space_bound: &SpaceRendererBound<'_>,
+    ) -> Result<(), GraphicsResourceError> {
+        let block_texture = match &mut self.block_texture {
+            Some(texture) => texture,
+            None => return Ok(()),
+        };
+        let block_offset: [i32; 3] = (-block_texture.grid.lower_bounds().to_vec()).into();
+        let bound_block_texture = pipeline.bind_texture(&mut block_texture.texture)?;
+
+        let camera = &space_bound.data.camera;
+        let options = camera.options();
+        let inverse_view_projection = (camera.projection() * camera.view_matrix())
+            .invert()
+            .unwrap_or_else(Matrix4::identity)
+            .cast::<f32>()
+            .unwrap();
+        let fog = FogParameters::new(options);
+        let alpha_threshold = match options.transparency {
+            TransparencyOption::Threshold(t) => t.into_inner(),
+            // TODO: TransparencyOption::Volumetric, once the CPU raytracer has it
+            TransparencyOption::Surface | TransparencyOption::Volumetric => -1.0,
+        };
+
+        let tess = &self.tess;
+        shading_gate.shade(
+            &mut self.program,
+            |ref mut program_iface, u, mut render_gate| {
+                program_iface.set(&u.inverse_view_projection, inverse_view_projection.into());
+                program_iface.set(&u.block_texture, bound_block_texture.binding());
+                program_iface.set(&u.block_offset, block_offset);
+                program_iface.set(
+                    &u.light_texture,
+                    space_bound.bound_light_texture.texture.binding(),
+                );
+                program_iface.set(
+                    &u.light_offset,
+                    space_bound.bound_light_texture.offset.into(),
+                );
+                program_iface.set(&u.view_distance, options.view_distance.into_inner() as f32);
+                program_iface.set(&u.alpha_threshold, alpha_threshold);
+                program_iface.set(&u.fog_mode_blend, fog.mode_blend);
+                program_iface.set(&u.fog_distance, fog.distance);
+                program_iface.set(&u.fog_color, space_bound.data.sky_color.into());
+                program_iface.set(&u.color_filter, options.color_filter.matrix().into());
+                render_gate.render(
+                    &RenderState::default()
+                        .set_depth_write(DepthWrite::Off)
+                        .set_blending(Some(Blending {
+                            equation: Equation::Additive,
+                            src: Factor::One,
+                            dst: Factor::SrcAlphaComplement,
+                        })),
+                    |mut tess_gate| -> Result<(), GraphicsResourceError> { tess_gate.render(tess) },
+                )
+            },
+        )
+    }
+}
+
+impl std::fmt::Debug for RaytraceRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RaytraceRenderer")
+            // Skipping GPU objects because they can't be usefully printed
+            .field("space", &self.space)
+            .field("dirty", &self.dirty)
+            .finish()
+    }
+}
+
+/// A 3D [`Texture`] of the colors of the blocks in a [`Space`], one texel per cube.
+struct SpaceBlockColorTexture {
+    texture: Texture<Dim3, NormRGBA8UI>,
+    /// The region of cube coordinates the texels correspond to.
+    grid: Grid,
+}
+
+impl SpaceBlockColorTexture {
+    fn new<C>(context: &mut C, grid: Grid) -> Result<Self, TextureError>
+    where
+        C: GraphicsContext<Backend = Backend>,
+    {
+        let texture = context.new_texture_no_texels(
+            grid.unsigned_size().into(),
+            /* mipmaps= */ 0,
+            Sampler::default(), // sampler options don't matter because we're using texelFetch()
+        )?;
+        Ok(Self { texture, grid })
+    }
+
+    /// Copy the specified region of block colors.
+    fn update(&mut self, space: &Space, region: Grid) -> Result<(), TextureError> {
+        let mut data = Vec::with_capacity(region.volume());
+        for z in region.z_range() {
+            for y in region.y_range() {
+                for x in region.x_range() {
+                    data.push(space.get_evaluated([x, y, z]).color.to_linear_32bit());
+                }
+            }
+        }
+        self.texture.upload_part(
+            GenMipmaps::No,
+            (region.lower_bounds() - self.grid.lower_bounds())
+                .map(|s| s as u32)
+                .into(),
+            region.unsigned_size().into(),
+            &data,
+        )
+    }
+}
+
+/// Uniform interface for the raytracing shader program. Names are as in
+/// `shaders/common.glsl` and `shaders/raytrace-fragment.glsl`.
+#[derive(Debug, UniformInterface)]
+pub(crate) struct RaytraceUniformInterface {
+    inverse_view_projection: Uniform<[[f32; 4]; 4]>,
+    block_texture: Uniform<TextureBinding<Dim3, NormUnsigned>>,
+    block_offset: Uniform<[i32; 3]>,
+    #[uniform(unbound)] // unbound if LightingOption::None
+    light_texture: Uniform<TextureBinding<Dim3, NormUnsigned>>,
+    #[uniform(unbound)] // unbound if LightingOption::None
+    light_offset: Uniform<[i32; 3]>,
+    view_distance: Uniform<f32>,
+    alpha_threshold: Uniform<f32>,
+    fog_mode_blend: Uniform<f32>,
+    fog_distance: Uniform<f32>,
+    fog_color: Uniform<[f32; 3]>,
+    color_filter: Uniform<[[f32; 4]; 4]>,
+}
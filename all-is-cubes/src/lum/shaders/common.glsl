@@ -23,6 +23,31 @@ uniform highp float fog_distance;
 // What color should fog fade into?
 uniform mediump vec3 fog_color;
 
+// Affine transformation of linear colors; see ColorFilter::matrix in src/camera.rs.
+uniform mediump mat4 color_filter;
+
+
+// These fog functions must be kept consistent with FogParameters::fog_mix in
+// src/camera.rs, which the other renderers use.
+
+// Physically realistic fog, but doesn't ever reach 1 (fully opaque).
+lowp float fog_exponential(highp float d) {
+  const lowp float fog_density = 1.6;
+  return 1.0 - exp(-fog_density * d);
+}
+
+// Fog that goes all the way from fully transparent to fully opaque.
+// The correction is smaller the denser the fog.
+lowp float fog_exp_fudged(highp float d) {
+  return fog_exponential(d) / fog_exponential(1.0);
+}
+
+lowp float fog_combo(highp float d) {
+  // Combination of realistic exponential (constant density) fog,
+  // and slower-starting fog so nearby stuff is clearer.
+  return mix(fog_exp_fudged(d), pow(d, 4.0), fog_mode_blend);
+}
+
 
 // Given integer cube coordinates, fetch and unpack a light_texture RGB value.
 // The alpha component corresponds to the `LightStatus` enum on the Rust side,
@@ -36,7 +61,10 @@ uniform mediump vec3 fog_color;
 // This encoding allows use of the 0-1 range for smooth lighting's blending
 // excluding opaque blocks, while the -1 value indicates values that should be
 // truly ignored.
-lowp vec4 light_texture_fetch(mediump vec3 p) {
+//
+// Light values range up to about 245, beyond the guaranteed range of lowp
+// (which is only ±2), so they must be handled at mediump or better.
+mediump vec4 light_texture_fetch(mediump vec3 p) {
   ivec3 lookup_position = ivec3(floor(p));
   lookup_position += light_offset;
   // Implement wrapping (not automatic since we're using texelFetch).
@@ -45,9 +73,10 @@ lowp vec4 light_texture_fetch(mediump vec3 p) {
   ivec3 size = textureSize(light_texture, 0);
   lookup_position = (lookup_position % size + size) % size;
 
-  lowp vec4 texel = texelFetch(light_texture, lookup_position, 0);
-  lowp vec3 packed_light = texel.rgb;
-  lowp vec3 unpacked_light = pow(vec3(2.0), (packed_light - 128.0 / 255.0) * (255.0 / 16.0));
+  mediump vec4 texel = texelFetch(light_texture, lookup_position, 0);
+  mediump vec3 packed_light = texel.rgb;
+  // The constants here are PackedLight::LOG_OFFSET and PackedLight::LOG_SCALE.
+  mediump vec3 unpacked_light = pow(vec3(2.0), (packed_light - 128.0 / 255.0) * (255.0 / 16.0));
 
   // See all_is_cubes::space::LightStatus for the value this is interpreting.
   // The enum values are grouped into approximately {0, 128, 255}, so multiplying by 2 and rounding
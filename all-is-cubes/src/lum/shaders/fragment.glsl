@@ -3,16 +3,16 @@
 
 in highp vec3 v_position;
 in highp vec3 v_position_in_cube;
-in mediump vec4 v_color_or_texture;
+in highp vec4 v_color_or_texture;
 in mediump vec3 v_normal;
-in mediump vec3 v_clamp_min;
-in mediump vec3 v_clamp_max;
+in highp vec3 v_clamp_min;
+in highp vec3 v_clamp_max;
 
 #ifdef SMOOTH_LIGHTING
   // Two positive unit vectors perpendicular to the normal vector.
   in lowp vec3 v_perpendicular_1, v_perpendicular_2;
 #else
-  in lowp vec3 v_lighting;
+  in mediump vec3 v_lighting;
 #endif
 
 // What fraction of the fragment color should be fog?
@@ -68,7 +68,7 @@ bool valid_light(vec4 light) {
 
 // Tweak a light value for ambient occlusion -- and convert the light status 
 // value returned from light_texture_fetch to an interpolation coefficient.
-lowp vec4 ao_fudge(lowp vec4 light_value) {
+mediump vec4 ao_fudge(mediump vec4 light_value) {
   // TODO: Make this a (uniform) graphics option
   const lowp float fudge = 0.25;
   lowp float status = light_value.a;
@@ -83,7 +83,7 @@ lowp vec4 ao_fudge(lowp vec4 light_value) {
 #ifdef SMOOTH_LIGHTING
 // Compute the interpolated ('smooth') light for the surface from light_texture.
 // This implementation is duplicated in Rust at src/raytracer.rs
-lowp vec3 interpolated_space_light() {
+mediump vec3 interpolated_space_light() {
   // About half the size of the smallest permissible voxel.
   const highp float above_surface_epsilon = 0.5 / 256.0;
 
@@ -117,10 +117,10 @@ lowp vec3 interpolated_space_light() {
   // Retrieve texels, again using the half-cube-offset grid (this way we won't have edge artifacts).
   const mediump float lin_lo = -0.5;
   const mediump float lin_hi = +0.5;
-  lowp vec4 near12    = light_texture_fetch(origin + lin_lo * dir_1 + lin_lo * dir_2);
-  lowp vec4 near1far2 = light_texture_fetch(origin + lin_lo * dir_1 + lin_hi * dir_2);
-  lowp vec4 near2far1 = light_texture_fetch(origin + lin_hi * dir_1 + lin_lo * dir_2);
-  lowp vec4 far12     = light_texture_fetch(origin + lin_hi * dir_1 + lin_hi * dir_2);
+  mediump vec4 near12    = light_texture_fetch(origin + lin_lo * dir_1 + lin_lo * dir_2);
+  mediump vec4 near1far2 = light_texture_fetch(origin + lin_lo * dir_1 + lin_hi * dir_2);
+  mediump vec4 near2far1 = light_texture_fetch(origin + lin_hi * dir_1 + lin_lo * dir_2);
+  mediump vec4 far12     = light_texture_fetch(origin + lin_hi * dir_1 + lin_hi * dir_2);
   
   if (!valid_light(near1far2) && !valid_light(near2far1)) {
     // The far corner is on the other side of a diagonal wall, so should be
@@ -135,7 +135,7 @@ lowp vec3 interpolated_space_light() {
   far12     = ao_fudge(far12);
 
   // Perform bilinear interpolation.
-  lowp vec4 v = mix(
+  mediump vec4 v = mix(
     mix(near12,    near1far2, mix_2),
     mix(near2far1, far12,     mix_2),
     mix_1
@@ -148,9 +148,9 @@ lowp vec3 interpolated_space_light() {
 }
 #endif
 
-lowp vec3 lighting() {
+mediump vec3 lighting() {
   #ifdef LIGHTING
-    lowp vec3 local_light;
+    mediump vec3 local_light;
     #ifdef SMOOTH_LIGHTING
       local_light = interpolated_space_light();
     #else
@@ -168,10 +168,10 @@ void main(void) {
   // TODO: Consider changing that.
   mediump vec4 diffuse_color;
   if (v_color_or_texture[3] < -0.5) {
-    // Texture coordinates.
-    mediump vec3 unclamped = v_color_or_texture.stp;
-    mediump vec3 texcoord = clamp(unclamped, v_clamp_min, v_clamp_max);
-    diffuse_color = texture(block_texture, texcoord);
+    // Texture coordinates, in texels.
+    highp vec3 unclamped = v_color_or_texture.stp;
+    highp vec3 texcoord = clamp(unclamped, v_clamp_min, v_clamp_max);
+    diffuse_color = texture(block_texture, texcoord / vec3(textureSize(block_texture, 0)));
 
     #ifdef DEBUG_TEXTURE_EDGE
       // Visualize the texture coordinate clamp boundaries, which happens to
@@ -228,6 +228,9 @@ void main(void) {
   // Fog
   color.rgb = mix(color.rgb, fog_color, fog_mix);
 
+  // Accessibility adjustment, matching ColorFilter::apply.
+  color.rgb = max((color_filter * vec4(color.rgb, 1.0)).rgb, vec3(0.0));
+
   // Convert from linear to sRGB color.
   // Source: <https://en.wikipedia.org/w/index.php?title=SRGB&oldid=1002296118#The_forward_transformation_(CIE_XYZ_to_sRGB)> (version as of Feb 3, 2020)
   if (true) {
@@ -0,0 +1,154 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+// Raytracer for the RenderMethod::Raytrace option. This follows the same rules as
+// all_is_cubes::raytracer::SpaceRaytracer; when changing one, change the other.
+//
+// block_texture (declared in common.glsl) here contains one texel per cube, the
+// block's color.
+
+// Inverse of the projection matrix times the view matrix, so that NDC can be
+// converted to world-space rays.
+uniform highp mat4 inverse_view_projection;
+
+// Offset applied to cube coordinates to get block_texture coordinates.
+uniform highp ivec3 block_offset;
+
+uniform highp float view_distance;
+
+// TransparencyOption::Threshold value, or negative for no threshold.
+uniform lowp float alpha_threshold;
+
+in highp vec2 v_ndc;
+
+out mediump vec4 fragment_color_srgb;
+
+// Same as fixed_directional_lighting() in fragment.glsl, but for any normal.
+lowp float fixed_directional_lighting_for(lowp vec3 normal) {
+  const lowp vec3 light_1_direction = vec3(0.4, -0.1, 0);
+  const lowp vec3 light_2_direction = vec3(-0.4, 0.35, 0.25);
+  return (1.0 - 1.0 / 16.0) + 0.25 * (max(0.0, dot(light_1_direction, normal)) + max(0.0, dot(light_2_direction, normal)));
+}
+
+void main(void) {
+  // Same computation as all_is_cubes::camera::Camera::project_ndc_into_world.
+  highp vec4 near = inverse_view_projection * vec4(v_ndc, -1.0, 1.0);
+  highp vec4 far = inverse_view_projection * vec4(v_ndc, 1.0, 1.0);
+  highp vec3 origin = near.xyz / near.w;
+  highp vec3 direction = far.xyz / far.w - origin;
+
+  // Converts t values into world distances.
+  highp float t_scale = length(direction);
+  highp float max_t = view_distance / t_scale;
+
+  // Avoid dividing by zero; such an axis is never stepped along anyway.
+  ivec3 step = ivec3(sign(direction));
+  highp vec3 safe_direction = direction + vec3(equal(step, ivec3(0))) * 1e-20;
+
+  // Find where the ray enters the region of the texture (slab method).
+  ivec3 lower = -block_offset;
+  ivec3 upper = lower + textureSize(block_texture, 0);
+  highp vec3 t_lower = (vec3(lower) - origin) / safe_direction;
+  highp vec3 t_upper = (vec3(upper) - origin) / safe_direction;
+  highp vec3 t_near = min(t_lower, t_upper);
+  highp vec3 t_far = max(t_lower, t_upper);
+  highp float t = max(max(t_near.x, t_near.y), max(t_near.z, 0.0));
+  highp float t_exit = min(t_far.x, min(t_far.y, t_far.z));
+
+  // Normal of the face through which the ray entered the current cube; zero if the
+  // ray started inside it, as with Face::Within.
+  lowp vec3 normal = vec3(0.0);
+  if (t > 0.0) {
+    if (t == t_near.x) {
+      normal = vec3(-step.x, 0.0, 0.0);
+    } else if (t == t_near.y) {
+      normal = vec3(0.0, -step.y, 0.0);
+    } else {
+      normal = vec3(0.0, 0.0, -step.z);
+    }
+  }
+
+  ivec3 cube = clamp(ivec3(floor(origin + direction * t)), lower, upper - 1);
+  highp vec3 t_delta = abs(1.0 / safe_direction);
+  highp vec3 t_next = (vec3(cube) + vec3(max(step, ivec3(0))) - origin) / safe_direction;
+  t_next = mix(t_next, vec3(1e30), vec3(equal(step, ivec3(0))));
+
+  // Accumulated color, premultiplied by alpha, and how much of the ray is left;
+  // as in all_is_cubes::raytracer::ColorBuf.
+  mediump vec3 color_accumulator = vec3(0.0);
+  mediump float ray_alpha = 1.0;
+
+  if (t <= t_exit) {
+    ivec3 size = upper - lower;
+    int max_steps = size.x + size.y + size.z;
+    for (int i = 0; i < max_steps; i++) {
+      if (any(lessThan(cube, lower)) || any(greaterThanEqual(cube, upper))) {
+        break;
+      }
+      if (t > max_t || ray_alpha < 1.0 / 256.0) {
+        break;
+      }
+
+      lowp vec4 surface = texelFetch(block_texture, cube + block_offset, 0);
+      if (alpha_threshold >= 0.0) {
+        surface.a = surface.a > alpha_threshold ? 1.0 : 0.0;
+      }
+      if (surface.a > 0.0) {
+        #ifdef LIGHTING
+          // Flat lighting: the light of the cube the ray came from.
+          mediump vec3 light = fixed_directional_lighting_for(normal)
+              * light_texture_fetch(vec3(cube) + normal + 0.5).rgb;
+        #else
+          lowp vec3 light = vec3(1.0);
+        #endif
+        mediump vec3 lit = surface.rgb * light;
+        lowp float fog_mix = clamp(fog_combo(t * t_scale / fog_distance), 0.0, 1.0);
+        lit = mix(lit, fog_color, fog_mix);
+
+        color_accumulator += lit * (surface.a * ray_alpha);
+        ray_alpha *= 1.0 - surface.a;
+      }
+
+      // Advance to the next cube.
+      if (t_next.x < t_next.y && t_next.x < t_next.z) {
+        t = t_next.x;
+        t_next.x += t_delta.x;
+        cube.x += step.x;
+        normal = vec3(-step.x, 0.0, 0.0);
+      } else if (t_next.y < t_next.z) {
+        t = t_next.y;
+        t_next.y += t_delta.y;
+        cube.y += step.y;
+        normal = vec3(0.0, -step.y, 0.0);
+      } else {
+        t = t_next.z;
+        t_next.z += t_delta.z;
+        cube.z += step.z;
+        normal = vec3(0.0, 0.0, -step.z);
+      }
+    }
+  }
+
+  // Whatever is left of the ray shows the background (sky color or skybox), which has
+  // already been drawn; blending takes care of combining them.
+  mediump float alpha = 1.0 - ray_alpha;
+  if (alpha <= 0.0) {
+    discard;
+  }
+  mediump vec3 color = color_accumulator / alpha;
+
+  // Accessibility adjustment, matching ColorFilter::apply. (The CPU raytracer applies
+  // it to each surface instead, but the result is the same since the filter is affine
+  // and the weights of the surfaces sum to alpha.)
+  color = max((color_filter * vec4(color, 1.0)).rgb, vec3(0.0));
+
+  // Convert from linear to sRGB color, as in fragment.glsl.
+  color = mix(
+    (211. * pow(color, vec3(5.0 / 12.0)) - vec3(11.0)) / 200.0,
+    color * (323.0 / 25.0),
+    vec3(lessThan(color, vec3(0.0031308)))
+  );
+
+  // Premultiplied alpha, matching the blend function.
+  fragment_color_srgb = vec4(color * alpha, alpha);
+}
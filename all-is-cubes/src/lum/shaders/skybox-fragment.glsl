@@ -0,0 +1,20 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+// Inverse of the projection matrix times the view matrix, so that NDC can be
+// converted to world-space directions.
+uniform highp mat4 inverse_view_projection;
+
+uniform lowp samplerCube skybox_texture;
+
+in highp vec2 v_ndc;
+
+out mediump vec4 color;
+
+void main() {
+  // Same computation as all_is_cubes::camera::Camera::project_ndc_into_world.
+  highp vec4 near = inverse_view_projection * vec4(v_ndc, -1.0, 1.0);
+  highp vec4 far = inverse_view_projection * vec4(v_ndc, 1.0, 1.0);
+  highp vec3 direction = far.xyz / far.w - near.xyz / near.w;
+  color = vec4(texture(skybox_texture, direction).rgb, 1.0);
+}
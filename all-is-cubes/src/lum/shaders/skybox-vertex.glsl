@@ -0,0 +1,16 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+// Normalized device coordinates of this fragment, for computing the view direction.
+out highp vec2 v_ndc;
+
+/// A single triangle that covers all of the screenarea
+const highp vec2[3] VERTICES = vec2[](
+    vec2(-1., -1.),
+    vec2(3., -1.),
+    vec2(-1., 3.)
+);
+void main() {
+    v_ndc = VERTICES[gl_VertexID];
+    gl_Position = vec4(v_ndc, 0.5, 1.);
+}
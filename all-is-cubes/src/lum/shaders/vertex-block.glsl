@@ -1,18 +1,32 @@
 // Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
 // in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
 
-in highp vec3 a_position;
-in highp vec3 a_cube;
-in lowp vec3 a_normal;
-in mediump vec4 a_color_or_texture;
-in mediump vec3 a_clamp_min;
-in mediump vec3 a_clamp_max;
+#ifdef INSTANCED
+  // Drawing one face of a solid-colored cube per instance; see LumCubeInstance.
+  in highp vec3 a_face_position;
+  in lowp vec3 a_normal;
+  in highp vec3 a_instance_cube;
+  in lowp vec4 a_instance_color;
+
+  #define a_position (a_face_position + a_instance_cube)
+  #define a_cube a_instance_cube
+  #define a_color_or_texture a_instance_color
+  #define a_clamp_min vec3(0.0)
+  #define a_clamp_max vec3(0.0)
+#else
+  in highp vec3 a_position;
+  in highp vec3 a_cube;
+  in lowp vec3 a_normal;
+  in highp vec4 a_color_or_texture;
+  in highp vec3 a_clamp_min;
+  in highp vec3 a_clamp_max;
+#endif
 
 out highp vec3 v_position;
 out highp vec3 v_position_in_cube;
-out mediump vec4 v_color_or_texture;
-out mediump vec3 v_clamp_min;
-out mediump vec3 v_clamp_max;
+out highp vec4 v_color_or_texture;
+out highp vec3 v_clamp_min;
+out highp vec3 v_clamp_max;
 out lowp vec3 v_normal;
 
 #ifdef LIGHTING
@@ -20,11 +34,11 @@ out lowp vec3 v_normal;
     // Two positive unit vectors perpendicular to the normal vector.
     out lowp vec3 v_perpendicular_1, v_perpendicular_2;
   #else
-    out lowp vec3 v_lighting;
+    out mediump vec3 v_lighting;
   #endif
 #endif
 
-lowp vec3 flat_space_light() {
+mediump vec3 flat_space_light() {
   mediump vec3 origin = a_cube + a_normal + vec3(0.5);
   return light_texture_fetch(origin).rgb;
 }
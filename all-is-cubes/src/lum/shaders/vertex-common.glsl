@@ -9,24 +9,6 @@ out lowp float fog_mix;
 // to the fragment as well).
 out highp vec3 camera_ray_direction;
 
-// Physically realistic fog, but doesn't ever reach 1 (fully opaque).
-lowp float fog_exponential(highp float d) {
-  const lowp float fog_density = 1.6;
-  return 1.0 - exp(-fog_density * d);
-}
-
-// Fog that goes all the way from fully transparent to fully opaque.
-// The correction is smaller the denser the fog.
-lowp float fog_exp_fudged(highp float d) {
-  return fog_exponential(d) / fog_exponential(1.0);
-}
-
-lowp float fog_combo(highp float d) {
-  // Combination of realistic exponential (constant density) fog,
-  // and slower-starting fog so nearby stuff is clearer.
-  return mix(fog_exp_fudged(d), pow(d, 4.0), fog_mode_blend);
-}
-
 void basic_vertex(highp vec3 vertex_position) {
   // Camera-relative position not transformed by projection.
   highp vec4 eye_vertex_position = view_matrix * vec4(vertex_position, 1.0);
@@ -5,6 +5,7 @@
 
 use cgmath::Matrix4;
 use instant::Instant;
+use luminance::vertex::Semantics;
 use luminance::UniformInterface;
 use luminance_front::context::GraphicsContext;
 use luminance_front::pipeline::TextureBinding;
@@ -13,20 +14,22 @@ use luminance_front::shader::{BuiltProgram, Program, ProgramError, ProgramInterf
 use luminance_front::texture::Dim3;
 use luminance_front::Backend;
 
-use crate::camera::{GraphicsOptions, LightingOption, TransparencyOption};
+use crate::camera::{FogParameters, GraphicsOptions, LightingOption, TransparencyOption};
 use crate::lum::block_texture::BoundBlockTexture;
 use crate::lum::space::SpaceRendererBound;
-use crate::lum::types::VertexSemantics;
+use crate::lum::types::{InstanceSemantics, VertexSemantics};
 use crate::lum::GraphicsResourceError;
 use crate::math::FreeCoordinate;
 
 /// Type of the block shader program (output of [`prepare_block_program`]).
-pub type BlockProgram = Program<VertexSemantics, (), BlockUniformInterface>;
+pub type BlockProgram<Sem = VertexSemantics> = Program<Sem, (), BlockUniformInterface>;
 
 /// Collection of shaders for rendering blocks, which all share the `BlockUniformInterface`.
 pub(crate) struct BlockPrograms {
     pub(crate) opaque: BlockProgram,
     pub(crate) transparent: BlockProgram,
+    /// Draws opaque cubes from [`LumCubeInstance`](crate::lum::types::LumCubeInstance)s.
+    pub(crate) instanced: BlockProgram<InstanceSemantics>,
 }
 
 impl BlockPrograms {
@@ -60,17 +63,25 @@ impl BlockPrograms {
                     .chain([("ALLOW_TRANSPARENCY", "1")].iter())
                     .copied(),
             )?,
+            instanced: prepare_block_program(
+                context,
+                base_defines
+                    .iter()
+                    .chain([("INSTANCED", "1")].iter())
+                    .copied(),
+            )?,
         })
     }
 }
 
 /// Compile the block shader program for the given [`GraphicsContext`].
-fn prepare_block_program<'a, C>(
+fn prepare_block_program<'a, C, Sem>(
     context: &mut C,
     defines: impl IntoIterator<Item = (&'a str, &'a str)>,
-) -> Result<BlockProgram, GraphicsResourceError>
+) -> Result<BlockProgram<Sem>, GraphicsResourceError>
 where
     C: GraphicsContext<Backend = Backend>,
+    Sem: Semantics,
 {
     let defines: String = defines
         .into_iter()
@@ -90,7 +101,7 @@ where
     let start_compile_time = Instant::now();
     let result = map_shader_result(
         context
-            .new_shader_program::<VertexSemantics, (), BlockUniformInterface>()
+            .new_shader_program::<Sem, (), BlockUniformInterface>()
             .from_strings(
                 &concatenated_vertex_shader,
                 None,
@@ -123,7 +134,7 @@ pub(crate) fn map_shader_result<Sem, Out, Uni>(
     }
 }
 
-const SHADER_COMMON: &str = include_str!("shaders/common.glsl");
+pub(crate) const SHADER_COMMON: &str = include_str!("shaders/common.glsl");
 const SHADER_FRAGMENT: &str = include_str!("shaders/fragment.glsl");
 const SHADER_VERTEX_BLOCK: &str = include_str!("shaders/vertex-block.glsl");
 const SHADER_VERTEX_COMMON: &str = include_str!("shaders/vertex-common.glsl");
@@ -153,6 +164,9 @@ pub struct BlockUniformInterface {
     fog_distance: Uniform<f32>,
     /// Color for the fog.
     fog_color: Uniform<[f32; 3]>,
+    /// Transformation applied to the final color; see
+    /// [`ColorFilter::matrix`](crate::camera::ColorFilter::matrix).
+    color_filter: Uniform<[[f32; 4]; 4]>,
 }
 
 impl BlockUniformInterface {
@@ -178,16 +192,11 @@ impl BlockUniformInterface {
         );
         program_iface.set(&self.light_offset, space.bound_light_texture.offset.into());
 
-        let view_distance = camera.view_distance() as f32;
-        let (fog_mode_blend, fog_distance) = match options.fog {
-            crate::camera::FogOption::None => (0.0, f32::INFINITY),
-            crate::camera::FogOption::Abrupt => (1.0, view_distance),
-            crate::camera::FogOption::Compromise => (0.5, view_distance),
-            crate::camera::FogOption::Physical => (0.0, view_distance),
-        };
-        program_iface.set(&self.fog_mode_blend, fog_mode_blend);
-        program_iface.set(&self.fog_distance, fog_distance);
+        let fog = FogParameters::new(options);
+        program_iface.set(&self.fog_mode_blend, fog.mode_blend);
+        program_iface.set(&self.fog_distance, fog.distance);
         program_iface.set(&self.fog_color, space.data.sky_color.into());
+        program_iface.set(&self.color_filter, options.color_filter.matrix().into());
     }
 
     /// Type converting wrapper for [`Self::projection_matrix`].
@@ -0,0 +1,200 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Drawing [`Skybox`]es as cube map textures.
+
+use cgmath::{Matrix4, SquareMatrix as _};
+use luminance::depth_test::DepthWrite;
+use luminance::texture::{CubeFace, Cubemap};
+use luminance::UniformInterface;
+use luminance_front::context::GraphicsContext;
+use luminance_front::pipeline::{Pipeline, TextureBinding};
+use luminance_front::pixel::{NormRGBA8UI, NormUnsigned};
+use luminance_front::render_state::RenderState;
+use luminance_front::shader::{Program, Uniform};
+use luminance_front::shading_gate::ShadingGate;
+use luminance_front::tess::{Mode, Tess};
+use luminance_front::texture::{GenMipmaps, MagFilter, MinFilter, Sampler, Texture, Wrap};
+use luminance_front::Backend;
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::lum::shading::map_shader_result;
+use crate::lum::GraphicsResourceError;
+use crate::math::{Face, Rgb};
+use crate::space::Skybox;
+
+/// Resolution of the cube map used for [`Skybox::Gradients`]; chosen to be high
+/// enough that linear filtering hides the texels.
+const GRADIENT_RESOLUTION: u16 = 64;
+
+/// Draws the [`Skybox`] of a space behind everything else, if it has one.
+pub(crate) struct SkyboxRenderer {
+    program: Program<(), (), SkyboxUniformInterface>,
+    tess: Tess<()>,
+    /// The skybox whose images are in `texture`, so we can tell when to reupload.
+    current: Option<Skybox>,
+    texture: Option<Texture<Cubemap, NormRGBA8UI>>,
+}
+
+impl SkyboxRenderer {
+    pub fn new<C: GraphicsContext<Backend = Backend>>(
+        context: &mut C,
+    ) -> Result<Self, GraphicsResourceError> {
+        let program = map_shader_result(context.new_shader_program().from_strings(
+            include_str!("shaders/skybox-vertex.glsl"),
+            None,
+            None,
+            include_str!("shaders/skybox-fragment.glsl"),
+        ))?;
+        Ok(Self {
+            program,
+            tess: context
+                .new_tess()
+                .set_render_vertex_nb(3)
+                .set_mode(Mode::Triangle)
+                .build()?,
+            current: None,
+            texture: None,
+        })
+    }
+
+    /// Make the cube map texture match `skybox`, uploading new images if it changed.
+    pub fn set_skybox<C: GraphicsContext<Backend = Backend>>(
+        &mut self,
+        context: &mut C,
+        skybox: Option<&Skybox>,
+    ) -> Result<(), GraphicsResourceError> {
+        let skybox = match skybox {
+            None => {
+                self.current = None;
+                self.texture = None;
+                return Ok(());
+            }
+            Some(skybox) => skybox,
+        };
+        if matches!(&self.current, Some(current) if same_skybox(current, skybox)) {
+            return Ok(());
+        }
+
+        let resolution = match skybox {
+            Skybox::Images { resolution, .. } => *resolution,
+            _ => GRADIENT_RESOLUTION,
+        };
+        let mut texture: Texture<Cubemap, NormRGBA8UI> = context.new_texture_no_texels(
+            u32::from(resolution),
+            0, // mipmaps
+            Sampler {
+                wrap_s: Wrap::ClampToEdge,
+                wrap_t: Wrap::ClampToEdge,
+                wrap_r: Wrap::ClampToEdge,
+                mag_filter: MagFilter::Linear,
+                min_filter: MinFilter::Linear,
+                ..Sampler::default()
+            },
+        )?;
+        for &face in Face::ALL_SIX {
+            let texels: Vec<[u8; 4]> = skybox
+                .face_image(face, resolution)
+                .into_iter()
+                .map(|color: Rgb| color.with_alpha_one().to_linear_32bit())
+                .collect();
+            texture.upload_part(
+                GenMipmaps::No,
+                ([0, 0], cube_face(face)),
+                u32::from(resolution),
+                &texels,
+            )?;
+        }
+
+        self.texture = Some(texture);
+        self.current = Some(skybox.clone());
+        Ok(())
+    }
+
+    /// Draw the skybox, if there is one, covering the entire framebuffer.
+    /// Depth is not written, so this may be drawn before anything else.
+    pub fn render(
+        &mut self,
+        pipeline: &Pipeline<'_>,
+        shading_gate: &mut ShadingGate<'_>,
+        camera: &Camera,
+    ) -> Result<(), GraphicsResourceError> {
+        let texture = match &mut self.texture {
+            Some(texture) => texture,
+            None => return Ok(()),
+        };
+        let bound_texture = pipeline.bind_texture(texture)?;
+        let inverse_view_projection = (camera.projection() * camera.view_matrix())
+            .invert()
+            .unwrap_or_else(Matrix4::identity)
+            .cast::<f32>()
+            .unwrap();
+        let tess = &self.tess;
+        shading_gate.shade(
+            &mut self.program,
+            |ref mut program_iface, uniform_iface, mut render_gate| {
+                program_iface.set(&uniform_iface.skybox_texture, bound_texture.binding());
+                program_iface.set(
+                    &uniform_iface.inverse_view_projection,
+                    inverse_view_projection.into(),
+                );
+                render_gate.render(
+                    &RenderState::default().set_depth_write(DepthWrite::Off),
+                    |mut tess_gate| -> Result<(), GraphicsResourceError> { tess_gate.render(tess) },
+                )
+            },
+        )
+    }
+}
+
+impl std::fmt::Debug for SkyboxRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SkyboxRenderer")
+            // Skipping GPU objects because they can't be usefully printed
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+/// Like `==` but doesn't compare image contents, which would be expensive to do
+/// every frame.
+fn same_skybox(a: &Skybox, b: &Skybox) -> bool {
+    match (a, b) {
+        (
+            Skybox::Images {
+                resolution: r1,
+                faces: f1,
+            },
+            Skybox::Images {
+                resolution: r2,
+                faces: f2,
+            },
+        ) => {
+            r1 == r2
+                && f1
+                    .iter()
+                    .zip(f2.iter())
+                    .all(|((_, i1), (_, i2))| Arc::ptr_eq(i1, i2))
+        }
+        _ => a == b,
+    }
+}
+
+fn cube_face(face: Face) -> CubeFace {
+    match face {
+        Face::PX => CubeFace::PositiveX,
+        Face::NX => CubeFace::NegativeX,
+        Face::PY => CubeFace::PositiveY,
+        Face::NY => CubeFace::NegativeY,
+        Face::PZ => CubeFace::PositiveZ,
+        Face::NZ => CubeFace::NegativeZ,
+        Face::Within => unreachable!("Face::Within is not a cube map face"),
+    }
+}
+
+#[derive(Debug, UniformInterface)]
+pub(crate) struct SkyboxUniformInterface {
+    inverse_view_projection: Uniform<[[f32; 4]; 4]>,
+    skybox_texture: Uniform<TextureBinding<Cubemap, NormUnsigned>>,
+}
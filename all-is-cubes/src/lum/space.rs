@@ -3,8 +3,10 @@
 
 //! Get from [`Space`] to [`Tess`].
 
-use bitvec::prelude::BitVec;
-use cgmath::{EuclideanSpace as _, Matrix4, Point3, Transform as _, Vector3, Zero as _};
+use cgmath::{
+    EuclideanSpace as _, Matrix4, Point3, SquareMatrix as _, Transform as _, Vector3, Vector4,
+    Zero as _,
+};
 use instant::Instant;
 use luminance::tess::View as _;
 use luminance_front::blending::{Blending, Equation, Factor};
@@ -20,51 +22,89 @@ use luminance_front::tess_gate::TessGate;
 use luminance_front::texture::{Dim3, GenMipmaps, Sampler, Texture, TextureError};
 use luminance_front::Backend;
 use std::cmp::Ordering;
-use std::collections::{hash_map::Entry::*, HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::hash::{Hash as _, Hasher as _};
 use std::sync::{Arc, Mutex, Weak};
 
-use crate::camera::{Camera, GraphicsOptions};
+use crate::block::EvaluatedBlock;
+use crate::camera::{Camera, GraphicsOptions, RenderMethod};
 use crate::chunking::{cube_to_chunk, point_to_chunk, ChunkChart, ChunkPos};
 use crate::content::palette;
+use crate::entity::EntityShape;
 use crate::listen::Listener;
 use crate::lum::block_texture::{BlockTexture, BoundBlockTexture, LumAtlasAllocator, LumAtlasTile};
+use crate::lum::chunk_worker::{
+    ChunkInput, ChunkMeshJob, ChunkMeshResult, ChunkMeshWorker, SharedBlocks,
+};
 use crate::lum::shading::BlockPrograms;
-use crate::lum::types::LumBlockVertex;
+use crate::lum::types::{LumBlockVertex, LumCubeInstance, LumFaceVertex};
 use crate::lum::{wireframe_vertices, GraphicsResourceError};
-use crate::math::{Aab, FaceMap, FreeCoordinate, GridCoordinate, GridPoint, Rgb};
+use crate::math::{Aab, FaceMap, FreeCoordinate, GridCoordinate, GridPoint, GridVector, Rgb, Rgba};
 use crate::raycast::Face;
-use crate::space::{BlockIndex, Grid, Space, SpaceChange};
+use crate::space::{BlockIndex, Grid, Skybox, Space, SpaceChange, Topology};
+use crate::trace::trace_span;
 use crate::triangulator::{
-    triangulate_block, triangulate_blocks, BlockTriangulation, BlockTriangulationProvider,
-    DepthOrdering, SpaceTriangulation,
+    triangulate_billboard, triangulate_block, triangulate_blocks, BillboardTriangulation,
+    BlockTriangulation, DepthOrdering, SpaceTriangulation,
 };
-use crate::universe::URef;
+use crate::universe::ReadRef;
 use crate::util::{CustomFormat, StatusText};
 
 use super::block_texture::AtlasFlushInfo;
 
-const CHUNK_SIZE: GridCoordinate = 16;
+pub(super) const CHUNK_SIZE: GridCoordinate = 16;
+
+/// Number of frames for which a [`ChunkMeshCache`] entry is kept after it was last
+/// used, so that content which disappears and reappears (such as by undo, or by moving
+/// back and forth) need not be triangulated again.
+const MESH_CACHE_RETENTION_FRAMES: u64 = 300;
+
+/// Distance, in cubes, beyond the view distance out to which chunks are kept rather
+/// than discarded, so that moving back and forth across a chunk boundary does not
+/// cause the chunks at the edge of the view to be repeatedly rebuilt.
+const CHUNK_RETENTION_MARGIN: FreeCoordinate = CHUNK_SIZE as FreeCoordinate;
 
 /// Manages cached data and GPU resources for drawing a single [`Space`].
 pub struct SpaceRenderer {
-    space: URef<Space>,
+    space: ReadRef<Space>,
     todo: Arc<Mutex<SpaceRendererTodo>>,
     block_triangulations: Vec<BlockTriangulation<LumBlockVertex, LumAtlasTile>>,
     /// Version IDs used to track whether chunks have stale block triangulations.
     /// Indices are block indices and values are version numbers.
     block_versioning: Vec<u32>,
     block_version_counter: u32,
+    /// For each block index, the color to draw it with by instancing, if it is drawn
+    /// that way instead of being included in the chunk meshes.
+    instance_colors: Vec<Option<Rgba>>,
+    /// Value of [`GraphicsOptions::instance_whole_cubes`] that `block_triangulations`
+    /// and `instance_colors` were computed with.
+    instancing: bool,
     block_texture: Option<LumAtlasAllocator>,
     light_texture: Option<SpaceLightTexture>,
     /// Invariant: the set of present chunks (keys here) is the same as the set of keys
     /// in `todo.borrow().chunks`.
     chunks: HashMap<ChunkPos<CHUNK_SIZE>, Chunk>,
+    /// Triangulations of recently computed chunks, shared by chunks with identical
+    /// contents.
+    mesh_cache: ChunkMeshCache,
+    /// Triangulates chunks off the render thread.
+    chunk_worker: ChunkMeshWorker,
+    /// Copy of `block_triangulations` for `chunk_worker` to use, made when a chunk is
+    /// submitted after the blocks have changed.
+    shared_blocks: Option<Arc<SharedBlocks>>,
     chunk_chart: ChunkChart<CHUNK_SIZE>,
     debug_chunk_boxes_tess: Option<Tess<LumBlockVertex>>,
+    /// Triangulations of the entities drawn as billboards, which are recomputed every
+    /// frame since they face the camera. Kept to retain their texture tiles.
+    billboards: Vec<BillboardTriangulation<LumAtlasTile>>,
+    billboards_tess: Option<Tess<LumBlockVertex>>,
     /// Whether, on the previous frame, some chunks were unavailable.
     /// If so, then we prioritize adding new chunks over updating existing ones.
     chunks_were_missing: bool,
+    /// Number of frames prepared so far, used to track when chunks were last visible.
+    frame_number: u64,
 }
 
 impl SpaceRenderer {
@@ -73,12 +113,12 @@ impl SpaceRenderer {
     /// Note that the actual geometry for the [`Space`] will be computed over several
     /// frames after construction. There is not currently a specific way to wait for
     /// completion.
-    pub fn new(space: URef<Space>) -> Self {
-        let space_borrowed = space.borrow_mut();
+    pub fn new(space: ReadRef<Space>) -> Self {
+        let space_borrowed = space.borrow();
 
         let todo = SpaceRendererTodo::default();
         let todo_rc = Arc::new(Mutex::new(todo));
-        space_borrowed.listen(TodoListener(Arc::downgrade(&todo_rc)));
+        space_borrowed.listen_batched(TodoListener(Arc::downgrade(&todo_rc)));
 
         Self {
             space,
@@ -86,17 +126,25 @@ impl SpaceRenderer {
             block_triangulations: Vec::new(),
             block_versioning: Vec::new(),
             block_version_counter: 0,
+            instance_colors: Vec::new(),
+            instancing: false,
             block_texture: None,
             light_texture: None,
             chunks: HashMap::new(),
+            mesh_cache: ChunkMeshCache::default(),
+            chunk_worker: ChunkMeshWorker::new(),
+            shared_blocks: None,
             chunk_chart: ChunkChart::new(0.0),
             debug_chunk_boxes_tess: None,
+            billboards: Vec::new(),
+            billboards_tess: None,
             chunks_were_missing: true,
+            frame_number: 0,
         }
     }
 
     /// Get the reference to the [`Space`] this draws.
-    pub fn space(&self) -> &URef<Space> {
+    pub fn space(&self) -> &ReadRef<Space> {
         &self.space
     }
 
@@ -124,12 +172,21 @@ impl SpaceRenderer {
         }
         let block_texture_allocator = self.block_texture.as_mut().unwrap();
 
-        if self.light_texture.is_none() {
-            todo.light = None; // signal to update everything
-            self.light_texture = Some(SpaceLightTexture::new(context, space.grid())?);
+        let topology = space.physics().topology;
+        if self
+            .light_texture
+            .as_ref()
+            .map_or(true, |t| t.grid != space.grid() || t.topology != topology)
+        {
+            self.light_texture = Some(SpaceLightTexture::new(context, space.grid(), topology)?);
         }
         let light_texture = self.light_texture.as_mut().unwrap();
 
+        if graphics_options.instance_whole_cubes != self.instancing {
+            self.instancing = graphics_options.instance_whole_cubes;
+            todo.all_blocks_and_chunks = true;
+        }
+
         if todo.all_blocks_and_chunks {
             todo.all_blocks_and_chunks = false;
             self.block_triangulations.clear();
@@ -151,6 +208,20 @@ impl SpaceRenderer {
                 block_texture_allocator,
                 &graphics_options.transparency,
             ));
+            self.instance_colors = space
+                .block_data()
+                .iter()
+                .map(|data| instance_color(data.evaluated(), graphics_options))
+                .collect();
+            for (triangulation, color) in self
+                .block_triangulations
+                .iter_mut()
+                .zip(self.instance_colors.iter())
+            {
+                if color.is_some() {
+                    *triangulation = triangulation.hollow();
+                }
+            }
             self.block_versioning =
                 vec![self.block_version_counter; self.block_triangulations.len()];
             block_update_count = self.block_triangulations.len();
@@ -173,11 +244,13 @@ impl SpaceRenderer {
                 Ordering::Less => {
                     self.block_triangulations.truncate(new_length);
                     self.block_versioning.truncate(new_length);
+                    self.instance_colors.truncate(new_length);
                 }
                 Ordering::Greater => {
                     let added = old_length..new_length;
                     self.block_triangulations
                         .extend(added.clone().map(|_| BlockTriangulation::default()));
+                    self.instance_colors.extend(added.clone().map(|_| None));
                     self.block_versioning.extend(added.map(|_| 0));
                 }
                 Ordering::Equal => {}
@@ -186,11 +259,16 @@ impl SpaceRenderer {
 
             for index in todo.blocks.drain() {
                 let index: usize = index.into();
-                let new_triangulation = triangulate_block(
-                    block_data[index].evaluated(),
+                let evaluated = block_data[index].evaluated();
+                let new_color = instance_color(evaluated, graphics_options);
+                let mut new_triangulation = triangulate_block(
+                    evaluated,
                     block_texture_allocator,
                     &graphics_options.transparency,
                 );
+                if new_color.is_some() {
+                    new_triangulation = new_triangulation.hollow();
+                }
 
                 // Only invalidate the chunks if we actually have different data.
                 // Note: This comparison depends on such things as the definition of PartialEq
@@ -199,8 +277,11 @@ impl SpaceRenderer {
                 // never reuses textures. (If it did, we'd need to consider what we want to do
                 // about stale chunks with fresh textures, which might have geometry gaps or
                 // otherwise be obviously inconsistent.)
-                if new_triangulation != self.block_triangulations[index] {
+                if new_triangulation != self.block_triangulations[index]
+                    || new_color != self.instance_colors[index]
+                {
                     self.block_triangulations[index] = new_triangulation;
+                    self.instance_colors[index] = new_color;
                     self.block_versioning[index] = self.block_version_counter;
                 } else {
                     // The new triangulation is identical to the old one (which might happen because
@@ -212,68 +293,158 @@ impl SpaceRenderer {
             }
         }
 
-        let texture_info = block_texture_allocator.flush()?;
+        // Triangulate billboards before flushing, so that their textures are uploaded.
+        let facing = billboard_facing(camera);
+        let mut billboard_vertices: Vec<LumBlockVertex> = Vec::new();
+        self.billboards.clear();
+        for (_, entity) in space.entities().iter() {
+            if entity.shape() != EntityShape::Billboard || entity.model().is_some() {
+                continue;
+            }
+            if let Some(block) = entity.appearance() {
+                let evaluated = block
+                    .evaluate()
+                    .unwrap_or_else(|error| error.to_placeholder());
+                let billboard = triangulate_billboard(
+                    &evaluated,
+                    block_texture_allocator,
+                    &graphics_options.transparency,
+                );
+                billboard_vertices
+                    .extend(billboard.instantiate(entity.billboard_transform(facing)));
+                self.billboards.push(billboard);
+            }
+        }
+
+        let texture_info = block_texture_allocator.flush(context)?;
+        if texture_info.tiles_moved {
+            // The texture coordinates in all our triangulations are now wrong; start over.
+            // (Until the chunks are recomputed, they may be drawn with wrong textures.)
+            todo.all_blocks_and_chunks = true;
+        }
 
         // Update light texture
-        if let Some(set) = &mut todo.light {
-            // TODO: work in larger, ahem, chunks
-            for cube in set.drain() {
-                light_texture.update(space, Grid::new(cube, [1, 1, 1]))?;
-            }
-        } else {
+        if todo.all_light {
+            todo.all_light = false;
             light_texture.update_all(space)?;
-            todo.light = Some(HashSet::new());
+        } else {
+            light_texture.update_changed(space)?;
         }
 
         let view_point = camera.view_position();
         let view_chunk = point_to_chunk(view_point);
         self.chunk_chart.resize_if_needed(camera.view_distance());
 
+        self.frame_number += 1;
+        let frame_number = self.frame_number;
+        let in_view = |p: ChunkPos<CHUNK_SIZE>| {
+            !graphics_options.use_frustum_culling || camera.aab_in_view(p.grid().into())
+        };
+
         // Update some chunk geometry.
+        self.mesh_cache.next_frame();
         let chunk_grid = space.grid().divide(CHUNK_SIZE);
+        let chunks_per_frame: usize = graphics_options.chunks_per_frame.into();
         let mut chunk_update_count = 0;
+        let mut chunk_backlog = 0;
         let mut chunks_are_missing = false;
-        for p in self.chunk_chart.chunks(view_chunk) {
-            if !chunk_grid.contains_cube(p.0) {
-                // Chunk not in the Space
+
+        // Swap in the chunk meshes which the worker has finished.
+        for result in self.chunk_worker.take_results() {
+            let chunk = match self.chunks.get_mut(&result.position) {
+                Some(chunk) => chunk,
+                // The chunk was discarded while it was being triangulated.
+                None => continue,
+            };
+            let stale = result.block_dependencies.iter().any(|&(index, version)| {
+                self.block_versioning.get(usize::from(index)) != Some(&version)
+            });
+            if stale {
+                // The blocks changed while it was being triangulated, so its vertices may
+                // refer to texture tiles that no longer exist. Do it again.
+                if let Some(chunk_todo) = todo.chunks.get_mut(&result.position) {
+                    chunk_todo.update_triangulation = true;
+                }
                 continue;
             }
+            chunk.apply_result(
+                context,
+                result,
+                space,
+                &self.block_triangulations,
+                &self.instance_colors,
+                &mut self.mesh_cache,
+            );
+            chunk_update_count += 1;
+        }
 
-            // TODO: tune max update count dynamically?
-            if chunk_update_count >= graphics_options.chunks_per_frame.into() {
-                break;
+        // The chunks won't be drawn unless we're using meshes, so don't spend time on them.
+        if graphics_options.render_method == RenderMethod::Mesh {
+            // Find the chunks needing work, nearest first.
+            let mut wanted: Vec<ChunkPos<CHUNK_SIZE>> = self
+                .chunk_chart
+                .chunks(view_chunk)
+                .filter(|p| chunk_grid.contains_cube(p.0))
+                .filter(|p| !self.chunk_worker.is_in_flight(*p))
+                .filter(|p| match self.chunks.get(p) {
+                    None => true,
+                    // Evicted chunks are only worth recomputing if they will be seen.
+                    Some(chunk) if chunk.evicted => in_view(*p),
+                    Some(chunk) => {
+                        (todo
+                            .chunks
+                            .get(p)
+                            .map(|ct| ct.update_triangulation)
+                            .unwrap_or(false)
+                            && !self.chunks_were_missing)
+                            || chunk.stale_blocks(&self.block_versioning)
+                    }
+                })
+                .collect();
+            // Then do the ones the camera can see before the ones it can't, so that
+            // turning toward an unmeshed area fills it in as soon as possible.
+            // (This is a stable sort, so distance order is otherwise preserved.)
+            if graphics_options.use_frustum_culling {
+                wanted.sort_by_key(|&p| !in_view(p));
             }
 
-            let chunk_entry = self.chunks.entry(p);
-            // If the chunk needs updating or never existed, update it.
-            if (todo
-                .chunks
-                .get(&p)
-                .map(|ct| ct.update_triangulation)
-                .unwrap_or(false)
-                && !self.chunks_were_missing)
-                || matches!(chunk_entry, Vacant(_))
-                || matches!(chunk_entry, Occupied(ref oe) if oe.get().stale_blocks(&self.block_versioning))
-            {
-                chunk_entry
-                    .or_insert_with(|| {
-                        // Chunk is missing. Note this for update planning.
-                        chunks_are_missing = true;
-                        // Remember that we want to track dirty flags.
-                        todo.chunks.insert(p, ChunkTodo::CLEAN);
-                        // Generate new chunk.
-                        Chunk::new(p)
-                    })
-                    .update(
-                        context,
-                        todo.chunks.get_mut(&p).unwrap(), // TODO: can we eliminate the double lookup with a todo entry?
-                        &space,
-                        graphics_options,
-                        &self.block_triangulations,
-                        &self.block_versioning,
-                    );
-                chunk_update_count += 1;
+            // Submit chunks to the worker until it has `chunks_per_frame` of them, so that
+            // it never has more work queued than we can upload in one frame.
+            // TODO: tune max update count dynamically?
+            let available = chunks_per_frame.saturating_sub(self.chunk_worker.in_flight_count());
+            chunk_backlog = wanted.len().saturating_sub(available);
+            for p in wanted.into_iter().take(available) {
+                let chunk = self.chunks.entry(p).or_insert_with(|| {
+                    // Chunk is missing. Note this for update planning.
+                    chunks_are_missing = true;
+                    // Remember that we want to track dirty flags.
+                    todo.chunks.insert(p, ChunkTodo::CLEAN);
+                    // Generate new chunk.
+                    Chunk::new(p)
+                });
+                // TODO: can we eliminate the double lookup with a todo entry?
+                todo.chunks.get_mut(&p).unwrap().update_triangulation = false;
+
+                let content_hash = chunk_content_hash(space, chunk.bounds, &self.block_versioning);
+                if let Some(cached) = self.mesh_cache.get(content_hash) {
+                    chunk.apply_cached(context, cached, space, &self.instance_colors);
+                    chunk_update_count += 1;
+                } else {
+                    self.chunk_worker.submit(ChunkMeshJob {
+                        position: p,
+                        content_hash,
+                        input: ChunkInput::copy_from(space, chunk.bounds),
+                        blocks: SharedBlocks::current(
+                            &mut self.shared_blocks,
+                            self.block_version_counter,
+                            &self.block_triangulations,
+                            &self.block_versioning,
+                        ),
+                        options: graphics_options.clone(),
+                    });
+                }
             }
+            chunk_backlog += self.chunk_worker.in_flight_count();
         }
         self.chunks_were_missing = chunks_are_missing;
 
@@ -281,7 +452,71 @@ impl SpaceRenderer {
             chunk.depth_sort_for_view(view_point);
         }
 
-        // TODO: flush todo.chunks and self.chunks of out-of-range chunks.
+        for p in self.chunk_chart.chunks(view_chunk) {
+            if let Some(chunk) = self.chunks.get_mut(&p) {
+                if in_view(p) {
+                    chunk.last_visible = frame_number;
+                }
+            }
+        }
+
+        // Discard chunks which have gone out of range.
+        let retention_distance = camera.view_distance() + CHUNK_RETENTION_MARGIN;
+        let todo_chunks = &mut todo.chunks;
+        self.chunks.retain(|&p, _| {
+            let keep = ChunkChart::is_within_distance(view_chunk, p, retention_distance);
+            if !keep {
+                todo_chunks.remove(&p);
+            }
+            keep
+        });
+
+        // Stay within the memory budget by discarding what is least likely to be needed.
+        let mut chunk_mesh_bytes: usize = self.chunks.values().map(Chunk::gpu_bytes).sum();
+        let mut chunks_evicted = 0;
+        if let Some(budget) = graphics_options.gpu_memory_budget {
+            if chunk_mesh_bytes + block_texture_allocator.bytes_in_use() > budget {
+                // Cached meshes and the triangulations of blocks that are no longer
+                // in the space hold texture tiles which nothing visible is using.
+                self.mesh_cache.clear();
+                for (triangulation, data) in self
+                    .block_triangulations
+                    .iter_mut()
+                    .zip(space.block_data().iter())
+                {
+                    if data.count() == 0 {
+                        // If the index is reused, we will be notified and recompute it.
+                        *triangulation = BlockTriangulation::default();
+                    }
+                }
+
+                let total = chunk_mesh_bytes + block_texture_allocator.bytes_in_use();
+                let candidates = self
+                    .chunks
+                    .iter()
+                    .map(|(&p, chunk)| (p, chunk.last_visible, chunk.gpu_bytes()))
+                    .collect();
+                for p in chunks_to_evict(candidates, frame_number, total.saturating_sub(budget)) {
+                    let chunk = self.chunks.get_mut(&p).unwrap();
+                    chunk_mesh_bytes -= chunk.gpu_bytes();
+                    chunk.evict();
+                    chunks_evicted += 1;
+                }
+            }
+        }
+        let texture_bytes = block_texture_allocator.bytes_in_use();
+
+        self.billboards_tess = if billboard_vertices.is_empty() {
+            None
+        } else {
+            Some(
+                context
+                    .new_tess()
+                    .set_vertices(billboard_vertices)
+                    .set_mode(Mode::Triangle)
+                    .build()?,
+            )
+        };
 
         if graphics_options.debug_chunk_boxes {
             if self.debug_chunk_boxes_tess.is_none() {
@@ -331,15 +566,22 @@ impl SpaceRenderer {
                 chunks: &self.chunks, // TODO visibility culling, and don't allocate every frame
                 chunk_chart: &self.chunk_chart,
                 debug_chunk_boxes_tess: &self.debug_chunk_boxes_tess,
+                billboards_tess: &self.billboards_tess,
                 view_chunk,
                 info: SpaceRenderInfo {
                     chunk_update_count,
+                    chunk_cache_hits: self.mesh_cache.hits,
+                    chunk_backlog,
                     block_update_count,
+                    chunk_mesh_bytes,
+                    texture_bytes,
+                    chunks_evicted,
                     chunks_drawn: 0,
                     squares_drawn: 0, // filled later
                     texture_info,
                 },
                 sky_color: space.physics().sky_color,
+                skybox: space.physics().skybox.clone(),
             },
             block_texture: &mut block_texture_allocator.texture,
             light_texture,
@@ -362,11 +604,14 @@ pub(super) struct SpaceRendererOutputData<'a> {
     chunks: &'a HashMap<ChunkPos<CHUNK_SIZE>, Chunk>,
     chunk_chart: &'a ChunkChart<CHUNK_SIZE>,
     debug_chunk_boxes_tess: &'a Option<Tess<LumBlockVertex>>,
+    billboards_tess: &'a Option<Tess<LumBlockVertex>>,
     view_chunk: ChunkPos<CHUNK_SIZE>,
     info: SpaceRenderInfo,
 
     /// Space's sky color, to be used as background color (clear color / fog).
     pub(super) sky_color: Rgb,
+    /// Space's skybox, to be drawn behind everything if present.
+    pub(super) skybox: Option<Skybox>,
 }
 
 /// As [`SpaceRendererOutput`], but past the texture-binding stage of the pipeline.
@@ -425,6 +670,9 @@ impl<'a> SpaceRendererBound<'a> {
                         }
                         // TODO: If the chunk is missing, draw a blocking shape, possibly?
                     }
+                    if let Some(billboards_tess) = self.data.billboards_tess {
+                        tess_gate.render(billboards_tess)?;
+                    }
                     Ok(())
                 })?;
 
@@ -448,6 +696,27 @@ impl<'a> SpaceRendererBound<'a> {
             },
         )?;
 
+        if self.data.camera.options().instance_whole_cubes {
+            shading_gate.shade(
+                &mut block_programs.instanced,
+                |ref mut program_iface, u, mut render_gate| {
+                    u.initialize(program_iface, self);
+                    let pass = SpaceRendererPass::Opaque;
+                    render_gate.render(&pass.render_state(), |mut tess_gate| {
+                        for p in self.data.chunk_chart.chunks(self.data.view_chunk) {
+                            if let Some(chunk) = self.data.chunks.get(&p) {
+                                if self.data.cull(p) {
+                                    continue;
+                                }
+                                squares_drawn += chunk.render_instances(&mut tess_gate)?;
+                            }
+                        }
+                        Ok(())
+                    })
+                },
+            )?;
+        }
+
         if self.data.camera.options().transparency.will_output_alpha() {
             shading_gate.shade(
                 &mut block_programs.transparent,
@@ -484,13 +753,39 @@ impl<'a> SpaceRendererBound<'a> {
     }
 }
 
+/// Returns the rotation which makes billboards drawn with
+/// [`Entity::billboard_transform`](crate::entity::Entity::billboard_transform) face
+/// the camera: the inverse of the rotation part of the view matrix.
+fn billboard_facing(camera: &Camera) -> Matrix4<FreeCoordinate> {
+    let mut facing = camera
+        .view_matrix()
+        .invert()
+        .unwrap_or_else(Matrix4::identity);
+    facing.w = Vector4::unit_w();
+    facing
+}
+
 /// Performance info from a [`SpaceRenderer`] drawing one frame.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct SpaceRenderInfo {
-    /// How many chunks were recomputed this frame.
+    /// How many chunk meshes were replaced with recomputed ones this frame.
     pub chunk_update_count: usize,
+    /// How many of the recomputed chunks were copied from a chunk with identical
+    /// contents instead of being triangulated.
+    pub chunk_cache_hits: usize,
+    /// How many chunks need to be recomputed and have not been yet: those being
+    /// triangulated on the background thread, and those waiting for it to be free, as
+    /// limited by [`GraphicsOptions::chunks_per_frame`].
+    pub chunk_backlog: usize,
     /// How many block triangulations were recomputed this time.
     pub block_update_count: usize,
+    /// Approximate GPU memory used by chunk meshes, in bytes.
+    pub chunk_mesh_bytes: usize,
+    /// GPU memory used by allocated block texture tiles, in bytes.
+    pub texture_bytes: usize,
+    /// How many chunk meshes were discarded this time to stay within
+    /// [`GraphicsOptions::gpu_memory_budget`].
+    pub chunks_evicted: usize,
     pub chunks_drawn: usize,
     /// How many squares (quadrilaterals; sets of 2 triangles = 6 vertices) were used
     /// to draw this frame.
@@ -503,8 +798,18 @@ impl CustomFormat<StatusText> for SpaceRenderInfo {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>, _: StatusText) -> fmt::Result {
         writeln!(
             fmt,
-            "Chunk updates: {:3} Block updates: {:3}",
-            self.chunk_update_count, self.block_update_count,
+            "Chunk updates: {:3} ({:3} cached, {:3} waiting) Block updates: {:3}",
+            self.chunk_update_count,
+            self.chunk_cache_hits,
+            self.chunk_backlog,
+            self.block_update_count,
+        )?;
+        writeln!(
+            fmt,
+            "GPU memory: meshes {:6} KiB, textures {:6} KiB, {:3} chunks evicted",
+            self.chunk_mesh_bytes / 1024,
+            self.texture_bytes / 1024,
+            self.chunks_evicted,
         )?;
         writeln!(
             fmt,
@@ -550,9 +855,17 @@ struct Chunk {
     bounds: Grid,
     triangulation: SpaceTriangulation<LumBlockVertex>,
     tess: Option<Tess<LumBlockVertex, u32>>,
+    /// One [`Tess`] per visible face direction of the cubes drawn by instancing.
+    instance_tesses: Vec<Tess<LumFaceVertex, u32, LumCubeInstance>>,
+    /// Total number of instances in `instance_tesses`.
+    instance_count: usize,
     /// Texture tiles that our vertices' texture coordinates refer to.
     tile_dependencies: Vec<LumAtlasTile>,
     block_dependencies: Vec<(BlockIndex, u32)>,
+    /// Value of [`SpaceRenderer::frame_number`] when this chunk was last in view.
+    last_visible: u64,
+    /// Whether the mesh was discarded by [`Chunk::evict`] and not yet recomputed.
+    evicted: bool,
 }
 
 impl Chunk {
@@ -561,11 +874,38 @@ impl Chunk {
             bounds: chunk_pos.grid(),
             triangulation: SpaceTriangulation::new(),
             tess: None,
+            instance_tesses: Vec::new(),
+            instance_count: 0,
             tile_dependencies: Vec::new(),
             block_dependencies: Vec::new(),
+            last_visible: 0,
+            evicted: false,
         }
     }
 
+    /// Approximate size of this chunk's GPU buffers.
+    fn gpu_bytes(&self) -> usize {
+        let mesh_bytes = if self.tess.is_some() {
+            self.triangulation.vertices().len() * std::mem::size_of::<LumBlockVertex>()
+                + self.triangulation.indices().len() * std::mem::size_of::<u32>()
+        } else {
+            0
+        };
+        mesh_bytes + self.instance_count * std::mem::size_of::<LumCubeInstance>()
+    }
+
+    /// Discards the mesh and everything it refers to, leaving the chunk empty until it
+    /// is next updated.
+    fn evict(&mut self) {
+        self.triangulation = SpaceTriangulation::new();
+        self.tess = None;
+        self.instance_tesses = Vec::new();
+        self.instance_count = 0;
+        self.tile_dependencies = Vec::new();
+        self.block_dependencies = Vec::new();
+        self.evicted = true;
+    }
+
     fn stale_blocks(&self, versions: &[u32]) -> bool {
         self.block_dependencies
             .iter()
@@ -573,39 +913,77 @@ impl Chunk {
             .any(|(index, version)| versions[usize::from(index)] != version)
     }
 
-    fn update<C: GraphicsContext<Backend = Backend>>(
+    /// Replaces this chunk's mesh with a copy of `cached`.
+    fn apply_cached<C: GraphicsContext<Backend = Backend>>(
         &mut self,
         context: &mut C,
-        chunk_todo: &mut ChunkTodo,
+        cached: &CachedChunkMesh,
         space: &Space,
-        options: &GraphicsOptions,
-        block_triangulations: &[BlockTriangulation<LumBlockVertex, LumAtlasTile>],
-        block_versioning: &[u32],
+        instance_colors: &[Option<Rgba>],
     ) {
-        let mut block_provider = TrackingBlockProvider::new(block_triangulations);
-
         let old_indices_len = self.triangulation.indices().len();
+        self.triangulation.clone_from(&cached.triangulation);
+        let offset = self.bounds.lower_bounds() - cached.origin;
+        if offset != GridVector::zero() {
+            self.triangulation
+                .map_vertices_in_place(|vertex| vertex.translate(offset));
+        }
+        self.tile_dependencies.clone_from(&cached.tile_dependencies);
+        self.block_dependencies
+            .clone_from(&cached.block_dependencies);
+        self.upload(context, old_indices_len, space, instance_colors);
+    }
 
-        self.triangulation
-            .compute(space, self.bounds, options, &mut block_provider);
+    /// Replaces this chunk's mesh with one triangulated by the [`ChunkMeshWorker`],
+    /// whose block dependencies must be current, and adds it to `mesh_cache`.
+    fn apply_result<C: GraphicsContext<Backend = Backend>>(
+        &mut self,
+        context: &mut C,
+        result: ChunkMeshResult,
+        space: &Space,
+        block_triangulations: &[BlockTriangulation<LumBlockVertex, LumAtlasTile>],
+        instance_colors: &[Option<Rgba>],
+        mesh_cache: &mut ChunkMeshCache,
+    ) {
+        let old_indices_len = self.triangulation.indices().len();
+        self.triangulation = result.triangulation;
 
         // Stash all the texture tiles so they aren't deallocated out from under us.
         // TODO: Maybe we should have something more like a Vec<Rc<BlockTriangulation>>
         self.tile_dependencies.clear();
         self.tile_dependencies.extend(
-            block_provider
-                .seen()
-                .flat_map(|index| block_triangulations[index].textures().iter())
+            result
+                .block_dependencies
+                .iter()
+                .flat_map(|&(index, _)| block_triangulations[usize::from(index)].textures())
                 .cloned(),
         );
         // Record the block triangulations we used.
-        self.block_dependencies.clear();
-        self.block_dependencies.extend(
-            block_provider
-                .seen()
-                .map(|index| (index as BlockIndex, block_versioning[index])),
+        self.block_dependencies = result.block_dependencies;
+
+        mesh_cache.insert(
+            result.content_hash,
+            CachedChunkMesh {
+                origin: self.bounds.lower_bounds(),
+                triangulation: self.triangulation.clone(),
+                tile_dependencies: self.tile_dependencies.clone(),
+                block_dependencies: self.block_dependencies.clone(),
+                last_used: 0, // set by insert()
+            },
         );
 
+        self.upload(context, old_indices_len, space, instance_colors);
+    }
+
+    /// Copies `self.triangulation` to the GPU and rebuilds the instances to match it.
+    fn upload<C: GraphicsContext<Backend = Backend>>(
+        &mut self,
+        context: &mut C,
+        old_indices_len: usize,
+        space: &Space,
+        instance_colors: &[Option<Rgba>],
+    ) {
+        let _span = trace_span!("chunk_update");
         let tess_option = &mut self.tess;
         let new_triangulation = &self.triangulation;
 
@@ -652,7 +1030,53 @@ impl Chunk {
             );
         }
 
-        chunk_todo.update_triangulation = false;
+        self.update_instances(context, space, instance_colors);
+
+        self.evicted = false;
+    }
+
+    /// Rebuilds `instance_tesses` from the cubes in this chunk whose blocks have an
+    /// entry in `instance_colors`, omitting faces that are hidden by an opaque neighbor.
+    fn update_instances<C: GraphicsContext<Backend = Backend>>(
+        &mut self,
+        context: &mut C,
+        space: &Space,
+        instance_colors: &[Option<Rgba>],
+    ) {
+        let mut instances: FaceMap<Vec<LumCubeInstance>> = FaceMap::from_fn(|_| Vec::new());
+        for cube in self.bounds.interior_iter() {
+            let color = space
+                .get_block_index(cube)
+                .and_then(|index| instance_colors.get(usize::from(index)).copied())
+                .flatten();
+            if let Some(color) = color {
+                for &face in Face::ALL_SIX {
+                    if !space.get_evaluated(cube + face.normal_vector()).opaque {
+                        instances[face].push(LumCubeInstance::new(cube, color));
+                    }
+                }
+            }
+        }
+
+        self.instance_count = 0;
+        self.instance_tesses.clear();
+        for (face, face_instances) in instances.iter() {
+            if face_instances.is_empty() {
+                continue;
+            }
+            self.instance_count += face_instances.len();
+            let (vertices, indices) = LumFaceVertex::unit_face(face);
+            self.instance_tesses.push(
+                context
+                    .new_tess()
+                    .set_vertices(&vertices[..])
+                    .set_indices(&indices[..])
+                    .set_instances(face_instances.clone())
+                    .set_mode(Mode::Triangle)
+                    .build()
+                    .unwrap(),
+            );
+        }
     }
 
     fn depth_sort_for_view(&mut self, view_position: Point3<FreeCoordinate>) {
@@ -697,42 +1121,14 @@ impl Chunk {
         }
         Ok(count)
     }
-}
-
-/// Helper for [`Chunk`]'s dependency tracking.
-struct TrackingBlockProvider<'a> {
-    block_triangulations: &'a [BlockTriangulation<LumBlockVertex, LumAtlasTile>],
-    seen: BitVec,
-}
-impl<'a> TrackingBlockProvider<'a> {
-    fn new(block_triangulations: &'a [BlockTriangulation<LumBlockVertex, LumAtlasTile>]) -> Self {
-        Self {
-            block_triangulations,
-            seen: BitVec::with_capacity(256), // TODO: cleverer choice
-        }
-    }
 
-    /// Return the indices of all the block triangulations that were used.
-    ///
-    /// Note: In principle, the value type should be [`BlockIndex`], but in practice it
-    /// is used as an array index so this avoids writing a double conversion.
-    fn seen<'s: 'a>(&'s self) -> impl Iterator<Item = usize> + 's {
-        self.seen.iter_ones()
-    }
-}
-impl<'a> BlockTriangulationProvider<'a, LumBlockVertex, LumAtlasTile>
-    for &mut TrackingBlockProvider<'a>
-{
-    fn get(
-        &mut self,
-        index: BlockIndex,
-    ) -> Option<&'a BlockTriangulation<LumBlockVertex, LumAtlasTile>> {
-        let index = usize::from(index);
-        if index >= self.seen.len() {
-            self.seen.resize(index + 1, false);
+    /// Draws the cubes in this chunk that are drawn by instancing rather than as part
+    /// of the mesh. Returns the number of squares drawn.
+    fn render_instances<E>(&self, tess_gate: &mut TessGate<'_>) -> Result<usize, E> {
+        for tess in &self.instance_tesses {
+            tess_gate.render(tess)?;
         }
-        self.seen.set(index, true);
-        self.block_triangulations.get(index)
+        Ok(self.instance_count)
     }
 }
 
@@ -745,11 +1141,9 @@ struct SpaceRendererTodo {
     /// outside of the view area are not tracked.
     chunks: HashMap<ChunkPos<CHUNK_SIZE>, ChunkTodo>,
 
-    /// Blocks whose light texels should be updated.
-    /// None means do a full space reupload.
-    ///
-    /// TODO: experiment with different granularities of light invalidation (chunks, dirty rects, etc.)
-    light: Option<HashSet<GridPoint>>,
+    /// Whether to reupload all light texels, rather than only those in chunks that
+    /// [`Space::light_changed_since`] reports.
+    all_light: bool,
 }
 
 impl SpaceRendererTodo {
@@ -771,6 +1165,124 @@ impl SpaceRendererTodo {
             }
         }
     }
+
+    /// As [`Self::modify_block_and_adjacent`] for every cube in `region`, but visiting
+    /// each chunk only once.
+    fn modify_region_and_adjacent<F>(&mut self, region: Grid, mut f: F)
+    where
+        F: FnMut(&mut ChunkTodo),
+    {
+        let lower = cube_to_chunk::<CHUNK_SIZE>(region.lower_bounds() - Vector3::new(1, 1, 1)).0;
+        // upper_bounds() is exclusive, so it is the farthest adjacent cube.
+        let upper = cube_to_chunk::<CHUNK_SIZE>(region.upper_bounds()).0;
+        for chunk in Grid::from_lower_upper(lower, upper + Vector3::new(1, 1, 1)).interior_iter() {
+            if let Some(chunk_todo) = self.chunks.get_mut(&ChunkPos(chunk)) {
+                f(chunk_todo);
+            }
+        }
+    }
+}
+
+/// Returns the color to draw `block` with by instancing, or [`None`] if it should be
+/// triangulated normally. Only blocks which are plain opaque cubes qualify.
+fn instance_color(block: &EvaluatedBlock, options: &GraphicsOptions) -> Option<Rgba> {
+    if options.instance_whole_cubes && block.voxels.is_none() && block.color.fully_opaque() {
+        Some(block.color)
+    } else {
+        None
+    }
+}
+
+/// Computes a hash of everything that affects the triangulation of the chunk occupying
+/// `bounds`: the blocks in it and its neighbors, and the versions of their
+/// triangulations.
+///
+/// Since this hash is used to share triangulations between chunks, a collision would
+/// cause incorrect rendering, but with 64 bits this is unlikely enough to ignore.
+fn chunk_content_hash(space: &Space, bounds: Grid, block_versioning: &[u32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    <[GridCoordinate; 3]>::from(bounds.size()).hash(&mut hasher);
+    for cube in bounds.expand(FaceMap::repeat(1)).interior_iter() {
+        space
+            .get_block_index(cube)
+            .map(|index| (index, block_versioning.get(usize::from(index))))
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Triangulations of chunks, keyed by [`chunk_content_hash`], for reuse by any chunk
+/// with the same contents.
+#[derive(Debug, Default)]
+struct ChunkMeshCache {
+    entries: HashMap<u64, CachedChunkMesh>,
+    /// Frame counter used to expire entries.
+    frame: u64,
+    /// Number of successful [`ChunkMeshCache::get`]s in the current frame.
+    hits: usize,
+}
+
+#[derive(Debug)]
+struct CachedChunkMesh {
+    /// Lower bounds of the chunk the triangulation was computed for, which its vertex
+    /// positions are relative to.
+    origin: GridPoint,
+    triangulation: SpaceTriangulation<LumBlockVertex>,
+    tile_dependencies: Vec<LumAtlasTile>,
+    block_dependencies: Vec<(BlockIndex, u32)>,
+    /// Value of [`ChunkMeshCache::frame`] when this was last used.
+    last_used: u64,
+}
+
+impl ChunkMeshCache {
+    /// Advances the frame counter and discards entries not recently used.
+    fn next_frame(&mut self) {
+        self.frame += 1;
+        self.hits = 0;
+        let frame = self.frame;
+        self.entries
+            .retain(|_, entry| frame - entry.last_used <= MESH_CACHE_RETENTION_FRAMES);
+    }
+
+    fn get(&mut self, content_hash: u64) -> Option<&CachedChunkMesh> {
+        let frame = self.frame;
+        let entry = self.entries.get_mut(&content_hash)?;
+        entry.last_used = frame;
+        self.hits += 1;
+        Some(entry)
+    }
+
+    fn insert(&mut self, content_hash: u64, mut entry: CachedChunkMesh) {
+        entry.last_used = self.frame;
+        self.entries.insert(content_hash, entry);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Chooses which chunks to evict to free at least `excess` bytes, given their positions,
+/// the frame each was last visible, and their sizes. Chunks visible in `frame` are never
+/// chosen; otherwise, those out of view the longest go first.
+fn chunks_to_evict(
+    mut candidates: Vec<(ChunkPos<CHUNK_SIZE>, u64, usize)>,
+    frame: u64,
+    excess: usize,
+) -> Vec<ChunkPos<CHUNK_SIZE>> {
+    candidates.retain(|&(_, last_visible, bytes)| last_visible < frame && bytes > 0);
+    // Sort by position too, so that the choice does not depend on HashMap order.
+    candidates.sort_by_key(|&(p, last_visible, _)| (last_visible, p.0.x, p.0.y, p.0.z));
+    let mut freed = 0;
+    candidates
+        .into_iter()
+        .take_while(|&(_, _, bytes)| {
+            let more_needed = freed < excess;
+            freed += bytes;
+            more_needed
+        })
+        .map(|(p, _, _)| p)
+        .collect()
 }
 
 /// What might be dirty about a single chunk.
@@ -797,18 +1309,20 @@ impl Listener<SpaceChange> for TodoListener {
                         todo.all_blocks_and_chunks = true;
                         todo.blocks.clear();
                         todo.chunks.clear();
-                        todo.light = None;
+                        todo.all_light = true;
                     }
                     SpaceChange::Block(p) => {
                         todo.modify_block_and_adjacent(p, |chunk_todo| {
                             chunk_todo.update_triangulation = true;
                         });
                     }
-                    SpaceChange::Lighting(p) => {
-                        // None means everything
-                        if let Some(set) = &mut todo.light {
-                            set.insert(p);
-                        }
+                    SpaceChange::BlockRegion(region) => {
+                        todo.modify_region_and_adjacent(region, |chunk_todo| {
+                            chunk_todo.update_triangulation = true;
+                        });
+                    }
+                    SpaceChange::Lighting(_) | SpaceChange::LightingRegion(_) => {
+                        // Light changes are found by Space::light_changed_since instead.
                     }
                     SpaceChange::Number(index) => {
                         if !todo.all_blocks_and_chunks {
@@ -832,27 +1346,40 @@ impl Listener<SpaceChange> for TodoListener {
 
 /// Keeps a 3D [`Texture`] up to date with the light data from a [`Space`].
 ///
-/// The texels are in [`PackedLight`] form.
+/// The texels are in the form of [`PackedLight::as_texel`](crate::space::PackedLight),
+/// which has [`LightPrecision::Standard`](crate::space::LightPrecision) regardless of the
+/// space's precision; spaces with `LightPrecision::High` get their full precision only
+/// from the raytracer. A 16-bit format is not used for them because WebGL 2 cannot
+/// sample normalized 16-bit textures without an extension.
 /// The alpha component is unused.
 /// TODO: Use alpha component to communicate block opacity.
 struct SpaceLightTexture {
     texture: Texture<Dim3, NormRGBA8UI>,
+    /// The grid and topology of the space this texture was created for.
+    grid: Grid,
+    topology: Topology,
     /// The region of cube coordinates for which there are valid texels.
     texture_grid: Grid,
+    /// [`Space::light_version`] as of the last update, or [`None`] if the texture has
+    /// not yet been filled.
+    uploaded_version: Option<u64>,
 }
 
 impl SpaceLightTexture {
-    /// Construct a new `SpaceLightTexture` for the specified size of [`Space`],
-    /// with no data.
-    pub fn new<C>(context: &mut C, grid: Grid) -> Result<Self, TextureError>
+    /// Construct a new `SpaceLightTexture` for the specified size and topology of
+    /// [`Space`], with no data.
+    pub fn new<C>(context: &mut C, grid: Grid, topology: Topology) -> Result<Self, TextureError>
     where
         C: GraphicsContext<Backend = Backend>,
     {
-        // Boundary of 1 extra cube automatically captures sky light.
+        // Boundary of 1 extra cube automatically captures sky light. Wrapped axes have
+        // no boundary, so that the shader's wrapping of texel coordinates at the size
+        // of the texture matches the space's own wrapping.
+        let boundary = |axis| if topology.wraps(axis) { 0 } else { 1 };
         let texture_grid = grid.expand(FaceMap {
-            px: 1,
-            py: 1,
-            pz: 1,
+            px: boundary(0),
+            py: boundary(1),
+            pz: boundary(2),
             nx: 0,
             ny: 0,
             nz: 0,
@@ -865,7 +1392,10 @@ impl SpaceLightTexture {
         )?;
         Ok(Self {
             texture,
+            grid,
+            topology,
             texture_grid,
+            uploaded_version: None,
         })
     }
 
@@ -895,7 +1425,24 @@ impl SpaceLightTexture {
     }
 
     pub fn update_all(&mut self, space: &Space) -> Result<(), TextureError> {
-        self.update(space, self.texture_grid)
+        let version = space.light_version();
+        self.update(space, self.texture_grid)?;
+        self.uploaded_version = Some(version);
+        Ok(())
+    }
+
+    /// Copy the light data which has changed since the last update, in whole chunks.
+    pub fn update_changed(&mut self, space: &Space) -> Result<(), TextureError> {
+        let since = match self.uploaded_version {
+            Some(version) => version,
+            None => return self.update_all(space),
+        };
+        let version = space.light_version();
+        for region in space.light_changed_since(since) {
+            self.update(space, region)?;
+        }
+        self.uploaded_version = Some(version);
+        Ok(())
     }
 
     fn bind<'a>(
@@ -936,6 +1483,54 @@ mod tests {
         v
     }
 
+    #[test]
+    fn chunk_content_hash_ignores_position() {
+        let [block] = crate::content::make_some_blocks();
+        let mut space = Space::empty_positive(CHUNK_SIZE * 4, 3, 3);
+        let versions = [0, 0];
+        let chunk = |x: GridCoordinate| Grid::new([x * CHUNK_SIZE, 0, 0], [CHUNK_SIZE; 3]);
+        // Chunks 1 and 2 have the same contents and neighbors; chunk 3 does not.
+        space.set([CHUNK_SIZE + 1, 1, 1], &block).unwrap();
+        space.set([CHUNK_SIZE * 2 + 1, 1, 1], &block).unwrap();
+
+        assert_eq!(
+            chunk_content_hash(&space, chunk(1), &versions),
+            chunk_content_hash(&space, chunk(2), &versions)
+        );
+        assert_ne!(
+            chunk_content_hash(&space, chunk(1), &versions),
+            chunk_content_hash(&space, chunk(3), &versions)
+        );
+        assert_ne!(
+            chunk_content_hash(&space, chunk(1), &versions),
+            chunk_content_hash(&space, chunk(1), &[0, 1])
+        );
+    }
+
+    #[test]
+    fn chunks_to_evict_oldest_first_and_never_visible() {
+        let candidates = vec![
+            (ChunkPos::new(0, 0, 0), 10, 100), // visible this frame
+            (ChunkPos::new(1, 0, 0), 5, 100),
+            (ChunkPos::new(2, 0, 0), 3, 0), // already empty
+            (ChunkPos::new(3, 0, 0), 2, 100),
+            (ChunkPos::new(4, 0, 0), 7, 100),
+        ];
+        assert_eq!(chunks_to_evict(candidates.clone(), 10, 0), vec![]);
+        assert_eq!(
+            chunks_to_evict(candidates.clone(), 10, 150),
+            vec![ChunkPos::new(3, 0, 0), ChunkPos::new(1, 0, 0)]
+        );
+        assert_eq!(
+            chunks_to_evict(candidates, 10, 1000),
+            vec![
+                ChunkPos::new(3, 0, 0),
+                ChunkPos::new(1, 0, 0),
+                ChunkPos::new(4, 0, 0)
+            ]
+        );
+    }
+
     #[test]
     fn update_adjacent_chunk_positive() {
         let todo: Arc<Mutex<SpaceRendererTodo>> = Default::default();
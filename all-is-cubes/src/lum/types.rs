@@ -3,7 +3,7 @@
 
 //! Core data types for graphics code to use.
 
-use cgmath::{EuclideanSpace as _, Point3, Vector3};
+use cgmath::{EuclideanSpace as _, Point3, Transform as _, Vector3};
 use luminance::{Semantics, Vertex};
 use luminance_front::context::GraphicsContext;
 use luminance_front::tess::{Mode, Tess};
@@ -72,6 +72,16 @@ impl LumBlockVertex {
         clamp_max: VertexClampHigh::new([0., 0., 0.]),
     };
 
+    /// Moves this vertex, and the cube it is considered to belong to, by `offset`.
+    #[inline]
+    pub(crate) fn translate(&mut self, offset: GridVector) {
+        let offset = offset.map(|c| c as f32);
+        for (axis, delta) in <[f32; 3]>::from(offset).iter().enumerate() {
+            self.position.repr[axis] += delta;
+            self.cube.repr[axis] += delta;
+        }
+    }
+
     /// Constructor taking our natural types instead of luminance specialized types.
     #[inline]
     pub fn new_colored(
@@ -189,6 +199,80 @@ impl GfxVertex for LumBlockVertex {
     }
 }
 
+/// Vertex and instance array structure for drawing cubes by instancing
+/// (see [`GraphicsOptions::instance_whole_cubes`]).
+///
+/// [`GraphicsOptions::instance_whole_cubes`]: crate::camera::GraphicsOptions::instance_whole_cubes
+#[derive(Copy, Clone, Debug, Semantics)]
+#[rustfmt::skip]
+pub enum InstanceSemantics {
+    /// Vertex position relative to the cube being drawn.
+    #[sem(name = "a_face_position", repr = "[f32; 3]", wrapper = "FacePosition")]
+    FacePosition,
+    /// Vertex normal (should be length 1).
+    #[sem(name = "a_normal", repr = "[f32; 3]", wrapper = "FaceNormal")]
+    Normal,
+    /// Position of the cube being drawn.
+    #[sem(name = "a_instance_cube", repr = "[f32; 3]", wrapper = "InstanceCube")]
+    InstanceCube,
+    /// RGBA color of the cube being drawn.
+    #[sem(name = "a_instance_color", repr = "[f32; 4]", wrapper = "InstanceColor")]
+    InstanceColor,
+}
+
+/// Vertex type for one face of a unit cube, which is drawn once per [`LumCubeInstance`].
+#[derive(Clone, Copy, Debug, PartialEq, Vertex)]
+#[vertex(sem = "InstanceSemantics")]
+pub struct LumFaceVertex {
+    position: FacePosition,
+    normal: FaceNormal,
+}
+
+impl LumFaceVertex {
+    /// Returns the vertices and indices of a unit square covering `face` of the unit
+    /// cube, in the same arrangement as the triangulator uses for block faces.
+    pub(crate) fn unit_face(face: Face) -> ([Self; 4], [u32; 6]) {
+        let transform = face.matrix(1).to_free();
+        let normal = FaceNormal::new(face.normal_vector::<f32>().into());
+        let corner = |x: FreeCoordinate, y: FreeCoordinate| Self {
+            position: FacePosition::new(
+                transform
+                    .transform_point(Point3::new(x, y, 0.0))
+                    .map(|c| c as f32)
+                    .into(),
+            ),
+            normal,
+        };
+        (
+            [
+                corner(0.0, 0.0),
+                corner(0.0, 1.0),
+                corner(1.0, 0.0),
+                corner(1.0, 1.0),
+            ],
+            [0, 1, 2, 2, 1, 3],
+        )
+    }
+}
+
+/// Per-instance data for drawing one face of a cube with [`LumFaceVertex`].
+#[derive(Clone, Copy, Debug, PartialEq, Vertex)]
+#[vertex(sem = "InstanceSemantics", instanced = "true")]
+pub struct LumCubeInstance {
+    cube: InstanceCube,
+    color: InstanceColor,
+}
+
+impl LumCubeInstance {
+    #[inline]
+    pub fn new(cube: GridPoint, color: Rgba) -> Self {
+        Self {
+            cube: InstanceCube::new(cube.to_vec().map(|c| c as f32).into()),
+            color: InstanceColor::new(color.into()),
+        }
+    }
+}
+
 /// Constructs a <code>[Tess]&lt;[LumBlockVertex]&gt;</code> that renders nothing but does
 /// not provoke a runtime error.
 pub fn empty_tess<C>(context: &mut C) -> Result<Tess<LumBlockVertex>, GraphicsResourceError>
@@ -206,7 +290,7 @@ where
 mod tests {
     use super::*;
     use crate::math::{Face, Rgb};
-    use cgmath::Vector3;
+    use cgmath::{InnerSpace as _, Vector3};
 
     #[test]
     fn vertex_dummy() {
@@ -243,4 +327,18 @@ mod tests {
         assert_eq!(vertex.normal.repr, [1.0, 0.0, 0.0]);
         assert_eq!(vertex.color_or_texture.repr, [7.0, 8.0, 9.0, 0.5]);
     }
+
+    #[test]
+    fn unit_face_lies_on_face() {
+        for &face in Face::ALL_SIX {
+            let (vertices, _) = LumFaceVertex::unit_face(face);
+            let normal = face.normal_vector::<f32>();
+            // Distance along the normal from the cube's center is 0.5 for every vertex.
+            for vertex in &vertices {
+                let p = Vector3::from(vertex.position.repr) - Vector3::new(0.5, 0.5, 0.5);
+                assert_eq!(p.dot(normal), 0.5, "{:?} {:?}", face, vertex);
+                assert_eq!(vertex.normal.repr, <[f32; 3]>::from(normal));
+            }
+        }
+    }
 }
@@ -2,10 +2,15 @@
 // in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
 
 //! Mathematical utilities and decisions.
+//!
+//! Where possible, this module and [`raycast`](crate::raycast) use `core` rather than
+//! `std`, but they are not usable without `std`: among other things, floating-point
+//! functions such as `floor()` come from `std`.
 
-use std::iter::FusedIterator;
+use core::iter::FusedIterator;
 
 use cgmath::{EuclideanSpace as _, Point3, Vector3};
+#[cfg(feature = "content")]
 use noise::NoiseFn;
 use num_traits::identities::Zero;
 pub use ordered_float::{FloatIsNan, NotNan};
@@ -20,6 +25,8 @@ mod face;
 pub use face::*;
 mod matrix;
 pub use matrix::*;
+mod rng;
+pub use rng::*;
 
 /// Coordinates that are locked to the cube grid.
 pub type GridCoordinate = i32;
@@ -187,6 +194,11 @@ impl Aab {
         self.sizes
     }
 
+    /// Volume enclosed by the box.
+    pub fn volume(&self) -> FreeCoordinate {
+        self.sizes.x * self.sizes.y * self.sizes.z
+    }
+
     /// Iterates over the eight corner points of the box.
     /// The ordering is deterministic but not currently declared stable.
     pub(crate) fn corner_points(
@@ -239,6 +251,34 @@ impl Aab {
         )
     }
 
+    /// Returns the box which is the intersection of `self` and `other`, or [`None`] if
+    /// they do not overlap with nonzero volume.
+    ///
+    /// ```
+    /// use all_is_cubes::math::Aab;
+    ///
+    /// let a = Aab::new(0.0, 2.0, 0.0, 2.0, 0.0, 2.0);
+    /// assert_eq!(
+    ///     a.intersection(Aab::new(1.0, 3.0, 0.5, 1.5, -1.0, 1.0)),
+    ///     Some(Aab::new(1.0, 2.0, 0.5, 1.5, 0.0, 1.0))
+    /// );
+    /// assert_eq!(a.intersection(Aab::new(2.0, 3.0, 0.0, 2.0, 0.0, 2.0)), None);
+    /// ```
+    pub fn intersection(self, other: Aab) -> Option<Aab> {
+        let lower = self
+            .lower_bounds
+            .zip(other.lower_bounds, FreeCoordinate::max);
+        let upper = self
+            .upper_bounds
+            .zip(other.upper_bounds, FreeCoordinate::min);
+        for axis in 0..3 {
+            if upper[axis] <= lower[axis] {
+                return None;
+            }
+        }
+        Some(Self::from_lower_upper(lower, upper))
+    }
+
     #[inline]
     // Not public because this is an odd interface that primarily helps with collision.
     pub(crate) fn leading_corner_trailing_box(
@@ -314,8 +354,8 @@ impl Aab {
     }
 }
 
-impl std::fmt::Debug for Aab {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for Aab {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             fmt,
             "Aab({:?} to {:?})",
@@ -366,6 +406,7 @@ impl Geometry for Aab {
 }
 
 /// Extension trait for [`noise::NoiseFn`] which makes it usable with our [`GridPoint`]s.
+#[cfg(feature = "content")]
 pub trait NoiseFnExt: NoiseFn<[f64; 3]> {
     /// Sample the noise at the center of the given cube. That is, convert the integer
     /// vector to `f64`, add 0.5 to all coordinates, and call [`NoiseFn::get`].
@@ -378,6 +419,7 @@ pub trait NoiseFnExt: NoiseFn<[f64; 3]> {
     /// does not apply any offset.
     fn at_grid(&self, point: GridPoint) -> f64;
 }
+#[cfg(feature = "content")]
 impl<T> NoiseFnExt for T
 where
     T: NoiseFn<[f64; 3]> + Sized,
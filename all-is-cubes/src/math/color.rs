@@ -4,9 +4,9 @@
 //! Color data types. This module is private but reexported by its parent.
 
 use cgmath::{ElementWise as _, Vector3, Vector4};
+use core::convert::{TryFrom, TryInto};
+use core::ops::{Add, AddAssign, Mul, Sub};
 pub use ordered_float::{FloatIsNan, NotNan};
-use std::convert::{TryFrom, TryInto};
-use std::ops::{Add, AddAssign, Mul, Sub};
 
 use crate::math::notnan;
 
@@ -47,6 +47,28 @@ macro_rules! rgba_const {
 ///   values, but they are permitted.)
 /// * NaN is banned so that [`Eq`] may be implemented. (Infinities are permitted.)
 /// * Color values are linear (gamma = 1).
+///
+/// # Light units
+///
+/// When an [`Rgb`] value is a quantity of light — a block's
+/// [`light_emission`](crate::block::BlockAttributes::light_emission), a sky color, or
+/// the light in a [`Space`](crate::space::Space) — it is in units where `1.0` is about
+/// the luminance of an overcast daytime sky, which is also how bright a white surface
+/// lit by such a sky is displayed. One unit is [`Rgb::NITS_PER_UNIT`] candela per
+/// square meter, which gives the following approximate values:
+///
+/// | Light source                    | cd/m²   | Units  |
+/// |---------------------------------|---------|--------|
+/// | Surfaces in a lamp-lit room     | 100     | 0.025  |
+/// | Overcast sky                    | 4,000   | 1      |
+/// | White surface in direct sun     | 25,000  | 6      |
+/// | Candle flame                    | 10,000  | 2.5    |
+/// | Frosted light bulb              | 100,000 | 25     |
+///
+/// Light stored in a [`Space`](crate::space::Space) is limited to a range depending on
+/// its [`LightPrecision`](crate::space::LightPrecision): from about 0.004 to 245 units
+/// at standard precision, or 0.00002 to 65000 at high precision. Negative emission,
+/// or emission beyond the high-precision maximum, is clamped.
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub struct Rgb(Vector3<NotNan<f32>>);
 
@@ -74,6 +96,10 @@ impl Rgb {
     /// White (unity brightness).
     pub const ONE: Rgb = Rgb(Vector3::new(NN1, NN1, NN1));
 
+    /// Luminance, in candela per square meter (nits), of light whose value is `1.0`;
+    /// see [light units](Rgb#light-units).
+    pub const NITS_PER_UNIT: f32 = 4000.0;
+
     /// Constructs a color from components. Panics if any component is NaN.
     /// No other range checks are performed.
     #[inline]
@@ -117,6 +143,49 @@ impl Rgb {
     pub const fn blue(self) -> NotNan<f32> {
         self.0.z
     }
+
+    /// Constructs white light with the given luminance in candela per square meter
+    /// (nits); see [light units](Rgb#light-units). Panics if `nits` is NaN.
+    ///
+    /// ```
+    /// use all_is_cubes::math::Rgb;
+    ///
+    /// assert_eq!(Rgb::from_luminance_nits(10_000.0), Rgb::new(2.5, 2.5, 2.5));
+    /// ```
+    pub fn from_luminance_nits(nits: f32) -> Self {
+        Self::ONE * (nits / Self::NITS_PER_UNIT)
+    }
+
+    /// Returns the luminance of this color (the weighted sum of its components which
+    /// corresponds to perceived brightness), using the sRGB coefficients.
+    ///
+    /// For light, this is in the same units as the components; multiply by
+    /// [`Rgb::NITS_PER_UNIT`] to obtain candela per square meter.
+    #[inline]
+    pub fn luminance(self) -> f32 {
+        0.2126 * self.red().into_inner()
+            + 0.7152 * self.green().into_inner()
+            + 0.0722 * self.blue().into_inner()
+    }
+
+    /// Returns this color scaled to have the given [luminance](Self::luminance), keeping
+    /// its hue and saturation. This is convenient for specifying a light's color and
+    /// brightness separately. Black, having no hue, is returned unchanged.
+    ///
+    /// ```
+    /// use all_is_cubes::math::Rgb;
+    ///
+    /// let lamp = Rgb::new(1.0, 0.8, 0.6).with_luminance(25.0);
+    /// assert!((lamp.luminance() - 25.0).abs() < 1e-4);
+    /// ```
+    pub fn with_luminance(self, luminance: f32) -> Self {
+        let current = self.luminance();
+        if current == 0.0 {
+            self
+        } else {
+            self * (luminance / current)
+        }
+    }
 }
 impl Rgba {
     /// Transparent black (all components zero); identical to
@@ -360,8 +429,8 @@ impl Mul<f32> for Rgb {
     }
 }
 
-impl std::fmt::Debug for Rgb {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for Rgb {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             fmt,
             "Rgb({:?}, {:?}, {:?})",
@@ -371,8 +440,8 @@ impl std::fmt::Debug for Rgb {
         )
     }
 }
-impl std::fmt::Debug for Rgba {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for Rgba {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             fmt,
             "Rgba({:?}, {:?}, {:?}, {:?})",
@@ -450,6 +519,17 @@ mod tests {
 
     // TODO: Add tests of the color not-NaN mechanisms.
 
+    #[test]
+    fn luminance() {
+        assert_eq!(Rgb::ZERO.luminance(), 0.0);
+        assert!((Rgb::ONE.luminance() - 1.0).abs() < 1e-6);
+        assert!(Rgb::new(0.0, 1.0, 0.0).luminance() > Rgb::new(0.0, 0.0, 1.0).luminance());
+        assert_eq!(Rgb::ZERO.with_luminance(5.0), Rgb::ZERO);
+        let scaled = Rgb::new(0.5, 0.25, 0.0).with_luminance(2.0);
+        assert!((scaled.luminance() - 2.0).abs() < 1e-5);
+        assert_eq!(scaled.red(), scaled.green() * 2.0);
+    }
+
     #[test]
     fn rgba_to_linear_32bit() {
         assert_eq!(
@@ -5,9 +5,9 @@
 //! This module is private but reexported by its parent.
 
 use cgmath::{BaseNum, Vector3};
+use core::convert::TryFrom;
+use core::ops::{Index, IndexMut};
 pub use ordered_float::{FloatIsNan, NotNan};
-use std::convert::TryFrom;
-use std::ops::{Index, IndexMut};
 
 use crate::math::*;
 
@@ -185,7 +185,7 @@ impl Face {
     #[inline]
     pub fn normal_vector<S>(self) -> Vector3<S>
     where
-        S: BaseNum + std::ops::Neg<Output = S>,
+        S: BaseNum + core::ops::Neg<Output = S>,
     {
         match self {
             Face::Within => Vector3::new(S::zero(), S::zero(), S::zero()),
@@ -295,6 +295,7 @@ impl TryFrom<GridVector> for Face {
 /// Container for values keyed by [`Face`]s.
 #[allow(clippy::exhaustive_structs)]
 #[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct FaceMap<V> {
     /// The value whose key is `Face::Within`.
     pub within: V,
@@ -345,7 +346,7 @@ impl<V> FaceMap<V> {
     }
 
     pub fn into_values_iter(self) -> impl Iterator<Item = V> {
-        std::array::IntoIter::new(self.into_values())
+        core::array::IntoIter::new(self.into_values())
     }
 
     /// Transform values.
@@ -448,8 +449,8 @@ impl CubeFace {
     }
 }
 
-impl std::fmt::Debug for CubeFace {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for CubeFace {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             fmt,
             "CubeFace({:?}, {:?})",
@@ -5,10 +5,10 @@
 //! This module is private but reexported by its parent.
 
 use cgmath::{InnerSpace, Matrix4, One, Transform, Vector3, Vector4, Zero as _};
+use core::cmp::Ordering;
+use core::convert::TryFrom as _;
+use core::ops::Mul;
 pub use ordered_float::{FloatIsNan, NotNan};
-use std::cmp::Ordering;
-use std::convert::TryFrom as _;
-use std::ops::Mul;
 
 use crate::math::*;
 
@@ -560,6 +560,46 @@ impl GridRotation {
         self.to_positive_octant_matrix(0)
     }
 
+    /// Expresses this rotation as a matrix which rotates about the point
+    /// `doubled_center / 2`, so that the center may be a cube's corner, the middle of
+    /// its edge or face, or its center.
+    ///
+    /// Returns [`None`] if there is no such matrix which moves whole cubes to whole
+    /// cubes; for example, a quarter turn about the vertical line through the middle of
+    /// an edge of a cube parallel to the X axis.
+    ///
+    /// ```
+    /// use all_is_cubes::math::{Face::*, GridPoint, GridRotation};
+    ///
+    /// // Reflecting across the middle of the cube at X = 2 leaves that cube in place.
+    /// let reflection = GridRotation::from_basis([NX, PY, PZ])
+    ///     .to_matrix_about(GridPoint::new(5, 0, 0))
+    ///     .unwrap();
+    /// assert_eq!(reflection.transform_cube(GridPoint::new(2, 7, 7)), GridPoint::new(2, 7, 7));
+    /// assert_eq!(reflection.transform_cube(GridPoint::new(0, 7, 7)), GridPoint::new(4, 7, 7));
+    ///
+    /// // Reflecting across the plane X = 2 swaps the cubes on either side of it.
+    /// let reflection = GridRotation::from_basis([NX, PY, PZ])
+    ///     .to_matrix_about(GridPoint::new(4, 0, 0))
+    ///     .unwrap();
+    /// assert_eq!(reflection.transform_cube(GridPoint::new(1, 7, 7)), GridPoint::new(2, 7, 7));
+    /// ```
+    pub fn to_matrix_about(self, doubled_center: GridPoint) -> Option<GridMatrix> {
+        let rotation = self.to_rotation_matrix();
+        let doubled_center = doubled_center.to_vec();
+        let doubled_translation = doubled_center - rotation.transform_vector(doubled_center);
+        if doubled_translation.x % 2 != 0
+            || doubled_translation.y % 2 != 0
+            || doubled_translation.z % 2 != 0
+        {
+            return None;
+        }
+        Some(GridMatrix {
+            w: doubled_translation / 2,
+            ..rotation
+        })
+    }
+
     // TODO: test equivalence with matrix
     #[inline]
     pub fn transform(self, face: Face) -> Face {
@@ -625,7 +665,7 @@ impl GridRotation {
     /// ```
     pub fn iterate(self) -> impl Iterator<Item = Self> {
         let mut item = Self::IDENTITY;
-        std::iter::once(Self::IDENTITY).chain(std::iter::from_fn(move || {
+        core::iter::once(Self::IDENTITY).chain(core::iter::from_fn(move || {
             item = item * self;
             if item == Self::IDENTITY {
                 // Cycled back to start; time to stop
@@ -778,6 +818,36 @@ mod tests {
         assert!(nontrivial > 100, "got {} inverses", nontrivial);
     }
 
+    #[test]
+    fn rotation_to_matrix_about() {
+        // Quarter turns about a cube center or a vertical edge map cubes to cubes.
+        for &doubled_center in &[GridPoint::new(3, 0, 5), GridPoint::new(4, 7, -2)] {
+            let matrix = GridRotation::CLOCKWISE
+                .to_matrix_about(doubled_center)
+                .unwrap();
+            assert_eq!(matrix.decompose().unwrap().0, GridRotation::CLOCKWISE);
+            let cube = GridPoint::new(10, 1, 3);
+            let mut rotated = cube;
+            for _ in 0..4 {
+                rotated = matrix.transform_cube(rotated);
+            }
+            assert_eq!(rotated, cube);
+        }
+        // The center cube stays put.
+        assert_eq!(
+            GridRotation::CLOCKWISE
+                .to_matrix_about(GridPoint::new(3, 0, 5))
+                .unwrap()
+                .transform_cube(GridPoint::new(1, 0, 2)),
+            GridPoint::new(1, 0, 2)
+        );
+        // Quarter turn about the middle of an edge doesn't.
+        assert_eq!(
+            GridRotation::CLOCKWISE.to_matrix_about(GridPoint::new(3, 0, 4)),
+            None
+        );
+    }
+
     #[test]
     fn rotation_identity() {
         assert_eq!(GridRotation::IDENTITY, GridRotation::one());
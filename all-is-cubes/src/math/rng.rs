@@ -0,0 +1,180 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`SeededRng`], the random number generator used for game mechanics.
+
+use core::convert::TryInto as _;
+
+use rand::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Multiplier of the PCG linear congruential generator.
+const MULTIPLIER: u64 = 6364136223846793005;
+
+/// Increment used by [`SeededRng::new`]; this is the default of the reference PCG
+/// implementation.
+const DEFAULT_INCREMENT: u64 = 1442695040888963407;
+
+/// A random number generator which produces the same numbers on every platform and in
+/// every version, given the same seed, and which may be serialized to continue the
+/// same sequence later.
+///
+/// Every [`Space`](crate::space::Space) and [`Universe`](crate::universe::Universe) has
+/// one, which is used for random ticks and by behaviors, so that a replay of the same
+/// inputs, or another participant in a multiplayer game, gets the same outcomes, and
+/// tests can check exact results.
+///
+/// The algorithm is PCG-XSH-RR with 64-bit state and 32-bit output, as described at
+/// <https://www.pcg-random.org/>. It is fast and small, but it is not suitable for
+/// cryptographic purposes.
+///
+/// ```
+/// use all_is_cubes::math::SeededRng;
+/// use rand::Rng as _;
+///
+/// let mut a = SeededRng::new(1234);
+/// let mut b = a.clone();
+/// let roll: u8 = a.gen_range(1..=6);
+/// assert_eq!(b.gen_range(1..=6), roll);
+///
+/// // Saving and restoring the generator continues the same sequence.
+/// let mut restored: SeededRng = serde_json::from_str(&serde_json::to_string(&a).unwrap())
+///     .unwrap();
+/// assert_eq!(restored.gen::<u64>(), a.gen::<u64>());
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct SeededRng {
+    state: u64,
+    /// Always odd.
+    increment: u64,
+}
+
+impl SeededRng {
+    /// Constructs a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Self::from_state_and_increment(seed, DEFAULT_INCREMENT)
+    }
+
+    /// Constructs a generator from a seed and a stream number. Generators with the same
+    /// seed but different streams produce unrelated sequences.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        Self::from_state_and_increment(seed, (stream << 1) | 1)
+    }
+
+    fn from_state_and_increment(state: u64, increment: u64) -> Self {
+        let mut rng = Self {
+            state: state.wrapping_add(increment),
+            increment,
+        };
+        rng.advance();
+        rng
+    }
+
+    /// Constructs a new generator seeded from this one's output, which may then be used
+    /// independently; for example, to give each of several consumers its own generator
+    /// so that the numbers each receives do not depend on how many the others used.
+    #[must_use]
+    pub fn fork(&mut self) -> Self {
+        let seed = self.next_u64();
+        let stream = self.next_u64();
+        Self::with_stream(seed, stream)
+    }
+
+    fn advance(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(MULTIPLIER)
+            .wrapping_add(self.increment);
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.advance();
+        let rotation = (state >> 59) as u32;
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = u64::from(self.next_u32());
+        let high = u64::from(self.next_u32());
+        (high << 32) | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for SeededRng {
+    /// The seed and the stream number, as little-endian bytes.
+    type Seed = [u8; 16];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::with_stream(
+            u64::from_le_bytes(seed[..8].try_into().unwrap()),
+            u64::from_le_bytes(seed[8..].try_into().unwrap()),
+        )
+    }
+
+    /// Equivalent to [`SeededRng::new`].
+    fn seed_from_u64(seed: u64) -> Self {
+        Self::new(seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of the reference implementation's demo program.
+    #[test]
+    fn reference_values() {
+        let mut rng = SeededRng::with_stream(42, 54);
+        let expected: [u32; 6] = [
+            0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e,
+        ];
+        for &value in &expected {
+            assert_eq!(rng.next_u32(), value);
+        }
+    }
+
+    #[test]
+    fn fill_bytes_matches_next_u32() {
+        let mut a = SeededRng::new(7);
+        let mut b = a.clone();
+        let mut bytes = [0; 6];
+        a.fill_bytes(&mut bytes);
+        assert_eq!(bytes[..4], b.next_u32().to_le_bytes());
+        assert_eq!(bytes[4..], b.next_u32().to_le_bytes()[..2]);
+    }
+
+    #[test]
+    fn fork_is_deterministic_and_distinct() {
+        let mut a = SeededRng::new(1);
+        let mut b = SeededRng::new(1);
+        let mut fork_a = a.fork();
+        assert_eq!(fork_a, b.fork());
+        assert_eq!(a, b);
+        assert_ne!(fork_a.next_u64(), a.next_u64());
+    }
+
+    #[test]
+    fn seedable() {
+        assert_eq!(SeededRng::seed_from_u64(99), SeededRng::new(99));
+        let mut seed = [0; 16];
+        seed[..8].copy_from_slice(&42u64.to_le_bytes());
+        seed[8..].copy_from_slice(&54u64.to_le_bytes());
+        assert_eq!(SeededRng::from_seed(seed), SeededRng::with_stream(42, 54));
+    }
+}
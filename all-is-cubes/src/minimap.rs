@@ -0,0 +1,293 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Minimap`], a top-down map of a [`Space`] suitable for display in a HUD.
+
+use cgmath::Vector2;
+use ordered_float::NotNan;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::listen::Listener;
+use crate::math::{GridCoordinate, Rgb, Rgba};
+use crate::space::{Grid, Space, SpaceChange};
+use crate::universe::{ReadRef, RefError};
+
+/// Brightness of the lowest layer of a [`Space`] in a [`Minimap`], relative to the
+/// highest layer, which is drawn at its full color.
+const LOWEST_BRIGHTNESS: f32 = 0.5;
+
+/// A top-down color map of a [`Space`]: each pixel shows the highest visible block in
+/// one column of the space, shaded darker the lower it is.
+///
+/// The map follows changes to the space, but recomputes only the columns that changed,
+/// when [`Minimap::update`] is called.
+///
+/// ```
+/// use all_is_cubes::prelude::*;
+/// use all_is_cubes::minimap::Minimap;
+///
+/// let mut universe = Universe::new();
+/// let mut space = Space::empty_positive(2, 3, 1);
+/// space.set([1, 2, 0], Rgba::WHITE).unwrap();
+/// let space = universe.insert_anonymous(space);
+///
+/// let mut map = Minimap::new(space.read_only());
+/// map.update().unwrap();
+/// assert_eq!(map.image(), &[Rgba::TRANSPARENT, Rgba::WHITE]);
+/// ```
+pub struct Minimap {
+    space: ReadRef<Space>,
+    /// The region of the space which is mapped, taken from the space when this was
+    /// created.
+    grid: Grid,
+    /// Colors of the columns of `grid`, in X then Z order.
+    image: Vec<Rgba>,
+    todo: Arc<Mutex<MinimapTodo>>,
+}
+
+impl Minimap {
+    /// Constructs a map of `space`, which is blank until [`Minimap::update`] is called.
+    pub fn new(space: ReadRef<Space>) -> Self {
+        let grid = space.borrow().grid();
+        let todo = Arc::new(Mutex::new(MinimapTodo {
+            all: true,
+            columns: HashSet::new(),
+        }));
+        space
+            .borrow()
+            .listen_batched(TodoListener(Arc::downgrade(&todo)));
+        let size = Self::size_of(grid);
+        Self {
+            space,
+            grid,
+            image: vec![Rgba::TRANSPARENT; size.x as usize * size.y as usize],
+            todo,
+        }
+    }
+
+    /// Returns the space this is a map of.
+    pub fn space(&self) -> &ReadRef<Space> {
+        &self.space
+    }
+
+    /// Returns the width and height of [`Self::image`]; these are the sizes of the space
+    /// along the X and Z axes.
+    pub fn size(&self) -> Vector2<u32> {
+        Self::size_of(self.grid)
+    }
+
+    fn size_of(grid: Grid) -> Vector2<u32> {
+        // Grid sizes are never negative.
+        Vector2::new(grid.size().x as u32, grid.size().z as u32)
+    }
+
+    /// Returns the map, as rows from north (-Z) to south, each containing the columns
+    /// from west (-X) to east. Columns with no visible blocks are transparent.
+    pub fn image(&self) -> &[Rgba] {
+        &self.image
+    }
+
+    /// Recomputes the colors of the columns of the space which have changed since the
+    /// last update, and returns how many there were.
+    ///
+    /// Returns an error if the space cannot be borrowed, in which case the changed
+    /// columns remain to be updated next time.
+    pub fn update(&mut self) -> Result<usize, RefError> {
+        let space = self.space.try_borrow()?;
+        let columns: Vec<(GridCoordinate, GridCoordinate)> = {
+            let mut todo = self.todo.lock().unwrap();
+            if todo.all {
+                todo.all = false;
+                todo.columns.clear();
+                let lower = self.grid.lower_bounds();
+                let upper = self.grid.upper_bounds();
+                (lower.z..upper.z)
+                    .flat_map(|z| (lower.x..upper.x).map(move |x| (x, z)))
+                    .collect()
+            } else {
+                todo.columns.drain().collect()
+            }
+        };
+
+        let lower = self.grid.lower_bounds();
+        let width = self.grid.size().x as usize;
+        for &(x, z) in &columns {
+            let index = (z - lower.z) as usize * width + (x - lower.x) as usize;
+            self.image[index] = column_color(&*space, self.grid, x, z);
+        }
+        Ok(columns.len())
+    }
+
+    /// Encodes the map as a PNG image, in sRGB, for example to be saved to a file.
+    ///
+    /// This does not [update](Self::update) the map first.
+    #[cfg(feature = "image")]
+    pub fn to_png(&self) -> Result<Vec<u8>, image::ImageError> {
+        let size = self.size();
+        let data: Vec<u8> = self
+            .image
+            .iter()
+            .flat_map(|color| std::array::IntoIter::new(color.to_srgb_32bit()))
+            .collect();
+        let mut png = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut png).encode(
+            &data,
+            size.x,
+            size.y,
+            image::ColorType::Rgba8,
+        )?;
+        Ok(png)
+    }
+}
+
+impl std::fmt::Debug for Minimap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Minimap")
+            .field("grid", &self.grid)
+            // Skipping image, which is large and not usefully printed
+            .finish()
+    }
+}
+
+/// Computes the color of the column of `space` at `x` and `z`, looking down from the
+/// top of `grid` through any transparent blocks.
+fn column_color(space: &Space, grid: Grid, x: GridCoordinate, z: GridCoordinate) -> Rgba {
+    let lower_y = grid.lower_bounds().y;
+    let upper_y = grid.upper_bounds().y;
+    let mut color = Rgb::ZERO;
+    let mut transmittance: f32 = 1.0;
+    for y in (lower_y..upper_y).rev() {
+        let evaluated = space.get_evaluated([x, y, z]);
+        if !evaluated.visible {
+            continue;
+        }
+        let height_fraction = if upper_y - lower_y > 1 {
+            (y - lower_y) as f32 / (upper_y - lower_y - 1) as f32
+        } else {
+            1.0
+        };
+        let brightness = LOWEST_BRIGHTNESS + (1.0 - LOWEST_BRIGHTNESS) * height_fraction;
+        let alpha = evaluated.color.alpha().into_inner();
+        color += evaluated.color.to_rgb() * (brightness * alpha * transmittance);
+        transmittance *= 1.0 - alpha;
+        if transmittance <= 0.0 {
+            break;
+        }
+    }
+    let alpha = 1.0 - transmittance;
+    if alpha <= 0.0 {
+        Rgba::TRANSPARENT
+    } else {
+        // Not premultiplied, like all other colors.
+        (color * (1.0 / alpha)).with_alpha(NotNan::new(alpha).unwrap())
+    }
+}
+
+/// Columns of a [`Minimap`] which need to be recomputed.
+#[derive(Debug)]
+struct MinimapTodo {
+    all: bool,
+    columns: HashSet<(GridCoordinate, GridCoordinate)>,
+}
+
+/// [`Listener`] adapter for [`MinimapTodo`].
+struct TodoListener(Weak<Mutex<MinimapTodo>>);
+
+impl Listener<SpaceChange> for TodoListener {
+    fn receive(&self, message: SpaceChange) {
+        if let Some(cell) = self.0.upgrade() {
+            if let Ok(mut todo) = cell.lock() {
+                if todo.all {
+                    return;
+                }
+                match message {
+                    SpaceChange::Block(p) => {
+                        todo.columns.insert((p.x, p.z));
+                    }
+                    SpaceChange::BlockRegion(region) => {
+                        let lower = region.lower_bounds();
+                        let upper = region.upper_bounds();
+                        for z in lower.z..upper.z {
+                            for x in lower.x..upper.x {
+                                todo.columns.insert((x, z));
+                            }
+                        }
+                    }
+                    SpaceChange::EveryBlock
+                    | SpaceChange::Number(_)
+                    | SpaceChange::BlockValue(_) => {
+                        // We don't know which columns contain the block, so redo them all.
+                        todo.all = true;
+                        todo.columns.clear();
+                    }
+                    SpaceChange::Lighting(_) | SpaceChange::LightingRegion(_) => {
+                        // The map is not lit.
+                    }
+                }
+            }
+        }
+    }
+
+    fn alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::AIR;
+    use crate::universe::Universe;
+
+    fn grey(value: f32) -> Rgba {
+        Rgba::new(value, value, value, 1.0)
+    }
+
+    #[test]
+    fn height_shading_and_transparency() {
+        let mut universe = Universe::new();
+        let mut space = Space::empty_positive(3, 3, 1);
+        space.set([0, 0, 0], Rgba::WHITE).unwrap();
+        space.set([1, 2, 0], Rgba::WHITE).unwrap();
+        // Half-transparent black over white.
+        space.set([2, 2, 0], Rgba::new(0.0, 0.0, 0.0, 0.5)).unwrap();
+        space.set([2, 0, 0], Rgba::WHITE).unwrap();
+        let space = universe.insert_anonymous(space);
+
+        let mut map = Minimap::new(space.read_only());
+        assert_eq!(map.size(), Vector2::new(3, 1));
+        assert_eq!(map.update(), Ok(3));
+        assert_eq!(map.image(), &[grey(0.5), grey(1.0), grey(0.25)]);
+    }
+
+    #[test]
+    fn updates_changed_columns() {
+        let mut universe = Universe::new();
+        let mut space = Space::empty_positive(2, 1, 2);
+        space.set([0, 0, 0], Rgba::WHITE).unwrap();
+        let space = universe.insert_anonymous(space);
+
+        let mut map = Minimap::new(space.read_only());
+        assert_eq!(map.update(), Ok(4));
+        assert_eq!(map.update(), Ok(0));
+
+        space.borrow_mut().set([1, 0, 1], Rgba::WHITE).unwrap();
+        space.borrow_mut().set([0, 0, 0], AIR).unwrap();
+        assert_eq!(map.update(), Ok(2));
+        assert_eq!(
+            map.image(),
+            &[
+                Rgba::TRANSPARENT,
+                Rgba::TRANSPARENT,
+                Rgba::TRANSPARENT,
+                Rgba::WHITE
+            ]
+        );
+
+        // A new block might be anywhere.
+        space.borrow_mut().set([0, 0, 0], Rgba::BLACK).unwrap();
+        assert_eq!(map.update(), Ok(4));
+        assert_eq!(map.image()[0], Rgba::BLACK);
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Breaking blocks gradually: progress tracking and crack overlays.
+//!
+//! A block whose [`BlockAttributes::hardness`](crate::block::BlockAttributes::hardness)
+//! is nonzero is not removed by a single use of the delete tool. Instead,
+//! [`Character::continue_breaking`](crate::character::Character::continue_breaking)
+//! is called every step while the tool is held, which accumulates a [`BreakingProgress`]
+//! and displays it by replacing the block with a [`Block::Overlay`] of the appropriate
+//! [`CrackStage`].
+
+use std::f64::consts::TAU;
+
+use crate::block::{Block, BlockCollision, Resolution};
+use crate::linking::{BlockModule, BlockProvider};
+use crate::math::{Face, FreeCoordinate, GridCoordinate, GridPoint, Rgba};
+use crate::space::{Grid, SetCubeError, Space, SpacePhysics, SpaceTransaction};
+use crate::transactions::{Transaction as _, UniverseTransaction};
+use crate::universe::{URef, Universe};
+
+/// Overlays showing how close a block is to being broken; see the
+/// [module documentation](self).
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, strum::Display, strum::EnumIter)]
+#[strum(serialize_all = "kebab-case")]
+#[non_exhaustive]
+pub enum CrackStage {
+    Stage1,
+    Stage2,
+    Stage3,
+    Stage4,
+}
+
+impl BlockModule for CrackStage {
+    fn namespace() -> &'static str {
+        "all-is-cubes/mining/crack"
+    }
+}
+
+impl CrackStage {
+    const ALL: [CrackStage; 4] = [Self::Stage1, Self::Stage2, Self::Stage3, Self::Stage4];
+
+    /// Returns the stage to display when `fraction` of the required breaking time has
+    /// elapsed, or [`None`] if no cracks should be shown yet.
+    ///
+    /// ```
+    /// use all_is_cubes::mining::CrackStage;
+    ///
+    /// assert_eq!(CrackStage::for_fraction(0.0), None);
+    /// assert_eq!(CrackStage::for_fraction(0.5), Some(CrackStage::Stage2));
+    /// assert_eq!(CrackStage::for_fraction(2.0), Some(CrackStage::Stage4));
+    /// ```
+    pub fn for_fraction(fraction: f64) -> Option<Self> {
+        // One more interval than there are stages, so that the first one is uncracked.
+        let intervals = (Self::ALL.len() + 1) as f64;
+        let index = (fraction * intervals).floor();
+        if index < 1.0 || index.is_nan() {
+            None
+        } else {
+            Some(Self::ALL[(index as usize - 1).min(Self::ALL.len() - 1)])
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|&s| s == self).unwrap()
+    }
+
+    /// Construct the standard crack overlay blocks, storing their voxels in `universe`.
+    pub fn new(universe: &mut Universe) -> BlockProvider<CrackStage> {
+        let resolution: Resolution = 16;
+        let crack_block = Block::from(Rgba::new(0.05, 0.04, 0.03, 0.85));
+        BlockProvider::new(|stage: CrackStage| {
+            let mut space = Space::empty(Grid::for_block(resolution));
+            space.set_physics(SpacePhysics::DEFAULT_FOR_BLOCK);
+            for &face in Face::ALL_SIX {
+                draw_cracks(&mut space, face, stage.index(), resolution, &crack_block)?;
+            }
+            Ok(Block::builder()
+                .display_name(format!("Crack {}", stage.index() + 1))
+                .selectable(false)
+                .collision(BlockCollision::None)
+                .voxels_ref(resolution, universe.insert_anonymous(space))
+                .build())
+        })
+        .unwrap()
+    }
+}
+
+/// Draw cracks radiating from the center of one face of a block-sized `space`.
+/// Higher `stage_index`es have more and longer cracks, and include all the cracks of
+/// lower stages.
+fn draw_cracks(
+    space: &mut Space,
+    face: Face,
+    stage_index: usize,
+    resolution: Resolution,
+    crack_block: &Block,
+) -> Result<(), SetCubeError> {
+    let size = FreeCoordinate::from(resolution);
+    let transform = face.matrix(GridCoordinate::from(resolution));
+    let ray_count = 2 * (stage_index + 1);
+    for ray in 0..ray_count {
+        // Spread the rays irregularly, but the same way for every stage.
+        let angle = ray as FreeCoordinate * TAU * 0.382 + face as usize as FreeCoordinate;
+        let length = size * (0.2 + 0.1 * (stage_index + 1 - ray / 2) as FreeCoordinate);
+        let mut t = 0.0;
+        while t < length {
+            let wobble = (t * 1.7 + ray as FreeCoordinate).sin() * 0.12;
+            let u = size / 2.0 + (angle + wobble).cos() * t;
+            let v = size / 2.0 + (angle + wobble).sin() * t;
+            let cube = transform.transform_cube(GridPoint::new(
+                u.floor() as GridCoordinate,
+                v.floor() as GridCoordinate,
+                0,
+            ));
+            if space.grid().contains_cube(cube) {
+                space.set(cube, crack_block)?;
+            }
+            t += 0.5;
+        }
+    }
+    Ok(())
+}
+
+/// The state of a [`Character`](crate::character::Character)'s ongoing attempt to
+/// break a particular block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreakingProgress {
+    pub(crate) space: URef<Space>,
+    pub(crate) cube: GridPoint,
+    /// The block being broken, without any crack overlay.
+    pub(crate) block: Block,
+    /// Seconds the tool has been held on this cube.
+    pub(crate) elapsed: f64,
+    /// The crack stage currently placed in the space, if any.
+    pub(crate) displayed: Option<CrackStage>,
+}
+
+impl BreakingProgress {
+    pub(crate) fn new(space: URef<Space>, cube: GridPoint, block: Block) -> Self {
+        Self {
+            space,
+            cube,
+            block,
+            elapsed: 0.0,
+            displayed: None,
+        }
+    }
+
+    /// The space containing the block being broken.
+    pub fn space(&self) -> &URef<Space> {
+        &self.space
+    }
+
+    /// The cube containing the block being broken.
+    pub fn cube(&self) -> GridPoint {
+        self.cube
+    }
+
+    /// The block being broken, not including any crack overlay.
+    pub fn block(&self) -> &Block {
+        &self.block
+    }
+
+    /// Time, in seconds, which has been spent breaking the block so far.
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// The block which should be in the cube to display `stage`.
+    pub(crate) fn block_for_stage(
+        &self,
+        stage: Option<CrackStage>,
+        cracks: &BlockProvider<CrackStage>,
+    ) -> Block {
+        match stage {
+            None => self.block.clone(),
+            Some(stage) => Block::Overlay {
+                base: Box::new(self.block.clone()),
+                overlay: Box::new(cracks[stage].clone()),
+            },
+        }
+    }
+
+    /// Transaction which replaces the currently displayed crack stage with `new_stage`.
+    ///
+    /// This does not change [`Self::displayed`]; that is up to the caller once the
+    /// transaction has succeeded.
+    pub(crate) fn set_stage(
+        &self,
+        new_stage: Option<CrackStage>,
+        cracks: &BlockProvider<CrackStage>,
+    ) -> UniverseTransaction {
+        if new_stage == self.displayed {
+            return UniverseTransaction::default();
+        }
+        SpaceTransaction::set_cube(
+            self.cube,
+            Some(self.block_for_stage(self.displayed, cracks)),
+            Some(self.block_for_stage(new_stage, cracks)),
+        )
+        .bind(self.space.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::AIR;
+
+    #[test]
+    fn stages_are_distinct_and_cumulative() {
+        let mut universe = Universe::new();
+        let cracks = CrackStage::new(&mut universe);
+        let counts: Vec<usize> = CrackStage::ALL
+            .iter()
+            .map(|&stage| {
+                let voxels = cracks[stage].evaluate().unwrap().voxels.unwrap();
+                voxels
+                    .grid()
+                    .interior_iter()
+                    .filter(|&p| !voxels[p].color.fully_transparent())
+                    .count()
+            })
+            .collect();
+        for pair in counts.windows(2) {
+            assert!(pair[0] < pair[1], "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn overlay_does_not_add_voxels_to_air() {
+        let mut universe = Universe::new();
+        let cracks = CrackStage::new(&mut universe);
+        let overlaid = Block::Overlay {
+            base: Box::new(AIR),
+            overlay: Box::new(cracks[CrackStage::Stage4].clone()),
+        }
+        .evaluate()
+        .unwrap();
+        assert!(!overlaid.visible);
+        let voxels = overlaid.voxels.unwrap();
+        assert!(voxels
+            .grid()
+            .interior_iter()
+            .all(|p| voxels[p].color.fully_transparent()));
+    }
+
+    #[test]
+    fn set_stage_round_trip() {
+        let mut universe = Universe::new();
+        let cracks = CrackStage::new(&mut universe);
+        let block = Block::from(Rgba::WHITE);
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &block).unwrap();
+        let space = universe.insert_anonymous(space);
+
+        let mut progress = BreakingProgress::new(space.clone(), GridPoint::new(0, 0, 0), block);
+        progress
+            .set_stage(Some(CrackStage::Stage1), &cracks)
+            .execute(&mut universe)
+            .unwrap();
+        assert_eq!(
+            space.borrow()[[0, 0, 0]],
+            progress.block_for_stage(Some(CrackStage::Stage1), &cracks)
+        );
+        progress.displayed = Some(CrackStage::Stage1);
+        progress
+            .set_stage(None, &cracks)
+            .execute(&mut universe)
+            .unwrap();
+        assert_eq!(space.borrow()[[0, 0, 0]], Block::from(Rgba::WHITE));
+    }
+}
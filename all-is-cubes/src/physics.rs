@@ -9,6 +9,8 @@ mod body;
 pub use body::*;
 mod collision;
 pub use collision::*;
+mod export;
+pub use export::*;
 
 /// Close-but-not-intersecting objects are set to this separation.
 pub(crate) const POSITION_EPSILON: FreeCoordinate = 1e-6 * 1e-6;
@@ -17,9 +19,9 @@ pub(crate) const POSITION_EPSILON: FreeCoordinate = 1e-6 * 1e-6;
 mod tests {
     use super::*;
     use crate::apps::Tick;
-    use crate::block::AIR;
+    use crate::block::{Block, BlockCollision, AIR};
     use crate::content::make_some_blocks;
-    use crate::math::Aab;
+    use crate::math::{Aab, Rgba};
     use crate::raycast::CubeFace;
     use crate::raycast::Face;
     use crate::space::SpacePhysics;
@@ -82,6 +84,63 @@ mod tests {
         assert_eq!(body.position, test_body().position);
     }
 
+    /// A space 1 cube wide with `fluid_depth` cubes of fluid of the given density at
+    /// the bottom and air above.
+    fn fluid_space(density: f32, fluid_depth: i32) -> Space {
+        let fluid = Block::builder()
+            .color(Rgba::new(0.0, 0.0, 1.0, 0.5))
+            .collision(BlockCollision::Fluid)
+            .density(density)
+            .build();
+        let mut space = Space::empty_positive(1, 10, 1);
+        space
+            .fill_uniform(Grid::new([0, 0, 0], [1, fluid_depth, 1]), &fluid)
+            .unwrap();
+        space
+    }
+
+    #[test]
+    fn fluid_immersion_fraction() {
+        let space = fluid_space(2.0, 2);
+        let immersion = |y: FreeCoordinate| {
+            collision::fluid_immersion(&space, Aab::new(0.25, 0.75, y, y + 1.0, 0.25, 0.75))
+        };
+        assert_eq!(immersion(0.5), 2.0);
+        assert_eq!(immersion(1.5), 1.0);
+        assert_eq!(immersion(3.0), 0.0);
+    }
+
+    #[test]
+    fn floats_in_dense_fluid() {
+        let space = fluid_space(2.0, 2);
+        let mut body = Body {
+            position: Point3::new(0.5, 1.0, 0.5),
+            ..test_body()
+        };
+        for _ in 0..600 {
+            body.step(Tick::from_seconds(1.0 / 60.0), Some(&space), collision_noop);
+        }
+        // At rest half immersed, where the buoyancy exactly balances gravity.
+        assert!((body.position.y - 2.0).abs() < 0.01, "{:?}", body);
+        assert!(body.velocity.magnitude() < 0.01, "{:?}", body);
+    }
+
+    #[test]
+    fn sinks_slowly_in_light_fluid() {
+        let space = fluid_space(0.5, 10);
+        let mut body = Body {
+            position: Point3::new(0.5, 8.0, 0.5),
+            ..test_body()
+        };
+        for _ in 0..60 {
+            body.step(Tick::from_seconds(1.0 / 60.0), Some(&space), collision_noop);
+        }
+        // Half of gravity is cancelled by buoyancy, and drag slows it further.
+        assert!(body.velocity.y < 0.0, "{:?}", body);
+        assert!(body.velocity.y > -10.0, "{:?}", body);
+        assert_eq!((body.velocity.x, body.velocity.z), (0.0, 0.0));
+    }
+
     #[test]
     fn falling_collision() {
         let [block] = make_some_blocks();
@@ -102,6 +161,69 @@ mod tests {
         assert_eq!(contacts, vec![CubeFace::new((0, 0, 0), Face::PY)]);
     }
 
+    /// A floor with a one-cube-high step at x = 1, and a body standing on the floor
+    /// and walking towards the step.
+    ///
+    /// The body is placed [`POSITION_EPSILON`] above the floor, where collision leaves
+    /// a body that has landed, since a ray starting exactly on a cube boundary does not
+    /// report crossing it.
+    fn step_test_setup(step_height: FreeCoordinate) -> (Space, Body) {
+        let [block] = make_some_blocks();
+        let mut space = Space::empty_positive(3, 4, 1);
+        space
+            .fill_uniform(Grid::new([0, 0, 0], [3, 1, 1]), &block)
+            .unwrap();
+        space
+            .fill_uniform(Grid::new([1, 1, 0], [2, 1, 1]), &block)
+            .unwrap();
+        let body = Body {
+            position: Point3::new(0.5, 1.0 + POSITION_EPSILON, 0.5),
+            velocity: Vector3::new(2.0, 0.0, 0.0),
+            step_height,
+            ..Body::new_minimal((0., 0., 0.), Aab::new(-0.25, 0.25, 0.0, 1.0, -0.25, 0.25))
+        };
+        (space, body)
+    }
+
+    #[test]
+    fn step_up() {
+        let (space, mut body) = step_test_setup(1.0);
+        for _ in 0..30 {
+            body.step(Tick::from_seconds(1.0 / 60.0), Some(&space), collision_noop);
+        }
+        assert!(body.position.x > 1.25, "{:?}", body);
+        assert!((body.position.y - 2.0).abs() < 0.01, "{:?}", body);
+    }
+
+    #[test]
+    fn step_too_high() {
+        let (space, mut body) = step_test_setup(0.5);
+        for _ in 0..30 {
+            body.step(Tick::from_seconds(1.0 / 60.0), Some(&space), collision_noop);
+        }
+        assert!((body.position.x - 0.75).abs() < 0.01, "{:?}", body);
+        assert!((body.position.y - 1.0).abs() < 0.01, "{:?}", body);
+    }
+
+    #[test]
+    fn noclip_ignores_space() {
+        let [block] = make_some_blocks();
+        let mut space = Space::empty_positive(3, 3, 3);
+        space.fill_uniform(space.grid(), &block).unwrap();
+        let mut body = Body {
+            position: Point3::new(0.5, 1.5, 1.5),
+            velocity: Vector3::new(2.0, 0.0, 0.0),
+            noclip: true,
+            ..test_body()
+        };
+        let mut contacts = Vec::new();
+        body.step(Tick::from_seconds(1.0), Some(&space), |c| contacts.push(c));
+        // Neither stopped by the blocks, nor pushed out of them, nor falling.
+        assert_eq!(body.position, Point3::new(2.5, 1.5, 1.5));
+        assert_eq!(body.velocity, Vector3::new(2.0, 0.0, 0.0));
+        assert!(contacts.is_empty());
+    }
+
     #[test]
     fn push_out_simple() {
         let [block] = make_some_blocks();
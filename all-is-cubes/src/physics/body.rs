@@ -5,7 +5,9 @@ use cgmath::{EuclideanSpace as _, InnerSpace as _, Point3, Vector3, Zero};
 use ordered_float::NotNan;
 use std::fmt;
 
-use super::collision::{aab_raycast, collide_along_ray, find_colliding_cubes, Contact};
+use super::collision::{
+    aab_raycast, collide_along_ray, find_colliding_cubes, fluid_immersion, Contact,
+};
 use super::POSITION_EPSILON;
 use crate::apps::Tick;
 use crate::block::BlockCollision;
@@ -18,6 +20,19 @@ use crate::util::{ConciseDebug, CustomFormat, StatusText};
 /// Velocities shorter than this are treated as zero, to allow things to come to unchanging rest sooner.
 const VELOCITY_EPSILON_SQUARED: FreeCoordinate = 1e-6 * 1e-6;
 
+/// Rate, per second, at which the velocity of a body fully immersed in a fluid of
+/// density 1 decays exponentially. The rate is proportional to the fluid's density and
+/// to the fraction of the body which is immersed.
+const FLUID_DRAG: FreeCoordinate = 3.0;
+
+/// Offset of the region below a body which, if it contains an obstacle, counts as the
+/// body standing on something for the purpose of [`Body::step_height`].
+const STEP_SUPPORT_PROBE: Vector3<FreeCoordinate> = Vector3 {
+    x: 0.0,
+    y: -0.01,
+    z: 0.0,
+};
+
 /// An object with a position, velocity, and collision volume.
 /// What it collides with is determined externally.
 #[derive(Clone, PartialEq)]
@@ -36,9 +51,15 @@ pub struct Body {
 
     /// Is this body not subject to gravity?
     pub flying: bool,
-    /// Is this body not subject to collision?
+    /// Is this body not subject to collision, gravity, or any other effect of the space
+    /// it moves in? This is intended for inspecting content rather than for play.
     pub noclip: bool,
 
+    /// Height of the tallest obstacle which this body will step up onto, instead of
+    /// being stopped by, when it moves horizontally into it while standing on
+    /// something. Zero disables stepping.
+    pub step_height: FreeCoordinate,
+
     /// Yaw of the camera look direction, in degrees clockwise from looking towards -Z.
     ///
     /// The preferred range is 0 inclusive to 360 exclusive.
@@ -65,6 +86,7 @@ impl std::fmt::Debug for Body {
             .field("collision_box", &self.collision_box)
             .field("flying", &self.flying)
             .field("noclip", &self.noclip)
+            .field("step_height", &self.step_height)
             .field("yaw", &self.yaw)
             .field("pitch", &self.pitch)
             .finish()
@@ -104,6 +126,7 @@ impl Body {
             collision_box: collision_box.into(),
             flying: false,
             noclip: false,
+            step_height: 0.0,
             yaw: 0.0,
             pitch: 0.0,
         }
@@ -115,6 +138,15 @@ impl Body {
     /// (constraining possible movement) and `collision_callback` will be called with all
     /// such blocks. It is not guaranteed that `collision_callback` will be called only once
     /// per block.
+    ///
+    /// Where the body overlaps [fluid](BlockCollision::Fluid) blocks in `colliding_space`,
+    /// it is buoyed up against gravity and slowed by drag.
+    ///
+    /// After moving, the body's position is wrapped according to the
+    /// [`Topology`](crate::space::Topology) of `colliding_space`.
+    ///
+    /// If the body is [`noclip`](Self::noclip), `colliding_space` is used only for that
+    /// wrapping.
     pub fn step<CC>(
         &mut self,
         tick: Tick,
@@ -126,12 +158,23 @@ impl Body {
     {
         let dt = tick.delta_t.as_secs_f64();
         let mut move_segments = [MoveSegment::default(); 3];
+        let wrapping_space = colliding_space;
+        let colliding_space = if self.noclip { None } else { colliding_space };
 
         // TODO: Reset any non-finite values found to allow recovery from glitches.
 
-        if !self.flying && !tick.paused() {
+        if !tick.paused() {
             if let Some(space) = colliding_space {
-                self.velocity += space.physics().gravity.map(|c| c.into_inner()) * dt;
+                let immersion = fluid_immersion(space, self.collision_box_abs());
+                if !self.flying {
+                    // Buoyancy opposes gravity in proportion to the weight of the fluid
+                    // displaced; bodies have the density of water.
+                    let gravity = space.physics().gravity.map(|c| c.into_inner());
+                    self.velocity += gravity * ((1.0 - immersion) * dt);
+                }
+                if immersion > 0.0 {
+                    self.velocity *= (-FLUID_DRAG * immersion * dt).exp();
+                }
             }
         }
 
@@ -161,7 +204,7 @@ impl Body {
                 // Each call to collide_and_advance will zero at least one axis of delta_position.
                 // The nonzero axes are for sliding movement.
                 let (new_delta_position, segment) =
-                    self.collide_and_advance(space, &mut collision_callback, delta_position);
+                    self.collide_and_advance(space, &mut collision_callback, delta_position, true);
                 delta_position = new_delta_position;
                 move_segments[i] = segment;
 
@@ -175,6 +218,15 @@ impl Body {
             };
         }
 
+        // Collision already follows the topology, so the position only needs wrapping
+        // once it is final.
+        if let Some(space) = wrapping_space {
+            self.position = space
+                .physics()
+                .topology
+                .wrap_point(space.grid(), self.position);
+        }
+
         // TODO: after gravity, falling-below-the-world protection
 
         BodyStepInfo {
@@ -186,11 +238,15 @@ impl Body {
 
     /// Perform a single straight-line position change, stopping at the first obstacle.
     /// Returns the remainder of `delta_position` that should be retried for sliding movement.
+    ///
+    /// If `may_step` is true and the obstacle is low enough, steps up onto it and
+    /// continues instead.
     fn collide_and_advance<CC>(
         &mut self,
         space: &Space,
         collision_callback: &mut CC,
         mut delta_position: Vector3<FreeCoordinate>,
+        may_step: bool,
     ) -> (Vector3<FreeCoordinate>, MoveSegment)
     where
         CC: FnMut(Contact),
@@ -199,7 +255,7 @@ impl Body {
             space,
             Ray::new(self.position, delta_position),
             self.collision_box,
-            collision_callback,
+            &mut *collision_callback,
         );
 
         if let Some(collision) = collision {
@@ -213,6 +269,26 @@ impl Body {
             self.position += unobstructed_delta_position;
             // Figure the distance we have have left.
             delta_position -= unobstructed_delta_position;
+
+            if may_step && axis != 1 {
+                if let Some(rise) = self.step_up_height(space, delta_position) {
+                    self.position.y += rise;
+                    // Continue the movement from the top of the step, but do not step
+                    // again, so that the number of segments stays bounded.
+                    let (remainder, segment) =
+                        self.collide_and_advance(space, collision_callback, delta_position, false);
+                    return (
+                        remainder,
+                        MoveSegment {
+                            delta_position: unobstructed_delta_position
+                                + Vector3::new(0.0, rise, 0.0)
+                                + segment.delta_position,
+                            stopped_by: segment.stopped_by,
+                        },
+                    );
+                }
+            }
+
             // Convert it to sliding movement for the axes we didn't collide in.
             delta_position[axis] = 0.0;
 
@@ -239,6 +315,46 @@ impl Body {
         }
     }
 
+    /// If this body is standing on something and moving by `delta_position` horizontally
+    /// would run into an obstacle no taller than [`Self::step_height`] with room above
+    /// it, returns how far the body must rise to be on top of the obstacle.
+    fn step_up_height(
+        &self,
+        space: &Space,
+        delta_position: Vector3<FreeCoordinate>,
+    ) -> Option<FreeCoordinate> {
+        if self.step_height <= 0.0 {
+            return None;
+        }
+        let aab = self.collision_box_abs();
+        let horizontal = Vector3::new(delta_position.x, 0.0, delta_position.z);
+
+        let supported = find_colliding_cubes(space, aab.translate(STEP_SUPPORT_PROBE))
+            .next()
+            .is_some();
+        if !supported {
+            return None;
+        }
+
+        let moved = aab.translate(horizontal);
+        // If there is no obstacle, this is negative infinity and we will not step.
+        let top = find_colliding_cubes(space, moved)
+            .map(|cube| FreeCoordinate::from(cube.y + 1))
+            .fold(FreeCoordinate::NEG_INFINITY, FreeCoordinate::max);
+        let rise = top - aab.lower_bounds_p().y + POSITION_EPSILON;
+        if rise <= 0.0 || rise > self.step_height + POSITION_EPSILON {
+            return None;
+        }
+
+        // There must be room for the body both to rise and then to move over the top.
+        let raised = aab.translate(Vector3::new(0.0, rise, 0.0));
+        let clear = find_colliding_cubes(space, raised).next().is_none()
+            && find_colliding_cubes(space, raised.translate(horizontal))
+                .next()
+                .is_none();
+        clear.then(|| rise)
+    }
+
     /// Check if we're intersecting any blocks and fix that if so.
     fn push_out(&mut self, space: &Space) -> Option<Vector3<FreeCoordinate>> {
         let colliding = find_colliding_cubes(space, self.collision_box_abs())
@@ -324,6 +440,29 @@ impl Body {
         self.yaw = (180.0 - (direction.x).atan2(direction.z).to_degrees()).rem_euclid(360.0);
         self.pitch = -(direction.y).atan2(horizontal_distance).to_degrees();
     }
+
+    /// Moves the body to `position` and stops it.
+    ///
+    /// If the body would then intersect any solid blocks in `space`, it is instead put
+    /// at the nearest position, straight or diagonally away from `position`, where it
+    /// does not (unless [`noclip`](Self::noclip) is set). Returns the body's new
+    /// position.
+    pub fn teleport(
+        &mut self,
+        space: &Space,
+        position: impl Into<Point3<FreeCoordinate>>,
+    ) -> Point3<FreeCoordinate> {
+        self.position = position.into();
+        self.velocity = Vector3::zero();
+        if !self.noclip {
+            if let Some(delta) = self.push_out(space) {
+                // push_out() stops exactly at the obstacle's surface, where rounding error
+                // may leave the body still intersecting it, so go a little further.
+                self.position += delta.normalize() * POSITION_EPSILON;
+            }
+        }
+        self.position
+    }
 }
 
 /// Diagnostic data returned by [`Body::step`]. The exact contents of this structure
@@ -365,11 +504,23 @@ impl Default for MoveSegment {
 /// The [`Transaction`] type for [`Body`].
 ///
 /// TODO: Very incomplete.
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub struct BodyTransaction {
     // TODO: Better strategy than just having public fields
     pub delta_yaw: FreeCoordinate,
+    /// Change to [`Body::velocity`], such as from being pushed by an
+    /// [`Explosion`](crate::explosion::Explosion).
+    pub delta_velocity: Vector3<FreeCoordinate>,
+}
+
+impl Default for BodyTransaction {
+    fn default() -> Self {
+        Self {
+            delta_yaw: 0.0,
+            delta_velocity: Vector3::zero(),
+        }
+    }
 }
 
 impl Transactional for Body {
@@ -392,6 +543,7 @@ impl Transaction<Body> for BodyTransaction {
         _: Self::CommitCheck,
     ) -> Result<(), Box<dyn std::error::Error>> {
         body.yaw += self.delta_yaw;
+        body.velocity += self.delta_velocity;
         Ok(())
     }
 
@@ -401,6 +553,7 @@ impl Transaction<Body> for BodyTransaction {
 
     fn commit_merge(mut self, other: Self, (): Self::MergeCheck) -> Self {
         self.delta_yaw += other.delta_yaw;
+        self.delta_velocity += other.delta_velocity;
         self
     }
 }
@@ -409,6 +562,7 @@ impl Transaction<Body> for BodyTransaction {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::space::{SpacePhysics, Topology};
     use crate::transactions::TransactionTester;
 
     fn test_body() -> Body {
@@ -444,14 +598,40 @@ mod tests {
         do_test((0., -1., 0.), exactly_vertical_yaw, 90.);
     }
 
+    #[test]
+    fn step_across_wrapping_edge() {
+        let mut space = Space::empty_positive(4, 4, 4);
+        space.set_physics(SpacePhysics {
+            topology: Topology::wrapping(true, false, false),
+            ..SpacePhysics::default()
+        });
+        let mut body = Body {
+            flying: true,
+            position: Point3::new(3.5, 2.0, 2.0),
+            velocity: Vector3::new(2.0, 0.0, 0.0),
+            ..test_body()
+        };
+
+        body.step(Tick::from_seconds(0.5), Some(&space), |_| {});
+        assert_eq!(body.position, Point3::new(0.5, 2.0, 2.0));
+    }
+
     #[test]
     fn body_transaction_systematic() {
         // TODO: this test is pretty flimsy ... because BodyTransaction hasn't actually got a
         // full set of operations yet and because the TransactionTester can't quite handle
         // additive rather than conflicting transactions well
+        let turn = BodyTransaction {
+            delta_yaw: 10.0,
+            ..Default::default()
+        };
+        let push = BodyTransaction {
+            delta_velocity: Vector3::new(0.0, 1.0, 0.0),
+            ..Default::default()
+        };
         TransactionTester::new()
             .transaction(BodyTransaction::default(), |_, _| Ok(()))
-            .transaction(BodyTransaction { delta_yaw: 10.0 }, |before, after| {
+            .transaction(turn, |before, after| {
                 if false {
                     // TODO: figure out how to make this assert work in the presence of more transactions
                     let expected = &Body {
@@ -464,6 +644,7 @@ mod tests {
                 }
                 Ok(())
             })
+            .transaction(push, |_, _| Ok(()))
             .target(test_body)
             .test();
     }
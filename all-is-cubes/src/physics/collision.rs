@@ -102,6 +102,28 @@ pub(crate) fn find_colliding_cubes(
     })
 }
 
+/// Returns how much of `aab` is immersed in [fluid](BlockCollision::Fluid) blocks in
+/// `space`: the sum over the cubes it intersects of the volume of the intersection times
+/// the block's [density](crate::block::BlockAttributes::density), divided by the volume
+/// of `aab`. If `aab` has zero volume, returns zero.
+pub(crate) fn fluid_immersion(space: &Space, aab: Aab) -> FreeCoordinate {
+    let volume = aab.volume();
+    if volume <= 0.0 {
+        return 0.0;
+    }
+    let mut displaced = 0.0;
+    for cube in aab.round_up_to_grid().interior_iter() {
+        let attributes = &space.get_evaluated(cube).attributes;
+        if attributes.collision != BlockCollision::Fluid {
+            continue;
+        }
+        if let Some(overlap) = aab.intersection(Aab::from_cube(cube)) {
+            displaced += overlap.volume() * FreeCoordinate::from(attributes.density.into_inner());
+        }
+    }
+    displaced / volume
+}
+
 /// Given a ray describing movement of the origin of an AAB, perform a raycast to find
 /// the positions where the AAB moves into new cubes.
 ///
@@ -0,0 +1,188 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Export of the collision geometry of a [`Space`], for use with other physics engines.
+
+use cgmath::Point3;
+
+use crate::block::BlockCollision;
+use crate::math::{Aab, FreeCoordinate, GridPoint};
+use crate::space::{Grid, Space};
+
+/// Returns boxes which do not overlap and which together cover exactly the cubes in
+/// `region` of `space` that a [`Body`](super::Body) would collide with.
+///
+/// Adjacent solid cubes are merged into larger boxes, so there are usually far fewer
+/// boxes than cubes, but the result is not guaranteed to be the smallest possible set.
+/// This is suitable for mirroring the world into a physics engine which works with
+/// boxes; cubes outside `region` or outside the bounds of `space` are not included.
+///
+/// ```
+/// use all_is_cubes::block::AIR;
+/// use all_is_cubes::math::{Aab, Rgba};
+/// use all_is_cubes::physics::collision_boxes;
+/// use all_is_cubes::space::Space;
+///
+/// let mut space = Space::empty_positive(4, 1, 1);
+/// space.fill_uniform(space.grid(), Rgba::WHITE).unwrap();
+/// space.set([2, 0, 0], AIR).unwrap();
+/// assert_eq!(
+///     collision_boxes(&space, space.grid()),
+///     vec![
+///         Aab::new(0.0, 2.0, 0.0, 1.0, 0.0, 1.0),
+///         Aab::new(3.0, 4.0, 0.0, 1.0, 0.0, 1.0),
+///     ],
+/// );
+/// ```
+pub fn collision_boxes(space: &Space, region: Grid) -> Vec<Aab> {
+    let grid = match region.intersection(space.grid()) {
+        Some(grid) => grid,
+        None => return Vec::new(),
+    };
+    // Solid cubes not yet covered by a box.
+    let mut remaining: Vec<bool> = grid
+        .interior_iter()
+        .map(|cube| {
+            // TODO: change this from `==` to `match` to allow for expansion of the enum
+            space.get_evaluated(cube).attributes.collision == BlockCollision::Hard
+        })
+        .collect();
+    let index = |cube: GridPoint| grid.index(cube).unwrap();
+
+    let mut boxes = Vec::new();
+    for start in grid.interior_iter() {
+        if !remaining[index(start)] {
+            continue;
+        }
+        let upper = grid.upper_bounds();
+        let all_remaining =
+            |remaining: &[bool], b: Grid| b.interior_iter().all(|cube| remaining[index(cube)]);
+
+        // Grow the box along each axis in turn, as far as every cube it would add is
+        // solid and not already covered.
+        let mut size = [1; 3];
+        for axis in 0..3 {
+            while start[axis] + size[axis] < upper[axis] {
+                let mut slab_lower = start;
+                slab_lower[axis] += size[axis];
+                let mut slab_size = size;
+                slab_size[axis] = 1;
+                if !all_remaining(&remaining, Grid::new(slab_lower, slab_size)) {
+                    break;
+                }
+                size[axis] += 1;
+            }
+        }
+
+        let found = Grid::new(start, size);
+        for cube in found.interior_iter() {
+            remaining[index(cube)] = false;
+        }
+        boxes.push(grid_to_aab(found));
+    }
+    boxes
+}
+
+/// Returns triangles which together form the surfaces of [`collision_boxes`], for use
+/// with physics engines which work with triangle meshes.
+///
+/// Each triangle's vertices are in counterclockwise order as seen from outside the box.
+/// Faces shared by two adjacent boxes are not removed.
+pub fn collision_triangles(space: &Space, region: Grid) -> Vec<[Point3<FreeCoordinate>; 3]> {
+    /// Corners of each face of a box, counterclockwise from outside, as indices into
+    /// the corners where bits 0, 1, and 2 select the upper bound of X, Y, and Z.
+    const FACES: [[usize; 4]; 6] = [
+        [0, 4, 6, 2], // -X
+        [1, 3, 7, 5], // +X
+        [0, 1, 5, 4], // -Y
+        [2, 6, 7, 3], // +Y
+        [0, 2, 3, 1], // -Z
+        [4, 5, 7, 6], // +Z
+    ];
+
+    let mut triangles = Vec::new();
+    for aab in collision_boxes(space, region) {
+        let l = aab.lower_bounds_p();
+        let u = aab.upper_bounds_p();
+        let corner = |i: usize| {
+            Point3::new(
+                if i & 1 == 0 { l.x } else { u.x },
+                if i & 2 == 0 { l.y } else { u.y },
+                if i & 4 == 0 { l.z } else { u.z },
+            )
+        };
+        for &[a, b, c, d] in FACES.iter() {
+            triangles.push([corner(a), corner(b), corner(c)]);
+            triangles.push([corner(a), corner(c), corner(d)]);
+        }
+    }
+    triangles
+}
+
+fn grid_to_aab(grid: Grid) -> Aab {
+    Aab::from_lower_upper(
+        grid.lower_bounds().cast::<FreeCoordinate>().unwrap(),
+        grid.upper_bounds().cast::<FreeCoordinate>().unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{Block, AIR};
+    use crate::content::make_some_blocks;
+    use crate::math::Rgba;
+    use cgmath::InnerSpace as _;
+
+    #[test]
+    fn boxes_cover_solid_cubes_exactly() {
+        let [block] = make_some_blocks();
+        let mut space = Space::empty_positive(4, 4, 4);
+        space.fill_uniform(space.grid(), &block).unwrap();
+        space.set([1, 2, 3], AIR).unwrap();
+        space.set([3, 0, 0], AIR).unwrap();
+        let not_solid = Block::builder()
+            .color(Rgba::WHITE)
+            .collision(BlockCollision::None)
+            .build();
+        space.set([0, 3, 1], not_solid).unwrap();
+
+        let boxes = collision_boxes(&space, space.grid());
+        assert!(boxes.len() < 61, "{:?}", boxes);
+        for cube in space.grid().interior_iter() {
+            let cube_aab = Aab::from_cube(cube);
+            let covering = boxes
+                .iter()
+                .filter(|aab| aab.intersection(cube_aab).is_some())
+                .count();
+            let solid = space.get_evaluated(cube).attributes.collision == BlockCollision::Hard;
+            assert_eq!(covering, usize::from(solid), "{:?}", cube);
+        }
+    }
+
+    #[test]
+    fn region_limits_boxes() {
+        let [block] = make_some_blocks();
+        let mut space = Space::empty_positive(4, 1, 1);
+        space.fill_uniform(space.grid(), &block).unwrap();
+        assert_eq!(
+            collision_boxes(&space, Grid::new([1, 0, 0], [10, 1, 1])),
+            vec![Aab::new(1.0, 4.0, 0.0, 1.0, 0.0, 1.0)],
+        );
+        assert!(collision_boxes(&space, Grid::new([5, 0, 0], [1, 1, 1])).is_empty());
+    }
+
+    #[test]
+    fn triangles_face_outward() {
+        let [block] = make_some_blocks();
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &block).unwrap();
+        let triangles = collision_triangles(&space, space.grid());
+        assert_eq!(triangles.len(), 12);
+        let center = Point3::new(0.5, 0.5, 0.5);
+        for [a, b, c] in triangles {
+            let normal = (b - a).cross(c - a);
+            assert!(normal.dot(a - center) > 0.0, "{:?}", [a, b, c]);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Re-exports of the types most commonly needed to work with All is Cubes, so that
+//! they can all be imported at once:
+//!
+//! ```
+//! use all_is_cubes::prelude::*;
+//!
+//! let mut universe = Universe::new();
+//! let mut space = Space::empty(Grid::new([0, 0, 0], [4, 4, 4]));
+//! space.set(GridPoint::new(1, 0, 1), Block::from(Rgba::WHITE)).unwrap();
+//! assert_eq!(space[[0, 0, 0]], AIR);
+//! let space_ref: URef<Space> = universe.insert_anonymous(space);
+//! assert_eq!(space_ref.borrow()[[1, 0, 1]], Block::from(Rgba::WHITE));
+//! ```
+//!
+//! Items are added here only once their names and purposes are settled, and are not
+//! removed except in a release which is already breaking compatibility for other
+//! reasons; so code which uses only the prelude should need fewer changes when
+//! upgrading than code which imports from each module.
+
+pub use crate::block::{Block, AIR};
+pub use crate::camera::Camera;
+pub use crate::math::{Face, FreeCoordinate, GridCoordinate, GridPoint, GridVector, Rgb, Rgba};
+pub use crate::space::{Grid, Space};
+pub use crate::universe::{URef, Universe};
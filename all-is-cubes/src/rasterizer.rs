@@ -0,0 +1,485 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Software rasterizer for [`triangulator`](crate::triangulator) meshes.
+//!
+//! This draws the same triangles a GPU renderer would, without needing a GPU, so that
+//! triangulator output can be checked in tests and images can be produced on machines
+//! without graphics hardware. It is slow, and implements only as much of the shading
+//! as [`LightingOption::Flat`](crate::camera::LightingOption::Flat) requires; smooth lighting is drawn as flat.
+//!
+//! ```
+//! use all_is_cubes::prelude::*;
+//! use all_is_cubes::camera::{GraphicsOptions, Viewport};
+//! use all_is_cubes::rasterizer::rasterize_space;
+//!
+//! let mut space = Space::empty_positive(1, 1, 1);
+//! space.set([0, 0, 0], &Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0))).unwrap();
+//! let camera = Camera::new(
+//!     GraphicsOptions::default(),
+//!     Viewport {
+//!         nominal_size: [16., 16.].into(),
+//!         framebuffer_size: [16, 16].into(),
+//!     },
+//! );
+//! let image = rasterize_space(&space, &camera);
+//! assert_eq!(image.len(), 16 * 16);
+//! ```
+
+use cgmath::{EuclideanSpace as _, InnerSpace as _, Point2, Point3, Vector2, Vector3, Vector4};
+use std::cell::RefCell;
+use std::convert::TryFrom as _;
+use std::rc::Rc;
+
+use crate::camera::{Camera, FogParameters};
+use crate::math::{Face, FreeCoordinate, GridCoordinate, GridPoint, Rgb, Rgba};
+use crate::raytracer::fixed_directional_lighting;
+use crate::space::{PackedLight, Skybox, Space};
+use crate::triangulator::{
+    triangulate_blocks, triangulate_space, BlockTriangulations, BlockVertex, Coloring,
+    DepthOrdering, GfxVertex, SpaceTriangulation, Texel, TextureAllocator, TextureCoordinate,
+    TextureTile,
+};
+
+/// Triangulate and rasterize `space` as seen by `camera`, using the camera's graphics
+/// options, into an image the size of the camera's viewport (in the usual left-right
+/// then top-bottom raster order).
+pub fn rasterize_space(space: &Space, camera: &Camera) -> Box<[Rgba]> {
+    let options = camera.options();
+    let mut textures = SoftTextureAllocator::new(16);
+    let block_triangulations: BlockTriangulations<SoftVertex, SoftTextureTile> =
+        triangulate_blocks(space, &mut textures, &options.transparency);
+    let mut triangulation: SpaceTriangulation<SoftVertex> =
+        triangulate_space(space, space.grid(), options, &*block_triangulations);
+    triangulation.depth_sort_for_view(camera.view_position());
+
+    let mut raster = Rasterizer::new(camera, space.physics().sky_color);
+    if let Some(skybox) = &space.physics().skybox {
+        raster.draw_skybox(skybox);
+    }
+    raster.draw_space_triangulation(&triangulation, &textures);
+    raster.into_image()
+}
+
+/// [`GfxVertex`] for the software rasterizer; keeps the light value the triangulator
+/// computes, which is equivalent to [`LightingOption::Flat`](crate::camera::LightingOption::Flat).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoftVertex {
+    position: Point3<FreeCoordinate>,
+    face: Face,
+    coloring: Coloring,
+    lighting: Rgb,
+}
+
+impl From<BlockVertex> for SoftVertex {
+    fn from(vertex: BlockVertex) -> Self {
+        Self {
+            position: vertex.position,
+            face: vertex.face,
+            coloring: vertex.coloring,
+            lighting: Rgb::ONE,
+        }
+    }
+}
+
+impl GfxVertex for SoftVertex {
+    type Coordinate = FreeCoordinate;
+    type BlockInst = Vector3<FreeCoordinate>;
+    const WANTS_LIGHT: bool = true;
+
+    fn instantiate_block(cube: GridPoint) -> Self::BlockInst {
+        cube.to_vec().map(FreeCoordinate::from)
+    }
+
+    fn instantiate_vertex(&mut self, offset: Self::BlockInst, lighting: PackedLight) {
+        self.position += offset;
+        self.lighting = lighting.value();
+    }
+
+    fn position(&self) -> Point3<FreeCoordinate> {
+        self.position
+    }
+
+    fn face(&self) -> Face {
+        self.face
+    }
+}
+
+/// [`TextureAllocator`] keeping texels in memory for the rasterizer to sample.
+///
+/// Tiles are stacked along the Z axis: tile `i` occupies texture coordinates
+/// `i ≤ z < i + 1`. Tiles are never deallocated.
+#[derive(Debug)]
+pub struct SoftTextureAllocator {
+    resolution: GridCoordinate,
+    texels: Rc<RefCell<Vec<Texel>>>,
+    count: usize,
+}
+
+impl SoftTextureAllocator {
+    /// Creates an allocator whose tiles have the given `resolution` on each axis.
+    pub fn new(resolution: GridCoordinate) -> Self {
+        Self {
+            resolution,
+            texels: Rc::new(RefCell::new(Vec::new())),
+            count: 0,
+        }
+    }
+
+    /// Returns the color at the given texture coordinates (nearest-neighbor), or
+    /// magenta if there is no tile there.
+    pub fn sample(&self, coord: Vector3<TextureCoordinate>) -> Rgba {
+        let resolution = self.resolution;
+        let tile = coord.z.floor();
+        let in_tile = Vector3::new(coord.x, coord.y, coord.z - tile);
+        let texel = in_tile.map(|c| {
+            ((c * resolution as TextureCoordinate).floor() as GridCoordinate)
+                .max(0)
+                .min(resolution - 1)
+        });
+        let index = (tile as usize) * (resolution as usize).pow(3)
+            + ((texel.z * resolution + texel.y) * resolution + texel.x) as usize;
+        match self.texels.borrow().get(index) {
+            Some(&[r, g, b, a]) => Rgba::from_linear_32bit((r, g, b, a)),
+            None => Rgba::new(1.0, 0.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl TextureAllocator for SoftTextureAllocator {
+    type Tile = SoftTextureTile;
+
+    fn resolution(&self) -> GridCoordinate {
+        self.resolution
+    }
+
+    fn allocate(&mut self) -> Option<Self::Tile> {
+        let index = self.count;
+        self.count += 1;
+        let tile_size = usize::try_from(self.resolution).unwrap().pow(3);
+        self.texels
+            .borrow_mut()
+            .resize(self.count * tile_size, [0, 0, 0, 0]);
+        Some(SoftTextureTile {
+            texels: self.texels.clone(),
+            index,
+            tile_size,
+        })
+    }
+}
+
+/// Tile allocated by [`SoftTextureAllocator`].
+#[derive(Clone, Debug)]
+pub struct SoftTextureTile {
+    texels: Rc<RefCell<Vec<Texel>>>,
+    index: usize,
+    tile_size: usize,
+}
+
+impl TextureTile for SoftTextureTile {
+    fn texcoord(&self, in_tile: Vector3<TextureCoordinate>) -> Vector3<TextureCoordinate> {
+        in_tile + Vector3::new(0.0, 0.0, self.index as TextureCoordinate)
+    }
+
+    fn write(&mut self, data: &[Texel]) {
+        assert_eq!(data.len(), self.tile_size);
+        let start = self.index * self.tile_size;
+        self.texels.borrow_mut()[start..][..self.tile_size].copy_from_slice(data);
+    }
+}
+
+/// Color and depth buffers, and the triangle drawing algorithm.
+///
+/// Use [`rasterize_space`] unless you need to draw a mesh not produced by
+/// [`triangulate_space`].
+pub struct Rasterizer<'a> {
+    camera: &'a Camera,
+    size: Vector2<usize>,
+    fog: FogParameters,
+    fog_color: Rgb,
+    color: Vec<Vector3<f32>>,
+    depth: Vec<FreeCoordinate>,
+}
+
+impl std::fmt::Debug for Rasterizer<'_> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("Rasterizer")
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl<'a> Rasterizer<'a> {
+    /// Creates a rasterizer drawing from the point of view of `camera` into an image
+    /// the size of its viewport, initially filled with `background`. Distant triangles
+    /// are fogged into `background` as well, as the GPU renderer does with the sky color.
+    pub fn new(camera: &'a Camera, background: Rgb) -> Self {
+        let size = camera.viewport().framebuffer_size.map(|s| s as usize);
+        let count = size.x * size.y;
+        Self {
+            camera,
+            size,
+            fog: FogParameters::new(camera.options()),
+            fog_color: background,
+            color: vec![background.into(); count],
+            depth: vec![FreeCoordinate::INFINITY; count],
+        }
+    }
+
+    /// Replace the background color with the view of `skybox`. Anything already drawn
+    /// is overwritten, so this should be called first.
+    pub fn draw_skybox(&mut self, skybox: &Skybox) {
+        let viewport = self.camera.viewport();
+        for y in 0..self.size.y {
+            let ndc_y = viewport.normalize_fb_y(y);
+            for x in 0..self.size.x {
+                let ray = self
+                    .camera
+                    .project_ndc_into_world(Point2::new(viewport.normalize_fb_x(x), ndc_y));
+                self.color[y * self.size.x + x] = skybox.sample(ray.direction).into();
+            }
+        }
+    }
+
+    /// Draw all triangles of `triangulation`, opaque ones first. The transparent
+    /// triangles are drawn in their current order, so
+    /// [`SpaceTriangulation::depth_sort_for_view`] should have been called.
+    pub fn draw_space_triangulation(
+        &mut self,
+        triangulation: &SpaceTriangulation<SoftVertex>,
+        textures: &SoftTextureAllocator,
+    ) {
+        let indices = triangulation.indices();
+        let vertices = triangulation.vertices();
+        for triangle in indices[triangulation.opaque_range()].chunks_exact(3) {
+            self.draw_triangle(triangle, vertices, textures, false);
+        }
+        for triangle in
+            indices[triangulation.transparent_range(DepthOrdering::Within)].chunks_exact(3)
+        {
+            self.draw_triangle(triangle, vertices, textures, true);
+        }
+    }
+
+    /// Draw one triangle, given as three indices into `vertices`.
+    ///
+    /// If `blend` is false, alpha is ignored (as in the opaque shader pass) and depth is
+    /// written; otherwise the triangle is composited over the existing color and depth
+    /// is only tested.
+    ///
+    /// Triangles which cross the plane of the eye are skipped rather than clipped.
+    pub fn draw_triangle(
+        &mut self,
+        triangle: &[u32],
+        vertices: &[SoftVertex],
+        textures: &SoftTextureAllocator,
+        blend: bool,
+    ) {
+        let matrix = self.camera.projection() * self.camera.view_matrix();
+        let verts: [&SoftVertex; 3] = [
+            &vertices[triangle[0] as usize],
+            &vertices[triangle[1] as usize],
+            &vertices[triangle[2] as usize],
+        ];
+        let clip: [Vector4<FreeCoordinate>; 3] = [
+            matrix * verts[0].position.to_homogeneous(),
+            matrix * verts[1].position.to_homogeneous(),
+            matrix * verts[2].position.to_homogeneous(),
+        ];
+        if clip.iter().any(|c| c.w <= 0.0) {
+            return;
+        }
+        // Screen coordinates in pixels (x, y) and NDC depth (z).
+        let size = self.size.map(|s| s as FreeCoordinate);
+        let to_screen = |c: Vector4<FreeCoordinate>| {
+            Vector3::new(
+                (c.x / c.w + 1.0) * 0.5 * size.x,
+                (1.0 - c.y / c.w) * 0.5 * size.y,
+                c.z / c.w,
+            )
+        };
+        let screen = [to_screen(clip[0]), to_screen(clip[1]), to_screen(clip[2])];
+        let eye = self.camera.view_position();
+
+        let area = edge(screen[0], screen[1], screen[2]);
+        if area == 0.0 {
+            return;
+        }
+
+        let x_range = pixel_range(screen.iter().map(|p| p.x), self.size.x);
+        let y_range = pixel_range(screen.iter().map(|p| p.y), self.size.y);
+        for y in y_range {
+            for x in x_range.clone() {
+                let p = Vector3::new(x as FreeCoordinate + 0.5, y as FreeCoordinate + 0.5, 0.0);
+                let b = [
+                    edge(screen[1], screen[2], p) / area,
+                    edge(screen[2], screen[0], p) / area,
+                    edge(screen[0], screen[1], p) / area,
+                ];
+                if b.iter().any(|&w| w < 0.0) {
+                    continue;
+                }
+                let z = b[0] * screen[0].z + b[1] * screen[1].z + b[2] * screen[2].z;
+                let pixel = y * self.size.x + x;
+                if z < -1.0 || z > 1.0 || z >= self.depth[pixel] {
+                    continue;
+                }
+
+                // Perspective-correct interpolation weights.
+                let pw = [b[0] / clip[0].w, b[1] / clip[1].w, b[2] / clip[2].w];
+                let pw_sum = pw[0] + pw[1] + pw[2];
+                let pw = [
+                    (pw[0] / pw_sum) as f32,
+                    (pw[1] / pw_sum) as f32,
+                    (pw[2] / pw_sum) as f32,
+                ];
+
+                let mut surface = fragment_color(verts, pw, textures);
+                if !blend {
+                    surface = surface.to_rgb().with_alpha_one();
+                }
+                // Flat lighting is constant over the face, so any vertex will do.
+                let lit = surface.to_rgb()
+                    * verts[0].lighting
+                    * fixed_directional_lighting(verts[0].face);
+                let position = verts[0].position.to_vec() * FreeCoordinate::from(pw[0])
+                    + verts[1].position.to_vec() * FreeCoordinate::from(pw[1])
+                    + verts[2].position.to_vec() * FreeCoordinate::from(pw[2]);
+                let fog_mix = self
+                    .fog
+                    .fog_mix((Point3::from_vec(position) - eye).magnitude() as f32);
+                let lit = lit * (1.0 - fog_mix) + self.fog_color * fog_mix;
+                let alpha = surface.alpha().into_inner().max(0.0).min(1.0);
+                let lit: Vector3<f32> = lit.into();
+                self.color[pixel] = lit * alpha + self.color[pixel] * (1.0 - alpha);
+                if !blend {
+                    self.depth[pixel] = z;
+                }
+            }
+        }
+    }
+
+    /// Returns the image drawn so far.
+    pub fn into_image(self) -> Box<[Rgba]> {
+        self.color
+            .into_iter()
+            .map(|c| Rgba::try_from(c.extend(1.0)).unwrap())
+            .collect()
+    }
+}
+
+/// Compute the unlit color of a fragment with the given interpolation weights.
+/// Equivalent to the `diffuse_color` computation in `fragment.glsl`.
+fn fragment_color(
+    verts: [&SoftVertex; 3],
+    weights: [f32; 3],
+    textures: &SoftTextureAllocator,
+) -> Rgba {
+    match verts[0].coloring {
+        Coloring::Solid(color) => color,
+        Coloring::Texture {
+            clamp_min,
+            clamp_max,
+            ..
+        } => {
+            let mut coord = Vector3::new(0.0, 0.0, 0.0);
+            for (vertex, &weight) in verts.iter().zip(weights.iter()) {
+                if let Coloring::Texture { pos, .. } = vertex.coloring {
+                    coord += pos * weight;
+                }
+            }
+            let coord = Vector3::new(
+                coord.x.max(clamp_min.x).min(clamp_max.x),
+                coord.y.max(clamp_min.y).min(clamp_max.y),
+                coord.z.max(clamp_min.z).min(clamp_max.z),
+            );
+            textures.sample(coord)
+        }
+    }
+}
+
+/// Signed area of the parallelogram formed by `a`, `b`, and `p`, ignoring z.
+fn edge(
+    a: Vector3<FreeCoordinate>,
+    b: Vector3<FreeCoordinate>,
+    p: Vector3<FreeCoordinate>,
+) -> FreeCoordinate {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Range of pixel indices whose centers might be within the given coordinates.
+fn pixel_range(
+    coords: impl Iterator<Item = FreeCoordinate> + Clone,
+    size: usize,
+) -> std::ops::Range<usize> {
+    let low = coords
+        .clone()
+        .fold(FreeCoordinate::INFINITY, FreeCoordinate::min);
+    let high = coords.fold(FreeCoordinate::NEG_INFINITY, FreeCoordinate::max);
+    let low = (low - 0.5).ceil().max(0.0) as usize;
+    let high = ((high - 0.5).floor() + 1.0)
+        .max(0.0)
+        .min(size as FreeCoordinate) as usize;
+    low..high.max(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::camera::{GraphicsOptions, Viewport};
+    use cgmath::Matrix4;
+
+    fn camera_looking_at_origin(eye: [FreeCoordinate; 3]) -> Camera {
+        let mut camera = Camera::new(
+            GraphicsOptions::default(),
+            Viewport {
+                nominal_size: Vector2::new(9., 9.),
+                framebuffer_size: Vector2::new(9, 9),
+            },
+        );
+        camera.set_view_matrix(Matrix4::look_at_rh(
+            Point3::from(eye),
+            Point3::new(0.5, 0.5, 0.5),
+            Vector3::new(0., 1., 0.),
+        ));
+        camera
+    }
+
+    #[test]
+    fn single_block_covers_center_not_corners() {
+        let color = Rgba::new(1.0, 0.5, 0.0, 1.0);
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &Block::from(color)).unwrap();
+        let sky = space.physics().sky_color.with_alpha_one();
+
+        let camera = camera_looking_at_origin([0.5, 0.5, 4.0]);
+        let image = rasterize_space(&space, &camera);
+
+        assert_eq!(image.len(), 81);
+        assert_eq!(image[0], sky);
+        assert_eq!(image[80], sky);
+        let center = image[4 * 9 + 4];
+        assert_ne!(center, sky);
+        // Lighting may scale the color, but not change its hue.
+        assert!(
+            center.red() > center.green() && center.green() > center.blue(),
+            "{:?}",
+            center
+        );
+    }
+
+    #[test]
+    fn nearer_opaque_triangle_wins() {
+        let mut space = Space::empty_positive(1, 1, 2);
+        let near = Block::from(Rgba::new(0.0, 1.0, 0.0, 1.0));
+        let far = Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0));
+        space.set([0, 0, 1], &near).unwrap();
+        space.set([0, 0, 0], &far).unwrap();
+
+        let camera = camera_looking_at_origin([0.5, 0.5, 6.0]);
+        let image = rasterize_space(&space, &camera);
+        let center = image[4 * 9 + 4];
+        assert!(center.green() > center.red(), "{:?}", center);
+    }
+}
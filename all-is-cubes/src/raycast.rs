@@ -388,7 +388,7 @@ impl Iterator for Raycaster {
     // count, last (requires precise version of size_hint algorithm)
 }
 
-impl std::iter::FusedIterator for Raycaster {}
+impl core::iter::FusedIterator for Raycaster {}
 
 /// Describes a ray crossing into a cube as defined by [`Raycaster`].
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -14,19 +14,34 @@
 //! as a means to display the state of `Space`s used for testing inline in test output.
 
 use cgmath::{EuclideanSpace as _, InnerSpace as _, Matrix4, Point2, Vector2, Vector3, Zero as _};
-use cgmath::{Point3, Vector4};
+use cgmath::{Point3, SquareMatrix as _, Transform as _, Vector4};
 use ouroboros::self_referencing;
 #[cfg(feature = "rayon")]
 use rayon::iter::{IntoParallelIterator as _, ParallelIterator as _};
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::TryFrom;
-
-use crate::block::{recursive_ray, Evoxel, Resolution};
-use crate::camera::{eye_for_look_at, Camera, GraphicsOptions, LightingOption, Viewport};
+use std::sync::Mutex;
+
+use crate::block::{recursive_ray, Block, Evoxel, Resolution};
+use crate::camera::{
+    eye_for_look_at, Camera, Eye, FogParameters, GraphicsOptions, LightingOption, StereoCamera,
+    Viewport,
+};
+use crate::entity::EntityShape;
 use crate::math::{smoothstep, GridCoordinate};
 use crate::math::{Face, FreeCoordinate, GridPoint, Rgb, Rgba};
-use crate::raycast::Ray;
-use crate::space::{Grid, GridArray, PackedLight, Space, SpaceBlockData};
+use crate::raycast::{Ray, Raycaster};
+use crate::space::{Grid, GridArray, PackedLight, Skybox, Space, SpaceBlockData, Topology};
+use crate::trace::trace_span;
+use crate::triangulator::billboard_image;
+
+mod cinematic;
+pub use cinematic::*;
+mod icon;
+pub use icon::*;
+mod progressive;
+pub use progressive::*;
 
 /// Precomputed data for raytracing a single frame of a single Space, and bearer of the
 /// methods for actually performing raytracing.
@@ -40,11 +55,22 @@ struct SpaceRaytracerImpl<P: PixelBuf> {
     #[borrows(blocks)]
     #[covariant]
     cubes: GridArray<TracingCubeData<'this, P::BlockData>>,
+    entities: Vec<TracingEntity<P::BlockData>>,
 
     options: GraphicsOptions,
+    fog: FogParameters,
     sky_color: Rgb,
+    skybox: Option<Skybox>,
+    topology: Topology,
+
+    /// Cache for [`SpaceRaytracer::get_interpolated_light`], split into independently
+    /// locked shards so that parallel tracing does not contend on one lock.
+    light_cache: [Mutex<HashMap<(GridPoint, Face), LightNeighborhood>>; LIGHT_CACHE_SHARDS],
 }
 
+/// Number of shards of [`SpaceRaytracerImpl::light_cache`].
+const LIGHT_CACHE_SHARDS: usize = 16;
+
 impl<P: PixelBuf> SpaceRaytracer<P> {
     /// Snapshots the given [`Space`] to prepare for raytracing it.
     pub fn new(space: &Space, options: GraphicsOptions) -> Self {
@@ -54,79 +80,253 @@ impl<P: PixelBuf> SpaceRaytracer<P> {
                 cubes_builder: |blocks: &Box<[TracingBlock<P::BlockData>]>| {
                     prepare_cubes::<P>(blocks, space)
                 },
+                entities: prepare_entities::<P>(space, &options),
+                fog: FogParameters::new(&options),
                 options,
                 sky_color: space.physics().sky_color,
+                skybox: space.physics().skybox.clone(),
+                topology: space.physics().topology,
+                light_cache: Default::default(),
             }
             .build(),
         )
     }
 
     /// Computes a single image pixel from the given ray.
+    ///
+    /// Surfaces are fogged according to their distance from the ray's origin, which
+    /// should therefore be at or near the eye, as it is for rays from
+    /// [`Camera::project_ndc_into_world`]. Surfaces farther away than
+    /// [`GraphicsOptions::view_distance`] are not drawn at all.
     pub fn trace_ray(&self, ray: Ray) -> (P::Pixel, RaytraceInfo) {
+        let mut tracing = self.start_ray(ray);
+        while self.step_ray(&mut tracing) {}
+        self.finish_ray(tracing)
+    }
+
+    /// Computes image pixels for many rays; the result is the same as calling
+    /// [`Self::trace_ray`] on each one, in the same order.
+    ///
+    /// This is faster than separate calls when the rays are coherent, as rays for
+    /// neighboring pixels are: rays which step mostly along the same axis are grouped
+    /// into packets and traced in lock-step, so that each step of a packet visits
+    /// nearby cubes.
+    pub fn trace_rays(&self, rays: &[Ray]) -> Vec<(P::Pixel, RaytraceInfo)> {
+        // Stable sort, so rays that were adjacent in the input stay adjacent.
+        let mut order: Vec<usize> = (0..rays.len()).collect();
+        order.sort_by_key(|&i| major_axis(rays[i].direction));
+
+        let mut results: Vec<Option<(P::Pixel, RaytraceInfo)>> =
+            (0..rays.len()).map(|_| None).collect();
+        let mut packet: Vec<(usize, RayTracing<P>)> = Vec::with_capacity(RAY_PACKET_SIZE);
+        let mut remaining = &order[..];
+        while let Some(&first) = remaining.first() {
+            let axis = major_axis(rays[first].direction);
+            let packet_len = remaining
+                .iter()
+                .take(RAY_PACKET_SIZE)
+                .take_while(|&&i| major_axis(rays[i].direction) == axis)
+                .count();
+            packet.extend(
+                remaining[..packet_len]
+                    .iter()
+                    .map(|&i| (i, self.start_ray(rays[i]))),
+            );
+            remaining = &remaining[packet_len..];
+
+            while !packet.is_empty() {
+                let mut k = 0;
+                while k < packet.len() {
+                    if self.step_ray(&mut packet[k].1) {
+                        k += 1;
+                    } else {
+                        let (i, tracing) = packet.swap_remove(k);
+                        results[i] = Some(self.finish_ray(tracing));
+                    }
+                }
+            }
+        }
+
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    fn start_ray(&self, ray: Ray) -> RayTracing<P> {
         self.0.with(|impl_fields| {
+            // Converts raycast t-distances into world distances.
+            let t_scale = ray.direction.magnitude();
+            let max_t = impl_fields.options.view_distance.into_inner() / t_scale;
+            let entity = impl_fields
+                .entities
+                .iter()
+                .enumerate()
+                .filter_map(|(index, entity)| {
+                    let (t_distance, face, color) = entity.intersect(ray)?;
+                    (t_distance <= max_t).then(|| EntityHit {
+                        t_distance,
+                        face,
+                        color,
+                        index,
+                    })
+                })
+                .min_by(|a, b| {
+                    a.t_distance
+                        .partial_cmp(&b.t_distance)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+            let grid = impl_fields.topology.raycast_grid(
+                impl_fields.cubes.grid(),
+                impl_fields.options.view_distance.into_inner(),
+            );
+            RayTracing {
+                ray,
+                raycaster: ray.cast().within_grid(grid),
+                state: TracingState::new(*impl_fields.sky_color),
+                max_t,
+                t_scale: t_scale as f32,
+                entity,
+            }
+        })
+    }
+
+    /// Traces `tracing` through one more cube. Returns false if the ray is finished.
+    fn step_ray(&self, tracing: &mut RayTracing<P>) -> bool {
+        self.0.with(|impl_fields| {
+            let RayTracing {
+                ray,
+                raycaster,
+                state: s,
+                max_t,
+                t_scale,
+                entity,
+            } = tracing;
+            let (ray, max_t, t_scale) = (*ray, *max_t, *t_scale);
             let cubes = impl_fields.cubes;
-            let mut s: TracingState<P> = TracingState::default();
-            for hit in ray.cast().within_grid(cubes.grid()) {
-                if s.count_step_should_stop() {
-                    break;
+            let fog = impl_fields.fog;
+
+            let hit = match raycaster.next() {
+                Some(hit) => hit,
+                None => return false,
+            };
+            if hit.t_distance() > max_t {
+                // Beyond the view distance; this also keeps huge spaces from
+                // being traced all the way through.
+                return false;
+            }
+            if s.count_step_should_stop() {
+                return false;
+            }
+            if let Some(entity_hit) = *entity {
+                // The entity is in front of every surface in this cube and beyond.
+                if entity_hit.t_distance <= hit.t_distance() {
+                    *entity = None;
+                    self.trace_entity(entity_hit, ray, s, t_scale);
                 }
+            }
 
-                match &cubes[hit.cube_ahead()].block {
-                    TracingBlock::Atom(pixel_block_data, color) => {
-                        if color.fully_transparent() {
-                            continue;
+            let cube = impl_fields
+                .topology
+                .wrap_cube(cubes.grid(), hit.cube_ahead());
+            match &cubes[cube].block {
+                TracingBlock::Atom(pixel_block_data, color) => {
+                    if color.fully_transparent() {
+                        return true;
+                    }
+                    // TODO: To implement TransparencyOption::Volumetric we need to peek forward to the next change of color and find the distance between them, but only if the alpha is not 0 or 1. (Same here and in the recursive block case.)
+                    let lighting = match impl_fields.options.lighting_display {
+                        LightingOption::None => Rgb::ONE,
+                        LightingOption::Flat => self.get_lighting(hit.cube_behind()),
+                        LightingOption::Smooth => {
+                            self.get_interpolated_light(hit.intersection_point(ray), hit.face(), s)
+                        }
+                    };
+                    s.trace_through_surface(
+                        pixel_block_data,
+                        *color,
+                        lighting,
+                        hit.face(),
+                        &impl_fields.options,
+                        fog.fog_mix(hit.t_distance() as f32 * t_scale),
+                    );
+                }
+                TracingBlock::Recur(pixel_block_data, resolution, array) => {
+                    let resolution = *resolution;
+                    let sub_ray = recursive_ray(ray, hit.cube_ahead(), resolution);
+                    let antiscale = FreeCoordinate::from(resolution).recip();
+                    for subcube_hit in sub_ray.cast().within_grid(Grid::for_block(resolution)) {
+                        if s.count_step_should_stop() {
+                            break;
                         }
-                        // TODO: To implement TransparencyOption::Volumetric we need to peek forward to the next change of color and find the distance between them, but only if the alpha is not 0 or 1. (Same here and in the recursive block case.)
-                        s.trace_through_surface(
-                            pixel_block_data,
-                            *color,
-                            match impl_fields.options.lighting_display {
+                        if let Some(voxel) = array.get(subcube_hit.cube_ahead()) {
+                            let lighting = match impl_fields.options.lighting_display {
                                 LightingOption::None => Rgb::ONE,
-                                LightingOption::Flat => self.get_lighting(hit.cube_behind()),
-                                LightingOption::Smooth => self.get_interpolated_light(
-                                    hit.intersection_point(ray),
-                                    hit.face(),
+                                LightingOption::Flat => self.get_lighting(
+                                    hit.cube_ahead() + subcube_hit.face().normal_vector(),
                                 ),
-                            },
-                            hit.face(),
-                            &impl_fields.options,
-                        );
-                    }
-                    TracingBlock::Recur(pixel_block_data, resolution, array) => {
-                        let resolution = *resolution;
-                        let sub_ray = recursive_ray(ray, hit.cube_ahead(), resolution);
-                        let antiscale = FreeCoordinate::from(resolution).recip();
-                        for subcube_hit in sub_ray.cast().within_grid(Grid::for_block(resolution)) {
-                            if s.count_step_should_stop() {
-                                break;
-                            }
-                            if let Some(voxel) = array.get(subcube_hit.cube_ahead()) {
-                                s.trace_through_surface(
-                                    pixel_block_data,
-                                    voxel.color,
-                                    match impl_fields.options.lighting_display {
-                                        LightingOption::None => Rgb::ONE,
-                                        LightingOption::Flat => self.get_lighting(
-                                            hit.cube_ahead() + subcube_hit.face().normal_vector(),
-                                        ),
-                                        LightingOption::Smooth => self.get_interpolated_light(
-                                            subcube_hit.intersection_point(sub_ray) * antiscale
-                                                + hit
-                                                    .cube_ahead()
-                                                    .map(FreeCoordinate::from)
-                                                    .to_vec(),
-                                            subcube_hit.face(),
-                                        ),
-                                    },
+                                LightingOption::Smooth => self.get_interpolated_light(
+                                    subcube_hit.intersection_point(sub_ray) * antiscale
+                                        + hit.cube_ahead().map(FreeCoordinate::from).to_vec(),
                                     subcube_hit.face(),
-                                    &impl_fields.options,
-                                );
-                            }
+                                    s,
+                                ),
+                            };
+                            s.trace_through_surface(
+                                pixel_block_data,
+                                voxel.color,
+                                lighting,
+                                subcube_hit.face(),
+                                &impl_fields.options,
+                                // The sub-ray's t is scaled up by the resolution.
+                                fog.fog_mix(
+                                    (subcube_hit.t_distance() * antiscale) as f32 * t_scale,
+                                ),
+                            );
                         }
                     }
                 }
             }
-            s.finish(*impl_fields.sky_color)
+            // The step may have made the pixel opaque; count_step_should_stop() will
+            // notice on the next call.
+            true
+        })
+    }
+
+    fn finish_ray(&self, mut tracing: RayTracing<P>) -> (P::Pixel, RaytraceInfo) {
+        if let Some(entity_hit) = tracing.entity.take() {
+            // Not reached by stepping, because it is beyond all cubes the ray stepped through.
+            self.trace_entity(entity_hit, tracing.ray, &mut tracing.state, tracing.t_scale);
+        }
+        self.0.with(|impl_fields| {
+            let sky = match impl_fields.skybox {
+                Some(skybox) => skybox.sample(tracing.ray.direction),
+                None => *impl_fields.sky_color,
+            };
+            tracing
+                .state
+                .finish(impl_fields.options.color_filter.apply(sky))
+        })
+    }
+
+    /// Applies the surface of an entity which `ray` hit to the tracing state.
+    fn trace_entity(&self, hit: EntityHit, ray: Ray, s: &mut TracingState<P>, t_scale: f32) {
+        self.0.with(|impl_fields| {
+            let entity = &impl_fields.entities[hit.index];
+            let lighting = match impl_fields.options.lighting_display {
+                LightingOption::None => Rgb::ONE,
+                // Entities are not aligned with the cubes, so use the light of the cube
+                // the surface is in rather than interpolating.
+                LightingOption::Flat | LightingOption::Smooth => {
+                    let point = ray.origin + ray.direction * hit.t_distance;
+                    self.get_lighting(point.map(|c| c.floor() as GridCoordinate))
+                }
+            };
+            s.trace_through_surface(
+                &entity.block_data,
+                hit.color,
+                lighting,
+                hit.face,
+                &impl_fields.options,
+                impl_fields.fog.fog_mix(hit.t_distance as f32 * t_scale),
+            );
         })
     }
 
@@ -135,14 +335,27 @@ impl<P: PixelBuf> SpaceRaytracer<P> {
     /// The returned `[P::Pixel]` is in the usual left-right then top-bottom raster order;
     /// its dimensions are `camera.framebuffer_size`.
     ///
-    /// TODO: Add a mechanism for incrementally rendering into a mutable buffer instead of
-    /// all-at-once into a newly allocated one, for interactive use.
+    /// For incremental rendering suitable for interactive use, see
+    /// [`ProgressiveRaytracer`].
     pub fn trace_scene_to_image(&self, camera: &Camera) -> (Box<[P::Pixel]>, RaytraceInfo) {
+        let _span = trace_span!("raytrace");
         // This wrapper function ensures that the two implementations have consistent
         // signatures.
         self.trace_scene_to_image_impl(camera)
     }
 
+    /// Compute a full image for each eye of `camera`, left then right, as by
+    /// [`Self::trace_scene_to_image`].
+    pub fn trace_stereo_to_images(
+        &self,
+        camera: &StereoCamera,
+    ) -> [(Box<[P::Pixel]>, RaytraceInfo); 2] {
+        [
+            self.trace_scene_to_image(camera.eye(Eye::Left)),
+            self.trace_scene_to_image(camera.eye(Eye::Right)),
+        ]
+    }
+
     #[cfg(feature = "rayon")]
     fn trace_scene_to_image_impl(&self, camera: &Camera) -> (Box<[P::Pixel]>, RaytraceInfo) {
         let viewport = camera.viewport();
@@ -150,13 +363,7 @@ impl<P: PixelBuf> SpaceRaytracer<P> {
 
         let output_iterator = (0..viewport_size.y)
             .into_par_iter()
-            .map(move |ych| {
-                let y = viewport.normalize_fb_y(ych);
-                (0..viewport_size.x).into_par_iter().map(move |xch| {
-                    let x = viewport.normalize_fb_x(xch);
-                    self.trace_ray(camera.project_ndc_into_world(Point2::new(x, y)))
-                })
-            })
+            .map(move |ych| self.trace_rays(&row_rays(camera, ych)))
             .flatten();
 
         let (image, info_sum): (Vec<P::Pixel>, rayon_helper::ParExtSum<RaytraceInfo>) =
@@ -173,11 +380,7 @@ impl<P: PixelBuf> SpaceRaytracer<P> {
 
         let mut total_info = RaytraceInfo::default();
         for ych in 0..viewport_size.y {
-            let y = viewport.normalize_fb_y(ych);
-            for xch in 0..viewport_size.x {
-                let x = viewport.normalize_fb_x(xch);
-                let (pixel, info) =
-                    self.trace_ray(camera.project_ndc_into_world(Point2::new(x, y)));
+            for (pixel, info) in self.trace_rays(&row_rays(camera, ych)) {
                 total_info += info;
                 image.push(pixel);
             }
@@ -190,6 +393,9 @@ impl<P: PixelBuf> SpaceRaytracer<P> {
     fn get_packed_light(&self, cube: GridPoint) -> PackedLight {
         // TODO: wrong unwrap_or value
         self.0.with(|impl_fields| {
+            let cube = impl_fields
+                .topology
+                .wrap_cube(impl_fields.cubes.grid(), cube);
             impl_fields
                 .cubes
                 .get(cube)
@@ -201,6 +407,9 @@ impl<P: PixelBuf> SpaceRaytracer<P> {
     #[inline]
     fn get_lighting(&self, cube: GridPoint) -> Rgb {
         self.0.with(|impl_fields| {
+            let cube = impl_fields
+                .topology
+                .wrap_cube(impl_fields.cubes.grid(), cube);
             impl_fields
                 .cubes
                 .get(cube)
@@ -209,7 +418,14 @@ impl<P: PixelBuf> SpaceRaytracer<P> {
         })
     }
 
-    fn get_interpolated_light(&self, point: Point3<FreeCoordinate>, face: Face) -> Rgb {
+    /// Computes the smoothly interpolated light falling on the surface at `point` which
+    /// faces toward `face`, counting cache hits and misses in `state`.
+    fn get_interpolated_light(
+        &self,
+        point: Point3<FreeCoordinate>,
+        face: Face,
+        state: &mut TracingState<P>,
+    ) -> Rgb {
         // This implementation is duplicated in GLSL at src/lum/shaders/fragment.glsl
 
         // About half the size of the smallest permissible voxel.
@@ -248,30 +464,26 @@ impl<P: PixelBuf> SpaceRaytracer<P> {
         let mix_2 = smoothstep(mix_2);
 
         // Retrieve light data, again using the half-cube-offset grid (this way we won't have edge artifacts).
+        // All of the cubes we look at are neighbors of the one the point is in.
+        let cube = Point3::from_vec(origin.map(|s| s.floor() as GridCoordinate));
+        let neighborhood = self.get_light_neighborhood(cube, face, state);
         let get_light = |p: Vector3<FreeCoordinate>| {
-            self.get_packed_light(Point3::from_vec(
-                (origin + p).map(|s| s.floor() as GridCoordinate),
-            ))
+            neighborhood
+                .get(Point3::from_vec((origin + p).map(|s| s.floor() as GridCoordinate)) - cube)
         };
         let lin_lo = -0.5;
         let lin_hi = 0.5;
-        let near12 = get_light(lin_lo * dir_1 + lin_lo * dir_2);
-        let near1far2 = get_light(lin_lo * dir_1 + lin_hi * dir_2);
-        let near2far1 = get_light(lin_hi * dir_1 + lin_lo * dir_2);
-        let mut far12 = get_light(lin_hi * dir_1 + lin_hi * dir_2);
+        let (_, near12) = get_light(lin_lo * dir_1 + lin_lo * dir_2);
+        let (near1far2_valid, near1far2) = get_light(lin_lo * dir_1 + lin_hi * dir_2);
+        let (near2far1_valid, near2far1) = get_light(lin_hi * dir_1 + lin_lo * dir_2);
+        let (_, mut far12) = get_light(lin_hi * dir_1 + lin_hi * dir_2);
 
-        if !near1far2.valid() && !near2far1.valid() {
+        if !near1far2_valid && !near2far1_valid {
             // The far corner is on the other side of a diagonal wall, so should be
             // ignored to prevent light leaks.
             far12 = near12;
         }
 
-        // Apply ambient occlusion.
-        let near12 = near12.value_with_ambient_occlusion();
-        let near1far2 = near1far2.value_with_ambient_occlusion();
-        let near2far1 = near2far1.value_with_ambient_occlusion();
-        let far12 = far12.value_with_ambient_occlusion();
-
         // Perform bilinear interpolation.
         fn mix(x: Vector4<f32>, y: Vector4<f32>, a: FreeCoordinate) -> Vector4<f32> {
             // This should be replaced with https://doc.rust-lang.org/nightly/std/primitive.f32.html#method.lerp when that's stable
@@ -285,6 +497,72 @@ impl<P: PixelBuf> SpaceRaytracer<P> {
         );
         Rgb::try_from(v.truncate() / v.w.max(0.1)).unwrap()
     }
+
+    /// Returns the light around `cube` in the plane perpendicular to `face`, from the
+    /// cache if possible.
+    fn get_light_neighborhood(
+        &self,
+        cube: GridPoint,
+        face: Face,
+        state: &mut TracingState<P>,
+    ) -> LightNeighborhood {
+        self.0.with(|impl_fields| {
+            let shard = &impl_fields.light_cache[(cube.x ^ cube.y ^ cube.z)
+                .rem_euclid(LIGHT_CACHE_SHARDS as GridCoordinate)
+                as usize];
+            if let Some(neighborhood) = shard.lock().unwrap().get(&(cube, face)) {
+                state.light_cache_hits += 1;
+                return *neighborhood;
+            }
+            state.light_cache_misses += 1;
+            // Computed without holding the lock, so that other threads may proceed.
+            let neighborhood =
+                LightNeighborhood::new(cube, face, |cube| self.get_packed_light(cube));
+            shard.lock().unwrap().insert((cube, face), neighborhood);
+            neighborhood
+        })
+    }
+}
+
+/// The light of the 3×3 square of cubes centered on some cube and perpendicular to
+/// some face, as needed for [`SpaceRaytracer::get_interpolated_light`] of any point
+/// in the central cube.
+#[derive(Clone, Copy, Debug)]
+struct LightNeighborhood {
+    /// The axes of the square, from [`Face::matrix`].
+    axes: [Vector3<GridCoordinate>; 2],
+    /// Whether each light value is [`PackedLight::valid`], and its
+    /// [`PackedLight::value_with_ambient_occlusion`].
+    values: [(bool, Vector4<f32>); 9],
+}
+
+impl LightNeighborhood {
+    fn new(cube: GridPoint, face: Face, get: impl Fn(GridPoint) -> PackedLight) -> Self {
+        let matrix = face.matrix(0);
+        let axes = [matrix.x, matrix.y];
+        let mut values = [(false, Vector4::zero()); 9];
+        for i in -1..=1 {
+            for j in -1..=1 {
+                let light = get(cube + axes[0] * i + axes[1] * j);
+                values[Self::index(i, j)] = (light.valid(), light.value_with_ambient_occlusion());
+            }
+        }
+        Self { axes, values }
+    }
+
+    /// Returns the value for the cube at `offset` from the central cube, which must be
+    /// within the square.
+    fn get(&self, offset: Vector3<GridCoordinate>) -> (bool, Vector4<f32>) {
+        let along = |axis: Vector3<GridCoordinate>| {
+            offset.x * axis.x + offset.y * axis.y + offset.z * axis.z
+        };
+        self.values[Self::index(along(self.axes[0]), along(self.axes[1]))]
+    }
+
+    fn index(i: GridCoordinate, j: GridCoordinate) -> usize {
+        debug_assert!((-1..=1).contains(&i) && (-1..=1).contains(&j));
+        ((i + 1) * 3 + (j + 1)) as usize
+    }
 }
 
 impl<P: PixelBuf<Pixel = String>> SpaceRaytracer<P> {
@@ -379,10 +657,18 @@ impl<P: PixelBuf<Pixel = String>> SpaceRaytracer<P> {
 #[non_exhaustive]
 pub struct RaytraceInfo {
     cubes_traced: usize,
+    /// Number of [`LightingOption::Smooth`] light computations which could reuse
+    /// previously fetched light data.
+    light_cache_hits: usize,
+    /// Number of [`LightingOption::Smooth`] light computations which had to fetch
+    /// light data.
+    light_cache_misses: usize,
 }
 impl std::ops::AddAssign<RaytraceInfo> for RaytraceInfo {
     fn add_assign(&mut self, other: Self) {
         self.cubes_traced += other.cubes_traced;
+        self.light_cache_hits += other.light_cache_hits;
+        self.light_cache_misses += other.light_cache_misses;
     }
 }
 impl std::iter::Sum for RaytraceInfo {
@@ -405,25 +691,110 @@ impl std::iter::Sum for RaytraceInfo {
 /// redirected when tests are run).
 ///
 /// `direction` specifies the direction from which the camera will be looking towards
-/// the center of the space. The text output will be 80 columns wide.
+/// the center of the space. The text output will be 80 columns wide; use
+/// [`print_space_with_options`] for other sizes and styles.
 pub fn print_space(space: &Space, direction: impl Into<Vector3<FreeCoordinate>>) {
-    print_space_impl(space, direction, |s| {
+    print_space_with_options(space, direction, &TextRenderOptions::default());
+}
+
+/// Print an image of the given space as text, as [`print_space`] does, but with the
+/// size and style given by `options`.
+///
+/// ```
+/// use all_is_cubes::raytracer::{print_space_with_options, TextRenderOptions, TextStyle};
+/// use all_is_cubes::space::Space;
+///
+/// let mut options = TextRenderOptions::default();
+/// options.width = 120;
+/// options.height = 30;
+/// options.style = TextStyle::AnsiHalfBlocks;
+/// options.borderless = true;
+/// print_space_with_options(&Space::empty_positive(1, 1, 1), (1., 1., 1.), &options);
+/// ```
+pub fn print_space_with_options(
+    space: &Space,
+    direction: impl Into<Vector3<FreeCoordinate>>,
+    options: &TextRenderOptions,
+) {
+    print_space_impl(space, direction, options, |s| {
         print!("{}", s);
     });
 }
 
+/// Options for [`print_space_with_options`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct TextRenderOptions {
+    /// Width of the image, in characters.
+    pub width: usize,
+    /// Height of the image, in lines.
+    pub height: usize,
+    /// How the characters depict the space.
+    pub style: TextStyle,
+    /// Whether to omit the rows and columns at the edges of the image in which no ray
+    /// passed through the space, so that the image is no bigger than the space's
+    /// appearance.
+    pub borderless: bool,
+}
+
+impl Default for TextRenderOptions {
+    /// 80 by 40 [`TextStyle::Characters`], with borders.
+    fn default() -> Self {
+        Self {
+            width: 80,
+            height: 40,
+            style: TextStyle::Characters,
+            borderless: false,
+        }
+    }
+}
+
+/// How [`print_space_with_options`] depicts a space.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum TextStyle {
+    /// Each character is the first character of the
+    /// [`display_name`](crate::block::BlockAttributes::display_name) of the block seen,
+    /// or `.` where no ray passed through the space, as computed by [`CharacterBuf`].
+    Characters,
+    /// Each character is a Unicode half block (`▀` or `▄`) colored with 24-bit ANSI
+    /// color escape sequences, so that it shows two pixels stacked vertically. This
+    /// requires a terminal which supports such colors.
+    AnsiHalfBlocks,
+}
+
 /// Version of `print_space` that takes a destination, for testing.
 fn print_space_impl<F: FnMut(&str)>(
     space: &Space,
     direction: impl Into<Vector3<FreeCoordinate>>,
+    options: &TextRenderOptions,
     mut write: F,
 ) -> RaytraceInfo {
+    let TextRenderOptions {
+        width,
+        height,
+        style,
+        borderless,
+    } = *options;
+
+    // Characters are about twice as tall as they are wide, so each character is two
+    // square pixels in the half-block style, or has an aspect ratio of 1/2 otherwise.
     // TODO: optimize height (and thus aspect ratio) for the shape of the space
+    let (framebuffer_size, nominal_size) = match style {
+        TextStyle::Characters => (
+            Vector2::new(width, height),
+            Vector2::new(width as FreeCoordinate / 2., height as FreeCoordinate),
+        ),
+        TextStyle::AnsiHalfBlocks => (
+            Vector2::new(width, height * 2),
+            Vector2::new(width as FreeCoordinate, (height * 2) as FreeCoordinate),
+        ),
+    };
     let mut camera = Camera::new(
         GraphicsOptions::default(),
         Viewport {
-            nominal_size: Vector2::new(40., 40.),
-            framebuffer_size: Vector2::new(80, 40),
+            nominal_size,
+            framebuffer_size: framebuffer_size.map(|s| s as u32),
         },
     );
     camera.set_view_matrix(Matrix4::look_at_rh(
@@ -432,13 +803,132 @@ fn print_space_impl<F: FnMut(&str)>(
         Vector3::new(0., 1., 0.),
     ));
 
-    SpaceRaytracer::<CharacterBuf>::new(space, GraphicsOptions::default())
-        .trace_scene_to_text(&camera, &"\n", move |s| {
-            write(s);
-            let r: Result<(), ()> = Ok(());
-            r
-        })
-        .unwrap()
+    // Text of each character, and whether it shows anything of the space, in raster
+    // order.
+    let (cells, info): (Vec<(String, bool)>, RaytraceInfo) = match style {
+        TextStyle::Characters => {
+            let (image, info) =
+                SpaceRaytracer::<CharacterBuf>::new(space, GraphicsOptions::default())
+                    .trace_scene_to_image(&camera);
+            let cells = image
+                .into_vec()
+                .into_iter()
+                .map(|text| {
+                    let shows_space = text != CharacterBuf::NOTHING;
+                    (text, shows_space)
+                })
+                .collect();
+            (cells, info)
+        }
+        TextStyle::AnsiHalfBlocks => {
+            let (image, info) =
+                SpaceRaytracer::<HalfBlockBuf>::new(space, GraphicsOptions::default())
+                    .trace_scene_to_image(&camera);
+            let cells = image
+                .chunks(width * 2)
+                .flat_map(|rows| {
+                    let (top, bottom) = rows.split_at(width);
+                    top.iter().zip(bottom.iter()).map(|(&top, &bottom)| {
+                        (half_block_text(top, bottom), top.or(bottom).is_some())
+                    })
+                })
+                .collect();
+            (cells, info)
+        }
+    };
+
+    let (mut rows, mut columns) = (0..height, 0..width);
+    if borderless {
+        let shows_space = |row: usize, column: usize| cells[row * width + column].1;
+        let nonempty_rows: Vec<usize> = (0..height)
+            .filter(|&row| (0..width).any(|column| shows_space(row, column)))
+            .collect();
+        let nonempty_columns: Vec<usize> = (0..width)
+            .filter(|&column| (0..height).any(|row| shows_space(row, column)))
+            .collect();
+        match (nonempty_rows.first(), nonempty_rows.last()) {
+            (Some(&first), Some(&last)) => rows = first..(last + 1),
+            _ => rows = 0..0,
+        }
+        if let (Some(&first), Some(&last)) = (nonempty_columns.first(), nonempty_columns.last()) {
+            columns = first..(last + 1);
+        }
+    }
+
+    let line_ending = match style {
+        TextStyle::Characters => "\n",
+        TextStyle::AnsiHalfBlocks => "\x1b[0m\n",
+    };
+    for row in rows {
+        for column in columns.clone() {
+            write(&cells[row * width + column].0);
+        }
+        write(line_ending);
+    }
+
+    info
+}
+
+/// Formats one character of [`TextStyle::AnsiHalfBlocks`] output, where [`None`] is
+/// a pixel in which no ray passed through the space and is left as the terminal's
+/// background color.
+fn half_block_text(top: Option<Rgba>, bottom: Option<Rgba>) -> String {
+    fn rgb(color: Rgba) -> String {
+        let [r, g, b, _] = color.to_srgb_32bit();
+        format!("{};{};{}", r, g, b)
+    }
+    match (top, bottom) {
+        (None, None) => "\x1b[0m ".to_owned(),
+        (Some(top), None) => format!("\x1b[49;38;2;{}m▀", rgb(top)),
+        (None, Some(bottom)) => format!("\x1b[49;38;2;{}m▄", rgb(bottom)),
+        (Some(top), Some(bottom)) => {
+            format!("\x1b[38;2;{};48;2;{}m▀", rgb(top), rgb(bottom))
+        }
+    }
+}
+
+/// [`PixelBuf`] for [`TextStyle::AnsiHalfBlocks`]: like [`ColorBuf`], but producing
+/// [`None`] where no ray passed through the space.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct HalfBlockBuf {
+    color: ColorBuf,
+    hit_nothing: bool,
+}
+
+impl PixelBuf for HalfBlockBuf {
+    type Pixel = Option<Rgba>;
+    type BlockData = ();
+
+    fn compute_block_data(_: &SpaceBlockData) {}
+
+    fn error_block_data() {}
+
+    fn sky_block_data() {}
+
+    #[inline]
+    fn opaque(&self) -> bool {
+        self.hit_nothing || self.color.opaque()
+    }
+
+    #[inline]
+    fn result(self) -> Option<Rgba> {
+        if self.hit_nothing {
+            None
+        } else {
+            Some(self.color.result())
+        }
+    }
+
+    #[inline]
+    fn add(&mut self, surface_color: Rgba, block_data: &()) {
+        if !self.hit_nothing {
+            self.color.add(surface_color, block_data);
+        }
+    }
+
+    fn hit_nothing(&mut self) {
+        self.hit_nothing = true;
+    }
 }
 
 /// Get block data out of [`Space`] (which is not [`Sync`], and not specialized for our
@@ -474,6 +964,69 @@ fn prepare_cubes<'a, P: PixelBuf>(
     })
 }
 
+/// Get entity data out of [`Space`] (which is not [`Sync`], and not specialized for our
+/// efficient use). Invisible entities are omitted, and each part of an entity's
+/// [`Model`](crate::entity::Model) becomes a separate [`TracingEntity`].
+fn prepare_entities<P: PixelBuf>(
+    space: &Space,
+    options: &GraphicsOptions,
+) -> Vec<TracingEntity<P::BlockData>> {
+    let mut entities = Vec::new();
+    for (_, entity) in space.entities().iter() {
+        if let Some(model) = entity.model() {
+            for (part, transform) in model.parts().iter().zip(entity.model_part_transforms()) {
+                entities.extend(prepare_cube_entity::<P>(part.appearance(), transform));
+            }
+            continue;
+        }
+        let block = match entity.appearance() {
+            Some(block) => block,
+            None => continue,
+        };
+        match entity.shape() {
+            EntityShape::Cube => {
+                entities.extend(prepare_cube_entity::<P>(block, entity.transform()));
+            }
+            EntityShape::Billboard => {
+                let block_data = SpaceBlockData::unlisted(block.clone());
+                let evaluated = block_data.evaluated();
+                let resolution = if evaluated.voxels.is_some() {
+                    GridCoordinate::from(evaluated.resolution)
+                } else {
+                    1
+                };
+                entities.push(TracingEntity {
+                    shape: TracingEntityShape::Billboard {
+                        center: entity.position(),
+                        size: entity.scale(),
+                        image: billboard_image(evaluated, resolution, &options.transparency),
+                        resolution,
+                    },
+                    block_data: P::compute_block_data(&block_data),
+                });
+            }
+        }
+    }
+    entities
+}
+
+/// Constructs a [`TracingEntity`] drawing `block` as a cube, the image of the unit cube
+/// centered on the origin under `transform`, unless `transform` is degenerate.
+fn prepare_cube_entity<P: PixelBuf>(
+    block: &Block,
+    transform: Matrix4<FreeCoordinate>,
+) -> Option<TracingEntity<P::BlockData>> {
+    let block_data = SpaceBlockData::unlisted(block.clone());
+    Some(TracingEntity {
+        shape: TracingEntityShape::Cube {
+            inverse_transform: transform.invert()?,
+            transform,
+            color: block_data.evaluated().color,
+        },
+        block_data: P::compute_block_data(&block_data),
+    })
+}
+
 #[derive(Clone, Debug)]
 struct TracingCubeData<'a, B: 'static> {
     block: &'a TracingBlock<B>,
@@ -486,14 +1039,216 @@ enum TracingBlock<B: 'static> {
     Recur(B, Resolution, GridArray<Evoxel>),
 }
 
-#[derive(Clone, Debug, Default)]
+/// An [`Entity`](crate::entity::Entity) with the information needed to draw it.
+#[derive(Clone, Debug)]
+struct TracingEntity<B: 'static> {
+    shape: TracingEntityShape,
+    block_data: B,
+}
+
+/// The [`EntityShape`] of a [`TracingEntity`] and the data for drawing it.
+#[derive(Clone, Debug)]
+enum TracingEntityShape {
+    /// Drawn as a cube of a single color.
+    Cube {
+        /// Transformation from the unit cube centered on the origin to the entity's cube.
+        transform: Matrix4<FreeCoordinate>,
+        /// Inverse of `transform`.
+        inverse_transform: Matrix4<FreeCoordinate>,
+        color: Rgba,
+    },
+    /// Drawn as a square of side `size` centered on `center`, perpendicular to each ray
+    /// (so that it faces the camera) and upright as far as possible.
+    Billboard {
+        center: Point3<FreeCoordinate>,
+        size: FreeCoordinate,
+        /// As computed by [`billboard_image`].
+        image: Vec<Rgba>,
+        resolution: GridCoordinate,
+    },
+}
+
+impl<B> TracingEntity<B> {
+    /// Returns the `t_distance` along `ray` at which it hits this entity, the face
+    /// (nearest to the actual surface orientation) that it hits, and the color there.
+    fn intersect(&self, ray: Ray) -> Option<(FreeCoordinate, Face, Rgba)> {
+        match self.shape {
+            TracingEntityShape::Cube {
+                transform,
+                inverse_transform,
+                color,
+            } => {
+                let (t_distance, face) = intersect_cube(transform, inverse_transform, ray)?;
+                Some((t_distance, face, color))
+            }
+            TracingEntityShape::Billboard {
+                center,
+                size,
+                ref image,
+                resolution,
+            } => {
+                let direction = ray.direction;
+                let t_distance = (center - ray.origin).dot(direction) / direction.magnitude2();
+                if t_distance < 0.0 {
+                    return None;
+                }
+                let forward = direction.normalize();
+                let right = forward.cross(Vector3::unit_y());
+                let right = if right.magnitude2() > 1e-12 {
+                    right.normalize()
+                } else {
+                    // Looking straight up or down; any orientation will do.
+                    Vector3::unit_x()
+                };
+                let up = right.cross(forward);
+
+                let relative = ray.origin + direction * t_distance - center;
+                let to_texel = |coordinate: FreeCoordinate| -> Option<GridCoordinate> {
+                    let texel =
+                        ((coordinate / size + 0.5) * FreeCoordinate::from(resolution)).floor();
+                    (texel >= 0.0 && texel < FreeCoordinate::from(resolution))
+                        .then(|| texel as GridCoordinate)
+                };
+                let x = to_texel(relative.dot(right))?;
+                let y = to_texel(relative.dot(up))?;
+                let color = image[(y * resolution + x) as usize];
+                if color.fully_transparent() {
+                    return None;
+                }
+                Some((t_distance, face_for_normal(-direction), color))
+            }
+        }
+    }
+}
+
+/// Returns the `t_distance` along `ray` at which it enters the cube which is the image
+/// of the unit cube centered on the origin under `transform`, and the face (nearest to
+/// the actual surface orientation) that it enters through.
+fn intersect_cube(
+    transform: Matrix4<FreeCoordinate>,
+    inverse_transform: Matrix4<FreeCoordinate>,
+    ray: Ray,
+) -> Option<(FreeCoordinate, Face)> {
+    // Affine transformations preserve the ray's parameterization, so `t` in the
+    // entity's coordinates is also `t` in the world.
+    let origin = inverse_transform.transform_point(ray.origin);
+    let direction = inverse_transform.transform_vector(ray.direction);
+    let mut t_enter = FreeCoordinate::NEG_INFINITY;
+    let mut t_exit = FreeCoordinate::INFINITY;
+    let mut enter_axis = 0;
+    for axis in 0..3 {
+        let (o, d) = (origin[axis], direction[axis]);
+        if d == 0.0 {
+            if o.abs() > 0.5 {
+                return None;
+            }
+            continue;
+        }
+        let (t1, t2) = ((-0.5 - o) / d, (0.5 - o) / d);
+        let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+        if near > t_enter {
+            t_enter = near;
+            enter_axis = axis;
+        }
+        t_exit = t_exit.min(far);
+    }
+    // Rays starting inside the entity don't see it, like the inside of a block.
+    if t_enter > t_exit || t_enter < 0.0 {
+        return None;
+    }
+
+    let mut local_normal = Vector3::zero();
+    local_normal[enter_axis] = -direction[enter_axis].signum();
+    Some((
+        t_enter,
+        face_for_normal(transform.transform_vector(local_normal)),
+    ))
+}
+
+/// Returns the [`Face`] whose normal is nearest to `normal`.
+fn face_for_normal(normal: Vector3<FreeCoordinate>) -> Face {
+    match major_axis(normal) {
+        (0, false) => Face::NX,
+        (0, true) => Face::PX,
+        (1, false) => Face::NY,
+        (1, true) => Face::PY,
+        (_, false) => Face::NZ,
+        (_, true) => Face::PZ,
+    }
+}
+
+/// Where a ray hit a [`TracingEntity`].
+#[derive(Clone, Copy, Debug)]
+struct EntityHit {
+    t_distance: FreeCoordinate,
+    face: Face,
+    color: Rgba,
+    /// Index into [`SpaceRaytracerImpl::entities`].
+    index: usize,
+}
+
+/// Number of rays [`SpaceRaytracer::trace_rays`] traces in lock-step.
+const RAY_PACKET_SIZE: usize = 16;
+
+/// Rays for one row of pixels of `camera`'s image.
+fn row_rays(camera: &Camera, ych: usize) -> Vec<Ray> {
+    let viewport = camera.viewport();
+    let y = viewport.normalize_fb_y(ych);
+    (0..viewport.framebuffer_size.x as usize)
+        .map(|xch| camera.project_ndc_into_world(Point2::new(viewport.normalize_fb_x(xch), y)))
+        .collect()
+}
+
+/// Identifies which axis `direction` is closest to, and which way along it, for
+/// grouping rays that will step through cubes in similar orders.
+fn major_axis(direction: Vector3<FreeCoordinate>) -> (usize, bool) {
+    let abs = direction.map(FreeCoordinate::abs);
+    let axis = if abs.x >= abs.y && abs.x >= abs.z {
+        0
+    } else if abs.y >= abs.z {
+        1
+    } else {
+        2
+    };
+    (axis, direction[axis] >= 0.0)
+}
+
+/// A ray in the process of being traced by [`SpaceRaytracer`], which can be advanced
+/// one cube at a time.
+struct RayTracing<P: PixelBuf> {
+    ray: Ray,
+    raycaster: Raycaster,
+    state: TracingState<P>,
+    /// Limit of `t_distance` imposed by the view distance.
+    max_t: FreeCoordinate,
+    /// Length of the ray's direction vector, which converts `t_distance` to distance.
+    t_scale: f32,
+    /// The nearest entity the ray hits, if it has not yet been drawn.
+    entity: Option<EntityHit>,
+}
+
+#[derive(Clone, Debug)]
 struct TracingState<P: PixelBuf> {
     /// Number of cubes traced through -- controlled by the caller, so not necessarily
     /// equal to the number of calls to [`Self::trace_through_surface()`].
     cubes_traced: usize,
+    light_cache_hits: usize,
+    light_cache_misses: usize,
     pixel_buf: P,
+    /// Color which distant surfaces fade into.
+    fog_color: Rgb,
 }
 impl<P: PixelBuf> TracingState<P> {
+    fn new(fog_color: Rgb) -> Self {
+        Self {
+            cubes_traced: 0,
+            light_cache_hits: 0,
+            light_cache_misses: 0,
+            pixel_buf: P::default(),
+            fog_color,
+        }
+    }
+
     #[inline]
     fn count_step_should_stop(&mut self) -> bool {
         self.cubes_traced += 1;
@@ -522,12 +1277,16 @@ impl<P: PixelBuf> TracingState<P> {
             self.pixel_buf.result(),
             RaytraceInfo {
                 cubes_traced: self.cubes_traced,
+                light_cache_hits: self.light_cache_hits,
+                light_cache_misses: self.light_cache_misses,
             },
         )
     }
 
     /// Apply the effect of a given surface color.
     ///
+    /// `fog_mix` is the [`FogParameters::fog_mix`] for the surface's distance.
+    ///
     /// Note this is not true volumetric ray tracing: we're considering each
     /// voxel surface to be discrete.
     #[inline]
@@ -538,12 +1297,17 @@ impl<P: PixelBuf> TracingState<P> {
         lighting: Rgb,
         face: Face,
         options: &GraphicsOptions,
+        fog_mix: f32,
     ) {
         let surface = options.transparency.limit_alpha(surface);
         if surface.fully_transparent() {
             return;
         }
-        let adjusted_rgb = surface.to_rgb() * lighting * fixed_directional_lighting(face);
+        let lit_rgb = surface.to_rgb() * lighting * fixed_directional_lighting(face);
+        // Same blending as the fragment shader does.
+        let adjusted_rgb = options
+            .color_filter
+            .apply(lit_rgb * (1.0 - fog_mix) + self.fog_color * fog_mix);
         self.pixel_buf
             .add(adjusted_rgb.with_alpha(surface.alpha()), block_data);
     }
@@ -551,7 +1315,7 @@ impl<P: PixelBuf> TracingState<P> {
 
 /// Simple directional lighting used to give corners extra definition.
 /// Note that this algorithm is also implemented in the fragment shader for GPU rendering.
-fn fixed_directional_lighting(face: Face) -> f32 {
+pub(crate) fn fixed_directional_lighting(face: Face) -> f32 {
     let normal = face.normal_vector();
     const LIGHT_1_DIRECTION: Vector3<f32> = Vector3::new(0.4, -0.1, 0.0);
     const LIGHT_2_DIRECTION: Vector3<f32> = Vector3::new(-0.4, 0.35, 0.25);
@@ -725,7 +1489,7 @@ impl PixelBuf for CharacterBuf {
 
     #[inline]
     fn result(self) -> String {
-        self.hit_text.unwrap_or_else(|| ".".to_owned())
+        self.hit_text.unwrap_or_else(|| Self::NOTHING.to_owned())
     }
 
     #[inline]
@@ -736,10 +1500,15 @@ impl PixelBuf for CharacterBuf {
     }
 
     fn hit_nothing(&mut self) {
-        self.hit_text = Some(".".to_owned());
+        self.hit_text = Some(Self::NOTHING.to_owned());
     }
 }
 
+impl CharacterBuf {
+    /// The text produced where no ray passed through the space.
+    const NOTHING: &'static str = ".";
+}
+
 #[cfg(feature = "rayon")]
 mod rayon_helper {
     use rayon::iter::{IntoParallelIterator, ParallelExtend, ParallelIterator as _};
@@ -778,8 +1547,13 @@ mod rayon_helper {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::block::Block;
-    use crate::content::make_some_blocks;
+    use crate::block::{Block, AIR};
+    use crate::camera::{ColorFilter, FogOption};
+    use crate::content::{make_some_blocks, make_some_voxel_blocks};
+    use crate::entity::{Entity, Model, ModelPart};
+    use crate::math::Aab;
+    use crate::space::{SpacePhysics, SpaceTransaction};
+    use crate::transactions::Transaction as _;
     use crate::universe::Universe;
     // use ordered_float::NotNan;
 
@@ -828,7 +1602,9 @@ mod tests {
         space.set((2, 0, 0), &b2).unwrap();
 
         let mut output = String::new();
-        print_space_impl(&space, (1., 1., 1.), |s| output += s);
+        print_space_impl(&space, (1., 1., 1.), &TextRenderOptions::default(), |s| {
+            output += s
+        });
         print!("{}", output);
         assert_eq!(
             output,
@@ -877,6 +1653,75 @@ mod tests {
         );
     }
 
+    fn print_space_options_test_space() -> Space {
+        let mut space = Space::empty_positive(3, 1, 1);
+        for (i, block) in make_some_blocks::<3>().iter().enumerate() {
+            space.set([i as GridCoordinate, 0, 0], block).unwrap();
+        }
+        space
+    }
+
+    #[test]
+    fn print_space_borderless() {
+        let space = print_space_options_test_space();
+        let mut options = TextRenderOptions {
+            width: 60,
+            height: 20,
+            ..TextRenderOptions::default()
+        };
+        let mut bordered = String::new();
+        print_space_impl(&space, (1., 1., 1.), &options, |s| bordered += s);
+        options.borderless = true;
+        let mut borderless = String::new();
+        print_space_impl(&space, (1., 1., 1.), &options, |s| borderless += s);
+        print!("{}", borderless);
+
+        // Compute the expected result by trimming the bordered output.
+        let lines: Vec<&str> = bordered
+            .lines()
+            .filter(|line| line.chars().any(|c| c != '.'))
+            .collect();
+        assert_eq!(bordered.lines().count(), 20);
+        assert!(lines.len() < 20);
+        let first = lines
+            .iter()
+            .filter_map(|l| l.find(|c: char| c != '.'))
+            .min()
+            .unwrap();
+        let last = lines
+            .iter()
+            .filter_map(|l| l.rfind(|c: char| c != '.'))
+            .max()
+            .unwrap();
+        let expected: String = lines
+            .iter()
+            .map(|line| format!("{}\n", &line[first..=last]))
+            .collect();
+        assert_eq!(borderless, expected);
+    }
+
+    #[test]
+    fn print_space_half_blocks() {
+        let space = print_space_options_test_space();
+        let options = TextRenderOptions {
+            width: 30,
+            height: 10,
+            style: TextStyle::AnsiHalfBlocks,
+            borderless: false,
+        };
+        let mut output = String::new();
+        print_space_impl(&space, (1., 1., 1.), &options, |s| output += s);
+        print!("{}", output);
+
+        assert_eq!(output.lines().count(), 10);
+        for line in output.lines() {
+            assert!(line.ends_with("\x1b[0m"), "{:?}", line);
+            let characters = line.chars().filter(|&c| c == ' ' || c == '▀' || c == '▄');
+            assert_eq!(characters.count(), 30, "{:?}", line);
+        }
+        assert!(output.contains("48;2;"), "no two-color characters");
+    }
+
     /// Check that blocks with small spaces are handled without out-of-bounds errors
     #[test]
     fn partial_voxels() {
@@ -898,7 +1743,9 @@ mod tests {
         space.set([1, 0, 0], &partial_block).unwrap();
 
         let mut output = String::new();
-        print_space_impl(&space, (1., 1., 1.), |s| output += s);
+        print_space_impl(&space, (1., 1., 1.), &TextRenderOptions::default(), |s| {
+            output += s
+        });
         print!("{}", output);
         assert_eq!(
             output,
@@ -946,4 +1793,297 @@ mod tests {
             "
         );
     }
+
+    #[test]
+    fn distant_surface_is_fogged() {
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &Block::from(Rgba::WHITE)).unwrap();
+        space.set_physics(SpacePhysics {
+            sky_color: Rgb::ZERO,
+            ..SpacePhysics::default()
+        });
+        // Far away, but still within the view distance so it is not clipped.
+        let ray = Ray::new([0.5, 0.5, -9.0], [0.0, 0.0, 1.0]);
+
+        let mut options = GraphicsOptions::default();
+        options.view_distance = notnan!(10.0);
+        options.lighting_display = LightingOption::None;
+        options.fog = FogOption::None;
+        let (clear, _) = SpaceRaytracer::<ColorBuf>::new(&space, options.clone()).trace_ray(ray);
+        assert!(clear.red().into_inner() > 0.5, "{:?}", clear);
+
+        options.fog = FogOption::Physical;
+        let (fogged, _) = SpaceRaytracer::<ColorBuf>::new(&space, options).trace_ray(ray);
+        // Mostly, but not entirely, the black sky color.
+        assert!(
+            fogged.red().into_inner() > 0.0 && fogged.red().into_inner() < 0.5,
+            "{:?}",
+            fogged
+        );
+    }
+
+    #[test]
+    fn color_filter_applies_to_surface_and_sky() {
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &Block::from(Rgba::WHITE)).unwrap();
+        let sky = space.physics().sky_color;
+        let mut options = GraphicsOptions::default();
+        options.fog = FogOption::None;
+        options.lighting_display = LightingOption::None;
+        let plain = SpaceRaytracer::<ColorBuf>::new(&space, options.clone());
+        options.color_filter = ColorFilter::HighContrast;
+        let filtered = SpaceRaytracer::<ColorBuf>::new(&space, options);
+
+        let surface_ray = Ray::new([0.5, 0.5, -1.0], [0.0, 0.0, 1.0]);
+        assert_eq!(
+            filtered.trace_ray(surface_ray).0,
+            ColorFilter::HighContrast
+                .apply(plain.trace_ray(surface_ray).0.to_rgb())
+                .with_alpha_one()
+        );
+        let sky_ray = Ray::new([0.5, 0.5, -1.0], [0.0, 0.0, -1.0]);
+        assert_eq!(
+            filtered.trace_ray(sky_ray).0,
+            ColorFilter::HighContrast.apply(sky).with_alpha_one()
+        );
+    }
+
+    #[test]
+    fn view_distance_clips() {
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &Block::from(Rgba::WHITE)).unwrap();
+        let sky = space.physics().sky_color;
+        let mut options = GraphicsOptions::default();
+        options.fog = FogOption::None;
+        options.view_distance = notnan!(10.0);
+        let tracer = SpaceRaytracer::<ColorBuf>::new(&space, options);
+
+        let (near, _) = tracer.trace_ray(Ray::new([0.5, 0.5, -9.0], [0.0, 0.0, 1.0]));
+        assert_ne!(near, sky.with_alpha_one());
+        let (far, info) = tracer.trace_ray(Ray::new([0.5, 0.5, -11.0], [0.0, 0.0, 1.0]));
+        assert_eq!(far, sky.with_alpha_one());
+        assert_eq!(info.cubes_traced, 0);
+    }
+
+    /// Rays should hit and miss the right voxels whatever the block's resolution is.
+    #[test]
+    fn recursive_block_at_any_resolution() {
+        let mut universe = Universe::new();
+        for &resolution in &[1, 2, 3, 5, 7, 15, 16, 17, 31, 100, 127, 128, 255] {
+            // A white layer at the far side of the block, with a hole at one corner.
+            let far = GridCoordinate::from(resolution) - 1;
+            let mut block_space = Space::empty(Grid::new([0, 0, far], [far + 1, far + 1, 1]));
+            block_space
+                .fill_uniform(block_space.grid(), Block::from(Rgba::WHITE))
+                .unwrap();
+            if resolution > 1 {
+                block_space.set([far, far, far], &AIR).unwrap();
+            }
+            let block = Block::builder()
+                .voxels_ref(resolution, universe.insert_anonymous(block_space))
+                .build();
+            let mut space = Space::empty_positive(1, 1, 1);
+            space.set([0, 0, 0], &block).unwrap();
+            let mut options = GraphicsOptions::default();
+            options.fog = FogOption::None;
+            let tracer = SpaceRaytracer::<ColorBuf>::new(&space, options);
+
+            let sky = tracer
+                .trace_ray(Ray::new([5.5, 0.5, -1.0], [0.0, 0.0, 1.0]))
+                .0;
+            // Not through the middle, since at resolution 2 that is a corner of the hole.
+            let (solid, _) = tracer.trace_ray(Ray::new([0.25, 0.25, -1.0], [0.0, 0.0, 1.0]));
+            assert_ne!(solid, sky, "resolution {}", resolution);
+            if resolution > 1 {
+                let hole = (FreeCoordinate::from(far) + 0.5) / FreeCoordinate::from(resolution);
+                let (through_hole, _) =
+                    tracer.trace_ray(Ray::new([hole, hole, -1.0], [0.0, 0.0, 1.0]));
+                assert_eq!(through_hole, sky, "resolution {}", resolution);
+            }
+        }
+    }
+
+    #[test]
+    fn trace_rays_matches_trace_ray() {
+        let mut universe = Universe::new();
+        let mut space = Space::empty_positive(3, 3, 3);
+        for (i, block) in make_some_blocks::<3>().iter().enumerate() {
+            space
+                .set([i as GridCoordinate, 0, 2 - i as GridCoordinate], block)
+                .unwrap();
+        }
+        let voxel_block = make_some_voxel_blocks::<1>(&mut universe)[0].clone();
+        space.set([1, 1, 1], &voxel_block).unwrap();
+        let tracer = SpaceRaytracer::<ColorBuf>::new(&space, GraphicsOptions::default());
+
+        // Rays from all around the space, in an order that mixes their major axes.
+        let rays: Vec<Ray> = (0..40)
+            .map(|i| {
+                let angle = FreeCoordinate::from(i) * 0.7;
+                let origin = Vector3::new(
+                    angle.cos() * 5.0,
+                    (angle * 0.3).sin() * 5.0,
+                    angle.sin() * 5.0,
+                );
+                Ray::new(
+                    Point3::new(1.5, 1.5, 1.5) + origin,
+                    -origin + Vector3::new(0.0, FreeCoordinate::from(i % 3) * 0.2, 0.0),
+                )
+            })
+            .collect();
+
+        let expected: Vec<(Rgba, RaytraceInfo)> =
+            rays.iter().map(|&ray| tracer.trace_ray(ray)).collect();
+        assert_eq!(tracer.trace_rays(&rays), expected);
+        assert_eq!(tracer.trace_rays(&[]), vec![]);
+    }
+
+    #[test]
+    fn entity_is_drawn_in_front_of_blocks() {
+        let red = Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0));
+        let mut space = Space::empty_positive(3, 1, 1);
+        space.set([2, 0, 0], &Block::from(Rgba::WHITE)).unwrap();
+        // Compare against a block of the same color in place of the entity.
+        let mut block_space = Space::empty_positive(3, 1, 1);
+        block_space.set([1, 0, 0], &red).unwrap();
+        SpaceTransaction::add_entity(
+            Entity::new([1.5, 0.5, 0.5])
+                .with_appearance(red)
+                .with_scale(0.5),
+        )
+        .execute(&mut space)
+        .unwrap();
+        let mut options = GraphicsOptions::default();
+        options.fog = FogOption::None;
+        options.lighting_display = LightingOption::None;
+        let tracer = SpaceRaytracer::<ColorBuf>::new(&space, options.clone());
+        let block_tracer = SpaceRaytracer::<ColorBuf>::new(&block_space, options);
+
+        for &ray in &[
+            Ray::new([1.5, 0.5, -1.0], [0.0, 0.0, 1.0]),
+            // Passes through the entity before reaching the white block.
+            Ray::new([-1.0, 0.5, 0.5], [1.0, 0.0, 0.0]),
+        ] {
+            assert_eq!(
+                tracer.trace_ray(ray).0,
+                block_tracer.trace_ray(ray).0,
+                "{:?}",
+                ray
+            );
+        }
+        let miss = Ray::new([1.1, 0.5, -1.0], [0.0, 0.0, 1.0]);
+        assert_eq!(
+            tracer.trace_ray(miss).0,
+            space.physics().sky_color.with_alpha_one()
+        );
+    }
+
+    #[test]
+    fn billboard_entity_faces_each_ray() {
+        let red = Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0));
+        let mut space = Space::empty_positive(3, 3, 3);
+        let mut block_space = Space::empty_positive(3, 3, 3);
+        block_space.set([1, 1, 1], &red).unwrap();
+        SpaceTransaction::add_entity(
+            Entity::new([1.5, 1.5, 1.5])
+                .with_appearance(red)
+                .with_shape(EntityShape::Billboard)
+                .with_scale(0.5),
+        )
+        .execute(&mut space)
+        .unwrap();
+        let mut options = GraphicsOptions::default();
+        options.fog = FogOption::None;
+        options.lighting_display = LightingOption::None;
+        let tracer = SpaceRaytracer::<ColorBuf>::new(&space, options.clone());
+        let block_tracer = SpaceRaytracer::<ColorBuf>::new(&block_space, options);
+
+        // The billboard is seen face-on from every direction.
+        for &ray in &[
+            Ray::new([1.5, 1.5, -1.0], [0.0, 0.0, 1.0]),
+            Ray::new([-1.0, 1.6, 1.6], [1.0, 0.0, 0.0]),
+            Ray::new([1.4, 4.0, 1.4], [0.0, -1.0, 0.0]),
+        ] {
+            assert_eq!(
+                tracer.trace_ray(ray).0,
+                block_tracer.trace_ray(ray).0,
+                "{:?}",
+                ray
+            );
+        }
+        let miss = Ray::new([1.1, 1.5, -1.0], [0.0, 0.0, 1.0]);
+        assert_eq!(
+            tracer.trace_ray(miss).0,
+            space.physics().sky_color.with_alpha_one()
+        );
+    }
+
+    #[test]
+    fn model_parts_are_drawn() {
+        let red = Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0));
+        let mut model = Model::new();
+        model
+            .add_part(ModelPart::new(
+                "part",
+                red.clone(),
+                Aab::new(0.5, 1.5, -0.5, 0.5, -0.5, 0.5),
+            ))
+            .unwrap();
+        let mut space = Space::empty_positive(3, 1, 1);
+        SpaceTransaction::add_entity(Entity::new([0.5, 0.5, 0.5]).with_model(model))
+            .execute(&mut space)
+            .unwrap();
+        let mut block_space = Space::empty_positive(3, 1, 1);
+        block_space.set([1, 0, 0], &red).unwrap();
+        let mut options = GraphicsOptions::default();
+        options.fog = FogOption::None;
+        options.lighting_display = LightingOption::None;
+        let tracer = SpaceRaytracer::<ColorBuf>::new(&space, options.clone());
+        let block_tracer = SpaceRaytracer::<ColorBuf>::new(&block_space, options);
+
+        let hit = Ray::new([1.5, 0.5, -1.0], [0.0, 0.0, 1.0]);
+        assert_eq!(tracer.trace_ray(hit).0, block_tracer.trace_ray(hit).0);
+        let miss = Ray::new([0.5, 0.5, -1.0], [0.0, 0.0, 1.0]);
+        assert_eq!(
+            tracer.trace_ray(miss).0,
+            space.physics().sky_color.with_alpha_one()
+        );
+    }
+
+    #[test]
+    fn smooth_light_cache() {
+        let mut space = Space::empty_positive(3, 2, 3);
+        space
+            .fill_uniform(Grid::new((0, 0, 0), (3, 1, 3)), &Block::from(Rgba::WHITE))
+            .unwrap();
+        space.evaluate_light(0, |_| {});
+        let mut options = GraphicsOptions::default();
+        options.lighting_display = LightingOption::Smooth;
+        let mut camera = Camera::new(
+            options.clone(),
+            Viewport {
+                nominal_size: Vector2::new(16., 16.),
+                framebuffer_size: Vector2::new(16, 16),
+            },
+        );
+        camera.set_view_matrix(Matrix4::look_at_rh(
+            eye_for_look_at(space.grid(), Vector3::new(0., 1., 0.2)),
+            space.grid().center(),
+            Vector3::new(0., 1., 0.),
+        ));
+        let tracer = SpaceRaytracer::<ColorBuf>::new(&space, options);
+
+        let (first_image, first_info) = tracer.trace_scene_to_image(&camera);
+        assert!(first_info.light_cache_misses > 0);
+        assert!(first_info.light_cache_hits > first_info.light_cache_misses);
+
+        // Everything needed is now cached, and the results are unchanged.
+        let (second_image, second_info) = tracer.trace_scene_to_image(&camera);
+        assert_eq!(second_image, first_image);
+        assert_eq!(second_info.light_cache_misses, 0);
+        assert_eq!(
+            second_info.light_cache_hits,
+            first_info.light_cache_hits + first_info.light_cache_misses
+        );
+    }
 }
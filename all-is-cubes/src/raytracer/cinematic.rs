@@ -0,0 +1,174 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`render_camera_path`], for offline rendering of a sequence of frames.
+
+use std::time::Duration;
+
+use crate::camera::{Camera, CameraPath};
+use crate::raytracer::{PixelBuf, RaytraceInfo, SpaceRaytracer};
+use crate::space::Space;
+
+/// One image rendered by [`render_camera_path`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct CinematicFrame<Pixel> {
+    /// Position of this frame in the sequence, counting from zero.
+    pub index: usize,
+    /// Time along the [`CameraPath`] which this frame shows.
+    pub time: Duration,
+    /// The pixels, in the usual left-right then top-bottom raster order; the dimensions
+    /// are the camera's [`Viewport::framebuffer_size`](crate::camera::Viewport).
+    pub image: Box<[Pixel]>,
+    pub info: RaytraceInfo,
+}
+
+/// Raytraces `space` as seen from each point along `path`, at `frames_per_second`, and
+/// passes each frame to `frame_callback` in order; for example, to write them to files
+/// to make a video.
+///
+/// `camera` supplies the viewport and graphics options; its view matrix is ignored.
+/// The frames start at time zero and end at or just before [`CameraPath::duration`].
+/// The space is captured once at the start, so it does not change during the sequence.
+///
+/// Returns the number of frames rendered, or the first error returned by
+/// `frame_callback`, in which case no more frames are rendered.
+///
+/// Panics if `frames_per_second` is not positive and finite.
+///
+/// ```
+/// use all_is_cubes::camera::{
+///     Camera, CameraKeyframe, CameraPath, CameraPose, GraphicsOptions, Viewport,
+/// };
+/// use all_is_cubes::cgmath::Vector2;
+/// use all_is_cubes::raytracer::{render_camera_path, ColorBuf};
+/// use all_is_cubes::space::Space;
+/// use std::time::Duration;
+///
+/// let space = Space::empty_positive(4, 4, 4);
+/// let camera = Camera::new(
+///     GraphicsOptions::default(),
+///     Viewport {
+///         nominal_size: Vector2::new(8.0, 8.0),
+///         framebuffer_size: Vector2::new(8, 8),
+///     },
+/// );
+/// let path = CameraPath::new()
+///     .with_keyframe(CameraKeyframe::new(
+///         Duration::ZERO,
+///         CameraPose::looking_at([2.0, 2.0, 10.0], [2.0, 2.0, 2.0]),
+///     ))
+///     .with_keyframe(CameraKeyframe::new(
+///         Duration::from_secs(1),
+///         CameraPose::looking_at([10.0, 2.0, 2.0], [2.0, 2.0, 2.0]),
+///     ));
+///
+/// let mut frames = Vec::new();
+/// let count = render_camera_path::<ColorBuf, _, ()>(&space, &camera, &path, 10.0, |frame| {
+///     frames.push(frame);
+///     Ok(())
+/// })
+/// .unwrap();
+/// assert_eq!(count, 11);
+/// assert_eq!(frames[10].time, Duration::from_secs(1));
+/// assert_eq!(frames[10].image.len(), 8 * 8);
+/// ```
+pub fn render_camera_path<P, F, E>(
+    space: &Space,
+    camera: &Camera,
+    path: &CameraPath,
+    frames_per_second: f64,
+    mut frame_callback: F,
+) -> Result<usize, E>
+where
+    P: PixelBuf,
+    F: FnMut(CinematicFrame<P::Pixel>) -> Result<(), E>,
+{
+    assert!(
+        frames_per_second.is_finite() && frames_per_second > 0.0,
+        "frames_per_second must be positive and finite, not {}",
+        frames_per_second
+    );
+    if path.keyframes().is_empty() {
+        return Ok(0);
+    }
+
+    let mut camera = camera.clone();
+    let tracer = SpaceRaytracer::<P>::new(space, camera.options().clone());
+    // Nudged, so that floating-point error does not drop the last frame.
+    let frame_count =
+        (path.duration().as_secs_f64() * frames_per_second + 1e-9).floor() as usize + 1;
+    for index in 0..frame_count {
+        let time = Duration::from_secs_f64(index as f64 / frames_per_second);
+        let pose = path.pose(time).expect("path has keyframes");
+        camera.set_view_matrix(pose.view_matrix());
+        let (image, info) = tracer.trace_scene_to_image(&camera);
+        frame_callback(CinematicFrame {
+            index,
+            time,
+            image,
+            info,
+        })?;
+    }
+    Ok(frame_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{CameraKeyframe, CameraPose, GraphicsOptions, Viewport};
+    use crate::raytracer::ColorBuf;
+    use cgmath::Vector2;
+
+    #[test]
+    fn callback_error_stops_rendering() {
+        let space = Space::empty_positive(1, 1, 1);
+        let camera = Camera::new(
+            GraphicsOptions::default(),
+            Viewport {
+                nominal_size: Vector2::new(2.0, 2.0),
+                framebuffer_size: Vector2::new(2, 2),
+            },
+        );
+        let path = CameraPath::new()
+            .with_keyframe(CameraKeyframe::new(
+                Duration::ZERO,
+                CameraPose::new([0.0, 0.0, 5.0], 0.0, 0.0),
+            ))
+            .with_keyframe(CameraKeyframe::new(
+                Duration::from_secs(2),
+                CameraPose::new([0.0, 0.0, 9.0], 0.0, 0.0),
+            ));
+        let mut indices = Vec::new();
+        let result =
+            render_camera_path::<ColorBuf, _, &str>(&space, &camera, &path, 2.0, |frame| {
+                indices.push(frame.index);
+                if frame.index == 2 {
+                    Err("stop")
+                } else {
+                    Ok(())
+                }
+            });
+        assert_eq!(result, Err("stop"));
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn empty_path_renders_nothing() {
+        let camera = Camera::new(
+            GraphicsOptions::default(),
+            Viewport {
+                nominal_size: Vector2::new(2.0, 2.0),
+                framebuffer_size: Vector2::new(2, 2),
+            },
+        );
+        let count = render_camera_path::<ColorBuf, _, ()>(
+            &Space::empty_positive(1, 1, 1),
+            &camera,
+            &CameraPath::new(),
+            30.0,
+            |_| panic!("should not be called"),
+        );
+        assert_eq!(count, Ok(0));
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`BlockIconRenderer`], for drawing images of blocks for use in 2D user interfaces.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cgmath::{Angle as _, Deg, InnerSpace as _, Matrix4, Vector2, Vector3};
+use ordered_float::NotNan;
+
+use crate::block::{Block, BlockContentHash, EvalBlockError};
+use crate::camera::{Camera, FogOption, GraphicsOptions, LightingOption, Viewport};
+use crate::math::{FreeCoordinate, Rgb, Rgba};
+use crate::raytracer::{ColorBuf, PixelBuf, SpaceRaytracer};
+use crate::space::{LightPhysics, Space, SpaceBlockData, SpacePhysics};
+
+/// Field of view used for icons; narrow, so that there is not much perspective
+/// distortion.
+const ICON_FOV_Y: FreeCoordinate = 30.0;
+
+/// Draws [`Block`]s as square images, as seen from above and to the front-right, with
+/// uniform lighting and a transparent background.
+///
+/// Icons are cached by the [`BlockContentHash`] of the evaluated block, so an icon is
+/// only drawn once no matter how many [`Block`] values refer to the same appearance,
+/// and a modified [`BlockDef`](crate::block::BlockDef) gets a new icon.
+#[derive(Debug)]
+pub struct BlockIconRenderer {
+    size: u32,
+    cache: HashMap<BlockContentHash, Arc<BlockIcon>>,
+}
+
+impl BlockIconRenderer {
+    /// Constructs a renderer producing `size`×`size` pixel images.
+    pub fn new(size: u32) -> Self {
+        Self {
+            size,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Returns the width and height of the icons.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the icon for `block`, drawing it if it is not already cached.
+    pub fn icon(&mut self, block: &Block) -> Result<Arc<BlockIcon>, EvalBlockError> {
+        let hash = block.evaluate()?.content_hash();
+        let size = self.size;
+        Ok(self
+            .cache
+            .entry(hash)
+            .or_insert_with(|| Arc::new(render_icon(block, size)))
+            .clone())
+    }
+
+    /// Discards all cached icons, such as to free memory after switching universes.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+}
+
+/// Image produced by [`BlockIconRenderer`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockIcon {
+    size: u32,
+    pixels: Box<[Rgba]>,
+}
+
+impl BlockIcon {
+    /// Returns the width and height of the image.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the pixels in the usual left-right then top-bottom raster order. The
+    /// colors are not premultiplied by alpha.
+    pub fn pixels(&self) -> &[Rgba] {
+        &self.pixels
+    }
+
+    /// Returns the pixels as 8-bit sRGB RGBA, such as for an HTML `ImageData` or a
+    /// GPU texture.
+    pub fn to_srgb_32bit(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel| std::array::IntoIter::new(pixel.to_srgb_32bit()))
+            .collect()
+    }
+}
+
+fn render_icon(block: &Block, size: u32) -> BlockIcon {
+    let mut space = Space::empty_positive(1, 1, 1);
+    space.set_physics(SpacePhysics {
+        sky_color: Rgb::ONE,
+        light: LightPhysics::None,
+        ..SpacePhysics::default()
+    });
+    // If this fails, the block could not be evaluated, which our caller already checked.
+    let _ = space.set([0, 0, 0], block);
+
+    let mut options = GraphicsOptions::default();
+    options.fov_y = NotNan::new(ICON_FOV_Y).unwrap();
+    options.fog = FogOption::None;
+    options.lighting_display = LightingOption::Flat;
+    let mut camera = Camera::new(
+        options.clone(),
+        Viewport {
+            nominal_size: Vector2::new(size.into(), size.into()),
+            framebuffer_size: Vector2::new(size, size),
+        },
+    );
+
+    // Place the eye such that the cube's bounding sphere exactly fits the view.
+    let center = space.grid().center();
+    let radius = 3f64.sqrt() / 2.;
+    let distance = radius / (Deg(ICON_FOV_Y) / 2.).sin();
+    let direction = Vector3::new(1., 1., 2.).normalize();
+    camera.set_view_matrix(Matrix4::look_at_rh(
+        center + direction * distance,
+        center,
+        Vector3::unit_y(),
+    ));
+
+    let (pixels, _info) =
+        SpaceRaytracer::<IconBuf>::new(&space, options).trace_scene_to_image(&camera);
+    BlockIcon { size, pixels }
+}
+
+/// [`PixelBuf`] which is like [`ColorBuf`] but leaves the background transparent.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct IconBuf {
+    color: ColorBuf,
+}
+
+impl PixelBuf for IconBuf {
+    type Pixel = Rgba;
+    /// Whether the surface is the sky.
+    type BlockData = bool;
+
+    fn compute_block_data(_: &SpaceBlockData) -> bool {
+        false
+    }
+
+    fn error_block_data() -> bool {
+        false
+    }
+
+    fn sky_block_data() -> bool {
+        true
+    }
+
+    #[inline]
+    fn opaque(&self) -> bool {
+        self.color.opaque()
+    }
+
+    #[inline]
+    fn result(self) -> Rgba {
+        self.color.result()
+    }
+
+    #[inline]
+    fn add(&mut self, surface_color: Rgba, is_sky: &bool) {
+        if !*is_sky {
+            self.color.add(surface_color, &());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::universe::Universe;
+
+    #[test]
+    fn icon_has_block_in_middle_and_transparent_corners() {
+        let mut renderer = BlockIconRenderer::new(16);
+        let icon = renderer.icon(&Block::from(Rgba::WHITE)).unwrap();
+        assert_eq!(icon.size(), 16);
+        assert_eq!(icon.pixels().len(), 16 * 16);
+        assert_eq!(icon.to_srgb_32bit().len(), 16 * 16 * 4);
+        assert_eq!(icon.pixels()[0], Rgba::TRANSPARENT);
+        let middle = icon.pixels()[8 * 16 + 8];
+        assert_eq!(middle.alpha().into_inner(), 1.0);
+        assert!(middle.to_rgb() != Rgb::ZERO);
+    }
+
+    #[test]
+    fn cache_by_content() {
+        let mut universe = Universe::new();
+        let mut renderer = BlockIconRenderer::new(4);
+        let block = Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0));
+        let indirect =
+            Block::Indirect(universe.insert_anonymous(crate::block::BlockDef::new(block.clone())));
+
+        let first = renderer.icon(&block).unwrap();
+        assert!(Arc::ptr_eq(&first, &renderer.icon(&indirect).unwrap()));
+        let other = renderer.icon(&Block::from(Rgba::WHITE)).unwrap();
+        assert!(!Arc::ptr_eq(&first, &other));
+    }
+}
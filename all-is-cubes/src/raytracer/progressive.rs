@@ -0,0 +1,245 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`ProgressiveRaytracer`], for incremental interactive rendering.
+
+use cgmath::{Matrix4, Point2};
+
+use crate::camera::{Camera, GraphicsOptions, Viewport};
+use crate::math::FreeCoordinate;
+use crate::raytracer::{PixelBuf, RaytraceInfo, SpaceRaytracer};
+use crate::space::{DirtyRegions, Space};
+use crate::universe::URef;
+
+/// Size, in pixels, of the square blocks that each pass traces one ray for. The first
+/// pass produces a quarter-resolution image and each later pass doubles the resolution.
+const PASS_BLOCK_SIZES: [usize; 3] = [4, 2, 1];
+
+/// Renders a [`Space`] in several passes of increasing resolution, spread across
+/// as many calls as necessary to stay within a per-call budget of rays.
+///
+/// The first pass traces one ray per 4×4 block of pixels; each subsequent pass halves
+/// the block size, tracing only the pixels that earlier passes have not. The image is
+/// always complete (every pixel has some value from the most recent pass covering it).
+///
+/// When neither the camera nor the space has changed since the image was completed,
+/// further calls do no work. Any change to either starts over from the first pass.
+pub struct ProgressiveRaytracer<P: PixelBuf> {
+    space: URef<Space>,
+    /// Any change to the space makes the whole image stale, so this is used only to
+    /// check whether there are any changes.
+    space_dirty: DirtyRegions,
+    tracer: Option<SpaceRaytracer<P>>,
+    /// The camera parameters the current image and `tracer` were made with.
+    camera_state: Option<(Viewport, Matrix4<FreeCoordinate>, GraphicsOptions)>,
+    image: Vec<P::Pixel>,
+    /// Index into [`PASS_BLOCK_SIZES`] of the pass in progress; equal to its length when
+    /// the image is complete.
+    pass: usize,
+    /// Index, in raster order, of the next block to trace in the current pass.
+    next_block: usize,
+}
+
+impl<P: PixelBuf> ProgressiveRaytracer<P>
+where
+    P::Pixel: Clone,
+{
+    /// Constructs a [`ProgressiveRaytracer`] which will draw `space`. No rendering is
+    /// done until [`Self::refine`] is called.
+    pub fn new(space: URef<Space>) -> Self {
+        let space_dirty = DirtyRegions::new(16).including_lighting();
+        space.borrow().listen_batched(space_dirty.listener());
+        Self {
+            space,
+            space_dirty,
+            tracer: None,
+            camera_state: None,
+            image: Vec::new(),
+            pass: 0,
+            next_block: 0,
+        }
+    }
+
+    /// Traces up to `ray_budget` rays to improve the image of the space as seen by
+    /// `camera`, first discarding the existing image if the camera or the space has
+    /// changed.
+    ///
+    /// Returns information about the rays traced by this call only.
+    pub fn refine(&mut self, camera: &Camera, ray_budget: usize) -> RaytraceInfo {
+        let camera_state = (
+            camera.viewport(),
+            camera.view_matrix(),
+            camera.options().clone(),
+        );
+        let space_changed = !self.space_dirty.take(self.space.borrow().grid()).is_empty();
+        if space_changed || self.camera_state.as_ref() != Some(&camera_state) {
+            if space_changed
+                || self.camera_state.as_ref().map(|(_, _, options)| options)
+                    != Some(&camera_state.2)
+            {
+                self.tracer = Some(SpaceRaytracer::new(
+                    &*self.space.borrow(),
+                    camera_state.2.clone(),
+                ));
+            }
+            let pixel_count = camera.viewport().pixel_count().expect("image too large");
+            self.image.clear();
+            self.image.resize(pixel_count, P::default().result());
+            self.pass = 0;
+            self.next_block = 0;
+            self.camera_state = Some(camera_state);
+        }
+        let tracer = self.tracer.as_ref().unwrap();
+
+        let viewport = camera.viewport();
+        let width = viewport.framebuffer_size.x as usize;
+        let height = viewport.framebuffer_size.y as usize;
+        let mut info = RaytraceInfo::default();
+        let mut rays_traced = 0;
+        while self.pass < PASS_BLOCK_SIZES.len() && rays_traced < ray_budget {
+            let size = PASS_BLOCK_SIZES[self.pass];
+            let blocks_wide = (width + size - 1) / size;
+            let block_count = blocks_wide * ((height + size - 1) / size);
+            if self.next_block >= block_count {
+                self.pass += 1;
+                self.next_block = 0;
+                continue;
+            }
+
+            let x = (self.next_block % blocks_wide) * size;
+            let y = (self.next_block / blocks_wide) * size;
+            self.next_block += 1;
+            if self.pass > 0 && x % (size * 2) == 0 && y % (size * 2) == 0 {
+                // Already traced by the previous pass.
+                continue;
+            }
+
+            let (pixel, ray_info) = tracer.trace_ray(camera.project_ndc_into_world(Point2::new(
+                viewport.normalize_fb_x(x),
+                viewport.normalize_fb_y(y),
+            )));
+            info += ray_info;
+            rays_traced += 1;
+            for fill_y in y..(y + size).min(height) {
+                for fill_x in x..(x + size).min(width) {
+                    self.image[fill_y * width + fill_x] = pixel.clone();
+                }
+            }
+        }
+        info
+    }
+
+    /// Returns the image as of the last call to [`Self::refine`], in the usual
+    /// left-right then top-bottom raster order. Its dimensions are the
+    /// `framebuffer_size` of the camera that was passed.
+    pub fn image(&self) -> &[P::Pixel] {
+        &self.image
+    }
+
+    /// Returns whether the image is at full resolution, in which case further calls to
+    /// [`Self::refine`] with the same camera will do nothing unless the space changes.
+    pub fn is_complete(&self) -> bool {
+        self.camera_state.is_some() && self.pass >= PASS_BLOCK_SIZES.len()
+    }
+}
+
+impl<P: PixelBuf> std::fmt::Debug for ProgressiveRaytracer<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressiveRaytracer")
+            .field("space", &self.space)
+            .field("camera_state", &self.camera_state)
+            .field("pass", &self.pass)
+            .field("next_block", &self.next_block)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::content::make_some_blocks;
+    use crate::math::{GridCoordinate, Rgba};
+    use crate::raytracer::ColorBuf;
+    use crate::universe::Universe;
+    use cgmath::{Vector2, Vector3};
+
+    fn setup() -> (Universe, URef<Space>, Camera) {
+        let mut universe = Universe::new();
+        let mut space = Space::empty_positive(3, 1, 1);
+        for (i, block) in make_some_blocks::<3>().iter().enumerate() {
+            space.set([i as GridCoordinate, 0, 0], block).unwrap();
+        }
+        let space = universe.insert_anonymous(space);
+        let mut camera = Camera::new(
+            GraphicsOptions::default(),
+            Viewport {
+                nominal_size: Vector2::new(10., 10.),
+                framebuffer_size: Vector2::new(10, 10),
+            },
+        );
+        camera.set_view_matrix(Matrix4::look_at_rh(
+            crate::camera::eye_for_look_at(space.borrow().grid(), Vector3::new(0., 0., 1.)),
+            space.borrow().grid().center(),
+            Vector3::new(0., 1., 0.),
+        ));
+        (universe, space, camera)
+    }
+
+    #[test]
+    fn first_pass_is_blocky_and_final_pass_matches() {
+        let (_universe, space, camera) = setup();
+        let mut progressive = ProgressiveRaytracer::<ColorBuf>::new(space.clone());
+
+        // 10×10 pixels in 4×4 blocks is 3×3 blocks.
+        progressive.refine(&camera, 9);
+        assert!(!progressive.is_complete());
+        let image = progressive.image();
+        assert_eq!(image.len(), 100);
+        for y in 0..10 {
+            for x in 0..10 {
+                assert_eq!(image[y * 10 + x], image[(y / 4 * 4) * 10 + (x / 4 * 4)]);
+            }
+        }
+
+        progressive.refine(&camera, usize::MAX);
+        assert!(progressive.is_complete());
+        let (expected, _) =
+            SpaceRaytracer::<ColorBuf>::new(&*space.borrow(), camera.options().clone())
+                .trace_scene_to_image(&camera);
+        assert_eq!(progressive.image(), &*expected);
+    }
+
+    #[test]
+    fn reuses_complete_image() {
+        let (_universe, space, camera) = setup();
+        let mut progressive = ProgressiveRaytracer::<ColorBuf>::new(space);
+        progressive.refine(&camera, usize::MAX);
+        assert!(progressive.is_complete());
+        assert_eq!(
+            progressive.refine(&camera, usize::MAX),
+            RaytraceInfo::default()
+        );
+        assert!(progressive.is_complete());
+    }
+
+    #[test]
+    fn restarts_on_change() {
+        let (_universe, space, mut camera) = setup();
+        let mut progressive = ProgressiveRaytracer::<ColorBuf>::new(space.clone());
+        progressive.refine(&camera, usize::MAX);
+
+        space
+            .borrow_mut()
+            .set([1, 0, 0], &Block::from(Rgba::WHITE))
+            .unwrap();
+        progressive.refine(&camera, 1);
+        assert!(!progressive.is_complete());
+        progressive.refine(&camera, usize::MAX);
+        assert!(progressive.is_complete());
+
+        camera.set_view_matrix(Matrix4::from_translation(Vector3::new(0., 0., -1.)));
+        progressive.refine(&camera, 1);
+        assert!(!progressive.is_complete());
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Test support for checking that the raytracer and the mesh renderer agree.
+//!
+//! The mesh half is rendered without any GPU, by the [`rasterizer`](crate::rasterizer),
+//! which implements the same shading rules the fragment shader uses. This catches the
+//! class of bugs where the Rust and GLSL versions of lighting/coloring code drift apart,
+//! as long as each is kept consistent with its counterpart in the rasterizer.
+//!
+//! Only available with the `rendertest` feature enabled (or in this crate's own tests).
+
+use cgmath::Vector4;
+
+use crate::camera::{Camera, LightingOption, Viewport};
+use crate::math::Rgba;
+use crate::rasterizer::rasterize_space;
+use crate::raytracer::{ColorBuf, SpaceRaytracer};
+use crate::space::Space;
+
+/// Renders `space` as seen by `camera` with both the raytracer and the software
+/// rasterization of the triangulator's output.
+///
+/// The two renderers differ in how much of [`LightingOption::Smooth`] they implement
+/// (vertex lighting cannot interpolate), so smooth lighting is replaced with
+/// [`LightingOption::Flat`] for both.
+pub fn compare_renderers(space: &Space, camera: &Camera) -> RenderComparison {
+    let mut options = camera.options().clone();
+    if options.lighting_display == LightingOption::Smooth {
+        options.lighting_display = LightingOption::Flat;
+    }
+    let mut camera = camera.clone();
+    camera.set_options(options.clone());
+
+    let (raytraced, _info) =
+        SpaceRaytracer::<ColorBuf>::new(space, options).trace_scene_to_image(&camera);
+
+    let rasterized = rasterize_space(space, &camera);
+
+    RenderComparison {
+        viewport: camera.viewport(),
+        raytraced,
+        rasterized,
+    }
+}
+
+/// Result of [`compare_renderers`]: two images of the same scene, in the usual
+/// left-right then top-bottom raster order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderComparison {
+    viewport: Viewport,
+    raytraced: Box<[Rgba]>,
+    rasterized: Box<[Rgba]>,
+}
+
+impl RenderComparison {
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// The image produced by [`SpaceRaytracer`].
+    pub fn raytraced(&self) -> &[Rgba] {
+        &self.raytraced
+    }
+
+    /// The image produced by rasterizing the triangulated mesh.
+    pub fn rasterized(&self) -> &[Rgba] {
+        &self.rasterized
+    }
+
+    /// Largest difference in any color component, over all pixels.
+    pub fn max_difference(&self) -> f32 {
+        self.differences().fold(0.0, f32::max)
+    }
+
+    /// Number of pixels which differ in any color component by more than `tolerance`.
+    pub fn count_differing_pixels(&self, tolerance: f32) -> usize {
+        self.differences().filter(|&d| d > tolerance).count()
+    }
+
+    /// Panics, printing a map of differing pixels, if more than `allowed_fraction` of
+    /// the pixels differ by more than `tolerance`.
+    ///
+    /// Some differences are expected along the edges of geometry, since the two
+    /// renderers do not sample edges identically; `allowed_fraction` should be small but
+    /// not zero unless the scene has no visible edges.
+    pub fn assert_similar(&self, tolerance: f32, allowed_fraction: f64) {
+        let differing = self.count_differing_pixels(tolerance);
+        let total = self.raytraced.len();
+        if differing as f64 > total as f64 * allowed_fraction {
+            panic!(
+                "renderers disagree on {} of {} pixels (max difference {})\n{}",
+                differing,
+                total,
+                self.max_difference(),
+                self.difference_map(tolerance)
+            );
+        }
+    }
+
+    /// Text picture of which pixels differ by more than `tolerance`, for test failure
+    /// messages.
+    pub fn difference_map(&self, tolerance: f32) -> String {
+        let width = self.viewport.framebuffer_size.x as usize;
+        let mut map = String::with_capacity(self.raytraced.len() + self.raytraced.len() / width);
+        for (i, d) in self.differences().enumerate() {
+            map.push(if d > tolerance { '#' } else { '.' });
+            if (i + 1) % width == 0 {
+                map.push('\n');
+            }
+        }
+        map
+    }
+
+    fn differences(&self) -> impl Iterator<Item = f32> + '_ {
+        self.raytraced
+            .iter()
+            .zip(self.rasterized.iter())
+            .map(|(&a, &b)| {
+                let a: Vector4<f32> = a.into();
+                let b: Vector4<f32> = b.into();
+                let d = a - b;
+                d.x.abs().max(d.y.abs()).max(d.z.abs()).max(d.w.abs())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{FogOption, GraphicsOptions, TransparencyOption};
+    use crate::content::{make_some_blocks, make_some_voxel_blocks};
+    use crate::math::{FaceMap, GridCoordinate, Rgb};
+    use crate::space::{Grid, SkyGradient, Skybox, SpacePhysics};
+    use crate::universe::Universe;
+    use cgmath::{Matrix4, Vector2, Vector3};
+
+    fn camera_looking_at(space: &Space, options: GraphicsOptions) -> Camera {
+        let mut camera = Camera::new(
+            options,
+            Viewport {
+                nominal_size: Vector2::new(64., 48.),
+                framebuffer_size: Vector2::new(64, 48),
+            },
+        );
+        camera.set_view_matrix(Matrix4::look_at_rh(
+            crate::camera::eye_for_look_at(space.grid(), Vector3::new(0.4, 0.7, 1.0)),
+            space.grid().center(),
+            Vector3::new(0., 1., 0.),
+        ));
+        camera
+    }
+
+    #[test]
+    fn atom_blocks_agree() {
+        let blocks = make_some_blocks::<3>();
+        let mut space = Space::empty_positive(3, 1, 3);
+        for (i, block) in blocks.iter().enumerate() {
+            space
+                .set([i as GridCoordinate, 0, i as GridCoordinate], block)
+                .unwrap();
+        }
+        let camera = camera_looking_at(&space, GraphicsOptions::default());
+        compare_renderers(&space, &camera).assert_similar(0.02, 0.05);
+    }
+
+    #[test]
+    fn voxel_blocks_agree() {
+        let mut universe = Universe::new();
+        let blocks = make_some_voxel_blocks::<3>(&mut universe);
+        let mut space = Space::empty(Grid::new((0, 0, 0), (blocks.len() as GridCoordinate, 1, 1)));
+        for (i, block) in blocks.iter().enumerate() {
+            space.set([i as GridCoordinate, 0, 0], block).unwrap();
+        }
+        let mut options = GraphicsOptions::default();
+        options.transparency = TransparencyOption::Threshold(notnan!(0.5));
+        let camera = camera_looking_at(&space, options);
+        // Looser tolerance to account for 8-bit texture storage.
+        compare_renderers(&space, &camera).assert_similar(0.05, 0.05);
+    }
+
+    #[test]
+    fn fog_agrees() {
+        let blocks = make_some_blocks::<3>();
+        let mut space = Space::empty_positive(3, 1, 3);
+        for (i, block) in blocks.iter().enumerate() {
+            space
+                .set([i as GridCoordinate, 0, i as GridCoordinate], block)
+                .unwrap();
+        }
+        let mut options = GraphicsOptions::default();
+        options.fog = FogOption::Physical;
+        // Close enough that the fog is thick, but not so close that anything is
+        // clipped by the far plane.
+        options.view_distance = notnan!(20.0);
+        let camera = camera_looking_at(&space, options);
+        compare_renderers(&space, &camera).assert_similar(0.02, 0.05);
+    }
+
+    #[test]
+    fn skybox_agrees() {
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set_physics(SpacePhysics {
+            skybox: Some(Skybox::Gradients(FaceMap::from_fn(|face| {
+                let n: Vector3<f32> = face.normal_vector();
+                SkyGradient {
+                    below: Rgb::new(0.0, 0.5, 0.0),
+                    above: Rgb::new(n.x.abs(), n.y.abs(), n.z.abs()),
+                }
+            }))),
+            ..SpacePhysics::default()
+        });
+        let camera = camera_looking_at(&space, GraphicsOptions::default());
+        compare_renderers(&space, &camera).assert_similar(0.01, 0.0);
+    }
+
+    #[test]
+    fn difference_map_shape() {
+        let comparison = RenderComparison {
+            viewport: Viewport {
+                nominal_size: Vector2::new(2., 1.),
+                framebuffer_size: Vector2::new(2, 1),
+            },
+            raytraced: vec![Rgba::BLACK, Rgba::BLACK].into(),
+            rasterized: vec![Rgba::BLACK, Rgba::WHITE].into(),
+        };
+        assert_eq!(comparison.difference_map(0.1), ".#\n");
+        assert_eq!(comparison.count_differing_pixels(0.1), 1);
+        assert_eq!(comparison.max_difference(), 1.0);
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Versioning of saved data, so that files written by older versions of All is Cubes
+//! can still be loaded after the data structures change.
+//!
+//! Each kind of saved data has a [`SaveFormat`], which names it, gives its current
+//! version number, and lists the [`Migration`]s that upgrade data from each previous
+//! version to the next. Saved files are JSON, wrapped in an envelope recording the
+//! format and version:
+//!
+//! ```json
+//! {"format": "all-is-cubes/prefab", "version": 1, "data": {...}}
+//! ```
+//!
+//! When the serialized form of a type changes, increment the format's
+//! `current_version`, add a [`Migration`] from the previous version, and add a fixture
+//! file in the old format to the tests.
+//!
+//! TODO: Universes are not yet serializable; once they are, they should get a format.
+//! Until then, [`Autosave`] saves each [`Space`](crate::space::Space) as a prefab.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::TryFrom as _;
+
+mod autosave;
+pub use autosave::*;
+
+/// A kind of saved data and the history of its versions.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SaveFormat {
+    /// Name identifying this format in saved files.
+    pub name: &'static str,
+    /// Version which [`SaveFormat::save`] writes.
+    pub current_version: u32,
+    /// Migrations from each version before `current_version`.
+    pub migrations: &'static [Migration],
+}
+
+/// A function which converts data from one version of a [`SaveFormat`] to the next.
+#[derive(Debug)]
+#[allow(clippy::exhaustive_structs)]
+pub struct Migration {
+    /// The version this migration accepts; it produces version `from_version + 1`.
+    pub from_version: u32,
+    /// Converts the `data` part of the saved file.
+    pub migrate: fn(Value) -> Result<Value, String>,
+}
+
+impl SaveFormat {
+    /// Constructs a format description; intended for defining constants.
+    pub const fn new(
+        name: &'static str,
+        current_version: u32,
+        migrations: &'static [Migration],
+    ) -> Self {
+        Self {
+            name,
+            current_version,
+            migrations,
+        }
+    }
+
+    /// Serializes `value` as the current version of this format.
+    pub fn save<T: Serialize>(&self, value: &T) -> Result<String, SaveError> {
+        let envelope = serde_json::json!({
+            "format": self.name,
+            "version": self.current_version,
+            "data": serde_json::to_value(value)?,
+        });
+        Ok(serde_json::to_string(&envelope)?)
+    }
+
+    /// Deserializes data in any version of this format that can be migrated to the
+    /// current version.
+    pub fn load<T: DeserializeOwned>(&self, text: &str) -> Result<T, SaveError> {
+        let value: Value = serde_json::from_str(text)?;
+        Ok(serde_json::from_value(self.upgrade(value)?)?)
+    }
+
+    /// Converts a saved file (including its envelope) to the `data` of the current
+    /// version, applying migrations as needed.
+    ///
+    /// Data without an envelope is treated as version 0, which is what was written
+    /// before saved files were versioned.
+    pub fn upgrade(&self, file: Value) -> Result<Value, SaveError> {
+        let (mut version, mut data) = match file {
+            Value::Object(mut map)
+                if map.contains_key("format")
+                    && map.contains_key("version")
+                    && map.contains_key("data") =>
+            {
+                let format = map.remove("format").unwrap();
+                if format.as_str() != Some(self.name) {
+                    return Err(SaveError::WrongFormat {
+                        expected: self.name,
+                        found: format.to_string(),
+                    });
+                }
+                let version = map
+                    .remove("version")
+                    .unwrap()
+                    .as_u64()
+                    .and_then(|v| u32::try_from(v).ok())
+                    .ok_or(SaveError::InvalidVersion)?;
+                (version, map.remove("data").unwrap())
+            }
+            unversioned => (0, unversioned),
+        };
+
+        if version > self.current_version {
+            return Err(SaveError::TooNew {
+                version,
+                current_version: self.current_version,
+            });
+        }
+        while version < self.current_version {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|m| m.from_version == version)
+                .ok_or(SaveError::NoMigration {
+                    format: self.name,
+                    from_version: version,
+                })?;
+            data = (migration.migrate)(data).map_err(|message| SaveError::Migration {
+                from_version: version,
+                message,
+            })?;
+            version += 1;
+        }
+        Ok(data)
+    }
+}
+
+/// Errors from [`SaveFormat::save`] and [`SaveFormat::load`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum SaveError {
+    /// The data is not valid JSON or does not match the expected structure.
+    #[error("invalid saved data: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The file is of a different format.
+    #[error("expected saved {expected}, found {found}")]
+    WrongFormat {
+        expected: &'static str,
+        found: String,
+    },
+    /// The file's version number is not a valid version number.
+    #[error("invalid version number")]
+    InvalidVersion,
+    /// The file was written by a newer version of All is Cubes.
+    #[error(
+        "saved data is version {version}, but only versions up to {current_version} are supported"
+    )]
+    TooNew { version: u32, current_version: u32 },
+    /// The file is of a version which cannot be upgraded.
+    #[error("no migration for {format} from version {from_version}")]
+    NoMigration {
+        format: &'static str,
+        from_version: u32,
+    },
+    /// A migration failed, because the data was not valid for its version.
+    #[error("failed to migrate saved data from version {from_version}: {message}")]
+    Migration { from_version: u32, message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    /// Version 0 was `{"n": 1}`; version 1 is `{"count": 1}`; version 2 adds `label`.
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Thing {
+        count: u32,
+        label: String,
+    }
+
+    const THING: SaveFormat = SaveFormat::new(
+        "test/thing",
+        2,
+        &[
+            Migration {
+                from_version: 0,
+                migrate: rename_n,
+            },
+            Migration {
+                from_version: 1,
+                migrate: add_label,
+            },
+        ],
+    );
+
+    fn rename_n(mut data: Value) -> Result<Value, String> {
+        let object = data.as_object_mut().ok_or("not an object")?;
+        let n = object.remove("n").ok_or("missing n")?;
+        object.insert("count".into(), n);
+        Ok(data)
+    }
+
+    fn add_label(mut data: Value) -> Result<Value, String> {
+        data.as_object_mut()
+            .ok_or("not an object")?
+            .insert("label".into(), "".into());
+        Ok(data)
+    }
+
+    #[test]
+    fn round_trip() {
+        let thing = Thing {
+            count: 3,
+            label: "x".into(),
+        };
+        let text = THING.save(&thing).unwrap();
+        assert_eq!(
+            text,
+            r#"{"data":{"count":3,"label":"x"},"format":"test/thing","version":2}"#
+        );
+        assert_eq!(THING.load::<Thing>(&text).unwrap(), thing);
+    }
+
+    #[test]
+    fn migrate_through_all_versions() {
+        assert_eq!(
+            THING.load::<Thing>(r#"{"n": 5}"#).unwrap(),
+            Thing {
+                count: 5,
+                label: "".into()
+            }
+        );
+        assert_eq!(
+            THING
+                .load::<Thing>(r#"{"format":"test/thing","version":1,"data":{"count":5}}"#)
+                .unwrap(),
+            Thing {
+                count: 5,
+                label: "".into()
+            }
+        );
+    }
+
+    #[test]
+    fn migration_failure() {
+        assert!(matches!(
+            THING.load::<Thing>(r#"{"m": 5}"#),
+            Err(SaveError::Migration {
+                from_version: 0,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn too_new() {
+        assert!(matches!(
+            THING.load::<Thing>(r#"{"format":"test/thing","version":3,"data":{}}"#),
+            Err(SaveError::TooNew {
+                version: 3,
+                current_version: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn wrong_format() {
+        assert!(matches!(
+            THING.load::<Thing>(r#"{"format":"test/other","version":1,"data":{}}"#),
+            Err(SaveError::WrongFormat { .. })
+        ));
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Autosave`], which periodically writes the [`Space`]s of a [`Universe`] to disk.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::apps::Tick;
+use crate::listen::{DirtyFlag, Listener, Notifier};
+use crate::save::SaveError;
+use crate::space::{Prefab, PrefabError, Space};
+use crate::universe::{Name, URef, Universe, UniverseIndex as _, UniverseMetadata};
+
+/// Periodically saves each [`Space`] in a [`Universe`] that has changed since it was
+/// last saved, as a [`PrefabData`](crate::space::PrefabData) file in a directory,
+/// along with the [`UniverseMetadata`] in `universe.json`.
+///
+/// Call [`Autosave::step`] after each [`Universe::step`] with the same [`Tick`]; once
+/// the configured interval of game time has passed, the changed spaces are written.
+/// Each file is written with [`write_atomically`], so a crash during saving leaves
+/// the previous save intact.
+///
+/// Progress is reported as [`AutosaveEvent`]s to listeners registered with
+/// [`Autosave::listen`], so that a frontend can display it.
+///
+/// TODO: Saving is done synchronously and will cause a hitch in large worlds. Also,
+/// only spaces are saved, and they can only contain blocks which
+/// [`PrefabBlockData`](crate::space::PrefabBlockData) can represent.
+pub struct Autosave {
+    directory: PathBuf,
+    interval: Duration,
+    elapsed: Duration,
+    /// For each space we have seen, whether it has changed since it was saved.
+    dirty: HashMap<Name, DirtyFlag>,
+    /// The metadata most recently written, to avoid rewriting it unchanged.
+    saved_metadata: Option<UniverseMetadata>,
+    notifier: Notifier<AutosaveEvent>,
+}
+
+impl std::fmt::Debug for Autosave {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Autosave")
+            .field("directory", &self.directory)
+            .field("interval", &self.interval)
+            .field("elapsed", &self.elapsed)
+            .field("dirty", &self.dirty)
+            .finish()
+    }
+}
+
+impl Autosave {
+    /// Constructs an [`Autosave`] which writes files in `directory` (which must exist)
+    /// every 60 seconds of game time.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            interval: Duration::from_secs(60),
+            elapsed: Duration::ZERO,
+            dirty: HashMap::new(),
+            saved_metadata: None,
+            notifier: Notifier::new(),
+        }
+    }
+
+    /// Sets how much game time passes between saves.
+    #[must_use]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Registers a listener for progress reports.
+    pub fn listen(&self, listener: impl Listener<AutosaveEvent> + 'static) {
+        self.notifier.listen(listener)
+    }
+
+    /// Returns the path of the file in which the space named `name` is saved.
+    pub fn path_for(&self, name: &Name) -> PathBuf {
+        let file_name = match name {
+            Name::Specific(name) => {
+                // Keep the name recognizable, but don't let it escape the directory
+                // or contain characters some filesystems reject.
+                let cleaned: String = name
+                    .chars()
+                    .map(|c| {
+                        if c.is_alphanumeric() || c == '-' || c == '_' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                format!("space-{}.json", cleaned)
+            }
+            Name::Anonym(index) => format!("anonymous-space-{}.json", index),
+        };
+        self.directory.join(file_name)
+    }
+
+    /// Advances the clock, and saves if the interval has passed.
+    ///
+    /// Returns whether a save was attempted. Errors are reported only as
+    /// [`AutosaveEvent::Failed`]; spaces that failed to save are tried again next time.
+    pub fn step(&mut self, universe: &Universe, tick: Tick) -> bool {
+        self.watch_new_spaces(universe);
+        self.elapsed += tick.delta_t;
+        if self.elapsed < self.interval {
+            return false;
+        }
+        self.elapsed = Duration::ZERO;
+        self.save_now(universe);
+        true
+    }
+
+    /// Immediately saves all spaces which have changed since they were last saved.
+    /// Returns the number of spaces successfully saved.
+    pub fn save_now(&mut self, universe: &Universe) -> usize {
+        self.watch_new_spaces(universe);
+
+        let to_save: Vec<(Name, URef<Space>)> = universe
+            .iter_by_type()
+            .filter(|(name, _): &(Name, URef<Space>)| {
+                self.dirty
+                    .get(name)
+                    .map(DirtyFlag::get_and_clear)
+                    .unwrap_or(false)
+            })
+            .collect();
+        let total = to_save.len();
+        self.notifier
+            .notify(AutosaveEvent::Started { spaces: total });
+
+        let mut saved = 0;
+        for (name, space_ref) in to_save {
+            match self.save_space(&name, &space_ref) {
+                Ok(()) => {
+                    saved += 1;
+                    self.notifier.notify(AutosaveEvent::SavedSpace {
+                        name,
+                        done: saved,
+                        total,
+                    });
+                }
+                Err(error) => {
+                    // Forget the space, so that the next save sees it as new and
+                    // tries again.
+                    self.dirty.remove(&name);
+                    self.notifier.notify(AutosaveEvent::Failed {
+                        name,
+                        error: error.to_string(),
+                    });
+                }
+            }
+        }
+
+        if self.saved_metadata.as_ref() != Some(universe.metadata()) {
+            match self.save_metadata(universe.metadata()) {
+                Ok(()) => self.saved_metadata = Some(universe.metadata().clone()),
+                Err(error) => self.notifier.notify(AutosaveEvent::MetadataFailed {
+                    error: error.to_string(),
+                }),
+            }
+        }
+
+        self.notifier
+            .notify(AutosaveEvent::Finished { saved, total });
+        saved
+    }
+
+    /// Starts tracking changes to any spaces not seen before; they are considered
+    /// to need saving.
+    fn watch_new_spaces(&mut self, universe: &Universe) {
+        let spaces: Vec<(Name, URef<Space>)> = universe.iter_by_type().collect();
+        for (name, space_ref) in spaces {
+            if self.dirty.contains_key(&name) {
+                continue;
+            }
+            // If the space is busy, we'll get it next time.
+            if let Ok(space) = space_ref.try_borrow() {
+                let flag = DirtyFlag::new(true);
+                space.listen(flag.listener());
+                self.dirty.insert(name, flag);
+            }
+        }
+    }
+
+    fn save_space(&self, name: &Name, space_ref: &URef<Space>) -> Result<(), AutosaveError> {
+        let text = {
+            let space = space_ref
+                .try_borrow()
+                .map_err(|e| AutosaveError::Busy(e.to_string()))?;
+            Prefab::capture_with_light(&space, space.grid())
+                .to_data()?
+                .save()?
+        };
+        write_atomically(&self.path_for(name), text.as_bytes())?;
+        Ok(())
+    }
+
+    fn save_metadata(&self, metadata: &UniverseMetadata) -> Result<(), AutosaveError> {
+        let text = metadata.save()?;
+        write_atomically(&self.directory.join("universe.json"), text.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Progress report from [`Autosave`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum AutosaveEvent {
+    /// A save has begun, and this many spaces need saving.
+    Started { spaces: usize },
+    /// A space was saved. `done` of `total` spaces have been saved so far.
+    SavedSpace {
+        name: Name,
+        done: usize,
+        total: usize,
+    },
+    /// A space could not be saved.
+    Failed { name: Name, error: String },
+    /// The [`UniverseMetadata`] could not be saved.
+    MetadataFailed { error: String },
+    /// The save is finished. `saved` of `total` spaces were saved successfully.
+    Finished { saved: usize, total: usize },
+}
+
+/// Errors from saving an individual space in [`Autosave`].
+#[derive(Debug, thiserror::Error)]
+enum AutosaveError {
+    #[error("space is in use: {0}")]
+    Busy(String),
+    #[error(transparent)]
+    Prefab(#[from] PrefabError),
+    #[error(transparent)]
+    Save(#[from] SaveError),
+    #[error("could not write file: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Writes `contents` to the file at `path` such that, even if the process or system
+/// crashes, the file either has its previous contents or `contents`, never a mixture.
+///
+/// This is done by writing a temporary file next to it, flushing it to disk, and
+/// renaming it over the original.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+        .to_owned();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::listen::Sink;
+    use crate::math::Rgba;
+    use crate::space::PrefabData;
+
+    fn temp_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "all-is-cubes-test-{}-{}",
+            test_name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn saves_only_dirty_spaces_on_interval() {
+        let dir = temp_dir("autosave");
+        let mut universe = Universe::new();
+        let space_ref = universe
+            .insert("a".into(), Space::empty_positive(1, 1, 1))
+            .unwrap();
+        universe
+            .insert("b/c".into(), Space::empty_positive(1, 1, 1))
+            .unwrap();
+        let mut autosave = Autosave::new(&dir).interval(Duration::from_secs(2));
+        let sink = Sink::new();
+        autosave.listen(sink.listener());
+        let tick = Tick::from_seconds(1.0);
+
+        // Everything is initially unsaved.
+        assert!(!autosave.step(&universe, tick));
+        assert!(autosave.step(&universe, tick));
+        assert!(sink.take_equal(AutosaveEvent::Finished { saved: 2, total: 2 }));
+        assert!(dir.join("universe.json").exists());
+        let path = autosave.path_for(&"b/c".into());
+        assert_eq!(path, dir.join("space-b_c.json"));
+        assert!(path.exists());
+
+        // Nothing changed.
+        autosave.step(&universe, tick);
+        assert!(autosave.step(&universe, tick));
+        assert!(sink.take_equal(AutosaveEvent::Finished { saved: 0, total: 0 }));
+
+        // One space changed.
+        let block = Block::from(Rgba::WHITE);
+        space_ref.borrow_mut().set([0, 0, 0], &block).unwrap();
+        autosave.step(&universe, tick);
+        autosave.step(&universe, tick);
+        assert!(sink.take_equal(AutosaveEvent::SavedSpace {
+            name: "a".into(),
+            done: 1,
+            total: 1,
+        }));
+        let data =
+            PrefabData::load(&fs::read_to_string(autosave.path_for(&"a".into())).unwrap()).unwrap();
+        assert_eq!(
+            data.into_prefab(&universe).unwrap().get([0, 0, 0]),
+            Some(&block)
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn write_atomically_replaces() {
+        let dir = temp_dir("atomic");
+        let path = dir.join("file");
+        write_atomically(&path, b"one").unwrap();
+        write_atomically(&path, b"two").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"two");
+        assert!(!dir.join("file.tmp").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
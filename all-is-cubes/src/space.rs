@@ -4,6 +4,7 @@
 //! That which contains many blocks.
 
 use cgmath::Vector3;
+use instant::Instant; // wasm-compatible replacement for std::time::Instant
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
@@ -15,27 +16,69 @@ use crate::behavior::BehaviorSet;
 use crate::block::*;
 use crate::character::Spawn;
 use crate::content::palette;
+#[cfg(feature = "content")]
 use crate::drawing::DrawingPlane;
+use crate::entity::Entities;
+use crate::item_drop::ItemDrops;
 use crate::listen::{Gate, Listener, ListenerHelper as _, Notifier};
 use crate::math::*;
+use crate::trace::trace_span;
 use crate::transactions::{Transaction as _, UniverseTransaction};
 use crate::universe::URef;
 use crate::util::ConciseDebug;
 use crate::util::{CustomFormat, StatusText};
 
+mod change_batch;
+use change_batch::{ChangeBatch, GranularListener};
+
+mod dirty_regions;
+pub use dirty_regions::*;
+
 mod grid;
 pub use grid::*;
 
+mod grid_mask;
+pub use grid_mask::*;
+
 mod lighting;
-pub use lighting::LightUpdatesInfo;
+pub use lighting::{LightParametersError, LightRayParameters, LightUpdatesInfo};
 
 mod light_data;
-pub use light_data::PackedLight;
-use light_data::{LightUpdateQueue, PackedLightScalar};
+use light_data::{LightDependencies, LightStorage, LightUpdateQueue, PackedLightScalar};
+pub use light_data::{LightPrecision, PackedLight, LIGHT_CHUNK_SIZE};
+
+mod mechanism;
+use mechanism::Mechanisms;
+pub use mechanism::{Mechanism, MechanismError, MechanismState};
+
+mod neighborhood;
+pub use neighborhood::Neighborhood;
+
+mod prefab;
+pub use prefab::*;
+
+mod random_tick;
+use random_tick::RandomTicks;
+
+mod region_edit;
+pub(crate) use region_edit::grid_between;
+pub use region_edit::{Collision, RegionEditError};
+
+mod signal;
+use signal::Signals;
+
+mod skybox;
+pub use skybox::*;
 
 mod space_txn;
 pub use space_txn::*;
 
+mod symmetry;
+pub use symmetry::Symmetry;
+
+mod topology;
+pub use topology::Topology;
+
 /// Container for [`Block`]s arranged in three-dimensional space. The main “game world”
 /// data structure.
 pub struct Space {
@@ -56,10 +99,12 @@ pub struct Space {
     // a cap on complex ones.
     contents: Box<[BlockIndex]>,
 
-    /// Parallel array to `contents` for lighting data.
-    pub(crate) lighting: Box<[PackedLight]>,
+    /// Light value of each cube, in chunks which track when they were changed.
+    pub(crate) lighting: LightStorage,
     /// Queue of cubes whose light values should be updated.
     light_update_queue: LightUpdateQueue,
+    /// Which cubes' light values were computed from which others.
+    light_dependencies: LightDependencies,
     /// Debug log of the updated cubes from last frame.
     /// Empty unless this debug function is enabled.
     pub(crate) last_light_updates: Vec<GridPoint>,
@@ -70,14 +115,44 @@ pub struct Space {
     /// A converted copy of `physics.sky_color`.
     packed_sky_color: PackedLight,
 
+    /// How [`Universe::step`](crate::universe::Universe::step) should step this space.
+    activity: SpaceActivity,
+
     // TODO: Replace this with something that has a spatial index so we can
     // search for behaviors in specific regions
     behaviors: BehaviorSet<Space>,
 
+    /// Signal levels and the sinks they operate.
+    signals: Signals,
+
+    /// Groups of cubes which change state together.
+    mechanisms: Mechanisms,
+
+    /// Source of randomness for random ticks and behaviors.
+    rng: SeededRng,
+
+    /// State of random ticks.
+    random_ticks: RandomTicks,
+
+    /// Items lying loose in the space.
+    item_drops: ItemDrops,
+
+    /// Non-block objects in the space.
+    entities: Entities,
+
     spawn: Spawn,
 
+    /// Whether to substitute [`EvaluatedBlock::placeholder`]s for blocks which fail to
+    /// evaluate, instead of refusing to store them.
+    /// See [`Space::set_placeholders_for_broken_blocks`].
+    placeholders_for_broken_blocks: bool,
+
     notifier: Notifier<SpaceChange>,
 
+    /// If a [`Space::batch_changes`] is in progress, the block changes not yet
+    /// notified.
+    change_batch: Option<ChangeBatch>,
+
     /// Storage for incoming change notifications from blocks.
     todo: Rc<RefCell<SpaceTodo>>,
 }
@@ -93,6 +168,9 @@ pub struct SpaceBlockData {
     /// Number of uses of this block in the space.
     count: usize,
     evaluated: EvaluatedBlock,
+    /// The light emitted by each face of the block, weighted by how much of the face
+    /// is covered by the block; see [`lighting::effective_emission`].
+    light_emission: FaceMap<Rgb>,
     #[allow(dead_code)] // Used only for its `Drop`
     block_listen_gate: Option<Gate>,
 }
@@ -151,14 +229,28 @@ impl Space {
                 vec![]
             },
             contents: vec![0; volume].into_boxed_slice(),
-            lighting: physics.light.initialize_lighting(grid, packed_sky_color),
+            lighting: LightStorage::new(
+                grid,
+                physics.light.initialize_lighting(packed_sky_color),
+                LightPrecision::default(),
+            ),
             light_update_queue: LightUpdateQueue::new(),
+            light_dependencies: LightDependencies::new(),
             last_light_updates: Vec::new(),
             physics,
             packed_sky_color,
+            activity: SpaceActivity::default(),
             behaviors: BehaviorSet::new(),
+            signals: Signals::default(),
+            mechanisms: Mechanisms::default(),
+            rng: SeededRng::new(0),
+            random_ticks: RandomTicks::default(),
+            item_drops: ItemDrops::default(),
+            entities: Entities::default(),
             spawn: Spawn::default_for_new_space(grid),
+            placeholders_for_broken_blocks: false,
             notifier: Notifier::new(),
+            change_batch: None,
             todo: Default::default(),
         }
     }
@@ -170,16 +262,72 @@ impl Space {
     }
 
     /// Registers a listener for mutations of this space.
+    ///
+    /// The listener receives a [`SpaceChange::Block`] for every changed cube, even if
+    /// the change was part of a [`Space::batch_changes`]; use [`Space::listen_batched`]
+    /// to receive fewer, coarser messages instead.
     pub fn listen(&self, listener: impl Listener<SpaceChange> + 'static) {
+        self.notifier.listen(GranularListener(listener))
+    }
+
+    /// Registers a listener for mutations of this space, which receives
+    /// [`SpaceChange::BlockRegion`] messages instead of individual
+    /// [`SpaceChange::Block`]s for changes made by [`Space::batch_changes`], as well as
+    /// by bulk operations such as [`Space::fill`].
+    ///
+    /// ```
+    /// use all_is_cubes::block::Block;
+    /// use all_is_cubes::listen::Sink;
+    /// use all_is_cubes::math::Rgba;
+    /// use all_is_cubes::space::{Grid, Space, SpaceChange};
+    ///
+    /// let mut space = Space::empty_positive(4, 4, 4);
+    /// let mut sink = Sink::new();
+    /// space.listen_batched(sink.listener());
+    ///
+    /// let region = Grid::new([0, 0, 0], [2, 2, 2]);
+    /// space.fill_uniform(region, Block::from(Rgba::WHITE)).unwrap();
+    /// assert!(sink.take_equal(SpaceChange::BlockRegion(region)));
+    /// assert!(!sink.any(|change| matches!(change, SpaceChange::Block(_))));
+    /// ```
+    pub fn listen_batched(&self, listener: impl Listener<SpaceChange> + 'static) {
         self.notifier.listen(listener)
     }
 
+    /// Performs `f`, deferring notification of the blocks it changes until it returns,
+    /// at which point the changes are reported as a few [`SpaceChange::BlockRegion`]s
+    /// to [batched listeners](Space::listen_batched) (and as the equivalent individual
+    /// [`SpaceChange::Block`]s to other listeners).
+    ///
+    /// Other kinds of [`SpaceChange`] are not deferred. If this is called during
+    /// another `batch_changes`, the changes become part of the outer batch.
+    pub fn batch_changes<R>(&mut self, f: impl FnOnce(&mut Space) -> R) -> R {
+        if self.change_batch.is_some() {
+            return f(self);
+        }
+        self.change_batch = Some(ChangeBatch::default());
+        let result = f(self);
+        if let Some(batch) = self.change_batch.take() {
+            for region in batch.into_regions() {
+                self.notifier.notify(SpaceChange::BlockRegion(region));
+            }
+        }
+        result
+    }
+
     /// Returns the [`Grid`] describing the bounds of this space; no blocks may exist
     /// outside it.
     pub fn grid(&self) -> Grid {
         self.grid
     }
 
+    /// Returns the cube within [`Space::grid`] which `cube` refers to, according to
+    /// the [`Topology`] of this space, or `cube` unchanged if it is out of bounds.
+    #[inline(always)]
+    pub(crate) fn wrap_cube(&self, cube: GridPoint) -> GridPoint {
+        self.physics.topology.wrap_cube(self.grid, cube)
+    }
+
     /// Returns the internal unstable numeric ID for the block at the given position,
     /// which may be mapped to a [`Block`] by [`Space::block_data`].
     /// If you are looking for *simple* access, use `space[position]` (the
@@ -190,7 +338,7 @@ impl Space {
     #[inline(always)]
     pub fn get_block_index(&self, position: impl Into<GridPoint>) -> Option<BlockIndex> {
         self.grid
-            .index(position.into())
+            .index(self.wrap_cube(position.into()))
             .map(|contents_index| self.contents[contents_index])
     }
 
@@ -198,7 +346,9 @@ impl Space {
     ///
     /// If the provided [`Grid`] contains portions outside of this space's grid,
     /// those positions in the output will be treated as if they are filled with [`AIR`]
-    /// and lit by [`SpacePhysics::sky_color`].
+    /// and lit by [`SpacePhysics::sky_color`], unless they are on a wrapped axis of
+    /// the [`SpacePhysics::topology`], in which case they are copies of the cubes
+    /// they wrap to.
     pub fn extract<V>(
         &self,
         subgrid: Grid,
@@ -207,16 +357,13 @@ impl Space {
         GridArray::from_fn(subgrid, |cube| {
             // TODO: Implement an iterator over the indexes (which is not just
             // interior_iter().enumerate() because it's a sub-grid).
-            match self.grid.index(cube) {
+            match self.grid.index(self.wrap_cube(cube)) {
                 Some(cube_index) => {
                     let block_index = self.contents[cube_index];
                     extractor(
                         Some(block_index),
                         &self.block_data[block_index as usize],
-                        match self.physics.light {
-                            LightPhysics::None => PackedLight::ONE,
-                            LightPhysics::Rays { .. } => self.lighting[cube_index],
-                        },
+                        self.get_lighting(cube),
                     )
                 }
                 None => extractor(None, &SpaceBlockData::NOTHING, self.packed_sky_color),
@@ -227,13 +374,30 @@ impl Space {
     /// Gets the [`EvaluatedBlock`] of the block in this space at the given position.
     #[inline(always)]
     pub fn get_evaluated(&self, position: impl Into<GridPoint>) -> &EvaluatedBlock {
-        if let Some(index) = self.grid.index(position) {
+        if let Some(index) = self.grid.index(self.wrap_cube(position.into())) {
             &self.block_data[self.contents[index] as usize].evaluated
         } else {
             &AIR_EVALUATED
         }
     }
 
+    /// Gets the [`SpaceBlockData`] of the block in this space at the given position,
+    /// or [`SpaceBlockData::NOTHING`] if it is out of bounds.
+    #[inline(always)]
+    pub(crate) fn get_block_data(&self, position: impl Into<GridPoint>) -> &SpaceBlockData {
+        if let Some(index) = self.grid.index(self.wrap_cube(position.into())) {
+            &self.block_data[self.contents[index] as usize]
+        } else {
+            &SpaceBlockData::NOTHING
+        }
+    }
+
+    /// Returns the blocks in and surrounding the given cube, for rules that depend on
+    /// neighboring blocks. See [`Neighborhood`] for details.
+    pub fn neighborhood(&self, center: impl Into<GridPoint>) -> Neighborhood<'_> {
+        Neighborhood::new(self, center.into())
+    }
+
     /// Returns the light occupying the given cube.
     ///
     /// This value may be considered as representing the average of the light reflecting
@@ -249,13 +413,45 @@ impl Space {
         match self.physics.light {
             LightPhysics::None => PackedLight::ONE,
             _ => self
-                .grid
-                .index(position.into())
-                .map(|contents_index| self.lighting[contents_index])
+                .lighting
+                .get(self.wrap_cube(position.into()))
                 .unwrap_or(self.packed_sky_color),
         }
     }
 
+    /// Returns a number which increases whenever any value returned by
+    /// [`Space::get_lighting`] changes (except for changes to
+    /// [`physics`](Self::physics)). Together with [`Space::light_changed_since`], this
+    /// allows keeping a copy of the light data up to date without copying all of it.
+    pub fn light_version(&self) -> u64 {
+        self.lighting.version()
+    }
+
+    /// Returns regions containing all the light values which have changed since
+    /// [`Space::light_version`] returned `version`.
+    ///
+    /// Each region is a chunk of [`LIGHT_CHUNK_SIZE`]³ cubes, aligned to multiples of
+    /// that size, and clipped to the bounds of the space.
+    ///
+    /// ```
+    /// use all_is_cubes::math::Rgba;
+    /// use all_is_cubes::space::{Grid, Space};
+    ///
+    /// let mut space = Space::empty_positive(40, 1, 1);
+    /// let version = space.light_version();
+    /// space.set([20, 0, 0], Rgba::WHITE).unwrap();
+    /// assert_eq!(
+    ///     space.light_changed_since(version).collect::<Vec<_>>(),
+    ///     vec![Grid::new([16, 0, 0], [16, 1, 1])],
+    /// );
+    /// ```
+    pub fn light_changed_since(&self, version: u64) -> impl Iterator<Item = Grid> + '_ {
+        let grid = self.grid;
+        self.lighting
+            .chunks_changed_since(version)
+            .filter_map(move |chunk| chunk.grid().intersection(grid))
+    }
+
     /// Replace the block in this space at the given position.
     ///
     /// If the position is out of bounds, there is no effect.
@@ -269,12 +465,48 @@ impl Space {
     /// space.set((0, 0, 0), &a_block);
     /// assert_eq!(space[(0, 0, 0)], a_block);
     /// ```
+    ///
+    /// If the new block or any of its neighbors has a [`Modifier::Connect`], their
+    /// connections are updated too, and if the change affects any
+    /// [signals](BlockAttributes::signal), their levels are recomputed.
     pub fn set<'a>(
         &mut self,
         position: impl Into<GridPoint>,
         block: impl Into<Cow<'a, Block>>,
     ) -> Result<bool, SetCubeError> {
         let position: GridPoint = position.into();
+        let changed = self.set_and_connect(position, block)?;
+        if changed {
+            self.signal_side_effects_of_set(position);
+        }
+        Ok(changed)
+    }
+
+    /// Implementation of [`Space::set`], not including updating signals.
+    fn set_and_connect<'a>(
+        &mut self,
+        position: GridPoint,
+        block: impl Into<Cow<'a, Block>>,
+    ) -> Result<bool, SetCubeError> {
+        let changed = self.set_without_connecting(position, block)?;
+        if changed {
+            for &face in Face::ALL_SEVEN {
+                let cube = position + face.normal_vector();
+                if let Some(block) = self.neighborhood(cube).reconnected_center() {
+                    self.set_without_connecting(cube, block)?;
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Implementation of [`Space::set`], not including updating connections or signals.
+    fn set_without_connecting<'a>(
+        &mut self,
+        position: GridPoint,
+        block: impl Into<Cow<'a, Block>>,
+    ) -> Result<bool, SetCubeError> {
+        let position: GridPoint = self.wrap_cube(position);
         let block: Cow<'a, Block> = block.into();
         if let Some(contents_index) = self.grid.index(position) {
             let old_block_index = self.contents[contents_index];
@@ -298,10 +530,8 @@ impl Space {
 
                 // Swap out the block_data entry.
                 let old_block = {
-                    let mut data = SpaceBlockData::new(
-                        block.clone().into_owned(),
-                        self.listener_for_block(old_block_index),
-                    )?;
+                    let mut data =
+                        self.new_block_data(block.clone().into_owned(), old_block_index)?;
                     data.count = 1;
                     std::mem::swap(&mut data, &mut self.block_data[old_block_index as usize]);
                     data.block
@@ -315,7 +545,7 @@ impl Space {
                 // Side effects.
                 self.notifier
                     .notify(SpaceChange::Number(old_block_index as BlockIndex));
-                self.side_effects_of_set(old_block_index, position, contents_index);
+                self.side_effects_of_set(old_block_index, position);
                 return Ok(true);
             }
 
@@ -337,7 +567,7 @@ impl Space {
             // Write actual space change.
             self.contents[contents_index] = new_block_index;
 
-            self.side_effects_of_set(new_block_index, position, contents_index);
+            self.side_effects_of_set(new_block_index, position);
             Ok(true)
         } else {
             Err(SetCubeError::OutOfBounds(Grid::single_cube(position)))
@@ -345,15 +575,20 @@ impl Space {
     }
 
     /// Implement the consequences of changing a block.
-    ///
-    /// `content_index` is redundant with `position` but saves computation.
     #[inline]
-    fn side_effects_of_set(
-        &mut self,
-        block_index: BlockIndex,
-        position: GridPoint,
-        contents_index: usize,
-    ) {
+    fn side_effects_of_set(&mut self, block_index: BlockIndex, position: GridPoint) {
+        self.light_side_effects_of_set(block_index, position);
+
+        match &mut self.change_batch {
+            Some(batch) => batch.add_cube(position),
+            None => self.notifier.notify(SpaceChange::Block(position)),
+        }
+    }
+
+    /// Schedule the lighting updates needed because the block at `position`, which
+    /// is now `block_index`, was replaced or changed its definition.
+    #[inline]
+    fn light_side_effects_of_set(&mut self, block_index: BlockIndex, position: GridPoint) {
         // TODO: Move this into a function in the lighting module since it is so tied to lighting
         if self.physics.light != LightPhysics::None {
             let opaque = self.block_data[block_index as usize].evaluated.opaque;
@@ -365,9 +600,12 @@ impl Space {
                 // (It would be mostly okay to skip doing this entirely, but doing it gives
                 // more determinism, and the old value could be temporarily revealed when
                 // the block is removed.)
-                self.lighting[contents_index] = PackedLight::OPAQUE;
+                self.lighting.set(position, PackedLight::OPAQUE);
+                self.light_dependencies.remove(position);
                 self.notifier.notify(SpaceChange::Lighting(position));
             }
+            // Cubes whose rays struck or passed through this cube's old block.
+            self.light_dependents_need_update(position, PackedLightScalar::MAX);
             for &face in Face::ALL_SIX {
                 let neighbor = position + face.normal_vector();
                 // Skip neighbor light updates in the definitely-black-inside case.
@@ -376,8 +614,6 @@ impl Space {
                 }
             }
         }
-
-        self.notifier.notify(SpaceChange::Block(position));
     }
 
     /// Replace blocks in `region` with a block computed by the function.
@@ -391,9 +627,7 @@ impl Space {
     /// made.)
     ///
     /// ```
-    /// use all_is_cubes::block::{AIR, Block};
-    /// use all_is_cubes::math::Rgba;
-    /// use all_is_cubes::space::{Grid, Space};
+    /// use all_is_cubes::prelude::*;
     ///
     /// let mut space = Space::empty_positive(10, 10, 10);
     /// let a_block: Block = Rgba::new(1.0, 0.0, 0.0, 1.0).into();
@@ -416,14 +650,16 @@ impl Space {
         if !self.grid().contains_grid(region) {
             return Err(SetCubeError::OutOfBounds(region));
         }
-        for cube in region.interior_iter() {
-            if let Some(block) = function(cube) {
-                // TODO: Optimize side effect processing by batching lighting updates for
-                // when we know what's now opaque or not.
-                self.set(cube, block.borrow())?;
+        self.batch_changes(|this| {
+            for cube in region.interior_iter() {
+                if let Some(block) = function(cube) {
+                    // TODO: Optimize side effect processing by batching lighting updates for
+                    // when we know what's now opaque or not.
+                    this.set(cube, block.borrow())?;
+                }
             }
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Replace blocks in `region` with the given block.
@@ -431,9 +667,7 @@ impl Space {
     /// TODO: Document error behavior
     ///
     /// ```
-    /// use all_is_cubes::block::{AIR, Block};
-    /// use all_is_cubes::math::Rgba;
-    /// use all_is_cubes::space::{Grid, Space};
+    /// use all_is_cubes::prelude::*;
     ///
     /// let mut space = Space::empty_positive(10, 10, 10);
     /// let a_block: Block = Rgba::new(1.0, 0.0, 0.0, 1.0).into();
@@ -451,16 +685,21 @@ impl Space {
         region: Grid,
         block: impl Into<Cow<'b, Block>>,
     ) -> Result<(), SetCubeError> {
+        let block = block.into();
         if !self.grid().contains_grid(region) {
             Err(SetCubeError::OutOfBounds(region))
-        } else if self.grid() == region {
+        } else if self.grid() == region
+            && with_connections(&block, FaceMap::repeat(false)).is_none()
+            && block
+                .evaluate()
+                .map_or(true, |e| e.attributes.signal == SignalRole::None)
+        {
             // We're overwriting the entire space, so we might as well re-initialize it.
-            let block = block.into();
+            // (Unless the block needs to connect to its neighbors or take part in
+            // signals, which `set` takes care of.)
             let new_block_index = 0;
-            let new_block_data = SpaceBlockData::new(
-                block.clone().into_owned(),
-                self.listener_for_block(new_block_index),
-            )?;
+            let new_block_data =
+                self.new_block_data(block.clone().into_owned(), new_block_index)?;
 
             self.block_to_index = {
                 let mut map = HashMap::new();
@@ -474,11 +713,14 @@ impl Space {
             for i in self.contents.iter_mut() {
                 *i = new_block_index;
             }
+            self.signals = Signals::default();
+            // Every mechanism's blocks are gone.
+            self.mechanisms = Mechanisms::default();
             self.notifier.notify(SpaceChange::EveryBlock);
             Ok(())
         } else {
             // Fall back to the generic strategy.
-            let block = block.into().into_owned();
+            let block = block.into_owned();
             self.fill(region, |_| Some(&block))
         }
     }
@@ -488,6 +730,7 @@ impl Space {
     ///
     /// For more information on how to use this, see
     /// [`all_is_cubes::drawing`](crate::drawing).
+    #[cfg(feature = "content")]
     pub fn draw_target<C>(&mut self, transform: GridMatrix) -> DrawingPlane<'_, C> {
         DrawingPlane::new(self, transform)
     }
@@ -514,40 +757,131 @@ impl Space {
         &self.block_data
     }
 
+    /// Returns all of the cubes in this space whose block has the given tag
+    /// (see [`BlockAttributes::tags`](crate::block::BlockAttributes::tags)),
+    /// in the order of [`Grid::interior_iter`].
+    pub fn find_cubes_with_tag<'a>(&'a self, tag: &str) -> impl Iterator<Item = GridPoint> + 'a {
+        let tagged: Vec<bool> = self
+            .block_data
+            .iter()
+            .map(|data| data.count > 0 && data.evaluated.attributes.has_tag(tag))
+            .collect();
+        self.grid.interior_iter().filter(move |&cube| {
+            let index = self.grid.index(cube).unwrap();
+            tagged[usize::from(self.contents[index])]
+        })
+    }
+
     /// Advance time in the space.
     pub fn step(
         &mut self,
         self_ref: Option<&URef<Space>>,
         tick: Tick,
     ) -> (SpaceStepInfo, UniverseTransaction) {
+        self.step_with_deadline(self_ref, tick, None)
+    }
+
+    /// As [`Space::step`], but lighting updates stop early if `deadline` passes.
+    pub(crate) fn step_with_deadline(
+        &mut self,
+        self_ref: Option<&URef<Space>>,
+        tick: Tick,
+        deadline: Option<Instant>,
+    ) -> (SpaceStepInfo, UniverseTransaction) {
+        let _span = trace_span!("space_step");
         // Process changed block definitions.
-        for block_index in self.todo.borrow_mut().blocks.drain() {
-            self.notifier.notify(SpaceChange::BlockValue(block_index));
+        let changed_blocks: Vec<BlockIndex> = self.todo.borrow_mut().blocks.drain().collect();
+        let mut relight_blocks: HashSet<BlockIndex> = HashSet::new();
+        for block_index in changed_blocks {
             let data: &mut SpaceBlockData = &mut self.block_data[usize::from(block_index)];
-            // TODO: handle error by switching to a "broken block" state.
-            // We may want to have a higher-level error handling by pausing the world
+            // TODO: We may want to have a higher-level error handling by pausing the world
             // and giving the user choices like reverting to save, editing to fix, or
             // continuing with a partly broken world.
-            data.evaluated = data.block.evaluate().expect("block reevaluation failed");
-            // TODO: Process side effects on individual cubes such as reevaluating the
-            // lighting influenced by the block.
+            let evaluated = data.block.evaluate().unwrap_or_else(|error| {
+                log::warn!("block reevaluation failed: {}", error);
+                error.to_placeholder()
+            });
+            if evaluated == data.evaluated {
+                // Nothing that depends on the evaluation needs to be redone, such as
+                // when the edit was to a part of a voxel space outside the block.
+                continue;
+            }
+            data.evaluated = evaluated;
+            data.light_emission = lighting::effective_emission(&data.evaluated);
+            if data.count > 0 {
+                relight_blocks.insert(block_index);
+            }
+            // Notify only after updating, so that listeners may read the new value.
+            self.notifier.notify(SpaceChange::BlockValue(block_index));
+        }
+        if !relight_blocks.is_empty() && self.physics.light != LightPhysics::None {
+            // Coarse, but definition edits are rare compared to placing blocks.
+            let affected_cubes: Vec<(BlockIndex, GridPoint)> = self
+                .grid
+                .interior_iter()
+                .filter_map(|cube| {
+                    let index = self.contents[self.grid.index(cube).unwrap()];
+                    relight_blocks.contains(&index).then(|| (index, cube))
+                })
+                .collect();
+            for (block_index, cube) in affected_cubes {
+                self.light_side_effects_of_set(block_index, cube);
+            }
+        }
+
+        let mut random_ticks = (0, 0);
+        if !tick.paused() {
+            self.update_signal_sinks();
+            self.update_mechanisms();
+            random_ticks = self.update_random_ticks(tick);
         }
 
         let mut transaction = UniverseTransaction::default();
         if let Some(self_ref) = self_ref {
             if !tick.paused() {
+                let mut rng = self.rng.fork();
                 transaction = self.behaviors.step(
                     &*self,
                     &(|t: SpaceTransaction| t.bind(self_ref.clone())),
                     SpaceTransaction::behaviors,
+                    &mut rng,
                     tick,
                 );
             }
         }
 
-        let light = self.update_lighting_from_queue();
+        // Drops need to collide with the space they are in, so take them out of it
+        // while they are stepped.
+        let mut item_drops = std::mem::take(&mut self.item_drops);
+        item_drops.step(tick, self);
+        self.item_drops = item_drops;
+
+        // Likewise for entities.
+        let mut entities = std::mem::take(&mut self.entities);
+        let mut entities_rng = self.rng.fork();
+        let entities_transaction = entities.step(self_ref, &mut entities_rng, tick, self);
+        self.entities = entities;
+        transaction = match transaction.clone().merge(entities_transaction) {
+            Ok(merged) => merged,
+            Err(_) => {
+                // The space's own behaviors take priority.
+                log::debug!("entity behaviors conflicted with space behaviors");
+                transaction
+            }
+        };
+
+        let light = self.update_lighting_from_queue(deadline);
 
-        (SpaceStepInfo { spaces: 1, light }, transaction)
+        let (random_ticks, random_tick_actions) = random_ticks;
+        (
+            SpaceStepInfo {
+                spaces: 1,
+                light,
+                random_ticks,
+                random_tick_actions,
+            },
+            transaction,
+        )
     }
 
     /// Perform lighting updates until there are none left to do. Returns the number of
@@ -566,7 +900,7 @@ impl Space {
     ) -> usize {
         let mut total = 0;
         loop {
-            let info = self.update_lighting_from_queue();
+            let info = self.update_lighting_from_queue(None);
 
             progress_callback(info);
 
@@ -590,23 +924,133 @@ impl Space {
         &self.physics
     }
 
+    /// Returns how [`Universe::step`](crate::universe::Universe::step) steps this space.
+    pub fn activity(&self) -> SpaceActivity {
+        self.activity
+    }
+
+    /// Sets how [`Universe::step`](crate::universe::Universe::step) steps this space,
+    /// such as to spend less time on spaces which are not currently visible.
+    /// This has no effect on calling [`Space::step`] directly.
+    pub fn set_activity(&mut self, activity: SpaceActivity) {
+        self.activity = activity;
+    }
+
+    /// Returns the random number generator used for this space's random ticks and by
+    /// its behaviors, for other uses which should be reproducible along with them,
+    /// such as generating contents of the space.
+    pub fn rng_mut(&mut self) -> &mut SeededRng {
+        &mut self.rng
+    }
+
+    /// Replaces this space's random number generator with one seeded by `seed`.
+    ///
+    /// Two spaces with the same contents, physics, behaviors, and seed, stepped with
+    /// the same [`Tick`]s, have the same random outcomes. Every space starts with a
+    /// seed of zero.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = SeededRng::new(seed);
+    }
+
+    /// Sets whether blocks which fail to evaluate (for example, because a
+    /// [`Block::Indirect`] refers to a definition that no longer exists) may still be
+    /// placed in this space, appearing as an [`EvaluatedBlock::placeholder`] describing
+    /// the error. This lets renderers and other users of [`Space::get_evaluated`] carry
+    /// on with the rest of the space instead of every caller of [`Space::set`] having to
+    /// handle the error.
+    ///
+    /// The default is `false`, in which case [`Space::set`] and similar operations
+    /// return [`SetCubeError::EvalBlock`]. Either way, blocks which *become* broken
+    /// after they were placed are replaced with placeholders.
+    ///
+    /// ```
+    /// use all_is_cubes::block::{Block, BlockDef};
+    /// use all_is_cubes::math::Rgba;
+    /// use all_is_cubes::space::Space;
+    /// use all_is_cubes::universe::{Universe, UniverseIndex as _};
+    ///
+    /// let mut universe = Universe::new();
+    /// let def = universe.insert_anonymous(BlockDef::new(Block::from(Rgba::WHITE)));
+    /// let broken = Block::Indirect(def.clone());
+    /// let _guard = def.borrow_mut(); // not available for evaluation
+    ///
+    /// let mut space = Space::empty_positive(1, 1, 1);
+    /// assert!(space.set((0, 0, 0), &broken).is_err());
+    /// space.set_placeholders_for_broken_blocks(true);
+    /// space.set((0, 0, 0), &broken).unwrap();
+    /// assert!(space.get_evaluated((0, 0, 0)).is_placeholder());
+    /// ```
+    pub fn set_placeholders_for_broken_blocks(&mut self, enabled: bool) {
+        self.placeholders_for_broken_blocks = enabled;
+    }
+
     /// Sets the physics parameters, as per [`physics`](Self::physics).
     ///
     /// This function does not currently cause any recomputation of cube lighting,
-    /// but \[TODO:\] it may later be improved to do so.
+    /// but \[TODO:\] it may later be improved to do so; the exception is that changing
+    /// the [`SpacePhysics::topology`] schedules every cube for a lighting update.
     pub fn set_physics(&mut self, physics: SpacePhysics) {
         self.packed_sky_color = physics.sky_color.into();
         if self.physics.light != physics.light {
             // TODO: comparison is too specific once there are parameters -- might be a minor change
-            self.lighting = physics
-                .light
-                .initialize_lighting(self.grid, self.packed_sky_color);
+            self.lighting
+                .reset(physics.light.initialize_lighting(self.packed_sky_color));
+            self.light_dependencies.clear();
             // TODO: Need to force updates potentially
         }
+        let topology_changed = self.physics.topology != physics.topology;
         self.physics = physics;
+        if topology_changed {
+            // Light can now travel across different edges.
+            for cube in self.grid.interior_iter() {
+                self.light_needs_update(cube, PackedLightScalar::MAX);
+            }
+            // TODO: Signals can too, but their levels are only recomputed when the
+            // blocks near the edges are next changed.
+        }
         // TODO: Also send out a SpaceChange notification, if anything is different.
     }
 
+    /// Returns the precision with which this space stores light values.
+    pub fn light_precision(&self) -> LightPrecision {
+        self.lighting.precision()
+    }
+
+    /// Sets the precision with which this space stores light values. The default is
+    /// [`LightPrecision::Standard`]; [`LightPrecision::High`] uses twice the memory to
+    /// avoid banding in dim or gradually fading light, and to allow brighter light.
+    ///
+    /// Light values already computed are converted, which is exact when reducing the
+    /// precision, but when increasing it they keep their lesser precision until they are
+    /// next recomputed; so, it is best to choose the precision before filling the space.
+    ///
+    /// ```
+    /// use all_is_cubes::space::{LightPrecision, Space};
+    ///
+    /// let mut space = Space::empty_positive(1, 1, 1);
+    /// assert_eq!(space.light_precision(), LightPrecision::Standard);
+    /// space.set_light_precision(LightPrecision::High);
+    /// assert_eq!(space.light_precision(), LightPrecision::High);
+    /// ```
+    pub fn set_light_precision(&mut self, precision: LightPrecision) {
+        if precision == self.lighting.precision() {
+            return;
+        }
+        self.lighting.set_precision(precision);
+        self.notifier
+            .notify(SpaceChange::LightingRegion(self.grid()));
+    }
+
+    /// Returns the items lying loose in this space.
+    pub fn item_drops(&self) -> &ItemDrops {
+        &self.item_drops
+    }
+
+    /// Returns the [`Entity`](crate::entity::Entity)s in this space.
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
     pub fn spawn(&self) -> &Spawn {
         &self.spawn
     }
@@ -628,10 +1072,8 @@ impl Space {
             let high_mark = self.block_data.len();
             for new_index in 0..high_mark {
                 if self.block_data[new_index].count == 0 {
-                    self.block_data[new_index] = SpaceBlockData::new(
-                        block.clone().into_owned(),
-                        self.listener_for_block(new_index as BlockIndex),
-                    )?;
+                    self.block_data[new_index] =
+                        self.new_block_data(block.clone().into_owned(), new_index as BlockIndex)?;
                     self.block_to_index
                         .insert(block.into_owned(), new_index as BlockIndex);
                     self.notifier
@@ -644,10 +1086,7 @@ impl Space {
             }
             let new_index = high_mark as BlockIndex;
             // Evaluate the new block type. Can fail, but we haven't done any mutation yet.
-            let new_data = SpaceBlockData::new(
-                block.clone().into_owned(),
-                self.listener_for_block(new_index),
-            )?;
+            let new_data = self.new_block_data(block.clone().into_owned(), new_index)?;
             // Grow the vector.
             self.block_data.push(new_data);
             self.block_to_index.insert(block.into_owned(), new_index);
@@ -656,6 +1095,19 @@ impl Space {
         }
     }
 
+    /// Evaluates a block which is about to be given the index `index`.
+    fn new_block_data(
+        &self,
+        block: Block,
+        index: BlockIndex,
+    ) -> Result<SpaceBlockData, SetCubeError> {
+        SpaceBlockData::new(
+            block,
+            self.listener_for_block(index),
+            self.placeholders_for_broken_blocks,
+        )
+    }
+
     fn listener_for_block(&self, index: BlockIndex) -> SpaceBlockChangeListener {
         SpaceBlockChangeListener {
             todo: Rc::downgrade(&self.todo),
@@ -744,7 +1196,7 @@ impl<T: Into<GridPoint>> std::ops::Index<T> for Space {
     /// use [`Space::set`] or [`Space::fill`] to modify blocks.
     #[inline(always)]
     fn index(&self, position: T) -> &Self::Output {
-        if let Some(index) = self.grid.index(position) {
+        if let Some(index) = self.grid.index(self.wrap_cube(position.into())) {
             &self.block_data[self.contents[index] as usize].block
         } else {
             &AIR
@@ -759,6 +1211,7 @@ impl SpaceBlockData {
         block: AIR,
         count: 0,
         evaluated: AIR_EVALUATED,
+        light_emission: NO_EMISSION,
         block_listen_gate: None,
     };
 
@@ -771,23 +1224,53 @@ impl SpaceBlockData {
             block: AIR,
             count: 0,
             evaluated: AIR_EVALUATED,
+            light_emission: NO_EMISSION,
+            block_listen_gate: None,
+        }
+    }
+
+    /// Data for a block which is not in any space's block table, such as the appearance
+    /// of an [`Entity`](crate::entity::Entity), so that renderers may draw it the same
+    /// way as the blocks of the space. Evaluation errors result in a placeholder, and
+    /// changes to the block are not tracked.
+    pub(crate) fn unlisted(block: Block) -> Self {
+        let evaluated = block
+            .evaluate()
+            .unwrap_or_else(|error| error.to_placeholder());
+        Self {
+            light_emission: lighting::effective_emission(&evaluated),
+            block,
+            count: 0,
+            evaluated,
             block_listen_gate: None,
         }
     }
 
+    /// If `use_placeholder` is true, then evaluation errors result in a placeholder
+    /// instead of failing.
     fn new(
         block: Block,
         listener: impl Listener<BlockChange> + 'static,
+        use_placeholder: bool,
     ) -> Result<Self, SetCubeError> {
         // TODO: double ref error check suggests that maybe evaluate() and listen() should be one combined operation.
-        let evaluated = block.evaluate().map_err(SetCubeError::EvalBlock)?;
+        let evaluated = match block.evaluate() {
+            Ok(evaluated) => evaluated,
+            Err(error) if use_placeholder => error.to_placeholder(),
+            Err(error) => return Err(SetCubeError::EvalBlock(error)),
+        };
         let (gate, block_listener) = listener.gate();
-        let _ = block
-            .listen(block_listener)
-            .map_err(SetCubeError::EvalBlock)?;
+        match block.listen(block_listener) {
+            Ok(()) => {}
+            // If we can't listen, then the placeholder will stay until the block is
+            // replaced, but that's no worse than refusing to store it.
+            Err(_) if use_placeholder => {}
+            Err(error) => return Err(SetCubeError::EvalBlock(error)),
+        }
         Ok(Self {
             block,
             count: 0,
+            light_emission: lighting::effective_emission(&evaluated),
             evaluated,
             block_listen_gate: Some(gate),
         })
@@ -808,8 +1291,38 @@ impl SpaceBlockData {
         &self.evaluated
     }
 
-    // TODO: Expose the count field? It is the most like an internal bookkeeping field,
-    // but might be interesting 'statistics'.
+    /// Returns the number of cubes in the [`Space`] which contain this block.
+    ///
+    /// This is zero if the block index is not currently in use; the other data about it
+    /// is then meaningless.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// How [`Universe::step`](crate::universe::Universe::step) steps a [`Space`], as set by
+/// [`Space::set_activity`]. Spaces which are not currently visible or interacted with
+/// can be stepped less often so that time is not wasted on them.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum SpaceActivity {
+    /// Stepped every time the universe is. This is the default.
+    Active,
+    /// Stepped only once every `divisor` times the universe is, with each step covering
+    /// the game time of all of them; behaviors and lighting therefore run less often
+    /// but keep pace with the rest of the universe. A divisor of 0 or 1 is equivalent
+    /// to [`SpaceActivity::Active`].
+    Background { divisor: u32 },
+    /// Stepped with a [paused](crate::apps::Tick::paused) tick, so that no game time
+    /// passes and behaviors do not act, but changes to block definitions and lighting
+    /// are still processed.
+    Paused,
+}
+
+impl Default for SpaceActivity {
+    fn default() -> Self {
+        Self::Active
+    }
 }
 
 /// The global characteristics of a [`Space`].
@@ -826,8 +1339,19 @@ pub struct SpacePhysics {
     /// and rendering.
     pub sky_color: Rgb,
 
+    /// Appearance of the outside of the space, if it should be something other than
+    /// a uniform [`sky_color`](Self::sky_color).
+    pub skybox: Option<Skybox>,
+
     /// Method used to compute the illumination of individual blocks.
     pub light: LightPhysics,
+
+    /// Which axes of the space wrap around, if any.
+    pub topology: Topology,
+
+    /// Average number of random ticks each cube receives per second; see
+    /// [`RandomTickAction`].
+    pub random_tick_rate: NotNan<f64>,
     // When adding a field, don't forget to expand the Debug impl.
 }
 
@@ -835,7 +1359,10 @@ impl SpacePhysics {
     pub const DEFAULT_FOR_BLOCK: Self = Self {
         gravity: Vector3::new(notnan!(0.), notnan!(0.), notnan!(0.)),
         sky_color: rgb_const!(0.5, 0.5, 0.5),
+        skybox: None,
         light: LightPhysics::None,
+        topology: Topology::BOUNDED,
+        random_tick_rate: notnan!(0.0),
     };
 }
 
@@ -850,7 +1377,10 @@ impl fmt::Debug for SpacePhysics {
                     .custom_format(ConciseDebug),
             )
             .field("sky_color", &self.sky_color)
+            .field("skybox", &self.skybox)
             .field("light", &self.light)
+            .field("topology", &self.topology)
+            .field("random_tick_rate", &self.random_tick_rate.into_inner())
             .finish()
     }
 }
@@ -860,34 +1390,28 @@ impl Default for SpacePhysics {
         Self {
             gravity: Vector3::new(notnan!(0.), notnan!(-20.), notnan!(0.)),
             sky_color: palette::DAY_SKY_COLOR,
+            skybox: None,
             light: LightPhysics::default(),
+            topology: Topology::BOUNDED,
+            random_tick_rate: notnan!(0.015625),
         }
     }
 }
 
 /// Method used to compute the illumination of individual blocks in a [`Space`].
 #[non_exhaustive]
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum LightPhysics {
     /// No light. All surface colors are taken exactly as displayed colors. The
     /// [`SpacePhysics::sky_color`] is used solely as a background color.
     None,
     /// Raycast-based light propagation and diffuse reflections.
-    ///
-    /// TODO: Need a to provide a builder or struct type so that this can be constructed.
-    #[non_exhaustive]
-    Rays {
-        /// The maximum distance a simulated light ray will travel; blocks farther than
-        /// that distance apart will never have direct influence on each other.
-        maximum_distance: u16,
-    },
+    Rays(LightRayParameters),
 }
 
 impl Default for LightPhysics {
     fn default() -> Self {
-        Self::Rays {
-            maximum_distance: 30,
-        }
+        Self::Rays(LightRayParameters::default())
     }
 }
 
@@ -917,12 +1441,20 @@ pub enum SpaceChange {
     Block(GridPoint),
     /// The light level value at the given location changed.
     Lighting(GridPoint),
+    /// Equivalent to [`SpaceChange::Lighting`] for every cube in the given region.
+    /// Only delivered to listeners registered with [`Space::listen_batched`].
+    LightingRegion(Grid),
     /// The given block index number was reassigned and now refers to a different
     /// [`Block`] value.
     Number(BlockIndex),
     /// The definition of the block referred to by the given block index number was
-    /// changed; the result of [`Space::get_evaluated`] may differ.
+    /// changed, and [`Space::get_evaluated`] now returns the new evaluation.
+    ///
+    /// This is delivered during [`Space::step`], and only if the evaluation differs.
     BlockValue(BlockIndex),
+    /// Equivalent to [`SpaceChange::Block`] for every cube in the given region.
+    /// Only delivered to listeners registered with [`Space::listen_batched`].
+    BlockRegion(Grid),
     /// Equivalent to [`SpaceChange::Block`] for every cube and [`SpaceChange::Number`]
     /// for every index.
     EveryBlock,
@@ -937,6 +1469,10 @@ pub struct SpaceStepInfo {
     /// Number of spaces whose updates were aggregated into this value.
     pub spaces: usize,
     pub light: LightUpdatesInfo,
+    /// Number of cubes given random ticks.
+    pub random_ticks: usize,
+    /// Number of random ticks which changed the space.
+    pub random_tick_actions: usize,
 }
 impl std::ops::AddAssign<SpaceStepInfo> for SpaceStepInfo {
     fn add_assign(&mut self, other: Self) {
@@ -946,6 +1482,8 @@ impl std::ops::AddAssign<SpaceStepInfo> for SpaceStepInfo {
         }
         self.spaces += other.spaces;
         self.light += other.light;
+        self.random_ticks += other.random_ticks;
+        self.random_tick_actions += other.random_tick_actions;
     }
 }
 impl CustomFormat<StatusText> for SpaceStepInfo {
@@ -953,6 +1491,11 @@ impl CustomFormat<StatusText> for SpaceStepInfo {
         write!(fmt, "{} spaces: ", self.spaces)?;
         if self.spaces > 0 {
             write!(fmt, "Relighting: {}", self.light.custom_format(StatusText))?;
+            write!(
+                fmt,
+                "; Random ticks: {:4} ({} acted)",
+                self.random_ticks, self.random_tick_actions
+            )?;
         }
         Ok(())
     }
@@ -990,13 +1533,38 @@ impl Listener<BlockChange> for SpaceBlockChangeListener {
 mod tests {
     use super::*;
     use crate::block::AIR;
-    use crate::content::make_some_blocks;
+    use crate::content::{make_some_blocks, make_some_voxel_blocks};
     use crate::listen::Sink;
     use crate::math::GridPoint;
-    use crate::universe::{RefError, Universe, UniverseIndex as _};
+    use crate::universe::{Name, RefError, Universe, UniverseIndex as _};
     use cgmath::EuclideanSpace as _;
     use std::rc::Rc;
 
+    #[test]
+    fn find_cubes_with_tag() {
+        let [plain] = make_some_blocks();
+        let stone = Block::builder()
+            .color(Rgba::WHITE)
+            .tag("stone")
+            .tag("natural")
+            .build();
+        let mut space = Space::empty_positive(3, 2, 1);
+        space.set([0, 0, 0], &stone).unwrap();
+        space.set([1, 0, 0], &plain).unwrap();
+        space.set([2, 1, 0], &stone).unwrap();
+
+        assert_eq!(
+            space.find_cubes_with_tag("stone").collect::<Vec<_>>(),
+            vec![GridPoint::new(0, 0, 0), GridPoint::new(2, 1, 0)]
+        );
+        assert_eq!(space.find_cubes_with_tag("wood").count(), 0);
+
+        // A block no longer present is not found even though it still has an index.
+        space.set([0, 0, 0], &plain).unwrap();
+        space.set([2, 1, 0], &plain).unwrap();
+        assert_eq!(space.find_cubes_with_tag("natural").count(), 0);
+    }
+
     // TODO: test consistency between the index and get_* methods
     // TODO: test fill() equivalence and error handling
 
@@ -1037,6 +1605,78 @@ mod tests {
         space.consistency_check(); // bonus testing
     }
 
+    #[test]
+    fn wrapping_topology_access() {
+        let [block] = make_some_blocks();
+        let mut space = Space::empty_positive(3, 3, 3);
+        space.set_physics(SpacePhysics {
+            topology: Topology::wrapping(true, false, true),
+            ..SpacePhysics::default()
+        });
+
+        assert_eq!(space.set([4, 1, -1], &block), Ok(true));
+        assert_eq!(space[[1, 1, 2]], block);
+        assert_eq!(space[[-2, 1, 5]], block);
+        assert_eq!(
+            space.get_block_index([-2, 1, 5]),
+            space.get_block_index([1, 1, 2])
+        );
+        assert_eq!(
+            space.get_evaluated([-2, 1, 5]),
+            space.get_evaluated([1, 1, 2])
+        );
+        assert_eq!(
+            space.get_lighting([-2, 1, 5]),
+            space.get_lighting([1, 1, 2])
+        );
+
+        // Y does not wrap.
+        let pt = GridPoint::new(1, 4, 2);
+        assert_eq!(
+            space.set(pt, &block),
+            Err(SetCubeError::OutOfBounds(Grid::single_cube(pt)))
+        );
+        assert_eq!(space[pt], AIR);
+
+        space.consistency_check(); // bonus testing
+    }
+
+    #[test]
+    fn set_connects_blocks() {
+        let mut universe = Universe::new();
+        let [base] = make_some_voxel_blocks(&mut universe);
+        let connect = |connected: FaceMap<bool>| {
+            base.clone().with_modifier(Modifier::Connect {
+                core: Grid::new([0, 0, 0], [1, 1, 1]),
+                connected,
+            })
+        };
+        let none = FaceMap::repeat(false);
+        let mut space = Space::empty_positive(3, 1, 1);
+
+        space.set([0, 0, 0], connect(none)).unwrap();
+        space.set([2, 0, 0], connect(none)).unwrap();
+        assert_eq!(space[[0, 0, 0]], connect(none));
+        space.set([1, 0, 0], connect(none)).unwrap();
+        assert_eq!(space[[0, 0, 0]], connect(FaceMap { px: true, ..none }));
+        assert_eq!(
+            space[[1, 0, 0]],
+            connect(FaceMap {
+                px: true,
+                nx: true,
+                ..none
+            })
+        );
+        assert_eq!(space[[2, 0, 0]], connect(FaceMap { nx: true, ..none }));
+
+        // Removing the middle block disconnects the others.
+        space.set([1, 0, 0], AIR).unwrap();
+        assert_eq!(space[[0, 0, 0]], connect(none));
+        assert_eq!(space[[2, 0, 0]], connect(none));
+
+        space.consistency_check(); // bonus testing
+    }
+
     /// This test case should also cover `RefError::Gone`.
     #[test]
     fn set_failure_borrow() {
@@ -1164,6 +1804,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn batched_and_granular_listeners() {
+        let [block] = make_some_blocks();
+        let mut space = Space::empty_positive(3, 3, 3);
+        let granular = Sink::new();
+        let batched = Sink::new();
+        space.listen(granular.listener());
+        space.listen_batched(batched.listener());
+
+        let region = Grid::new([0, 0, 0], [2, 2, 1]);
+        space
+            .batch_changes(|space| {
+                space.fill_uniform(region, &block)?;
+                space.set([2, 2, 2], &block)
+            })
+            .unwrap();
+
+        let is_block_change =
+            |c: &SpaceChange| matches!(c, SpaceChange::Block(_) | SpaceChange::BlockRegion(_));
+        let mut granular: Vec<SpaceChange> = granular.filter(is_block_change).collect();
+        granular.sort_by_key(|c| format!("{:?}", c));
+        let mut expected: Vec<SpaceChange> = region
+            .interior_iter()
+            .chain([GridPoint::new(2, 2, 2)].iter().copied())
+            .map(SpaceChange::Block)
+            .collect();
+        expected.sort_by_key(|c| format!("{:?}", c));
+        assert_eq!(granular, expected);
+
+        let mut batched: Vec<SpaceChange> = batched.filter(is_block_change).collect();
+        batched.sort_by_key(|c| format!("{:?}", c));
+        assert_eq!(
+            batched,
+            vec![
+                SpaceChange::BlockRegion(region),
+                SpaceChange::BlockRegion(Grid::single_cube(GridPoint::new(2, 2, 2))),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_light_precision_notifies_one_region() {
+        let mut space = Space::empty_positive(2, 2, 2);
+        let granular = Sink::new();
+        let batched = Sink::new();
+        space.listen(granular.listener());
+        space.listen_batched(batched.listener());
+
+        space.set_light_precision(LightPrecision::High);
+        assert_eq!(
+            batched.collect::<Vec<_>>(),
+            vec![SpaceChange::LightingRegion(space.grid())]
+        );
+        assert_eq!(granular.count(), space.grid().volume());
+    }
+
     #[test]
     fn change_listener() {
         let [block] = make_some_blocks();
@@ -1288,10 +1984,100 @@ mod tests {
         // Instead, it only happens the next time the space is stepped.
         let (_, _) = space.step(None, Tick::arbitrary());
         // Now we should see a notification and the evaluated block data having changed.
-        assert_eq!(sink.next(), Some(SpaceChange::BlockValue(0)));
+        assert!(sink.take_equal(SpaceChange::BlockValue(0)));
         assert_eq!(space.get_evaluated((0, 0, 0)), &new_evaluated);
     }
 
+    #[test]
+    fn indirect_becoming_cyclic_is_replaced_with_placeholder() {
+        let mut universe = Universe::new();
+        let block_def_ref = universe.insert_anonymous(BlockDef::new(Block::from(Rgba::WHITE)));
+        let indirect = Block::Indirect(block_def_ref.clone());
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set((0, 0, 0), &indirect).unwrap();
+
+        *(block_def_ref.borrow_mut().modify()) = indirect;
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(
+            space.get_evaluated((0, 0, 0)),
+            &EvalBlockError::Cycle(Name::Anonym(0)).to_placeholder()
+        );
+    }
+
+    #[test]
+    fn listens_to_voxel_space_edits_through_block_def() {
+        let mut universe = Universe::new();
+        let [a, b] = make_some_blocks();
+        let recursive = Block::builder()
+            .voxels_fn(&mut universe, 2, |_| &a)
+            .unwrap()
+            .build();
+        let voxel_space_ref = match &recursive {
+            Block::Recur { space, .. } => space.clone(),
+            _ => unreachable!(),
+        };
+        let block_def_ref = universe.insert_anonymous(BlockDef::new(recursive));
+
+        let mut space = Space::empty_positive(1, 1, 1);
+        space
+            .set((0, 0, 0), Block::Indirect(block_def_ref))
+            .unwrap();
+        let mut sink = Sink::new();
+        space.listen(sink.listener());
+
+        // Edit the voxels, as a block editor would.
+        voxel_space_ref.borrow_mut().set([1, 1, 1], &b).unwrap();
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert!(sink.take_equal(SpaceChange::BlockValue(0)));
+        // Anything else is the light update that follows from the new block.
+        assert!(sink.all(|change| matches!(change, SpaceChange::Lighting(_))));
+        assert_eq!(
+            space.get_evaluated((0, 0, 0)).voxels.as_ref().unwrap()[(1, 1, 1)].color,
+            b.evaluate().unwrap().color
+        );
+    }
+
+    #[test]
+    fn unchanged_block_value_is_not_notified() {
+        let mut universe = Universe::new();
+        let block = Block::from(Rgba::WHITE);
+        let block_def_ref = universe.insert_anonymous(BlockDef::new(block.clone()));
+        let mut space = Space::empty_positive(1, 1, 1);
+        space
+            .set((0, 0, 0), Block::Indirect(block_def_ref.clone()))
+            .unwrap();
+        let mut sink = Sink::new();
+        space.listen(sink.listener());
+
+        *(block_def_ref.borrow_mut().modify()) = block;
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(sink.next(), None);
+    }
+
+    #[test]
+    fn block_value_change_updates_light() {
+        let mut universe = Universe::new();
+        let block_def_ref = universe.insert_anonymous(BlockDef::new(Block::from(Rgba::WHITE)));
+        let mut space = Space::empty_positive(3, 3, 3);
+        space.set_physics(SpacePhysics {
+            sky_color: Rgb::ZERO,
+            ..Default::default()
+        });
+        space
+            .set([1, 1, 1], Block::Indirect(block_def_ref.clone()))
+            .unwrap();
+        space.evaluate_light(0, |_| ());
+        assert_eq!(space.get_lighting([2, 1, 1]).value(), Rgb::ZERO);
+
+        *(block_def_ref.borrow_mut().modify()) = Block::builder()
+            .color(Rgba::WHITE)
+            .light_emission(Rgb::ONE)
+            .build();
+        let (_, _) = space.step(None, Tick::arbitrary());
+        space.evaluate_light(0, |_| ());
+        assert_ne!(space.get_lighting([2, 1, 1]).value(), Rgb::ZERO);
+    }
+
     #[test]
     fn space_debug() {
         let mut space = Space::empty_positive(1, 1, 1);
@@ -1325,7 +2111,10 @@ mod tests {
             \x20   physics: SpacePhysics {\n\
             \x20       gravity: (+0.000, -20.000, +0.000),\n\
             \x20       sky_color: Rgb(0.79, 0.79, 1.0),\n\
+            \x20       skybox: None,\n\
             \x20       light: None,\n\
+            \x20       topology: Bounded,\n\
+            \x20       random_tick_rate: 0.015625,\n\
             \x20   },\n\
             \x20   behaviors: BehaviorSet([]),\n\
             \x20   ..\n\
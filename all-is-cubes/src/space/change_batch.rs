@@ -0,0 +1,115 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Coalescing of [`SpaceChange::Block`] notifications; see [`Space::batch_changes`].
+//!
+//! [`Space::batch_changes`]: super::Space::batch_changes
+
+use crate::listen::Listener;
+use crate::math::{GridCoordinate, GridPoint};
+use crate::space::{Grid, SpaceChange};
+
+/// Accumulates the cubes changed during a batch as a list of [`Grid`]s covering them.
+///
+/// Cubes changed in [`Grid::interior_iter`] order (or any other order which completes
+/// one row, then plane, at a time) are merged into a single region; other patterns
+/// produce more regions, but never more than one per cube.
+#[derive(Debug, Default)]
+pub(super) struct ChangeBatch {
+    regions: Vec<Grid>,
+}
+
+impl ChangeBatch {
+    pub fn add_cube(&mut self, cube: GridPoint) {
+        if matches!(self.regions.last(), Some(last) if last.contains_cube(cube)) {
+            return;
+        }
+        self.regions.push(Grid::single_cube(cube));
+
+        // Merge the newest region into the one before it for as long as the two exactly
+        // tile their bounding box.
+        while self.regions.len() >= 2 {
+            let b = self.regions[self.regions.len() - 1];
+            let a = self.regions[self.regions.len() - 2];
+            let union = bounding_union(a, b);
+            if union.volume() != a.volume() + b.volume() {
+                break;
+            }
+            self.regions.pop();
+            *self.regions.last_mut().unwrap() = union;
+        }
+    }
+
+    pub fn into_regions(self) -> Vec<Grid> {
+        self.regions
+    }
+}
+
+/// Returns the smallest [`Grid`] containing both `a` and `b`.
+fn bounding_union(a: Grid, b: Grid) -> Grid {
+    Grid::from_lower_upper(
+        a.lower_bounds().zip(b.lower_bounds(), GridCoordinate::min),
+        a.upper_bounds().zip(b.upper_bounds(), GridCoordinate::max),
+    )
+}
+
+/// Adapts a listener registered with [`Space::listen`](super::Space::listen) by
+/// expanding each [`SpaceChange::BlockRegion`] and [`SpaceChange::LightingRegion`] into
+/// the individual messages it stands for.
+pub(super) struct GranularListener<L>(pub L);
+
+impl<L: Listener<SpaceChange>> Listener<SpaceChange> for GranularListener<L> {
+    fn receive(&self, message: SpaceChange) {
+        match message {
+            SpaceChange::BlockRegion(region) => {
+                for cube in region.interior_iter() {
+                    self.0.receive(SpaceChange::Block(cube));
+                }
+            }
+            SpaceChange::LightingRegion(region) => {
+                for cube in region.interior_iter() {
+                    self.0.receive(SpaceChange::Lighting(cube));
+                }
+            }
+            message => self.0.receive(message),
+        }
+    }
+
+    fn alive(&self) -> bool {
+        self.0.alive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch_of(cubes: impl IntoIterator<Item = GridPoint>) -> Vec<Grid> {
+        let mut batch = ChangeBatch::default();
+        for cube in cubes {
+            batch.add_cube(cube);
+        }
+        batch.into_regions()
+    }
+
+    #[test]
+    fn whole_box_becomes_one_region() {
+        let grid = Grid::new([1, 2, 3], [4, 5, 6]);
+        assert_eq!(batch_of(grid.interior_iter()), vec![grid]);
+    }
+
+    #[test]
+    fn repeated_cube_is_not_duplicated() {
+        let cube = GridPoint::new(1, 1, 1);
+        assert_eq!(batch_of(vec![cube, cube]), vec![Grid::single_cube(cube)]);
+    }
+
+    #[test]
+    fn scattered_cubes_stay_separate() {
+        let cubes = vec![GridPoint::new(0, 0, 0), GridPoint::new(5, 0, 0)];
+        assert_eq!(
+            batch_of(cubes.clone()),
+            cubes.into_iter().map(Grid::single_cube).collect::<Vec<_>>()
+        );
+    }
+}
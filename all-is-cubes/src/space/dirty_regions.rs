@@ -0,0 +1,246 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`DirtyRegions`], for consumers of [`SpaceChange`]s that only need to know where to
+//! look.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::listen::Listener;
+use crate::math::{GridCoordinate, GridPoint};
+use crate::space::{Grid, SpaceChange};
+
+/// Accumulates the parts of a [`Space`](super::Space) which have changed, as a set of
+/// chunk-aligned [`Grid`]s, so that a renderer or other consumer can update only those
+/// parts each frame.
+///
+/// Register [`DirtyRegions::listener`] with
+/// [`Space::listen_batched`](super::Space::listen_batched) and call
+/// [`DirtyRegions::take`] when ready to process the changes.
+///
+/// ```
+/// use all_is_cubes::block::Block;
+/// use all_is_cubes::math::Rgba;
+/// use all_is_cubes::space::{DirtyRegions, Grid, Space};
+///
+/// let mut space = Space::empty_positive(16, 16, 16);
+/// let dirty = DirtyRegions::new(4);
+/// space.listen_batched(dirty.listener());
+///
+/// // Initially, everything needs to be processed.
+/// assert_eq!(dirty.take(space.grid()), vec![space.grid()]);
+/// assert_eq!(dirty.take(space.grid()), vec![]);
+///
+/// space.set([5, 6, 7], Block::from(Rgba::WHITE)).unwrap();
+/// assert_eq!(dirty.take(space.grid()), vec![Grid::new([4, 4, 4], [4, 4, 4])]);
+/// ```
+#[derive(Debug)]
+pub struct DirtyRegions {
+    state: Arc<Mutex<DirtyState>>,
+}
+
+#[derive(Debug)]
+struct DirtyState {
+    chunk_size: GridCoordinate,
+    include_lighting: bool,
+    everything: bool,
+    /// Positions of dirty chunks, in units of `chunk_size`.
+    chunks: HashSet<GridPoint>,
+}
+
+impl DirtyRegions {
+    /// Constructs a [`DirtyRegions`] which reports changes in units of cubical chunks
+    /// `chunk_size` cubes on a side. Initially, everything is dirty.
+    ///
+    /// Panics if `chunk_size` is not positive.
+    pub fn new(chunk_size: GridCoordinate) -> Self {
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        Self {
+            state: Arc::new(Mutex::new(DirtyState {
+                chunk_size,
+                include_lighting: false,
+                everything: true,
+                chunks: HashSet::new(),
+            })),
+        }
+    }
+
+    /// Also treat changes to light ([`SpaceChange::Lighting`]) as making a region dirty;
+    /// by default, only changes to blocks are.
+    pub fn including_lighting(self) -> Self {
+        self.state.lock().unwrap().include_lighting = true;
+        self
+    }
+
+    /// Returns a [`Listener`] which records changes in this [`DirtyRegions`].
+    pub fn listener(&self) -> impl Listener<SpaceChange> {
+        DirtyRegionsListener(Arc::downgrade(&self.state))
+    }
+
+    /// Marks `region` as dirty, as if a change there had been received.
+    pub fn mark(&self, region: Grid) {
+        self.state.lock().unwrap().mark(region);
+    }
+
+    /// Marks everything as dirty, as if [`SpaceChange::EveryBlock`] had been received.
+    pub fn mark_everything(&self) {
+        self.state.lock().unwrap().mark_everything();
+    }
+
+    /// Returns the dirty regions which intersect `bounds` (normally the
+    /// [`Space::grid`](super::Space::grid)), clipped to it, and marks everything clean.
+    ///
+    /// The regions do not overlap, and are sorted in an arbitrary but deterministic
+    /// order.
+    pub fn take(&self, bounds: Grid) -> Vec<Grid> {
+        let mut state = self.state.lock().unwrap();
+        let chunk_size = state.chunk_size;
+        let chunks = std::mem::take(&mut state.chunks);
+        if std::mem::replace(&mut state.everything, false) {
+            return if bounds.volume() > 0 {
+                vec![bounds]
+            } else {
+                vec![]
+            };
+        }
+
+        let mut chunks: Vec<GridPoint> = chunks.into_iter().collect();
+        chunks.sort_by_key(|p| (p.x, p.y, p.z));
+
+        // Merge runs of chunks along the Z axis.
+        let mut runs: Vec<(GridPoint, GridCoordinate)> = Vec::new();
+        for chunk in chunks {
+            match runs.last_mut() {
+                Some((start, length))
+                    if start.x == chunk.x && start.y == chunk.y && start.z + *length == chunk.z =>
+                {
+                    *length += 1;
+                }
+                _ => runs.push((chunk, 1)),
+            }
+        }
+
+        runs.into_iter()
+            .filter_map(|(start, length)| {
+                Grid::new(
+                    start * chunk_size,
+                    [chunk_size, chunk_size, chunk_size * length],
+                )
+                .intersection(bounds)
+            })
+            .collect()
+    }
+}
+
+impl DirtyState {
+    fn mark(&mut self, region: Grid) {
+        if self.everything || region.volume() == 0 {
+            return;
+        }
+        let size = self.chunk_size;
+        let lower = region.lower_bounds().map(|c| c.div_euclid(size));
+        let upper = region.upper_bounds().map(|c| (c - 1).div_euclid(size) + 1);
+        self.chunks
+            .extend(Grid::from_lower_upper(lower, upper).interior_iter());
+    }
+
+    fn mark_cube(&mut self, cube: GridPoint) {
+        if !self.everything {
+            let size = self.chunk_size;
+            self.chunks.insert(cube.map(|c| c.div_euclid(size)));
+        }
+    }
+
+    fn mark_everything(&mut self) {
+        self.everything = true;
+        self.chunks.clear();
+    }
+}
+
+struct DirtyRegionsListener(Weak<Mutex<DirtyState>>);
+
+impl Listener<SpaceChange> for DirtyRegionsListener {
+    fn receive(&self, message: SpaceChange) {
+        if let Some(cell) = self.0.upgrade() {
+            if let Ok(mut state) = cell.lock() {
+                match message {
+                    SpaceChange::Block(cube) => state.mark_cube(cube),
+                    SpaceChange::BlockRegion(region) => state.mark(region),
+                    SpaceChange::Lighting(cube) => {
+                        if state.include_lighting {
+                            state.mark_cube(cube);
+                        }
+                    }
+                    SpaceChange::LightingRegion(region) => {
+                        if state.include_lighting {
+                            state.mark(region);
+                        }
+                    }
+                    // Every cube whose block is affected by renumbering also gets a
+                    // SpaceChange::Block.
+                    SpaceChange::Number(_) => {}
+                    // We don't know which cubes have this block, so everything is dirty.
+                    SpaceChange::BlockValue(_) | SpaceChange::EveryBlock => state.mark_everything(),
+                }
+            }
+        }
+    }
+
+    fn alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(chunk_size: GridCoordinate) -> DirtyRegions {
+        let dirty = DirtyRegions::new(chunk_size);
+        dirty.take(Grid::new([0, 0, 0], [0, 0, 0]));
+        dirty
+    }
+
+    #[test]
+    fn region_is_chunk_aligned_and_merged() {
+        let dirty = clean(4);
+        dirty.mark(Grid::new([1, 1, 1], [1, 1, 6]));
+        assert_eq!(
+            dirty.take(Grid::new([-10, -10, -10], [20, 20, 20])),
+            vec![Grid::new([0, 0, 0], [4, 4, 8])]
+        );
+    }
+
+    #[test]
+    fn clipped_to_bounds() {
+        let dirty = clean(4);
+        dirty.mark(Grid::new([-1, 0, 0], [2, 1, 1]));
+        assert_eq!(
+            dirty.take(Grid::new([0, 0, 0], [3, 3, 3])),
+            vec![Grid::new([0, 0, 0], [3, 3, 3])]
+        );
+    }
+
+    #[test]
+    fn lighting_only_when_requested() {
+        let cube = GridPoint::new(0, 0, 0);
+        let bounds = Grid::new([0, 0, 0], [8, 8, 8]);
+
+        let dirty = clean(4);
+        dirty.listener().receive(SpaceChange::Lighting(cube));
+        assert_eq!(dirty.take(bounds), vec![]);
+
+        let dirty = clean(4).including_lighting();
+        dirty.listener().receive(SpaceChange::Lighting(cube));
+        assert_eq!(dirty.take(bounds), vec![Grid::new([0, 0, 0], [4, 4, 4])]);
+    }
+
+    #[test]
+    fn block_value_dirties_everything() {
+        let bounds = Grid::new([0, 0, 0], [8, 8, 8]);
+        let dirty = clean(4);
+        dirty.listener().receive(SpaceChange::BlockValue(0));
+        assert_eq!(dirty.take(bounds), vec![bounds]);
+    }
+}
@@ -0,0 +1,348 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`GridMask`], a set of cubes within a [`Grid`].
+
+use cgmath::Vector3;
+use std::fmt;
+
+use crate::math::{GridCoordinate, GridPoint};
+use crate::space::Grid;
+
+type Word = u64;
+const WORD_BITS: usize = 64;
+
+/// A set of cubes within a [`Grid`], such as a selection or the cubes reached by a
+/// flood fill, stored as one bit per cube of the grid.
+///
+/// This is much more compact than a `HashSet<GridPoint>` when the set is a
+/// substantial fraction of the grid, but costs memory in proportion to the grid's
+/// volume however few cubes are in the set, so it is best used with grids no larger
+/// than necessary.
+///
+/// ```
+/// use all_is_cubes::math::GridPoint;
+/// use all_is_cubes::space::{Grid, GridMask};
+///
+/// let mut mask = GridMask::new(Grid::new([0, 0, 0], [4, 4, 4]));
+/// assert!(mask.insert(GridPoint::new(1, 2, 3)));
+/// assert!(!mask.insert(GridPoint::new(1, 2, 3)));
+/// assert!(mask.contains(GridPoint::new(1, 2, 3)));
+/// assert!(!mask.contains(GridPoint::new(3, 2, 1)));
+/// assert_eq!(mask.iter().collect::<Vec<_>>(), vec![GridPoint::new(1, 2, 3)]);
+/// ```
+#[derive(Clone, Eq, Hash, PartialEq)]
+pub struct GridMask {
+    grid: Grid,
+    /// Bits in the order of [`Grid::index`]. Bits past the volume of the grid are
+    /// always zero.
+    bits: Box<[Word]>,
+}
+
+impl GridMask {
+    /// Constructs an empty mask which may contain cubes within `grid`.
+    pub fn new(grid: Grid) -> Self {
+        Self {
+            grid,
+            bits: vec![0; word_count(grid)].into_boxed_slice(),
+        }
+    }
+
+    /// Constructs a mask containing every cube of `grid`.
+    pub fn full(grid: Grid) -> Self {
+        let mut mask = Self {
+            grid,
+            bits: vec![Word::MAX; word_count(grid)].into_boxed_slice(),
+        };
+        mask.clear_unused_bits();
+        mask
+    }
+
+    /// Constructs a mask containing the cubes of `grid` for which `f` returns true.
+    pub fn from_fn(grid: Grid, mut f: impl FnMut(GridPoint) -> bool) -> Self {
+        let mut mask = Self::new(grid);
+        for (index, cube) in grid.interior_iter().enumerate() {
+            if f(cube) {
+                let (word, bit) = bit_position(index);
+                mask.bits[word] |= bit;
+            }
+        }
+        mask
+    }
+
+    /// Returns the [`Grid`] which all cubes in this mask are within.
+    pub fn grid(&self) -> Grid {
+        self.grid
+    }
+
+    /// Returns whether `cube` is in this mask. Cubes outside the [`Self::grid`] never
+    /// are.
+    #[inline]
+    pub fn contains(&self, cube: GridPoint) -> bool {
+        match self.grid.index(cube) {
+            Some(index) => {
+                let (word, bit) = bit_position(index);
+                self.bits[word] & bit != 0
+            }
+            None => false,
+        }
+    }
+
+    /// Adds `cube` to this mask. Returns whether it was not already present.
+    ///
+    /// Panics if `cube` is outside the [`Self::grid`].
+    #[inline]
+    #[track_caller]
+    pub fn insert(&mut self, cube: GridPoint) -> bool {
+        let index = match self.grid.index(cube) {
+            Some(index) => index,
+            None => panic!("GridMask cube out of range {:?} in {:?}", cube, self.grid),
+        };
+        let (word, bit) = bit_position(index);
+        let word = &mut self.bits[word];
+        let added = *word & bit == 0;
+        *word |= bit;
+        added
+    }
+
+    /// Removes `cube` from this mask. Returns whether it was present.
+    #[inline]
+    pub fn remove(&mut self, cube: GridPoint) -> bool {
+        match self.grid.index(cube) {
+            Some(index) => {
+                let (word, bit) = bit_position(index);
+                let word = &mut self.bits[word];
+                let removed = *word & bit != 0;
+                *word &= !bit;
+                removed
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the number of cubes in this mask.
+    pub fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns whether this mask contains no cubes.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&word| word == 0)
+    }
+
+    /// Removes all cubes from this mask.
+    pub fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+    }
+
+    /// Adds every cube of `other` to this mask, except for those outside of
+    /// [`Self::grid`], which are ignored.
+    pub fn union_with(&mut self, other: &GridMask) {
+        if self.grid == other.grid {
+            for (word, &other_word) in self.bits.iter_mut().zip(other.bits.iter()) {
+                *word |= other_word;
+            }
+        } else {
+            for cube in other.iter() {
+                if self.grid.contains_cube(cube) {
+                    self.insert(cube);
+                }
+            }
+        }
+    }
+
+    /// Removes every cube of this mask which is not in `other`.
+    pub fn intersect_with(&mut self, other: &GridMask) {
+        if self.grid == other.grid {
+            for (word, &other_word) in self.bits.iter_mut().zip(other.bits.iter()) {
+                *word &= other_word;
+            }
+        } else {
+            for (index, cube) in self.grid.interior_iter().enumerate() {
+                if !other.contains(cube) {
+                    let (word, bit) = bit_position(index);
+                    self.bits[word] &= !bit;
+                }
+            }
+        }
+    }
+
+    /// Removes every cube of this mask which is in `other`.
+    pub fn subtract(&mut self, other: &GridMask) {
+        if self.grid == other.grid {
+            for (word, &other_word) in self.bits.iter_mut().zip(other.bits.iter()) {
+                *word &= !other_word;
+            }
+        } else {
+            for cube in other.iter() {
+                self.remove(cube);
+            }
+        }
+    }
+
+    /// Iterates over the cubes in this mask, in the same order as
+    /// [`Grid::interior_iter`].
+    pub fn iter(&self) -> impl Iterator<Item = GridPoint> + '_ {
+        let grid = self.grid;
+        let size = grid.size();
+        // Sizes are nonnegative and the volume fits in usize.
+        let (size_y, size_z) = (size.y as usize, size.z as usize);
+        self.bits
+            .iter()
+            .enumerate()
+            .flat_map(move |(word_index, &word)| {
+                let mut word = word;
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1; // clear lowest set bit
+                    Some(word_index * WORD_BITS + bit)
+                })
+            })
+            .map(move |index| {
+                // Inverse of Grid::index().
+                let offset = Vector3::new(
+                    index / (size_y * size_z),
+                    index / size_z % size_y,
+                    index % size_z,
+                );
+                grid.lower_bounds() + offset.map(|c| c as GridCoordinate)
+            })
+    }
+
+    fn clear_unused_bits(&mut self) {
+        let volume = self.grid.volume();
+        if volume % WORD_BITS != 0 {
+            if let Some(last) = self.bits.last_mut() {
+                // The bit just past the end, minus one, is all the bits before it.
+                *last &= bit_position(volume).1 - 1;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for GridMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GridMask")
+            .field("grid", &self.grid)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+fn word_count(grid: Grid) -> usize {
+    (grid.volume() + WORD_BITS - 1) / WORD_BITS
+}
+
+/// Returns the index of the word containing the bit for the cube with the given
+/// [`Grid::index`], and the mask selecting that bit.
+#[inline]
+fn bit_position(index: usize) -> (usize, Word) {
+    let one: Word = 1;
+    (index / WORD_BITS, one << (index % WORD_BITS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: i32, y: i32, z: i32) -> GridPoint {
+        GridPoint::new(x, y, z)
+    }
+
+    #[test]
+    fn insert_remove_contains() {
+        let grid = Grid::new([-1, 0, 5], [3, 4, 5]);
+        let mut mask = GridMask::new(grid);
+        assert!(mask.is_empty());
+        assert!(mask.insert(point(-1, 3, 9)));
+        assert!(mask.insert(point(1, 0, 5)));
+        assert!(!mask.insert(point(1, 0, 5)));
+        assert_eq!(mask.len(), 2);
+        assert!(mask.contains(point(-1, 3, 9)));
+        assert!(!mask.contains(point(0, 0, 5)));
+        assert!(!mask.contains(point(100, 0, 0)));
+
+        assert!(mask.remove(point(-1, 3, 9)));
+        assert!(!mask.remove(point(-1, 3, 9)));
+        assert!(!mask.remove(point(100, 0, 0)));
+        assert_eq!(mask.iter().collect::<Vec<_>>(), vec![point(1, 0, 5)]);
+
+        mask.clear();
+        assert!(mask.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "GridMask cube out of range")]
+    fn insert_out_of_bounds() {
+        GridMask::new(Grid::new([0, 0, 0], [1, 1, 1])).insert(point(1, 0, 0));
+    }
+
+    #[test]
+    fn full_and_iter_order() {
+        // Volume 70 is not a multiple of the word size.
+        let grid = Grid::new([1, 2, 3], [2, 5, 7]);
+        let mask = GridMask::full(grid);
+        assert_eq!(mask.len(), grid.volume());
+        assert_eq!(
+            mask.iter().collect::<Vec<_>>(),
+            grid.interior_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(mask, GridMask::from_fn(grid, |_| true));
+    }
+
+    #[test]
+    fn empty_grid() {
+        let grid = Grid::new([0, 0, 0], [4, 0, 4]);
+        assert!(GridMask::full(grid).is_empty());
+        assert_eq!(GridMask::full(grid).iter().count(), 0);
+    }
+
+    #[test]
+    fn set_operations_same_grid() {
+        let grid = Grid::new([0, 0, 0], [10, 1, 1]);
+        let evens = GridMask::from_fn(grid, |p| p.x % 2 == 0);
+        let low = GridMask::from_fn(grid, |p| p.x < 5);
+
+        let mut union = evens.clone();
+        union.union_with(&low);
+        assert_eq!(union, GridMask::from_fn(grid, |p| p.x % 2 == 0 || p.x < 5));
+
+        let mut intersection = evens.clone();
+        intersection.intersect_with(&low);
+        assert_eq!(
+            intersection,
+            GridMask::from_fn(grid, |p| p.x % 2 == 0 && p.x < 5)
+        );
+
+        let mut difference = evens;
+        difference.subtract(&low);
+        assert_eq!(
+            difference,
+            GridMask::from_fn(grid, |p| p.x % 2 == 0 && p.x >= 5)
+        );
+    }
+
+    #[test]
+    fn set_operations_different_grids() {
+        let grid = Grid::new([0, 0, 0], [10, 1, 1]);
+        let other = GridMask::full(Grid::new([5, 0, 0], [10, 1, 1]));
+
+        let mut union = GridMask::from_fn(grid, |p| p.x == 0);
+        union.union_with(&other);
+        assert_eq!(union, GridMask::from_fn(grid, |p| p.x == 0 || p.x >= 5));
+
+        let mut intersection = GridMask::full(grid);
+        intersection.intersect_with(&other);
+        assert_eq!(intersection, GridMask::from_fn(grid, |p| p.x >= 5));
+
+        let mut difference = GridMask::full(grid);
+        difference.subtract(&other);
+        assert_eq!(difference, GridMask::from_fn(grid, |p| p.x < 5));
+    }
+}
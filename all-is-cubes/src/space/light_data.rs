@@ -5,16 +5,59 @@
 
 use std::collections::hash_map::Entry;
 use std::collections::BTreeSet;
+use std::convert::TryFrom;
 use std::fmt;
 
 use cgmath::{EuclideanSpace as _, Vector3, Vector4};
 
+use crate::chunking::{cube_to_chunk, ChunkPos};
 use crate::math::*;
 use crate::space::*;
 
-/// One component of a `PackedLight`.
+/// One component of a `PackedLight` at [`LightPrecision::Standard`], as in
+/// [`PackedLight::as_texel`]. Light update priorities are also in these units.
 pub(crate) type PackedLightScalar = u8;
 
+/// One component of a `PackedLight` as it is held in the `PackedLight`, which has the
+/// precision of [`LightPrecision::High`].
+type HighLightScalar = u16;
+
+/// How precisely a [`Space`] stores its light values, trading memory for quality; see
+/// [`Space::set_light_precision`].
+///
+/// Both precisions store light logarithmically, so steps between adjacent values are
+/// a constant ratio rather than a constant difference.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum LightPrecision {
+    /// 8 bits per color component, for 4 bytes per cube. Components range from about
+    /// 0.004 to 245, in steps of about 4.4%, which can show as banding where light
+    /// fades gradually.
+    Standard,
+    /// 16 bits per color component, for 8 bytes per cube. Components range from about
+    /// 0.00002 to 65000, in steps of about 0.03%.
+    ///
+    /// Only the raytracer displays light at this precision; the GPU renderer
+    /// (`all_is_cubes::lum`) reduces it to `Standard` when copying it to the GPU.
+    High,
+}
+
+impl LightPrecision {
+    /// Returns the number of bytes of light data stored for each cube of a [`Space`].
+    pub const fn bytes_per_cube(self) -> usize {
+        match self {
+            LightPrecision::Standard => 4,
+            LightPrecision::High => 8,
+        }
+    }
+}
+
+impl Default for LightPrecision {
+    fn default() -> Self {
+        LightPrecision::Standard
+    }
+}
+
 /// Special reasons for a cube having zero light in it.
 /// These may be used to help compute smoothed lighting across blocks.
 ///
@@ -36,14 +79,18 @@ pub(crate) enum LightStatus {
 
 /// Lighting within a [`Space`]; an [`Rgb`] value stored with reduced precision and range.
 ///
+/// A `PackedLight` itself has the precision of [`LightPrecision::High`]; a [`Space`]
+/// using [`LightPrecision::Standard`] stores only values which are unchanged by
+/// [`PackedLight::quantize`] to that precision.
+///
 /// TODO: This now stores additional information. Rename to 'SpaceLight' or some such.
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct PackedLight {
     // LightStatus being other than Visible is mutually exclusive with value being nonzero,
     // so we could in theory make this an enum, but that wouldn't actually compact the
-    // representation, and this representation maps to 8-bit-per-component RGBA which is
-    // what the shader expects.
-    value: Vector3<PackedLightScalar>,
+    // representation. The 8-bit-per-component RGBA form which the shader expects is
+    // produced by `as_texel()`.
+    value: Vector3<HighLightScalar>,
     status: LightStatus,
 }
 // TODO: Once we've built out the rest of the game, do some performance testing and
@@ -52,17 +99,30 @@ pub struct PackedLight {
 // representation, or possibly something that GPUs specifically do well with.
 
 impl PackedLight {
+    /// Parameters of the logarithmic encoding of [`PackedLightScalar`]: a value `s` other
+    /// than zero represents 2<sup>(`s` − `LOG_OFFSET`) / `LOG_SCALE`</sup>.
+    /// The shaders also use these numbers.
     const LOG_SCALE: f32 = 16.0;
     const LOG_OFFSET: f32 = 128.0;
+    /// Parameters of the logarithmic encoding of [`HighLightScalar`], chosen so that
+    /// each [`PackedLightScalar`] step is exactly [`Self::HIGH_STEPS`] steps.
+    const HIGH_LOG_SCALE: f32 = Self::LOG_SCALE * Self::HIGH_STEPS as f32;
+    const HIGH_LOG_OFFSET: f32 =
+        Self::LOG_OFFSET * Self::HIGH_STEPS as f32 + Self::HIGH_BASE as f32;
+    /// The ratio of [`Self::HIGH_LOG_SCALE`] to [`Self::LOG_SCALE`].
+    const HIGH_STEPS: HighLightScalar = 128;
+    /// The [`HighLightScalar`] corresponding to the [`PackedLightScalar`] 0 (if it were
+    /// not a special case).
+    const HIGH_BASE: HighLightScalar = 16384;
 
     pub(crate) const OPAQUE: Self = Self::none(LightStatus::Opaque);
     pub(crate) const NO_RAYS: Self = Self::none(LightStatus::NoRays);
     pub(crate) const ONE: PackedLight = PackedLight {
         status: LightStatus::Visible,
         value: Vector3 {
-            x: Self::LOG_OFFSET as PackedLightScalar,
-            y: Self::LOG_OFFSET as PackedLightScalar,
-            z: Self::LOG_OFFSET as PackedLightScalar,
+            x: Self::HIGH_LOG_OFFSET as HighLightScalar,
+            y: Self::HIGH_LOG_OFFSET as HighLightScalar,
+            z: Self::HIGH_LOG_OFFSET as HighLightScalar,
         },
     };
 
@@ -93,6 +153,38 @@ impl PackedLight {
         )
     }
 
+    /// Returns the largest light component value which can be stored at any
+    /// [`LightPrecision`]; greater values are stored as this value. See
+    /// [light units](Rgb#light-units).
+    pub(crate) fn max_value() -> f32 {
+        Self::scalar_out(HighLightScalar::MAX)
+    }
+
+    /// Reduces this value to the given precision, as a [`Space`] with that
+    /// [`LightPrecision`] would store it.
+    ///
+    /// ```
+    /// use all_is_cubes::math::Rgb;
+    /// use all_is_cubes::space::{LightPrecision, PackedLight};
+    ///
+    /// let light = PackedLight::from(Rgb::new(1.01, 1.0, 1.0));
+    /// assert_ne!(light.value().red(), light.value().green());
+    /// let standard = light.quantize(LightPrecision::Standard);
+    /// assert_eq!(standard.value().red(), standard.value().green());
+    /// ```
+    #[must_use]
+    pub fn quantize(self, precision: LightPrecision) -> Self {
+        match precision {
+            LightPrecision::Standard => Self {
+                value: self
+                    .value
+                    .map(|h| Self::standard_to_high(Self::high_to_standard(h))),
+                status: self.status,
+            },
+            LightPrecision::High => self,
+        }
+    }
+
     // TODO: Expose LightStatus once we are more confident in its API stability
 
     /// Returns true if the light value is meaningful, or false if it is
@@ -102,6 +194,13 @@ impl PackedLight {
         self.status == LightStatus::Visible
     }
 
+    /// Returns false if this value could not have been produced by light computation:
+    /// one which is not [`Self::valid`] must have a zero value. Such values might come
+    /// from [`Self::from_texel`].
+    pub(crate) fn is_consistent(&self) -> bool {
+        self.valid() || self.value == Vector3::new(0, 0, 0)
+    }
+
     /// RGB color plus a fourth component which is a “weight” value which indicates how
     /// much this color should actually contribute to the surface color. It is usually
     /// 0 or 1, but is set slightly above zero for opaque blocks to create the ambient
@@ -121,13 +220,36 @@ impl PackedLight {
         )
     }
 
+    /// Converts this value to the form used by the shaders and in saved data, which has
+    /// [`LightPrecision::Standard`].
     #[inline]
     pub(crate) fn as_texel(self) -> [u8; 4] {
         let Self {
             value: Vector3 { x, y, z },
             status,
         } = self;
-        [x, y, z, status as u8]
+        [
+            Self::high_to_standard(x),
+            Self::high_to_standard(y),
+            Self::high_to_standard(z),
+            status as u8,
+        ]
+    }
+
+    /// Inverse of [`Self::as_texel`]. Returns [`None`] if the fourth component is not
+    /// a valid status value.
+    pub(crate) fn from_texel([x, y, z, status]: [u8; 4]) -> Option<Self> {
+        let status = match status {
+            0 => LightStatus::Uninitialized,
+            1 => LightStatus::NoRays,
+            128 => LightStatus::Opaque,
+            255 => LightStatus::Visible,
+            _ => return None,
+        };
+        Some(PackedLight {
+            value: Vector3::new(x, y, z).map(Self::standard_to_high),
+            status,
+        })
     }
 
     /// Computes a degree of difference between two [`PackedLight`] values, used to decide
@@ -135,12 +257,18 @@ impl PackedLight {
     /// The value is zero if and only if the two inputs are equal.
     #[inline]
     pub(crate) fn difference_priority(self, other: PackedLight) -> PackedLightScalar {
-        fn abs_diff(a: PackedLightScalar, b: PackedLightScalar) -> PackedLightScalar {
-            a.max(b) - a.min(b)
+        fn abs_diff(a: HighLightScalar, b: HighLightScalar) -> u32 {
+            u32::from(a.max(b) - a.min(b))
         }
-        let mut difference = abs_diff(self.value[0], other.value[0])
+        let high_difference = abs_diff(self.value[0], other.value[0])
             .max(abs_diff(self.value[1], other.value[1]))
             .max(abs_diff(self.value[2], other.value[2]));
+        // Measure in standard steps, so that priorities mean the same thing at any
+        // precision, but round up so that a difference is never counted as none.
+        let standard_difference =
+            (high_difference + u32::from(Self::HIGH_STEPS) - 1) / u32::from(Self::HIGH_STEPS);
+        let mut difference =
+            PackedLightScalar::try_from(standard_difference).unwrap_or(PackedLightScalar::MAX);
 
         if other.status != self.status {
             // A non-opaque block changing to an opaque one, or similar, changes the
@@ -153,28 +281,46 @@ impl PackedLight {
         difference
     }
 
-    fn scalar_in(value: impl Into<f32>) -> PackedLightScalar {
+    fn scalar_in(value: impl Into<f32>) -> HighLightScalar {
         // Note that `as` is a saturating cast.
-        (value.into().log2() * Self::LOG_SCALE + Self::LOG_OFFSET) as PackedLightScalar
+        (value.into().log2() * Self::HIGH_LOG_SCALE + Self::HIGH_LOG_OFFSET) as HighLightScalar
     }
 
-    /// Convert a `PackedLightScalar` value to a linear color component value.
+    /// Convert a `HighLightScalar` value to a linear color component value.
     /// This function is guaranteed (and tested) to only return finite floats.
-    fn scalar_out(value: PackedLightScalar) -> f32 {
+    fn scalar_out(value: HighLightScalar) -> f32 {
         // Special representation to ensure we don't "round" zero up to a small nonzero value.
         if value == 0 {
             0.0
         } else {
-            ((f32::from(value) - Self::LOG_OFFSET) / Self::LOG_SCALE).exp2()
+            ((f32::from(value) - Self::HIGH_LOG_OFFSET) / Self::HIGH_LOG_SCALE).exp2()
         }
     }
 
-    fn scalar_out_nn(value: PackedLightScalar) -> NotNan<f32> {
+    fn scalar_out_nn(value: HighLightScalar) -> NotNan<f32> {
         unsafe {
             // Safety: a test verifies that `scalar_out` can never return NaN.
             NotNan::new_unchecked(Self::scalar_out(value))
         }
     }
+
+    /// Converts a [`PackedLightScalar`] to the [`HighLightScalar`] for the same value.
+    fn standard_to_high(value: PackedLightScalar) -> HighLightScalar {
+        if value == 0 {
+            0
+        } else {
+            HighLightScalar::from(value) * Self::HIGH_STEPS + Self::HIGH_BASE
+        }
+    }
+
+    /// Converts a [`HighLightScalar`] to the [`PackedLightScalar`] for the nearest value
+    /// not greater than it (or the maximum value, if it is greater than that). This
+    /// produces the same result as encoding the original value at standard precision
+    /// would.
+    fn high_to_standard(value: HighLightScalar) -> PackedLightScalar {
+        PackedLightScalar::try_from(value.saturating_sub(Self::HIGH_BASE) / Self::HIGH_STEPS)
+            .unwrap_or(PackedLightScalar::MAX)
+    }
 }
 
 impl fmt::Debug for PackedLight {
@@ -269,6 +415,20 @@ impl LightUpdateQueue {
         }
     }
 
+    /// If `cube` is in the queue, change its priority to `priority`, whether higher or
+    /// lower.
+    pub fn reprioritize(&mut self, cube: GridPoint, priority: PackedLightScalar) {
+        if let Some(existing_priority) = self.table.get_mut(&cube) {
+            let removed = self.queue.remove(&LightUpdateRequest {
+                cube,
+                priority: *existing_priority,
+            });
+            debug_assert!(removed);
+            *existing_priority = priority;
+            self.queue.insert(LightUpdateRequest { cube, priority });
+        }
+    }
+
     #[inline]
     pub fn pop(&mut self) -> Option<LightUpdateRequest> {
         // This can become self.queue.pop_last() when that's stable
@@ -299,6 +459,291 @@ impl LightUpdateQueue {
     }
 }
 
+/// Record of which cubes' light values were computed from which other cubes' light
+/// values (or blocks), in both directions, so that when a cube changes, every cube
+/// whose light was derived from it can be updated.
+///
+/// Cubes which rays merely passed through empty space are not recorded. Each direction
+/// is stored as sorted, deduplicated lists rather than sets, which are several times
+/// smaller for the few dozen entries a cube typically has, and lists which have
+/// shrunk are reallocated so that removed entries do not keep their memory.
+#[derive(Debug, Default)]
+pub(crate) struct LightDependencies {
+    /// For each cube, the cubes its light was computed from.
+    dependencies: HashMap<GridPoint, Box<[GridPoint]>>,
+    /// For each cube, the cubes whose light was computed from it.
+    dependents: HashMap<GridPoint, Vec<GridPoint>>,
+}
+
+impl LightDependencies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the recorded dependencies of `cube`.
+    pub fn set(&mut self, cube: GridPoint, mut dependencies: Vec<GridPoint>) {
+        self.remove(cube);
+        dependencies.retain(|&d| d != cube);
+        if dependencies.is_empty() {
+            return;
+        }
+        dependencies.sort_unstable_by_key(|&p| sort_key(p));
+        dependencies.dedup();
+        for &dependency in &dependencies {
+            let dependents = self.dependents.entry(dependency).or_default();
+            if let Err(index) = dependents.binary_search_by_key(&sort_key(cube), |&p| sort_key(p)) {
+                dependents.insert(index, cube);
+            }
+        }
+        self.dependencies
+            .insert(cube, dependencies.into_boxed_slice());
+    }
+
+    /// Forgets the dependencies of `cube`, such as because it has become opaque.
+    pub fn remove(&mut self, cube: GridPoint) {
+        if let Some(old) = self.dependencies.remove(&cube) {
+            for dependency in old.iter() {
+                if let Entry::Occupied(mut e) = self.dependents.entry(*dependency) {
+                    let dependents = e.get_mut();
+                    if let Ok(index) =
+                        dependents.binary_search_by_key(&sort_key(cube), |&p| sort_key(p))
+                    {
+                        dependents.remove(index);
+                    }
+                    if dependents.is_empty() {
+                        e.remove();
+                    } else if dependents.len() * 4 <= dependents.capacity() {
+                        dependents.shrink_to_fit();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the cubes whose light was computed from `cube`.
+    pub fn dependents(&self, cube: GridPoint) -> impl Iterator<Item = GridPoint> + '_ {
+        self.dependents
+            .get(&cube)
+            .into_iter()
+            .flat_map(|dependents| dependents.iter().copied())
+    }
+
+    /// Forgets everything, such as because all light values have been reset.
+    pub fn clear(&mut self) {
+        self.dependencies.clear();
+        self.dependents.clear();
+    }
+}
+
+/// Ordering for the lists in [`LightDependencies`], since [`GridPoint`] is not [`Ord`].
+fn sort_key(cube: GridPoint) -> [GridCoordinate; 3] {
+    cube.into()
+}
+
+/// Number of cubes along each edge of the chunks in which light values are stored;
+/// see [`Space::light_changed_since`].
+pub const LIGHT_CHUNK_SIZE: GridCoordinate = 16;
+
+/// Number of cubes in a chunk of [`LightStorage`].
+const LIGHT_CHUNK_VOLUME: usize = (LIGHT_CHUNK_SIZE * LIGHT_CHUNK_SIZE * LIGHT_CHUNK_SIZE) as usize;
+
+/// Storage for the light values of the cubes of a [`Space`], divided into chunks of
+/// [`LIGHT_CHUNK_SIZE`]³ cubes which each record a version number when they are
+/// changed, so that copies of the data (such as GPU textures) can be updated by
+/// copying only the changed chunks.
+///
+/// Chunks are aligned to multiples of [`LIGHT_CHUNK_SIZE`] in cube coordinates, as
+/// [`ChunkPos`] is, rather than to the bounds of the space; cubes of edge chunks which
+/// lie outside the space are stored but never used.
+///
+/// Values are stored with a [`LightPrecision`], and [`Self::get`] returns exactly the
+/// values [`PackedLight::quantize`] produces at that precision.
+pub(crate) struct LightStorage {
+    /// Bounds of the cubes whose light is stored.
+    grid: Grid,
+    precision: LightPrecision,
+    /// Bounds of the chunks, in chunk coordinates. Empty if light is not being stored.
+    chunk_grid: Grid,
+    /// Indexed by `chunk_grid.index()`.
+    chunks: Box<[LightChunk]>,
+    /// The version number most recently given to a chunk.
+    version: u64,
+}
+
+struct LightChunk {
+    /// Indexed by [`LightStorage::locate`].
+    light: LightChunkData,
+    /// Value of [`LightStorage::version`] when this chunk was last changed.
+    version: u64,
+}
+
+/// The light values of a [`LightChunk`], in a form depending on [`LightPrecision`].
+enum LightChunkData {
+    /// Values in the form of [`PackedLight::as_texel`].
+    Standard(Box<[[PackedLightScalar; 4]]>),
+    High(Box<[PackedLight]>),
+}
+
+impl LightChunkData {
+    fn new(precision: LightPrecision, value: PackedLight) -> Self {
+        match precision {
+            LightPrecision::Standard => {
+                Self::Standard(vec![value.as_texel(); LIGHT_CHUNK_VOLUME].into_boxed_slice())
+            }
+            LightPrecision::High => Self::High(vec![value; LIGHT_CHUNK_VOLUME].into_boxed_slice()),
+        }
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> PackedLight {
+        match self {
+            // Only valid texels are ever stored.
+            Self::Standard(texels) => {
+                PackedLight::from_texel(texels[index]).unwrap_or(PackedLight::NO_RAYS)
+            }
+            Self::High(values) => values[index],
+        }
+    }
+
+    /// Stores `value`, which must already have been quantized to this data's precision.
+    #[inline]
+    fn set(&mut self, index: usize, value: PackedLight) {
+        match self {
+            Self::Standard(texels) => texels[index] = value.as_texel(),
+            Self::High(values) => values[index] = value,
+        }
+    }
+
+    fn convert(&self, precision: LightPrecision) -> Self {
+        match (self, precision) {
+            (Self::Standard(texels), LightPrecision::Standard) => Self::Standard(texels.clone()),
+            (Self::High(values), LightPrecision::High) => Self::High(values.clone()),
+            (_, precision) => {
+                let mut new = Self::new(precision, PackedLight::NO_RAYS);
+                for index in 0..LIGHT_CHUNK_VOLUME {
+                    new.set(index, self.get(index).quantize(precision));
+                }
+                new
+            }
+        }
+    }
+}
+
+impl LightStorage {
+    /// Constructs storage for the cubes in `grid`, all initially having light `initial`,
+    /// or storing nothing if `initial` is [`None`].
+    pub fn new(grid: Grid, initial: Option<PackedLight>, precision: LightPrecision) -> Self {
+        let mut storage = Self {
+            grid,
+            precision,
+            chunk_grid: Grid::new([0, 0, 0], [0, 0, 0]),
+            chunks: Box::new([]),
+            version: 0,
+        };
+        storage.reset(initial);
+        storage
+    }
+
+    /// Replaces every light value with `initial`, or discards them all if it is
+    /// [`None`]. Every chunk counts as changed.
+    pub fn reset(&mut self, initial: Option<PackedLight>) {
+        self.version += 1;
+        let version = self.version;
+        match initial {
+            None => {
+                self.chunk_grid = Grid::new([0, 0, 0], [0, 0, 0]);
+                self.chunks = Box::new([]);
+            }
+            Some(value) => {
+                self.chunk_grid = self.grid.divide(LIGHT_CHUNK_SIZE);
+                let value = value.quantize(self.precision);
+                self.chunks = (0..self.chunk_grid.volume())
+                    .map(|_| LightChunk {
+                        light: LightChunkData::new(self.precision, value),
+                        version,
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    /// Returns the index of the chunk containing `cube` and the index of `cube` within
+    /// that chunk, or [`None`] if no light is stored for `cube`.
+    #[inline]
+    fn locate(&self, cube: GridPoint) -> Option<(usize, usize)> {
+        if !self.grid.contains_cube(cube) {
+            return None;
+        }
+        let chunk = cube_to_chunk::<LIGHT_CHUNK_SIZE>(cube).0;
+        let chunk_index = self.chunk_grid.index(chunk)?;
+        let offset = cube - chunk * LIGHT_CHUNK_SIZE;
+        let index = (offset.x * LIGHT_CHUNK_SIZE + offset.y) * LIGHT_CHUNK_SIZE + offset.z;
+        Some((chunk_index, index as usize))
+    }
+
+    /// Returns the light value of `cube`, or [`None`] if it is out of bounds or light is
+    /// not being stored.
+    #[inline]
+    pub fn get(&self, cube: GridPoint) -> Option<PackedLight> {
+        let (chunk_index, index) = self.locate(cube)?;
+        Some(self.chunks[chunk_index].light.get(index))
+    }
+
+    /// Sets the light value of `cube`, reduced to [`Self::precision`], and returns whether
+    /// it differs from the old value.
+    /// Does nothing if `cube` is out of bounds or light is not being stored.
+    #[inline]
+    pub fn set(&mut self, cube: GridPoint, value: PackedLight) -> bool {
+        let value = value.quantize(self.precision);
+        if let Some((chunk_index, index)) = self.locate(cube) {
+            let chunk = &mut self.chunks[chunk_index];
+            if chunk.light.get(index) != value {
+                chunk.light.set(index, value);
+                self.version += 1;
+                chunk.version = self.version;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn precision(&self) -> LightPrecision {
+        self.precision
+    }
+
+    /// Changes the precision of the stored values, converting the existing ones. Every
+    /// chunk counts as changed.
+    pub fn set_precision(&mut self, precision: LightPrecision) {
+        if precision == self.precision {
+            return;
+        }
+        self.precision = precision;
+        self.version += 1;
+        let version = self.version;
+        for chunk in self.chunks.iter_mut() {
+            chunk.light = chunk.light.convert(precision);
+            chunk.version = version;
+        }
+    }
+
+    /// Returns a number which is increased whenever any light value changes.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the chunks which have changed since [`Self::version`] returned `version`.
+    pub fn chunks_changed_since(
+        &self,
+        version: u64,
+    ) -> impl Iterator<Item = ChunkPos<LIGHT_CHUNK_SIZE>> + '_ {
+        self.chunk_grid
+            .interior_iter()
+            .zip(self.chunks.iter())
+            .filter(move |(_, chunk)| chunk.version > version)
+            .map(|(position, _)| ChunkPos(position))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -308,20 +753,13 @@ mod tests {
         (PackedLightScalar::MIN..PackedLightScalar::MAX)
             .flat_map(|s| {
                 vec![
+                    PackedLight::from_texel([s, 0, 0, 255]).unwrap(),
+                    PackedLight::from_texel([0, s, 0, 255]).unwrap(),
+                    PackedLight::from_texel([0, 0, s, 255]).unwrap(),
+                    PackedLight::from_texel([s, 127, 255, 255]).unwrap(),
+                    // A value not representable at standard precision
                     PackedLight {
-                        value: Vector3::new(s, 0, 0),
-                        status: LightStatus::Visible,
-                    },
-                    PackedLight {
-                        value: Vector3::new(0, s, 0),
-                        status: LightStatus::Visible,
-                    },
-                    PackedLight {
-                        value: Vector3::new(0, 0, s),
-                        status: LightStatus::Visible,
-                    },
-                    PackedLight {
-                        value: Vector3::new(s, 127, 255),
+                        value: Vector3::new(PackedLight::standard_to_high(s) + 1, 0, 0),
                         status: LightStatus::Visible,
                     },
                 ]
@@ -336,7 +774,23 @@ mod tests {
     #[test]
     fn packed_light_roundtrip() {
         for i in PackedLightScalar::MIN..PackedLightScalar::MAX {
-            assert_eq!(i, PackedLight::scalar_in(PackedLight::scalar_out(i)));
+            let high = PackedLight::standard_to_high(i);
+            assert_eq!(
+                i,
+                PackedLight::high_to_standard(PackedLight::scalar_in(PackedLight::scalar_out(
+                    high
+                )))
+            );
+        }
+    }
+
+    /// The same for high precision; but here float rounding may shift the value by one
+    /// step, which is harmless since a shift never accumulates past one standard step.
+    #[test]
+    fn packed_light_roundtrip_high() {
+        for h in 1..=HighLightScalar::MAX {
+            let r = PackedLight::scalar_in(PackedLight::scalar_out(h));
+            assert!(r == h || r + 1 == h, "{} became {}", h, r);
         }
     }
 
@@ -344,7 +798,7 @@ mod tests {
     /// from `PackedLight`, so it had better not be NaN for any possible input.
     #[test]
     fn packed_light_always_finite() {
-        for i in PackedLightScalar::MIN..PackedLightScalar::MAX {
+        for i in HighLightScalar::MIN..=HighLightScalar::MAX {
             assert!(PackedLight::scalar_out(i).is_finite(), "{}", i);
         }
     }
@@ -358,14 +812,15 @@ mod tests {
                 PackedLight::scalar_in(NotNan::new(1e-30).unwrap()),
                 PackedLight::scalar_in(NotNan::new(1e+30).unwrap()),
             ],
-            [0, 0, 255],
+            [0, 0, HighLightScalar::MAX],
         );
     }
 
     #[test]
     fn packed_light_is_packed() {
         // Technically this is not guaranteed by the compiler, but if it's false something probably went wrong.
-        assert_eq!(std::mem::size_of::<PackedLight>(), 4);
+        // Three u16 components and a u8 status, padded to u16 alignment.
+        assert_eq!(std::mem::size_of::<PackedLight>(), 8);
     }
 
     /// Demonstrate what range and step sizes we get out of the encoding.
@@ -373,11 +828,11 @@ mod tests {
     fn packed_light_extreme_values_out() {
         assert_eq!(
             [
-                PackedLight::scalar_out(0),
-                PackedLight::scalar_out(1),
-                PackedLight::scalar_out(2),
-                PackedLight::scalar_out(254),
-                PackedLight::scalar_out(255),
+                PackedLight::scalar_out(PackedLight::standard_to_high(0)),
+                PackedLight::scalar_out(PackedLight::standard_to_high(1)),
+                PackedLight::scalar_out(PackedLight::standard_to_high(2)),
+                PackedLight::scalar_out(PackedLight::standard_to_high(254)),
+                PackedLight::scalar_out(PackedLight::standard_to_high(255)),
             ],
             [0.0, 0.0040791943, 0.004259796, 234.75304, 245.14644],
         );
@@ -422,4 +877,94 @@ mod tests {
     }
 
     // TODO: Test of queue priority updates
+
+    #[test]
+    fn storage_versions_changed_chunks() {
+        let grid = Grid::new([-1, 0, 0], [LIGHT_CHUNK_SIZE + 2, 1, 1]);
+        let mut storage = LightStorage::new(grid, Some(PackedLight::ONE), LightPrecision::Standard);
+        let chunks = |storage: &LightStorage, version| -> Vec<ChunkPos<LIGHT_CHUNK_SIZE>> {
+            storage.chunks_changed_since(version).collect()
+        };
+        assert_eq!(
+            chunks(&storage, 0),
+            vec![
+                ChunkPos::new(-1, 0, 0),
+                ChunkPos::new(0, 0, 0),
+                ChunkPos::new(1, 0, 0)
+            ]
+        );
+
+        let version = storage.version();
+        assert_eq!(chunks(&storage, version), vec![]);
+        let cube = GridPoint::new(LIGHT_CHUNK_SIZE, 0, 0);
+        assert!(!storage.set(cube, PackedLight::ONE));
+        assert_eq!(chunks(&storage, version), vec![]);
+        assert!(storage.set(cube, PackedLight::OPAQUE));
+        assert_eq!(storage.get(cube), Some(PackedLight::OPAQUE));
+        assert_eq!(chunks(&storage, version), vec![ChunkPos::new(1, 0, 0)]);
+
+        // Out of bounds, including the unused part of a chunk.
+        assert_eq!(
+            storage.get(GridPoint::new(LIGHT_CHUNK_SIZE + 1, 0, 0)),
+            None
+        );
+        assert!(!storage.set(GridPoint::new(0, 1, 0), PackedLight::OPAQUE));
+
+        let version = storage.version();
+        storage.reset(None);
+        assert_eq!(storage.get(cube), None);
+        assert_eq!(chunks(&storage, version), vec![]);
+        assert!(storage.version() > version);
+    }
+
+    #[test]
+    fn storage_precision() {
+        let grid = Grid::new([0, 0, 0], [2, 1, 1]);
+        let cube = GridPoint::new(0, 0, 0);
+        let fine = PackedLight::from(Rgb::new(1.01, 1.0, 1.0));
+        let coarse = fine.quantize(LightPrecision::Standard);
+        assert_ne!(fine, coarse);
+
+        let mut storage = LightStorage::new(grid, Some(PackedLight::ONE), LightPrecision::High);
+        assert!(storage.set(cube, fine));
+        assert_eq!(storage.get(cube), Some(fine));
+
+        let version = storage.version();
+        storage.set_precision(LightPrecision::Standard);
+        assert_eq!(storage.precision(), LightPrecision::Standard);
+        assert_eq!(storage.get(cube), Some(coarse));
+        assert_eq!(
+            storage.chunks_changed_since(version).count(),
+            1,
+            "conversion should count as a change"
+        );
+        // Setting a value which differs only in the discarded precision is no change.
+        assert!(!storage.set(cube, fine));
+        assert_eq!(storage.get(cube), Some(coarse));
+    }
+
+    #[test]
+    fn dependencies_deduplicated_and_removed() {
+        let a = GridPoint::new(0, 0, 0);
+        let b = GridPoint::new(1, 0, 0);
+        let c = GridPoint::new(2, 0, 0);
+        let mut deps = LightDependencies::new();
+        deps.set(a, vec![c, b, c, a]);
+        deps.set(b, vec![c]);
+        assert_eq!(deps.dependents(c).collect::<Vec<_>>(), vec![a, b]);
+        assert_eq!(deps.dependents(b).collect::<Vec<_>>(), vec![a]);
+        assert_eq!(
+            deps.dependents(a).count(),
+            0,
+            "self-dependency should be dropped"
+        );
+
+        deps.remove(a);
+        assert_eq!(deps.dependents(c).collect::<Vec<_>>(), vec![b]);
+        assert_eq!(deps.dependents(b).count(), 0);
+        assert!(
+            !deps.dependents.contains_key(&b),
+            "empty lists should be removed"
+        );
+    }
 }
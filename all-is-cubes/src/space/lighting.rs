@@ -4,16 +4,19 @@
 //! Lighting algorithms for `Space`. This module is closely tied to `Space`
 //! and separated out for readability, not modularity.
 
-use std::convert::TryInto as _;
+use std::convert::TryFrom;
 use std::fmt;
 
 use cgmath::{EuclideanSpace as _, InnerSpace as _, Point3, Vector3};
+use instant::Instant;
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 
 use crate::math::*;
 use crate::raycast::Ray;
 use crate::space::light_data::*;
 use crate::space::*;
+use crate::trace::trace_span;
 
 /// This parameter determines to what degree absorption of light due to a block surface's
 /// color is taken into account. At zero, it is not (all surfaces are perfectly
@@ -26,10 +29,20 @@ const SURFACE_ABSORPTION: f32 = 0.75;
 /// is assumed to intercept this much of the ray passing through.
 const TRANSPARENT_BLOCK_COVERAGE: f32 = 0.25;
 
-const RAY_DIRECTION_STEP: isize = 5;
-const RAY_CUBE_EDGE: usize = (RAY_DIRECTION_STEP as usize) * 2 + 1;
+/// Update priority given to cubes whose light was set by [`Space::seed_sky_light`], so
+/// that they are refined only after cubes with no good estimate.
+const SEEDED_PRIORITY: PackedLightScalar = 1;
+
+/// Largest ray pattern; see [`light_rays`].
+const MAX_RAY_DIRECTION_STEP: isize = 5;
+const RAY_CUBE_EDGE: usize = (MAX_RAY_DIRECTION_STEP as usize) * 2 + 1;
 const ALL_RAYS_COUNT: usize = RAY_CUBE_EDGE.pow(3) - (RAY_CUBE_EDGE - 2).pow(3);
 
+/// Largest allowed [`LightRayParameters::rays_per_face`].
+const MAX_RAYS_PER_FACE: u16 = (RAY_CUBE_EDGE * RAY_CUBE_EDGE) as u16;
+/// Largest allowed [`LightRayParameters::falloff`].
+const MAX_FALLOFF: f32 = 4.0;
+
 #[derive(Debug)]
 struct LightRayData {
     ray: Ray,
@@ -37,18 +50,23 @@ struct LightRayData {
 }
 
 // TODO: Make multiple ray patterns that suit the maximum_distance parameter.
-static LIGHT_RAYS: Lazy<[LightRayData; ALL_RAYS_COUNT]> = Lazy::new(|| {
+/// Ray patterns for each step from 1 to [`MAX_RAY_DIRECTION_STEP`], indexed by
+/// step minus 1.
+static LIGHT_RAYS: Lazy<Vec<Box<[LightRayData]>>> =
+    Lazy::new(|| (1..=MAX_RAY_DIRECTION_STEP).map(light_rays).collect());
+
+/// Computes rays from the center of a cube toward each point of the integer lattice on
+/// the surface of a larger cube of radius `step`, so that (2 × `step` + 1)² rays pass
+/// through each face (sharing the edges with the neighboring faces).
+fn light_rays(step: isize) -> Box<[LightRayData]> {
     let mut rays: Vec<LightRayData> = Vec::new();
     let origin = Point3::new(0.5, 0.5, 0.5);
 
     // TODO: octahedron instead of cube
-    for x in -RAY_DIRECTION_STEP..=RAY_DIRECTION_STEP {
-        for y in -RAY_DIRECTION_STEP..=RAY_DIRECTION_STEP {
-            for z in -RAY_DIRECTION_STEP..=RAY_DIRECTION_STEP {
-                if x.abs() == RAY_DIRECTION_STEP
-                    || y.abs() == RAY_DIRECTION_STEP
-                    || z.abs() == RAY_DIRECTION_STEP
-                {
+    for x in -step..=step {
+        for y in -step..=step {
+            for z in -step..=step {
+                if x.abs() == step || y.abs() == step || z.abs() == step {
                     let direction = Vector3::new(
                         x as FreeCoordinate,
                         y as FreeCoordinate,
@@ -68,8 +86,8 @@ static LIGHT_RAYS: Lazy<[LightRayData; ALL_RAYS_COUNT]> = Lazy::new(|| {
             }
         }
     }
-    rays.try_into().unwrap()
-});
+    rays.into_boxed_slice()
+}
 
 /// Methods on Space that specifically implement the lighting algorithm.
 impl Space {
@@ -78,14 +96,86 @@ impl Space {
             return;
         }
 
+        let cube = self.wrap_cube(cube);
         if self.grid().contains_cube(cube) {
             self.light_update_queue
                 .insert(LightUpdateRequest { priority, cube });
         }
     }
 
-    /// Do some lighting updates.
-    pub(crate) fn update_lighting_from_queue(&mut self) -> LightUpdatesInfo {
+    /// Queues light updates for every cube whose light was computed from `cube`.
+    pub(crate) fn light_dependents_need_update(
+        &mut self,
+        cube: GridPoint,
+        priority: PackedLightScalar,
+    ) {
+        if self.physics.light == LightPhysics::None {
+            return;
+        }
+
+        // Dependents are always cubes whose light was computed, so they are already
+        // wrapped and within the grid.
+        let queue = &mut self.light_update_queue;
+        for dependent in self.light_dependencies.dependents(cube) {
+            queue.insert(LightUpdateRequest {
+                priority,
+                cube: dependent,
+            });
+        }
+    }
+
+    /// Quickly estimates the light of every cube which is open to the sky — which has
+    /// only invisible blocks above it, up to the top of the space — as being the sky
+    /// color, which is nearly what the full lighting computation would find for cubes
+    /// outdoors. Returns the number of such cubes.
+    ///
+    /// The ray-based lighting updates (performed by [`Space::step`] and
+    /// [`Space::evaluate_light`]) will still refine these cubes' light, but only after
+    /// cubes which do not have such an estimate, so this is appropriate to call after
+    /// generating a large outdoor area, to make it look right much sooner.
+    ///
+    /// Does nothing if the space's [`Topology`] wraps the Y axis, since
+    /// then there is no top of the space.
+    pub fn seed_sky_light(&mut self) -> usize {
+        if self.physics.light == LightPhysics::None || self.physics.topology.wraps(1) {
+            return 0;
+        }
+        let grid = self.grid();
+        let mut count = 0;
+        for x in grid.x_range() {
+            for z in grid.z_range() {
+                for y in grid.y_range().rev() {
+                    let cube = GridPoint::new(x, y, z);
+                    let index = grid.index(cube).unwrap();
+                    if self.block_data[self.contents[index] as usize]
+                        .evaluated
+                        .visible
+                    {
+                        break;
+                    }
+                    count += 1;
+                    if self.lighting.set(cube, self.packed_sky_color) {
+                        self.notifier.notify(SpaceChange::Lighting(cube));
+                    }
+                    self.light_update_queue.reprioritize(cube, SEEDED_PRIORITY);
+                }
+            }
+        }
+        count
+    }
+
+    /// Number of cubes waiting for their lighting to be updated.
+    pub(crate) fn light_update_queue_len(&self) -> usize {
+        self.light_update_queue.len()
+    }
+
+    /// Do some lighting updates, stopping early if `deadline` passes. At least one
+    /// update is done if any are queued, so that lighting always makes progress.
+    pub(crate) fn update_lighting_from_queue(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> LightUpdatesInfo {
+        let _span = trace_span!("lighting");
         let mut light_update_count: usize = 0;
         self.last_light_updates.clear();
         let mut max_difference: PackedLightScalar = 0;
@@ -104,7 +194,7 @@ impl Space {
                 let (difference, cube_cost, _) = self.update_lighting_now_on(cube);
                 max_difference = max_difference.max(difference);
                 cost += cube_cost;
-                if cost >= 40000 {
+                if cost >= 40000 || deadline.map_or(false, |d| Instant::now() >= d) {
                     break;
                 }
             }
@@ -124,17 +214,24 @@ impl Space {
         cube: GridPoint,
     ) -> (PackedLightScalar, usize, LightUpdateCubeInfo) {
         let (new_light_value, dependencies, mut cost, info) = self.compute_lighting(cube);
+        // Compare only what will be stored, or changes too small to store would be
+        // retried forever.
+        let new_light_value = new_light_value.quantize(self.lighting.precision());
         let old_light_value: PackedLight = self.get_lighting(cube);
         let difference_priority = new_light_value.difference_priority(old_light_value);
         if difference_priority > 0 {
             cost += 200;
-            // TODO: compute index only once
-            self.lighting[self.grid().index(cube).unwrap()] = new_light_value;
+            self.lighting.set(cube, new_light_value);
             self.notifier.notify(SpaceChange::Lighting(cube));
-            for cube in dependencies {
-                self.light_needs_update(cube, difference_priority);
+            // Cubes which were computed from this cube's old value are now stale.
+            self.light_dependents_need_update(cube, difference_priority);
+            // Cubes this cube can see can probably see it too, and so may be affected,
+            // even if they have never been computed from it.
+            for &dependency in &dependencies {
+                self.light_needs_update(dependency, difference_priority);
             }
         }
+        self.light_dependencies.set(cube, dependencies);
         (difference_priority, cost, info)
     }
 
@@ -147,12 +244,20 @@ impl Space {
         &self,
         cube: GridPoint,
     ) -> (PackedLight, Vec<GridPoint>, usize, LightUpdateCubeInfo) {
-        let maximum_distance = match self.physics.light {
+        let parameters = match &self.physics.light {
             LightPhysics::None => {
                 panic!("Light is disabled; should not reach here");
             }
-            LightPhysics::Rays { maximum_distance } => FreeCoordinate::from(maximum_distance),
+            LightPhysics::Rays(parameters) => parameters,
         };
+        let maximum_distance = FreeCoordinate::from(parameters.maximum_distance);
+        // Rays may leave the grid and come back in if the space wraps.
+        let raycast_grid = self
+            .physics
+            .topology
+            .raycast_grid(self.grid(), maximum_distance);
+        let reflections = parameters.reflections;
+        let falloff = parameters.falloff.into_inner();
 
         // Accumulator of incoming light encountered.
         let mut incoming_light: Rgb = Rgb::ZERO;
@@ -177,14 +282,15 @@ impl Space {
                 FaceMap::repeat(1.0)
             } else {
                 FaceMap::from_fn(|face| {
-                    // We want directions that either face away from visible faces, or towards light sources.
+                    // We want directions that either face away from visible faces, or towards
+                    // light sources emitting light toward this cube.
                     if self
                         .get_evaluated(cube + face.opposite().normal_vector())
                         .visible
                         || self
                             .get_evaluated(cube + face.normal_vector())
                             .attributes
-                            .light_emission
+                            .light_emission[face.opposite()]
                             != Rgb::ZERO
                     {
                         // TODO: Once we have fancier block opacity precomputations, use them to
@@ -197,7 +303,8 @@ impl Space {
             };
 
             // TODO: Choose a ray pattern that suits the maximum_distance.
-            'each_ray: for LightRayData { ray, face_cosines } in &LIGHT_RAYS[..] {
+            let rays = &LIGHT_RAYS[parameters.ray_direction_step() - 1];
+            'each_ray: for LightRayData { ray, face_cosines } in rays.iter() {
                 // TODO: Theoretically we should weight light rays by the cosine but that has caused poor behavior in the past.
                 let ray_weight_by_faces = face_cosines
                     .zip(adjacent_faces, |_face, ray_cosine, reflects| {
@@ -210,7 +317,7 @@ impl Space {
                 }
 
                 let translated_ray = ray.translate(cube.cast::<FreeCoordinate>().unwrap().to_vec());
-                let raycaster = translated_ray.cast().within_grid(self.grid());
+                let raycaster = translated_ray.cast().within_grid(raycast_grid);
 
                 // Fraction of the light value that is to be determined by future, rather than past,
                 // tracing; starts at 1.0 and decreases as opaque surfaces are encountered.
@@ -225,11 +332,13 @@ impl Space {
                         // Don't count rays that didn't hit anything close enough.
                         break 'raycast;
                     }
-                    let ev_hit = self.get_evaluated(hit.cube_ahead());
+                    let hit_data = self.get_block_data(hit.cube_ahead());
+                    let ev_hit = hit_data.evaluated();
                     if !ev_hit.visible {
                         // Completely transparent block is passed through.
                         continue 'raycast;
                     }
+                    let attenuation = (1.0 + hit.t_distance() as f32).powf(-falloff);
 
                     // TODO: Implement blocks with some faces opaque.
                     if ev_hit.opaque {
@@ -245,10 +354,20 @@ impl Space {
 
                         let surface_color = ev_hit.color.to_rgb() * SURFACE_ABSORPTION
                             + Rgb::ONE * (1. - SURFACE_ABSORPTION);
+                        let reflected_light = if reflections {
+                            stored_light.value() * surface_color
+                        } else {
+                            Rgb::ZERO
+                        };
+                        // The struck face is the one the ray entered by, so that is the face
+                        // whose emission travels back along the ray.
                         let light_from_struck_face =
-                            ev_hit.attributes.light_emission + stored_light.value() * surface_color;
-                        incoming_light += light_from_struck_face * ray_alpha * ray_weight_by_faces;
-                        dependencies.push(light_cube);
+                            hit_data.light_emission[hit.face()] + reflected_light;
+                        incoming_light +=
+                            light_from_struck_face * attenuation * ray_alpha * ray_weight_by_faces;
+                        dependencies.push(self.wrap_cube(light_cube));
+                        // Also depend on the struck block, so that removing it updates us.
+                        dependencies.push(self.wrap_cube(hit.cube_ahead()));
                         cost += 10;
                         // This terminates the raycast; we don't bounce rays
                         // (diffuse reflections, not specular/mirror).
@@ -268,24 +387,32 @@ impl Space {
                         break;
                     } else {
                         // Block is partly transparent and light should pass through.
-                        let light_cube = hit.cube_ahead();
+                        let light_cube = self.wrap_cube(hit.cube_ahead());
 
-                        let stored_light = if light_cube == cube {
-                            // Don't read the value we're trying to recalculate.
+                        let stored_light = if light_cube == cube || !reflections {
+                            // Don't read the value we're trying to recalculate, or reflected
+                            // light if that is disabled.
                             Rgb::ZERO
                         } else {
                             self.get_lighting(light_cube).value()
                         };
+                        // The origin cube's own emission is added once, below, rather than
+                        // per ray.
+                        let emission = if light_cube == cube {
+                            Rgb::ZERO
+                        } else {
+                            hit_data.light_emission[hit.face()]
+                        };
                         // 'coverage' is what fraction of the light ray we assume to hit this block,
                         // as opposed to passing through it.
                         // TODO: Compute coverage (and connectivity) in EvaluatedBlock.
                         let coverage = TRANSPARENT_BLOCK_COVERAGE;
-                        incoming_light += (ev_hit.attributes.light_emission * ray_alpha
-                            + stored_light)
+                        incoming_light += (emission * ray_alpha + stored_light)
+                            * attenuation
                             * coverage
                             * ray_weight_by_faces;
                         ray_alpha *= 1.0 - coverage;
-                        dependencies.push(hit.cube_ahead());
+                        dependencies.push(light_cube);
                         cost += 10;
                     }
                 }
@@ -304,10 +431,20 @@ impl Space {
         // if total_rays is zero then incoming_light is zero so the result will be zero.
         // We just need to avoid dividing by zero.
         let scale = NotNan::new(1.0 / total_ray_weight.max(1.0)).unwrap();
+        // A non-opaque block's Within emission lights the cube it is in, whether or not
+        // any rays were cast. It is reduced by the same coverage as light passing through.
+        // TODO: Arguably TRANSPARENT_BLOCK_COVERAGE shouldn't affect light emission.
+        let own_emission = if ev_origin.opaque {
+            Rgb::ZERO
+        } else {
+            self.get_block_data(cube).light_emission.within * TRANSPARENT_BLOCK_COVERAGE
+        };
         let new_light_value: PackedLight = if total_rays > 0 {
-            PackedLight::some(incoming_light * scale)
+            PackedLight::some(incoming_light * scale + own_emission)
         } else if ev_origin.opaque {
             PackedLight::OPAQUE
+        } else if own_emission != Rgb::ZERO {
+            PackedLight::some(own_emission)
         } else {
             PackedLight::NO_RAYS
         };
@@ -325,20 +462,207 @@ impl Space {
     }
 }
 
+/// Returns the light emitted by each face of `block`, reduced in proportion to how much
+/// of the face is covered by the block's voxels, so that a small glowing detail gives
+/// off less light than a block whose whole face glows.
+///
+/// The coverage of a face is the average, over each line of voxels perpendicular to the
+/// face, of the opacity of the line as a whole. Blocks without voxels cover their faces
+/// entirely.
+///
+/// Emission is also clamped to the range which [`PackedLight`] can store; in particular,
+/// negative emission would otherwise subtract light from the surroundings.
+pub(crate) fn effective_emission(block: &EvaluatedBlock) -> FaceMap<Rgb> {
+    let max = PackedLight::max_value();
+    let emission = block.attributes.light_emission.map(|_, light| {
+        Rgb::new(
+            light.red().into_inner().clamp(0.0, max),
+            light.green().into_inner().clamp(0.0, max),
+            light.blue().into_inner().clamp(0.0, max),
+        )
+    });
+    let voxels = match &block.voxels {
+        Some(voxels) if emission != NO_EMISSION => voxels,
+        _ => return emission,
+    };
+    let resolution = GridCoordinate::from(block.resolution);
+    let coverage = |axis: usize| -> f32 {
+        let mut total = 0.0;
+        for u in 0..resolution {
+            for v in 0..resolution {
+                let mut transmittance = 1.0;
+                for w in 0..resolution {
+                    let mut cube = GridPoint::new(0, 0, 0);
+                    cube[axis] = w;
+                    cube[(axis + 1) % 3] = u;
+                    cube[(axis + 2) % 3] = v;
+                    if let Some(voxel) = voxels.get(cube) {
+                        transmittance *= 1.0 - voxel.color.alpha().into_inner();
+                    }
+                }
+                total += 1.0 - transmittance;
+            }
+        }
+        total / (resolution * resolution) as f32
+    };
+    let axis_coverage = [coverage(0), coverage(1), coverage(2)];
+    FaceMap::from_fn(|face| match face {
+        Face::Within => emission.within,
+        face => emission[face] * axis_coverage[face.axis_number()],
+    })
+}
+
 impl LightPhysics {
-    /// Generate the lighting data array that a newly created empty [`Space`] should have.
-    pub(crate) fn initialize_lighting(
-        &self,
-        grid: Grid,
-        ambient_color: PackedLight,
-    ) -> Box<[PackedLight]> {
+    /// Returns the light value every cube of a newly created empty [`Space`] should have,
+    /// or [`None`] if light values are not stored at all.
+    pub(crate) fn initialize_lighting(&self, ambient_color: PackedLight) -> Option<PackedLight> {
         match self {
-            LightPhysics::None => Box::new([]),
-            LightPhysics::Rays { .. } => vec![ambient_color; grid.volume()].into_boxed_slice(),
+            LightPhysics::None => None,
+            LightPhysics::Rays(_) => Some(ambient_color),
+        }
+    }
+}
+
+/// Parameters of [`LightPhysics::Rays`], which trade off between appearance and speed of
+/// the light computation.
+///
+/// Every combination of values can be used, but values outside the documented ranges
+/// are clamped; [`LightRayParameters::validate`] checks for them, and deserialization
+/// rejects them.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedLightRayParameters")]
+#[non_exhaustive]
+pub struct LightRayParameters {
+    /// The maximum distance a simulated light ray will travel; blocks farther than
+    /// that distance apart will never have direct influence on each other.
+    /// Must be at least 1.
+    pub maximum_distance: u16,
+
+    /// Number of rays cast through each face of a cube whose light is being computed.
+    /// More rays give smoother light, but take proportionally longer to compute.
+    ///
+    /// The rays are arranged in a square grid with one passing through the center, so
+    /// this is rounded to the nearest of 9, 25, 49, 81, and 121. Must be from 1 to 121.
+    pub rays_per_face: u16,
+
+    /// Whether light is reflected off surfaces on its way to a cube. If false, only
+    /// light coming directly from light-emitting blocks and the sky counts, giving
+    /// stark shadows; if true, light may be reflected any number of times.
+    pub reflections: bool,
+
+    /// How much light fades with the distance it travels: light reaching a cube from a
+    /// surface at distance *d* is multiplied by (1 + *d*)<sup>−`falloff`</sup>. Zero
+    /// means no falloff; larger values make light sources and reflections more local,
+    /// which suits indoor scenes. Light from the sky does not fall off.
+    /// Must be from 0 to 4.
+    pub falloff: NotNan<f32>,
+}
+
+impl Default for LightRayParameters {
+    fn default() -> Self {
+        Self {
+            maximum_distance: 30,
+            rays_per_face: MAX_RAYS_PER_FACE,
+            reflections: true,
+            falloff: NotNan::new(0.0).unwrap(),
+        }
+    }
+}
+
+impl LightRayParameters {
+    /// Returns an error if any parameter is outside its documented range.
+    ///
+    /// ```
+    /// use all_is_cubes::space::LightRayParameters;
+    ///
+    /// let mut parameters = LightRayParameters::default();
+    /// assert!(parameters.validate().is_ok());
+    /// parameters.rays_per_face = 1000;
+    /// assert!(parameters.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), LightParametersError> {
+        if self.maximum_distance == 0 {
+            return Err(LightParametersError::MaximumDistance);
+        }
+        if !(1..=MAX_RAYS_PER_FACE).contains(&self.rays_per_face) {
+            return Err(LightParametersError::RaysPerFace(self.rays_per_face));
+        }
+        if !(0.0..=MAX_FALLOFF).contains(&self.falloff.into_inner()) {
+            return Err(LightParametersError::Falloff(self.falloff.into_inner()));
+        }
+        Ok(())
+    }
+
+    /// Returns the `step` of the [`light_rays`] pattern closest to `rays_per_face`.
+    fn ray_direction_step(&self) -> usize {
+        let edge = f32::from(self.rays_per_face).sqrt();
+        (((edge - 1.0) / 2.0).round() as usize).clamp(1, MAX_RAY_DIRECTION_STEP as usize)
+    }
+}
+
+/// Error from [`LightRayParameters::validate`].
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum LightParametersError {
+    /// [`LightRayParameters::maximum_distance`] was zero.
+    #[error("maximum_distance must be at least 1")]
+    MaximumDistance,
+    /// [`LightRayParameters::rays_per_face`] was out of range.
+    #[error("rays_per_face must be from 1 to {}, not {0}", MAX_RAYS_PER_FACE)]
+    RaysPerFace(u16),
+    /// [`LightRayParameters::falloff`] was out of range.
+    #[error("falloff must be from 0 to {}, not {0}", MAX_FALLOFF)]
+    Falloff(f32),
+}
+
+/// Serialization form of [`LightRayParameters`], which is validated when converted.
+/// Missing fields take their default values.
+#[derive(Deserialize)]
+#[serde(default)]
+struct UncheckedLightRayParameters {
+    maximum_distance: u16,
+    rays_per_face: u16,
+    reflections: bool,
+    falloff: NotNan<f32>,
+}
+
+impl Default for UncheckedLightRayParameters {
+    fn default() -> Self {
+        let LightRayParameters {
+            maximum_distance,
+            rays_per_face,
+            reflections,
+            falloff,
+        } = LightRayParameters::default();
+        Self {
+            maximum_distance,
+            rays_per_face,
+            reflections,
+            falloff,
         }
     }
 }
 
+impl TryFrom<UncheckedLightRayParameters> for LightRayParameters {
+    type Error = LightParametersError;
+    fn try_from(value: UncheckedLightRayParameters) -> Result<Self, Self::Error> {
+        let UncheckedLightRayParameters {
+            maximum_distance,
+            rays_per_face,
+            reflections,
+            falloff,
+        } = value;
+        let parameters = Self {
+            maximum_distance,
+            rays_per_face,
+            reflections,
+            falloff,
+        };
+        parameters.validate()?;
+        Ok(parameters)
+    }
+}
+
 /// Performance data for bulk light updates.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
 #[non_exhaustive]
@@ -438,16 +762,25 @@ impl Geometry for LightUpdateRayInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::block::AIR;
+    #[cfg(feature = "content")]
+    use crate::content::{install_demo_blocks, DemoBlocks};
+    #[cfg(feature = "content")]
+    use crate::linking::BlockProvider;
     use crate::listen::Sink;
     use crate::space::Space;
+    #[cfg(feature = "content")]
+    use crate::universe::Universe;
+
+    /// The light from the sky, as the space stores it at its [`LightPrecision`].
+    fn sky_light(space: &Space) -> PackedLight {
+        PackedLight::from(space.physics().sky_color).quantize(space.light_precision())
+    }
 
     #[test]
     fn initial_lighting_value() {
         let space = Space::empty_positive(1, 1, 1);
-        assert_eq!(
-            PackedLight::from(space.physics().sky_color),
-            space.get_lighting((0, 0, 0))
-        );
+        assert_eq!(sky_light(&space), space.get_lighting((0, 0, 0)));
     }
 
     #[test]
@@ -462,12 +795,12 @@ mod tests {
     #[test]
     fn step() {
         let mut space = Space::empty_positive(3, 1, 1);
-        let former_sky_light = PackedLight::from(space.physics().sky_color);
+        let former_sky_light = sky_light(&space);
         space.set_physics(SpacePhysics {
             sky_color: Rgb::new(1.0, 0.0, 0.0),
             ..SpacePhysics::default()
         });
-        let new_sky_light = PackedLight::from(space.physics().sky_color);
+        let new_sky_light = sky_light(&space);
 
         space.set((0, 0, 0), Rgb::ONE).unwrap();
         // Not changed yet... except for the now-opaque block
@@ -504,6 +837,78 @@ mod tests {
 
     // TODO: test evaluate_light's epsilon parameter
 
+    /// Removing a block must update cubes whose light was computed from rays striking
+    /// it, even if they are not adjacent to it.
+    #[test]
+    fn removal_updates_distant_dependents() {
+        let mut space = Space::empty_positive(5, 1, 1);
+        let block = Block::from(Rgba::WHITE);
+        space.set([0, 0, 0], &block).unwrap();
+        space.set([4, 0, 0], &block).unwrap();
+        space.evaluate_light(0, |_| {});
+        assert!(space
+            .light_dependencies
+            .dependents(GridPoint::new(4, 0, 0))
+            .any(|cube| cube == GridPoint::new(1, 0, 0)));
+
+        space.set([4, 0, 0], AIR).unwrap();
+        let mut queued = Vec::new();
+        while let Some(request) = space.light_update_queue.pop() {
+            queued.push(request.cube);
+        }
+        assert!(queued.contains(&GridPoint::new(1, 0, 0)), "{:?}", queued);
+    }
+
+    #[test]
+    fn light_wraps_around_topology() {
+        let block = Block::from(Rgba::WHITE);
+        let light_around_edge = |topology| {
+            let mut space = Space::empty_positive(5, 1, 1);
+            space.set_physics(SpacePhysics {
+                topology,
+                ..SpacePhysics::default()
+            });
+            space.set([1, 0, 0], &block).unwrap();
+            space.set([4, 0, 0], &block).unwrap();
+            space.evaluate_light(0, |_| {});
+            let sees_across = space
+                .light_dependencies
+                .dependents(GridPoint::new(4, 0, 0))
+                .any(|cube| cube == GridPoint::new(0, 0, 0));
+            sees_across
+        };
+        // Cube 0 sees cube 4 only if the -X ray from it re-enters at +X.
+        assert!(!light_around_edge(Topology::BOUNDED));
+        assert!(light_around_edge(Topology::wrapping(true, false, false)));
+    }
+
+    #[test]
+    fn seed_sky_light() {
+        let mut space = Space::empty_positive(2, 3, 1);
+        space.set([0, 1, 0], Rgb::ONE).unwrap();
+        let former_sky_light = sky_light(&space);
+        space.set_physics(SpacePhysics {
+            sky_color: Rgb::new(1.0, 0.0, 0.0),
+            ..SpacePhysics::default()
+        });
+        let new_sky_light = sky_light(&space);
+
+        assert_eq!(space.seed_sky_light(), 4);
+        assert_eq!(space.get_lighting([0, 2, 0]), new_sky_light);
+        assert_eq!(space.get_lighting([0, 1, 0]), PackedLight::OPAQUE);
+        assert_eq!(space.get_lighting([0, 0, 0]), former_sky_light); // covered
+        for y in 0..3 {
+            assert_eq!(space.get_lighting([1, y, 0]), new_sky_light);
+        }
+
+        // The covered cube is updated before the seeded ones.
+        assert_eq!(
+            space.light_update_queue.pop().map(|r| r.cube),
+            Some(GridPoint::new(0, 0, 0))
+        );
+        assert_eq!(space.light_update_queue.peek_priority(), SEEDED_PRIORITY);
+    }
+
     /// There's a special case for setting cubes to opaque. That case must do the usual
     /// light update and notification.
     #[test]
@@ -585,6 +990,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn light_source_one_face() {
+        let light = Rgb::new(0.5, 1.0, 2.0);
+        let block = Block::builder()
+            .light_emission_faces(FaceMap::from_fn(|face| {
+                if face == Face::PX {
+                    light
+                } else {
+                    Rgb::ZERO
+                }
+            }))
+            .color(Rgba::new(1.0, 1.0, 1.0, 1.0))
+            .build();
+
+        let space = light_source_test_space(block);
+        let lit = space.get_lighting([2, 1, 1]).value();
+        assert!(
+            lit.red().into_inner() > 0.0 && lit.blue() > lit.red(),
+            "{:?}",
+            lit
+        );
+        // Nothing is emitted toward -X, and nothing else is lit to reflect light there.
+        assert_eq!(space.get_lighting([0, 1, 1]).value(), Rgb::ZERO);
+    }
+
+    #[test]
+    fn light_source_within_only() {
+        let light = Rgb::new(0.5, 1.0, 2.0);
+        let block = Block::builder()
+            .light_emission_faces(FaceMap {
+                within: light,
+                ..FaceMap::repeat(Rgb::ZERO)
+            })
+            .color(Rgba::new(1.0, 0.0, 0.0, 0.33))
+            .build();
+
+        let space = light_source_test_space(block);
+        assert_eq!(
+            space.get_lighting([1, 1, 1]).value(),
+            light * TRANSPARENT_BLOCK_COVERAGE
+        );
+    }
+
+    #[test]
+    fn light_source_within_ignored_if_opaque() {
+        let block = Block::builder()
+            .light_emission_faces(FaceMap {
+                within: Rgb::ONE,
+                ..FaceMap::repeat(Rgb::ZERO)
+            })
+            .color(Rgba::WHITE)
+            .build();
+
+        let space = light_source_test_space(block);
+        assert_eq!(space.get_lighting([1, 1, 1]), PackedLight::OPAQUE);
+        assert_eq!(space.get_lighting([2, 1, 1]).value(), Rgb::ZERO);
+    }
+
+    #[test]
+    #[cfg(feature = "content")]
+    fn emission_weighted_by_voxel_coverage() {
+        let mut universe = Universe::new();
+        install_demo_blocks(&mut universe).unwrap();
+        let lamp = BlockProvider::<DemoBlocks>::using(&universe).unwrap()[DemoBlocks::Lamp].clone();
+        let ev_lamp = lamp.evaluate().unwrap();
+        // The lamp is a sphere, which covers about π/4 of each face.
+        for &face in Face::ALL_SIX {
+            let ratio = effective_emission(&ev_lamp)[face].red().into_inner()
+                / ev_lamp.attributes.light_emission[face].red().into_inner();
+            assert!(
+                (ratio - std::f32::consts::FRAC_PI_4).abs() < 0.05,
+                "{:?} {}",
+                face,
+                ratio
+            );
+        }
+
+        // A single voxel covers only 1/resolution² of each face.
+        let speck = Block::builder()
+            .light_emission(Rgb::ONE)
+            .voxels_fn(&mut universe, 4, |cube| {
+                if cube == GridPoint::new(0, 0, 0) {
+                    Block::from(Rgba::WHITE)
+                } else {
+                    AIR
+                }
+            })
+            .unwrap()
+            .build();
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &speck).unwrap();
+        assert_eq!(
+            space.get_block_data([0, 0, 0]).light_emission.nx,
+            Rgb::ONE * (1. / 16.)
+        );
+    }
+
+    #[test]
+    fn emission_is_clamped() {
+        let block = Block::builder()
+            .light_emission(Rgb::new(-1.0, 1.0, f32::INFINITY))
+            .color(Rgba::WHITE)
+            .build();
+        let emission = effective_emission(&block.evaluate().unwrap());
+        assert_eq!(
+            emission.within,
+            Rgb::new(0.0, 1.0, PackedLight::max_value())
+        );
+
+        // Bright light is not saturated by storage well above sky level.
+        let bright = Rgb::from_luminance_nits(100_000.0);
+        assert_eq!(bright, Rgb::new(25.0, 25.0, 25.0));
+        let stored = PackedLight::from(bright).value().red().into_inner();
+        assert!((stored / 25.0 - 1.0).abs() < 0.05, "{}", stored);
+    }
+
     /// Helper to construct a space with LightPhysics set to None
     fn space_with_disabled_light() -> Space {
         let mut space = Space::empty_positive(1, 1, 1);
@@ -613,6 +1134,95 @@ mod tests {
         );
     }
 
+    #[test]
+    fn light_parameters_serialization() {
+        let physics = LightPhysics::Rays(LightRayParameters {
+            falloff: NotNan::new(2.0).unwrap(),
+            ..LightRayParameters::default()
+        });
+        let json = serde_json::to_string(&physics).unwrap();
+        assert_eq!(
+            serde_json::from_str::<LightPhysics>(&json).unwrap(),
+            physics
+        );
+
+        // Missing parameters are defaulted.
+        assert_eq!(
+            serde_json::from_str::<LightPhysics>(r#"{"Rays": {"maximum_distance": 10}}"#).unwrap(),
+            LightPhysics::Rays(LightRayParameters {
+                maximum_distance: 10,
+                ..LightRayParameters::default()
+            })
+        );
+
+        // Invalid parameters are rejected.
+        let error = serde_json::from_str::<LightPhysics>(r#"{"Rays": {"rays_per_face": 0}}"#)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("rays_per_face must be from 1 to 121"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn light_parameters_ray_count() {
+        let with_rays = |rays_per_face| LightRayParameters {
+            rays_per_face,
+            ..LightRayParameters::default()
+        };
+        assert_eq!(with_rays(1).ray_direction_step(), 1);
+        assert_eq!(with_rays(30).ray_direction_step(), 2);
+        assert_eq!(with_rays(121).ray_direction_step(), 5);
+        assert_eq!(with_rays(u16::MAX).ray_direction_step(), 5);
+        assert_eq!(LIGHT_RAYS[1].len(), 5usize.pow(3) - 3usize.pow(3));
+    }
+
+    #[test]
+    fn light_falloff_and_reflections() {
+        let light = Rgb::new(0.5, 1.0, 2.0);
+        let block = Block::builder()
+            .light_emission(light)
+            .color(Rgba::WHITE)
+            .build();
+        let lit_cube = |parameters: LightRayParameters| {
+            let mut space = Space::empty_positive(3, 3, 3);
+            space.set_physics(SpacePhysics {
+                sky_color: Rgb::ZERO,
+                light: LightPhysics::Rays(parameters),
+                ..Default::default()
+            });
+            space.set([1, 1, 1], &block).unwrap();
+            space.evaluate_light(0, |_| ());
+            space.get_lighting([2, 1, 1]).value()
+        };
+
+        let normal = lit_cube(LightRayParameters::default());
+        let falloff = lit_cube(LightRayParameters {
+            falloff: NotNan::new(2.0).unwrap(),
+            ..LightRayParameters::default()
+        });
+        assert!(
+            falloff.blue() < normal.blue() && falloff.blue() > notnan!(0.0),
+            "{:?} vs. {:?}",
+            falloff,
+            normal
+        );
+
+        // Without reflections, the light is only what comes directly from the emitter.
+        let no_reflections = lit_cube(LightRayParameters {
+            reflections: false,
+            ..LightRayParameters::default()
+        });
+        assert!(
+            no_reflections.blue() < normal.blue() && no_reflections.blue() > notnan!(0.0),
+            "{:?} vs. {:?}",
+            no_reflections,
+            normal
+        );
+    }
+
     // TODO: test sky lighting propagation onto blocks after quiescing
 
     // TODO: test a single semi-transparent block will receive and diffuse light
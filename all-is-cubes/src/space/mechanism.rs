@@ -0,0 +1,337 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Mechanism`], for groups of cubes, such as doors, which open and close together.
+
+use crate::block::Block;
+use crate::math::GridPoint;
+use crate::space::{Grid, GridArray, SetCubeError, Space, SpaceTransaction};
+use crate::transactions::Transaction as _;
+
+/// One of the two states of a [`Mechanism`].
+#[allow(clippy::exhaustive_enums)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MechanismState {
+    /// The state a mechanism is in when not operated, such as a shut door.
+    Closed,
+    /// The state a mechanism is in when operated, such as an open door.
+    Open,
+}
+
+impl MechanismState {
+    /// Returns the other state.
+    #[must_use]
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Closed => Self::Open,
+            Self::Open => Self::Closed,
+        }
+    }
+}
+
+/// A group of cubes, such as the two halves of a door or the panels of a hatch, which
+/// change between a closed and an open state together, always as a single
+/// transaction, so the group is never seen half open.
+///
+/// Each state is defined by the blocks occupying the mechanism's [`Grid`] in that
+/// state. The state of a mechanism in a [`Space`] is not stored separately, but is
+/// whichever state its cubes currently match; if they match neither, such as because
+/// part of a door was broken, the mechanism no longer operates.
+///
+/// Once added to a space with [`Space::add_mechanism`], a mechanism is opened and closed
+/// by using [`Tool::Activate`](crate::tools::Tool::Activate) on any of its cubes, and
+/// also opens when any of its cubes becomes next to a signal
+/// [source](crate::block::SignalRole::Source) or powered
+/// [wire](crate::block::SignalRole::Wire), and closes when none is.
+///
+/// ```
+/// use all_is_cubes::block::{Block, AIR};
+/// use all_is_cubes::math::Rgba;
+/// use all_is_cubes::space::{Grid, Mechanism, MechanismState, Space};
+/// use all_is_cubes::transactions::Transaction as _;
+///
+/// let door = Block::from(Rgba::new(0.5, 0.3, 0.1, 1.0));
+/// let mechanism = Mechanism::new(Grid::new([1, 0, 0], [1, 2, 1]), |_, state| match state {
+///     MechanismState::Closed => door.clone(),
+///     MechanismState::Open => AIR,
+/// });
+///
+/// let mut space = Space::empty_positive(3, 2, 1);
+/// space.add_mechanism(mechanism.clone(), MechanismState::Closed).unwrap();
+/// assert_eq!(space[[1, 1, 0]], door);
+///
+/// mechanism.transaction(MechanismState::Open).execute(&mut space).unwrap();
+/// assert_eq!(space[[1, 1, 0]], AIR);
+/// assert_eq!(mechanism.state_in(&space), Some(MechanismState::Open));
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Mechanism {
+    closed: GridArray<Block>,
+    open: GridArray<Block>,
+}
+
+impl Mechanism {
+    /// Constructs a mechanism occupying `grid`, whose block at each cube in each state
+    /// is given by `f`.
+    pub fn new(grid: Grid, mut f: impl FnMut(GridPoint, MechanismState) -> Block) -> Self {
+        Self {
+            closed: GridArray::from_fn(grid, |cube| f(cube, MechanismState::Closed)),
+            open: GridArray::from_fn(grid, |cube| f(cube, MechanismState::Open)),
+        }
+    }
+
+    /// Returns the cubes this mechanism occupies.
+    pub fn grid(&self) -> Grid {
+        self.closed.grid()
+    }
+
+    /// Returns the block at `cube` in the given state, or [`None`] if `cube` is outside
+    /// [`Self::grid`].
+    pub fn block(&self, state: MechanismState, cube: impl Into<GridPoint>) -> Option<&Block> {
+        self.blocks(state).get(cube)
+    }
+
+    fn blocks(&self, state: MechanismState) -> &GridArray<Block> {
+        match state {
+            MechanismState::Closed => &self.closed,
+            MechanismState::Open => &self.open,
+        }
+    }
+
+    /// Returns the state whose blocks all of this mechanism's cubes in `space` contain,
+    /// or [`None`] if there is no such state.
+    pub fn state_in(&self, space: &Space) -> Option<MechanismState> {
+        [MechanismState::Closed, MechanismState::Open]
+            .iter()
+            .copied()
+            .find(|&state| {
+                self.grid()
+                    .interior_iter()
+                    .all(|cube| space[cube] == self.blocks(state)[cube])
+            })
+    }
+
+    /// Returns a transaction which changes this mechanism to the state `to` from the
+    /// other state. The transaction fails if any cube of the mechanism does not contain
+    /// the block of the other state.
+    pub fn transaction(&self, to: MechanismState) -> SpaceTransaction {
+        let from = to.toggled();
+        self.grid()
+            .interior_iter()
+            .fold(SpaceTransaction::default(), |transaction, cube| {
+                let old = self.blocks(from)[cube].clone();
+                let new = &self.blocks(to)[cube];
+                // Cubes which are the same in both states are still checked, so that the
+                // mechanism is operated only if it is intact.
+                let new = if *new == old { None } else { Some(new.clone()) };
+                transaction
+                    .merge(SpaceTransaction::set_cube(cube, Some(old), new))
+                    .expect("can't happen: cubes are distinct")
+            })
+    }
+
+    /// Returns a transaction which changes this mechanism to the opposite of its current
+    /// state in `space`, or [`None`] if it is in neither state.
+    pub fn toggle_transaction(&self, space: &Space) -> Option<SpaceTransaction> {
+        self.state_in(space)
+            .map(|state| self.transaction(state.toggled()))
+    }
+}
+
+/// Errors from [`Space::add_mechanism`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum MechanismError {
+    /// The mechanism is not within the space.
+    #[error("{0:?} is outside the space")]
+    OutOfBounds(Grid),
+    /// The mechanism overlaps a mechanism already in the space.
+    #[error("{0:?} overlaps another mechanism")]
+    Overlap(Grid),
+    /// The mechanism's blocks could not be placed.
+    #[error("error placing mechanism: {0}")]
+    SetCube(#[from] SetCubeError),
+}
+
+/// The mechanisms in a [`Space`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Mechanisms {
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    mechanism: Mechanism,
+    /// Whether the mechanism was next to a signal when last checked.
+    powered: bool,
+}
+
+impl Space {
+    /// Adds `mechanism` to this space, placing the blocks of `state` in its cubes.
+    ///
+    /// Fails if the mechanism is not within the space or overlaps a mechanism already
+    /// added.
+    pub fn add_mechanism(
+        &mut self,
+        mechanism: Mechanism,
+        state: MechanismState,
+    ) -> Result<(), MechanismError> {
+        let grid = mechanism.grid();
+        if !self.grid().contains_grid(grid) {
+            return Err(MechanismError::OutOfBounds(grid));
+        }
+        if self
+            .mechanisms
+            .entries
+            .iter()
+            .any(|entry| entry.mechanism.grid().intersection(grid).is_some())
+        {
+            return Err(MechanismError::Overlap(grid));
+        }
+        self.fill(grid, |cube| mechanism.block(state, cube))?;
+        self.mechanisms.entries.push(Entry {
+            mechanism,
+            powered: false,
+        });
+        Ok(())
+    }
+
+    /// Returns the mechanism occupying `cube`, if there is one.
+    pub fn mechanism_at(&self, cube: impl Into<GridPoint>) -> Option<&Mechanism> {
+        let cube = self.wrap_cube(cube.into());
+        self.mechanisms
+            .entries
+            .iter()
+            .map(|entry| &entry.mechanism)
+            .find(|mechanism| mechanism.grid().contains_cube(cube))
+    }
+
+    /// Opens mechanisms which have become next to a signal, and closes those which no
+    /// longer are. Called from [`Space::step`].
+    pub(super) fn update_mechanisms(&mut self) {
+        if !self.signals.take_changed() {
+            return;
+        }
+        for index in 0..self.mechanisms.entries.len() {
+            let entry = &self.mechanisms.entries[index];
+            let powered = entry
+                .mechanism
+                .grid()
+                .interior_iter()
+                .any(|cube| self.signal_input(cube) > 0);
+            if powered == entry.powered {
+                continue;
+            }
+            let state = if powered {
+                MechanismState::Open
+            } else {
+                MechanismState::Closed
+            };
+            let transaction = entry.mechanism.transaction(state);
+            self.mechanisms.entries[index].powered = powered;
+            if let Err(error) = transaction.execute(self) {
+                // Most likely, the mechanism is already in that state, or was broken.
+                log::debug!("mechanism not operated by signal: {}", error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::Tick;
+    use crate::block::{SignalRole, AIR};
+    use crate::content::make_some_blocks;
+    use crate::math::Rgba;
+
+    fn door_mechanism(block: &Block) -> Mechanism {
+        Mechanism::new(Grid::new([1, 0, 0], [1, 2, 1]), |_, state| match state {
+            MechanismState::Closed => block.clone(),
+            MechanismState::Open => AIR,
+        })
+    }
+
+    #[test]
+    fn add_and_toggle() {
+        let [door] = make_some_blocks();
+        let mechanism = door_mechanism(&door);
+        let mut space = Space::empty_positive(3, 2, 1);
+        space
+            .add_mechanism(mechanism.clone(), MechanismState::Closed)
+            .unwrap();
+        assert_eq!(space.mechanism_at([1, 1, 0]), Some(&mechanism));
+        assert_eq!(space.mechanism_at([0, 0, 0]), None);
+        assert_eq!(mechanism.state_in(&space), Some(MechanismState::Closed));
+
+        mechanism
+            .toggle_transaction(&space)
+            .unwrap()
+            .execute(&mut space)
+            .unwrap();
+        assert_eq!(mechanism.state_in(&space), Some(MechanismState::Open));
+        assert_eq!(space[[1, 0, 0]], AIR);
+        assert_eq!(space[[1, 1, 0]], AIR);
+    }
+
+    #[test]
+    fn broken_mechanism_does_not_operate() {
+        let [door, other] = make_some_blocks();
+        let mechanism = door_mechanism(&door);
+        let mut space = Space::empty_positive(3, 2, 1);
+        space
+            .add_mechanism(mechanism.clone(), MechanismState::Closed)
+            .unwrap();
+        space.set([1, 1, 0], &other).unwrap();
+
+        assert_eq!(mechanism.state_in(&space), None);
+        assert_eq!(mechanism.toggle_transaction(&space), None);
+        // The transaction is atomic: the intact cube is not changed either.
+        assert!(mechanism
+            .transaction(MechanismState::Open)
+            .execute(&mut space)
+            .is_err());
+        assert_eq!(space[[1, 0, 0]], door);
+    }
+
+    #[test]
+    fn add_errors() {
+        let [door] = make_some_blocks();
+        let mut space = Space::empty_positive(3, 1, 1);
+        assert_eq!(
+            space.add_mechanism(door_mechanism(&door), MechanismState::Closed),
+            Err(MechanismError::OutOfBounds(Grid::new([1, 0, 0], [1, 2, 1])))
+        );
+
+        let single = Mechanism::new(Grid::new([1, 0, 0], [1, 1, 1]), |_, _| door.clone());
+        space
+            .add_mechanism(single.clone(), MechanismState::Closed)
+            .unwrap();
+        assert_eq!(
+            space.add_mechanism(single, MechanismState::Closed),
+            Err(MechanismError::Overlap(Grid::new([1, 0, 0], [1, 1, 1])))
+        );
+    }
+
+    #[test]
+    fn operated_by_signal() {
+        let [door] = make_some_blocks();
+        let switch = Block::builder()
+            .color(Rgba::WHITE)
+            .signal(SignalRole::Source(1))
+            .build();
+        let mechanism = door_mechanism(&door);
+        let mut space = Space::empty_positive(3, 2, 1);
+        space
+            .add_mechanism(mechanism.clone(), MechanismState::Closed)
+            .unwrap();
+
+        space.set([0, 1, 0], &switch).unwrap();
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(mechanism.state_in(&space), Some(MechanismState::Open));
+
+        space.set([0, 1, 0], AIR).unwrap();
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(mechanism.state_in(&space), Some(MechanismState::Closed));
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Neighborhood`], for examining the blocks around a cube.
+
+use cgmath::Vector3;
+
+use crate::block::{with_connections, Block, EvaluatedBlock};
+use crate::math::{Face, FaceMap, GridCoordinate, GridPoint, GridVector};
+use crate::space::{Space, SpaceBlockData};
+
+/// The blocks in the 3×3×3 cubes centered on one cube of a [`Space`], for rules which
+/// depend on a block's neighbors, such as fences and pipes which connect to adjacent
+/// blocks.
+///
+/// The blocks are looked up once, when the [`Neighborhood`] is created by
+/// [`Space::neighborhood`]; afterward, accessing them is only an array lookup. To
+/// examine many cubes in turn, use [`Neighborhood::move_to`], which reuses the lookups
+/// already made for cubes the old and new neighborhoods share; visiting cubes in the
+/// order of [`Grid::interior_iter`](crate::space::Grid::interior_iter) shares most of
+/// them.
+///
+/// Cubes outside the space are treated as [`SpaceBlockData::NOTHING`], as with
+/// [`Space::get_evaluated`].
+///
+/// ```
+/// use all_is_cubes::prelude::*;
+///
+/// let mut space = Space::empty_positive(3, 3, 3);
+/// space.set([1, 2, 1], Rgba::WHITE).unwrap();
+///
+/// let neighborhood = space.neighborhood(GridPoint::new(1, 1, 1));
+/// assert_eq!(neighborhood.face(Face::PY).block(), &space[[1, 2, 1]]);
+/// assert_eq!(neighborhood.face(Face::NY).block(), &AIR);
+/// assert!(neighborhood.get([0, 1, 0]).evaluated().opaque);
+/// ```
+#[derive(Clone, Copy)]
+pub struct Neighborhood<'a> {
+    space: &'a Space,
+    center: GridPoint,
+    /// Indexed by [`slot`] of the offset from `center`.
+    data: [&'a SpaceBlockData; 27],
+}
+
+impl<'a> Neighborhood<'a> {
+    pub(crate) fn new(space: &'a Space, center: GridPoint) -> Self {
+        let mut data = [&SpaceBlockData::NOTHING; 27];
+        for (index, entry) in data.iter_mut().enumerate() {
+            *entry = space.get_block_data(center + offset_of_slot(index));
+        }
+        Self {
+            space,
+            center,
+            data,
+        }
+    }
+
+    /// Returns the cube this neighborhood is centered on.
+    pub fn center(&self) -> GridPoint {
+        self.center
+    }
+
+    /// Returns the data of the block at `offset` from [`Self::center`].
+    ///
+    /// Panics if any component of `offset` is not -1, 0, or 1.
+    #[inline]
+    #[track_caller]
+    pub fn get(&self, offset: impl Into<GridVector>) -> &'a SpaceBlockData {
+        let offset = offset.into();
+        match slot(offset) {
+            Some(slot) => self.data[slot],
+            None => panic!("Neighborhood offset out of range: {:?}", offset),
+        }
+    }
+
+    /// Returns the data of the block adjacent to [`Self::center`] across `face`, or of
+    /// the center block itself if `face` is [`Face::Within`].
+    #[inline]
+    pub fn face(&self, face: Face) -> &'a SpaceBlockData {
+        self.get(face.normal_vector())
+    }
+
+    /// Returns the evaluations of the six blocks adjacent to [`Self::center`] (and the
+    /// center block itself, as [`FaceMap::within`]).
+    pub fn faces(&self) -> FaceMap<&'a EvaluatedBlock> {
+        FaceMap::from_fn(|face| self.face(face).evaluated())
+    }
+
+    /// Iterates over the 26 cubes surrounding [`Self::center`], not including the center,
+    /// as offsets from the center and block data.
+    pub fn surrounding(&self) -> impl Iterator<Item = (GridVector, &'a SpaceBlockData)> + '_ {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(index, &data)| (offset_of_slot(index), data))
+            .filter(|&(offset, _)| offset != Vector3::new(0, 0, 0))
+    }
+
+    /// Changes this neighborhood to be centered on `center`, reusing the block data
+    /// already looked up for cubes which are in both the old and new neighborhoods.
+    pub fn move_to(&mut self, center: GridPoint) {
+        let delta = center - self.center;
+        if delta == Vector3::new(0, 0, 0) {
+            return;
+        }
+        let old = self.data;
+        for (index, entry) in self.data.iter_mut().enumerate() {
+            let offset = offset_of_slot(index);
+            // Offset of the same cube from the old center.
+            *entry = match slot(offset + delta) {
+                Some(old_slot) => old[old_slot],
+                None => self.space.get_block_data(center + offset),
+            };
+        }
+        self.center = center;
+    }
+
+    /// If the center block has a [`Modifier::Connect`](crate::block::Modifier::Connect)
+    /// whose connections do not match its neighbors, returns the block with the correct
+    /// connections.
+    pub(crate) fn reconnected_center(&self) -> Option<Block> {
+        let disconnected = FaceMap::repeat(false);
+        let center = self.face(Face::Within).block();
+        let key = with_connections(center, disconnected)?;
+        let connected = FaceMap::from_fn(|face| {
+            face != Face::Within
+                && with_connections(self.face(face).block(), disconnected).as_ref() == Some(&key)
+        });
+        let reconnected = with_connections(center, connected)?;
+        (reconnected != *center).then(|| reconnected)
+    }
+}
+
+impl std::fmt::Debug for Neighborhood<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Neighborhood")
+            .field("center", &self.center)
+            .finish()
+    }
+}
+
+/// Returns the index into [`Neighborhood::data`] for the given offset, or [`None`] if
+/// it is outside the neighborhood.
+#[inline]
+fn slot(offset: GridVector) -> Option<usize> {
+    let mut slot = 0;
+    for axis in 0..3 {
+        let coordinate = offset[axis];
+        if !(-1..=1).contains(&coordinate) {
+            return None;
+        }
+        slot = slot * 3 + (coordinate + 1) as usize;
+    }
+    Some(slot)
+}
+
+/// Inverse of [`slot`].
+#[inline]
+fn offset_of_slot(slot: usize) -> GridVector {
+    let coordinate = |place: usize| (slot / place % 3) as GridCoordinate - 1;
+    Vector3::new(coordinate(9), coordinate(3), coordinate(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{Block, AIR};
+    use crate::content::make_some_blocks;
+    use crate::math::Rgba;
+
+    fn test_space() -> Space {
+        let mut space = Space::empty_positive(4, 3, 3);
+        for (i, cube) in space.grid().interior_iter().enumerate() {
+            space
+                .set(cube, Block::from(Rgba::new(i as f32 / 36., 0., 0., 1.)))
+                .unwrap();
+        }
+        space
+    }
+
+    #[test]
+    fn slot_roundtrip() {
+        for slot in 0..27 {
+            assert_eq!(super::slot(offset_of_slot(slot)), Some(slot));
+        }
+        assert_eq!(super::slot(Vector3::new(0, 2, 0)), None);
+    }
+
+    #[test]
+    fn matches_space() {
+        let space = test_space();
+        let center = GridPoint::new(1, 1, 1);
+        let neighborhood = space.neighborhood(center);
+        assert_eq!(neighborhood.center(), center);
+        for slot in 0..27 {
+            let offset = offset_of_slot(slot);
+            assert_eq!(neighborhood.get(offset).block(), &space[center + offset]);
+        }
+        assert_eq!(neighborhood.surrounding().count(), 26);
+        for (face, &evaluated) in neighborhood.faces().iter() {
+            assert_eq!(
+                evaluated,
+                space.get_evaluated(center + face.normal_vector())
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_bounds_is_nothing() {
+        let [block] = make_some_blocks();
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &block).unwrap();
+        let neighborhood = space.neighborhood(GridPoint::new(0, 0, 0));
+        assert_eq!(neighborhood.face(Face::Within).block(), &block);
+        for (_, data) in neighborhood.surrounding() {
+            assert_eq!(data.block(), &AIR);
+        }
+    }
+
+    #[test]
+    fn move_to_matches_new() {
+        let space = test_space();
+        let mut neighborhood = space.neighborhood(GridPoint::new(0, 0, 0));
+        for &center in &[
+            GridPoint::new(0, 0, 1),
+            GridPoint::new(1, 1, 1),
+            GridPoint::new(3, 1, 1),
+            GridPoint::new(-1, -1, -1),
+            GridPoint::new(100, 0, -100),
+            GridPoint::new(2, 2, 2),
+        ] {
+            neighborhood.move_to(center);
+            let fresh = space.neighborhood(center);
+            assert_eq!(neighborhood.center(), center);
+            for slot in 0..27 {
+                assert!(
+                    std::ptr::eq(neighborhood.data[slot], fresh.data[slot]),
+                    "{:?} {:?}",
+                    center,
+                    offset_of_slot(slot)
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Neighborhood offset out of range")]
+    fn get_out_of_range() {
+        let space = Space::empty_positive(1, 1, 1);
+        space.neighborhood(GridPoint::new(0, 0, 0)).get([2, 0, 0]);
+    }
+}
@@ -0,0 +1,572 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Prefab`], a captured region of a [`Space`] which can be placed repeatedly.
+
+use std::collections::HashMap;
+use std::convert::TryFrom as _;
+
+use cgmath::Vector4;
+
+use super::{Grid, LightPhysics, PackedLight, SetCubeError, Space, SpaceChange};
+use crate::block::{Block, BlockAttributes, BlockDef, AIR};
+use crate::math::{GridCoordinate, GridPoint, GridRotation, Rgba};
+use crate::save::{Migration, SaveError, SaveFormat};
+use crate::universe::{Name, URef, Universe};
+
+/// A region of blocks, and optionally their light, copied out of a [`Space`] so that it
+/// can be stamped into other spaces (or elsewhere in the same one), possibly rotated
+/// and with its blocks replaced.
+///
+/// The blocks are stored as a palette of distinct blocks plus, for each cube, an index
+/// into the palette, so replacing blocks with [`Prefab::map_blocks`] is cheap.
+///
+/// ```
+/// use all_is_cubes::block::Block;
+/// use all_is_cubes::math::{GridRotation, Rgba};
+/// use all_is_cubes::space::{Prefab, Space};
+///
+/// let block = Block::from(Rgba::WHITE);
+/// let mut source = Space::empty_positive(1, 2, 1);
+/// source.set([0, 1, 0], &block).unwrap();
+/// let prefab = Prefab::capture(&source, source.grid());
+///
+/// let mut destination = Space::empty_positive(4, 4, 4);
+/// prefab.stamp(&mut destination, [2, 0, 2], GridRotation::IDENTITY).unwrap();
+/// assert_eq!(destination[[2, 1, 2]], block);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prefab {
+    /// The distinct blocks in the prefab.
+    palette: Vec<Block>,
+    /// For each cube, an index into `palette`, in the order of [`Grid::interior_iter`]
+    /// over [`Self::grid`].
+    contents: Box<[u16]>,
+    /// Light values, if captured, parallel to `contents`.
+    light: Option<Box<[PackedLight]>>,
+    grid: Grid,
+}
+
+impl Prefab {
+    /// Copies the blocks in `region` of `space`.
+    ///
+    /// Parts of `region` outside of the space's bounds are filled with [`AIR`].
+    pub fn capture(space: &Space, region: Grid) -> Self {
+        let mut block_to_index: HashMap<&Block, u16> = HashMap::new();
+        let mut palette: Vec<Block> = Vec::new();
+        let contents = region
+            .interior_iter()
+            .map(|cube| {
+                let block = &space[cube];
+                *block_to_index.entry(block).or_insert_with(|| {
+                    palette.push(block.clone());
+                    u16::try_from(palette.len() - 1)
+                        .expect("a space cannot contain this many distinct blocks")
+                })
+            })
+            .collect();
+        Self {
+            palette,
+            contents,
+            light: None,
+            grid: Grid::new([0, 0, 0], region.size()),
+        }
+    }
+
+    /// Copies the blocks and light in `region` of `space`.
+    ///
+    /// The light is used as an initial estimate when the prefab is stamped, so that the
+    /// result looks reasonable before the destination space's lighting has caught up.
+    pub fn capture_with_light(space: &Space, region: Grid) -> Self {
+        Self {
+            light: Some(
+                region
+                    .interior_iter()
+                    .map(|cube| space.get_lighting(cube))
+                    .collect(),
+            ),
+            ..Self::capture(space, region)
+        }
+    }
+
+    /// Returns the bounds of the prefab, whose lower bounds are always zero.
+    pub fn grid(&self) -> Grid {
+        self.grid
+    }
+
+    /// Returns the distinct blocks this prefab contains.
+    pub fn palette(&self) -> &[Block] {
+        &self.palette
+    }
+
+    /// Returns whether this prefab carries light values.
+    pub fn has_light(&self) -> bool {
+        self.light.is_some()
+    }
+
+    /// Returns the block at `cube`, or [`None`] if it is out of bounds.
+    pub fn get(&self, cube: impl Into<GridPoint>) -> Option<&Block> {
+        self.grid
+            .index(cube)
+            .map(|index| &self.palette[usize::from(self.contents[index])])
+    }
+
+    /// Replaces each block of the palette with the result of `f`, such as to build the
+    /// same structure out of different materials.
+    pub fn map_blocks(mut self, mut f: impl FnMut(&Block) -> Block) -> Self {
+        for block in self.palette.iter_mut() {
+            *block = f(block);
+        }
+        self
+    }
+
+    /// Returns the region that [`Self::stamp`] with the same arguments will write to.
+    pub fn stamped_grid(&self, origin: impl Into<GridPoint>, rotation: GridRotation) -> Grid {
+        let rotated = self
+            .grid
+            .transform(rotation.to_rotation_matrix())
+            .expect("rotation can't fail");
+        rotated.translate(origin.into() - rotated.lower_bounds())
+    }
+
+    /// Writes the prefab into `space`, rotated by `rotation` (which is also applied to
+    /// each block), such that the lower bounds of the rotated prefab are at `origin`.
+    ///
+    /// Every cube of the prefab is written, including [`AIR`]. If the prefab has light,
+    /// it is copied into the space as the starting point for the space's own light
+    /// updates.
+    ///
+    /// If the prefab does not fit in the space, no changes are made and
+    /// [`SetCubeError::OutOfBounds`] is returned. Other errors stop the operation,
+    /// potentially leaving some cubes written.
+    pub fn stamp(
+        &self,
+        space: &mut Space,
+        origin: impl Into<GridPoint>,
+        rotation: GridRotation,
+    ) -> Result<(), SetCubeError> {
+        let destination = self.stamped_grid(origin, rotation);
+        if !space.grid().contains_grid(destination) {
+            return Err(SetCubeError::OutOfBounds(destination));
+        }
+        let rotation_matrix = rotation.to_rotation_matrix();
+        let offset = destination.lower_bounds()
+            - self
+                .grid
+                .transform(rotation_matrix)
+                .expect("rotation can't fail")
+                .lower_bounds();
+        let rotated_palette: Vec<Block> = self
+            .palette
+            .iter()
+            // Block::rotate() transforms the voxels by the inverse of its argument.
+            .map(|block| block.clone().rotate(rotation.inverse()))
+            .collect();
+
+        space.batch_changes(|space| {
+            for (index, cube) in self.grid.interior_iter().enumerate() {
+                let target = rotation_matrix.transform_cube(cube) + offset;
+                space.set(target, &rotated_palette[usize::from(self.contents[index])])?;
+                if let Some(light) = &self.light {
+                    // Only meaningful if the space is tracking light at all.
+                    if space.physics.light != LightPhysics::None {
+                        if space.lighting.set(target, light[index]) {
+                            space.notifier.notify(SpaceChange::Lighting(target));
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Converts this prefab to a serializable form.
+    ///
+    /// Returns an error if any block in the palette cannot be represented; see
+    /// [`PrefabBlockData`] for what can be.
+    pub fn to_data(&self) -> Result<PrefabData, PrefabError> {
+        Ok(PrefabData {
+            size: self.grid.size().into(),
+            palette: self
+                .palette
+                .iter()
+                .map(PrefabBlockData::from_block)
+                .collect::<Result<_, _>>()?,
+            contents: self.contents.to_vec(),
+            light: self
+                .light
+                .as_ref()
+                .map(|light| light.iter().map(|l| l.as_texel()).collect()),
+        })
+    }
+}
+
+/// Serializable form of a [`Prefab`], produced by [`Prefab::to_data`] and converted back
+/// by [`PrefabData::into_prefab`].
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[non_exhaustive]
+pub struct PrefabData {
+    /// Size of the prefab's [`Grid`].
+    pub size: [GridCoordinate; 3],
+    /// The distinct blocks in the prefab.
+    pub palette: Vec<PrefabBlockData>,
+    /// For each cube, in the order of [`Grid::interior_iter`], an index into `palette`.
+    pub contents: Vec<u16>,
+    /// For each cube, the light value in its texture format, if light was captured.
+    pub light: Option<Vec<[u8; 4]>>,
+}
+
+/// [`SaveFormat`] for [`PrefabData`].
+pub const PREFAB_FORMAT: SaveFormat = SaveFormat::new(
+    "all-is-cubes/prefab",
+    1,
+    &[Migration {
+        from_version: 0,
+        migrate: prefab_v0_to_v1,
+    }],
+);
+
+/// Version 0 is [`PrefabData`] as it was serialized before saved data was versioned;
+/// the data itself did not change.
+fn prefab_v0_to_v1(data: serde_json::Value) -> Result<serde_json::Value, String> {
+    Ok(data)
+}
+
+impl PrefabData {
+    /// Serializes this data as JSON in the current version of [`PREFAB_FORMAT`].
+    pub fn save(&self) -> Result<String, SaveError> {
+        PREFAB_FORMAT.save(self)
+    }
+
+    /// Deserializes data written by [`PrefabData::save`] in this or any earlier version.
+    pub fn load(text: &str) -> Result<Self, SaveError> {
+        PREFAB_FORMAT.load(text)
+    }
+
+    /// Converts the data back to a [`Prefab`], looking up named blocks in `universe`.
+    pub fn into_prefab(self, universe: &Universe) -> Result<Prefab, PrefabError> {
+        let grid = Grid::checked_new([0, 0, 0], self.size)
+            .map_err(|e| PrefabError::Invalid(e.to_string()))?;
+        if self.contents.len() != grid.volume() {
+            return Err(PrefabError::Invalid(format!(
+                "contents has {} elements but size {:?} requires {}",
+                self.contents.len(),
+                self.size,
+                grid.volume()
+            )));
+        }
+        if let Some(&index) = self
+            .contents
+            .iter()
+            .find(|&&index| usize::from(index) >= self.palette.len())
+        {
+            return Err(PrefabError::Invalid(format!(
+                "palette index {} out of range",
+                index
+            )));
+        }
+        let light = match self.light {
+            None => None,
+            Some(texels) => {
+                if texels.len() != grid.volume() {
+                    return Err(PrefabError::Invalid(format!(
+                        "light has {} elements but size {:?} requires {}",
+                        texels.len(),
+                        self.size,
+                        grid.volume()
+                    )));
+                }
+                Some(
+                    texels
+                        .into_iter()
+                        .map(|texel| {
+                            PackedLight::from_texel(texel).ok_or_else(|| {
+                                PrefabError::Invalid(format!("invalid light value {:?}", texel))
+                            })
+                        })
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+        };
+        Ok(Prefab {
+            palette: self
+                .palette
+                .into_iter()
+                .map(|data| data.into_block(universe))
+                .collect::<Result<_, _>>()?,
+            contents: self.contents.into(),
+            light,
+            grid,
+        })
+    }
+}
+
+/// Serializable form of a block in a [`PrefabData`] palette.
+///
+/// Only blocks without attributes or rotation, or references to named [`BlockDef`]s,
+/// can currently be represented.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[non_exhaustive]
+pub enum PrefabBlockData {
+    /// [`AIR`].
+    Air,
+    /// A [`Block::Atom`] with default attributes and the given RGBA color.
+    Color([f32; 4]),
+    /// A [`Block::Indirect`] referring to the [`BlockDef`] with the given
+    /// [`Name::Specific`] name.
+    Named(String),
+}
+
+impl PrefabBlockData {
+    pub(crate) fn from_block(block: &Block) -> Result<Self, PrefabError> {
+        match block {
+            _ if *block == AIR => Ok(Self::Air),
+            Block::Atom(attributes, color) if *attributes == BlockAttributes::default() => {
+                Ok(Self::Color((*color).into()))
+            }
+            Block::Indirect(block_def_ref) => match &**block_def_ref.name() {
+                Name::Specific(name) => Ok(Self::Named(name.clone())),
+                Name::Anonym(_) => Err(PrefabError::Unserializable(block.clone())),
+            },
+            _ => Err(PrefabError::Unserializable(block.clone())),
+        }
+    }
+
+    pub(crate) fn into_block(self, universe: &Universe) -> Result<Block, PrefabError> {
+        match self {
+            Self::Air => Ok(AIR),
+            Self::Color(color) => Rgba::try_from(Vector4::from(color))
+                .map(Block::from)
+                .map_err(|_| PrefabError::Invalid(format!("invalid color {:?}", color))),
+            Self::Named(name) => {
+                let name = Name::Specific(name);
+                let block_def_ref: Option<URef<BlockDef>> = universe.get(&name);
+                block_def_ref
+                    .map(Block::Indirect)
+                    .ok_or(PrefabError::MissingBlock(name))
+            }
+        }
+    }
+}
+
+/// Errors from converting a [`Prefab`] to or from [`PrefabData`].
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum PrefabError {
+    /// The prefab contains a block which [`PrefabBlockData`] cannot represent.
+    #[error("block cannot be serialized: {0:?}")]
+    Unserializable(Block),
+    /// The data refers to a block definition which is not in the universe.
+    #[error("block definition {0} not found")]
+    MissingBlock(Name),
+    /// The data is malformed.
+    #[error("invalid prefab data: {0}")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::make_some_blocks;
+    use crate::math::GridVector;
+    use crate::universe::UniverseIndex as _;
+
+    fn l_shape() -> (Space, [Block; 2]) {
+        let blocks = make_some_blocks::<2>();
+        let mut space = Space::empty_positive(2, 3, 1);
+        space.set([0, 0, 0], &blocks[0]).unwrap();
+        space.set([0, 1, 0], &blocks[0]).unwrap();
+        space.set([0, 2, 0], &blocks[0]).unwrap();
+        space.set([1, 0, 0], &blocks[1]).unwrap();
+        (space, blocks)
+    }
+
+    #[test]
+    fn capture_and_stamp() {
+        let (source, blocks) = l_shape();
+        let prefab = Prefab::capture(&source, source.grid());
+        assert_eq!(prefab.grid(), Grid::new([0, 0, 0], [2, 3, 1]));
+        assert_eq!(prefab.palette().len(), 3);
+        assert!(!prefab.has_light());
+
+        let mut destination = Space::empty_positive(5, 5, 5);
+        prefab
+            .stamp(&mut destination, [1, 1, 1], GridRotation::IDENTITY)
+            .unwrap();
+        for cube in source.grid().interior_iter() {
+            assert_eq!(source[cube], destination[cube + GridVector::new(1, 1, 1)]);
+        }
+        assert_eq!(destination[[2, 1, 1]], blocks[1]);
+    }
+
+    #[test]
+    fn capture_offset_region() {
+        let (source, blocks) = l_shape();
+        let prefab = Prefab::capture(&source, Grid::new([1, 0, 0], [2, 1, 1]));
+        assert_eq!(prefab.grid(), Grid::new([0, 0, 0], [2, 1, 1]));
+        assert_eq!(prefab.get([0, 0, 0]), Some(&blocks[1]));
+        // Outside the source space
+        assert_eq!(prefab.get([1, 0, 0]), Some(&AIR));
+        assert_eq!(prefab.get([2, 0, 0]), None);
+    }
+
+    #[test]
+    fn stamp_rotated() {
+        let (source, _) = l_shape();
+        let prefab = Prefab::capture(&source, source.grid());
+        let rotation = GridRotation::CLOCKWISE;
+        assert_eq!(
+            prefab.stamped_grid([0, 0, 0], rotation),
+            Grid::new([0, 0, 0], [1, 3, 2])
+        );
+
+        let mut destination = Space::empty_positive(1, 3, 2);
+        prefab.stamp(&mut destination, [0, 0, 0], rotation).unwrap();
+        // The column along +Y stays in place, and the foot at +X moves to +Z.
+        assert_eq!(
+            destination[[0, 0, 1]],
+            source[[1, 0, 0]].clone().rotate(rotation.inverse())
+        );
+        assert_eq!(destination[[0, 2, 0]], source[[0, 2, 0]]);
+    }
+
+    #[test]
+    fn stamp_out_of_bounds() {
+        let (source, _) = l_shape();
+        let prefab = Prefab::capture(&source, source.grid());
+        let mut destination = Space::empty_positive(2, 2, 2);
+        assert_eq!(
+            prefab.stamp(&mut destination, [0, 0, 0], GridRotation::IDENTITY),
+            Err(SetCubeError::OutOfBounds(Grid::new([0, 0, 0], [2, 3, 1])))
+        );
+        assert_eq!(destination[[0, 0, 0]], AIR);
+    }
+
+    #[test]
+    fn map_blocks() {
+        let (source, blocks) = l_shape();
+        let [replacement] = make_some_blocks::<1>();
+        let prefab = Prefab::capture(&source, source.grid()).map_blocks(|block| {
+            if *block == blocks[0] {
+                replacement.clone()
+            } else {
+                block.clone()
+            }
+        });
+        assert_eq!(prefab.get([0, 2, 0]), Some(&replacement));
+        assert_eq!(prefab.get([1, 0, 0]), Some(&blocks[1]));
+    }
+
+    #[test]
+    fn light_is_copied() {
+        let (mut source, _) = l_shape();
+        source.evaluate_light(0, |_| {});
+        let prefab = Prefab::capture_with_light(&source, source.grid());
+        assert!(prefab.has_light());
+
+        let mut destination = Space::empty_positive(2, 3, 1);
+        prefab
+            .stamp(&mut destination, [0, 0, 0], GridRotation::IDENTITY)
+            .unwrap();
+        for cube in source.grid().interior_iter() {
+            assert_eq!(source.get_lighting(cube), destination.get_lighting(cube));
+        }
+    }
+
+    #[test]
+    fn data_round_trip() {
+        let mut universe = Universe::new();
+        let [atom] = make_some_blocks::<1>();
+        let atom = Block::from(atom.evaluate().unwrap().color);
+        let named = Block::Indirect(
+            universe
+                .insert("named".into(), BlockDef::new(atom.clone()))
+                .unwrap(),
+        );
+        let mut space = Space::empty_positive(3, 1, 1);
+        space.set([0, 0, 0], &atom).unwrap();
+        space.set([1, 0, 0], &named).unwrap();
+        let prefab = Prefab::capture_with_light(&space, space.grid());
+
+        let data = prefab.to_data().unwrap();
+        assert_eq!(
+            data.palette,
+            vec![
+                PrefabBlockData::Color(atom.evaluate().unwrap().color.into()),
+                PrefabBlockData::Named("named".into()),
+                PrefabBlockData::Air,
+            ]
+        );
+        assert_eq!(data.into_prefab(&universe).unwrap(), prefab);
+
+        // Missing definition
+        assert_eq!(
+            prefab.to_data().unwrap().into_prefab(&Universe::new()),
+            Err(PrefabError::MissingBlock("named".into()))
+        );
+    }
+
+    #[test]
+    fn data_unserializable() {
+        let [block] = make_some_blocks::<1>();
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], &block).unwrap();
+        assert_eq!(
+            Prefab::capture(&space, space.grid()).to_data(),
+            Err(PrefabError::Unserializable(block))
+        );
+    }
+
+    #[test]
+    fn data_invalid() {
+        let data = PrefabData {
+            size: [2, 1, 1],
+            palette: vec![PrefabBlockData::Air],
+            contents: vec![0],
+            light: None,
+        };
+        assert!(matches!(
+            data.into_prefab(&Universe::new()),
+            Err(PrefabError::Invalid(_))
+        ));
+        let data = PrefabData {
+            size: [1, 1, 1],
+            palette: vec![PrefabBlockData::Air],
+            contents: vec![1],
+            light: None,
+        };
+        assert!(matches!(
+            data.into_prefab(&Universe::new()),
+            Err(PrefabError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn save_round_trip() {
+        let data = Prefab::capture_with_light(&l_shape().0, Grid::new([0, 0, 0], [1, 1, 1]))
+            .map_blocks(|_| Block::from(Rgba::WHITE))
+            .to_data()
+            .unwrap();
+        assert_eq!(PrefabData::load(&data.save().unwrap()).unwrap(), data);
+    }
+
+    /// Saved data from each previous version must still load.
+    #[test]
+    fn load_fixtures() {
+        for text in &[
+            include_str!("../../test-data/save/prefab-v0.json"),
+            include_str!("../../test-data/save/prefab-v1.json"),
+        ] {
+            let prefab = PrefabData::load(text)
+                .unwrap()
+                .into_prefab(&Universe::new())
+                .unwrap();
+            assert_eq!(prefab.grid(), Grid::new([0, 0, 0], [2, 1, 1]));
+            assert_eq!(
+                prefab.get([0, 0, 0]),
+                Some(&Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0)))
+            );
+            assert_eq!(prefab.get([1, 0, 0]), Some(&AIR));
+        }
+    }
+}
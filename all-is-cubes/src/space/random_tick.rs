@@ -0,0 +1,185 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Random ticks: performing [`RandomTickAction`]s of blocks in randomly chosen cubes.
+
+use rand::Rng as _;
+
+use crate::apps::Tick;
+use crate::block::RandomTickAction;
+use crate::math::{Face, GridPoint};
+use crate::space::Space;
+
+/// State of a [`Space`]'s random ticks, other than the random number generator, which
+/// is [`Space::rng_mut`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RandomTicks {
+    /// Fraction of a tick left over from previous steps, so that rates which are less
+    /// than one tick per step still produce ticks on average.
+    carry: f64,
+}
+
+impl Space {
+    /// Gives random ticks to cubes chosen at the rate of
+    /// [`SpacePhysics::random_tick_rate`](super::SpacePhysics::random_tick_rate), and
+    /// performs their blocks' actions. Called from [`Space::step`].
+    ///
+    /// Returns the number of cubes ticked and the number of those whose actions
+    /// changed the space.
+    pub(super) fn update_random_ticks(&mut self, tick: Tick) -> (usize, usize) {
+        let expected = self.grid.volume() as f64
+            * self.physics.random_tick_rate.into_inner()
+            * tick.delta_t.as_secs_f64()
+            + self.random_ticks.carry;
+        let count = expected.floor();
+        self.random_ticks.carry = expected - count;
+        let count = count as usize;
+
+        let mut acted = 0;
+        for _ in 0..count {
+            let cube = match self.grid.random_cube(&mut self.rng) {
+                Some(cube) => cube,
+                None => break,
+            };
+            if self.random_tick(cube) {
+                acted += 1;
+            }
+        }
+        (count, acted)
+    }
+
+    /// Performs the [`RandomTickAction`] of the block at `cube`, and returns whether
+    /// anything changed.
+    fn random_tick(&mut self, cube: GridPoint) -> bool {
+        let action = self.get_evaluated(cube).attributes.random_tick.clone();
+        let result = match action {
+            RandomTickAction::None => return false,
+            RandomTickAction::Become(block) => self.set(cube, *block),
+            RandomTickAction::SpreadTo(target) => {
+                let face = Face::ALL_SIX[self.rng.gen_range(0..6)];
+                let neighbor = self.wrap_cube(cube + face.normal_vector());
+                if !self.grid.contains_cube(neighbor) || self[neighbor] != *target {
+                    return false;
+                }
+                let block = self[cube].clone();
+                self.set(neighbor, block)
+            }
+        };
+        match result {
+            Ok(changed) => changed,
+            Err(error) => {
+                log::debug!("random tick action failed: {}", error);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{Block, AIR};
+    use crate::content::make_some_blocks;
+    use crate::math::NotNan;
+    use crate::space::SpacePhysics;
+
+    fn space_with_rate(wx: i32, rate: f64) -> Space {
+        let mut space = Space::empty_positive(wx, 1, 1);
+        space.set_physics(SpacePhysics {
+            random_tick_rate: NotNan::new(rate).unwrap(),
+            ..SpacePhysics::default()
+        });
+        space
+    }
+
+    fn growing_blocks() -> [Block; 2] {
+        let [seed, grown] = make_some_blocks();
+        let seed = Block::builder()
+            .color(seed.evaluate().unwrap().color)
+            .random_tick(RandomTickAction::Become(Box::new(grown.clone())))
+            .build();
+        [seed, grown]
+    }
+
+    #[test]
+    fn become_and_counts() {
+        let [seed, grown] = growing_blocks();
+        let mut space = space_with_rate(4, 0.25);
+        for x in 0..4 {
+            space.set([x, 0, 0], &seed).unwrap();
+        }
+
+        let (info, _) = space.step(None, Tick::arbitrary());
+        assert_eq!((info.random_ticks, info.random_tick_actions), (1, 1));
+        assert_eq!(
+            space
+                .grid()
+                .interior_iter()
+                .filter(|&c| space[c] == grown)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn fractional_rate() {
+        let [seed, grown] = growing_blocks();
+        let mut space = space_with_rate(1, 0.25);
+        space.set([0, 0, 0], &seed).unwrap();
+        for _ in 0..3 {
+            let (info, _) = space.step(None, Tick::arbitrary());
+            assert_eq!(info.random_ticks, 0);
+        }
+        assert_eq!(space[[0, 0, 0]], seed);
+        let (info, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(info.random_ticks, 1);
+        assert_eq!(space[[0, 0, 0]], grown);
+    }
+
+    #[test]
+    fn spread_to() {
+        let [grass, dirt] = make_some_blocks();
+        let grass = Block::builder()
+            .color(grass.evaluate().unwrap().color)
+            .random_tick(RandomTickAction::SpreadTo(Box::new(dirt.clone())))
+            .build();
+        let mut space = space_with_rate(3, 10.0);
+        space.set([0, 0, 0], &grass).unwrap();
+        space.set([1, 0, 0], &dirt).unwrap();
+        for _ in 0..20 {
+            let (_, _) = space.step(None, Tick::arbitrary());
+        }
+        assert_eq!(space[[0, 0, 0]], grass);
+        assert_eq!(space[[1, 0, 0]], grass);
+        // Air is not the block spread to, so it is unchanged.
+        assert_eq!(space[[2, 0, 0]], AIR);
+    }
+
+    #[test]
+    fn deterministic_with_seed() {
+        let [seed, _] = growing_blocks();
+        let run = |random_seed: u64| {
+            let mut space = space_with_rate(100, 0.25);
+            space.set_seed(random_seed);
+            space.fill_uniform(space.grid(), &seed).unwrap();
+            let (_, _) = space.step(None, Tick::arbitrary());
+            space
+                .grid()
+                .interior_iter()
+                .map(|cube| space[cube] == seed)
+                .collect::<Vec<bool>>()
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+    }
+
+    #[test]
+    fn paused() {
+        let [seed, _] = growing_blocks();
+        let mut space = space_with_rate(1, 10.0);
+        space.set([0, 0, 0], &seed).unwrap();
+        let (info, _) = space.step(None, Tick::arbitrary().pause());
+        assert_eq!(info.random_ticks, 0);
+        assert_eq!(space[[0, 0, 0]], seed);
+    }
+}
@@ -0,0 +1,425 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Transactions which edit whole regions of a [`Space`] at once: flood fill, and
+//! moving, copying, and rotating box-shaped regions.
+//!
+//! Every cube these transactions change is given its current block as a precondition,
+//! so they fail if the space has changed since they were constructed, and
+//! [`SpaceTransaction::inverse`] will undo them exactly.
+
+use cgmath::{EuclideanSpace as _, Transform as _};
+use std::collections::{HashMap, VecDeque};
+
+use crate::block::{Block, AIR};
+use crate::math::{Face, GridCoordinate, GridMatrix, GridPoint, GridRotation};
+use crate::space::{Grid, GridMask, Space, SpaceTransaction};
+use crate::transactions::Transaction as _;
+
+/// How [`SpaceTransaction::copy_region`] and related operations treat blocks already
+/// present where the copy is placed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Collision {
+    /// Every cube of the destination is replaced, including by air.
+    Replace,
+    /// Air in the source leaves the destination unchanged, and the operation fails with
+    /// [`RegionEditError::Collision`] if any other block would replace a block that is
+    /// not air.
+    Fail,
+}
+
+/// Errors from constructing region editing transactions such as
+/// [`SpaceTransaction::flood_fill`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum RegionEditError {
+    /// The region, or the region it would be copied to, is not within the space.
+    #[error("{0:?} is outside the space")]
+    OutOfBounds(Grid),
+    /// A block is in the way of the copy, with [`Collision::Fail`].
+    #[error("a block is in the way at {0:?}")]
+    Collision(GridPoint),
+    /// More cubes would be filled than the given limit.
+    #[error("more than {0} cubes would be filled")]
+    TooLarge(usize),
+    /// There is no rotation about the center of the region which moves whole cubes to
+    /// whole cubes.
+    #[error("{0:?} cannot be rotated about its center")]
+    NotRotatable(Grid),
+}
+
+impl Space {
+    /// Returns `start` and all cubes connected to it through faces of cubes that contain
+    /// the same block as `start`, in order of distance, or [`None`] if there are more
+    /// than `limit` such cubes.
+    ///
+    /// Connections follow the space's [`Topology`](crate::space::Topology), so a region
+    /// may continue across a wrapping edge. If `start` is outside the space, returns an
+    /// empty list.
+    ///
+    /// ```
+    /// use all_is_cubes::prelude::*;
+    ///
+    /// let mut space = Space::empty_positive(3, 1, 1);
+    /// space.set([1, 0, 0], Block::from(Rgba::WHITE)).unwrap();
+    /// assert_eq!(
+    ///     space.connected_cubes(GridPoint::new(0, 0, 0), 10),
+    ///     Some(vec![GridPoint::new(0, 0, 0)]),
+    /// );
+    /// assert_eq!(space.connected_cubes(GridPoint::new(0, 0, 0), 0), None);
+    /// ```
+    pub fn connected_cubes(&self, start: GridPoint, limit: usize) -> Option<Vec<GridPoint>> {
+        let block_index = match self.grid.index(start) {
+            Some(index) => self.contents[index],
+            None => return Some(Vec::new()),
+        };
+        if limit == 0 {
+            return None;
+        }
+        let mut found = vec![start];
+        let mut visited = GridMask::new(self.grid);
+        visited.insert(start);
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some(cube) = queue.pop_front() {
+            for &face in Face::ALL_SIX {
+                let neighbor = self.wrap_cube(cube + face.normal_vector());
+                let same = self
+                    .grid
+                    .index(neighbor)
+                    .map_or(false, |index| self.contents[index] == block_index);
+                if same && visited.insert(neighbor) {
+                    if found.len() >= limit {
+                        return None;
+                    }
+                    found.push(neighbor);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        Some(found)
+    }
+}
+
+impl SpaceTransaction {
+    /// Constructs a transaction which replaces the block at `start`, and every cube
+    /// [connected](Space::connected_cubes) to it containing the same block, with
+    /// `block`. Fails if there are more than `limit` such cubes, which prevents
+    /// accidentally filling, for example, all of the open air.
+    pub fn flood_fill(
+        space: &Space,
+        start: GridPoint,
+        block: Block,
+        limit: usize,
+    ) -> Result<Self, RegionEditError> {
+        if !space.grid().contains_cube(start) {
+            return Err(RegionEditError::OutOfBounds(Grid::single_cube(start)));
+        }
+        let cubes = space
+            .connected_cubes(start, limit)
+            .ok_or(RegionEditError::TooLarge(limit))?;
+        Ok(Self::replacing(
+            space,
+            cubes.into_iter().map(|cube| (cube, block.clone())),
+        ))
+    }
+
+    /// Constructs a transaction which copies `region` of `space` to `region`
+    /// transformed by `transform`, with each block rotated to match.
+    ///
+    /// Panics if `transform` scales or skews.
+    ///
+    /// ```
+    /// use all_is_cubes::block::{Block, AIR};
+    /// use all_is_cubes::math::{GridMatrix, Rgba};
+    /// use all_is_cubes::space::{Collision, Grid, Space, SpaceTransaction};
+    /// use all_is_cubes::transactions::Transaction as _;
+    ///
+    /// let block = Block::from(Rgba::WHITE);
+    /// let mut space = Space::empty_positive(4, 1, 1);
+    /// space.set([0, 0, 0], &block).unwrap();
+    /// let transaction = SpaceTransaction::copy_region(
+    ///     &space,
+    ///     Grid::new([0, 0, 0], [2, 1, 1]),
+    ///     GridMatrix::from_translation([2, 0, 0]),
+    ///     Collision::Fail,
+    /// )
+    /// .unwrap();
+    /// transaction.execute(&mut space).unwrap();
+    /// assert_eq!(space[[2, 0, 0]], block);
+    ///
+    /// // Undo it.
+    /// transaction.inverse().unwrap().execute(&mut space).unwrap();
+    /// assert_eq!(space[[2, 0, 0]], AIR);
+    /// ```
+    pub fn copy_region(
+        space: &Space,
+        region: Grid,
+        transform: GridMatrix,
+        collision: Collision,
+    ) -> Result<Self, RegionEditError> {
+        Self::transfer(space, region, transform, collision, false)
+    }
+
+    /// Constructs a transaction which moves the contents of `region` of `space` to
+    /// `region` transformed by `transform`, with each block rotated to match, leaving
+    /// air in the parts of `region` that are not overwritten.
+    ///
+    /// The destination may overlap `region`; blocks being moved do not count as
+    /// collisions with themselves.
+    ///
+    /// Panics if `transform` scales or skews.
+    pub fn move_region(
+        space: &Space,
+        region: Grid,
+        transform: GridMatrix,
+        collision: Collision,
+    ) -> Result<Self, RegionEditError> {
+        Self::transfer(space, region, transform, collision, true)
+    }
+
+    /// Constructs a transaction which rotates the contents of `region` of `space` about
+    /// its center. If the region is not symmetric under the rotation, the rotated
+    /// blocks occupy a differently shaped region with the same center, as if by
+    /// [`SpaceTransaction::move_region`].
+    pub fn rotate_region(
+        space: &Space,
+        region: Grid,
+        rotation: GridRotation,
+        collision: Collision,
+    ) -> Result<Self, RegionEditError> {
+        let transform = rotation
+            .to_matrix_about(region.lower_bounds() + region.upper_bounds().to_vec())
+            .ok_or(RegionEditError::NotRotatable(region))?;
+        Self::move_region(space, region, transform, collision)
+    }
+
+    fn transfer(
+        space: &Space,
+        region: Grid,
+        transform: GridMatrix,
+        collision: Collision,
+        remove_source: bool,
+    ) -> Result<Self, RegionEditError> {
+        if !space.grid().contains_grid(region) {
+            return Err(RegionEditError::OutOfBounds(region));
+        }
+        let destination = region
+            .transform(transform)
+            .expect("transform must not scale or skew");
+        if !space.grid().contains_grid(destination) {
+            return Err(RegionEditError::OutOfBounds(destination));
+        }
+        let (rotation, _) = transform
+            .decompose()
+            .expect("transform must not scale or skew");
+        // Block::rotate() transforms the voxels by the inverse of its argument.
+        let block_rotation = rotation.inverse();
+        let inverse = transform
+            .inverse_transform()
+            .expect("can't happen: rotation is invertible");
+
+        let mut changes: HashMap<GridPoint, Block> = HashMap::new();
+        if remove_source {
+            for cube in region.interior_iter() {
+                changes.insert(cube, AIR);
+            }
+        }
+        for cube in destination.interior_iter() {
+            let block = &space[inverse.transform_cube(cube)];
+            if collision == Collision::Fail {
+                if *block == AIR {
+                    continue;
+                }
+                let vacated = remove_source && region.contains_cube(cube);
+                if space[cube] != AIR && !vacated {
+                    return Err(RegionEditError::Collision(cube));
+                }
+            }
+            changes.insert(
+                cube,
+                if block_rotation == GridRotation::IDENTITY {
+                    block.clone()
+                } else {
+                    block.clone().rotate(block_rotation)
+                },
+            );
+        }
+        Ok(Self::replacing(space, changes))
+    }
+
+    /// Constructs a transaction which places each of the given blocks, with the blocks
+    /// currently in `space` as preconditions. Cubes which would not change are omitted.
+    fn replacing(space: &Space, changes: impl IntoIterator<Item = (GridPoint, Block)>) -> Self {
+        changes
+            .into_iter()
+            .filter(|(cube, new)| space[*cube] != *new)
+            .fold(Self::default(), |transaction, (cube, new)| {
+                transaction
+                    .merge(Self::set_cube(cube, Some(space[cube].clone()), Some(new)))
+                    .expect("can't happen: cubes are distinct")
+            })
+    }
+}
+
+/// Returns the region spanning the two given cubes, inclusive, as selected by the
+/// player picking two opposite corners.
+pub(crate) fn grid_between(a: GridPoint, b: GridPoint) -> Grid {
+    let lower = a.zip(b, GridCoordinate::min);
+    let upper = a.zip(b, GridCoordinate::max);
+    Grid::from_lower_upper(lower, upper + cgmath::Vector3::new(1, 1, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::{make_some_blocks, make_some_voxel_blocks};
+    use crate::space::{SpacePhysics, Topology};
+    use crate::universe::Universe;
+
+    #[test]
+    fn flood_fill_is_six_connected() {
+        let [a, b, c] = make_some_blocks();
+        let mut space = Space::empty_positive(3, 3, 1);
+        space.fill(space.grid(), |_| Some(&a)).unwrap();
+        // Divide the space with a diagonal of b.
+        for i in 0..3 {
+            space.set([i, 2 - i, 0], &b).unwrap();
+        }
+        SpaceTransaction::flood_fill(&space, GridPoint::new(0, 0, 0), c.clone(), 100)
+            .unwrap()
+            .execute(&mut space)
+            .unwrap();
+        assert_eq!(space[[0, 0, 0]], c);
+        assert_eq!(space[[1, 0, 0]], c);
+        assert_eq!(space[[0, 1, 0]], c);
+        assert_eq!(space[[1, 1, 0]], b);
+        assert_eq!(space[[2, 2, 0]], a);
+    }
+
+    #[test]
+    fn connected_cubes_across_wrapping_edge() {
+        let [a, b] = make_some_blocks();
+        let mut space = Space::empty_positive(4, 1, 1);
+        space.set_physics(SpacePhysics {
+            topology: Topology::wrapping(true, false, false),
+            ..SpacePhysics::default()
+        });
+        space.fill(space.grid(), |_| Some(&a)).unwrap();
+        space.set([2, 0, 0], &b).unwrap();
+        let mut found = space.connected_cubes(GridPoint::new(0, 0, 0), 10).unwrap();
+        found.sort_by_key(|cube| cube.x);
+        assert_eq!(
+            found,
+            vec![
+                GridPoint::new(0, 0, 0),
+                GridPoint::new(1, 0, 0),
+                GridPoint::new(3, 0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn flood_fill_limit() {
+        let [block] = make_some_blocks();
+        let space = Space::empty_positive(4, 4, 4);
+        assert_eq!(
+            SpaceTransaction::flood_fill(&space, GridPoint::new(0, 0, 0), block, 63),
+            Err(RegionEditError::TooLarge(63))
+        );
+    }
+
+    #[test]
+    fn move_overlapping_and_undo() {
+        let [a, b] = make_some_blocks();
+        let mut space = Space::empty_positive(4, 1, 1);
+        space.set([0, 0, 0], &a).unwrap();
+        space.set([1, 0, 0], &b).unwrap();
+        let transaction = SpaceTransaction::move_region(
+            &space,
+            Grid::new([0, 0, 0], [2, 1, 1]),
+            GridMatrix::from_translation([1, 0, 0]),
+            Collision::Fail,
+        )
+        .unwrap();
+        transaction.execute(&mut space).unwrap();
+        assert_eq!(space[[0, 0, 0]], AIR);
+        assert_eq!(space[[1, 0, 0]], a);
+        assert_eq!(space[[2, 0, 0]], b);
+
+        // The transaction cannot be repeated, since its preconditions no longer hold.
+        transaction.execute(&mut space).unwrap_err();
+
+        transaction.inverse().unwrap().execute(&mut space).unwrap();
+        assert_eq!(space[[0, 0, 0]], a);
+        assert_eq!(space[[1, 0, 0]], b);
+        assert_eq!(space[[2, 0, 0]], AIR);
+    }
+
+    #[test]
+    fn copy_collision() {
+        let [a, b] = make_some_blocks();
+        let mut space = Space::empty_positive(4, 1, 1);
+        space.set([0, 0, 0], &a).unwrap();
+        space.set([2, 0, 0], &b).unwrap();
+        space.set([3, 0, 0], &b).unwrap();
+        let region = Grid::new([0, 0, 0], [2, 1, 1]);
+        let transform = GridMatrix::from_translation([2, 0, 0]);
+        assert_eq!(
+            SpaceTransaction::copy_region(&space, region, transform, Collision::Fail),
+            Err(RegionEditError::Collision(GridPoint::new(2, 0, 0)))
+        );
+
+        // Air doesn't collide, or overwrite.
+        space.set([2, 0, 0], AIR).unwrap();
+        SpaceTransaction::copy_region(&space, region, transform, Collision::Fail)
+            .unwrap()
+            .execute(&mut space)
+            .unwrap();
+        assert_eq!(space[[2, 0, 0]], a);
+        assert_eq!(space[[3, 0, 0]], b);
+
+        SpaceTransaction::copy_region(&space, region, transform, Collision::Replace)
+            .unwrap()
+            .execute(&mut space)
+            .unwrap();
+        assert_eq!(space[[3, 0, 0]], AIR);
+    }
+
+    #[test]
+    fn rotate_region_rotates_blocks() {
+        let mut universe = Universe::new();
+        let [a] = make_some_voxel_blocks(&mut universe);
+        let mut space = Space::empty_positive(3, 1, 3);
+        space.set([0, 0, 0], &a).unwrap();
+        SpaceTransaction::rotate_region(
+            &space,
+            space.grid(),
+            GridRotation::CLOCKWISE,
+            Collision::Replace,
+        )
+        .unwrap()
+        .execute(&mut space)
+        .unwrap();
+        assert_eq!(space[[0, 0, 0]], AIR);
+        assert_eq!(
+            space[[2, 0, 0]],
+            a.clone().rotate(GridRotation::COUNTERCLOCKWISE)
+        );
+    }
+
+    #[test]
+    fn rotate_region_not_rotatable() {
+        let space = Space::empty_positive(2, 1, 3);
+        assert_eq!(
+            SpaceTransaction::rotate_region(
+                &space,
+                space.grid(),
+                GridRotation::CLOCKWISE,
+                Collision::Replace
+            ),
+            Err(RegionEditError::NotRotatable(space.grid()))
+        );
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Propagation of signals between blocks; see [`SignalRole`].
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::block::{Block, SignalLevel, SignalRole};
+use crate::math::{Face, GridCoordinate, GridPoint};
+use crate::space::Space;
+
+/// The signal state of a [`Space`].
+#[derive(Debug, Default)]
+pub(crate) struct Signals {
+    /// Level of every cube whose level is nonzero.
+    levels: HashMap<GridPoint, SignalLevel>,
+    /// Sinks whose level changed between zero and nonzero since the last step, and
+    /// which may therefore need replacing or restoring.
+    pending_sinks: HashSet<GridPoint>,
+    /// Powered sinks which have been replaced, and the original sink blocks to put back
+    /// when they are no longer powered.
+    replaced: HashMap<GridPoint, Block>,
+    /// Whether any levels may have changed since [`Signals::take_changed`] was last
+    /// called.
+    changed: bool,
+}
+
+impl Signals {
+    /// Returns whether any levels may have changed since the last call.
+    pub(super) fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+/// The part of a [`SignalRole`] which matters for propagation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Kind {
+    Inert,
+    Source(SignalLevel),
+    Wire,
+    Sink,
+}
+
+impl Space {
+    /// Returns the signal level at the given cube: for a
+    /// [source](SignalRole::Source), the level it produces; for a
+    /// [wire](SignalRole::Wire), the level it conducts; for a [sink](SignalRole::Sink),
+    /// the greatest level of its adjacent sources and wires; and zero otherwise.
+    ///
+    /// ```
+    /// use all_is_cubes::block::{Block, SignalRole};
+    /// use all_is_cubes::math::Rgba;
+    /// use all_is_cubes::space::Space;
+    ///
+    /// let source = Block::builder().color(Rgba::WHITE).signal(SignalRole::Source(3)).build();
+    /// let wire = Block::builder().color(Rgba::BLACK).signal(SignalRole::Wire).build();
+    ///
+    /// let mut space = Space::empty_positive(4, 1, 1);
+    /// space.set([0, 0, 0], &source).unwrap();
+    /// for x in 1..4 {
+    ///     space.set([x, 0, 0], &wire).unwrap();
+    /// }
+    /// assert_eq!(space.signal_level([0, 0, 0]), 3);
+    /// assert_eq!(space.signal_level([1, 0, 0]), 2);
+    /// assert_eq!(space.signal_level([2, 0, 0]), 1);
+    /// assert_eq!(space.signal_level([3, 0, 0]), 0);
+    /// ```
+    pub fn signal_level(&self, cube: impl Into<GridPoint>) -> SignalLevel {
+        let cube = self.wrap_cube(cube.into());
+        self.signals.levels.get(&cube).copied().unwrap_or(0)
+    }
+
+    /// Returns the greatest level of the sources and wires adjacent to `cube`, which is
+    /// the level received by a sink or [`Mechanism`](super::Mechanism) there.
+    pub(crate) fn signal_input(&self, cube: GridPoint) -> SignalLevel {
+        self.signal_neighbors(self.wrap_cube(cube))
+            .map(|neighbor| match self.signal_kind(neighbor) {
+                Kind::Source(level) => level,
+                Kind::Wire => self.signal_level(neighbor),
+                Kind::Sink | Kind::Inert => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn signal_kind(&self, cube: GridPoint) -> Kind {
+        if self.signals.replaced.contains_key(&cube) {
+            return Kind::Sink;
+        }
+        match self.get_evaluated(cube).attributes.signal {
+            SignalRole::None => Kind::Inert,
+            SignalRole::Source(level) => Kind::Source(level),
+            SignalRole::Wire => Kind::Wire,
+            SignalRole::Sink(_) => Kind::Sink,
+        }
+    }
+
+    /// Returns the six cubes adjacent to `cube`, wrapped according to the topology.
+    fn signal_neighbors(&self, cube: GridPoint) -> impl Iterator<Item = GridPoint> + '_ {
+        Face::ALL_SIX
+            .iter()
+            .map(move |face| self.wrap_cube(cube + face.normal_vector()))
+    }
+
+    /// Recomputes the signal levels which may have been changed by the block at
+    /// `cube` being replaced.
+    ///
+    /// Only the wires connected to `cube` and the sinks next to them or to `cube` can be
+    /// affected, so only those are recomputed, from scratch.
+    pub(super) fn signal_side_effects_of_set(&mut self, cube: GridPoint) {
+        let cube = self.wrap_cube(cube);
+        self.signals.changed = true;
+
+        // A sink which was replaced by something other than the signal is gone.
+        self.signals.replaced.remove(&cube);
+
+        // Find the network of wires touching the cube, or containing it.
+        let mut network: HashSet<GridPoint> = HashSet::new();
+        let mut to_visit: Vec<GridPoint> = self.signal_neighbors(cube).collect();
+        to_visit.push(cube);
+        while let Some(visiting) = to_visit.pop() {
+            if self.signal_kind(visiting) == Kind::Wire && network.insert(visiting) {
+                to_visit.extend(self.signal_neighbors(visiting));
+            }
+        }
+
+        // Recompute the wire levels, greatest first, so that each wire's level is final
+        // the first time it is reached.
+        for wire in network.iter() {
+            self.signals.levels.remove(wire);
+        }
+        // `GridPoint` is not `Ord`, so the heap contains arrays.
+        let mut heap: BinaryHeap<(SignalLevel, [GridCoordinate; 3])> = BinaryHeap::new();
+        for &wire in network.iter() {
+            for neighbor in self.signal_neighbors(wire) {
+                if let Kind::Source(level) = self.signal_kind(neighbor) {
+                    if level > 1 {
+                        heap.push((level - 1, wire.into()));
+                    }
+                }
+            }
+        }
+        while let Some((level, wire)) = heap.pop() {
+            let wire = GridPoint::from(wire);
+            if self.signals.levels.contains_key(&wire) {
+                continue;
+            }
+            self.signals.levels.insert(wire, level);
+            if level > 1 {
+                for neighbor in self.signal_neighbors(wire) {
+                    if network.contains(&neighbor) && !self.signals.levels.contains_key(&neighbor) {
+                        heap.push((level - 1, neighbor.into()));
+                    }
+                }
+            }
+        }
+
+        match self.signal_kind(cube) {
+            Kind::Source(level) if level > 0 => {
+                self.signals.levels.insert(cube, level);
+            }
+            Kind::Wire | Kind::Sink => {}
+            Kind::Source(_) | Kind::Inert => {
+                self.signals.levels.remove(&cube);
+            }
+        }
+
+        // Recompute the sinks which may be affected.
+        let mut sinks: Vec<GridPoint> = self.signal_neighbors(cube).collect();
+        sinks.push(cube);
+        for &wire in network.iter() {
+            sinks.extend(self.signal_neighbors(wire));
+        }
+        for sink in sinks {
+            if self.signal_kind(sink) != Kind::Sink {
+                continue;
+            }
+            let level = self.signal_input(sink);
+            let old_level = if level > 0 {
+                self.signals.levels.insert(sink, level)
+            } else {
+                self.signals.levels.remove(&sink)
+            };
+            if old_level.is_some() != (level > 0) {
+                self.signals.pending_sinks.insert(sink);
+            }
+        }
+    }
+
+    /// Replaces sinks which have become powered, and restores those which have become
+    /// unpowered. Called from [`Space::step`].
+    pub(super) fn update_signal_sinks(&mut self) {
+        if self.signals.pending_sinks.is_empty() {
+            return;
+        }
+        let pending: Vec<GridPoint> = self.signals.pending_sinks.drain().collect();
+        for cube in pending {
+            let result = if self.signal_level(cube) > 0 {
+                if self.signals.replaced.contains_key(&cube) {
+                    continue;
+                }
+                let replacement = match &self.get_evaluated(cube).attributes.signal {
+                    SignalRole::Sink(replacement) => Block::clone(replacement),
+                    _ => continue,
+                };
+                let original = self[cube].clone();
+                self.signals.replaced.insert(cube, original);
+                let result = self.set_and_connect(cube, replacement);
+                if result.is_err() {
+                    self.signals.replaced.remove(&cube);
+                }
+                result
+            } else {
+                match self.signals.replaced.remove(&cube) {
+                    Some(original) => self.set_and_connect(cube, original),
+                    None => continue,
+                }
+            };
+            if let Err(error) = result {
+                log::warn!("failed to operate signal sink at {:?}: {}", cube, error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::Tick;
+    use crate::block::AIR;
+    use crate::math::Rgba;
+    use crate::space::Topology;
+
+    fn source(level: SignalLevel) -> Block {
+        Block::builder()
+            .display_name("source")
+            .color(Rgba::WHITE)
+            .signal(SignalRole::Source(level))
+            .build()
+    }
+
+    fn wire() -> Block {
+        Block::builder()
+            .display_name("wire")
+            .color(Rgba::BLACK)
+            .signal(SignalRole::Wire)
+            .build()
+    }
+
+    fn lamp_off() -> Block {
+        Block::builder()
+            .display_name("lamp")
+            .color(Rgba::new(0.2, 0.2, 0.2, 1.0))
+            .signal(SignalRole::Sink(Box::new(lamp_on())))
+            .build()
+    }
+
+    fn lamp_on() -> Block {
+        Block::builder()
+            .display_name("lamp")
+            .color(Rgba::WHITE)
+            .light_emission(crate::math::Rgb::ONE)
+            .build()
+    }
+
+    fn levels(space: &Space) -> Vec<SignalLevel> {
+        space
+            .grid()
+            .interior_iter()
+            .map(|cube| space.signal_level(cube))
+            .collect()
+    }
+
+    #[test]
+    fn wire_decays_with_distance() {
+        let mut space = Space::empty_positive(6, 1, 1);
+        for x in 1..6 {
+            space.set([x, 0, 0], wire()).unwrap();
+        }
+        assert_eq!(levels(&space), vec![0; 6]);
+
+        space.set([0, 0, 0], source(4)).unwrap();
+        assert_eq!(levels(&space), vec![4, 3, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn strongest_source_wins() {
+        let mut space = Space::empty_positive(7, 1, 1);
+        for x in 1..6 {
+            space.set([x, 0, 0], wire()).unwrap();
+        }
+        space.set([0, 0, 0], source(3)).unwrap();
+        space.set([6, 0, 0], source(5)).unwrap();
+        assert_eq!(levels(&space), vec![3, 2, 1, 2, 3, 4, 5]);
+
+        // Removing a source removes its contribution.
+        space.set([6, 0, 0], AIR).unwrap();
+        assert_eq!(levels(&space), vec![3, 2, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn breaking_wire_disconnects() {
+        let mut space = Space::empty_positive(5, 1, 1);
+        space.set([0, 0, 0], source(10)).unwrap();
+        for x in 1..5 {
+            space.set([x, 0, 0], wire()).unwrap();
+        }
+        assert_eq!(levels(&space), vec![10, 9, 8, 7, 6]);
+
+        space.set([2, 0, 0], AIR).unwrap();
+        assert_eq!(levels(&space), vec![10, 9, 0, 0, 0]);
+
+        space.set([2, 0, 0], wire()).unwrap();
+        assert_eq!(levels(&space), vec![10, 9, 8, 7, 6]);
+    }
+
+    #[test]
+    fn sink_is_replaced_on_step() {
+        let mut space = Space::empty_positive(3, 1, 1);
+        space.set([1, 0, 0], wire()).unwrap();
+        space.set([2, 0, 0], lamp_off()).unwrap();
+
+        space.set([0, 0, 0], source(5)).unwrap();
+        assert_eq!(space.signal_level([2, 0, 0]), 4);
+        // Not replaced until the step.
+        assert_eq!(space[[2, 0, 0]], lamp_off());
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(space[[2, 0, 0]], lamp_on());
+        // Still acts as a sink while replaced.
+        assert_eq!(space.signal_level([2, 0, 0]), 4);
+
+        space.set([0, 0, 0], AIR).unwrap();
+        assert_eq!(space.signal_level([2, 0, 0]), 0);
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(space[[2, 0, 0]], lamp_off());
+    }
+
+    #[test]
+    fn replaced_sink_overwritten() {
+        let mut space = Space::empty_positive(2, 1, 1);
+        space.set([1, 0, 0], lamp_off()).unwrap();
+        space.set([0, 0, 0], source(1)).unwrap();
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(space[[1, 0, 0]], lamp_on());
+
+        // Placing something else in the powered sink's cube means it is not restored.
+        space.set([1, 0, 0], AIR).unwrap();
+        space.set([0, 0, 0], AIR).unwrap();
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(space[[1, 0, 0]], AIR);
+        assert_eq!(space.signal_level([1, 0, 0]), 0);
+    }
+
+    #[test]
+    fn paused_does_not_operate_sinks() {
+        let mut space = Space::empty_positive(2, 1, 1);
+        space.set([1, 0, 0], lamp_off()).unwrap();
+        space.set([0, 0, 0], source(1)).unwrap();
+        let (_, _) = space.step(None, Tick::arbitrary().pause());
+        assert_eq!(space[[1, 0, 0]], lamp_off());
+        let (_, _) = space.step(None, Tick::arbitrary());
+        assert_eq!(space[[1, 0, 0]], lamp_on());
+    }
+
+    #[test]
+    fn wraps_around_topology() {
+        let mut space = Space::empty_positive(4, 1, 1);
+        let mut physics = space.physics().clone();
+        physics.topology = Topology::wrapping(true, false, false);
+        space.set_physics(physics);
+        space.set([0, 0, 0], source(3)).unwrap();
+        space.set([3, 0, 0], wire()).unwrap();
+        assert_eq!(space.signal_level([3, 0, 0]), 2);
+        assert_eq!(space.signal_level([-1, 0, 0]), 2);
+    }
+
+    #[test]
+    fn fill_uniform_with_sources() {
+        let mut space = Space::empty_positive(2, 1, 1);
+        space.fill_uniform(space.grid(), source(7)).unwrap();
+        assert_eq!(levels(&space), vec![7, 7]);
+    }
+}
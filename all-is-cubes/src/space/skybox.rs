@@ -0,0 +1,225 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Skybox`], the appearance of everything outside a [`Space`](super::Space).
+
+use cgmath::Vector3;
+use std::sync::Arc;
+
+use crate::math::{Face, FaceMap, FreeCoordinate, Rgb};
+
+/// The appearance of whatever is outside a [`Space`](super::Space), as seen in
+/// directions where no blocks are; set by [`SpacePhysics::skybox`](super::SpacePhysics::skybox).
+///
+/// A skybox is a cube surrounding the viewer at infinite distance, one image per face.
+/// Face images are oriented as in OpenGL cube maps; see [`Skybox::direction_of`].
+/// The [`FaceMap::within`] element of any face map is ignored.
+///
+/// The skybox affects only what is displayed; light entering the space is still
+/// [`SpacePhysics::sky_color`](super::SpacePhysics::sky_color), which should be
+/// chosen to be roughly the average color of the skybox.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Skybox {
+    /// Each face is a vertical gradient between two colors.
+    Gradients(FaceMap<SkyGradient>),
+    /// Each face is an image of `resolution × resolution` texels, in row-major order.
+    ///
+    /// If any image has the wrong number of texels, the missing ones are black.
+    Images {
+        resolution: u16,
+        faces: FaceMap<Arc<[Rgb]>>,
+    },
+}
+
+/// A gradient on one face of a [`Skybox::Gradients`], varying with the elevation of
+/// the viewing direction: `below` at the bottom edge of a side face and `above` at the
+/// top edge. The top ([`Face::PY`]) and bottom ([`Face::NY`]) faces have no vertical
+/// extent, so they are uniformly `above` and `below` respectively.
+#[allow(clippy::exhaustive_structs)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SkyGradient {
+    pub below: Rgb,
+    pub above: Rgb,
+}
+
+impl SkyGradient {
+    /// A gradient which is the same color everywhere.
+    pub const fn uniform(color: Rgb) -> Self {
+        Self {
+            below: color,
+            above: color,
+        }
+    }
+}
+
+impl Skybox {
+    /// Returns the color seen when looking in `direction`, which need not be normalized.
+    ///
+    /// ```
+    /// use all_is_cubes::math::{FaceMap, Rgb};
+    /// use all_is_cubes::space::{SkyGradient, Skybox};
+    ///
+    /// let sky = Skybox::Gradients(FaceMap::repeat(SkyGradient {
+    ///     below: Rgb::ZERO,
+    ///     above: Rgb::ONE,
+    /// }));
+    /// assert_eq!(sky.sample([0., 1., 0.].into()), Rgb::ONE);
+    /// assert_eq!(sky.sample([0., -1., 0.].into()), Rgb::ZERO);
+    /// assert_eq!(sky.sample([1., 0., 0.].into()), Rgb::new(0.5, 0.5, 0.5));
+    /// ```
+    pub fn sample(&self, direction: Vector3<FreeCoordinate>) -> Rgb {
+        let (face, s, t) = cube_map_coordinates(direction);
+        match self {
+            Skybox::Gradients(faces) => {
+                let gradient = faces[face];
+                let elevation = match face {
+                    Face::PY => 1.0,
+                    Face::NY => 0.0,
+                    // On the side faces, t runs downward.
+                    _ => 1.0 - t,
+                };
+                gradient.below * (1.0 - elevation) as f32 + gradient.above * elevation as f32
+            }
+            Skybox::Images { resolution, faces } => {
+                let resolution = usize::from(*resolution);
+                let texel = |c: FreeCoordinate| {
+                    ((c * resolution as FreeCoordinate) as usize).min(resolution.saturating_sub(1))
+                };
+                faces[face]
+                    .get(texel(t) * resolution + texel(s))
+                    .copied()
+                    .unwrap_or(Rgb::ZERO)
+            }
+        }
+    }
+
+    /// Returns the direction (not normalized) in which the point `(s, t)` of the given
+    /// face's image is seen, where `s` and `t` range from 0 to 1 across the image
+    /// columns and rows respectively. This is the OpenGL cube map convention.
+    ///
+    /// Panics if `face` is [`Face::Within`].
+    pub fn direction_of(
+        face: Face,
+        s: FreeCoordinate,
+        t: FreeCoordinate,
+    ) -> Vector3<FreeCoordinate> {
+        let sc = s * 2.0 - 1.0;
+        let tc = t * 2.0 - 1.0;
+        match face {
+            Face::Within => panic!("Face::Within is not a face of a skybox"),
+            Face::PX => Vector3::new(1.0, -tc, -sc),
+            Face::NX => Vector3::new(-1.0, -tc, sc),
+            Face::PY => Vector3::new(sc, 1.0, tc),
+            Face::NY => Vector3::new(sc, -1.0, -tc),
+            Face::PZ => Vector3::new(sc, -tc, 1.0),
+            Face::NZ => Vector3::new(-sc, -tc, -1.0),
+        }
+    }
+
+    /// Renders one face of the skybox as a `resolution × resolution` image in
+    /// row-major order, suitable for uploading as one face of a cube map texture.
+    pub fn face_image(&self, face: Face, resolution: u16) -> Vec<Rgb> {
+        let scale = FreeCoordinate::from(resolution).recip();
+        let mut image = Vec::with_capacity(usize::from(resolution).pow(2));
+        for row in 0..resolution {
+            for column in 0..resolution {
+                image.push(self.sample(Self::direction_of(
+                    face,
+                    (FreeCoordinate::from(column) + 0.5) * scale,
+                    (FreeCoordinate::from(row) + 0.5) * scale,
+                )));
+            }
+        }
+        image
+    }
+}
+
+/// Inverse of [`Skybox::direction_of`]: finds the face and image coordinates at which
+/// `direction` points. Zero or NaN directions are treated as [`Face::PZ`].
+fn cube_map_coordinates(
+    direction: Vector3<FreeCoordinate>,
+) -> (Face, FreeCoordinate, FreeCoordinate) {
+    let Vector3 { x, y, z } = direction;
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+    let (face, sc, tc, major) = if ax >= ay && ax >= az && ax > 0.0 {
+        if x > 0.0 {
+            (Face::PX, -z, -y, ax)
+        } else {
+            (Face::NX, z, -y, ax)
+        }
+    } else if ay >= az && ay > 0.0 {
+        if y > 0.0 {
+            (Face::PY, x, z, ay)
+        } else {
+            (Face::NY, x, -z, ay)
+        }
+    } else if z < 0.0 {
+        (Face::NZ, -x, -y, az)
+    } else if az > 0.0 {
+        (Face::PZ, x, -y, az)
+    } else {
+        return (Face::PZ, 0.5, 0.5);
+    };
+    let to_unit = |c: FreeCoordinate| ((c / major + 1.0) * 0.5).max(0.0).min(1.0);
+    (face, to_unit(sc), to_unit(tc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates_round_trip() {
+        for &face in Face::ALL_SIX {
+            for &(s, t) in &[(0.5, 0.5), (0.25, 0.75), (0.9, 0.1)] {
+                let direction = Skybox::direction_of(face, s, t);
+                let (face2, s2, t2) = cube_map_coordinates(direction);
+                assert_eq!(face, face2, "direction {:?}", direction);
+                assert!((s - s2).abs() < 1e-9 && (t - t2).abs() < 1e-9);
+            }
+            // Center of each face is along its normal.
+            assert_eq!(Skybox::direction_of(face, 0.5, 0.5), face.normal_vector());
+        }
+    }
+
+    #[test]
+    fn degenerate_direction() {
+        let sky = Skybox::Gradients(FaceMap::from_fn(|face| {
+            SkyGradient::uniform(if face == Face::PZ {
+                Rgb::ONE
+            } else {
+                Rgb::ZERO
+            })
+        }));
+        assert_eq!(sky.sample(Vector3::new(0., 0., 0.)), Rgb::ONE);
+        assert_eq!(
+            sky.sample(Vector3::new(FreeCoordinate::NAN, 0., 0.)),
+            Rgb::ONE
+        );
+    }
+
+    #[test]
+    fn image_sampling() {
+        let red = Rgb::new(1.0, 0.0, 0.0);
+        let blue = Rgb::new(0.0, 0.0, 1.0);
+        // 2×2 image with red on the top row (t < 0.5) and blue on the bottom.
+        let image: Arc<[Rgb]> = vec![red, red, blue, blue].into();
+        let sky = Skybox::Images {
+            resolution: 2,
+            faces: FaceMap::repeat(image),
+        };
+        assert_eq!(sky.sample(Vector3::new(0.1, 0.5, -1.0)), red);
+        assert_eq!(sky.sample(Vector3::new(0.1, -0.5, -1.0)), blue);
+        assert_eq!(sky.face_image(Face::NZ, 2), vec![red, red, blue, blue]);
+    }
+
+    #[test]
+    fn image_wrong_size_is_black() {
+        let sky = Skybox::Images {
+            resolution: 4,
+            faces: FaceMap::repeat(Arc::from(vec![Rgb::ONE])),
+        };
+        assert_eq!(sky.sample(Vector3::new(0., 0., 1.)), Rgb::ZERO);
+    }
+}
@@ -4,13 +4,15 @@
 //! TODO: Maybe this file is too small
 
 use std::collections::btree_map::Entry::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt::Debug;
 
-use super::Space;
+use super::{SetCubeError, Space};
 use crate::behavior::BehaviorSetTransaction;
 use crate::block::Block;
+use crate::entity::{Entity, EntityId, EntityTransaction};
+use crate::item_drop::{DropId, ItemDrop};
 use crate::math::{GridCoordinate, GridPoint};
 use crate::transactions::PreconditionFailed;
 use crate::transactions::{Transaction, TransactionConflict, Transactional};
@@ -24,6 +26,16 @@ impl Transactional for Space {
 pub struct SpaceTransaction {
     cubes: BTreeMap<[GridCoordinate; 3], CubeTransaction>,
     behaviors: BehaviorSetTransaction<Space>,
+    /// Drops to be added to the space.
+    new_drops: Vec<ItemDrop>,
+    /// Numbers of items to be removed from existing drops.
+    take_drops: BTreeMap<DropId, u32>,
+    /// Entities to be added to the space.
+    new_entities: Vec<Entity>,
+    /// Existing entities to be removed.
+    remove_entities: BTreeSet<EntityId>,
+    /// Changes to existing entities.
+    modify_entities: BTreeMap<EntityId, EntityTransaction>,
 }
 
 impl SpaceTransaction {
@@ -53,11 +65,104 @@ impl SpaceTransaction {
             ..Default::default()
         }
     }
+
+    /// Construct a [`SpaceTransaction`] which adds an [`ItemDrop`] to the space.
+    pub fn add_item_drop(drop: ItemDrop) -> Self {
+        Self {
+            new_drops: vec![drop],
+            ..Default::default()
+        }
+    }
+
+    /// Construct a [`SpaceTransaction`] which adds an [`Entity`] to the space.
+    pub fn add_entity(entity: Entity) -> Self {
+        Self {
+            new_entities: vec![entity],
+            ..Default::default()
+        }
+    }
+
+    /// Construct a [`SpaceTransaction`] which removes the existing entity `id`.
+    ///
+    /// The transaction will fail if the entity no longer exists.
+    pub fn remove_entity(id: EntityId) -> Self {
+        let mut remove_entities = BTreeSet::new();
+        remove_entities.insert(id);
+        Self {
+            remove_entities,
+            ..Default::default()
+        }
+    }
+
+    /// Construct a [`SpaceTransaction`] which applies `transaction` to the existing
+    /// entity `id`.
+    ///
+    /// The transaction will fail if the entity no longer exists.
+    pub fn modify_entity(id: EntityId, transaction: EntityTransaction) -> Self {
+        let mut modify_entities = BTreeMap::new();
+        modify_entities.insert(id, transaction);
+        Self {
+            modify_entities,
+            ..Default::default()
+        }
+    }
+
+    /// Returns a transaction which undoes the effect of this one, if this transaction
+    /// only replaces blocks and specifies the previous block of every cube it replaces;
+    /// otherwise [`None`].
+    ///
+    /// Transactions from region editing operations such as
+    /// [`SpaceTransaction::move_region`] always meet this condition.
+    pub fn inverse(&self) -> Option<Self> {
+        if self.behaviors != Default::default()
+            || !self.new_drops.is_empty()
+            || !self.take_drops.is_empty()
+            || !self.new_entities.is_empty()
+            || !self.remove_entities.is_empty()
+            || !self.modify_entities.is_empty()
+        {
+            return None;
+        }
+        let mut cubes = BTreeMap::new();
+        for (&cube, CubeTransaction { old, new }) in &self.cubes {
+            match (old, new) {
+                (Some(old), Some(new)) => {
+                    cubes.insert(
+                        cube,
+                        CubeTransaction {
+                            old: Some(new.clone()),
+                            new: Some(old.clone()),
+                        },
+                    );
+                }
+                // Preconditions alone have nothing to undo.
+                (_, None) => {}
+                (None, Some(_)) => return None,
+            }
+        }
+        Some(Self {
+            cubes,
+            ..Default::default()
+        })
+    }
+
+    /// Construct a [`SpaceTransaction`] which removes `count` items from the existing
+    /// drop `id`, removing it entirely if none are left.
+    ///
+    /// The transaction will fail if the drop no longer exists or has fewer items.
+    pub fn take_item_drop(id: DropId, count: u32) -> Self {
+        let mut take_drops = BTreeMap::new();
+        take_drops.insert(id, count);
+        Self {
+            take_drops,
+            ..Default::default()
+        }
+    }
 }
 
 impl Transaction<Space> for SpaceTransaction {
-    type CommitCheck = ();
-    type MergeCheck = ();
+    type CommitCheck = BTreeMap<EntityId, <EntityTransaction as Transaction<Entity>>::CommitCheck>;
+    type MergeCheck = BTreeMap<EntityId, <EntityTransaction as Transaction<Entity>>::MergeCheck>;
     type Output = ();
 
     fn check(&self, space: &Space) -> Result<Self::CommitCheck, PreconditionFailed> {
@@ -68,14 +173,54 @@ impl Transaction<Space> for SpaceTransaction {
                 }
             }
         }
-        Ok(())
+        for (&id, &count) in &self.take_drops {
+            if !matches!(space.item_drops.get(id), Some(drop) if drop.count() >= count) {
+                return Err(PreconditionFailed {});
+            }
+        }
+        for &id in &self.remove_entities {
+            if space.entities.get(id).is_none() {
+                return Err(PreconditionFailed {});
+            }
+        }
+        let mut entity_checks = BTreeMap::new();
+        for (&id, transaction) in &self.modify_entities {
+            let entity = space.entities.get(id).ok_or(PreconditionFailed {})?;
+            entity_checks.insert(id, transaction.check(entity)?);
+        }
+        Ok(entity_checks)
     }
 
-    fn commit(&self, target: &mut Space, _check: Self::CommitCheck) -> Result<(), Box<dyn Error>> {
-        for (&cube, CubeTransaction { old: _, new }) in &self.cubes {
-            if let Some(new) = new {
-                target.set(cube, new)?;
+    fn commit(
+        &self,
+        target: &mut Space,
+        mut entity_checks: Self::CommitCheck,
+    ) -> Result<(), Box<dyn Error>> {
+        target.batch_changes(|target| {
+            for (&cube, CubeTransaction { old: _, new }) in &self.cubes {
+                if let Some(new) = new {
+                    target.set(cube, new)?;
+                }
             }
+            Ok::<(), SetCubeError>(())
+        })?;
+        for (&id, &count) in &self.take_drops {
+            // Already checked, so this cannot fail.
+            target.item_drops.take(id, count);
+        }
+        for drop in &self.new_drops {
+            target.item_drops.insert(drop.clone());
+        }
+        for (&id, transaction) in &self.modify_entities {
+            // Already checked, so these cannot fail.
+            let entity = target.entities.get_mut(id).unwrap();
+            transaction.commit(entity, entity_checks.remove(&id).unwrap())?;
+        }
+        for &id in &self.remove_entities {
+            target.entities.remove(id);
+        }
+        for entity in &self.new_entities {
+            target.entities.insert(entity.clone());
         }
         Ok(())
     }
@@ -95,10 +240,37 @@ impl Transaction<Space> for SpaceTransaction {
                 }
             }
         }
-        Ok(())
+        if self
+            .take_drops
+            .keys()
+            .any(|id| other.take_drops.contains_key(id))
+        {
+            // Two takers of the same drop may together want more than it has.
+            return Err(TransactionConflict {});
+        }
+        if self
+            .remove_entities
+            .iter()
+            .any(|id| other.remove_entities.contains(id) || other.modify_entities.contains_key(id))
+            || other
+                .remove_entities
+                .iter()
+                .any(|id| self.modify_entities.contains_key(id))
+        {
+            // Modifying an entity that is being removed is meaningless, and removing
+            // it twice is probably a mistake.
+            return Err(TransactionConflict {});
+        }
+        let mut entity_checks = BTreeMap::new();
+        for (&id, t1) in &self.modify_entities {
+            if let Some(t2) = other.modify_entities.get(&id) {
+                entity_checks.insert(id, t1.check_merge(t2)?);
+            }
+        }
+        Ok(entity_checks)
     }
 
-    fn commit_merge(mut self, other: Self, (): Self::MergeCheck) -> Self {
+    fn commit_merge(mut self, other: Self, mut entity_checks: Self::MergeCheck) -> Self {
         for (cube, t2) in other.cubes {
             match self.cubes.entry(cube) {
                 Occupied(mut entry) => {
@@ -115,6 +287,22 @@ impl Transaction<Space> for SpaceTransaction {
                 }
             }
         }
+        self.new_drops.extend(other.new_drops);
+        self.take_drops.extend(other.take_drops);
+        self.new_entities.extend(other.new_entities);
+        self.remove_entities.extend(other.remove_entities);
+        for (id, t2) in other.modify_entities {
+            match self.modify_entities.entry(id) {
+                Occupied(entry) => {
+                    let (id, t1) = entry.remove_entry();
+                    let merged = t1.commit_merge(t2, entity_checks.remove(&id).unwrap());
+                    self.modify_entities.insert(id, merged);
+                }
+                Vacant(entry) => {
+                    entry.insert(t2);
+                }
+            }
+        }
         self
     }
 }
@@ -130,6 +318,21 @@ impl Debug for SpaceTransaction {
                 txn,
             );
         }
+        if !self.new_drops.is_empty() {
+            ds.field("new_drops", &self.new_drops);
+        }
+        if !self.take_drops.is_empty() {
+            ds.field("take_drops", &self.take_drops);
+        }
+        if !self.new_entities.is_empty() {
+            ds.field("new_entities", &self.new_entities);
+        }
+        if !self.remove_entities.is_empty() {
+            ds.field("remove_entities", &self.remove_entities);
+        }
+        if !self.modify_entities.is_empty() {
+            ds.field("modify_entities", &self.modify_entities);
+        }
         ds.finish()
     }
 }
@@ -145,6 +348,7 @@ struct CubeTransaction {
 #[cfg(test)]
 mod tests {
     use crate::content::make_some_blocks;
+    use crate::tools::Tool;
     use crate::transactions::TransactionTester;
 
     use super::*;
@@ -208,6 +412,55 @@ mod tests {
         assert_eq!(t1.clone(), t1.clone().merge(t2).unwrap());
     }
 
+    #[test]
+    fn inverse() {
+        let [b1, b2] = make_some_blocks();
+        let t = SpaceTransaction::set_cube([0, 0, 0], Some(b1.clone()), Some(b2.clone()))
+            .merge(SpaceTransaction::set_cube(
+                [1, 0, 0],
+                Some(b1.clone()),
+                None,
+            ))
+            .unwrap();
+        assert_eq!(
+            t.inverse(),
+            Some(SpaceTransaction::set_cube(
+                [0, 0, 0],
+                Some(b2.clone()),
+                Some(b1.clone())
+            ))
+        );
+        assert_eq!(
+            SpaceTransaction::set_cube([0, 0, 0], None, Some(b1.clone())).inverse(),
+            None
+        );
+    }
+
+    #[test]
+    fn item_drops() {
+        let [block] = make_some_blocks();
+        let item = Tool::PlaceBlock(block);
+        let mut space = Space::empty_positive(1, 1, 1);
+        SpaceTransaction::add_item_drop(ItemDrop::new(item.clone(), 2, [0.5, 0.5, 0.5]))
+            .execute(&mut space)
+            .unwrap();
+        let (id, drop) = space.item_drops().iter().next().unwrap();
+        assert_eq!((drop.item(), drop.count()), (&item, 2));
+
+        // Taking the same drop twice in one transaction is a conflict.
+        SpaceTransaction::take_item_drop(id, 1)
+            .merge(SpaceTransaction::take_item_drop(id, 1))
+            .unwrap_err();
+
+        SpaceTransaction::take_item_drop(id, 3)
+            .execute(&mut space)
+            .unwrap_err();
+        SpaceTransaction::take_item_drop(id, 2)
+            .execute(&mut space)
+            .unwrap();
+        assert!(space.item_drops().is_empty());
+    }
+
     #[test]
     fn systematic() {
         let [b1, b2, b3] = make_some_blocks();
@@ -0,0 +1,599 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Copying regions of a [`Space`] with rotation, reflection, and repetition, and
+//! duplicating edits symmetrically.
+
+use cgmath::{EuclideanSpace as _, One as _, Transform as _};
+
+use crate::block::{Block, AIR};
+use crate::math::{Face, FaceMap, GridCoordinate, GridMatrix, GridPoint, GridRotation};
+use crate::space::{Grid, Prefab, SetCubeError, Space, SpaceTransaction};
+use crate::transactions::Transaction as _;
+
+impl Space {
+    /// Copies `source_region` of `source` into this space, transformed by `transform`,
+    /// with each block rotated to match. Parts of `source_region` outside of `source`
+    /// are copied as [`AIR`].
+    ///
+    /// Panics if `transform` scales or skews.
+    ///
+    /// ```
+    /// use all_is_cubes::block::Block;
+    /// use all_is_cubes::math::{GridMatrix, Rgba};
+    /// use all_is_cubes::space::{Grid, Space};
+    ///
+    /// let block = Block::from(Rgba::WHITE);
+    /// let mut source = Space::empty_positive(2, 1, 1);
+    /// source.set([1, 0, 0], &block).unwrap();
+    ///
+    /// let mut destination = Space::empty_positive(10, 10, 10);
+    /// destination
+    ///     .copy_from(&source, source.grid(), GridMatrix::from_translation([5, 5, 5]))
+    ///     .unwrap();
+    /// assert_eq!(destination[[6, 5, 5]], block);
+    /// ```
+    pub fn copy_from(
+        &mut self,
+        source: &Space,
+        source_region: Grid,
+        transform: GridMatrix,
+    ) -> Result<(), SetCubeError> {
+        self.fill_transformed(source_region, transform, |cube| &source[cube])
+    }
+
+    /// Copies `region` of this space to the other side of its `face`, reflected, so
+    /// that the two copies are mirror images of each other. Returns the region
+    /// containing both.
+    ///
+    /// If `shared_boundary` is true, then the layer of cubes on that face of `region` is
+    /// the plane of symmetry, and is left unchanged; otherwise the plane of symmetry is
+    /// the face itself. For example, to generate a plaza 9 cubes wide, generate one
+    /// 5 cubes wide and mirror it with a shared boundary.
+    ///
+    /// Panics if `face` is [`Face::Within`].
+    ///
+    /// ```
+    /// use all_is_cubes::prelude::*;
+    ///
+    /// let block = Block::from(Rgba::WHITE);
+    /// let mut space = Space::empty_positive(9, 1, 1);
+    /// space.set([0, 0, 0], &block).unwrap();
+    /// let plaza = space.mirror(Grid::new([0, 0, 0], [5, 1, 1]), Face::PX, true).unwrap();
+    /// assert_eq!(plaza, space.grid());
+    /// assert_eq!(space[[8, 0, 0]], block);
+    /// ```
+    pub fn mirror(
+        &mut self,
+        region: Grid,
+        face: Face,
+        shared_boundary: bool,
+    ) -> Result<Grid, SetCubeError> {
+        let axis = face.axis_number();
+        let source_region = if shared_boundary {
+            region.expand(FaceMap::from_fn(|f| if f == face { -1 } else { 0 }))
+        } else {
+            region
+        };
+        // In doubled coordinates, so that it may be the middle of a cube.
+        let mut doubled_center = GridPoint::new(0, 0, 0);
+        doubled_center[axis] = if face.is_positive() {
+            region.upper_bounds()[axis] * 2 - GridCoordinate::from(shared_boundary)
+        } else {
+            region.lower_bounds()[axis] * 2 + GridCoordinate::from(shared_boundary)
+        };
+        let mut basis = [Face::PX, Face::PY, Face::PZ];
+        basis[axis] = basis[axis].opposite();
+        let transform = GridRotation::from_basis(basis)
+            .to_matrix_about(doubled_center)
+            .expect("can't happen: reflection must be representable");
+
+        let reflected = source_region
+            .transform(transform)
+            .expect("can't happen: reflection failed");
+        if !self.grid().contains_grid(reflected) {
+            return Err(SetCubeError::OutOfBounds(reflected));
+        }
+        // The prefab's coordinates are relative to the region's lower corner.
+        let prefab = Prefab::capture(self, source_region);
+        let origin = source_region.lower_bounds().to_vec();
+        self.fill_transformed(source_region, transform, |cube| {
+            prefab.get(cube - origin).unwrap_or(&AIR)
+        })?;
+
+        let mut lower = region.lower_bounds();
+        let mut upper = region.upper_bounds();
+        lower[axis] = lower[axis].min(reflected.lower_bounds()[axis]);
+        upper[axis] = upper[axis].max(reflected.upper_bounds()[axis]);
+        Ok(Grid::from_lower_upper(lower, upper))
+    }
+
+    /// Copies `region` of this space repeatedly rotated by `rotation` about the point
+    /// `doubled_center / 2` (see [`GridRotation::to_matrix_about`]), until the rotation
+    /// cycles back to the original. For example, with [`GridRotation::CLOCKWISE`], one
+    /// quadrant of a square is copied to the other three quadrants.
+    ///
+    /// All of the copies are of the contents of `region` before any copying, and where
+    /// they overlap, the later rotations take precedence. To leave a shared boundary
+    /// unchanged, such as the center row of a square with an odd width, exclude it
+    /// from `region`.
+    ///
+    /// Panics if there is no rotation about that center which moves whole cubes to
+    /// whole cubes.
+    ///
+    /// ```
+    /// use all_is_cubes::block::Block;
+    /// use all_is_cubes::math::{GridPoint, GridRotation, Rgba};
+    /// use all_is_cubes::space::{Grid, Space};
+    ///
+    /// let block = Block::from(Rgba::WHITE);
+    /// let mut space = Space::empty_positive(4, 1, 4);
+    /// space.set([0, 0, 0], &block).unwrap();
+    /// space
+    ///     .rotate_copies(
+    ///         Grid::new([0, 0, 0], [2, 1, 2]),
+    ///         GridRotation::CLOCKWISE,
+    ///         GridPoint::new(4, 0, 4),
+    ///     )
+    ///     .unwrap();
+    /// for &corner in &[[0, 0, 0], [3, 0, 0], [0, 0, 3], [3, 0, 3]] {
+    ///     assert_eq!(space[corner], block);
+    /// }
+    /// ```
+    pub fn rotate_copies(
+        &mut self,
+        region: Grid,
+        rotation: GridRotation,
+        doubled_center: GridPoint,
+    ) -> Result<(), SetCubeError> {
+        let transforms: Vec<GridMatrix> = rotation
+            .iterate()
+            .skip(1)
+            .map(|r| {
+                r.to_matrix_about(doubled_center).unwrap_or_else(|| {
+                    panic!(
+                        "rotation {:?} about doubled center {:?} does not preserve cubes",
+                        r, doubled_center
+                    )
+                })
+            })
+            .collect();
+        for &transform in &transforms {
+            let destination = region.transform(transform).unwrap();
+            if !self.grid().contains_grid(destination) {
+                return Err(SetCubeError::OutOfBounds(destination));
+            }
+        }
+        // The prefab's coordinates are relative to the region's lower corner.
+        let prefab = Prefab::capture(self, region);
+        let origin = region.lower_bounds().to_vec();
+        for transform in transforms {
+            self.fill_transformed(region, transform, |cube| {
+                prefab.get(cube - origin).unwrap_or(&AIR)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Fills `destination` with copies of `region` of this space, repeated in every
+    /// direction, aligned so that `region` itself is one of the copies. Copies which
+    /// extend past the edges of `destination` are cut off.
+    ///
+    /// ```
+    /// use all_is_cubes::prelude::*;
+    ///
+    /// let block = Block::from(Rgba::WHITE);
+    /// let mut space = Space::empty_positive(10, 1, 1);
+    /// space.set([0, 0, 0], &block).unwrap();
+    /// space.tile(Grid::new([0, 0, 0], [3, 1, 1]), space.grid()).unwrap();
+    /// assert_eq!(space[[3, 0, 0]], block);
+    /// assert_eq!(space[[9, 0, 0]], block);
+    /// assert_ne!(space[[8, 0, 0]], block);
+    /// ```
+    pub fn tile(&mut self, region: Grid, destination: Grid) -> Result<(), SetCubeError> {
+        if region.volume() == 0 {
+            return Ok(());
+        }
+        let prefab = Prefab::capture(self, region);
+        let lower = region.lower_bounds();
+        let size = region.size();
+        self.fill(destination, |cube| {
+            let offset = cube - lower;
+            prefab.get(GridPoint::new(
+                offset.x.rem_euclid(size.x),
+                offset.y.rem_euclid(size.y),
+                offset.z.rem_euclid(size.z),
+            ))
+        })
+    }
+
+    /// Writes `source_region`, as read by `source`, transformed by `transform`.
+    fn fill_transformed<'a>(
+        &mut self,
+        source_region: Grid,
+        transform: GridMatrix,
+        source: impl Fn(GridPoint) -> &'a Block,
+    ) -> Result<(), SetCubeError> {
+        let (rotation, _) = transform
+            .decompose()
+            .expect("transform must not scale or skew");
+        // Block::rotate() transforms the voxels by the inverse of its argument.
+        let block_rotation = rotation.inverse();
+        let inverse = transform
+            .inverse_transform()
+            .expect("can't happen: rotation is invertible");
+        self.fill(
+            source_region
+                .transform(transform)
+                .expect("can't happen: rotation failed"),
+            |cube| {
+                let block = source(inverse.transform_cube(cube)).clone();
+                Some(if block_rotation == GridRotation::IDENTITY {
+                    block
+                } else {
+                    block.rotate(block_rotation)
+                })
+            },
+        )
+    }
+}
+
+/// A set of transformations under which edits to a [`Space`] are duplicated, such as
+/// mirroring across a plane or rotating about an axis, for building symmetric
+/// structures one cube at a time.
+///
+/// Symmetries are combined by chaining [`Symmetry::mirror`] and [`Symmetry::rotation`];
+/// each one applies to all of the copies produced by the ones before it.
+///
+/// ```
+/// use all_is_cubes::block::Block;
+/// use all_is_cubes::math::{GridPoint, Rgba};
+/// use all_is_cubes::space::{Space, Symmetry};
+/// use all_is_cubes::transactions::Transaction as _;
+///
+/// let block = Block::from(Rgba::WHITE);
+/// let mut space = Space::empty_positive(4, 1, 4);
+/// // Mirror across the planes X = 2 and Z = 2.
+/// let symmetry = Symmetry::none().mirror(0, 4).mirror(2, 4);
+/// symmetry
+///     .set_cube(space.grid(), GridPoint::new(0, 0, 1), None, Some(block.clone()))
+///     .execute(&mut space)
+///     .unwrap();
+/// for &cube in &[[0, 0, 1], [3, 0, 1], [0, 0, 2], [3, 0, 2]] {
+///     assert_eq!(space[cube], block);
+/// }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Symmetry {
+    /// Transformations producing each copy; the first is always the identity, and there
+    /// are no duplicates.
+    transforms: Vec<GridMatrix>,
+}
+
+impl Symmetry {
+    /// The symmetry which does not duplicate anything.
+    pub fn none() -> Self {
+        Self {
+            transforms: vec![GridMatrix::one()],
+        }
+    }
+
+    /// Adds reflection across the plane perpendicular to `axis` (0 for X, 1 for Y, 2 for
+    /// Z) at the coordinate `doubled_position / 2`.
+    ///
+    /// The position is doubled so that the plane may either lie between cubes (an even
+    /// value), so that every cube has a distinct mirror image, or pass through the
+    /// middle of a layer of cubes (an odd value), which are their own mirror images.
+    #[must_use]
+    pub fn mirror(self, axis: usize, doubled_position: GridCoordinate) -> Self {
+        let mut basis = [Face::PX, Face::PY, Face::PZ];
+        basis[axis] = basis[axis].opposite();
+        let mut doubled_center = GridPoint::new(0, 0, 0);
+        doubled_center[axis] = doubled_position;
+        self.with_generator(
+            GridRotation::from_basis(basis)
+                .to_matrix_about(doubled_center)
+                .expect("can't happen: reflection must be representable"),
+        )
+    }
+
+    /// Adds four-fold rotation about the line parallel to `axis` (0 for X, 1 for Y, 2 for
+    /// Z) passing through `doubled_center / 2` (see [`GridRotation::to_matrix_about`]).
+    ///
+    /// Panics if there is no such rotation which moves whole cubes to whole cubes; that
+    /// is, if the two coordinates of `doubled_center` other than `axis` are not both
+    /// even or both odd.
+    #[must_use]
+    pub fn rotation(self, axis: usize, doubled_center: GridPoint) -> Self {
+        let faces = [Face::PX, Face::PY, Face::PZ];
+        let b = (axis + 1) % 3;
+        let c = (axis + 2) % 3;
+        let mut basis = faces;
+        basis[b] = faces[c];
+        basis[c] = faces[b].opposite();
+        let rotation = GridRotation::from_basis(basis);
+        self.with_generator(rotation.to_matrix_about(doubled_center).unwrap_or_else(|| {
+            panic!(
+                "rotation {:?} about doubled center {:?} does not preserve cubes",
+                rotation, doubled_center
+            )
+        }))
+    }
+
+    /// Adds copies of every existing copy transformed by every power of `generator`.
+    fn with_generator(mut self, generator: GridMatrix) -> Self {
+        let mut powers = Vec::new();
+        let mut power = generator;
+        while power != GridMatrix::one() {
+            powers.push(power);
+            power = generator * power;
+        }
+        for existing in self.transforms.clone() {
+            for &power in &powers {
+                let transform = power * existing;
+                if !self.transforms.contains(&transform) {
+                    self.transforms.push(transform);
+                }
+            }
+        }
+        self
+    }
+
+    /// Returns the number of copies this symmetry makes of each edit, including the
+    /// original (though fewer may be made of cubes which are their own images).
+    pub fn copies(&self) -> usize {
+        self.transforms.len()
+    }
+
+    /// Returns the cubes which `cube` is duplicated to, starting with `cube` itself,
+    /// each with the rotation to apply to a block placed there.
+    ///
+    /// Where multiple copies land in the same cube, only the first is returned, so an
+    /// edit to a cube on a plane of symmetry is not modified by its own mirror image.
+    pub fn images(&self, cube: GridPoint) -> Vec<(GridPoint, GridRotation)> {
+        let mut images: Vec<(GridPoint, GridRotation)> = Vec::with_capacity(self.copies());
+        for transform in &self.transforms {
+            let image = transform.transform_cube(cube);
+            if images.iter().all(|&(existing, _)| existing != image) {
+                let (rotation, _) = transform
+                    .decompose()
+                    .expect("can't happen: symmetry must not scale or skew");
+                images.push((image, rotation));
+            }
+        }
+        images
+    }
+
+    /// Constructs a transaction which performs
+    /// [`SpaceTransaction::set_cube(cube, old, new)`](SpaceTransaction::set_cube) and
+    /// also places `new`, rotated to match, at each image of `cube`.
+    ///
+    /// The `old` precondition is checked only at `cube` itself, since the rest of the
+    /// space need not already be symmetric. Images which are outside of `bounds`
+    /// (normally the grid of the space to be modified) are omitted, rather than causing
+    /// the transaction to fail.
+    pub fn set_cube(
+        &self,
+        bounds: Grid,
+        cube: GridPoint,
+        old: Option<Block>,
+        new: Option<Block>,
+    ) -> SpaceTransaction {
+        let mut transaction = SpaceTransaction::set_cube(cube, old, new.clone());
+        if let Some(new) = new {
+            for (image, rotation) in self.images(cube).into_iter().skip(1) {
+                if !bounds.contains_cube(image) {
+                    continue;
+                }
+                // Block::rotate() transforms the voxels by the inverse of its argument.
+                let block_rotation = rotation.inverse();
+                let block = if block_rotation == GridRotation::IDENTITY {
+                    new.clone()
+                } else {
+                    new.clone().rotate(block_rotation)
+                };
+                transaction = transaction
+                    .merge(SpaceTransaction::set_cube(image, None, Some(block)))
+                    .expect("can't happen: images are distinct cubes");
+            }
+        }
+        transaction
+    }
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::{make_some_blocks, make_some_voxel_blocks};
+    use crate::universe::Universe;
+
+    #[test]
+    fn mirror_without_shared_boundary() {
+        let [a, b] = make_some_blocks();
+        let mut space = Space::empty(Grid::new([-3, 0, 0], [6, 1, 1]));
+        space.set([-3, 0, 0], &a).unwrap();
+        space.set([-1, 0, 0], &b).unwrap();
+        let region = space
+            .mirror(Grid::new([-3, 0, 0], [3, 1, 1]), Face::PX, false)
+            .unwrap();
+        assert_eq!(region, space.grid());
+        assert_eq!(space[[0, 0, 0]], b);
+        assert_eq!(space[[1, 0, 0]], AIR);
+        assert_eq!(space[[2, 0, 0]], a);
+    }
+
+    #[test]
+    fn mirror_shared_boundary_unchanged() {
+        // A rotated block on the plane of symmetry stays as it was.
+        let mut universe = Universe::new();
+        let [a, b] = make_some_voxel_blocks(&mut universe);
+        let a = a.rotate(GridRotation::CLOCKWISE);
+        let mut space = Space::empty_positive(1, 5, 1);
+        space.set([0, 2, 0], &a).unwrap();
+        space.set([0, 3, 0], &b).unwrap();
+        let region = space
+            .mirror(Grid::new([0, 2, 0], [1, 3, 1]), Face::NY, true)
+            .unwrap();
+        assert_eq!(region, space.grid());
+        assert_eq!(space[[0, 2, 0]], a);
+        assert_eq!(
+            space[[0, 1, 0]],
+            b.clone()
+                .rotate(GridRotation::from_basis([Face::PX, Face::NY, Face::PZ]))
+        );
+        assert_eq!(space[[0, 0, 0]], AIR);
+    }
+
+    #[test]
+    fn mirror_out_of_bounds() {
+        let mut space = Space::empty_positive(2, 2, 2);
+        assert_eq!(
+            space.mirror(space.grid(), Face::PZ, false),
+            Err(SetCubeError::OutOfBounds(Grid::new([0, 0, 2], [2, 2, 2])))
+        );
+    }
+
+    #[test]
+    fn rotate_copies_rotates_blocks() {
+        let mut universe = Universe::new();
+        let [a] = make_some_voxel_blocks(&mut universe);
+        let mut space = Space::empty_positive(3, 1, 3);
+        space.set([2, 0, 0], &a).unwrap();
+        // Rotate the edge strip about the center cube, not including it.
+        space
+            .rotate_copies(
+                Grid::new([1, 0, 0], [2, 1, 1]),
+                GridRotation::CLOCKWISE,
+                GridPoint::new(3, 0, 3),
+            )
+            .unwrap();
+        let mut cube = GridPoint::new(2, 0, 0);
+        let matrix = GridRotation::CLOCKWISE
+            .to_matrix_about(GridPoint::new(3, 0, 3))
+            .unwrap();
+        for rotation in GridRotation::CLOCKWISE.iterate() {
+            let expected = if rotation == GridRotation::IDENTITY {
+                a.clone()
+            } else {
+                a.clone().rotate(rotation.inverse())
+            };
+            assert_eq!(space[cube], expected, "{:?}", cube);
+            cube = matrix.transform_cube(cube);
+        }
+        assert_eq!(space[[1, 0, 1]], AIR);
+    }
+
+    #[test]
+    fn tile_with_negative_offset() {
+        let [a, b] = make_some_blocks();
+        let mut space = Space::empty(Grid::new([-5, 0, 0], [10, 1, 1]));
+        space.set([0, 0, 0], &a).unwrap();
+        space.set([1, 0, 0], &b).unwrap();
+        space
+            .tile(Grid::new([0, 0, 0], [2, 1, 1]), space.grid())
+            .unwrap();
+        for x in -5i32..5 {
+            let expected = if x.rem_euclid(2) == 0 { &a } else { &b };
+            assert_eq!(&space[[x, 0, 0]], expected, "{}", x);
+        }
+    }
+
+    #[test]
+    fn symmetry_rotation_images() {
+        let symmetry = Symmetry::none().rotation(1, GridPoint::new(5, 0, 5));
+        assert_eq!(symmetry.copies(), 4);
+        let images = symmetry.images(GridPoint::new(0, 3, 1));
+        let cubes: Vec<GridPoint> = images.iter().map(|&(cube, _)| cube).collect();
+        assert_eq!(cubes.len(), 4);
+        for &expected in &[[0, 3, 1], [1, 3, 4], [4, 3, 3], [3, 3, 0]] {
+            assert!(cubes.contains(&GridPoint::from(expected)), "{:?}", cubes);
+        }
+        assert_eq!(images[0], (GridPoint::new(0, 3, 1), GridRotation::IDENTITY));
+        // The center column is its own image.
+        assert_eq!(symmetry.images(GridPoint::new(2, 0, 2)).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not preserve cubes")]
+    fn symmetry_rotation_off_grid() {
+        let _ = Symmetry::none().rotation(1, GridPoint::new(4, 0, 5));
+    }
+
+    #[test]
+    fn symmetry_combined_mirror_and_rotation() {
+        let symmetry = Symmetry::none()
+            .mirror(0, 4)
+            .rotation(1, GridPoint::new(4, 0, 4));
+        // The symmetries of a square.
+        assert_eq!(symmetry.copies(), 8);
+        assert_eq!(symmetry.images(GridPoint::new(0, 0, 1)).len(), 8);
+        // Cubes on a diagonal are mirrored onto each other.
+        assert_eq!(symmetry.images(GridPoint::new(0, 0, 0)).len(), 4);
+    }
+
+    #[test]
+    fn symmetry_set_cube_rotates_blocks() {
+        let mut universe = Universe::new();
+        let [a] = make_some_voxel_blocks(&mut universe);
+        let mut space = Space::empty_positive(2, 1, 1);
+        Symmetry::none()
+            .mirror(0, 2)
+            .set_cube(space.grid(), GridPoint::new(0, 0, 0), None, Some(a.clone()))
+            .execute(&mut space)
+            .unwrap();
+        assert_eq!(space[[0, 0, 0]], a);
+        assert_eq!(
+            space[[1, 0, 0]],
+            a.clone()
+                .rotate(GridRotation::from_basis([Face::NX, Face::PY, Face::PZ]))
+        );
+    }
+
+    #[test]
+    fn symmetry_set_cube_precondition_and_bounds() {
+        let [a, b] = make_some_blocks();
+        let mut space = Space::empty_positive(3, 1, 1);
+        space.set([2, 0, 0], &b).unwrap();
+        // Mirror across X = 3; the image of [2, 0, 0] is outside the space.
+        let symmetry = Symmetry::none().mirror(0, 6);
+        symmetry
+            .set_cube(
+                space.grid(),
+                GridPoint::new(2, 0, 0),
+                Some(b.clone()),
+                Some(a.clone()),
+            )
+            .execute(&mut space)
+            .unwrap();
+        assert_eq!(space[[2, 0, 0]], a);
+
+        // Mirror across X = 1.5; the precondition is only checked at the original cube.
+        let symmetry = Symmetry::none().mirror(0, 3);
+        symmetry
+            .set_cube(
+                space.grid(),
+                GridPoint::new(0, 0, 0),
+                Some(AIR),
+                Some(b.clone()),
+            )
+            .execute(&mut space)
+            .unwrap();
+        assert_eq!(space[[0, 0, 0]], b);
+        assert_eq!(space[[2, 0, 0]], b);
+        symmetry
+            .set_cube(
+                space.grid(),
+                GridPoint::new(0, 0, 0),
+                Some(AIR),
+                Some(a.clone()),
+            )
+            .execute(&mut space)
+            .unwrap_err();
+    }
+}
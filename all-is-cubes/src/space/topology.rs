@@ -0,0 +1,259 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Topology`] of a [`Space`](super::Space): which of its edges connect to each other.
+
+use cgmath::Point3;
+use std::convert::TryFrom;
+
+use crate::math::{FreeCoordinate, GridCoordinate, GridPoint};
+use crate::space::Grid;
+
+/// Specifies, for each axis of a [`Space`](super::Space), whether the space is bounded
+/// along that axis, or wraps around so that leaving one side of its [`Grid`] enters the
+/// opposite side, as in a video game world which is a cylinder (one axis wrapping)
+/// or a torus (two or three axes wrapping).
+///
+/// In a wrapping space, cube coordinates outside the grid along a wrapped axis are
+/// treated as equivalent to the coordinates inside it which are congruent modulo the
+/// grid size, for reading and writing blocks ([`Space::set`](super::Space::set),
+/// [`Space::get_evaluated`](super::Space::get_evaluated), etc.), and hence for physics
+/// collision; and light and raytraced images follow rays across the edges.
+///
+/// A *spherical* topology cannot be expressed by wrapping axes of a box (the edges of
+/// a sphere's map join with a half turn rather than a translation), so it is not
+/// offered.
+///
+/// The positions of [`Body`](crate::physics::Body)s colliding with the space are
+/// wrapped too (see [`Self::wrap_point`]), so that they stay within the grid.
+///
+/// Current limitation: the mesh-based renderer (`all_is_cubes::lum`) draws only the
+/// space's own grid, not the surrounding repetitions of it, though its lighting does
+/// follow the wrapping.
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Topology {
+    wrap: [bool; 3],
+}
+
+impl Topology {
+    /// A topology in which no axis wraps; there is nothing outside the space's grid.
+    /// This is the default.
+    pub const BOUNDED: Self = Self { wrap: [false; 3] };
+
+    /// Constructs a topology which wraps along each axis for which the corresponding
+    /// argument is `true`.
+    ///
+    /// ```
+    /// use all_is_cubes::space::Topology;
+    ///
+    /// // A world which can be circumnavigated east–west, but not north–south.
+    /// let cylinder = Topology::wrapping(true, false, false);
+    /// assert!(cylinder.wraps(0));
+    /// assert!(!cylinder.wraps(2));
+    /// ```
+    pub const fn wrapping(x: bool, y: bool, z: bool) -> Self {
+        Self { wrap: [x, y, z] }
+    }
+
+    /// Returns whether the given axis (0 for X, 1 for Y, 2 for Z) wraps.
+    ///
+    /// Panics if `axis` is greater than 2.
+    pub fn wraps(self, axis: usize) -> bool {
+        self.wrap[axis]
+    }
+
+    /// Returns whether no axis wraps; equivalent to `self == Topology::BOUNDED`.
+    pub fn is_bounded(self) -> bool {
+        self == Self::BOUNDED
+    }
+
+    /// Returns the cube of `grid` which `cube` is equivalent to under this topology:
+    /// coordinates along wrapped axes are reduced modulo the size of `grid`, and other
+    /// coordinates are unchanged (so the result may be outside `grid` if `cube` was
+    /// outside it along an unwrapped axis).
+    ///
+    /// ```
+    /// use all_is_cubes::cgmath::Point3;
+    /// use all_is_cubes::space::{Grid, Topology};
+    ///
+    /// let grid = Grid::new([0, 0, 0], [10, 10, 10]);
+    /// let topology = Topology::wrapping(true, false, false);
+    /// assert_eq!(topology.wrap_cube(grid, Point3::new(-1, 5, 5)), Point3::new(9, 5, 5));
+    /// assert_eq!(topology.wrap_cube(grid, Point3::new(13, 5, 5)), Point3::new(3, 5, 5));
+    /// assert_eq!(topology.wrap_cube(grid, Point3::new(5, -1, 5)), Point3::new(5, -1, 5));
+    /// ```
+    #[inline]
+    pub fn wrap_cube(self, grid: Grid, mut cube: GridPoint) -> GridPoint {
+        if self.is_bounded() {
+            return cube;
+        }
+        let lower = grid.lower_bounds();
+        let size = grid.size();
+        for axis in 0..3 {
+            if self.wrap[axis] && size[axis] > 0 {
+                // Compute in i64 since the difference may overflow GridCoordinate.
+                let offset = (i64::from(cube[axis]) - i64::from(lower[axis]))
+                    .rem_euclid(i64::from(size[axis]));
+                // Cannot fail since 0 ≤ offset < size.
+                cube[axis] = lower[axis] + GridCoordinate::try_from(offset).unwrap();
+            }
+        }
+        cube
+    }
+
+    /// Returns the point of `grid` which `point` is equivalent to under this topology,
+    /// as [`Self::wrap_cube`] does for cubes.
+    ///
+    /// ```
+    /// use all_is_cubes::cgmath::Point3;
+    /// use all_is_cubes::space::{Grid, Topology};
+    ///
+    /// let grid = Grid::new([0, 0, 0], [10, 10, 10]);
+    /// let topology = Topology::wrapping(true, false, false);
+    /// assert_eq!(
+    ///     topology.wrap_point(grid, Point3::new(-0.25, 5.0, 5.0)),
+    ///     Point3::new(9.75, 5.0, 5.0),
+    /// );
+    /// ```
+    pub fn wrap_point(
+        self,
+        grid: Grid,
+        mut point: Point3<FreeCoordinate>,
+    ) -> Point3<FreeCoordinate> {
+        if self.is_bounded() {
+            return point;
+        }
+        let lower = grid.lower_bounds();
+        let size = grid.size();
+        for axis in 0..3 {
+            if self.wrap[axis] && size[axis] > 0 {
+                let lower = FreeCoordinate::from(lower[axis]);
+                point[axis] =
+                    lower + (point[axis] - lower).rem_euclid(FreeCoordinate::from(size[axis]));
+            }
+        }
+        point
+    }
+
+    /// Returns a [`Grid`] suitable for
+    /// [`Raycaster::within_grid`](crate::raycast::Raycaster::within_grid) when tracing
+    /// rays, no longer than `distance`, through a space with this topology and grid:
+    /// `grid` expanded along the wrapped axes so that the rays are not cut off at its
+    /// edges. The cubes the rays pass through must then be passed through
+    /// [`Self::wrap_cube`].
+    ///
+    /// If the expanded grid would be too large to represent, returns `grid` unchanged,
+    /// so rays stop at its edges as if it did not wrap.
+    pub(crate) fn raycast_grid(self, grid: Grid, distance: FreeCoordinate) -> Grid {
+        if self.is_bounded() {
+            return grid;
+        }
+        // Saturating float-to-int conversion; also maps NaN to 0.
+        let distance = i64::from((distance.ceil().max(0.) as GridCoordinate).saturating_add(1));
+        let mut lower = grid.lower_bounds();
+        let mut size = grid.size();
+        for axis in 0..3 {
+            if self.wrap[axis] {
+                let expanded_lower = GridCoordinate::try_from(i64::from(lower[axis]) - distance);
+                let expanded_size = GridCoordinate::try_from(i64::from(size[axis]) + distance * 2);
+                match (expanded_lower, expanded_size) {
+                    (Ok(l), Ok(s)) => {
+                        lower[axis] = l;
+                        size[axis] = s;
+                    }
+                    _ => return grid,
+                }
+            }
+        }
+        Grid::checked_new(lower, size).unwrap_or(grid)
+    }
+}
+
+impl std::fmt::Debug for Topology {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_bounded() {
+            write!(f, "Bounded")
+        } else {
+            let names = ["x", "y", "z"];
+            write!(f, "Wrapping(")?;
+            let mut first = true;
+            for (&wrap, name) in self.wrap.iter().zip(names.iter()) {
+                if wrap {
+                    if !first {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", name)?;
+                    first = false;
+                }
+            }
+            write!(f, ")")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_cube_bounded() {
+        let grid = Grid::new([0, 0, 0], [3, 3, 3]);
+        let cube = Point3::new(-5, 10, 100);
+        assert_eq!(Topology::BOUNDED.wrap_cube(grid, cube), cube);
+    }
+
+    #[test]
+    fn wrap_cube_all_axes() {
+        let grid = Grid::new([-2, 10, 0], [4, 3, 1]);
+        let topology = Topology::wrapping(true, true, true);
+        assert_eq!(
+            topology.wrap_cube(grid, Point3::new(-3, 9, 7)),
+            Point3::new(1, 12, 0)
+        );
+        assert_eq!(
+            topology.wrap_cube(grid, Point3::new(2, 13, -1)),
+            Point3::new(-2, 10, 0)
+        );
+        // Cubes within the grid are unchanged.
+        for cube in grid.interior_iter() {
+            assert_eq!(topology.wrap_cube(grid, cube), cube);
+        }
+    }
+
+    #[test]
+    fn wrap_cube_extreme_coordinates() {
+        let grid = Grid::new([-5, 0, 0], [7, 1, 1]);
+        let topology = Topology::wrapping(true, false, false);
+        for &x in &[GridCoordinate::MIN, GridCoordinate::MAX] {
+            let wrapped = topology.wrap_cube(grid, Point3::new(x, 0, 0));
+            assert!(grid.contains_cube(wrapped), "{:?}", wrapped);
+        }
+    }
+
+    #[test]
+    fn wrap_cube_empty_axis() {
+        let grid = Grid::new([0, 0, 0], [0, 1, 1]);
+        let topology = Topology::wrapping(true, false, false);
+        let cube = Point3::new(4, 0, 0);
+        assert_eq!(topology.wrap_cube(grid, cube), cube);
+    }
+
+    #[test]
+    fn raycast_grid() {
+        let grid = Grid::new([0, 0, 0], [10, 10, 10]);
+        assert_eq!(Topology::BOUNDED.raycast_grid(grid, 5.5), grid);
+        assert_eq!(
+            Topology::wrapping(false, true, false).raycast_grid(grid, 5.5),
+            Grid::new([0, -7, 0], [10, 24, 10])
+        );
+    }
+
+    #[test]
+    fn debug() {
+        assert_eq!(format!("{:?}", Topology::BOUNDED), "Bounded");
+        assert_eq!(
+            format!("{:?}", Topology::wrapping(true, false, true)),
+            "Wrapping(x, z)"
+        );
+    }
+}
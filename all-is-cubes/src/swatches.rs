@@ -0,0 +1,342 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Named colors and blocks offered to the player when editing, as opposed to the
+//! fixed colors of [`content::palette`](crate::content::palette).
+
+use std::cmp::Ordering;
+
+use crate::block::Block;
+use crate::content::palette;
+use crate::math::Rgb;
+use crate::save::{SaveError, SaveFormat};
+use crate::space::{PrefabBlockData, PrefabError};
+use crate::universe::Universe;
+
+/// Maximum number of blocks remembered by [`Swatches::note_used`].
+pub const RECENT_LIMIT: usize = 10;
+
+/// A [`Block`] with a name, as an entry in [`Swatches`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Swatch {
+    name: String,
+    block: Block,
+}
+
+impl Swatch {
+    /// Returns the name of this swatch.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the block this swatch offers.
+    pub fn block(&self) -> &Block {
+        &self.block
+    }
+}
+
+/// A collection of named [`Swatch`]es defined by the player or content, plus a list
+/// of recently used blocks, for choosing blocks to build or paint with.
+///
+/// ```
+/// use all_is_cubes::block::Block;
+/// use all_is_cubes::math::Rgba;
+/// use all_is_cubes::swatches::Swatches;
+///
+/// let mut swatches = Swatches::new();
+/// let red = Block::from(Rgba::new(1.0, 0.0, 0.0, 1.0));
+/// swatches.insert("Red", red.clone());
+/// assert_eq!(swatches.get("Red"), Some(&red));
+/// assert_eq!(swatches.name_of(&red), Some("Red"));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Swatches {
+    /// In the order they were added.
+    swatches: Vec<Swatch>,
+    /// Most recent first.
+    recent: Vec<Block>,
+}
+
+impl Swatches {
+    /// Constructs an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs a collection containing the material colors from
+    /// [`content::palette`](crate::content::palette), plus white.
+    pub fn standard() -> Self {
+        let mut swatches = Self::new();
+        for &(name, color) in &[
+            ("Black", palette::ALMOST_BLACK),
+            ("Grass", palette::GRASS),
+            ("Dirt", palette::DIRT),
+            ("Stone", palette::STONE),
+            ("Bark", palette::TREE_BARK),
+            ("Leaves", palette::TREE_LEAVES),
+            ("Steel", palette::STEEL),
+            ("Plank", palette::PLANK),
+            ("White", Rgb::ONE),
+        ] {
+            swatches.insert(name, Block::from(color));
+        }
+        swatches
+    }
+
+    /// Adds a swatch, replacing any existing swatch with the same name (and keeping its
+    /// position in [`Swatches::iter`]).
+    pub fn insert(&mut self, name: impl Into<String>, block: Block) {
+        let name = name.into();
+        match self.swatches.iter_mut().find(|s| s.name == name) {
+            Some(swatch) => swatch.block = block,
+            None => self.swatches.push(Swatch { name, block }),
+        }
+    }
+
+    /// Removes the swatch with the given name, returning its block.
+    pub fn remove(&mut self, name: &str) -> Option<Block> {
+        let index = self.swatches.iter().position(|s| s.name == name)?;
+        Some(self.swatches.remove(index).block)
+    }
+
+    /// Returns the block of the swatch with the given name.
+    pub fn get(&self, name: &str) -> Option<&Block> {
+        self.swatches
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| &s.block)
+    }
+
+    /// Returns the name of the first swatch whose block is `block`.
+    pub fn name_of(&self, block: &Block) -> Option<&str> {
+        self.swatches
+            .iter()
+            .find(|s| s.block == *block)
+            .map(|s| s.name.as_str())
+    }
+
+    /// Returns the swatches in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = &Swatch> {
+        self.swatches.iter()
+    }
+
+    /// Returns the swatches ordered for presenting as a color chart: grays first, from
+    /// dark to light, then other colors by hue, starting at red. Blocks which cannot
+    /// be evaluated are last.
+    pub fn sorted_by_hue(&self) -> Vec<&Swatch> {
+        let mut sorted: Vec<(HueKey, &Swatch)> = self
+            .swatches
+            .iter()
+            .map(|s| (HueKey::of(&s.block), s))
+            .collect();
+        // Stable sort, so equal colors stay in insertion order.
+        sorted.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        sorted.into_iter().map(|(_, s)| s).collect()
+    }
+
+    /// Records that `block` was used, such as by placing it, so that it appears first
+    /// in [`Swatches::recent`]. Only the most recent [`RECENT_LIMIT`] blocks are kept.
+    pub fn note_used(&mut self, block: Block) {
+        self.recent.retain(|b| *b != block);
+        self.recent.insert(0, block);
+        self.recent.truncate(RECENT_LIMIT);
+    }
+
+    /// Returns the recently used blocks, most recent first.
+    pub fn recent(&self) -> &[Block] {
+        &self.recent
+    }
+
+    /// Converts this collection to a serializable form. Fails if any of the blocks are
+    /// not representable by [`PrefabBlockData`].
+    pub fn to_data(&self) -> Result<SwatchesData, PrefabError> {
+        Ok(SwatchesData {
+            swatches: self
+                .swatches
+                .iter()
+                .map(|s| Ok((s.name.clone(), PrefabBlockData::from_block(&s.block)?)))
+                .collect::<Result<_, PrefabError>>()?,
+            recent: self
+                .recent
+                .iter()
+                .map(PrefabBlockData::from_block)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Sort key for [`Swatches::sorted_by_hue`].
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum HueKey {
+    Gray { lightness: f32 },
+    Chromatic { hue: f32, lightness: f32 },
+    Unknown,
+}
+
+impl HueKey {
+    fn of(block: &Block) -> Self {
+        let color: Rgb = match block.evaluate() {
+            Ok(evaluated) => evaluated.color.to_rgb(),
+            Err(_) => return HueKey::Unknown,
+        };
+        let [r, g, b]: [f32; 3] = color.into();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let lightness = (max + min) / 2.0;
+        let chroma = max - min;
+        if chroma <= f32::EPSILON {
+            return HueKey::Gray { lightness };
+        }
+        let sector = if max == r {
+            ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            (b - r) / chroma + 2.0
+        } else {
+            (r - g) / chroma + 4.0
+        };
+        HueKey::Chromatic {
+            hue: sector * 60.0,
+            lightness,
+        }
+    }
+}
+
+/// Serializable form of [`Swatches`], produced by [`Swatches::to_data`] and converted
+/// back by [`SwatchesData::into_swatches`].
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[non_exhaustive]
+pub struct SwatchesData {
+    /// Names and blocks of the swatches, in order.
+    pub swatches: Vec<(String, PrefabBlockData)>,
+    /// Recently used blocks, most recent first.
+    pub recent: Vec<PrefabBlockData>,
+}
+
+/// [`SaveFormat`] for [`SwatchesData`].
+pub const SWATCHES_FORMAT: SaveFormat = SaveFormat::new("all-is-cubes/swatches", 1, &[]);
+
+impl SwatchesData {
+    /// Serializes this data as JSON in the current version of [`SWATCHES_FORMAT`].
+    pub fn save(&self) -> Result<String, SaveError> {
+        SWATCHES_FORMAT.save(self)
+    }
+
+    /// Deserializes data written by [`SwatchesData::save`].
+    pub fn load(text: &str) -> Result<Self, SaveError> {
+        SWATCHES_FORMAT.load(text)
+    }
+
+    /// Converts the data back to [`Swatches`], looking up named blocks in `universe`.
+    pub fn into_swatches(self, universe: &Universe) -> Result<Swatches, PrefabError> {
+        Ok(Swatches {
+            swatches: self
+                .swatches
+                .into_iter()
+                .map(|(name, data)| {
+                    Ok(Swatch {
+                        name,
+                        block: data.into_block(universe)?,
+                    })
+                })
+                .collect::<Result<_, PrefabError>>()?,
+            recent: self
+                .recent
+                .into_iter()
+                .map(|data| data.into_block(universe))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockDef;
+    use crate::math::Rgba;
+    use crate::universe::UniverseIndex as _;
+
+    fn color(r: f32, g: f32, b: f32) -> Block {
+        Block::from(Rgba::new(r, g, b, 1.0))
+    }
+
+    #[test]
+    fn insert_replaces_by_name() {
+        let mut swatches = Swatches::new();
+        swatches.insert("a", color(1.0, 0.0, 0.0));
+        swatches.insert("b", color(0.0, 1.0, 0.0));
+        swatches.insert("a", color(0.0, 0.0, 1.0));
+        let names: Vec<&str> = swatches.iter().map(Swatch::name).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(swatches.get("a"), Some(&color(0.0, 0.0, 1.0)));
+        assert_eq!(swatches.remove("a"), Some(color(0.0, 0.0, 1.0)));
+        assert_eq!(swatches.get("a"), None);
+    }
+
+    #[test]
+    fn sorted_by_hue() {
+        let mut swatches = Swatches::new();
+        swatches.insert("blue", color(0.0, 0.0, 1.0));
+        swatches.insert("white", color(1.0, 1.0, 1.0));
+        swatches.insert("magenta", color(1.0, 0.0, 1.0));
+        swatches.insert("red", color(1.0, 0.0, 0.0));
+        swatches.insert("black", color(0.0, 0.0, 0.0));
+        swatches.insert("yellow", color(1.0, 1.0, 0.0));
+        let names: Vec<&str> = swatches
+            .sorted_by_hue()
+            .into_iter()
+            .map(Swatch::name)
+            .collect();
+        assert_eq!(
+            names,
+            vec!["black", "white", "red", "yellow", "blue", "magenta"]
+        );
+    }
+
+    #[test]
+    fn recent_is_deduplicated_and_limited() {
+        let mut swatches = Swatches::new();
+        for i in 0..=RECENT_LIMIT {
+            swatches.note_used(color(i as f32 / 100.0, 0.0, 0.0));
+        }
+        swatches.note_used(color(0.05, 0.0, 0.0));
+        let recent = swatches.recent();
+        assert_eq!(recent.len(), RECENT_LIMIT);
+        assert_eq!(recent[0], color(0.05, 0.0, 0.0));
+        assert_eq!(recent[1], color(0.1, 0.0, 0.0));
+        assert!(!recent.contains(&color(0.0, 0.0, 0.0)));
+        assert_eq!(
+            recent
+                .iter()
+                .filter(|&b| *b == color(0.05, 0.0, 0.0))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut universe = Universe::new();
+        let def_ref = universe
+            .insert("named".into(), BlockDef::new(color(0.5, 0.5, 0.5)))
+            .unwrap();
+        let mut swatches = Swatches::standard();
+        swatches.insert("Named", Block::Indirect(def_ref));
+        swatches.note_used(color(0.25, 0.5, 0.75));
+
+        let text = swatches.to_data().unwrap().save().unwrap();
+        let loaded = SwatchesData::load(&text)
+            .unwrap()
+            .into_swatches(&universe)
+            .unwrap();
+        assert_eq!(loaded, swatches);
+    }
+
+    #[test]
+    fn anonymous_block_is_unserializable() {
+        let mut universe = Universe::new();
+        let block = Block::Indirect(universe.insert_anonymous(BlockDef::new(color(0.0, 0.0, 0.0))));
+        let mut swatches = Swatches::new();
+        swatches.insert("x", block.clone());
+        assert_eq!(swatches.to_data(), Err(PrefabError::Unserializable(block)));
+    }
+}
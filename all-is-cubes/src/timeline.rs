@@ -0,0 +1,382 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Recording what happened in a [`Space`](crate::space::Space) and when, for
+//! diagnosing the behavior of game mechanics which change blocks over time.
+//!
+//! A [`Timeline`] records changes reported by a space's [`Listener`] interface, and
+//! also any notes added explicitly, such as by a [`Behavior`](crate::behavior::Behavior)
+//! describing its actions. The events may then be queried by time and region, or
+//! drawn as an overlay via [`Timeline::overlay`].
+//!
+//! ```
+//! use all_is_cubes::apps::Tick;
+//! use all_is_cubes::prelude::*;
+//! use all_is_cubes::timeline::Timeline;
+//! use std::time::Duration;
+//!
+//! let timeline = Timeline::new(1000);
+//! let mut space = Space::empty_positive(10, 10, 10);
+//! space.listen(timeline.listener());
+//!
+//! timeline.advance(Tick::from_seconds(0.5));
+//! space.set([1, 2, 3], Block::from(Rgba::WHITE)).unwrap();
+//! timeline.note(Some(Grid::new([5, 5, 5], [2, 2, 2])), "something happened here");
+//!
+//! let events = timeline.events_in(Grid::new([0, 0, 0], [4, 4, 4]));
+//! assert_eq!(events.len(), 1);
+//! assert_eq!(events[0].time, Duration::from_millis(500));
+//! ```
+
+use cgmath::{Point3, Vector3};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::apps::Tick;
+use crate::listen::Listener;
+use crate::math::{Aab, FreeCoordinate, Geometry, GridCoordinate, GridVector};
+use crate::space::{Grid, SpaceChange};
+
+/// A record of events, with the time at which each occurred. Only the most recent
+/// events, up to a fixed capacity, are kept.
+///
+/// `Timeline` is a shared handle: clones of it refer to the same record, so that one
+/// may be given to each source of events.
+#[derive(Clone, Debug)]
+pub struct Timeline {
+    state: Arc<Mutex<TimelineState>>,
+}
+
+#[derive(Debug)]
+struct TimelineState {
+    now: Duration,
+    capacity: usize,
+    /// Oldest first.
+    events: VecDeque<TimelineEvent>,
+}
+
+/// An event recorded in a [`Timeline`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct TimelineEvent {
+    /// Value of [`Timeline::now`] when the event was recorded.
+    pub time: Duration,
+    /// The cubes the event concerns, if it concerns specific cubes.
+    pub location: Option<Grid>,
+    /// What happened.
+    pub kind: TimelineEventKind,
+}
+
+/// What kind of event a [`TimelineEvent`] is.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum TimelineEventKind {
+    /// A change to the blocks of a [`Space`](crate::space::Space), received by
+    /// [`Timeline::listener`].
+    Space(SpaceChange),
+    /// A description added by [`Timeline::note`].
+    Note(String),
+}
+
+impl Timeline {
+    /// Constructs an empty timeline which will keep up to `capacity` events, discarding
+    /// the oldest ones when more are recorded.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TimelineState {
+                now: Duration::ZERO,
+                capacity,
+                events: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Returns a [`Listener`] which records changes to a [`Space`](crate::space::Space)
+    /// as events, to be registered with
+    /// [`Space::listen`](crate::space::Space::listen) or
+    /// [`Space::listen_batched`](crate::space::Space::listen_batched).
+    ///
+    /// Changes to lighting are not recorded, since they are frequent and a consequence
+    /// of other changes.
+    pub fn listener(&self) -> impl Listener<SpaceChange> {
+        TimelineListener {
+            state: Arc::downgrade(&self.state),
+        }
+    }
+
+    /// Advances the current time by the length of `tick`, unless it is paused. This
+    /// should be called whenever the space(s) being observed are stepped.
+    pub fn advance(&self, tick: Tick) {
+        if !tick.paused() {
+            self.state.lock().unwrap().now += tick.delta_t;
+        }
+    }
+
+    /// Returns the time which new events will be recorded as occurring at: the total
+    /// of the ticks passed to [`Timeline::advance`].
+    pub fn now(&self) -> Duration {
+        self.state.lock().unwrap().now
+    }
+
+    /// Records an event described by `message`, concerning the cubes in `location`
+    /// if any.
+    pub fn note(&self, location: Option<Grid>, message: impl Into<String>) {
+        self.state
+            .lock()
+            .unwrap()
+            .record(location, TimelineEventKind::Note(message.into()));
+    }
+
+    /// Returns all recorded events, oldest first.
+    pub fn events(&self) -> Vec<TimelineEvent> {
+        self.state.lock().unwrap().events.iter().cloned().collect()
+    }
+
+    /// Returns the recorded events whose locations intersect `region`, oldest first.
+    /// Events without a location are not included.
+    pub fn events_in(&self, region: Grid) -> Vec<TimelineEvent> {
+        self.filtered(|event| {
+            event
+                .location
+                .map_or(false, |location| location.intersection(region).is_some())
+        })
+    }
+
+    /// Returns the recorded events which occurred at or after `time`, oldest first.
+    pub fn events_since(&self, time: Duration) -> Vec<TimelineEvent> {
+        self.filtered(|event| event.time >= time)
+    }
+
+    /// Discards all recorded events.
+    pub fn clear(&self) {
+        self.state.lock().unwrap().events.clear();
+    }
+
+    /// Returns a [`Geometry`] outlining the locations of events which occurred within
+    /// `age` of [`Timeline::now`], for drawing over the space as a debugging aid.
+    pub fn overlay(&self, age: Duration) -> TimelineOverlay {
+        let state = self.state.lock().unwrap();
+        let since = state.now.checked_sub(age).unwrap_or(Duration::ZERO);
+        TimelineOverlay {
+            regions: state
+                .events
+                .iter()
+                .filter(|event| event.time >= since)
+                .filter_map(|event| event.location)
+                .collect(),
+        }
+    }
+
+    fn filtered(&self, predicate: impl Fn(&TimelineEvent) -> bool) -> Vec<TimelineEvent> {
+        self.state
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .filter(|event| predicate(event))
+            .cloned()
+            .collect()
+    }
+}
+
+impl TimelineState {
+    fn record(&mut self, location: Option<Grid>, kind: TimelineEventKind) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(TimelineEvent {
+            time: self.now,
+            location,
+            kind,
+        });
+    }
+}
+
+struct TimelineListener {
+    state: Weak<Mutex<TimelineState>>,
+}
+
+impl Listener<SpaceChange> for TimelineListener {
+    fn receive(&self, message: SpaceChange) {
+        let location = match message {
+            SpaceChange::Lighting(_) | SpaceChange::LightingRegion(_) => return,
+            SpaceChange::Block(cube) => Some(Grid::single_cube(cube)),
+            SpaceChange::BlockRegion(region) => Some(region),
+            _ => None,
+        };
+        if let Some(state) = self.state.upgrade() {
+            state
+                .lock()
+                .unwrap()
+                .record(location, TimelineEventKind::Space(message));
+        }
+    }
+
+    fn alive(&self) -> bool {
+        self.state.strong_count() > 0
+    }
+}
+
+/// Outlines of the locations of recent events in a [`Timeline`], returned by
+/// [`Timeline::overlay`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimelineOverlay {
+    regions: Vec<Grid>,
+}
+
+impl Geometry for TimelineOverlay {
+    type Coord = FreeCoordinate;
+
+    /// Translates the outlined regions by `offset`, rounded to the nearest whole cube,
+    /// since the regions are always made of whole cubes.
+    fn translate(self, offset: impl Into<Vector3<FreeCoordinate>>) -> Self {
+        let offset: GridVector = offset.into().map(|c| c.round() as GridCoordinate);
+        Self {
+            regions: self
+                .regions
+                .into_iter()
+                .map(|region| region.translate(offset))
+                .collect(),
+        }
+    }
+
+    fn wireframe_points<E>(&self, output: &mut E)
+    where
+        E: Extend<Point3<FreeCoordinate>>,
+    {
+        for &region in &self.regions {
+            // Slightly enlarged so as not to be hidden by the blocks themselves.
+            Aab::from(region).enlarge(0.01).wireframe_points(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::make_some_blocks;
+    use crate::math::GridPoint;
+    use crate::space::Space;
+
+    #[test]
+    fn records_block_changes_with_time() {
+        let [block] = make_some_blocks();
+        let timeline = Timeline::new(10);
+        let mut space = Space::empty_positive(4, 4, 4);
+        space.listen(timeline.listener());
+
+        space.set([0, 0, 0], &block).unwrap();
+        timeline.advance(Tick::from_seconds(1.0));
+        timeline.advance(Tick::from_seconds(1.0).pause());
+        space.set([3, 3, 3], &block).unwrap();
+
+        let block_events: Vec<(Duration, Option<Grid>)> = timeline
+            .events()
+            .into_iter()
+            .filter(|event| matches!(event.kind, TimelineEventKind::Space(SpaceChange::Block(_))))
+            .map(|event| (event.time, event.location))
+            .collect();
+        assert_eq!(
+            block_events,
+            vec![
+                (Duration::ZERO, Some(Grid::new([0, 0, 0], [1, 1, 1]))),
+                (
+                    Duration::from_secs(1),
+                    Some(Grid::new([3, 3, 3], [1, 1, 1]))
+                ),
+            ]
+        );
+        let recent = timeline.events_since(Duration::from_secs(1));
+        assert!(recent
+            .iter()
+            .all(|event| event.time == Duration::from_secs(1)));
+        assert!(recent.iter().any(|event| event.kind
+            == TimelineEventKind::Space(SpaceChange::Block(GridPoint::new(3, 3, 3)))));
+    }
+
+    #[test]
+    fn query_by_region() {
+        let timeline = Timeline::new(10);
+        timeline.note(Some(Grid::new([0, 0, 0], [2, 2, 2])), "a");
+        timeline.note(Some(Grid::new([5, 0, 0], [1, 1, 1])), "b");
+        timeline.note(None, "c");
+        let notes: Vec<TimelineEventKind> = timeline
+            .events_in(Grid::new([1, 0, 0], [5, 1, 1]))
+            .into_iter()
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(
+            notes,
+            vec![
+                TimelineEventKind::Note("a".into()),
+                TimelineEventKind::Note("b".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn capacity_discards_oldest() {
+        let timeline = Timeline::new(2);
+        for message in &["a", "b", "c"] {
+            timeline.note(None, *message);
+        }
+        let notes: Vec<TimelineEventKind> = timeline
+            .events()
+            .into_iter()
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(
+            notes,
+            vec![
+                TimelineEventKind::Note("b".into()),
+                TimelineEventKind::Note("c".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn overlay_includes_only_recent() {
+        let timeline = Timeline::new(10);
+        timeline.note(Some(Grid::new([0, 0, 0], [1, 1, 1])), "old");
+        timeline.advance(Tick::from_seconds(5.0));
+        timeline.note(Some(Grid::new([1, 0, 0], [1, 1, 1])), "new");
+        timeline.note(None, "nowhere");
+        assert_eq!(
+            timeline.overlay(Duration::from_secs(1)),
+            TimelineOverlay {
+                regions: vec![Grid::new([1, 0, 0], [1, 1, 1])]
+            }
+        );
+        let mut points = Vec::new();
+        timeline
+            .overlay(Duration::from_secs(10))
+            .wireframe_points(&mut points);
+        assert_eq!(points.len(), 2 * 24);
+    }
+
+    #[test]
+    fn overlay_translate_rounds_to_cubes() {
+        let overlay = TimelineOverlay {
+            regions: vec![Grid::new([1, 0, 0], [1, 2, 1])],
+        };
+        assert_eq!(
+            overlay.translate([0.4, -1.0, 2.6]),
+            TimelineOverlay {
+                regions: vec![Grid::new([1, -1, 3], [1, 2, 1])]
+            }
+        );
+    }
+
+    #[test]
+    fn listener_dies_with_timeline() {
+        let timeline = Timeline::new(10);
+        let listener = timeline.listener();
+        assert!(listener.alive());
+        drop(timeline);
+        assert!(!listener.alive());
+    }
+}
@@ -3,6 +3,8 @@
 
 //! Means by which the player may alter or interact with the world.
 
+use cgmath::EuclideanSpace as _;
+#[cfg(feature = "content")]
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::error::Error;
@@ -10,13 +12,17 @@ use std::sync::Arc;
 
 use crate::block::{Block, AIR};
 use crate::character::{Character, CharacterTransaction, Cursor};
+#[cfg(feature = "content")]
 use crate::linking::BlockProvider;
-use crate::math::GridPoint;
-use crate::space::{SetCubeError, SpaceTransaction};
+use crate::math::{GridMatrix, GridPoint, GridRotation};
+use crate::space::{
+    grid_between, Collision, Grid, RegionEditError, SetCubeError, Space, SpaceTransaction,
+};
 use crate::transactions::{
     PreconditionFailed, Transaction, TransactionConflict, UniverseTransaction,
 };
 use crate::universe::{RefError, URef};
+#[cfg(feature = "content")]
 use crate::vui::Icons;
 
 /// A `Tool` is an object which a character can use to have some effect in the game,
@@ -31,14 +37,31 @@ pub enum Tool {
     /// Empty slot; does nothing.
     None,
     /// “Click”, or “push button”, or generally “activate the function of this”
-    /// as opposed to editing it. Used for [`vui`](crate::vui) interaction.
+    /// as opposed to editing it. Used for [`vui`](crate::vui) interaction, and opens
+    /// and closes [`Mechanism`](crate::space::Mechanism)s.
     Activate,
-    /// Destroy any targeted block.
+    /// Destroy any targeted block. Blocks with nonzero
+    /// [`hardness`](crate::block::BlockAttributes::hardness) must instead be broken
+    /// gradually with [`Character::continue_breaking`].
     DeleteBlock,
     /// Place a copy of the given block in empty space.
     PlaceBlock(Block),
     /// Copy block from space to inventory.
     CopyFromSpace,
+    /// Add to inventory a plain block of the color of the targeted block, discarding
+    /// its shape and other attributes.
+    PickColor,
+    /// Replace the targeted block, and all blocks connected to it through faces of the
+    /// same block, with the given block.
+    FloodFill(Block),
+    /// Select a region, then move it so that its lowest corner is next to the targeted
+    /// face. The selection follows the moved blocks.
+    MoveRegion(RegionSelection),
+    /// Select a region, then copy it so that the copy's lowest corner is next to the
+    /// targeted face. The selection becomes the copy, so that it may be copied again.
+    CopyRegion(RegionSelection),
+    /// Select a region, then rotate it clockwise (seen from above) about its center.
+    RotateRegion(RegionSelection),
 }
 
 impl Tool {
@@ -50,13 +73,26 @@ impl Tool {
         match self {
             Self::None => Err(ToolError::NotUsable),
             Self::Activate => {
-                // TODO: We have nothing to activate yet.
-                Err(ToolError::NotUsable)
+                let space = input
+                    .cursor()
+                    .space
+                    .try_borrow()
+                    .map_err(ToolError::SpaceRef)?;
+                let transaction = space
+                    .mechanism_at(input.cursor().place.cube)
+                    .and_then(|mechanism| mechanism.toggle_transaction(&space))
+                    .ok_or(ToolError::NotUsable)?;
+                Ok((self, transaction.bind(input.cursor().space.clone())))
+            }
+            Self::DeleteBlock => {
+                if input.cursor().evaluated.attributes.hardness.into_inner() > 0.0 {
+                    return Err(ToolError::RequiresHolding);
+                }
+                Ok((
+                    self,
+                    input.set_cube(input.cursor().place.cube, input.cursor().block.clone(), AIR)?,
+                ))
             }
-            Self::DeleteBlock => Ok((
-                self,
-                input.set_cube(input.cursor().place.cube, input.cursor().block.clone(), AIR)?,
-            )),
             Self::PlaceBlock(ref block) => {
                 let block = block.clone();
                 Ok((
@@ -70,6 +106,93 @@ impl Tool {
                     input.cursor().block.clone().unspecialize(),
                 ))?,
             )),
+            Self::PickColor => {
+                let color = input.cursor().evaluated.color;
+                Ok((
+                    self,
+                    input.produce_item(Tool::PlaceBlock(Block::from(color)))?,
+                ))
+            }
+            Self::FloodFill(ref block) => {
+                let transaction = input.edit_space(|space| {
+                    SpaceTransaction::flood_fill(
+                        space,
+                        input.cursor().place.cube,
+                        block.clone(),
+                        FLOOD_FILL_LIMIT,
+                    )
+                })?;
+                Ok((self, transaction))
+            }
+            Self::MoveRegion(selection) => {
+                let region = match selection.select(input.cursor().place.cube) {
+                    Ok(region) => region,
+                    Err(selection) => {
+                        return Ok((Self::MoveRegion(selection), UniverseTransaction::default()))
+                    }
+                };
+                let offset = input.cursor().place.adjacent() - region.lower_bounds();
+                let transaction = input.edit_space(|space| {
+                    SpaceTransaction::move_region(
+                        space,
+                        region,
+                        GridMatrix::from_translation(offset),
+                        Collision::Fail,
+                    )
+                })?;
+                Ok((
+                    Self::MoveRegion(RegionSelection::Region(region.translate(offset))),
+                    transaction,
+                ))
+            }
+            Self::CopyRegion(selection) => {
+                let region = match selection.select(input.cursor().place.cube) {
+                    Ok(region) => region,
+                    Err(selection) => {
+                        return Ok((Self::CopyRegion(selection), UniverseTransaction::default()))
+                    }
+                };
+                let offset = input.cursor().place.adjacent() - region.lower_bounds();
+                let transaction = input.edit_space(|space| {
+                    SpaceTransaction::copy_region(
+                        space,
+                        region,
+                        GridMatrix::from_translation(offset),
+                        Collision::Fail,
+                    )
+                })?;
+                Ok((
+                    Self::CopyRegion(RegionSelection::Region(region.translate(offset))),
+                    transaction,
+                ))
+            }
+            Self::RotateRegion(selection) => {
+                let region = match selection.select(input.cursor().place.cube) {
+                    Ok(region) => region,
+                    Err(selection) => {
+                        return Ok((
+                            Self::RotateRegion(selection),
+                            UniverseTransaction::default(),
+                        ))
+                    }
+                };
+                let transaction = input.edit_space(|space| {
+                    SpaceTransaction::rotate_region(
+                        space,
+                        region,
+                        GridRotation::CLOCKWISE,
+                        Collision::Fail,
+                    )
+                })?;
+                let rotated = GridRotation::CLOCKWISE
+                    .to_matrix_about(region.lower_bounds() + region.upper_bounds().to_vec())
+                    .and_then(|transform| region.transform(transform))
+                    .expect("can't happen: region was already rotated");
+                Ok((
+                    Self::RotateRegion(RegionSelection::Region(rotated)),
+                    transaction,
+                ))
+            }
         }
     }
 
@@ -80,6 +203,7 @@ impl Tool {
     /// TODO (API instability): Eventually we will want additional decorations like "use
     /// count" that probably should not need to be painted into the block itself.
 
+    #[cfg(feature = "content")]
     pub fn icon<'a>(&'a self, predefined: &'a BlockProvider<Icons>) -> Cow<'a, Block> {
         match self {
             Self::None => Cow::Borrowed(&predefined[Icons::EmptySlot]),
@@ -88,10 +212,49 @@ impl Tool {
             // TODO: Once blocks have behaviors, we need to defuse them for this use.
             Self::PlaceBlock(block) => Cow::Borrowed(&block),
             Self::CopyFromSpace => Cow::Borrowed(&predefined[Icons::CopyFromSpace]),
+            Self::PickColor => Cow::Borrowed(&predefined[Icons::PickColor]),
+            Self::FloodFill(_) => Cow::Borrowed(&predefined[Icons::FloodFill]),
+            Self::MoveRegion(_) => Cow::Borrowed(&predefined[Icons::MoveRegion]),
+            Self::CopyRegion(_) => Cow::Borrowed(&predefined[Icons::CopyRegion]),
+            Self::RotateRegion(_) => Cow::Borrowed(&predefined[Icons::RotateRegion]),
+        }
+    }
+}
+
+/// Maximum number of cubes [`Tool::FloodFill`] will change at once.
+const FLOOD_FILL_LIMIT: usize = 4096;
+
+/// Which region a tool such as [`Tool::MoveRegion`] operates on. The first two uses of
+/// such a tool select opposite corners of the region, and later uses operate on it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum RegionSelection {
+    /// Nothing is selected yet.
+    None,
+    /// One corner of the region has been selected.
+    Corner(GridPoint),
+    /// The region has been selected.
+    Region(Grid),
+}
+
+impl RegionSelection {
+    /// Advances the selection given that `cube` was targeted. Returns the region if it
+    /// was already selected, or the new state of the selection otherwise.
+    fn select(self, cube: GridPoint) -> Result<Grid, Self> {
+        match self {
+            Self::None => Err(Self::Corner(cube)),
+            Self::Corner(corner) => Err(Self::Region(grid_between(corner, cube))),
+            Self::Region(region) => Ok(region),
         }
     }
 }
 
+impl Default for RegionSelection {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
 /// Resources available to a `Tool` to perform its function.
 ///
 /// This is intended to provide future extensibility compared to having a complex
@@ -127,6 +290,20 @@ impl ToolInput {
         )
     }
 
+    /// Generic handler for a tool that edits a region of the space, given a function to
+    /// construct the transaction from the current contents of the space.
+    fn edit_space(
+        &self,
+        f: impl FnOnce(&Space) -> Result<SpaceTransaction, RegionEditError>,
+    ) -> Result<UniverseTransaction, ToolError> {
+        let space = self
+            .cursor
+            .space
+            .try_borrow()
+            .map_err(ToolError::SpaceRef)?;
+        Ok(f(&space)?.bind(self.cursor.space.clone()))
+    }
+
     pub fn cursor(&self) -> &Cursor {
         &self.cursor
     }
@@ -157,9 +334,16 @@ pub enum ToolError {
     /// The tool requires a target cube and none was present.
     #[error("nothing is selected")]
     NothingSelected,
+    /// The target must be acted on over time by holding the tool, rather than by a
+    /// single use; see [`Character::continue_breaking`].
+    #[error("must be held to take effect")]
+    RequiresHolding,
     /// The cube to be modified could not be modified; see the inner error for why.
     #[error("error placing block: {0}")]
     SetCube(#[from] SetCubeError),
+    /// The region to be modified could not be modified; see the inner error for why.
+    #[error("error editing region: {0}")]
+    RegionEdit(#[from] RegionEditError),
     /// The space to be operated on could not be accessed.
     #[error("error accessing space: {0}")]
     SpaceRef(#[from] RefError),
@@ -351,7 +535,7 @@ mod tests {
     use crate::math::Rgba;
     use crate::raycast::Ray;
     use crate::raytracer::print_space;
-    use crate::space::Space;
+    use crate::space::{Mechanism, MechanismState, Space};
     use crate::universe::{UBorrow, UBorrowMut, URef, Universe};
 
     #[derive(Debug)]
@@ -411,6 +595,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "content")]
     fn dummy_icons() -> BlockProvider<Icons> {
         // TODO: Might be good to generate differently labeled blocks... maybe BlockProvider should have a way to do that for any enum.
         let [block] = make_some_blocks();
@@ -418,6 +603,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "content")]
     fn icon_none() {
         let dummy_icons = dummy_icons();
         assert_eq!(
@@ -441,6 +627,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "content")]
     fn icon_activate() {
         let dummy_icons = dummy_icons();
         assert_eq!(
@@ -462,11 +649,33 @@ mod tests {
     }
 
     #[test]
-    fn use_activate_effect() {
-        // TODO: When Tool::Activate actually gains some effects, test them here.
+    fn use_activate_mechanism() {
+        let [door] = make_some_blocks();
+        let mechanism = Mechanism::new(Grid::new([1, 0, 0], [1, 2, 1]), |_, state| match state {
+            MechanismState::Closed => door.clone(),
+            MechanismState::Open => AIR,
+        });
+        let mut tester = ToolTester::new(|space| {
+            space
+                .add_mechanism(mechanism.clone(), MechanismState::Closed)
+                .unwrap();
+        });
+        let transaction = tester.equip_and_use_tool(Tool::Activate).unwrap();
+        assert_eq!(
+            transaction,
+            mechanism
+                .transaction(MechanismState::Open)
+                .bind(tester.space_ref.clone())
+        );
+        transaction.execute(&mut tester.universe).unwrap();
+        assert_eq!(
+            mechanism.state_in(&tester.space()),
+            Some(MechanismState::Open)
+        );
     }
 
     #[test]
+    #[cfg(feature = "content")]
     fn icon_delete_block() {
         let dummy_icons = dummy_icons();
         assert_eq!(
@@ -493,6 +702,19 @@ mod tests {
     }
 
     #[test]
+    fn use_delete_block_with_hardness() {
+        let hard = Block::builder().color(Rgba::WHITE).hardness(1.0).build();
+        let tester = ToolTester::new(|space| {
+            space.set((1, 0, 0), &hard).unwrap();
+        });
+        assert_eq!(
+            tester.equip_and_use_tool(Tool::DeleteBlock),
+            Err(ToolError::RequiresHolding)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "content")]
     fn icon_place_block() {
         let dummy_icons = dummy_icons();
         let [block] = make_some_blocks();
@@ -555,6 +777,102 @@ mod tests {
         assert_eq!(&tester.space()[(1, 0, 0)], &existing);
     }
 
+    #[test]
+    fn use_pick_color() {
+        let color = Rgba::new(0.25, 0.5, 0.75, 1.0);
+        let existing = Block::builder()
+            .display_name("Existing")
+            .color(color)
+            .build();
+        let mut tester = ToolTester::new(|space| {
+            space.set((1, 0, 0), &existing).unwrap();
+        });
+        let transaction = tester.equip_and_use_tool(Tool::PickColor).unwrap();
+        assert_eq!(
+            transaction,
+            CharacterTransaction::inventory(InventoryTransaction::insert(Tool::PlaceBlock(
+                Block::from(color)
+            )))
+            .bind(tester.character_ref.clone())
+        );
+        transaction.execute(&mut tester.universe).unwrap();
+        assert_eq!(&tester.space()[(1, 0, 0)], &existing);
+    }
+
+    #[test]
+    fn use_flood_fill() {
+        let [existing, other, tool_block] = make_some_blocks();
+        let mut tester = ToolTester::new(|space| {
+            space
+                .fill(Grid::new([1, 0, 0], [3, 1, 1]), |_| Some(&existing))
+                .unwrap();
+            space.set((1, 1, 0), &existing).unwrap();
+            space.set((4, 0, 0), &other).unwrap();
+        });
+        let transaction = tester
+            .equip_and_use_tool(Tool::FloodFill(tool_block.clone()))
+            .unwrap();
+        transaction.execute(&mut tester.universe).unwrap();
+        for &cube in &[[1, 0, 0], [2, 0, 0], [3, 0, 0], [1, 1, 0]] {
+            assert_eq!(&tester.space()[cube], &tool_block);
+        }
+        assert_eq!(&tester.space()[(4, 0, 0)], &other);
+    }
+
+    #[test]
+    fn use_move_region_selects_then_moves() {
+        let [existing] = make_some_blocks();
+        let mut tester = ToolTester::new(|space| {
+            space.set((2, 0, 0), &existing).unwrap();
+        });
+
+        // The first use selects a corner, without modifying the space.
+        let transaction = tester
+            .equip_and_use_tool(Tool::MoveRegion(RegionSelection::None))
+            .unwrap();
+        transaction.execute(&mut tester.universe).unwrap();
+        assert_eq!(
+            tester.character_ref.borrow().inventory().slots[0],
+            Tool::MoveRegion(RegionSelection::Corner(GridPoint::new(2, 0, 0)))
+        );
+        assert_eq!(&tester.space()[(2, 0, 0)], &existing);
+
+        // Once a region is selected, it is moved next to the targeted face.
+        let mut tester = ToolTester::new(|space| {
+            space.set((2, 0, 0), &existing).unwrap();
+        });
+        let region = Grid::new([2, 0, 0], [1, 2, 1]);
+        let transaction = tester
+            .equip_and_use_tool(Tool::MoveRegion(RegionSelection::Region(region)))
+            .unwrap();
+        transaction.execute(&mut tester.universe).unwrap();
+        assert_eq!(&tester.space()[(1, 0, 0)], &existing);
+        assert_eq!(&tester.space()[(2, 0, 0)], &AIR);
+        assert_eq!(
+            tester.character_ref.borrow().inventory().slots[0],
+            Tool::MoveRegion(RegionSelection::Region(region.translate([-1, 0, 0])))
+        );
+    }
+
+    #[test]
+    fn use_rotate_region_collision() {
+        let [existing, obstacle] = make_some_blocks();
+        let tester = ToolTester::new(|space| {
+            space.set((2, 0, 1), &existing).unwrap();
+            space.set((3, 0, 0), &obstacle).unwrap();
+        });
+        // Rotating this row about its center turns it into a column along Z, which
+        // would put `existing` where `obstacle` is.
+        let region = Grid::new([2, 0, 1], [3, 1, 1]);
+        assert_eq!(
+            tester.equip_and_use_tool(Tool::RotateRegion(RegionSelection::Region(region))),
+            Err(ToolError::RegionEdit(RegionEditError::Collision(
+                GridPoint::new(3, 0, 0)
+            )))
+        );
+        assert_eq!(&tester.space()[(2, 0, 1)], &existing);
+    }
+
     // TODO: test for Inventory::use_tool
 
     #[test]
@@ -0,0 +1,229 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Instrumentation of the engine with [`tracing`] spans, when the `tracing` feature is
+//! enabled.
+//!
+//! Stepping, lighting, triangulation, and rendering each run inside a span named after
+//! the work being done (`universe_step`, `space_step`, `lighting`, `triangulate_space`,
+//! `triangulate_blocks`, `chunk_update`, `render_frame`, and `raytrace`), so that any
+//! `tracing` subscriber can show where time goes. [`SpanTimings`] is a minimal
+//! subscriber which totals the time spent in each span, for use in benchmarks and tests.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+
+#[cfg(feature = "tracing")]
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "tracing")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "tracing")]
+use std::time::Duration;
+
+#[cfg(feature = "tracing")]
+use instant::Instant; // wasm-compatible replacement for std::time::Instant
+
+/// Enters a `tracing` span, at debug level, which lasts until the returned guard is
+/// dropped. The arguments are as for `tracing::debug_span!`.
+///
+/// If the `tracing` feature is not enabled, this does nothing and the arguments are not
+/// evaluated.
+macro_rules! trace_span {
+    ($($args:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        let guard = ::tracing::debug_span!($($args)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let guard = $crate::trace::NoSpan;
+        guard
+    }};
+}
+pub(crate) use trace_span;
+
+/// Stand-in for a span guard when the `tracing` feature is not enabled.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+/// A [`tracing`] subscriber which records how many times each span was entered and the
+/// total time spent in it, identifying spans by name. Events are ignored.
+///
+/// Clones share the same records, so a clone may be installed as the subscriber and the
+/// original used to read the results.
+///
+/// ```
+/// use all_is_cubes::apps::Tick;
+/// use all_is_cubes::space::Space;
+/// use all_is_cubes::trace::SpanTimings;
+/// use all_is_cubes::universe::Universe;
+///
+/// let mut universe = Universe::new();
+/// universe.insert_anonymous(Space::empty_positive(4, 4, 4));
+///
+/// let timings = SpanTimings::new();
+/// tracing::subscriber::with_default(timings.clone(), || {
+///     universe.step(Tick::from_seconds(1.0 / 60.0));
+/// });
+/// assert_eq!(timings.timings()["universe_step"].count, 1);
+/// ```
+///
+/// [`tracing`]: https://docs.rs/tracing
+#[cfg(feature = "tracing")]
+#[derive(Clone, Debug, Default)]
+pub struct SpanTimings {
+    state: Arc<Mutex<TimingState>>,
+}
+
+#[cfg(feature = "tracing")]
+#[derive(Debug, Default)]
+struct TimingState {
+    /// Last span ID assigned; IDs start at 1 because they must be nonzero.
+    last_id: u64,
+    spans: HashMap<u64, LiveSpan>,
+    totals: BTreeMap<&'static str, SpanTiming>,
+}
+
+/// A span which has been created and not yet closed.
+#[cfg(feature = "tracing")]
+#[derive(Debug)]
+struct LiveSpan {
+    name: &'static str,
+    /// Number of handles to the span, as counted by `clone_span` and `try_close`.
+    references: usize,
+    /// When each current entry into the span started, innermost last.
+    entered: Vec<Instant>,
+}
+
+/// Statistics about one span, recorded by [`SpanTimings`].
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct SpanTiming {
+    /// Number of times the span was entered and exited.
+    pub count: usize,
+    /// Total time between entering and exiting the span.
+    pub total: Duration,
+}
+
+#[cfg(feature = "tracing")]
+impl SpanTiming {
+    /// Returns the average time spent in the span each time it was entered, or zero if
+    /// it never was.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.count as f64)
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl SpanTimings {
+    /// Constructs a subscriber with no records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the statistics for each span name which has been exited at least once.
+    pub fn timings(&self) -> BTreeMap<&'static str, SpanTiming> {
+        self.state.lock().unwrap().totals.clone()
+    }
+
+    /// Discards the statistics recorded so far.
+    pub fn clear(&self) {
+        self.state.lock().unwrap().totals.clear();
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::Subscriber for SpanTimings {
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        metadata.is_span()
+    }
+
+    fn new_span(&self, attributes: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        let mut state = self.state.lock().unwrap();
+        state.last_id += 1;
+        let id = state.last_id;
+        state.spans.insert(
+            id,
+            LiveSpan {
+                name: attributes.metadata().name(),
+                references: 1,
+                entered: Vec::new(),
+            },
+        );
+        tracing::span::Id::from_u64(id)
+    }
+
+    fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, _: &tracing::Event<'_>) {}
+
+    fn enter(&self, id: &tracing::span::Id) {
+        if let Some(span) = self.state.lock().unwrap().spans.get_mut(&id.into_u64()) {
+            span.entered.push(Instant::now());
+        }
+    }
+
+    fn exit(&self, id: &tracing::span::Id) {
+        let mut state = self.state.lock().unwrap();
+        let (name, elapsed) = match state.spans.get_mut(&id.into_u64()) {
+            Some(span) => match span.entered.pop() {
+                Some(start) => (span.name, start.elapsed()),
+                None => return,
+            },
+            None => return,
+        };
+        let timing = state.totals.entry(name).or_default();
+        timing.count += 1;
+        timing.total += elapsed;
+    }
+
+    fn clone_span(&self, id: &tracing::span::Id) -> tracing::span::Id {
+        if let Some(span) = self.state.lock().unwrap().spans.get_mut(&id.into_u64()) {
+            span.references += 1;
+        }
+        id.clone()
+    }
+
+    fn try_close(&self, id: tracing::span::Id) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let closed = match state.spans.get_mut(&id.into_u64()) {
+            Some(span) => {
+                span.references -= 1;
+                span.references == 0
+            }
+            None => false,
+        };
+        if closed {
+            state.spans.remove(&id.into_u64());
+        }
+        closed
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_and_repeated_spans() {
+        let timings = SpanTimings::new();
+        tracing::subscriber::with_default(timings.clone(), || {
+            for _ in 0..3 {
+                let _outer = trace_span!("outer");
+                let _inner = trace_span!("inner");
+            }
+        });
+        let results = timings.timings();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results["outer"].count, 3);
+        assert_eq!(results["inner"].count, 3);
+        assert!(results["inner"].total <= results["outer"].total);
+        assert!(timings.state.lock().unwrap().spans.is_empty());
+
+        timings.clear();
+        assert!(timings.timings().is_empty());
+    }
+}
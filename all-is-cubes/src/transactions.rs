@@ -400,6 +400,22 @@ mod any_transaction {
 #[must_use]
 pub struct UniverseTransaction {
     members: HashMap<Rc<Name>, AnyTransaction>,
+    /// Transactions to pass to [`Universe::schedule`], with their delays.
+    scheduled: Vec<(u64, UniverseTransaction)>,
+}
+
+impl UniverseTransaction {
+    /// Constructs a transaction which, when committed, schedules `transaction` to be
+    /// executed after `delay` steps; see [`Universe::schedule`].
+    ///
+    /// This allows behaviors to have delayed effects, such as a fuse which is lit by one
+    /// transaction and sets off an explosion later.
+    pub fn schedule(delay: u64, transaction: UniverseTransaction) -> Self {
+        UniverseTransaction {
+            members: HashMap::new(),
+            scheduled: vec![(delay, transaction)],
+        }
+    }
 }
 
 impl Transactional for Universe {
@@ -411,7 +427,10 @@ impl From<AnyTransaction> for UniverseTransaction {
         if let Some(name) = transaction.target_name() {
             let mut members: HashMap<Rc<Name>, AnyTransaction> = HashMap::new();
             members.insert(name.clone(), transaction);
-            UniverseTransaction { members }
+            UniverseTransaction {
+                members,
+                scheduled: Vec::new(),
+            }
         } else {
             UniverseTransaction::default()
         }
@@ -436,12 +455,15 @@ impl Transaction<Universe> for UniverseTransaction {
 
     fn commit(
         &self,
-        _target: &mut Universe,
+        target: &mut Universe,
         checks: Self::CommitCheck,
     ) -> Result<(), Box<dyn Error>> {
         for (name, check) in checks {
             self.members[&name].commit(&mut (), check)?;
         }
+        for (delay, transaction) in &self.scheduled {
+            target.schedule(*delay, transaction.clone());
+        }
         Ok(())
     }
 
@@ -472,6 +494,7 @@ impl Transaction<Universe> for UniverseTransaction {
                 }
             }
         }
+        self.scheduled.extend(other.scheduled);
         self
     }
 }
@@ -485,6 +508,9 @@ impl Debug for UniverseTransaction {
             // TransactionInUniverse wrapper
             ds.field(&name.to_string(), txn.transaction_as_debug());
         }
+        if !self.scheduled.is_empty() {
+            ds.field("scheduled", &self.scheduled);
+        }
         ds.finish()
     }
 }
@@ -652,6 +678,7 @@ mod tests {
             UniverseTransaction {
                 // TODO: Replace this literal with some other means of specifying an empty transaction
                 members: HashMap::new(),
+                scheduled: Vec::new(),
             }
         )
     }
@@ -16,6 +16,8 @@ mod block_vertex;
 pub use block_vertex::*;
 mod block_tri;
 pub use block_tri::*;
+mod billboard;
+pub use billboard::*;
 mod space_tri;
 pub use space_tri::*;
 mod planar;
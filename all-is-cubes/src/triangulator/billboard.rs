@@ -0,0 +1,299 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! Algorithm for converting a block to a flat, camera-facing “billboard” mesh.
+//!
+//! This module is internal and reexported by its parent.
+
+use cgmath::{EuclideanSpace as _, Matrix4, Point3, Transform as _};
+
+use crate::block::{EvaluatedBlock, Evoxel};
+use crate::camera::TransparencyOption;
+use crate::content::palette;
+use crate::math::{Face, FreeCoordinate, GridCoordinate, Rgba};
+use crate::space::{Grid, GridArray, PackedLight};
+use crate::triangulator::{
+    block_texture_resolution, copy_voxels_to_texture, push_quad, BlockVertex, GfxVertex,
+    GreedyMesher, QuadColoring, TextureAllocator, TextureCoordinate,
+};
+
+/// A triangle mesh for a block drawn as a flat square, such as for an
+/// [`Entity`](crate::entity::Entity) with
+/// [`EntityShape::Billboard`](crate::entity::EntityShape::Billboard).
+///
+/// The square is the [`Face::PZ`] face of the unit cube with its corner at the origin,
+/// and shows the block as seen looking at that face: each point has the color of the
+/// nearest visible voxel behind it.
+///
+/// Get it from [`triangulate_billboard`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BillboardTriangulation<T> {
+    vertices: Vec<BlockVertex>,
+    /// Indices into `self.vertices` that form triangles.
+    indices_opaque: Vec<u32>,
+    /// Indices for partially transparent (alpha neither 0 nor 1) vertices.
+    indices_transparent: Vec<u32>,
+    /// Texture tiles used by the vertices; holding these objects is intended to ensure
+    /// the texture coordinates stay valid.
+    textures_used: Vec<T>,
+}
+
+impl<T> BillboardTriangulation<T> {
+    /// Returns whether there are no triangles (the block is invisible).
+    pub fn is_empty(&self) -> bool {
+        self.indices_opaque.is_empty() && self.indices_transparent.is_empty()
+    }
+
+    /// Return the textures used by the vertices, which must be retained for as long as
+    /// the vertices are being used.
+    pub fn textures(&self) -> &[T] {
+        &self.textures_used
+    }
+
+    /// Returns the triangles, opaque ones first, as a list of vertices (in groups of
+    /// three) positioned by `transform`, which is typically
+    /// [`Entity::billboard_transform`](crate::entity::Entity::billboard_transform).
+    ///
+    /// The vertices are all instantiated as belonging to the cube containing the center
+    /// of the billboard, and their normals are always [`Face::PZ`].
+    pub fn instantiate<V: GfxVertex>(&self, transform: Matrix4<FreeCoordinate>) -> Vec<V> {
+        let center = transform.transform_point(Point3::new(0.5, 0.5, 1.0));
+        let cube = center.map(|c| c.floor() as GridCoordinate);
+        let offset = cube.to_vec().map(FreeCoordinate::from);
+        let block_inst = V::instantiate_block(cube);
+        self.indices_opaque
+            .iter()
+            .chain(self.indices_transparent.iter())
+            .map(|&index| {
+                let mut vertex = self.vertices[index as usize];
+                vertex.position = transform.transform_point(vertex.position) - offset;
+                let mut vertex = V::from(vertex);
+                vertex.instantiate_vertex(block_inst, PackedLight::ONE);
+                vertex
+            })
+            .collect()
+    }
+}
+
+/// Generate a [`BillboardTriangulation`] for a block's current appearance.
+pub fn triangulate_billboard<A: TextureAllocator>(
+    block: &EvaluatedBlock,
+    texture_allocator: &mut A,
+    transparency: &TransparencyOption,
+) -> BillboardTriangulation<A::Tile> {
+    let mut output = BillboardTriangulation {
+        vertices: Vec::new(),
+        indices_opaque: Vec::new(),
+        indices_transparent: Vec::new(),
+        textures_used: Vec::new(),
+    };
+
+    let tile_resolution: GridCoordinate = texture_allocator.resolution();
+    let resolution = if block.voxels.is_some() {
+        block_texture_resolution(GridCoordinate::from(block.resolution), tile_resolution)
+    } else {
+        1
+    };
+    let image = billboard_image(block, resolution, transparency);
+
+    // The image is in block coordinates, but meshing works in the coordinates of the
+    // face, in which Y is flipped.
+    let transform = Face::PZ.matrix(resolution - 1);
+    let face_image: Vec<Rgba> = (0..resolution)
+        .flat_map(|t| (0..resolution).map(move |s| (s, t)))
+        .map(|(s, t)| {
+            let cube = transform.transform_point(Point3::new(s, t, 0));
+            image[(cube.y * resolution + cube.x) as usize]
+        })
+        .collect();
+
+    let scale_vertex = |s| FreeCoordinate::from(s) / FreeCoordinate::from(resolution);
+    let mut texture_if_needed: Option<A::Tile> = None;
+    GreedyMesher::new(face_image, 0..resolution, 0..resolution).run(
+        |mesher, low_corner, high_corner| {
+            let coloring = if let Some(single_color) = mesher.single_color {
+                QuadColoring::<A::Tile>::Solid(single_color)
+            } else {
+                if texture_if_needed.is_none() {
+                    // Only the front layer of the tile is used.
+                    let voxels = GridArray::from_fn(
+                        Grid::new([0, 0, resolution - 1], [resolution, resolution, 1]),
+                        |cube| Evoxel::new(image[(cube.y * resolution + cube.x) as usize]),
+                    );
+                    texture_if_needed =
+                        copy_voxels_to_texture(texture_allocator, &voxels, resolution);
+                }
+                if let Some(ref texture) = texture_if_needed {
+                    QuadColoring::Texture(
+                        texture,
+                        resolution as TextureCoordinate / tile_resolution as TextureCoordinate,
+                    )
+                } else {
+                    // Texture allocation failure.
+                    QuadColoring::Solid(palette::MISSING_TEXTURE_FALLBACK)
+                }
+            };
+
+            push_quad(
+                &mut output.vertices,
+                if mesher.rect_has_alpha {
+                    &mut output.indices_transparent
+                } else {
+                    &mut output.indices_opaque
+                },
+                Face::PZ,
+                /* depth= */ 0.,
+                low_corner.map(scale_vertex),
+                high_corner.map(scale_vertex),
+                coloring,
+                resolution,
+                resolution,
+            );
+        },
+    );
+
+    output.textures_used.extend(texture_if_needed);
+    output
+}
+
+/// Computes the colors of a block as seen looking at its [`Face::PZ`] side: for each
+/// of `resolution`² columns of voxels parallel to the Z axis, the color of the
+/// frontmost voxel which is not fully transparent (after applying `transparency`), or
+/// [`Rgba::TRANSPARENT`] if there is none.
+///
+/// The result is indexed by `y * resolution + x`, in the block's coordinates. If the
+/// block has more voxels than `resolution`, they are sampled as
+/// [`TextureAllocator`]s do.
+pub(crate) fn billboard_image(
+    block: &EvaluatedBlock,
+    resolution: GridCoordinate,
+    transparency: &TransparencyOption,
+) -> Vec<Rgba> {
+    let voxels = match &block.voxels {
+        Some(voxels) => voxels,
+        None => {
+            let color = transparency.limit_alpha(block.color);
+            return vec![color; (resolution * resolution) as usize];
+        }
+    };
+    let block_resolution = GridCoordinate::from(block.resolution);
+    let sample = |i: GridCoordinate| (2 * i + 1) * block_resolution / (2 * resolution);
+    let mut image = Vec::with_capacity((resolution * resolution) as usize);
+    for y in 0..resolution {
+        for x in 0..resolution {
+            let (vx, vy) = (sample(x), sample(y));
+            let color = (0..block_resolution)
+                .rev()
+                .map(|z| {
+                    transparency.limit_alpha(voxels.get([vx, vy, z]).unwrap_or(&Evoxel::AIR).color)
+                })
+                .find(|color| !color.fully_transparent())
+                .unwrap_or(Rgba::TRANSPARENT);
+            image.push(color);
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::content::make_some_blocks;
+    use crate::space::Space;
+    use crate::triangulator::{Coloring, TestTextureAllocator};
+    use crate::universe::Universe;
+
+    #[test]
+    fn atom_is_one_solid_quad() {
+        let [block] = make_some_blocks();
+        let evaluated = block.evaluate().unwrap();
+        let mut allocator = TestTextureAllocator::new(16);
+        let billboard =
+            triangulate_billboard(&evaluated, &mut allocator, &TransparencyOption::Volumetric);
+        assert_eq!(billboard.indices_opaque.len(), 6);
+        assert!(billboard.textures().is_empty());
+        assert!(billboard
+            .vertices
+            .iter()
+            .all(|v| v.coloring == Coloring::Solid(evaluated.color)
+                && v.position.z == 1.0
+                && v.face == Face::PZ));
+
+        let placed: Vec<BlockVertex> = billboard.instantiate(Matrix4::from_translation(
+            cgmath::Vector3::new(10.0, 0.0, 0.0),
+        ));
+        assert_eq!(placed.len(), 6);
+        assert!(placed
+            .iter()
+            .all(|v| (10.0..=11.0).contains(&v.position.x) && v.position.z == 1.0));
+    }
+
+    #[test]
+    fn voxels_project_frontmost() {
+        let mut universe = Universe::new();
+        let [back, front] = make_some_blocks();
+        let (back, front) = (
+            back.evaluate().unwrap().color,
+            front.evaluate().unwrap().color,
+        );
+        // A block whose lower half has `front` in front of `back`, and whose upper
+        // half has only `back`.
+        let mut block_space = Space::empty_positive(2, 2, 2);
+        block_space
+            .fill_uniform(Grid::new([0, 0, 0], [2, 2, 1]), &Block::from(back))
+            .unwrap();
+        block_space
+            .fill_uniform(Grid::new([0, 0, 1], [2, 1, 1]), &Block::from(front))
+            .unwrap();
+        let block = Block::builder()
+            .voxels_ref(2, universe.insert_anonymous(block_space))
+            .build();
+        let evaluated = block.evaluate().unwrap();
+
+        assert_eq!(
+            billboard_image(&evaluated, 2, &TransparencyOption::Volumetric),
+            vec![front, front, back, back]
+        );
+
+        let mut allocator = TestTextureAllocator::new(16);
+        let billboard =
+            triangulate_billboard(&evaluated, &mut allocator, &TransparencyOption::Volumetric);
+        // The two colors are merged into one textured quad.
+        assert_eq!(billboard.indices_opaque.len(), 6);
+        assert_eq!(allocator.count_allocated(), 1);
+        assert_eq!(billboard.textures().len(), 1);
+    }
+
+    #[test]
+    fn transparent_voxels_are_omitted() {
+        let mut universe = Universe::new();
+        let [block] = make_some_blocks();
+        let color = block.evaluate().unwrap().color;
+        // Only the lower half of the block has any voxels.
+        let mut block_space = Space::empty_positive(2, 2, 2);
+        block_space
+            .fill_uniform(Grid::new([0, 0, 0], [2, 1, 1]), &block)
+            .unwrap();
+        let evaluated = Block::builder()
+            .voxels_ref(2, universe.insert_anonymous(block_space))
+            .build()
+            .evaluate()
+            .unwrap();
+
+        assert_eq!(
+            billboard_image(&evaluated, 2, &TransparencyOption::Volumetric),
+            vec![color, color, Rgba::TRANSPARENT, Rgba::TRANSPARENT]
+        );
+
+        let mut allocator = TestTextureAllocator::new(16);
+        let billboard =
+            triangulate_billboard(&evaluated, &mut allocator, &TransparencyOption::Volumetric);
+        assert_eq!(allocator.count_allocated(), 0);
+        assert_eq!(billboard.indices_opaque.len(), 6);
+        assert!(billboard
+            .vertices
+            .iter()
+            .all(|v| v.coloring == Coloring::Solid(color) && (0.0..=0.5).contains(&v.position.y)));
+    }
+}
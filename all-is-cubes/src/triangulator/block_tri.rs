@@ -13,9 +13,10 @@ use crate::camera::TransparencyOption;
 use crate::content::palette;
 use crate::math::{Face, FaceMap, FreeCoordinate, GridCoordinate, Rgba};
 use crate::space::{Grid, Space};
+use crate::trace::trace_span;
 use crate::triangulator::{
-    copy_voxels_to_texture, push_quad, BlockVertex, GreedyMesher, QuadColoring, TextureAllocator,
-    TextureCoordinate,
+    block_texture_resolution, copy_voxels_to_texture, push_quad, BlockVertex, GreedyMesher,
+    QuadColoring, TextureAllocator, TextureCoordinate,
 };
 
 /// Describes how to draw one [`Face`] of a [`Block`].
@@ -82,6 +83,32 @@ impl<V, T> BlockTriangulation<V, T> {
     pub(crate) fn textures(&self) -> &[T] {
         &self.textures_used
     }
+
+    /// Returns a triangulation with no triangles, but which hides the faces of adjacent
+    /// blocks just as this one does. This may be used in place of this triangulation when
+    /// the block is to be drawn by some other means.
+    pub(crate) fn hollow(&self) -> Self {
+        Self {
+            faces: FaceMap::from_fn(|face| FaceTriangulation {
+                fully_opaque: self.faces[face].fully_opaque,
+                ..FaceTriangulation::default()
+            }),
+            textures_used: Vec::new(),
+        }
+    }
+
+    /// Returns a copy of this triangulation that does not hold its texture tiles, such as
+    /// to use it where `T` cannot be sent. The caller is responsible for keeping the
+    /// textures of `self` for as long as the vertices of the copy are being used.
+    pub(crate) fn without_textures(&self) -> BlockTriangulation<V, ()>
+    where
+        V: Clone,
+    {
+        BlockTriangulation {
+            faces: self.faces.clone(),
+            textures_used: Vec::new(),
+        }
+    }
 }
 
 impl<V, T> Default for BlockTriangulation<V, T> {
@@ -134,6 +161,7 @@ pub fn triangulate_block<V: From<BlockVertex>, A: TextureAllocator>(
                         Point2 { x: 1., y: 1. },
                         QuadColoring::<A::Tile>::Solid(color),
                         1,
+                        1,
                     );
                 }
                 FaceTriangulation {
@@ -176,12 +204,14 @@ pub fn triangulate_block<V: From<BlockVertex>, A: TextureAllocator>(
             });
 
             // If the texture tile resolution is greater, we will just not use the extra
-            // space. If it is lesser, we should use multiple texture tiles but don't for now.
+            // space. If it is lesser, the voxels are sampled down to fit the tile; we
+            // could use multiple texture tiles instead but don't for now.
             let tile_resolution: GridCoordinate = texture_allocator.resolution();
             let block_resolution = GridCoordinate::from(block.resolution);
+            let texture_resolution = block_texture_resolution(block_resolution, tile_resolution);
             // How should we scale texels versus the standard size to get correct display?
             let voxel_scale_modifier =
-                block_resolution as TextureCoordinate / tile_resolution as TextureCoordinate;
+                texture_resolution as TextureCoordinate / tile_resolution as TextureCoordinate;
             // Map quad vertices in voxel grid coordinates to containing block coordinates.
             let vertex_scale = FreeCoordinate::from(block.resolution).recip();
             let scale_vertex = |s| FreeCoordinate::from(s) * vertex_scale;
@@ -293,8 +323,11 @@ pub fn triangulate_block<V: From<BlockVertex>, A: TextureAllocator>(
                         } else {
                             if texture_if_needed.is_none() {
                                 // Try to compute texture
-                                texture_if_needed =
-                                    copy_voxels_to_texture(texture_allocator, voxels);
+                                texture_if_needed = copy_voxels_to_texture(
+                                    texture_allocator,
+                                    voxels,
+                                    block_resolution,
+                                );
                             }
                             if let Some(ref texture) = texture_if_needed {
                                 QuadColoring::Texture(texture, voxel_scale_modifier)
@@ -321,7 +354,8 @@ pub fn triangulate_block<V: From<BlockVertex>, A: TextureAllocator>(
                             low_corner,
                             high_corner,
                             coloring,
-                            tile_resolution,
+                            block_resolution,
+                            texture_resolution,
                         );
                     });
                 }
@@ -345,6 +379,7 @@ pub fn triangulate_blocks<V: From<BlockVertex>, A: TextureAllocator>(
     texture_allocator: &mut A,
     transparency: &TransparencyOption,
 ) -> BlockTriangulations<V, A::Tile> {
+    let _span = trace_span!("triangulate_blocks");
     space
         .block_data()
         .iter()
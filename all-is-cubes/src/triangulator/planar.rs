@@ -151,6 +151,9 @@ pub(super) enum QuadColoring<'a, T> {
 /// Compute vertices for a quad and push them into the supplied vectors.
 ///
 /// `depth`, `low_corner`, and `high_corner` are in 0-1 coordinates.
+/// `resolution` is the block's resolution, and `texture_resolution` the number of texels
+/// along each edge of its texture, which is less if the voxels were sampled to fit the
+/// tile.
 #[inline]
 #[allow(clippy::too_many_arguments)] // TODO: Figure out how to simplify
 pub(super) fn push_quad<V: From<BlockVertex>>(
@@ -162,39 +165,49 @@ pub(super) fn push_quad<V: From<BlockVertex>>(
     high_corner: Point2<FreeCoordinate>,
     coloring: QuadColoring<'_, impl TextureTile>,
     resolution: GridCoordinate,
+    texture_resolution: GridCoordinate,
 ) {
     // TODO: Refactor so we don't have to do 100% of this anew for each individual quad
     // This is tricky, though, since the coloring can vary per quad (though the scale _can_ be constant).
     let transform_f = face.matrix(1).to_free();
     let transform_t = transform_f.cast::<TextureCoordinate>().unwrap();
     let index_origin: u32 = vertices.len().try_into().expect("vertex index overflow");
-    let half_texel = 0.5 / (resolution as TextureCoordinate);
-    let depth_fudge = Vector3::new(0., 0., half_texel);
+    let half_voxel = 0.5 / (resolution as TextureCoordinate);
+    let half_texel = 0.5 / (texture_resolution as TextureCoordinate);
+    let depth_fudge = Vector3::new(0., 0., half_voxel);
 
     let (clamp_min, clamp_max) = match coloring {
         QuadColoring::Solid(_) => (Vector3::zero(), Vector3::zero()),
-        QuadColoring::Texture(tile, scale) => (
-            tile.texcoord(
-                transform_t
-                    .transform_point(Point3 {
-                        x: low_corner.x as TextureCoordinate + half_texel,
-                        y: low_corner.y as TextureCoordinate + half_texel,
-                        z: depth as TextureCoordinate + half_texel,
-                    })
-                    .to_vec()
-                    * scale,
-            ),
-            tile.texcoord(
-                transform_t
-                    .transform_point(Point3 {
-                        x: high_corner.x as TextureCoordinate - half_texel,
-                        y: high_corner.y as TextureCoordinate - half_texel,
-                        z: depth as TextureCoordinate + half_texel,
-                    })
-                    .to_vec()
-                    * scale,
-            ),
-        ),
+        QuadColoring::Texture(tile, scale) => {
+            // Keep the clamp range from inverting when the quad is narrower than a texel,
+            // which happens when the voxels were sampled down to fit the tile.
+            let clamp_range = |low: FreeCoordinate, high: FreeCoordinate| {
+                let (low, high) = (low as TextureCoordinate, high as TextureCoordinate);
+                if high - low >= 2. * half_texel {
+                    (low + half_texel, high - half_texel)
+                } else {
+                    let middle = (low + high) / 2.;
+                    (middle, middle)
+                }
+            };
+            let (x_min, x_max) = clamp_range(low_corner.x, high_corner.x);
+            let (y_min, y_max) = clamp_range(low_corner.y, high_corner.y);
+            let z = depth as TextureCoordinate + half_voxel;
+            (
+                tile.texcoord(
+                    transform_t
+                        .transform_point(Point3::new(x_min, y_min, z))
+                        .to_vec()
+                        * scale,
+                ),
+                tile.texcoord(
+                    transform_t
+                        .transform_point(Point3::new(x_max, y_max, z))
+                        .to_vec()
+                        * scale,
+                ),
+            )
+        }
     };
 
     for &p in QUAD_VERTICES {
@@ -8,8 +8,9 @@ use std::fmt::Debug;
 use std::ops::Range;
 
 use crate::camera::{GraphicsOptions, LightingOption};
-use crate::math::{Face, FaceMap, GridCoordinate, GridRotation};
+use crate::math::{Face, FaceMap, GridCoordinate, GridPoint, GridRotation};
 use crate::space::{BlockIndex, Grid, PackedLight, Space};
+use crate::trace::trace_span;
 use crate::triangulator::{BlockTriangulation, GfxVertex};
 
 /// Computes a triangle mesh of a [`Space`].
@@ -17,13 +18,14 @@ use crate::triangulator::{BlockTriangulation, GfxVertex};
 /// Shorthand for
 /// <code>[SpaceTriangulation::new()].[compute](SpaceTriangulation::compute)(space, bounds, block_triangulations)</code>.
 #[inline]
-pub fn triangulate_space<'p, V, T, P>(
-    space: &Space,
+pub fn triangulate_space<'p, S, V, T, P>(
+    space: &S,
     bounds: Grid,
     options: &GraphicsOptions,
     block_triangulations: P,
 ) -> SpaceTriangulation<V>
 where
+    S: SpaceTriangulationSource + ?Sized,
     V: GfxVertex + 'p,
     P: BlockTriangulationProvider<'p, V, T>,
     T: 'p,
@@ -84,6 +86,15 @@ impl<V> SpaceTriangulation<V> {
         &self.indices
     }
 
+    /// Modifies every vertex with `f`, such as to move a copy of the triangulation to a
+    /// different location.
+    ///
+    /// `f` should preserve the relative positions of vertices (that is, be a
+    /// translation), or the orderings of [`Self::transparent_range`] will be incorrect.
+    pub fn map_vertices_in_place(&mut self, f: impl FnMut(&mut V)) {
+        self.vertices.iter_mut().for_each(f);
+    }
+
     /// True if there is nothing to draw.
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -137,18 +148,23 @@ impl<V: GfxVertex> SpaceTriangulation<V> {
     /// `block_triangulations` (as opposed to, for example, using face opacity data not the
     /// same as the meshes and thus producing a rendering with gaps in it).
     ///
+    /// `space` is usually a [`Space`], but may be any [`SpaceTriangulationSource`] which
+    /// has the contents of `bounds` and the cubes adjacent to it.
+    ///
     /// [`triangulate_blocks`]: super::triangulate_blocks
-    pub fn compute<'p, T, P>(
+    pub fn compute<'p, S, T, P>(
         &mut self,
-        space: &Space,
+        space: &S,
         bounds: Grid,
         options: &GraphicsOptions,
         mut block_triangulations: P,
     ) where
+        S: SpaceTriangulationSource + ?Sized,
         P: BlockTriangulationProvider<'p, V, T>,
         V: 'p,
         T: 'p,
     {
+        let _span = trace_span!("triangulate_space");
         // TODO: On out-of-range, draw an obviously invalid block instead of an invisible one?
         // If we do this, we'd make it the provider's responsibility
         let empty_render = BlockTriangulation::<V, T>::default();
@@ -335,6 +351,32 @@ impl<GV> Default for SpaceTriangulation<GV> {
     }
 }
 
+/// Source of the block indices and light of the cubes to triangulate, for
+/// [`SpaceTriangulation::compute`].
+///
+/// This is implemented by [`Space`], and may be implemented by a copy of part of one
+/// so that the triangulation can be computed without access to the [`Space`].
+pub trait SpaceTriangulationSource {
+    /// Returns the index of the block at `cube`, or [`None`] if it is outside the space.
+    /// See [`Space::get_block_index`].
+    fn get_block_index(&self, cube: GridPoint) -> Option<BlockIndex>;
+
+    /// Returns the light at `cube`. See [`Space::get_lighting`].
+    ///
+    /// This is called only if the vertex type [wants light](GfxVertex::WANTS_LIGHT).
+    fn get_lighting(&self, cube: GridPoint) -> PackedLight;
+}
+impl SpaceTriangulationSource for Space {
+    #[inline]
+    fn get_block_index(&self, cube: GridPoint) -> Option<BlockIndex> {
+        Space::get_block_index(self, cube)
+    }
+    #[inline]
+    fn get_lighting(&self, cube: GridPoint) -> PackedLight {
+        Space::get_lighting(self, cube)
+    }
+}
+
 /// Source of [`BlockTriangulation`] values for [`SpaceTriangulation::compute`].
 ///
 /// This trait allows the caller of [`SpaceTriangulation::compute`] to provide an
@@ -3,7 +3,7 @@
 
 //! Tests for [`crate::triangulator`].
 
-use cgmath::{MetricSpace as _, Point3, Transform as _, Vector3};
+use cgmath::{EuclideanSpace as _, MetricSpace as _, Point3, Transform as _, Vector3};
 
 use super::*;
 use crate::block::{Block, BlockAttributes, Resolution, AIR};
@@ -114,6 +114,31 @@ fn excludes_hidden_faces_of_blocks() {
     );
 }
 
+#[test]
+fn hollow_triangulation_still_hides_neighbors() {
+    let [hollowed, drawn] = make_some_blocks();
+    let mut space = Space::empty_positive(2, 1, 1);
+    space.set((0, 0, 0), &hollowed).unwrap();
+    space.set((1, 0, 0), &drawn).unwrap();
+    let mut block_triangulations: BlockTriangulations<BlockVertex, _> = triangulate_blocks(
+        &space,
+        &mut TestTextureAllocator::new(16),
+        &TransparencyOption::Volumetric,
+    );
+    let index = usize::from(space.get_block_index((0, 0, 0)).unwrap());
+    block_triangulations[index] = block_triangulations[index].hollow();
+
+    let space_tri: SpaceTriangulation<BlockVertex> = triangulate_space(
+        &space,
+        space.grid(),
+        &GraphicsOptions::default(),
+        &*block_triangulations,
+    );
+    // Only the faces of the second block, except the one facing the first, are drawn.
+    assert_eq!(space_tri.vertices().len(), 4 * 5);
+    assert!(space_tri.vertices().iter().all(|v| v.position.x >= 1.0));
+}
+
 /// Run [`triangulate_space`] with stale block data and confirm it does not panic.
 #[test]
 fn no_panic_on_missing_blocks() {
@@ -235,6 +260,61 @@ fn block_resolution_greater_than_tile() {
     // TODO: Figure out how to make a useful assert. At least this is "it doesn't panic".
 }
 
+/// Blocks of any resolution, whether or not it is a power of two or fits in the
+/// texture tile, should produce geometry within the block and texture coordinates
+/// within the tile.
+#[test]
+fn coordinates_in_bounds_at_any_resolution() {
+    let mut u = Universe::new();
+    for &block_resolution in &[1, 2, 3, 5, 7, 8, 9, 15, 16, 17, 31, 33, 64] {
+        let block = Block::builder()
+            .voxels_fn(&mut u, block_resolution, non_uniform_fill)
+            .unwrap()
+            .build();
+        let evaluated = block.evaluate().unwrap();
+        for &tile_resolution in &[1, 4, 7, 16] {
+            let mut tex = TestTextureAllocator::new(tile_resolution);
+            let triangulation: BlockTriangulation<BlockVertex, _> =
+                triangulate_block(&evaluated, &mut tex, &TransparencyOption::Volumetric);
+            let context = format!("block {} tile {}", block_resolution, tile_resolution);
+
+            // A single voxel needs no texture; anything more is textured.
+            assert_eq!(
+                tex.count_allocated(),
+                if block_resolution > 1 { 1 } else { 0 },
+                "{}",
+                context
+            );
+            let in_unit = |v: Vector3<FreeCoordinate>| (0..3).all(|i| (0.0..=1.0).contains(&v[i]));
+            let in_tile =
+                |v: Vector3<TextureCoordinate>| (0..3).all(|i| (0.0..=1.0).contains(&v[i]));
+            for (_, face_triangulation) in triangulation.faces.iter() {
+                for vertex in face_triangulation.vertices.iter() {
+                    assert!(
+                        in_unit(vertex.position.to_vec()),
+                        "{} {:?}",
+                        context,
+                        vertex
+                    );
+                    if let Coloring::Texture {
+                        pos,
+                        clamp_min,
+                        clamp_max,
+                    } = vertex.coloring
+                    {
+                        assert!(
+                            in_tile(pos) && in_tile(clamp_min) && in_tile(clamp_max),
+                            "{} {:?}",
+                            context,
+                            vertex
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// Check for hidden surfaces being given internal geometry.
 /// Exercise the “shrinkwrap” logic that generates geometry no larger than necessary.
 #[test]
@@ -502,7 +582,7 @@ fn space_triangulation_empty() {
     let t = SpaceTriangulation::<BlockVertex>::new();
     assert!(t.is_empty());
     assert_eq!(t.vertices(), &[]);
-    assert_eq!(t.indices(), &[]);
+    assert_eq!(t.indices(), &[] as &[u32]);
 }
 
 #[test]
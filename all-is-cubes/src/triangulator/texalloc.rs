@@ -50,12 +50,42 @@ pub trait TextureTile: Clone {
     fn write(&mut self, data: &[Texel]);
 }
 
+/// Returns the number of texels along each edge of a block of `block_resolution` when
+/// it is written into a tile of `tile_resolution` by [`copy_voxels_to_texture`].
+///
+/// If the block has fewer voxels than the tile, the rest of the tile is unused; if it
+/// has more, the voxels are sampled to fit the tile.
+pub(super) fn block_texture_resolution(
+    block_resolution: GridCoordinate,
+    tile_resolution: GridCoordinate,
+) -> GridCoordinate {
+    block_resolution.min(tile_resolution)
+}
+
+/// Allocates a tile and writes `voxels`, which belong to a block of `block_resolution`,
+/// into it.
+///
+/// The voxel data occupies the low corner of the tile, with
+/// [`block_texture_resolution`] texels along each axis; each texel takes the color
+/// of the voxel nearest its center.
 pub(super) fn copy_voxels_to_texture<A: TextureAllocator>(
     texture_allocator: &mut A,
     voxels: &GridArray<Evoxel>,
+    block_resolution: GridCoordinate,
 ) -> Option<A::Tile> {
     texture_allocator.allocate().map(|mut texture| {
         let tile_resolution = texture_allocator.resolution();
+        let used_resolution = block_texture_resolution(block_resolution, tile_resolution);
+        // Voxel coordinate whose color texel coordinate `t` should have, or `None` if
+        // the texel is outside the block's part of the tile.
+        let sample = |t: GridCoordinate| -> Option<GridCoordinate> {
+            if t < used_resolution {
+                Some((2 * t + 1) * block_resolution / (2 * used_resolution))
+            } else {
+                None
+            }
+        };
+
         let mut tile_texels: Vec<Texel> = Vec::with_capacity((tile_resolution as usize).pow(3));
         // Note that this is row-major order whereas `Grid` uses column-major order, so
         // expressing this with `Grid::interior_iter` would require shuffling the texture
@@ -64,9 +94,12 @@ pub(super) fn copy_voxels_to_texture<A: TextureAllocator>(
         for z in 0..tile_resolution {
             for y in 0..tile_resolution {
                 for x in 0..tile_resolution {
+                    let voxel = match (sample(x), sample(y), sample(z)) {
+                        (Some(x), Some(y), Some(z)) => voxels.get([x, y, z]),
+                        _ => None,
+                    };
                     tile_texels.push(
-                        voxels
-                            .get([x, y, z])
+                        voxel
                             .unwrap_or(&Evoxel::new(palette::MISSING_VOXEL_FALLBACK))
                             .color
                             .to_linear_32bit(),
@@ -154,6 +187,10 @@ impl TextureTile for TestTextureTile {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::math::Rgba;
+    use crate::space::Grid;
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
     /// Test the [`TestTextureAllocator`].
     #[test]
@@ -168,4 +205,66 @@ mod tests {
         assert!(allocator.allocate().is_some());
         assert!(allocator.allocate().is_none());
     }
+
+    /// Allocator whose single tile keeps the data written to it.
+    struct CaptureAllocator(GridCoordinate, Rc<RefCell<Vec<Texel>>>);
+    #[derive(Clone)]
+    struct CaptureTile(Rc<RefCell<Vec<Texel>>>);
+    impl TextureAllocator for CaptureAllocator {
+        type Tile = CaptureTile;
+        fn resolution(&self) -> GridCoordinate {
+            self.0
+        }
+        fn allocate(&mut self) -> Option<Self::Tile> {
+            Some(CaptureTile(self.1.clone()))
+        }
+    }
+    impl TextureTile for CaptureTile {
+        fn texcoord(&self, in_tile: Vector3<TextureCoordinate>) -> Vector3<TextureCoordinate> {
+            in_tile
+        }
+        fn write(&mut self, data: &[Texel]) {
+            *self.0.borrow_mut() = data.to_vec();
+        }
+    }
+
+    /// Voxels are copied one-to-one if they fit in the tile, and sampled if they don't,
+    /// whatever the resolutions are.
+    #[test]
+    fn copy_voxels_at_any_resolution() {
+        let tile_resolution = 4;
+        for block_resolution in 1..=9 {
+            let voxels = GridArray::from_fn(Grid::for_block(block_resolution), |cube| {
+                Evoxel::new(Rgba::new(cube.x as f32 / 10., 0.0, 0.0, 1.0))
+            });
+            let block_g = GridCoordinate::from(block_resolution);
+            let data = Rc::new(RefCell::new(Vec::new()));
+            let mut allocator = CaptureAllocator(tile_resolution, data.clone());
+            copy_voxels_to_texture(&mut allocator, &voxels, block_g).unwrap();
+
+            let data = data.borrow();
+            assert_eq!(data.len(), 4 * 4 * 4);
+            let used = block_texture_resolution(block_g, tile_resolution);
+            for x in 0..tile_resolution {
+                let expected = if x < used {
+                    // Same rounding as the implementation, but checked for sanity below.
+                    let voxel_x = (2 * x + 1) * block_g / (2 * used);
+                    assert!(voxel_x < block_g);
+                    if block_g <= tile_resolution {
+                        assert_eq!(voxel_x, x);
+                    }
+                    voxels[[voxel_x, 0, 0]].color
+                } else {
+                    palette::MISSING_VOXEL_FALLBACK
+                };
+                assert_eq!(
+                    data[x as usize],
+                    expected.to_linear_32bit(),
+                    "block {} texel {}",
+                    block_resolution,
+                    x
+                );
+            }
+        }
+    }
 }
@@ -8,6 +8,7 @@ use owning_ref::{OwningHandle, OwningRef, OwningRefMut};
 use std::borrow::{Borrow, BorrowMut};
 use std::cell::{Ref, RefCell, RefMut};
 use std::collections::hash_map::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{self, Debug, Display};
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
@@ -16,13 +17,33 @@ use std::rc::{Rc, Weak};
 use std::time::Duration;
 
 use crate::apps::Tick;
-use crate::block::BlockDef;
+use crate::block::{BlockContentHash, BlockDef, EvaluatedBlock};
 use crate::character::Character;
-use crate::space::{Space, SpaceStepInfo};
-use crate::transactions::Transaction as _;
+use crate::math::SeededRng;
+use crate::space::{Space, SpaceActivity, SpaceStepInfo};
+use crate::trace::trace_span;
+use crate::transactions::{Transaction as _, UniverseTransaction};
 use crate::util::{CustomFormat, StatusText, TypeName};
 
+mod bookmark;
+pub use bookmark::*;
+mod metadata;
+pub use metadata::*;
+mod schedule;
+pub use schedule::*;
+mod validate;
+pub use validate::*;
+
+/// Smallest value accepted by [`Universe::set_time_scale`].
+pub const MIN_TIME_SCALE: f64 = 0.1;
+/// Largest value accepted by [`Universe::set_time_scale`].
+pub const MAX_TIME_SCALE: f64 = 10.0;
+
 /// Name/key of an object in a [`Universe`].
+///
+/// Specific names may be hierarchical, with [`Name::SEPARATOR`] dividing them into
+/// folders, as in `demo/city/lamp`. Folders exist only by virtue of the names within them;
+/// see [`Universe::folder_contents`] for browsing them.
 #[allow(clippy::exhaustive_enums)]
 #[derive(Clone, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
 pub enum Name {
@@ -31,6 +52,88 @@ pub enum Name {
     /// An automatically assigned name.
     Anonym(usize),
 }
+impl Name {
+    /// Separator between the folders and final component of a hierarchical name.
+    pub const SEPARATOR: char = '/';
+
+    /// Constructs the name of `base_name` within `folder`, or a top-level name if `folder`
+    /// is empty.
+    ///
+    /// ```
+    /// use all_is_cubes::universe::Name;
+    ///
+    /// assert_eq!(Name::in_folder("demo/city", "lamp"), Name::from("demo/city/lamp"));
+    /// assert_eq!(Name::in_folder("", "lamp"), Name::from("lamp"));
+    /// ```
+    pub fn in_folder(folder: &str, base_name: &str) -> Self {
+        Self::Specific(join_path(folder, base_name))
+    }
+
+    /// Returns the folder containing this name (everything before the last
+    /// [`Name::SEPARATOR`]), or [`None`] if it is a top-level or anonymous name.
+    pub fn folder(&self) -> Option<&str> {
+        match self {
+            Name::Specific(name) => name.rsplit_once(Self::SEPARATOR).map(|(folder, _)| folder),
+            Name::Anonym(_) => None,
+        }
+    }
+
+    /// Returns the last component of this name (everything after the last
+    /// [`Name::SEPARATOR`]), or [`None`] if it is an anonymous name.
+    pub fn base_name(&self) -> Option<&str> {
+        match self {
+            Name::Specific(name) => Some(
+                name.rsplit_once(Self::SEPARATOR)
+                    .map_or(name.as_str(), |(_, base)| base),
+            ),
+            Name::Anonym(_) => None,
+        }
+    }
+
+    /// Returns whether this name is within `folder`, directly or in a subfolder.
+    /// Every specific name is within the root folder, `""`.
+    ///
+    /// ```
+    /// use all_is_cubes::universe::Name;
+    ///
+    /// let name = Name::from("demo/city/lamp");
+    /// assert!(name.is_in_folder("demo"));
+    /// assert!(name.is_in_folder("demo/city"));
+    /// assert!(!name.is_in_folder("demo/cit"));
+    /// assert!(!name.is_in_folder("demo/city/lamp"));
+    /// ```
+    pub fn is_in_folder(&self, folder: &str) -> bool {
+        self.path_within(folder).is_some()
+    }
+
+    /// If this name is within `folder`, returns the rest of the name after it.
+    fn path_within(&self, folder: &str) -> Option<&str> {
+        match self {
+            Name::Specific(name) if folder.is_empty() => Some(name),
+            Name::Specific(name) => name
+                .strip_prefix(folder)?
+                .strip_prefix(Self::SEPARATOR)
+                .filter(|rest| !rest.is_empty()),
+            Name::Anonym(_) => None,
+        }
+    }
+
+    /// Returns whether this name may be used in a [`Universe`]: hierarchical names may
+    /// not have empty components.
+    fn is_valid(&self) -> bool {
+        match self {
+            Name::Specific(name) => name.split(Self::SEPARATOR).all(|part| !part.is_empty()),
+            Name::Anonym(_) => true,
+        }
+    }
+}
+fn join_path(folder: &str, base_name: &str) -> String {
+    if folder.is_empty() {
+        base_name.to_string()
+    } else {
+        format!("{}{}{}", folder, Name::SEPARATOR, base_name)
+    }
+}
 impl From<&str> for Name {
     fn from(value: &str) -> Self {
         Self::Specific(value.to_string())
@@ -49,11 +152,48 @@ impl Display for Name {
 /// future, it will enable garbage collection and inter-object invariants.
 ///
 /// See also the [`UniverseIndex`] trait for methods for adding and removing objects.
+///
+/// ```
+/// use all_is_cubes::space::Space;
+/// use all_is_cubes::universe::{Name, Universe, UniverseIndex as _};
+///
+/// let mut universe = Universe::new();
+/// let overworld = Name::from("overworld");
+/// universe.insert(overworld.clone(), Space::empty_positive(1, 1, 1)).unwrap();
+///
+/// let space_ref = universe.get::<Space>(&overworld).unwrap();
+/// assert!(universe.contains::<Space>(&overworld));
+///
+/// universe.rename::<Space>(&overworld, Name::from("underworld")).unwrap();
+/// assert!(!universe.contains::<Space>(&overworld));
+/// assert!(space_ref.try_borrow().is_ok());
+///
+/// universe.remove::<Space>(&Name::from("underworld")).unwrap();
+/// assert!(space_ref.try_borrow().is_err());
+/// ```
 pub struct Universe {
     blocks: HashMap<Name, URootRef<BlockDef>>,
     characters: HashMap<Name, URootRef<Character>>,
     spaces: HashMap<Name, URootRef<Space>>,
     next_anonym: usize,
+    metadata: UniverseMetadata,
+    /// See [`Universe::set_bookmark`].
+    bookmarks: BTreeMap<String, Bookmark>,
+    /// Number of times [`Universe::step`] has been called, for scheduling
+    /// [`SpaceActivity::Background`] spaces.
+    step_count: u64,
+    /// See [`Universe::set_step_budget`].
+    step_budget: Option<Duration>,
+    /// See [`Universe::set_paused`].
+    paused: bool,
+    /// Number of steps requested by [`Universe::step_once`] and not yet taken.
+    pending_single_steps: usize,
+    /// See [`Universe::set_time_scale`].
+    time_scale: f64,
+    /// See [`Universe::schedule`].
+    scheduled: Schedule<UniverseTransaction>,
+    /// See [`Universe::rng_mut`].
+    rng: SeededRng,
 }
 
 impl Universe {
@@ -65,26 +205,278 @@ impl Universe {
             // TODO: bodies so body-in-world stepping
             characters: HashMap::new(),
             next_anonym: 0,
+            metadata: UniverseMetadata::default(),
+            bookmarks: BTreeMap::new(),
+            step_count: 0,
+            step_budget: None,
+            paused: false,
+            pending_single_steps: 0,
+            time_scale: 1.0,
+            scheduled: Schedule::new(),
+            rng: SeededRng::new(0),
         }
     }
 
+    /// Returns the descriptive information about this universe.
+    pub fn metadata(&self) -> &UniverseMetadata {
+        &self.metadata
+    }
+
+    /// Returns the descriptive information about this universe, for modification.
+    pub fn metadata_mut(&mut self) -> &mut UniverseMetadata {
+        &mut self.metadata
+    }
+
+    /// Returns the bookmark with the given name, if there is one.
+    pub fn bookmark(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.get(name)
+    }
+
+    /// Returns all bookmarks in this universe, sorted by name, as for a menu of places
+    /// to go.
+    pub fn bookmarks(&self) -> impl Iterator<Item = (&str, &Bookmark)> + '_ {
+        self.bookmarks
+            .iter()
+            .map(|(name, bookmark)| (name.as_str(), bookmark))
+    }
+
+    /// Stores a bookmark under the given name, returning the bookmark previously stored
+    /// under that name, if any.
+    ///
+    /// ```
+    /// use all_is_cubes::camera::CameraPose;
+    /// use all_is_cubes::character::Character;
+    /// use all_is_cubes::space::Space;
+    /// use all_is_cubes::universe::{Bookmark, Universe};
+    ///
+    /// let mut universe = Universe::new();
+    /// let space = universe.insert_anonymous(Space::empty_positive(10, 10, 10));
+    /// universe.set_bookmark(
+    ///     "corner",
+    ///     Bookmark::new(space.clone(), CameraPose::new([2.0, 3.0, 2.0], 45.0, 0.0)),
+    /// );
+    /// assert_eq!(universe.bookmarks().map(|(name, _)| name).collect::<Vec<_>>(), ["corner"]);
+    ///
+    /// let mut character = Character::spawn_default(space);
+    /// character.teleport(universe.bookmark("corner").unwrap()).unwrap();
+    /// assert_eq!(character.body.position, [2.0, 3.0, 2.0].into());
+    /// ```
+    pub fn set_bookmark(
+        &mut self,
+        name: impl Into<String>,
+        bookmark: Bookmark,
+    ) -> Option<Bookmark> {
+        self.bookmarks.insert(name.into(), bookmark)
+    }
+
+    /// Removes and returns the bookmark with the given name, if there is one.
+    pub fn remove_bookmark(&mut self, name: &str) -> Option<Bookmark> {
+        self.bookmarks.remove(name)
+    }
+
     // TODO: temporary shortcuts to be replaced with more nuance
     pub fn get_default_character(&self) -> Option<URef<Character>> {
         self.get(&"character".into())
     }
 
+    /// Returns the time limit set by [`Universe::set_step_budget`].
+    pub fn step_budget(&self) -> Option<Duration> {
+        self.step_budget
+    }
+
+    /// Sets a limit on how long each [`Universe::step`] should spend on lighting
+    /// updates, or [`None`] for no limit other than the fixed amount of work each space
+    /// does per step (the default).
+    ///
+    /// The budget is divided among the spaces stepped, with any time one space leaves
+    /// unused being available to the spaces stepped after it. Each space still does at
+    /// least one update per step, so lighting always makes progress, and the time taken
+    /// by behaviors and other work is not limited.
+    pub fn set_step_budget(&mut self, budget: Option<Duration>) {
+        self.step_budget = budget;
+    }
+
+    /// Returns whether the simulation is paused by [`Universe::set_paused`].
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses or unpauses the simulation. While paused, [`Universe::step`] steps
+    /// everything with a [paused](Tick::paused) tick, so that behaviors and physics
+    /// do not act, except for steps requested by [`Universe::step_once`].
+    ///
+    /// This is independent of the ticks passed to [`Universe::step`], so that other
+    /// universes, such as the user interface's, may keep running normally.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if !paused {
+            self.pending_single_steps = 0;
+        }
+    }
+
+    /// While [paused](Universe::set_paused), makes the next call to [`Universe::step`]
+    /// advance the simulation by one tick as if it were not paused. Calling this
+    /// several times before stepping requests that many steps. Has no effect if not
+    /// paused.
+    pub fn step_once(&mut self) {
+        if self.paused {
+            self.pending_single_steps += 1;
+        }
+    }
+
+    /// Returns the factor set by [`Universe::set_time_scale`].
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Sets the factor by which the length of each tick passed to [`Universe::step`] is
+    /// multiplied before being used, so that the simulation runs in slow or fast motion
+    /// without changing how often it is stepped. The default is 1.
+    ///
+    /// The value is clamped to the range [`MIN_TIME_SCALE`]..=[`MAX_TIME_SCALE`];
+    /// NaN is treated as 1.
+    pub fn set_time_scale(&mut self, scale: f64) {
+        self.time_scale = if scale.is_nan() {
+            1.0
+        } else {
+            scale.max(MIN_TIME_SCALE).min(MAX_TIME_SCALE)
+        };
+    }
+
+    /// Schedules `transaction` to be executed after `delay` more steps of this universe
+    /// which are not [paused](Tick::paused), such as to have an effect some time after
+    /// its cause. A `delay` of zero is treated as one. Transactions due on the same step
+    /// are executed in the order they were scheduled, at the start of that step.
+    ///
+    /// A transaction which fails its preconditions when it is due is discarded.
+    ///
+    /// Behaviors and other sources of transactions may schedule transactions with
+    /// [`UniverseTransaction::schedule`].
+    ///
+    /// ```
+    /// use all_is_cubes::apps::Tick;
+    /// use all_is_cubes::prelude::*;
+    /// use all_is_cubes::space::SpaceTransaction;
+    /// use all_is_cubes::transactions::Transaction as _;
+    ///
+    /// let mut universe = Universe::new();
+    /// let mut space = Space::empty_positive(1, 1, 1);
+    /// space.set([0, 0, 0], Rgba::WHITE).unwrap();
+    /// let space = universe.insert_anonymous(space);
+    ///
+    /// // A block which vanishes after two steps.
+    /// let vanish = SpaceTransaction::set_cube([0, 0, 0], None, Some(AIR));
+    /// universe.schedule(2, vanish.bind(space.clone()));
+    /// universe.step(Tick::arbitrary());
+    /// assert_ne!(space.borrow()[[0, 0, 0]], AIR);
+    /// universe.step(Tick::arbitrary());
+    /// assert_eq!(space.borrow()[[0, 0, 0]], AIR);
+    /// ```
+    pub fn schedule(&mut self, delay: u64, transaction: UniverseTransaction) -> ScheduledEventId {
+        self.scheduled.schedule(delay, transaction)
+    }
+
+    /// Cancels a transaction added by [`Universe::schedule`]. Returns whether it had
+    /// not already been executed or cancelled.
+    pub fn cancel_scheduled(&mut self, id: ScheduledEventId) -> bool {
+        self.scheduled.cancel(id).is_some()
+    }
+
+    /// Returns the transactions added by [`Universe::schedule`] which have not yet been
+    /// executed.
+    pub fn scheduled(&self) -> &Schedule<UniverseTransaction> {
+        &self.scheduled
+    }
+
+    /// Returns the random number generator from which the behaviors of characters are
+    /// given random numbers, for other uses which should be reproducible along with
+    /// them. Each [`Space`] has its own; see [`Space::rng_mut`].
+    pub fn rng_mut(&mut self) -> &mut SeededRng {
+        &mut self.rng
+    }
+
+    /// Replaces this universe's random number generator with one seeded by `seed`.
+    /// Every universe starts with a seed of zero.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = SeededRng::new(seed);
+    }
+
+    /// Applies the pause and time scale settings to a tick passed to
+    /// [`Universe::step`].
+    fn simulation_tick(&mut self, tick: Tick) -> Tick {
+        let mut scaled = Tick::from_duration(tick.delta_t.mul_f64(self.time_scale));
+        if tick.paused() {
+            scaled = scaled.pause();
+        } else if self.paused {
+            if self.pending_single_steps > 0 {
+                self.pending_single_steps -= 1;
+            } else {
+                scaled = scaled.pause();
+            }
+        }
+        scaled
+    }
+
     /// Advance time for all members.
+    ///
+    /// The tick is adjusted by the simulation controls [`Universe::set_paused`],
+    /// [`Universe::step_once`], and [`Universe::set_time_scale`]. Spaces are stepped
+    /// according to their [`Space::activity`].
     pub fn step(&mut self, tick: Tick) -> UniverseStepInfo {
+        let _span = trace_span!("universe_step");
         let mut info = UniverseStepInfo::default();
         let start_time = Instant::now();
+        let tick = self.simulation_tick(tick);
+        let step_number = self.step_count;
+        self.step_count = self.step_count.wrapping_add(1);
+
+        if !tick.paused() {
+            for t in self.scheduled.advance() {
+                if let Err(e) = t.execute(self) {
+                    log::info!("Scheduled transaction failure: {}", e);
+                }
+            }
+        }
 
         let mut transactions = Vec::new();
 
-        for space in self.spaces.values() {
+        let scheduled: Vec<(&URootRef<Space>, Tick)> = self
+            .spaces
+            .values()
+            .filter_map(|space| {
+                let activity = space
+                    .try_borrow()
+                    .expect("space borrowed during universe.step()")
+                    .activity();
+                match activity {
+                    SpaceActivity::Active => Some((space, tick)),
+                    SpaceActivity::Background { divisor } => {
+                        let divisor = divisor.max(1);
+                        (step_number % u64::from(divisor) == 0).then(|| {
+                            let mut space_tick = Tick::from_duration(tick.delta_t * divisor);
+                            if tick.paused() {
+                                space_tick = space_tick.pause();
+                            }
+                            (space, space_tick)
+                        })
+                    }
+                    SpaceActivity::Paused => Some((space, tick.pause())),
+                }
+            })
+            .collect();
+        let scheduled_count = scheduled.len();
+        for (i, (space, space_tick)) in scheduled.into_iter().enumerate() {
+            let deadline = self.step_budget.map(|budget| {
+                let now = Instant::now();
+                let remaining = budget
+                    .checked_sub(now.duration_since(start_time))
+                    .unwrap_or(Duration::ZERO);
+                now + remaining / (scheduled_count - i) as u32
+            });
             let (space_info, transaction) = space
                 .try_borrow_mut()
                 .expect("space borrowed during universe.step()")
-                .step(Some(&space.downgrade()), tick);
+                .step_with_deadline(Some(&space.downgrade()), space_tick, deadline);
             transactions.push(transaction);
             info.space_step += space_info;
         }
@@ -94,7 +486,7 @@ impl Universe {
             let transaction = character
                 .try_borrow_mut()
                 .expect("character borrowed during universe.step()")
-                .step(Some(&character.downgrade()), tick);
+                .step_with_rng(Some(&character.downgrade()), tick, &mut self.rng);
             transactions.push(transaction);
         }
 
@@ -111,6 +503,104 @@ impl Universe {
         info
     }
 
+    /// Translates a name for an object of type `T` into a [`URef`] for it, as
+    /// [`UniverseIndex::get`] does; this method allows naming the type at the call site,
+    /// as in `universe.get::<Space>(&name)`.
+    pub fn get<T>(&self, name: &Name) -> Option<URef<T>>
+    where
+        Self: UniverseIndex<T>,
+    {
+        UniverseIndex::<T>::get(self, name)
+    }
+
+    /// Returns whether an object of type `T` exists with the given name.
+    pub fn contains<T>(&self, name: &Name) -> bool
+    where
+        Self: UniverseIndex<T>,
+    {
+        UniverseIndex::<T>::get(self, name).is_some()
+    }
+
+    /// Removes the object of type `T` with the given name, as
+    /// [`UniverseIndex::remove`] does; this method allows naming the type at the call
+    /// site.
+    pub fn remove<T>(&mut self, name: &Name) -> Result<T, RemoveError>
+    where
+        Self: UniverseIndex<T>,
+    {
+        UniverseIndex::<T>::remove(self, name)
+    }
+
+    /// Renames the object of type `T` with the given name, as
+    /// [`UniverseIndex::rename`] does; this method allows naming the type at the call
+    /// site.
+    pub fn rename<T>(&mut self, old_name: &Name, new_name: Name) -> Result<URef<T>, RenameError>
+    where
+        Self: UniverseIndex<T>,
+    {
+        UniverseIndex::<T>::rename(self, old_name, new_name)
+    }
+
+    /// Iterates over all the objects of type `T` whose names are within `folder`
+    /// (see [`Name::is_in_folder`]), including those in its subfolders.
+    pub fn iter_in_folder<'u, T: 'u>(
+        &'u self,
+        folder: &'u str,
+    ) -> impl Iterator<Item = (Name, URef<T>)> + 'u
+    where
+        Self: UniverseIndex<T>,
+    {
+        UniverseIndex::<T>::iter_by_type(self).filter(move |(name, _)| name.is_in_folder(folder))
+    }
+
+    /// Lists the immediate contents of `folder`, of any type, in sorted order. Use `""`
+    /// for the top level.
+    ///
+    /// ```
+    /// use all_is_cubes::block::{BlockDef, AIR};
+    /// use all_is_cubes::space::Space;
+    /// use all_is_cubes::universe::{FolderEntry, Name, Universe, UniverseIndex as _};
+    ///
+    /// let mut universe = Universe::new();
+    /// universe.insert("demo/city/lamp".into(), BlockDef::new(AIR)).unwrap();
+    /// universe.insert("demo/city/street".into(), Space::empty_positive(1, 1, 1)).unwrap();
+    /// universe.insert("demo/readme".into(), BlockDef::new(AIR)).unwrap();
+    ///
+    /// assert_eq!(
+    ///     universe.folder_contents("demo"),
+    ///     vec![
+    ///         FolderEntry::Folder("demo/city".into()),
+    ///         FolderEntry::Member(Name::from("demo/readme")),
+    ///     ],
+    /// );
+    /// ```
+    pub fn folder_contents(&self, folder: &str) -> Vec<FolderEntry> {
+        let entries: BTreeSet<FolderEntry> = self
+            .blocks
+            .keys()
+            .chain(self.characters.keys())
+            .chain(self.spaces.keys())
+            .filter_map(|name| {
+                let rest = name.path_within(folder)?;
+                Some(match rest.split_once(Name::SEPARATOR) {
+                    Some((subfolder, _)) => FolderEntry::Folder(join_path(folder, subfolder)),
+                    None => FolderEntry::Member(name.clone()),
+                })
+            })
+            .collect();
+        entries.into_iter().collect()
+    }
+
+    /// Checks the contents of this universe for problems which would not be caught when
+    /// using it, or would be caught only later, such as references to removed objects
+    /// or blocks whose voxels are missing; see [`ValidationProblem`] for the full list.
+    ///
+    /// This is intended for use before saving or after importing data. Objects which are
+    /// currently borrowed mutably cannot be checked, and are reported as such.
+    pub fn validate(&self) -> ValidationReport {
+        validate::validate_universe(self)
+    }
+
     /// Inserts a new object without giving it a specific name, and returns
     /// a reference to it.
     pub fn insert_anonymous<T>(&mut self, value: T) -> URef<T>
@@ -122,6 +612,46 @@ impl Universe {
         self.insert(name, value)
             .expect("shouldn't happen: newly created anonym already in use")
     }
+
+    /// Returns all of the [`BlockDef`]s in this universe whose block has the given tag
+    /// (see [`BlockAttributes::tags`](crate::block::BlockAttributes::tags)), sorted by
+    /// name.
+    ///
+    /// Definitions whose block cannot currently be evaluated, such as because it is
+    /// being modified, are skipped.
+    pub fn find_blocks_with_tag(&self, tag: &str) -> Vec<(Name, URef<BlockDef>)> {
+        self.find_blocks(|evaluated| evaluated.attributes.has_tag(tag))
+    }
+
+    /// Returns all of the [`BlockDef`]s in this universe whose block has the given
+    /// [`BlockContentHash`], sorted by name. This may be used to find the local
+    /// equivalent of a block described in data from elsewhere.
+    ///
+    /// Definitions whose block cannot currently be evaluated are skipped.
+    pub fn find_blocks_with_content_hash(
+        &self,
+        hash: BlockContentHash,
+    ) -> Vec<(Name, URef<BlockDef>)> {
+        self.find_blocks(|evaluated| evaluated.content_hash() == hash)
+    }
+
+    fn find_blocks(
+        &self,
+        mut predicate: impl FnMut(&EvaluatedBlock) -> bool,
+    ) -> Vec<(Name, URef<BlockDef>)> {
+        let mut found: Vec<(Name, URef<BlockDef>)> = self
+            .iter_by_type()
+            .filter(|(_, block_def_ref): &(Name, URef<BlockDef>)| {
+                block_def_ref
+                    .try_borrow()
+                    .ok()
+                    .and_then(|block_def| block_def.evaluate().ok())
+                    .map_or(false, |evaluated| predicate(&evaluated))
+            })
+            .collect();
+        found.sort_by(|(a, _), (b, _)| a.cmp(b));
+        found
+    }
 }
 
 impl std::fmt::Debug for Universe {
@@ -194,6 +724,22 @@ pub trait UniverseIndex<T>: sealed_gimmick::Sealed {
     /// Returns an error if the name is already in use.
     fn insert(&mut self, name: Name, value: T) -> Result<URef<T>, InsertError>;
 
+    /// Removes the object of type `T` with the given name from the universe, and
+    /// returns it.
+    ///
+    /// All [`URef`]s to the object become invalid: borrowing them fails with
+    /// [`RefError::Gone`]. Returns an error, and does not remove the object, if it
+    /// is currently borrowed.
+    fn remove(&mut self, name: &Name) -> Result<T, RemoveError>;
+
+    /// Changes the name of the object of type `T` named `old_name` to `new_name`, and
+    /// returns a [`URef`] to it.
+    ///
+    /// Existing [`URef`]s to the object remain valid and equal to the returned one,
+    /// but their [`URef::name`] is still `old_name`. Returns an error if `new_name` is
+    /// already in use, or the object is currently borrowed.
+    fn rename(&mut self, old_name: &Name, new_name: Name) -> Result<URef<T>, RenameError>;
+
     /// Iterate over all of the objects of type `T`.
     /// Note that this includes anonymous objects.
     ///
@@ -227,6 +773,12 @@ impl UniverseIndex<BlockDef> for Universe {
     fn insert(&mut self, name: Name, value: BlockDef) -> Result<URef<BlockDef>, InsertError> {
         index_insert(self, name, value)
     }
+    fn remove(&mut self, name: &Name) -> Result<BlockDef, RemoveError> {
+        index_remove(self, name)
+    }
+    fn rename(&mut self, old_name: &Name, new_name: Name) -> Result<URef<BlockDef>, RenameError> {
+        index_rename(self, old_name, new_name)
+    }
     fn iter_by_type(&self) -> UniverseIter<'_, BlockDef> {
         UniverseIter(self.table().iter())
     }
@@ -238,6 +790,12 @@ impl UniverseIndex<Character> for Universe {
     fn insert(&mut self, name: Name, value: Character) -> Result<URef<Character>, InsertError> {
         index_insert(self, name, value)
     }
+    fn remove(&mut self, name: &Name) -> Result<Character, RemoveError> {
+        index_remove(self, name)
+    }
+    fn rename(&mut self, old_name: &Name, new_name: Name) -> Result<URef<Character>, RenameError> {
+        index_rename(self, old_name, new_name)
+    }
     fn iter_by_type(&self) -> UniverseIter<'_, Character> {
         UniverseIter(self.table().iter())
     }
@@ -249,6 +807,12 @@ impl UniverseIndex<Space> for Universe {
     fn insert(&mut self, name: Name, value: Space) -> Result<URef<Space>, InsertError> {
         index_insert(self, name, value)
     }
+    fn remove(&mut self, name: &Name) -> Result<Space, RemoveError> {
+        index_remove(self, name)
+    }
+    fn rename(&mut self, old_name: &Name, new_name: Name) -> Result<URef<Space>, RenameError> {
+        index_rename(self, old_name, new_name)
+    }
     fn iter_by_type(&self) -> UniverseIter<'_, Space> {
         UniverseIter(self.table().iter())
     }
@@ -268,6 +832,9 @@ where
 {
     use std::collections::hash_map::Entry::*;
     // TODO: prohibit existing names under any type, not just the same type
+    if !name.is_valid() {
+        return Err(InsertError::InvalidName(name));
+    }
     let table = this.table_mut();
     match table.entry(name.clone()) {
         Occupied(_) => Err(InsertError::AlreadyExists(name)),
@@ -280,6 +847,60 @@ where
     }
 }
 
+fn index_remove<T>(this: &mut Universe, name: &Name) -> Result<T, RemoveError>
+where
+    Universe: UniverseTable<T>,
+{
+    let table = this.table_mut();
+    let root = table
+        .remove(name)
+        .ok_or_else(|| RemoveError::NotFound(name.clone()))?;
+    match Rc::try_unwrap(root.strong_ref) {
+        // With the only strong reference gone, all URefs are now dead.
+        Ok(cell) => Ok(cell.into_inner().data),
+        Err(strong_ref) => {
+            // Someone is borrowing it; put it back.
+            table.insert(
+                name.clone(),
+                URootRef {
+                    strong_ref,
+                    name: root.name,
+                },
+            );
+            Err(RemoveError::InUse(name.clone()))
+        }
+    }
+}
+fn index_rename<T>(
+    this: &mut Universe,
+    old_name: &Name,
+    new_name: Name,
+) -> Result<URef<T>, RenameError>
+where
+    Universe: UniverseTable<T>,
+{
+    if !new_name.is_valid() {
+        return Err(RenameError::InvalidName(new_name));
+    }
+    let table = this.table_mut();
+    if table.contains_key(&new_name) {
+        return Err(RenameError::AlreadyExists(new_name));
+    }
+    let new_name_rc = Rc::new(new_name.clone());
+    table
+        .get(old_name)
+        .ok_or_else(|| RenameError::NotFound(old_name.clone()))?
+        .strong_ref
+        .try_borrow_mut()
+        .map_err(|_| RenameError::InUse(old_name.clone()))?
+        .name = Rc::clone(&new_name_rc);
+    let mut root = table.remove(old_name).unwrap();
+    root.name = new_name_rc;
+    let uref = root.downgrade();
+    table.insert(new_name, root);
+    Ok(uref)
+}
+
 /// Iterator type for [`UniverseIndex::iter_by_type`].
 pub struct UniverseIter<'u, T>(std::collections::hash_map::Iter<'u, Name, URootRef<T>>);
 impl<'u, T> Iterator for UniverseIter<'u, T> {
@@ -303,6 +924,42 @@ impl Default for Universe {
 pub enum InsertError {
     #[error("an object already exists with name {0}")]
     AlreadyExists(Name),
+    #[error("{0} is not a valid name")]
+    InvalidName(Name),
+}
+
+/// An item in a folder of a [`Universe`]; see [`Universe::folder_contents`].
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum FolderEntry {
+    /// A subfolder, given by its full path.
+    Folder(String),
+    /// An object directly within the folder.
+    Member(Name),
+}
+
+/// Errors resulting from attempting to remove an object from a `Universe`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum RemoveError {
+    #[error("no object exists with name {0}")]
+    NotFound(Name),
+    #[error("object {0} is in use and cannot be removed")]
+    InUse(Name),
+}
+
+/// Errors resulting from attempting to rename an object in a `Universe`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum RenameError {
+    #[error("no object exists with name {0}")]
+    NotFound(Name),
+    #[error("an object already exists with name {0}")]
+    AlreadyExists(Name),
+    #[error("{0} is not a valid name")]
+    InvalidName(Name),
+    #[error("object {0} is in use and cannot be renamed")]
+    InUse(Name),
 }
 
 /// Type of a strong reference to an entry in a [`Universe`]. Defined to make types
@@ -328,6 +985,8 @@ pub struct URef<T> {
 }
 
 impl<T: 'static> URef<T> {
+    /// Returns the name of the referenced object, as of when this [`URef`] was obtained;
+    /// see [`UniverseIndex::rename`].
     pub fn name(&self) -> &Rc<Name> {
         &self.name
     }
@@ -396,9 +1055,11 @@ impl<T> PartialEq for URef<T> {
 }
 /// `URef`s are compared by pointer equality.
 impl<T> Eq for URef<T> {}
+/// Consistent with equality, `URef`s are hashed by pointer, not by name, since a renamed
+/// object may have `URef`s with different names.
 impl<T> Hash for URef<T> {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.name.hash(state);
+        self.weak_ref.as_ptr().hash(state);
     }
 }
 
@@ -412,6 +1073,84 @@ impl<T> Clone for URef<T> {
     }
 }
 
+/// A reference to an object in a [`Universe`] which, unlike [`URef`], only permits
+/// reading the object.
+///
+/// This is for handing out access to objects whose recipients should observe but not
+/// modify them, such as renderers, without relying on them to refrain from calling
+/// [`URef::borrow_mut`].
+///
+/// ```
+/// use all_is_cubes::space::Space;
+/// use all_is_cubes::universe::{ReadRef, Universe, UniverseIndex as _};
+///
+/// let mut universe = Universe::new();
+/// let space_ref = universe.insert_anonymous(Space::empty_positive(1, 1, 1));
+/// let read_ref: ReadRef<Space> = space_ref.read_only();
+/// assert_eq!(read_ref.borrow().grid(), space_ref.borrow().grid());
+/// assert_eq!(read_ref, ReadRef::from(space_ref));
+/// ```
+pub struct ReadRef<T>(URef<T>);
+
+impl<T: 'static> ReadRef<T> {
+    /// Returns the name of the referenced object, as [`URef::name`].
+    pub fn name(&self) -> &Rc<Name> {
+        self.0.name()
+    }
+
+    /// Borrow the value, in the sense of [`RefCell::borrow`], and panic on failure.
+    #[track_caller]
+    pub fn borrow(&self) -> UBorrow<T> {
+        self.0.borrow()
+    }
+
+    /// Borrow the value, in the sense of [`RefCell::try_borrow`].
+    pub fn try_borrow(&self) -> Result<UBorrow<T>, RefError> {
+        self.0.try_borrow()
+    }
+
+    /// Returns whether `uref` refers to the same object as this.
+    pub fn is(&self, uref: &URef<T>) -> bool {
+        self.0 == *uref
+    }
+}
+
+impl<T: 'static> URef<T> {
+    /// Returns a [`ReadRef`] to the same object, which does not permit mutation.
+    pub fn read_only(&self) -> ReadRef<T> {
+        ReadRef(self.clone())
+    }
+}
+
+impl<T> From<URef<T>> for ReadRef<T> {
+    fn from(uref: URef<T>) -> Self {
+        ReadRef(uref)
+    }
+}
+
+impl<T> Debug for ReadRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ReadRef({})", self.0.name)
+    }
+}
+/// `ReadRef`s are compared by pointer equality, like [`URef`]s.
+impl<T> PartialEq for ReadRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl<T> Eq for ReadRef<T> {}
+impl<T> Hash for ReadRef<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+impl<T> Clone for ReadRef<T> {
+    fn clone(&self) -> Self {
+        ReadRef(self.0.clone())
+    }
+}
+
 /// Errors resulting from attempting to borrow/dereference a [`URef`].
 #[allow(clippy::exhaustive_enums)] // If this has to change it will be a major semantic change
 #[derive(Clone, Debug, Eq, Hash, PartialEq, thiserror::Error)]
@@ -532,6 +1271,11 @@ impl<T> URootRef<T> {
         }
     }
 
+    /// Borrow the value, in the sense of [`RefCell::try_borrow`].
+    fn try_borrow(&self) -> Result<UBorrow<T>, RefError> {
+        self.downgrade().try_borrow()
+    }
+
     /// Borrow the value mutably, in the sense of [`RefCell::try_borrow_mut`].
     fn try_borrow_mut(&self) -> Result<UBorrowMut<T>, RefError> {
         self.downgrade().try_borrow_mut()
@@ -572,8 +1316,13 @@ mod sealed_gimmick {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::block::AIR;
+    use crate::behavior::{Behavior, BehaviorContext};
+    use crate::block::{Block, AIR};
     use crate::content::make_some_blocks;
+    use crate::math::Rgba;
+    use crate::space::SpaceTransaction;
+    use crate::transactions::UniverseTransaction;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn universe_debug_empty() {
@@ -603,6 +1352,43 @@ Universe {
         );
     }
 
+    #[test]
+    fn find_blocks_with_tag() {
+        let mut u = Universe::new();
+        let stone = Block::builder().color(Rgba::WHITE).tag("stone").build();
+        let lever = Block::builder()
+            .color(Rgba::BLACK)
+            .tag("mechanism")
+            .tag("metal")
+            .build();
+        u.insert("b".into(), BlockDef::new(stone.clone())).unwrap();
+        u.insert("a".into(), BlockDef::new(stone)).unwrap();
+        u.insert("lever".into(), BlockDef::new(lever)).unwrap();
+        u.insert("air".into(), BlockDef::new(AIR)).unwrap();
+
+        let names = |tag: &str| -> Vec<Name> {
+            u.find_blocks_with_tag(tag)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        };
+        assert_eq!(names("stone"), vec!["a".into(), "b".into()]);
+        assert_eq!(names("metal"), vec![Name::from("lever")]);
+        assert_eq!(names("wood"), Vec::<Name>::new());
+    }
+
+    #[test]
+    fn find_blocks_with_content_hash() {
+        let mut u = Universe::new();
+        let [block, other] = make_some_blocks();
+        u.insert("a".into(), BlockDef::new(block.clone())).unwrap();
+        u.insert("b".into(), BlockDef::new(other)).unwrap();
+        let hash = block.evaluate().unwrap().content_hash();
+        let found = u.find_blocks_with_content_hash(hash);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "a".into());
+    }
+
     #[test]
     fn uref_debug() {
         let mut u = Universe::new();
@@ -666,6 +1452,180 @@ Universe {
 
     // TODO: more tests of the hairy reference logic
 
+    #[test]
+    fn name_hierarchy() {
+        let name = Name::from("demo/city/lamp");
+        assert_eq!(name.folder(), Some("demo/city"));
+        assert_eq!(name.base_name(), Some("lamp"));
+        assert_eq!(Name::from("lamp").folder(), None);
+        assert_eq!(Name::from("lamp").base_name(), Some("lamp"));
+        assert_eq!(Name::Anonym(0).base_name(), None);
+        assert!(name.is_in_folder(""));
+        assert!(!Name::Anonym(0).is_in_folder(""));
+    }
+
+    #[test]
+    fn invalid_names_rejected() {
+        let mut u = Universe::new();
+        for bad in &["", "/a", "a/", "a//b"] {
+            assert_eq!(
+                u.insert(Name::from(*bad), BlockDef::new(AIR)).unwrap_err(),
+                InsertError::InvalidName(Name::from(*bad))
+            );
+        }
+        u.insert("a/b".into(), BlockDef::new(AIR)).unwrap();
+        assert_eq!(
+            u.rename::<BlockDef>(&"a/b".into(), "a/".into())
+                .unwrap_err(),
+            RenameError::InvalidName("a/".into())
+        );
+    }
+
+    #[test]
+    fn iter_in_folder_and_contents() {
+        let mut u = Universe::new();
+        for name in &[
+            "ui/hud",
+            "demo/city/lamp",
+            "demo/city/post",
+            "demo/lamp",
+            "demos",
+        ] {
+            u.insert(Name::from(*name), BlockDef::new(AIR)).unwrap();
+        }
+        u.insert("demo/city".into(), Space::empty_positive(1, 1, 1))
+            .unwrap();
+        u.insert_anonymous(BlockDef::new(AIR));
+
+        let mut in_demo: Vec<Name> = u
+            .iter_in_folder::<BlockDef>("demo")
+            .map(|(name, _)| name)
+            .collect();
+        in_demo.sort();
+        assert_eq!(
+            in_demo,
+            vec![
+                Name::from("demo/city/lamp"),
+                Name::from("demo/city/post"),
+                Name::from("demo/lamp"),
+            ]
+        );
+
+        // "demo/city" is both a folder and a member.
+        assert_eq!(
+            u.folder_contents("demo"),
+            vec![
+                FolderEntry::Folder("demo/city".into()),
+                FolderEntry::Member("demo/city".into()),
+                FolderEntry::Member("demo/lamp".into()),
+            ]
+        );
+        assert_eq!(
+            u.folder_contents(""),
+            vec![
+                FolderEntry::Folder("demo".into()),
+                FolderEntry::Folder("ui".into()),
+                FolderEntry::Member("demos".into()),
+            ]
+        );
+        assert_eq!(u.folder_contents("nowhere"), vec![]);
+    }
+
+    #[test]
+    fn typed_get_and_contains() {
+        let mut u = Universe::new();
+        let name = Name::from("overworld");
+        let space_ref = u
+            .insert(name.clone(), Space::empty_positive(1, 1, 1))
+            .unwrap();
+        assert_eq!(u.get::<Space>(&name), Some(space_ref));
+        assert_eq!(u.get::<BlockDef>(&name), None);
+        assert!(u.contains::<Space>(&name));
+        assert!(!u.contains::<Character>(&name));
+        assert!(!u.contains::<Space>(&"elsewhere".into()));
+    }
+
+    #[test]
+    fn remove_invalidates_urefs() {
+        let mut u = Universe::new();
+        let name = Name::from("a");
+        let r = u
+            .insert(name.clone(), Space::empty_positive(1, 2, 3))
+            .unwrap();
+        let removed: Space = u.remove(&name).unwrap();
+        assert_eq!(
+            removed.grid(),
+            crate::space::Grid::new([0, 0, 0], [1, 2, 3])
+        );
+        assert_eq!(
+            r.try_borrow().unwrap_err(),
+            RefError::Gone(Rc::new(name.clone()))
+        );
+        assert!(!u.contains::<Space>(&name));
+        assert_eq!(
+            u.remove::<Space>(&name).unwrap_err(),
+            RemoveError::NotFound(name)
+        );
+    }
+
+    #[test]
+    fn remove_in_use() {
+        let mut u = Universe::new();
+        let name = Name::from("a");
+        let r = u
+            .insert(name.clone(), Space::empty_positive(1, 1, 1))
+            .unwrap();
+        let borrow = r.borrow();
+        assert_eq!(
+            u.remove::<Space>(&name).unwrap_err(),
+            RemoveError::InUse(name.clone())
+        );
+        drop(borrow);
+        assert_eq!(u.get::<Space>(&name), Some(r.clone()));
+        r.borrow_mut(); // still usable
+    }
+
+    #[test]
+    fn rename_keeps_urefs_valid() {
+        let mut u = Universe::new();
+        let (a, b) = (Name::from("a"), Name::from("b"));
+        let old_ref = u.insert(a.clone(), BlockDef::new(AIR)).unwrap();
+        let new_ref = u.rename::<BlockDef>(&a, b.clone()).unwrap();
+
+        assert_eq!(old_ref, new_ref);
+        assert_eq!(**old_ref.name(), a);
+        assert_eq!(**new_ref.name(), b);
+        assert_eq!(**old_ref.borrow(), AIR);
+        assert_eq!(u.get::<BlockDef>(&b), Some(new_ref.clone()));
+        assert!(!u.contains::<BlockDef>(&a));
+        // Equal URefs must hash equally despite the different names.
+        let set: std::collections::HashSet<URef<BlockDef>> =
+            vec![old_ref, new_ref].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn rename_errors() {
+        let mut u = Universe::new();
+        let (a, b) = (Name::from("a"), Name::from("b"));
+        let r = u.insert(a.clone(), BlockDef::new(AIR)).unwrap();
+        u.insert(b.clone(), BlockDef::new(AIR)).unwrap();
+        assert_eq!(
+            u.rename::<BlockDef>(&a, b.clone()).unwrap_err(),
+            RenameError::AlreadyExists(b)
+        );
+        assert_eq!(
+            u.rename::<BlockDef>(&"c".into(), "d".into()).unwrap_err(),
+            RenameError::NotFound("c".into())
+        );
+        let _borrow = r.borrow();
+        assert_eq!(
+            u.rename::<BlockDef>(&a, "d".into()).unwrap_err(),
+            RenameError::InUse(a.clone())
+        );
+        assert!(u.contains::<BlockDef>(&a));
+    }
+
     #[test]
     fn insert_anonymous_makes_distinct_names() {
         let [block_0, block_1] = make_some_blocks();
@@ -691,4 +1651,147 @@ Universe {
             Err(InsertError::AlreadyExists("test_block".into()))
         );
     }
+
+    /// Steps a universe containing only `space`, after changing a block in it so that
+    /// there is lighting work to do, and returns the number of spaces that did work.
+    fn step_spaces_with_work(u: &mut Universe, space: &URef<Space>, block: &Block) -> usize {
+        {
+            let mut space = space.borrow_mut();
+            let new = if space[(0, 0, 0)] == AIR { block } else { &AIR };
+            space.set((0, 0, 0), new).unwrap();
+        }
+        u.step(Tick::arbitrary()).space_step.spaces
+    }
+
+    #[test]
+    fn step_background_space() {
+        let [block] = make_some_blocks();
+        let mut u = Universe::new();
+        let space = u.insert_anonymous(Space::empty_positive(2, 2, 2));
+        space
+            .borrow_mut()
+            .set_activity(SpaceActivity::Background { divisor: 3 });
+        let stepped: Vec<usize> = (0..7)
+            .map(|_| step_spaces_with_work(&mut u, &space, &block))
+            .collect();
+        assert_eq!(stepped, vec![1, 0, 0, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn step_paused_space_still_lights() {
+        let [block] = make_some_blocks();
+        let mut u = Universe::new();
+        let space = u.insert_anonymous(Space::empty_positive(2, 2, 2));
+        space.borrow_mut().set_activity(SpaceActivity::Paused);
+        assert_eq!(step_spaces_with_work(&mut u, &space, &block), 1);
+    }
+
+    #[test]
+    fn step_budget_limits_light_updates() {
+        let [block] = make_some_blocks();
+        let mut u = Universe::new();
+        u.set_step_budget(Some(Duration::ZERO));
+        let space = u.insert_anonymous(Space::empty_positive(3, 3, 3));
+        space.borrow_mut().set((1, 1, 1), &block).unwrap();
+        assert!(space.borrow().light_update_queue_len() > 1);
+        let info = u.step(Tick::arbitrary());
+        assert_eq!(info.space_step.light.update_count, 1);
+    }
+
+    /// Records the ticks it is stepped with (which are only unpaused ones, since
+    /// characters do not step their behaviors while paused).
+    #[derive(Debug)]
+    struct TickRecorder(Arc<Mutex<Vec<Tick>>>);
+    impl Behavior<Character> for TickRecorder {
+        fn step(
+            &self,
+            _context: &BehaviorContext<'_, Character>,
+            tick: Tick,
+        ) -> UniverseTransaction {
+            self.0.lock().unwrap().push(tick);
+            UniverseTransaction::default()
+        }
+
+        fn alive(&self, _context: &BehaviorContext<'_, Character>) -> bool {
+            true
+        }
+
+        fn ephemeral(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn simulation_controls() {
+        let mut u = Universe::new();
+        let space = u.insert_anonymous(Space::empty_positive(1, 1, 1));
+        let mut character = Character::spawn_default(space);
+        let ticks = Arc::new(Mutex::new(Vec::new()));
+        character.add_behavior(TickRecorder(ticks.clone()));
+        u.insert_anonymous(character);
+        let tick = Tick::from_duration(Duration::from_millis(100));
+        let slow = Tick::from_duration(Duration::from_millis(50));
+
+        u.set_time_scale(0.5);
+        u.step(tick);
+        u.set_paused(true);
+        u.step(tick);
+        u.step_once();
+        u.step(tick);
+        u.step(tick);
+        u.set_paused(false);
+        u.step(tick.pause());
+        assert_eq!(ticks.lock().unwrap().len(), 2);
+        u.step(tick);
+        assert_eq!(*ticks.lock().unwrap(), vec![slow, slow, slow]);
+    }
+
+    #[test]
+    fn scheduled_transactions() {
+        let [a, b] = make_some_blocks();
+        let mut u = Universe::new();
+        let space = u.insert_anonymous(Space::empty_positive(1, 1, 1));
+        let set = |old: &Block, new: &Block| {
+            SpaceTransaction::set_cube([0, 0, 0], Some(old.clone()), Some(new.clone()))
+                .bind(space.clone())
+        };
+
+        // A transaction which schedules another, which is not due until the step after
+        // it is executed.
+        u.schedule(
+            1,
+            set(&AIR, &a)
+                .merge(UniverseTransaction::schedule(1, set(&a, &b)))
+                .unwrap(),
+        );
+        assert_eq!(u.scheduled().len(), 1);
+        u.step(Tick::arbitrary());
+        assert_eq!(space.borrow()[[0, 0, 0]], a);
+        assert_eq!(u.scheduled().len(), 1);
+
+        // Paused steps do not count.
+        u.step(Tick::arbitrary().pause());
+        assert_eq!(space.borrow()[[0, 0, 0]], a);
+        u.step(Tick::arbitrary());
+        assert_eq!(space.borrow()[[0, 0, 0]], b);
+        assert!(u.scheduled().is_empty());
+
+        // Cancelling.
+        let id = u.schedule(1, set(&b, &a));
+        assert!(u.cancel_scheduled(id));
+        assert!(!u.cancel_scheduled(id));
+        u.step(Tick::arbitrary());
+        assert_eq!(space.borrow()[[0, 0, 0]], b);
+    }
+
+    #[test]
+    fn time_scale_is_clamped() {
+        let mut u = Universe::new();
+        u.set_time_scale(100.0);
+        assert_eq!(u.time_scale(), MAX_TIME_SCALE);
+        u.set_time_scale(0.0);
+        assert_eq!(u.time_scale(), MIN_TIME_SCALE);
+        u.set_time_scale(f64::NAN);
+        assert_eq!(u.time_scale(), 1.0);
+    }
 }
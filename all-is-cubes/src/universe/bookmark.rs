@@ -0,0 +1,37 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Bookmark`], a saved viewpoint within a [`Universe`](super::Universe).
+
+use crate::camera::CameraPose;
+use crate::character::Character;
+use crate::space::Space;
+use crate::universe::URef;
+
+/// A place in a [`Universe`](super::Universe) which may be returned to, such as by
+/// [`Character::teleport`]; stored in the universe by
+/// [`Universe::set_bookmark`](super::Universe::set_bookmark).
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Bookmark {
+    /// The space the bookmark is in.
+    pub space: URef<Space>,
+    /// The position and look direction of a character's eye, or of a camera.
+    pub pose: CameraPose,
+}
+
+impl Bookmark {
+    pub fn new(space: URef<Space>, pose: CameraPose) -> Self {
+        Self { space, pose }
+    }
+
+    /// Constructs a bookmark of where `character` currently is and which way it is
+    /// looking.
+    pub fn from_character(character: &Character) -> Self {
+        let body = &character.body;
+        Self {
+            space: character.space.clone(),
+            pose: CameraPose::new(body.position, body.yaw, body.pitch),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`UniverseMetadata`], descriptive information about a [`Universe`](super::Universe).
+
+use std::fmt;
+use std::time::SystemTime;
+
+use cgmath::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::{Camera, GraphicsOptions, Viewport};
+use crate::character::Character;
+use crate::raytracer::{ColorBuf, SpaceRaytracer};
+use crate::save::{SaveError, SaveFormat};
+use crate::space::Space;
+use crate::util::{CustomFormat, StatusText};
+
+/// Descriptive information about a [`Universe`](super::Universe), for display to the
+/// user when choosing which one to play, rather than anything affecting its behavior.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct UniverseMetadata {
+    /// Title of the world.
+    pub name: String,
+    /// Who made the world.
+    pub author: String,
+    /// Longer description of the world, in plain text.
+    pub description: String,
+    /// When the world was created, if known.
+    pub created: Option<SystemTime>,
+    /// When the world was last modified, if known.
+    pub modified: Option<SystemTime>,
+    /// Picture of the world.
+    pub thumbnail: Option<Thumbnail>,
+}
+
+/// Format in which [`UniverseMetadata`] is saved.
+pub const UNIVERSE_METADATA_FORMAT: SaveFormat =
+    SaveFormat::new("all-is-cubes/universe-metadata", 1, &[]);
+
+impl UniverseMetadata {
+    /// Records that the world was modified at time `now`, which is also taken as the
+    /// creation time if that is unknown.
+    ///
+    /// The time is a parameter because the system clock is not available on all
+    /// platforms.
+    pub fn touch(&mut self, now: SystemTime) {
+        self.modified = Some(now);
+        self.created.get_or_insert(now);
+    }
+
+    /// Serializes this metadata as a saved file.
+    pub fn save(&self) -> Result<String, SaveError> {
+        UNIVERSE_METADATA_FORMAT.save(self)
+    }
+
+    /// Deserializes metadata written by [`UniverseMetadata::save`].
+    pub fn load(text: &str) -> Result<Self, SaveError> {
+        UNIVERSE_METADATA_FORMAT.load(text)
+    }
+}
+
+impl CustomFormat<StatusText> for UniverseMetadata {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>, _: StatusText) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(fmt, "Untitled world")?;
+        } else {
+            write!(fmt, "{}", self.name)?;
+        }
+        if !self.author.is_empty() {
+            write!(fmt, " by {}", self.author)?;
+        }
+        Ok(())
+    }
+}
+
+/// A small image of a world, in sRGB RGBA with 8 bits per component, in the usual
+/// left-right then top-bottom raster order.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Thumbnail {
+    /// Renders `space` as seen by `camera` using the raytracer.
+    pub fn render(space: &Space, camera: &Camera) -> Self {
+        let (image, _info) = SpaceRaytracer::<ColorBuf>::new(space, camera.options().clone())
+            .trace_scene_to_image(camera);
+        let size = camera.viewport().framebuffer_size;
+        Self {
+            width: size.x,
+            height: size.y,
+            pixels: image
+                .iter()
+                .flat_map(|pixel| std::array::IntoIter::new(pixel.to_srgb_32bit()))
+                .collect(),
+        }
+    }
+
+    /// Renders what `character` sees, at the given size.
+    pub fn from_character(character: &Character, width: u32, height: u32) -> Self {
+        let mut camera = Camera::new(
+            GraphicsOptions::default(),
+            Viewport {
+                nominal_size: Vector2::new(width.into(), height.into()),
+                framebuffer_size: Vector2::new(width, height),
+            },
+        );
+        camera.set_view_matrix(character.view());
+        Self::render(&*character.space.borrow(), &camera)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Rgba;
+    use crate::universe::Universe;
+
+    #[test]
+    fn save_round_trip() {
+        let mut metadata = UniverseMetadata {
+            name: "Test".into(),
+            author: "Someone".into(),
+            ..UniverseMetadata::default()
+        };
+        metadata.touch(SystemTime::UNIX_EPOCH);
+        assert_eq!(
+            UniverseMetadata::load(&metadata.save().unwrap()).unwrap(),
+            metadata
+        );
+        assert_eq!(
+            metadata.custom_format(StatusText).to_string(),
+            "Test by Someone"
+        );
+    }
+
+    #[test]
+    fn thumbnail_size() {
+        let mut universe = Universe::new();
+        let mut space = Space::empty_positive(1, 1, 1);
+        space.set([0, 0, 0], Rgba::WHITE).unwrap();
+        let space = universe.insert_anonymous(space);
+        let character = Character::spawn_default(space);
+
+        let thumbnail = Thumbnail::from_character(&character, 4, 3);
+        assert_eq!((thumbnail.width, thumbnail.height), (4, 3));
+        assert_eq!(thumbnail.pixels.len(), 4 * 3 * 4);
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Schedule`], a queue of events to happen after a number of steps.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies an event added to a [`Schedule`], so that it may be cancelled.
+///
+/// Identifiers are ordered in the same order the events will happen in.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct ScheduledEventId {
+    /// Value of [`Schedule::now`] at which the event happens.
+    due: u64,
+    /// Order in which the event was scheduled, to keep events due on the same step in
+    /// that order.
+    sequence: u64,
+}
+
+impl ScheduledEventId {
+    /// Returns the value of [`Schedule::now`] at which the event happens.
+    pub fn due(self) -> u64 {
+        self.due
+    }
+}
+
+/// A queue of events of type `E` which are to happen after given numbers of steps, such
+/// as a fuse burning down or a crop growing, so that delayed effects need not be
+/// implemented by checking for them on every step.
+///
+/// Events due on the same step happen in the order they were scheduled, so the results
+/// do not depend on hashing or other incidental details, and the schedule may be
+/// saved and restored (if `E` can be) without changing its behavior.
+///
+/// The [`Universe`](super::Universe) has a schedule of transactions; see
+/// [`Universe::schedule`](super::Universe::schedule).
+///
+/// ```
+/// use all_is_cubes::universe::Schedule;
+///
+/// let mut schedule = Schedule::new();
+/// schedule.schedule(2, "later");
+/// schedule.schedule(1, "first");
+/// schedule.schedule(1, "second");
+/// assert_eq!(schedule.advance(), vec!["first", "second"]);
+/// assert_eq!(schedule.advance(), vec!["later"]);
+/// assert_eq!(schedule.advance(), Vec::<&str>::new());
+/// ```
+#[derive(Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct Schedule<E> {
+    now: u64,
+    next_sequence: u64,
+    #[serde(with = "events_as_seq")]
+    #[serde(bound(serialize = "E: Serialize", deserialize = "E: Deserialize<'de>"))]
+    events: BTreeMap<ScheduledEventId, E>,
+}
+
+impl<E> Schedule<E> {
+    /// Constructs an empty schedule whose [`Self::now`] is zero.
+    pub fn new() -> Self {
+        Self {
+            now: 0,
+            next_sequence: 0,
+            events: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the number of times [`Self::advance`] has been called.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Returns the number of events waiting to happen.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns whether there are no events waiting to happen.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Adds `event` to happen after [`Self::advance`] has been called `delay` more
+    /// times. A `delay` of zero is treated as one, so the event happens on the next
+    /// call.
+    pub fn schedule(&mut self, delay: u64, event: E) -> ScheduledEventId {
+        let id = ScheduledEventId {
+            due: self.now.saturating_add(delay.max(1)),
+            sequence: self.next_sequence,
+        };
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+        self.events.insert(id, event);
+        id
+    }
+
+    /// Removes the event with the given ID, and returns it if it had not yet happened.
+    pub fn cancel(&mut self, id: ScheduledEventId) -> Option<E> {
+        self.events.remove(&id)
+    }
+
+    /// Returns the ID of the next event to happen, if any.
+    pub fn next_event(&self) -> Option<ScheduledEventId> {
+        self.events.keys().next().copied()
+    }
+
+    /// Iterates over the events waiting to happen, in the order they will happen.
+    pub fn iter(&self) -> impl Iterator<Item = (ScheduledEventId, &E)> + '_ {
+        self.events.iter().map(|(&id, event)| (id, event))
+    }
+
+    /// Advances the schedule by one step, and returns the events which are now due,
+    /// in the order they should happen.
+    pub fn advance(&mut self) -> Vec<E> {
+        self.now = self.now.saturating_add(1);
+        let later = self.events.split_off(&ScheduledEventId {
+            due: self.now.saturating_add(1),
+            sequence: 0,
+        });
+        std::mem::replace(&mut self.events, later)
+            .into_iter()
+            .map(|(_, event)| event)
+            .collect()
+    }
+}
+
+impl<E> Default for Schedule<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for Schedule<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Schedule")
+            .field("now", &self.now)
+            .field("events", &self.events.iter().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Serialization of [`Schedule::events`] as a sequence of pairs, since
+/// [`ScheduledEventId`] cannot be the key of a JSON object.
+mod events_as_seq {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub(super) fn serialize<S, E>(
+        events: &BTreeMap<ScheduledEventId, E>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        E: Serialize,
+    {
+        serializer.collect_seq(events.iter())
+    }
+
+    pub(super) fn deserialize<'de, D, E>(
+        deserializer: D,
+    ) -> Result<BTreeMap<ScheduledEventId, E>, D::Error>
+    where
+        D: Deserializer<'de>,
+        E: Deserialize<'de>,
+    {
+        Ok(Vec::<(ScheduledEventId, E)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_delay_is_next_step() {
+        let mut schedule = Schedule::new();
+        schedule.schedule(0, 'a');
+        assert_eq!(schedule.advance(), vec!['a']);
+        assert!(schedule.is_empty());
+    }
+
+    #[test]
+    fn ordering_is_stable() {
+        let mut schedule = Schedule::new();
+        for i in 0..20 {
+            schedule.schedule(3 - (i % 3), i);
+        }
+        assert_eq!(schedule.advance(), vec![2, 5, 8, 11, 14, 17]);
+        assert_eq!(schedule.advance(), vec![1, 4, 7, 10, 13, 16, 19]);
+        assert_eq!(schedule.advance(), vec![0, 3, 6, 9, 12, 15, 18]);
+        assert_eq!(schedule.now(), 3);
+    }
+
+    #[test]
+    fn cancel() {
+        let mut schedule = Schedule::new();
+        let a = schedule.schedule(1, 'a');
+        let b = schedule.schedule(2, 'b');
+        assert_eq!(schedule.next_event(), Some(a));
+        assert_eq!(schedule.cancel(a), Some('a'));
+        assert_eq!(schedule.cancel(a), None);
+        assert_eq!(schedule.next_event(), Some(b));
+        assert_eq!(b.due(), 2);
+        assert_eq!(schedule.advance(), Vec::<char>::new());
+        assert_eq!(schedule.advance(), vec!['b']);
+    }
+
+    #[test]
+    fn serialization_roundtrip() {
+        let mut schedule = Schedule::new();
+        schedule.schedule(5, "fuse".to_owned());
+        schedule.advance();
+        schedule.schedule(1, "crop".to_owned());
+        let json = serde_json::to_string(&schedule).unwrap();
+        let mut restored: Schedule<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, schedule);
+
+        // Events scheduled after restoring are ordered after existing ones.
+        restored.schedule(4, "new".to_owned());
+        assert_eq!(restored.advance(), vec!["crop".to_owned()]);
+        restored.advance();
+        restored.advance();
+        assert_eq!(
+            restored.advance(),
+            vec!["fuse".to_owned(), "new".to_owned()]
+        );
+    }
+}
@@ -0,0 +1,396 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`Universe::validate`](super::Universe::validate) and its results.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::block::{Block, BlockAttributes, BlockDef, Modifier};
+use crate::character::Character;
+use crate::math::{GridCoordinate, GridPoint, Rgb, Rgba};
+use crate::space::{Grid, Space};
+use crate::tools::Tool;
+use crate::universe::{Name, RefError, URef, Universe, UniverseIndex};
+
+/// Result of [`Universe::validate`]: the problems found, if any.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Returns whether no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns the problems found, sorted by [`ValidationIssue::member`].
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.issues.is_empty() {
+            return write!(f, "no problems found");
+        }
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// A problem found by [`Universe::validate`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ValidationIssue {
+    /// The member of the universe in which the problem was found.
+    pub member: Name,
+    /// What is wrong.
+    pub problem: ValidationProblem,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.member, self.problem)
+    }
+}
+
+/// The kinds of problem reported by [`Universe::validate`].
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ValidationProblem {
+    /// A [`URef`] refers to an object which has been removed from its universe.
+    #[error("refers to {0}, which no longer exists")]
+    DanglingRef(Name),
+    /// A [`URef`] refers to an object which is not a member of this universe.
+    #[error("refers to {0}, which is not in this universe")]
+    ForeignRef(Name),
+    /// An object could not be checked because it is currently being mutated.
+    #[error("could not check {0} because it is in use")]
+    InUse(Name),
+    /// A [`Block::Recur`] refers to voxels outside the bounds of its space.
+    #[error("voxels {expected:?} are not all within the bounds {actual:?} of {space}")]
+    VoxelsOutOfBounds {
+        space: Name,
+        expected: Grid,
+        actual: Grid,
+    },
+    /// A color has a component which is negative or infinite, or an alpha greater
+    /// than 1.
+    #[error("invalid color {0:?}")]
+    InvalidColor(Rgba),
+    /// A light emission or sky color has a component which is negative or infinite.
+    #[error("invalid light {0:?}")]
+    InvalidLight(Rgb),
+    /// The stored light value of a cube in a [`Space`] is not a possible one.
+    #[error("invalid stored light at {0:?}")]
+    InvalidStoredLight(GridPoint),
+}
+
+pub(super) fn validate_universe(universe: &Universe) -> ValidationReport {
+    let checker = Checker {
+        block_defs: UniverseIndex::<BlockDef>::iter_by_type(universe)
+            .map(|(_, r)| r)
+            .collect(),
+        spaces: UniverseIndex::<Space>::iter_by_type(universe)
+            .map(|(_, r)| r)
+            .collect(),
+    };
+
+    let mut issues = Vec::new();
+    let mut check_member = |name: Name, problems: Vec<ValidationProblem>| {
+        issues.extend(problems.into_iter().map(|problem| ValidationIssue {
+            member: name.clone(),
+            problem,
+        }));
+    };
+    for (name, def_ref) in UniverseIndex::<BlockDef>::iter_by_type(universe) {
+        let mut problems = Vec::new();
+        match def_ref.try_borrow() {
+            Ok(def) => checker.check_block(&**def, &mut problems),
+            Err(_) => problems.push(ValidationProblem::InUse(name.clone())),
+        }
+        check_member(name, problems);
+    }
+    for (name, character_ref) in UniverseIndex::<Character>::iter_by_type(universe) {
+        let mut problems = Vec::new();
+        match character_ref.try_borrow() {
+            Ok(character) => checker.check_character(&character, &mut problems),
+            Err(_) => problems.push(ValidationProblem::InUse(name.clone())),
+        }
+        check_member(name, problems);
+    }
+    for (name, space_ref) in UniverseIndex::<Space>::iter_by_type(universe) {
+        let mut problems = Vec::new();
+        match space_ref.try_borrow() {
+            Ok(space) => checker.check_space(&space, &mut problems),
+            Err(_) => problems.push(ValidationProblem::InUse(name.clone())),
+        }
+        check_member(name, problems);
+    }
+
+    // Stable sort, so each member's problems stay in the order they were found.
+    issues.sort_by(|a, b| a.member.cmp(&b.member));
+    ValidationReport { issues }
+}
+
+/// The members of the universe being validated, for checking [`URef`]s against.
+struct Checker {
+    block_defs: HashSet<URef<BlockDef>>,
+    spaces: HashSet<URef<Space>>,
+}
+
+impl Checker {
+    fn check_character(&self, character: &Character, problems: &mut Vec<ValidationProblem>) {
+        check_ref(&character.space, &self.spaces, problems);
+        for tool in character.inventory().slots.iter() {
+            match tool {
+                Tool::PlaceBlock(block) | Tool::FloodFill(block) => {
+                    self.check_block(block, problems)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_space(&self, space: &Space, problems: &mut Vec<ValidationProblem>) {
+        check_light(space.physics().sky_color, problems);
+        for data in space.block_data() {
+            if data.count() > 0 {
+                self.check_block(data.block(), problems);
+            }
+        }
+        for (_, entity) in space.entities().iter() {
+            if let Some(block) = entity.appearance() {
+                self.check_block(block, problems);
+            }
+            for part in entity.model().into_iter().flat_map(|model| model.parts()) {
+                self.check_block(part.appearance(), problems);
+            }
+        }
+        for cube in space.grid().interior_iter() {
+            if matches!(space.lighting.get(cube), Some(light) if !light.is_consistent()) {
+                problems.push(ValidationProblem::InvalidStoredLight(cube));
+            }
+        }
+    }
+
+    /// Checks `block` itself, but not the definitions of any [`Block::Indirect`]s in it,
+    /// since those are checked as members of the universe.
+    fn check_block(&self, block: &Block, problems: &mut Vec<ValidationProblem>) {
+        match block {
+            Block::Indirect(def_ref) => {
+                check_ref(def_ref, &self.block_defs, problems);
+            }
+            Block::Atom(attributes, color) => {
+                check_attributes(attributes, problems);
+                check_color(*color, problems);
+            }
+            Block::Recur {
+                attributes,
+                offset,
+                resolution,
+                space: space_ref,
+            } => {
+                check_attributes(attributes, problems);
+                if !check_ref(space_ref, &self.spaces, problems) {
+                    return;
+                }
+                match space_ref.try_borrow() {
+                    Ok(space) => {
+                        let resolution = GridCoordinate::from(*resolution).max(1);
+                        let expected = Grid::new(*offset, [resolution, resolution, resolution]);
+                        let actual = space.grid();
+                        if !actual.contains_grid(expected) {
+                            problems.push(ValidationProblem::VoxelsOutOfBounds {
+                                space: (**space_ref.name()).clone(),
+                                expected,
+                                actual,
+                            });
+                        }
+                    }
+                    Err(_) => problems.push(ValidationProblem::InUse((**space_ref.name()).clone())),
+                }
+            }
+            Block::Rotated(_, base) => self.check_block(base, problems),
+            Block::Overlay { base, overlay } => {
+                self.check_block(base, problems);
+                self.check_block(overlay, problems);
+            }
+            Block::Modified { base, modifiers } => {
+                self.check_block(base, problems);
+                for modifier in modifiers {
+                    match modifier {
+                        Modifier::Tint(color) | Modifier::Recolor(color) => {
+                            check_color(color.with_alpha_one(), problems)
+                        }
+                        Modifier::Overlay(block) => self.check_block(block, problems),
+                        Modifier::Mirror(_) | Modifier::Crop(_) | Modifier::Connect { .. } => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Checks that `uref` refers to a member of the universe, and returns whether it does.
+fn check_ref<T: 'static>(
+    uref: &URef<T>,
+    members: &HashSet<URef<T>>,
+    problems: &mut Vec<ValidationProblem>,
+) -> bool {
+    if members.contains(uref) {
+        return true;
+    }
+    problems.push(match uref.try_borrow() {
+        Err(RefError::Gone(name)) => ValidationProblem::DanglingRef((*name).clone()),
+        _ => ValidationProblem::ForeignRef((**uref.name()).clone()),
+    });
+    false
+}
+
+fn check_attributes(attributes: &BlockAttributes, problems: &mut Vec<ValidationProblem>) {
+    // Report only the first bad face, since they are usually all the same.
+    if let Some((_, &light)) = attributes
+        .light_emission
+        .iter()
+        .find(|(_, &light)| !is_valid_light(light))
+    {
+        problems.push(ValidationProblem::InvalidLight(light));
+    }
+}
+
+fn check_color(color: Rgba, problems: &mut Vec<ValidationProblem>) {
+    if !(is_valid_light(color.to_rgb()) && color.alpha().into_inner() <= 1.0) {
+        problems.push(ValidationProblem::InvalidColor(color));
+    }
+}
+
+fn check_light(light: Rgb, problems: &mut Vec<ValidationProblem>) {
+    if !is_valid_light(light) {
+        problems.push(ValidationProblem::InvalidLight(light));
+    }
+}
+
+fn is_valid_light(light: Rgb) -> bool {
+    [light.red(), light.green(), light.blue()]
+        .iter()
+        .all(|c| c.is_finite() && c.into_inner() >= 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::AIR;
+    use crate::content::make_some_blocks;
+    use crate::space::PackedLight;
+
+    #[test]
+    fn empty_and_valid_universes_pass() {
+        assert!(Universe::new().validate().is_ok());
+
+        let mut universe = Universe::new();
+        let [block] = make_some_blocks();
+        let def = universe.insert("b".into(), BlockDef::new(block)).unwrap();
+        let mut space = Space::empty_positive(2, 2, 2);
+        space.set([0, 0, 0], Block::Indirect(def)).unwrap();
+        let space_ref = universe.insert("s".into(), space).unwrap();
+        universe
+            .insert("c".into(), Character::spawn_default(space_ref))
+            .unwrap();
+        let report = universe.validate();
+        assert_eq!(report, ValidationReport::default());
+        assert_eq!(report.to_string(), "no problems found");
+    }
+
+    #[test]
+    fn dangling_and_foreign_refs() {
+        let mut universe = Universe::new();
+        let mut other_universe = Universe::new();
+        let removed = universe.insert("gone".into(), BlockDef::new(AIR)).unwrap();
+        let foreign = other_universe
+            .insert("foreign".into(), BlockDef::new(AIR))
+            .unwrap();
+
+        // Placing a block requires evaluating it, so remove its definition afterward.
+        let mut space = Space::empty_positive(2, 1, 1);
+        space.set([0, 0, 0], Block::Indirect(removed)).unwrap();
+        space.set([1, 0, 0], Block::Indirect(foreign)).unwrap();
+        universe.insert("s".into(), space).unwrap();
+        universe.remove::<BlockDef>(&"gone".into()).unwrap();
+
+        let report = universe.validate();
+        let mut problems: Vec<ValidationProblem> =
+            report.issues().iter().map(|i| i.problem.clone()).collect();
+        problems.sort_by_key(|p| format!("{:?}", p));
+        assert_eq!(
+            problems,
+            vec![
+                ValidationProblem::DanglingRef("gone".into()),
+                ValidationProblem::ForeignRef("foreign".into()),
+            ]
+        );
+        assert!(report.issues().iter().all(|i| i.member == Name::from("s")));
+    }
+
+    #[test]
+    fn voxels_out_of_bounds() {
+        let mut universe = Universe::new();
+        let space_ref = universe
+            .insert("voxels".into(), Space::empty_positive(4, 4, 2))
+            .unwrap();
+        let block = Block::builder().voxels_ref(4, space_ref).build();
+        universe.insert("b".into(), BlockDef::new(block)).unwrap();
+        assert_eq!(
+            universe.validate().issues(),
+            &[ValidationIssue {
+                member: "b".into(),
+                problem: ValidationProblem::VoxelsOutOfBounds {
+                    space: "voxels".into(),
+                    expected: Grid::new([0, 0, 0], [4, 4, 4]),
+                    actual: Grid::new([0, 0, 0], [4, 4, 2]),
+                }
+            }]
+        );
+    }
+
+    #[test]
+    fn bad_light_emission_and_stored_light() {
+        let mut universe = Universe::new();
+        let block = Block::builder()
+            .color(Rgba::WHITE)
+            .light_emission(Rgb::new(1.0, f32::INFINITY, 0.0))
+            .build();
+        universe.insert("b".into(), BlockDef::new(block)).unwrap();
+        let mut space = Space::empty_positive(1, 1, 1);
+        // A nonzero value with a status other than visible, as could be loaded from
+        // a corrupted texture.
+        space.lighting.set(
+            GridPoint::new(0, 0, 0),
+            PackedLight::from_texel([10, 10, 10, 128]).unwrap(),
+        );
+        universe.insert("s".into(), space).unwrap();
+
+        let issues = universe.validate().issues().to_vec();
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue {
+                    member: "b".into(),
+                    problem: ValidationProblem::InvalidLight(Rgb::new(1.0, f32::INFINITY, 0.0)),
+                },
+                ValidationIssue {
+                    member: "s".into(),
+                    problem: ValidationProblem::InvalidStoredLight(GridPoint::new(0, 0, 0)),
+                },
+            ]
+        );
+    }
+}
@@ -110,6 +110,102 @@ impl CustomFormat<StatusText> for Duration {
     }
 }
 
+/// The result of an operation which carries on past problems rather than stopping at
+/// the first one: the value produced, which may be incomplete, along with whatever went
+/// wrong along the way.
+///
+/// This is used, for example, when generating a [`Universe`](crate::universe::Universe)
+/// from content in which a single broken piece should not prevent the rest from being
+/// built. Problems which prevent producing any value at all should still be reported by
+/// wrapping this in a [`Result`].
+///
+/// ```
+/// use all_is_cubes::util::WarningsResult;
+///
+/// fn parse_all(words: &[&str]) -> WarningsResult<Vec<i32>, String> {
+///     let mut warnings = Vec::new();
+///     let numbers = words
+///         .iter()
+///         .filter_map(|word| match word.parse() {
+///             Ok(number) => Some(number),
+///             Err(e) => {
+///                 warnings.push(format!("{:?}: {}", word, e));
+///                 None
+///             }
+///         })
+///         .collect();
+///     WarningsResult::with_warnings(numbers, warnings)
+/// }
+///
+/// let mut all_warnings = Vec::new();
+/// let numbers = parse_all(&["1", "two", "3"]).collect_warnings(&mut all_warnings);
+/// assert_eq!(numbers, vec![1, 3]);
+/// assert_eq!(all_warnings.len(), 1);
+/// assert!(parse_all(&["4"]).into_result().is_ok());
+/// ```
+#[allow(clippy::exhaustive_structs)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[must_use]
+pub struct WarningsResult<T, W> {
+    /// The value produced.
+    pub value: T,
+    /// The problems encountered while producing it, in the order they happened.
+    pub warnings: Vec<W>,
+}
+
+impl<T, W> WarningsResult<T, W> {
+    /// Constructs a result with no warnings.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Constructs a result from a value and the warnings produced along with it.
+    pub fn with_warnings(value: T, warnings: Vec<W>) -> Self {
+        Self { value, warnings }
+    }
+
+    /// Returns whether there were no warnings.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Transforms the value, keeping the warnings.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> WarningsResult<U, W> {
+        WarningsResult {
+            value: f(self.value),
+            warnings: self.warnings,
+        }
+    }
+
+    /// Transforms each of the warnings, keeping the value.
+    pub fn map_warnings<X>(self, f: impl FnMut(W) -> X) -> WarningsResult<T, X> {
+        WarningsResult {
+            value: self.value,
+            warnings: self.warnings.into_iter().map(f).collect(),
+        }
+    }
+
+    /// Moves the warnings to the end of `warnings` and returns the value; for combining
+    /// the results of several steps into one [`WarningsResult`].
+    pub fn collect_warnings(self, warnings: &mut Vec<W>) -> T {
+        warnings.extend(self.warnings);
+        self.value
+    }
+
+    /// Returns the value if there were no warnings, and the warnings otherwise; for
+    /// when any problem should be treated as a failure.
+    pub fn into_result(self) -> Result<T, Vec<W>> {
+        if self.warnings.is_empty() {
+            Ok(self.value)
+        } else {
+            Err(self.warnings)
+        }
+    }
+}
+
 /// Equivalent of [`Iterator::map`] but applied to an [`Extend`] instead, transforming
 /// the incoming elements.
 pub(crate) struct MapExtend<'a, A, B, T, F>
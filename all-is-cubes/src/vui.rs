@@ -14,23 +14,28 @@ use ordered_float::NotNan;
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::rc::{Rc, Weak};
+use std::sync::Arc;
 use std::time::Duration;
 
 use crate::apps::{InputProcessor, Tick};
 use crate::block::{Block, AIR};
-use crate::camera::{FogOption, GraphicsOptions};
+use crate::camera::{FogOption, GraphicsOptions, ProjectionMode};
 use crate::content::palette;
 use crate::drawing::VoxelBrush;
+use crate::i18n::{localize, Localizer, NoLocalizer};
 use crate::listen::{ListenableSource, Listener};
 use crate::math::{FreeCoordinate, GridMatrix};
 use crate::space::{SetCubeError, Space};
+use crate::swatches::Swatches;
 use crate::tools::Tool;
-use crate::universe::{URef, Universe, UniverseStepInfo};
+use crate::universe::{ReadRef, URef, Universe, UniverseStepInfo};
 
 mod hud;
 use hud::*;
 mod icons;
 pub use icons::*;
+mod monitor;
+pub use monitor::*;
 
 /// `Vui` builds user interfaces out of voxels. It owns a `Universe` dedicated to the
 /// purpose and draws into spaces to form the HUD and menus.
@@ -47,6 +52,12 @@ pub(crate) struct Vui {
     /// None if the tooltip is blanked
     tooltip_age: Option<Duration>,
 
+    /// Translates text before it is displayed.
+    localizer: Arc<dyn Localizer>,
+
+    /// Names for blocks which have none of their own, such as plain colors.
+    swatches: Swatches,
+
     todo: Rc<RefCell<VuiTodo>>,
 
     // Things we're listening to...
@@ -80,6 +91,10 @@ impl Vui {
 
             tooltip_age: None,
 
+            localizer: Arc::new(NoLocalizer),
+
+            swatches: Swatches::standard(),
+
             todo,
 
             mouselook_mode: input_processor.mouselook_mode(),
@@ -87,8 +102,14 @@ impl Vui {
         }
     }
 
-    // TODO: It'd be more encapsulating if we could provide a _read-only_ reference...
-    pub fn current_space(&self) -> &URef<Space> {
+    /// Returns the [`Space`] which should be displayed as the user interface.
+    pub fn current_space(&self) -> ReadRef<Space> {
+        self.current_space.read_only()
+    }
+
+    /// Returns the [`Vui::current_space`] with permission to modify it, for use by
+    /// tools operating on the UI.
+    pub(crate) fn current_space_mut(&self) -> &URef<Space> {
         &self.current_space
     }
 
@@ -118,6 +139,7 @@ impl Vui {
     /// Compute graphics options to render the VUI space given the user's regular options.
     pub fn graphics_options(mut options: GraphicsOptions) -> GraphicsOptions {
         // Set FOV to give a predictable, not-too-wide-angle perspective.
+        options.projection_mode = ProjectionMode::Perspective;
         options.fov_y = NotNan::new(30.).unwrap();
 
         // Disable fog for maximum clarity and because we shouldn't have any far clipping to hide.
@@ -159,8 +181,9 @@ impl Vui {
         if let Some(ref mut age) = self.tooltip_age {
             *age += tick.delta_t;
             if *age > Duration::from_secs(1) {
-                // TODO: log errors
-                let _ = self.set_tooltip_text("");
+                if let Err(e) = self.set_tooltip_text("") {
+                    log::warn!("failed to clear tooltip: {}", e);
+                }
                 self.tooltip_age = None;
             }
         }
@@ -190,19 +213,36 @@ impl Vui {
         let text = selections
             .get(1)
             .and_then(|&i| tools.get(i))
-            .and_then(|tool| tool.icon(&self.hud_blocks.icons).evaluate().ok())
-            .map(|ev_block| ev_block.attributes.display_name)
+            .and_then(|tool| {
+                let icon = tool.icon(&self.hud_blocks.icons);
+                let name = icon.evaluate().ok()?.attributes.display_name;
+                if name.is_empty() {
+                    // Plain colors have no name, but may be one of the swatches.
+                    self.swatches
+                        .name_of(&icon)
+                        .map(|name| Cow::Owned(name.to_owned()))
+                } else {
+                    Some(name)
+                }
+            })
             .unwrap_or(Cow::Borrowed(""));
         self.set_tooltip_text(&text)?;
 
         Ok(())
     }
 
+    /// Displays `text` in the tooltip, after localizing it.
     // TODO: handle errors in a local/transient way instead of propagating
     pub fn set_tooltip_text(&mut self, text: &str) -> Result<(), SetCubeError> {
         self.tooltip_age = Some(Duration::ZERO);
+        let text = localize(&*self.localizer, text);
         self.hud_layout
-            .set_tooltip_text(&mut *self.hud_space.borrow_mut(), &self.hud_blocks, text)
+            .set_tooltip_text(&mut *self.hud_space.borrow_mut(), &self.hud_blocks, &text)
+    }
+
+    /// Sets the [`Localizer`] used for all text subsequently displayed.
+    pub fn set_localizer(&mut self, localizer: Arc<dyn Localizer>) {
+        self.localizer = localizer;
     }
 }
 
@@ -28,6 +28,16 @@ pub enum Icons {
     Delete,
     /// Icon for `Tool::CopyFromSpace`.
     CopyFromSpace,
+    /// Icon for `Tool::PickColor`.
+    PickColor,
+    /// Icon for `Tool::FloodFill`.
+    FloodFill,
+    /// Icon for `Tool::MoveRegion`.
+    MoveRegion,
+    /// Icon for `Tool::CopyRegion`.
+    CopyRegion,
+    /// Icon for `Tool::RotateRegion`.
+    RotateRegion,
 }
 
 impl BlockModule for Icons {
@@ -138,6 +148,31 @@ impl Icons {
                     // TODO: design actual icon
                     .color(Rgba::new(0., 1., 0., 1.))
                     .build(),
+                Icons::PickColor => Block::builder()
+                    .display_name("Pick Color from Cursor")
+                    // TODO: design actual icon
+                    .color(Rgba::new(1., 0.5, 0., 1.))
+                    .build(),
+                Icons::FloodFill => Block::builder()
+                    .display_name("Flood Fill")
+                    // TODO: design actual icon
+                    .color(Rgba::new(0., 0.5, 1., 1.))
+                    .build(),
+                Icons::MoveRegion => Block::builder()
+                    .display_name("Move Region")
+                    // TODO: design actual icon
+                    .color(Rgba::new(0.5, 0., 1., 1.))
+                    .build(),
+                Icons::CopyRegion => Block::builder()
+                    .display_name("Copy Region")
+                    // TODO: design actual icon
+                    .color(Rgba::new(0.5, 1., 0.5, 1.))
+                    .build(),
+                Icons::RotateRegion => Block::builder()
+                    .display_name("Rotate Region")
+                    // TODO: design actual icon
+                    .color(Rgba::new(1., 1., 0., 1.))
+                    .build(),
             })
         })
         .unwrap()
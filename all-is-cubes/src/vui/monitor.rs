@@ -0,0 +1,218 @@
+// Copyright 2020-2021 Kevin Reid under the terms of the MIT License as detailed
+// in the accompanying file README.md or <https://opensource.org/licenses/MIT>.
+
+//! [`ViewMonitor`], for showing a live view of one [`Space`] on blocks in another.
+
+use cgmath::{Matrix4, Vector2};
+
+use crate::block::{space_to_blocks, Block, BlockAttributes, EvalBlockError, Resolution};
+use crate::camera::{Camera, GraphicsOptions, Viewport};
+use crate::math::{FreeCoordinate, GridCoordinate, GridPoint, Rgba};
+use crate::raytracer::{ColorBuf, ProgressiveRaytracer, RaytraceInfo};
+use crate::space::{Grid, SetCubeError, Space, SpacePhysics};
+use crate::universe::{URef, Universe};
+
+/// A screen made of blocks, such as a “camera monitor” or “mirror” in a user interface,
+/// which displays a low-resolution raytraced view of a [`Space`].
+///
+/// Each pixel of the view is one voxel of the blocks returned by [`Self::blocks`], so
+/// the view reaches the renderer through the same path as any other voxel block. The
+/// view is refined a limited number of rays at a time by [`Self::update`], using
+/// [`ProgressiveRaytracer`], so the cost per frame stays bounded however large the
+/// screen is.
+///
+/// ```
+/// use all_is_cubes::camera::GraphicsOptions;
+/// use all_is_cubes::cgmath::Vector2;
+/// use all_is_cubes::space::Space;
+/// use all_is_cubes::universe::Universe;
+/// use all_is_cubes::vui::ViewMonitor;
+///
+/// let mut universe = Universe::new();
+/// let watched = universe.insert_anonymous(Space::empty_positive(4, 4, 4));
+/// let mut monitor = ViewMonitor::new(
+///     &mut universe,
+///     watched,
+///     Vector2::new(2, 1),
+///     8,
+///     GraphicsOptions::default(),
+/// )
+/// .unwrap();
+///
+/// // Place the monitor's blocks somewhere they can be seen...
+/// let mut ui_space = Space::empty_positive(2, 1, 1);
+/// let screen_grid = monitor.blocks().grid();
+/// ui_space.fill(screen_grid, |cube| Some(monitor.blocks()[cube].clone())).unwrap();
+///
+/// // ...and then, once per frame:
+/// monitor.update(1000).unwrap();
+/// ```
+pub struct ViewMonitor {
+    raytracer: ProgressiveRaytracer<ColorBuf>,
+    camera: Camera,
+    /// Space whose voxels are the pixels of the image.
+    screen: URef<Space>,
+    /// Blocks made from `screen`.
+    blocks: Space,
+    /// The colors of the voxels of `screen`, in the order of the image.
+    displayed: Vec<Rgba>,
+}
+
+impl ViewMonitor {
+    /// Constructs a monitor showing `source`, made of `size.x` by `size.y` blocks each
+    /// displaying `resolution`×`resolution` pixels. Its voxels are stored in `universe`.
+    ///
+    /// The view is initially from the default camera position; use
+    /// [`Self::set_view_matrix`] to aim it. Nothing is drawn until [`Self::update`] is
+    /// called.
+    pub fn new(
+        universe: &mut Universe,
+        source: URef<Space>,
+        size: Vector2<GridCoordinate>,
+        resolution: Resolution,
+        options: GraphicsOptions,
+    ) -> Result<Self, SetCubeError> {
+        let resolution_g = GridCoordinate::from(resolution);
+        let pixel_size = size * resolution_g;
+        let screen_grid = Grid::new([0, 0, 0], [pixel_size.x, pixel_size.y, 1]);
+        let mut screen = Space::empty(screen_grid);
+        screen.set_physics(SpacePhysics::DEFAULT_FOR_BLOCK);
+        screen.fill_uniform(screen_grid, Block::from(Rgba::BLACK))?;
+        let screen = universe.insert_anonymous(screen);
+        let blocks = space_to_blocks(resolution, BlockAttributes::default(), screen.clone())?;
+
+        let camera = Camera::new(
+            options,
+            Viewport {
+                nominal_size: pixel_size.map(FreeCoordinate::from),
+                framebuffer_size: pixel_size.map(|s| s as u32),
+            },
+        );
+
+        Ok(Self {
+            raytracer: ProgressiveRaytracer::new(source),
+            camera,
+            screen,
+            blocks,
+            displayed: vec![Rgba::BLACK; screen_grid.volume()],
+        })
+    }
+
+    /// Returns a [`Space`] containing the blocks which display the view, arranged with
+    /// the top of the image toward +Y and the image facing +Z. Place copies of them
+    /// wherever the view should appear.
+    pub fn blocks(&self) -> &Space {
+        &self.blocks
+    }
+
+    /// Sets the view matrix of the camera, determining where the view is seen from.
+    pub fn set_view_matrix(&mut self, view_matrix: Matrix4<FreeCoordinate>) {
+        self.camera.set_view_matrix(view_matrix);
+    }
+
+    /// Traces up to `ray_budget` rays to improve the view, then updates the voxels of
+    /// the blocks whose pixels changed.
+    ///
+    /// Returns information about the rays traced by this call only.
+    pub fn update(&mut self, ray_budget: usize) -> Result<RaytraceInfo, SetCubeError> {
+        let info = self.raytracer.refine(&self.camera, ray_budget);
+        let image = self.raytracer.image();
+        if image.len() != self.displayed.len() {
+            // Nothing has been traced yet.
+            return Ok(info);
+        }
+
+        let mut screen = self
+            .screen
+            .try_borrow_mut()
+            .map_err(EvalBlockError::DataRefIs)?;
+        let width = self.camera.viewport().framebuffer_size.x as usize;
+        let height = self.displayed.len() / width;
+        for (index, (pixel, displayed)) in image.iter().zip(self.displayed.iter_mut()).enumerate() {
+            let color = pixel.to_rgb().with_alpha_one();
+            if color == *displayed {
+                continue;
+            }
+            // The image is in top-to-bottom order, but Y is up in the space.
+            let (x, y) = (index % width, index / width);
+            let cube = GridPoint::new(x as GridCoordinate, (height - 1 - y) as _, 0);
+            screen.set(cube, Block::from(color))?;
+            *displayed = color;
+        }
+        Ok(info)
+    }
+}
+
+impl std::fmt::Debug for ViewMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ViewMonitor")
+            .field("raytracer", &self.raytracer)
+            .field("screen", &self.screen)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::make_some_blocks;
+    use cgmath::Vector3;
+
+    fn monitor_for_test(universe: &mut Universe) -> ViewMonitor {
+        let mut source = Space::empty_positive(2, 1, 1);
+        let [a, b] = make_some_blocks();
+        source.set([0, 0, 0], a).unwrap();
+        source.set([1, 0, 0], b).unwrap();
+        let grid = source.grid();
+        let source = universe.insert_anonymous(source);
+        let mut monitor = ViewMonitor::new(
+            universe,
+            source,
+            Vector2::new(2, 1),
+            4,
+            GraphicsOptions::default(),
+        )
+        .unwrap();
+        monitor.set_view_matrix(Matrix4::look_at_rh(
+            crate::camera::eye_for_look_at(grid, Vector3::new(0., 0., 1.)),
+            grid.center(),
+            Vector3::new(0., 1., 0.),
+        ));
+        monitor
+    }
+
+    #[test]
+    fn image_is_copied_to_voxels() {
+        let mut universe = Universe::new();
+        let mut monitor = monitor_for_test(&mut universe);
+        assert_eq!(monitor.blocks().grid(), Grid::new([0, 0, 0], [2, 1, 1]));
+
+        monitor.update(usize::MAX).unwrap();
+        assert!(monitor.raytracer.is_complete());
+        let image = monitor.raytracer.image();
+        assert_eq!(image.len(), 8 * 4);
+        let screen = monitor.screen.borrow();
+        for y in 0..4 {
+            for x in 0..8 {
+                assert_eq!(
+                    screen[[x, 3 - y, 0]],
+                    Block::from(image[(y * 8 + x) as usize].to_rgb().with_alpha_one()),
+                    "pixel {:?}",
+                    (x, y)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn update_within_budget() {
+        let mut universe = Universe::new();
+        let mut monitor = monitor_for_test(&mut universe);
+        // 8×4 pixels in 4×4 blocks is 2 blocks in the first pass.
+        monitor.update(1).unwrap();
+        assert!(!monitor.raytracer.is_complete());
+        monitor.update(usize::MAX).unwrap();
+        assert!(monitor.raytracer.is_complete());
+        assert_eq!(monitor.update(usize::MAX).unwrap(), RaytraceInfo::default());
+    }
+}